@@ -0,0 +1,303 @@
+//! Implemented according to ["Adiantum: length-preserving encryption for entry-level processors"](https://eprint.iacr.org/2018/720.pdf),
+//! with the XChaCha12 and AES-256 instantiation, as used by Linux for disk
+//! and file encryption. Adiantum is a wide-block cipher: it encrypts a whole
+//! sector at once under a tweak, such as the sector number, so changing any
+//! bit of the plaintext changes the entire ciphertext. Nearly all of the work
+//! is done by XChaCha12 and the NH and Poly1305 hashes, with AES only used on
+//! a single block per sector, which makes it much faster than XTS-AES on
+//! processors without AES instructions.
+//!
+//! Like [XTS](crate::aes_xts), Adiantum is length-preserving, so it doesn't
+//! authenticate anything, and encrypting the same sector twice with the same
+//! tweak reveals whether it changed. Its tweaks can be any length, though
+//! Linux always uses 32 bytes.
+//!
+//! ```
+//! use libkrypton::adiantum::Adiantum;
+//!
+//! let adiantum = Adiantum::new([0x42; 32]);
+//!
+//! let mut tweak = [0; 32];
+//! tweak[.. 8].copy_from_slice(&7u64.to_le_bytes());
+//!
+//! let mut sector = [0; 4096];
+//! adiantum.encrypt(&tweak, &mut sector);
+//! assert_ne!(sector, [0; 4096]);
+//!
+//! adiantum.decrypt(&tweak, &mut sector);
+//! assert_eq!(sector, [0; 4096]);
+//! ```
+
+use crate::aes::Aes;
+use crate::chacha20::{hchacha12, ChaCha20};
+use crate::poly1305::Poly1305;
+use crate::zeroize::zeroize;
+use crate::Error;
+
+// the largest message NH hashes at once, and the size of its key, which is
+// longer so that each of the 4 passes can start 16 bytes further in
+const NH_MESSAGE_SIZE: usize = 1024;
+const NH_KEY_SIZE: usize = NH_MESSAGE_SIZE + 48;
+
+// encrypts or decrypts `buffer` with XChaCha12, in the 64-bit nonce layout
+// of the original ChaCha, where the second half of the extended nonce is
+// the nonce and the counter starts at 0
+fn xchacha12(key: [u8; 32], nonce: [u8; 24], buffer: &mut [u8]) {
+	let mut subkey = hchacha12(key, nonce[.. 16].try_into().unwrap());
+
+	let mut chacha_nonce = [0; 12];
+	chacha_nonce[4 ..].copy_from_slice(&nonce[16 ..]);
+
+	ChaCha20::new_chacha12(subkey, chacha_nonce).crypt(buffer);
+	zeroize(&mut subkey);
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+	u32::from_le_bytes(bytes[4 * i .. 4 * (i + 1)].try_into().unwrap())
+}
+
+// hashes up to NH_MESSAGE_SIZE bytes, padded with zeros to a multiple of 16
+fn nh(key: &[u8; NH_KEY_SIZE], message: &[u8]) -> [u8; 32] {
+	let mut sums = [0u64; 4];
+
+	for (i, chunk) in message.chunks(16).enumerate() {
+		let mut unit = [0; 16];
+		unit[.. chunk.len()].copy_from_slice(chunk);
+
+		let m = [0, 1, 2, 3].map(|j| u32_at(&unit, j));
+
+		for (pass, sum) in sums.iter_mut().enumerate() {
+			let k = [0, 1, 2, 3].map(|j| u32_at(key, 4 * (i + pass) + j));
+
+			let a = m[0].wrapping_add(k[0]) as u64 * m[2].wrapping_add(k[2]) as u64;
+			let b = m[1].wrapping_add(k[1]) as u64 * m[3].wrapping_add(k[3]) as u64;
+			*sum = sum.wrapping_add(a).wrapping_add(b);
+		}
+	}
+
+	let mut out = [0; 32];
+
+	for (i, sum) in sums.iter().enumerate() {
+		out[8 * i .. 8 * (i + 1)].copy_from_slice(&sum.to_le_bytes());
+	}
+
+	out
+}
+
+/// An Adiantum key schedule, holding the subkeys derived from the key.
+/// Zeroized when dropped.
+#[derive(Clone)]
+pub struct Adiantum {
+	stream_key: [u8; 32],
+	block_cipher: Aes,
+	header_key: [u8; 16],
+	message_key: [u8; 16],
+	nh_key: [u8; NH_KEY_SIZE],
+}
+
+impl Adiantum {
+	/// Creates an Adiantum instance from a 32-byte key.
+	pub fn new(key: [u8; 32]) -> Self {
+		let mut subkeys = [0; 32 + 16 + 16 + NH_KEY_SIZE];
+
+		let mut nonce = [0; 24];
+		nonce[0] = 1;
+		xchacha12(key, nonce, &mut subkeys);
+
+		let mut block_key: [u8; 32] = subkeys[.. 32].try_into().unwrap();
+
+		let adiantum = Self {
+			stream_key: key,
+			block_cipher: Aes::new_256(block_key),
+			header_key: subkeys[32 .. 48].try_into().unwrap(),
+			message_key: subkeys[48 .. 64].try_into().unwrap(),
+			nh_key: subkeys[64 ..].try_into().unwrap(),
+		};
+
+		zeroize(&mut block_key);
+		zeroize(&mut subkeys);
+
+		adiantum
+	}
+
+	// the hash of the tweak and the bulk of the message, the part that's
+	// encrypted with XChaCha12, as a 128-bit integer
+	fn hash(&self, tweak: &[u8], bulk: &[u8]) -> u128 {
+		let mut header = Poly1305::new(self.header_key, [0; 16]);
+		header.add_bytes(&(bulk.len() as u64 * 8).to_le_bytes());
+		header.add_bytes(&[0; 8]);
+		header.add_bytes(tweak);
+
+		let mut message = Poly1305::new(self.message_key, [0; 16]);
+
+		for chunk in bulk.chunks(NH_MESSAGE_SIZE) {
+			message.add_bytes(&nh(&self.nh_key, chunk));
+		}
+
+		u128::from_le_bytes(header.out()).wrapping_add(u128::from_le_bytes(message.out()))
+	}
+
+	fn stream(&self, block: [u8; 16], bulk: &mut [u8]) {
+		let mut nonce = [0; 24];
+		nonce[.. 16].copy_from_slice(&block);
+		nonce[16] = 1;
+
+		xchacha12(self.stream_key, nonce, bulk);
+	}
+
+	/// Encrypts `buffer` in place under `tweak`, which is usually the sector
+	/// number.
+	///
+	/// # Panics
+	/// * Panics if `buffer` is shorter than 16 bytes.
+	pub fn encrypt(&self, tweak: &[u8], buffer: &mut [u8]) {
+		self.try_encrypt(tweak, buffer).expect("adiantum needs at least 16 bytes");
+	}
+
+	/// Like [`Adiantum::encrypt`], but returns [`Error::InvalidLength`] instead
+	/// of panicking if `buffer` is shorter than 16 bytes.
+	pub fn try_encrypt(&self, tweak: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+		if buffer.len() < 16 {
+			return Err(Error::InvalidLength);
+		}
+
+		let (bulk, last) = buffer.split_at_mut(buffer.len() - 16);
+		let last: &mut [u8; 16] = last.try_into().unwrap();
+
+		let mut block = u128::from_le_bytes(*last).wrapping_add(self.hash(tweak, bulk)).to_le_bytes();
+		self.block_cipher.encrypt_block(&mut block);
+
+		self.stream(block, bulk);
+		*last = u128::from_le_bytes(block).wrapping_sub(self.hash(tweak, bulk)).to_le_bytes();
+
+		Ok(())
+	}
+
+	/// Decrypts `buffer` in place under `tweak`.
+	///
+	/// # Panics
+	/// * Panics if `buffer` is shorter than 16 bytes.
+	pub fn decrypt(&self, tweak: &[u8], buffer: &mut [u8]) {
+		self.try_decrypt(tweak, buffer).expect("adiantum needs at least 16 bytes");
+	}
+
+	/// Like [`Adiantum::decrypt`], but returns [`Error::InvalidLength`] instead
+	/// of panicking if `buffer` is shorter than 16 bytes.
+	pub fn try_decrypt(&self, tweak: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+		if buffer.len() < 16 {
+			return Err(Error::InvalidLength);
+		}
+
+		let (bulk, last) = buffer.split_at_mut(buffer.len() - 16);
+		let last: &mut [u8; 16] = last.try_into().unwrap();
+
+		let mut block = u128::from_le_bytes(*last).wrapping_add(self.hash(tweak, bulk)).to_le_bytes();
+		self.stream(block, bulk);
+
+		self.block_cipher.decrypt_block(&mut block);
+		*last = u128::from_le_bytes(block).wrapping_sub(self.hash(tweak, bulk)).to_le_bytes();
+
+		Ok(())
+	}
+}
+
+impl Drop for Adiantum {
+	fn drop(&mut self) {
+		zeroize(&mut self.stream_key);
+		zeroize(&mut self.header_key);
+		zeroize(&mut self.message_key);
+		zeroize(&mut self.nh_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Adiantum {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.stream_key);
+		::zeroize::Zeroize::zeroize(&mut self.block_cipher);
+		zeroize(&mut self.header_key);
+		zeroize(&mut self.message_key);
+		zeroize(&mut self.nh_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Adiantum {}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// checked against a python transcription of the paper, using AES-256 and
+// Poly1305 from python's cryptography package and the ChaCha12 transcription
+// from the chacha20 tests
+#[test]
+fn test_adiantum() {
+	let mut key = [0; 32];
+
+	for i in 0 .. 32 {
+		key[i] = i as u8;
+	}
+
+	let adiantum = Adiantum::new(key);
+
+	let mut buffer = [0; 16];
+	adiantum.encrypt(&[0; 32], &mut buffer);
+	assert_eq!(buffer, hex("72a48b9f268a5c74e7c2ba7cdcb156e9"));
+
+	adiantum.decrypt(&[0; 32], &mut buffer);
+	assert_eq!(buffer, [0; 16]);
+
+	let mut plaintext = [0; 512];
+
+	for i in 0 .. 512 {
+		plaintext[i] = i as u8;
+	}
+
+	// the key doubles as the tweak
+	let mut buffer = plaintext;
+	adiantum.encrypt(&key, &mut buffer);
+	assert_eq!(buffer[.. 32], hex::<32>("90b6b043a207c1462f5357399bddaf89c984a6c5ce2a1645aba7eee9b4f98f28"));
+	assert_eq!(buffer[480 ..], hex::<32>("8a72b6170e91d3862312f177a44bc0849f4eaf758f618ff8bad6e38e867e4bcc"));
+
+	adiantum.decrypt(&key, &mut buffer);
+	assert_eq!(buffer, plaintext);
+
+	// a message that isn't a multiple of 16 bytes, and a short tweak
+	let adiantum = Adiantum::new([0x42; 32]);
+
+	let mut buffer: [u8; 17] = plaintext[.. 17].try_into().unwrap();
+	adiantum.encrypt(b"sector 7", &mut buffer);
+	assert_eq!(buffer, hex("3331a1edb1fa77a59391a8cb69bc50b9ae"));
+
+	adiantum.decrypt(b"sector 7", &mut buffer);
+	assert_eq!(buffer, plaintext[.. 17]);
+
+	assert_eq!(adiantum.try_encrypt(b"sector 7", &mut buffer[.. 15]), Err(Error::InvalidLength));
+	assert_eq!(adiantum.try_decrypt(b"sector 7", &mut buffer), Ok(()));
+}
+
+// checked against the same python transcription
+#[cfg(feature = "std")]
+#[test]
+fn test_adiantum_long() {
+	let adiantum = Adiantum::new([0x42; 32]);
+
+	let mut tweak = [0; 32];
+
+	for i in 0 .. 32 {
+		tweak[i] = i as u8;
+	}
+
+	// several NH chunks, the last of them partial
+	let plaintext = (0 .. 4196).map(|i| (i % 256) as u8).collect::<Vec<_>>();
+
+	let mut buffer = plaintext.clone();
+	adiantum.encrypt(&tweak, &mut buffer);
+	assert_eq!(buffer[.. 32], hex::<32>("ca2b31d36b57c69dd36892ef44835ceb4abf7621dda0111c69d663ff702fdc50"));
+	assert_eq!(buffer[4164 ..], hex::<32>("b39e7b4b55b62d1cf5ac286407b077d9e430e2dbd9718b8514c473a6f0ddac38"));
+
+	// changing any byte of the ciphertext changes the whole plaintext
+	buffer[0] ^= 1;
+	adiantum.decrypt(&tweak, &mut buffer);
+	assert!(buffer.iter().zip(&plaintext).filter(|(a, b)| a == b).count() < 100);
+}
@@ -0,0 +1,269 @@
+//! Implemented according to [FIPS 197](https://csrc.nist.gov/pubs/fips/197/final).
+//! Rather than using lookup tables, the S-box is computed with arithmetic
+//! in GF(2 ** 8), so that no memory accesses depend on secret data.
+
+use crate::zeroize::zeroize;
+
+// multiplies two elements of GF(2 ** 8) in constant time
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+	let mut out = 0;
+
+	for _ in 0 .. 8 {
+		out ^= a & 0u8.wrapping_sub(b & 0x01);
+		let high_bit = a >> 7;
+		a = (a << 1) ^ (0x1b & 0u8.wrapping_sub(high_bit));
+		b >>= 1;
+	}
+
+	out
+}
+
+// x ** 254 is the multiplicative inverse of x, and maps 0 to 0 as required
+fn gf_inv(x: u8) -> u8 {
+	let x2 = gf_mul(x, x);
+	let x3 = gf_mul(x2, x);
+	let x6 = gf_mul(x3, x3);
+	let x12 = gf_mul(x6, x6);
+	let x15 = gf_mul(x12, x3);
+	let x30 = gf_mul(x15, x15);
+	let x60 = gf_mul(x30, x30);
+	let x120 = gf_mul(x60, x60);
+	let x240 = gf_mul(x120, x120);
+	let x252 = gf_mul(x240, x12);
+	gf_mul(x252, x2)
+}
+
+fn sub_byte(x: u8) -> u8 {
+	let inv = gf_inv(x);
+	inv ^ inv.rotate_left(1) ^ inv.rotate_left(2) ^ inv.rotate_left(3) ^ inv.rotate_left(4) ^ 0x63
+}
+
+fn inv_sub_byte(x: u8) -> u8 {
+	gf_inv(x.rotate_left(1) ^ x.rotate_left(3) ^ x.rotate_left(6) ^ 0x05)
+}
+
+fn sub_word(word: [u8; 4]) -> [u8; 4] {
+	word.map(sub_byte)
+}
+
+const ROUND_CONSTANTS: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// An AES key schedule, for either AES-128 or AES-256.
+#[derive(Clone)]
+pub struct Aes {
+	round_keys: [[u8; 16]; 15],
+	num_rounds: usize,
+}
+
+impl Aes {
+	/// Creates an AES-128 instance from a 16-byte key.
+	pub fn new_128(key: [u8; 16]) -> Self {
+		Self::expand_key(&key)
+	}
+
+	/// Creates an AES-256 instance from a 32-byte key.
+	pub fn new_256(key: [u8; 32]) -> Self {
+		Self::expand_key(&key)
+	}
+
+	fn expand_key(key: &[u8]) -> Self {
+		let key_words = key.len() / 4;
+		let num_rounds = key_words + 6;
+
+		let mut words = [[0; 4]; 60];
+
+		for i in 0 .. key_words {
+			words[i].copy_from_slice(&key[4 * i .. 4 * (i + 1)]);
+		}
+
+		for i in key_words .. 4 * (num_rounds + 1) {
+			let mut temp = words[i - 1];
+
+			if i % key_words == 0 {
+				temp.rotate_left(1);
+				temp = sub_word(temp);
+				temp[0] ^= ROUND_CONSTANTS[i / key_words - 1];
+			} else if key_words > 6 && i % key_words == 4 {
+				temp = sub_word(temp);
+			}
+
+			for j in 0 .. 4 {
+				words[i][j] = words[i - key_words][j] ^ temp[j];
+			}
+		}
+
+		let mut round_keys = [[0; 16]; 15];
+
+		for i in 0 .. num_rounds + 1 {
+			for j in 0 .. 4 {
+				round_keys[i][4 * j .. 4 * (j + 1)].copy_from_slice(&words[4 * i + j]);
+			}
+		}
+
+		zeroize(&mut words);
+
+		Self {round_keys, num_rounds}
+	}
+
+	/// Encrypts a single 16-byte block in place.
+	pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+		add_round_key(block, &self.round_keys[0]);
+
+		for round in 1 .. self.num_rounds {
+			sub_bytes(block);
+			shift_rows(block);
+			mix_columns(block);
+			add_round_key(block, &self.round_keys[round]);
+		}
+
+		sub_bytes(block);
+		shift_rows(block);
+		add_round_key(block, &self.round_keys[self.num_rounds]);
+	}
+
+	/// Decrypts a single 16-byte block in place.
+	pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+		add_round_key(block, &self.round_keys[self.num_rounds]);
+
+		for round in (1 .. self.num_rounds).rev() {
+			inv_shift_rows(block);
+			inv_sub_bytes(block);
+			add_round_key(block, &self.round_keys[round]);
+			inv_mix_columns(block);
+		}
+
+		inv_shift_rows(block);
+		inv_sub_bytes(block);
+		add_round_key(block, &self.round_keys[0]);
+	}
+}
+
+impl Drop for Aes {
+	fn drop(&mut self) {
+		zeroize(&mut self.round_keys);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Aes {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.round_keys);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Aes {}
+
+// the state is stored column by column, so byte r + 4 * c is at row r and column c
+
+fn add_round_key(state: &mut [u8; 16], round_key: &[u8; 16]) {
+	for i in 0 .. 16 {
+		state[i] ^= round_key[i];
+	}
+}
+
+fn sub_bytes(state: &mut [u8; 16]) {
+	for byte in state.iter_mut() {
+		*byte = sub_byte(*byte);
+	}
+}
+
+fn inv_sub_bytes(state: &mut [u8; 16]) {
+	for byte in state.iter_mut() {
+		*byte = inv_sub_byte(*byte);
+	}
+}
+
+fn shift_rows(state: &mut [u8; 16]) {
+	let old = *state;
+
+	for row in 1 .. 4 {
+		for column in 0 .. 4 {
+			state[row + 4 * column] = old[row + 4 * ((column + row) % 4)];
+		}
+	}
+}
+
+fn inv_shift_rows(state: &mut [u8; 16]) {
+	let old = *state;
+
+	for row in 1 .. 4 {
+		for column in 0 .. 4 {
+			state[row + 4 * ((column + row) % 4)] = old[row + 4 * column];
+		}
+	}
+}
+
+fn mix_columns(state: &mut [u8; 16]) {
+	for column in state.chunks_exact_mut(4) {
+		let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+
+		column[0] = gf_mul(a0, 2) ^ gf_mul(a1, 3) ^ a2 ^ a3;
+		column[1] = a0 ^ gf_mul(a1, 2) ^ gf_mul(a2, 3) ^ a3;
+		column[2] = a0 ^ a1 ^ gf_mul(a2, 2) ^ gf_mul(a3, 3);
+		column[3] = gf_mul(a0, 3) ^ a1 ^ a2 ^ gf_mul(a3, 2);
+	}
+}
+
+fn inv_mix_columns(state: &mut [u8; 16]) {
+	for column in state.chunks_exact_mut(4) {
+		let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+
+		column[0] = gf_mul(a0, 14) ^ gf_mul(a1, 11) ^ gf_mul(a2, 13) ^ gf_mul(a3, 9);
+		column[1] = gf_mul(a0, 9) ^ gf_mul(a1, 14) ^ gf_mul(a2, 11) ^ gf_mul(a3, 13);
+		column[2] = gf_mul(a0, 13) ^ gf_mul(a1, 9) ^ gf_mul(a2, 14) ^ gf_mul(a3, 11);
+		column[3] = gf_mul(a0, 11) ^ gf_mul(a1, 13) ^ gf_mul(a2, 9) ^ gf_mul(a3, 14);
+	}
+}
+
+#[test]
+fn fips197_aes128_test_vector() {
+	let key = [
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+	];
+
+	let mut block = [
+		0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+		0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+	];
+
+	let plaintext = block;
+	let aes = Aes::new_128(key);
+	aes.encrypt_block(&mut block);
+
+	assert_eq!(block, [
+		0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30,
+		0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+	]);
+
+	aes.decrypt_block(&mut block);
+	assert_eq!(block, plaintext);
+}
+
+#[test]
+fn fips197_aes256_test_vector() {
+	let key = [
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+		0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+		0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+	];
+
+	let mut block = [
+		0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+		0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+	];
+
+	let plaintext = block;
+	let aes = Aes::new_256(key);
+	aes.encrypt_block(&mut block);
+
+	assert_eq!(block, [
+		0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf,
+		0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+	]);
+
+	aes.decrypt_block(&mut block);
+	assert_eq!(block, plaintext);
+}
@@ -0,0 +1,294 @@
+//! Implemented according to [IEEE 1619-2018](https://standards.ieee.org/ieee/1619/5767/)
+//! and [NIST SP 800-38E](https://csrc.nist.gov/pubs/sp/800/38/e/final).
+//! XTS-AES is a length-preserving mode for encrypting storage, such as the
+//! sectors of a disk: each sector, or data unit, is encrypted under a tweak
+//! derived from its number, so identical data in different sectors encrypts
+//! differently, and no room is needed for a nonce or tag. Sectors whose
+//! length isn't a multiple of 16 bytes are handled with ciphertext stealing.
+//!
+//! XTS doesn't authenticate anything: an attacker who can modify the storage
+//! can replace a sector with an older version of itself, or scramble 16-byte
+//! blocks of it undetected. Encrypting the same sector repeatedly also shows
+//! which of its blocks changed. When there's room for a nonce and tag, an
+//! authenticated encryption scheme such as XChaCha20-Poly1305 is better.
+//!
+//! ```
+//! use libkrypton::aes_xts::AesXts;
+//!
+//! let xts = AesXts::new_256([0x42; 64]);
+//!
+//! let mut sector = [0; 512];
+//! xts.encrypt_sector(7, &mut sector);
+//! assert_ne!(sector, [0; 512]);
+//!
+//! xts.decrypt_sector(7, &mut sector);
+//! assert_eq!(sector, [0; 512]);
+//! ```
+
+use crate::aes::Aes;
+use crate::Error;
+
+// multiplies the tweak by the primitive element of GF(2 ** 128), with the
+// tweak read as a little-endian integer
+fn mul_alpha(tweak: u128) -> u128 {
+	(tweak << 1) ^ (0x87 & 0u128.wrapping_sub(tweak >> 127))
+}
+
+fn xor_block(block: &mut [u8; 16], tweak: u128) {
+	for (byte, key) in block.iter_mut().zip(tweak.to_le_bytes()) {
+		*byte ^= key;
+	}
+}
+
+/// An XTS-AES key schedule, for either XTS-AES-128 or XTS-AES-256. Zeroized
+/// when dropped.
+#[derive(Clone)]
+pub struct AesXts {
+	data_cipher: Aes,
+	tweak_cipher: Aes,
+}
+
+impl AesXts {
+	/// Creates an XTS-AES-128 instance from a 32-byte key: the AES-128 key
+	/// for the data followed by the one for the tweak. The two halves must be
+	/// generated independently, since XTS is weaker when they're equal.
+	pub fn new_128(key: [u8; 32]) -> Self {
+		Self {
+			data_cipher: Aes::new_128(key[.. 16].try_into().unwrap()),
+			tweak_cipher: Aes::new_128(key[16 ..].try_into().unwrap()),
+		}
+	}
+
+	/// Creates an XTS-AES-256 instance from a 64-byte key: the AES-256 key
+	/// for the data followed by the one for the tweak. The two halves must be
+	/// generated independently, since XTS is weaker when they're equal.
+	pub fn new_256(key: [u8; 64]) -> Self {
+		Self {
+			data_cipher: Aes::new_256(key[.. 32].try_into().unwrap()),
+			tweak_cipher: Aes::new_256(key[32 ..].try_into().unwrap()),
+		}
+	}
+
+	fn initial_tweak(&self, sector: u128) -> u128 {
+		let mut tweak = sector.to_le_bytes();
+		self.tweak_cipher.encrypt_block(&mut tweak);
+		u128::from_le_bytes(tweak)
+	}
+
+	fn crypt_block(&self, block: &mut [u8; 16], tweak: u128, decrypt: bool) {
+		xor_block(block, tweak);
+
+		match decrypt {
+			false => self.data_cipher.encrypt_block(block),
+			true => self.data_cipher.decrypt_block(block),
+		}
+
+		xor_block(block, tweak);
+	}
+
+	/// Encrypts the sector numbered `sector` in place. The standard limits
+	/// sectors to 2 ** 20 blocks of 16 bytes, though this isn't checked.
+	///
+	/// # Panics
+	/// * Panics if the sector is shorter than 16 bytes.
+	pub fn encrypt_sector(&self, sector: u128, buffer: &mut [u8]) {
+		self.try_encrypt_sector(sector, buffer).expect("aes-xts needs at least one block");
+	}
+
+	/// Like [`AesXts::encrypt_sector`], but returns [`Error::InvalidLength`]
+	/// instead of panicking if the sector is shorter than 16 bytes.
+	pub fn try_encrypt_sector(&self, sector: u128, buffer: &mut [u8]) -> Result<(), Error> {
+		if buffer.len() < 16 {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut tweak = self.initial_tweak(sector);
+		let (blocks, partial) = buffer.split_at_mut(buffer.len() / 16 * 16);
+
+		for chunk in blocks.chunks_exact_mut(16) {
+			self.crypt_block(chunk.try_into().unwrap(), tweak, false);
+			tweak = mul_alpha(tweak);
+		}
+
+		if !partial.is_empty() {
+			// the partial block takes the start of the last full ciphertext
+			// block, which is replaced with the encryption of the partial
+			// block padded with the rest of it
+			let start = blocks.len() - 16;
+			let last: &mut [u8; 16] = (&mut blocks[start ..]).try_into().unwrap();
+			let mut stolen = *last;
+
+			stolen[.. partial.len()].swap_with_slice(partial);
+			self.crypt_block(&mut stolen, tweak, false);
+			*last = stolen;
+		}
+
+		Ok(())
+	}
+
+	/// Decrypts the sector numbered `sector` in place.
+	///
+	/// # Panics
+	/// * Panics if the sector is shorter than 16 bytes.
+	pub fn decrypt_sector(&self, sector: u128, buffer: &mut [u8]) {
+		self.try_decrypt_sector(sector, buffer).expect("aes-xts needs at least one block");
+	}
+
+	/// Like [`AesXts::decrypt_sector`], but returns [`Error::InvalidLength`]
+	/// instead of panicking if the sector is shorter than 16 bytes.
+	pub fn try_decrypt_sector(&self, sector: u128, buffer: &mut [u8]) -> Result<(), Error> {
+		if buffer.len() < 16 {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut tweak = self.initial_tweak(sector);
+		let (blocks, partial) = buffer.split_at_mut(buffer.len() / 16 * 16);
+		let full_blocks = blocks.len() / 16;
+
+		for (i, chunk) in blocks.chunks_exact_mut(16).enumerate() {
+			let block: &mut [u8; 16] = chunk.try_into().unwrap();
+
+			// with ciphertext stealing, the last full block was encrypted
+			// under the tweak after its own
+			if i == full_blocks - 1 && !partial.is_empty() {
+				let next_tweak = mul_alpha(tweak);
+				self.crypt_block(block, next_tweak, true);
+
+				block[.. partial.len()].swap_with_slice(partial);
+				self.crypt_block(block, tweak, true);
+			} else {
+				self.crypt_block(block, tweak, true);
+				tweak = mul_alpha(tweak);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Encrypts consecutive sectors of `sector_size` bytes in place, the first
+	/// of which is numbered `first_sector`.
+	///
+	/// # Panics
+	/// * Panics if `sector_size` is less than 16, or the length of `buffer`
+	///   isn't a multiple of it.
+	pub fn encrypt_sectors(&self, first_sector: u128, sector_size: usize, buffer: &mut [u8]) {
+		self.try_encrypt_sectors(first_sector, sector_size, buffer).expect("aes-xts needs whole sectors");
+	}
+
+	/// Like [`AesXts::encrypt_sectors`], but returns [`Error::InvalidLength`]
+	/// instead of panicking, in which case `buffer` is left unchanged.
+	pub fn try_encrypt_sectors(&self, first_sector: u128, sector_size: usize, buffer: &mut [u8]) -> Result<(), Error> {
+		if sector_size < 16 || !buffer.len().is_multiple_of(sector_size) {
+			return Err(Error::InvalidLength);
+		}
+
+		for (i, chunk) in buffer.chunks_exact_mut(sector_size).enumerate() {
+			self.encrypt_sector(first_sector.wrapping_add(i as u128), chunk);
+		}
+
+		Ok(())
+	}
+
+	/// Decrypts consecutive sectors of `sector_size` bytes in place, the first
+	/// of which is numbered `first_sector`.
+	///
+	/// # Panics
+	/// * Panics if `sector_size` is less than 16, or the length of `buffer`
+	///   isn't a multiple of it.
+	pub fn decrypt_sectors(&self, first_sector: u128, sector_size: usize, buffer: &mut [u8]) {
+		self.try_decrypt_sectors(first_sector, sector_size, buffer).expect("aes-xts needs whole sectors");
+	}
+
+	/// Like [`AesXts::decrypt_sectors`], but returns [`Error::InvalidLength`]
+	/// instead of panicking, in which case `buffer` is left unchanged.
+	pub fn try_decrypt_sectors(&self, first_sector: u128, sector_size: usize, buffer: &mut [u8]) -> Result<(), Error> {
+		if sector_size < 16 || !buffer.len().is_multiple_of(sector_size) {
+			return Err(Error::InvalidLength);
+		}
+
+		for (i, chunk) in buffer.chunks_exact_mut(sector_size).enumerate() {
+			self.decrypt_sector(first_sector.wrapping_add(i as u128), chunk);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for AesXts {
+	fn zeroize(&mut self) {
+		self.data_cipher.zeroize();
+		self.tweak_cipher.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for AesXts {}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// vectors 2 and 15 from IEEE 1619
+#[test]
+fn test_xts_aes_128() {
+	let xts = AesXts::new_128(hex("1111111111111111111111111111111122222222222222222222222222222222"));
+
+	let mut buffer = [0x44; 32];
+	xts.encrypt_sector(0x3333333333, &mut buffer);
+	assert_eq!(buffer, hex("c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0"));
+
+	xts.decrypt_sector(0x3333333333, &mut buffer);
+	assert_eq!(buffer, [0x44; 32]);
+
+	let xts = AesXts::new_128(hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0"));
+	let plaintext: [u8; 17] = hex("000102030405060708090a0b0c0d0e0f10");
+
+	let mut buffer = plaintext;
+	xts.encrypt_sector(0x123456789a, &mut buffer);
+	assert_eq!(buffer, hex("6c1625db4671522d3d7599601de7ca09ed"));
+
+	xts.decrypt_sector(0x123456789a, &mut buffer);
+	assert_eq!(buffer, plaintext);
+}
+
+// vector 10 from IEEE 1619, and the rest checked against XTS mode from python's
+// cryptography package
+#[test]
+fn test_xts_aes_256() {
+	let xts = AesXts::new_256(hex("27182818284590452353602874713526624977572470936999595749669676273141592653589793238462643383279502884197169399375105820974944592"));
+
+	let mut plaintext = [0; 512];
+
+	for i in 0 .. 512 {
+		plaintext[i] = i as u8;
+	}
+
+	let mut buffer = plaintext;
+	xts.encrypt_sector(0xff, &mut buffer);
+	assert_eq!(buffer[.. 32], hex::<32>("1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b"));
+	assert_eq!(buffer[480 ..], hex::<32>("773dad38014bd2092fa755c824bb5e54c4f36ffda9fcea70b9c6e693e148c151"));
+
+	xts.decrypt_sector(0xff, &mut buffer);
+	assert_eq!(buffer, plaintext);
+
+	let mut buffer: [u8; 31] = plaintext[.. 31].try_into().unwrap();
+	xts.encrypt_sector(1, &mut buffer);
+	assert_eq!(buffer, hex("4ff99e498785790f40f9c9d7c8da3074cdd765f24ea1d4ecfc5af7233967ef"));
+
+	xts.decrypt_sector(1, &mut buffer);
+	assert_eq!(buffer, plaintext[.. 31]);
+
+	// sectors are numbered consecutively
+	let mut buffer: [u8; 64] = plaintext[.. 64].try_into().unwrap();
+	xts.encrypt_sectors(5, 32, &mut buffer);
+	assert_eq!(buffer, hex("ae2d6eefd1d4a383caa92e9ff0240bf489f39b8be14a031ada9184623409a5636ecba067d098a1464d0897535d0e32c3483b440e11afc212b66937c6b87f68f1"));
+
+	xts.decrypt_sectors(5, 32, &mut buffer);
+	assert_eq!(buffer, plaintext[.. 64]);
+
+	// partial blocks and sectors are rejected without touching the buffer
+	assert_eq!(xts.try_encrypt_sector(0, &mut buffer[.. 15]), Err(Error::InvalidLength));
+	assert_eq!(xts.try_decrypt_sectors(0, 48, &mut buffer), Err(Error::InvalidLength));
+	assert_eq!(xts.try_encrypt_sectors(0, 8, &mut buffer), Err(Error::InvalidLength));
+	assert_eq!(buffer, plaintext[.. 64]);
+}
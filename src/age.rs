@@ -0,0 +1,824 @@
+//! Implemented according to the [age v1 specification](https://age-encryption.org/v1).
+//! age is a simple file encryption format, where a random file key is wrapped
+//! for each recipient in the header, and the file itself is encrypted with
+//! ChaCha20-Poly1305 in chunks of 64 KiB. Files can be encrypted to X25519
+//! recipients, written like `age1...`, or with a passphrase, and are
+//! interoperable with the `age` and `rage` command line tools. The ASCII
+//! armored format isn't supported.
+//!
+//! ```
+//! use libkrypton::age::{self, Identity};
+//! use libkrypton::x25519::StaticSecret;
+//!
+//! # let secret_key = [0x42; 32];
+//! # let randomness = [0x24; 32];
+//! let identity = Identity::new(StaticSecret::from_bytes(secret_key));
+//! let recipient = identity.recipient();
+//!
+//! let file = age::encrypt(&[recipient], b"attack at dawn", randomness).unwrap();
+//!
+//! assert_eq!(age::decrypt(&[identity], &file).unwrap(), b"attack at dawn");
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::string::String;
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::base64;
+use crate::bech32;
+use crate::chacha20::ChaCha20;
+use crate::chacha20poly1305::{chacha20poly1305_decrypt, chacha20poly1305_encrypt};
+use crate::hkdf::hkdf_sha256;
+use crate::hmac::{hmac_sha256, hmac_sha256_verify};
+use crate::scrypt::scrypt;
+use crate::x25519::{EphemeralSecret, PublicKey, StaticSecret};
+use crate::zeroize::zeroize;
+
+/// The work factor used by the `age` command line tool for passphrases, as
+/// the base 2 logarithm of the scrypt cost parameter. This takes about a
+/// second and 256 MiB of memory.
+pub const DEFAULT_LOG_N: u8 = 18;
+
+const VERSION_LINE: &str = "age-encryption.org/v1";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+// the number of base64 characters in each full line of a stanza body
+const LINE_LENGTH: usize = 64;
+
+/// An error returned when parsing age keys, or encrypting or decrypting
+/// files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The text passed in is not a valid age recipient or identity.
+	InvalidKey,
+	/// The header of the file is malformed.
+	InvalidHeader,
+	/// None of the identities or the passphrase could unwrap the file key.
+	NoMatchingIdentity,
+	/// The passphrase-encrypted file needs more work to decrypt than allowed.
+	WorkFactorTooHigh,
+	/// The header was modified.
+	InvalidHeaderMac,
+	/// The payload is truncated or was modified.
+	DecryptionFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidKey => "invalid age key",
+			Error::InvalidHeader => "invalid age header",
+			Error::NoMatchingIdentity => "no identity matched any of the age recipients",
+			Error::WorkFactorTooHigh => "age scrypt work factor is too high",
+			Error::InvalidHeaderMac => "invalid age header mac",
+			Error::DecryptionFailed => "failed to decrypt age payload",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The public key of an X25519 recipient, which files are encrypted to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Recipient {
+	key: PublicKey,
+}
+
+impl Recipient {
+	pub fn new(key: PublicKey) -> Self {
+		Self {key}
+	}
+
+	/// Parses a recipient from its `age1...` encoding.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		match bech32::decode(text) {
+			Some((hrp, data)) if hrp == RECIPIENT_HRP && data.len() == 32 && text.starts_with(RECIPIENT_HRP) => {
+				Ok(Self {key: PublicKey::from_bytes(data.try_into().unwrap())})
+			},
+			_ => Err(Error::InvalidKey),
+		}
+	}
+
+	/// Encodes this recipient as `age1...`, as printed by `age-keygen`.
+	pub fn to_text(&self) -> String {
+		bech32::encode(RECIPIENT_HRP, self.key.as_bytes())
+	}
+
+	/// Returns the underlying X25519 public key.
+	pub fn public_key(&self) -> PublicKey {
+		self.key
+	}
+}
+
+/// The private key of an X25519 recipient, which files are decrypted with.
+#[derive(Clone, Debug)]
+pub struct Identity {
+	key: StaticSecret,
+}
+
+impl Identity {
+	pub fn new(key: StaticSecret) -> Self {
+		Self {key}
+	}
+
+	/// Generates a new identity using a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		Self {key: StaticSecret::generate(rng)}
+	}
+
+	/// Parses an identity from its `AGE-SECRET-KEY-1...` encoding.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		let is_uppercase = !text.bytes().any(|x| x.is_ascii_lowercase());
+
+		match bech32::decode(text) {
+			Some((hrp, mut data)) if hrp == IDENTITY_HRP && data.len() == 32 && is_uppercase => {
+				let key = StaticSecret::from_bytes(data[..].try_into().unwrap());
+				zeroize(&mut data);
+
+				Ok(Self {key})
+			},
+			Some((_, mut data)) => {
+				zeroize(&mut data);
+				Err(Error::InvalidKey)
+			},
+			None => Err(Error::InvalidKey),
+		}
+	}
+
+	/// Encodes this identity as `AGE-SECRET-KEY-1...`, as written by
+	/// `age-keygen`. This must be kept secret.
+	pub fn to_text(&self) -> String {
+		let mut bytes = self.key.to_bytes();
+		let out = bech32::encode(IDENTITY_HRP, &bytes).to_ascii_uppercase();
+		zeroize(&mut bytes);
+
+		out
+	}
+
+	/// Returns the recipient that files can be encrypted to for this
+	/// identity.
+	pub fn recipient(&self) -> Recipient {
+		Recipient {key: PublicKey::from(&self.key)}
+	}
+
+	/// Returns the underlying X25519 private key.
+	pub fn secret_key(&self) -> &StaticSecret {
+		&self.key
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Identity {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Identity {}
+
+// a recipient stanza from the header
+struct Stanza {
+	tag: String,
+	args: Vec<String>,
+	body: Vec<u8>,
+}
+
+impl Stanza {
+	fn write(&self, out: &mut String) {
+		out.push_str("->");
+
+		for arg in [&self.tag].into_iter().chain(&self.args) {
+			out.push(' ');
+			out.push_str(arg);
+		}
+
+		out.push('\n');
+
+		let mut body = String::new();
+		base64::encode_unpadded(&self.body, &mut body);
+
+		// every line is full except the last, which may be empty
+		let mut rest = &body[..];
+
+		loop {
+			let (line, next) = rest.split_at(rest.len().min(LINE_LENGTH));
+			out.push_str(line);
+			out.push('\n');
+
+			if line.len() < LINE_LENGTH {
+				break;
+			}
+
+			rest = next;
+		}
+	}
+}
+
+// a valid argument is a nonempty string of printable ascii characters
+fn is_valid_arg(arg: &str) -> bool {
+	!arg.is_empty() && arg.bytes().all(|x| (33 ..= 126).contains(&x))
+}
+
+// splits off the next line of the header, which has to be valid ascii
+fn read_line<'a>(input: &mut &'a [u8]) -> Result<&'a str, Error> {
+	let end = input.iter().position(|&x| x == b'\n').ok_or(Error::InvalidHeader)?;
+	let line = &input[.. end];
+	*input = &input[end + 1 ..];
+
+	if !line.iter().all(|&x| (32 ..= 126).contains(&x)) {
+		return Err(Error::InvalidHeader);
+	}
+
+	Ok(core::str::from_utf8(line).unwrap())
+}
+
+struct Header<'a> {
+	stanzas: Vec<Stanza>,
+	// everything up to and including the `---` the mac is computed over
+	mac_input: &'a [u8],
+	mac: [u8; 32],
+	payload: &'a [u8],
+}
+
+fn parse_header(file: &[u8]) -> Result<Header<'_>, Error> {
+	let mut input = file;
+
+	if read_line(&mut input)? != VERSION_LINE {
+		return Err(Error::InvalidHeader);
+	}
+
+	let mut stanzas = Vec::new();
+
+	loop {
+		let line_start = file.len() - input.len();
+		let line = read_line(&mut input)?;
+
+		if let Some(encoded_mac) = line.strip_prefix("--- ") {
+			let mac = base64::decode_unpadded(encoded_mac)
+				.and_then(|x| x.try_into().ok())
+				.ok_or(Error::InvalidHeader)?;
+
+			return Ok(Header {
+				stanzas,
+				mac_input: &file[.. line_start + 3],
+				mac,
+				payload: input,
+			});
+		}
+
+		let mut args = line
+			.strip_prefix("-> ")
+			.ok_or(Error::InvalidHeader)?
+			.split(' ')
+			.map(String::from);
+
+		let tag = args.next().unwrap();
+		let args: Vec<String> = args.collect();
+
+		if !is_valid_arg(&tag) || !args.iter().all(|x| is_valid_arg(x)) {
+			return Err(Error::InvalidHeader);
+		}
+
+		let mut body = Vec::new();
+
+		loop {
+			let line = read_line(&mut input)?;
+
+			if line.len() > LINE_LENGTH {
+				return Err(Error::InvalidHeader);
+			}
+
+			body.extend(base64::decode_unpadded(line).ok_or(Error::InvalidHeader)?);
+
+			if line.len() < LINE_LENGTH {
+				break;
+			}
+		}
+
+		stanzas.push(Stanza {tag, args, body});
+	}
+}
+
+fn decode_arg<const N: usize>(arg: &str) -> Result<[u8; N], Error> {
+	base64::decode_unpadded(arg)
+		.and_then(|x| x.try_into().ok())
+		.ok_or(Error::InvalidHeader)
+}
+
+// encrypts the file key with a key derived for one recipient
+fn wrap_file_key(wrap_key: &[u8; 32], file_key: &[u8; 16]) -> Vec<u8> {
+	let mut body = file_key.to_vec();
+	let tag = chacha20poly1305_encrypt(*wrap_key, [0; 12], &[], &mut body);
+	body.extend_from_slice(&tag);
+
+	body
+}
+
+fn unwrap_file_key(wrap_key: &[u8; 32], body: &[u8]) -> Option<[u8; 16]> {
+	let mut file_key: [u8; 16] = body[.. 16].try_into().unwrap();
+	let tag = body[16 ..].try_into().unwrap();
+
+	match chacha20poly1305_decrypt(*wrap_key, [0; 12], &[], &mut file_key, tag) {
+		Ok(()) => Some(file_key),
+		Err(_) => None,
+	}
+}
+
+fn x25519_wrap_key(shared_secret: &[u8; 32], share: &PublicKey, recipient: &PublicKey) -> [u8; 32] {
+	let mut salt = [0; 64];
+	salt[0 .. 32].copy_from_slice(share.as_bytes());
+	salt[32 .. 64].copy_from_slice(recipient.as_bytes());
+
+	let mut wrap_key = [0; 32];
+	hkdf_sha256(&salt, shared_secret, X25519_LABEL, &mut wrap_key);
+	wrap_key
+}
+
+fn scrypt_wrap_key(passphrase: &[u8], salt: &[u8; 16], log_n: u8) -> [u8; 32] {
+	let mut full_salt = [0; 28 + 16];
+	full_salt[.. 28].copy_from_slice(SCRYPT_LABEL);
+	full_salt[28 ..].copy_from_slice(salt);
+
+	let mut wrap_key = [0; 32];
+	scrypt(passphrase, &full_salt, log_n, 8, 1, &mut wrap_key);
+	wrap_key
+}
+
+fn header_mac_key(file_key: &[u8; 16]) -> [u8; 32] {
+	let mut mac_key = [0; 32];
+	hkdf_sha256(&[], file_key, b"header", &mut mac_key);
+	mac_key
+}
+
+fn payload_key(file_key: &[u8; 16], nonce: &[u8]) -> [u8; 32] {
+	let mut key = [0; 32];
+	hkdf_sha256(nonce, file_key, b"payload", &mut key);
+	key
+}
+
+// the nonce of a STREAM chunk: an 11-byte big-endian counter, followed by a
+// byte that's 1 for the last chunk
+fn chunk_nonce(counter: u64, last: bool) -> [u8; 12] {
+	let mut nonce = [0; 12];
+	nonce[3 .. 11].copy_from_slice(&counter.to_be_bytes());
+	nonce[11] = last as u8;
+	nonce
+}
+
+// writes the header and payload, given the stanzas wrapping `file_key`
+fn write_file(stanzas: &[Stanza], file_key: &[u8; 16], payload_nonce: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+	let mut header = String::new();
+	header.push_str(VERSION_LINE);
+	header.push('\n');
+
+	for stanza in stanzas {
+		stanza.write(&mut header);
+	}
+
+	header.push_str("---");
+
+	let mut mac_key = header_mac_key(file_key);
+	let mac = hmac_sha256(&mac_key, header.as_bytes());
+	zeroize(&mut mac_key);
+
+	header.push(' ');
+	base64::encode_unpadded(&mac, &mut header);
+	header.push('\n');
+
+	let num_chunks = plaintext.len().div_ceil(CHUNK_SIZE).max(1);
+
+	let mut out = Vec::with_capacity(header.len() + 16 + plaintext.len() + num_chunks * TAG_SIZE);
+	out.extend_from_slice(header.as_bytes());
+	out.extend_from_slice(payload_nonce);
+
+	let mut key = payload_key(file_key, payload_nonce);
+
+	for i in 0 .. num_chunks {
+		let chunk = &plaintext[i * CHUNK_SIZE .. ((i + 1) * CHUNK_SIZE).min(plaintext.len())];
+		let start = out.len();
+		out.extend_from_slice(chunk);
+
+		let nonce = chunk_nonce(i as u64, i == num_chunks - 1);
+		let tag = chacha20poly1305_encrypt(key, nonce, &[], &mut out[start ..]);
+		out.extend_from_slice(&tag);
+	}
+
+	zeroize(&mut key);
+
+	out
+}
+
+// checks the header mac and decrypts the payload
+fn read_payload(header: &Header, file_key: &[u8; 16]) -> Result<Vec<u8>, Error> {
+	let mut mac_key = header_mac_key(file_key);
+	let valid = hmac_sha256_verify(&mac_key, header.mac_input, &header.mac);
+	zeroize(&mut mac_key);
+
+	if !valid {
+		return Err(Error::InvalidHeaderMac);
+	}
+
+	if header.payload.len() < 16 + TAG_SIZE {
+		return Err(Error::DecryptionFailed);
+	}
+
+	let (nonce, mut rest) = header.payload.split_at(16);
+	let mut key = payload_key(file_key, nonce);
+
+	let mut plaintext = Vec::with_capacity(rest.len());
+	let mut counter = 0;
+
+	let result = loop {
+		let chunk_len = rest.len().min(CHUNK_SIZE + TAG_SIZE);
+		let last = chunk_len == rest.len();
+
+		// only an empty file has an empty chunk, which is then the only one
+		if chunk_len < TAG_SIZE || (last && chunk_len == TAG_SIZE && counter != 0) {
+			break Err(Error::DecryptionFailed);
+		}
+
+		let (chunk, tag) = rest[.. chunk_len].split_at(chunk_len - TAG_SIZE);
+		let start = plaintext.len();
+		plaintext.extend_from_slice(chunk);
+
+		let nonce = chunk_nonce(counter, last);
+
+		if chacha20poly1305_decrypt(key, nonce, &[], &mut plaintext[start ..], tag.try_into().unwrap()).is_err() {
+			break Err(Error::DecryptionFailed);
+		}
+
+		if last {
+			break Ok(());
+		}
+
+		rest = &rest[chunk_len ..];
+		counter += 1;
+	};
+
+	zeroize(&mut key);
+
+	match result {
+		Ok(()) => Ok(plaintext),
+		Err(error) => {
+			zeroize(&mut plaintext);
+			Err(error)
+		},
+	}
+}
+
+/// Encrypts `plaintext` as an age file that any of the `recipients` can
+/// decrypt. `randomness` is expanded into the file key, the payload nonce and
+/// an ephemeral private key for each recipient, so it must be freshly
+/// generated by a secure random source for every file.
+///
+/// # Panics
+/// * Panics if `recipients` is empty.
+pub fn encrypt(recipients: &[Recipient], plaintext: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+	assert!(!recipients.is_empty());
+
+	let mut random_stream = ChaCha20::new(randomness, [0; 12]);
+
+	let mut file_key = [0; 16];
+	let mut payload_nonce = [0; 16];
+	random_stream.read_infallible(&mut file_key);
+	random_stream.read_infallible(&mut payload_nonce);
+
+	let mut stanzas = Vec::with_capacity(recipients.len());
+
+	for recipient in recipients {
+		let mut ephemeral = [0; 32];
+		random_stream.read_infallible(&mut ephemeral);
+
+		let ephemeral_secret = EphemeralSecret::from_bytes(ephemeral);
+		let share = PublicKey::from(&ephemeral_secret);
+		zeroize(&mut ephemeral);
+
+		let shared_secret = match ephemeral_secret.diffie_hellman(&recipient.key) {
+			Ok(shared_secret) => shared_secret,
+			Err(_) => {
+				zeroize(&mut file_key);
+				return Err(Error::InvalidKey);
+			},
+		};
+
+		let mut wrap_key = x25519_wrap_key(shared_secret.as_bytes(), &share, &recipient.key);
+		let mut encoded_share = String::new();
+		base64::encode_unpadded(share.as_bytes(), &mut encoded_share);
+
+		stanzas.push(Stanza {
+			tag: String::from("X25519"),
+			args: vec![encoded_share],
+			body: wrap_file_key(&wrap_key, &file_key),
+		});
+
+		zeroize(&mut wrap_key);
+	}
+
+	let out = write_file(&stanzas, &file_key, &payload_nonce, plaintext);
+	zeroize(&mut file_key);
+
+	Ok(out)
+}
+
+/// Encrypts `plaintext` like [`encrypt`], with randomness generated by `rng`,
+/// which must be a cryptographically secure random number generator.
+///
+/// # Panics
+/// * Panics if `recipients` is empty.
+#[cfg(feature = "rand_core")]
+pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+	recipients: &[Recipient],
+	plaintext: &[u8],
+	rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = encrypt(recipients, plaintext, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Encrypts `plaintext` as an age file that can be decrypted with
+/// `passphrase`. The key is derived with scrypt, with a cost parameter of
+/// `2 ** log_n`, such as [`DEFAULT_LOG_N`]. `randomness` is expanded into the
+/// file key, the payload nonce and the scrypt salt, so it must be freshly
+/// generated by a secure random source for every file.
+///
+/// # Panics
+/// * Panics if `log_n` is 0 or at least 32.
+pub fn encrypt_with_passphrase(passphrase: &[u8], log_n: u8, plaintext: &[u8], randomness: [u8; 32]) -> Vec<u8> {
+	assert!(log_n != 0 && log_n < 32);
+
+	let mut random_stream = ChaCha20::new(randomness, [0; 12]);
+
+	let mut file_key = [0; 16];
+	let mut payload_nonce = [0; 16];
+	let mut salt = [0; 16];
+	random_stream.read_infallible(&mut file_key);
+	random_stream.read_infallible(&mut payload_nonce);
+	random_stream.read_infallible(&mut salt);
+
+	let mut wrap_key = scrypt_wrap_key(passphrase, &salt, log_n);
+	let mut encoded_salt = String::new();
+	base64::encode_unpadded(&salt, &mut encoded_salt);
+
+	let stanza = Stanza {
+		tag: String::from("scrypt"),
+		args: vec![encoded_salt, format!("{}", log_n)],
+		body: wrap_file_key(&wrap_key, &file_key),
+	};
+
+	let out = write_file(&[stanza], &file_key, &payload_nonce, plaintext);
+
+	zeroize(&mut wrap_key);
+	zeroize(&mut file_key);
+
+	out
+}
+
+/// Encrypts `plaintext` like [`encrypt_with_passphrase`], with randomness
+/// generated by `rng`, which must be a cryptographically secure random number
+/// generator.
+///
+/// # Panics
+/// * Panics if `log_n` is 0 or at least 32.
+#[cfg(feature = "rand_core")]
+pub fn encrypt_with_passphrase_with_rng<R: RngCore + CryptoRng>(
+	passphrase: &[u8],
+	log_n: u8,
+	plaintext: &[u8],
+	rng: &mut R,
+) -> Vec<u8> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = encrypt_with_passphrase(passphrase, log_n, plaintext, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Decrypts an age file with whichever of the `identities` it was encrypted
+/// to. Stanzas for other kinds of recipients are skipped.
+pub fn decrypt(identities: &[Identity], file: &[u8]) -> Result<Vec<u8>, Error> {
+	let header = parse_header(file)?;
+
+	// a passphrase-encrypted file is never encrypted to anything else as well
+	if header.stanzas.iter().any(|x| x.tag == "scrypt") {
+		return Err(Error::NoMatchingIdentity);
+	}
+
+	for stanza in header.stanzas.iter().filter(|x| x.tag == "X25519") {
+		if stanza.args.len() != 1 || stanza.body.len() != 32 {
+			return Err(Error::InvalidHeader);
+		}
+
+		let share = PublicKey::from_bytes(decode_arg(&stanza.args[0])?);
+
+		for identity in identities {
+			// a low-order share makes the header invalid for every identity
+			let shared_secret = identity.key.diffie_hellman(&share).map_err(|_| Error::InvalidHeader)?;
+			let mut wrap_key = x25519_wrap_key(shared_secret.as_bytes(), &share, &PublicKey::from(&identity.key));
+			let file_key = unwrap_file_key(&wrap_key, &stanza.body);
+			zeroize(&mut wrap_key);
+
+			if let Some(mut file_key) = file_key {
+				let result = read_payload(&header, &file_key);
+				zeroize(&mut file_key);
+
+				return result;
+			}
+		}
+	}
+
+	Err(Error::NoMatchingIdentity)
+}
+
+/// Decrypts a passphrase-encrypted age file. Files that need a scrypt cost
+/// parameter of more than `2 ** max_log_n` are rejected with
+/// [`Error::WorkFactorTooHigh`] before doing any work, since otherwise a
+/// malicious file could take an unbounded amount of time and memory to
+/// decrypt.
+pub fn decrypt_with_passphrase(passphrase: &[u8], max_log_n: u8, file: &[u8]) -> Result<Vec<u8>, Error> {
+	let header = parse_header(file)?;
+
+	let stanza = match &header.stanzas[..] {
+		[stanza] if stanza.tag == "scrypt" => stanza,
+		stanzas if stanzas.iter().any(|x| x.tag == "scrypt") => return Err(Error::InvalidHeader),
+		_ => return Err(Error::NoMatchingIdentity),
+	};
+
+	if stanza.args.len() != 2 || stanza.body.len() != 32 {
+		return Err(Error::InvalidHeader);
+	}
+
+	let salt = decode_arg(&stanza.args[0])?;
+	let encoded_log_n = &stanza.args[1];
+
+	// the work factor is a decimal number without leading zeros
+	if !encoded_log_n.bytes().all(|x| x.is_ascii_digit()) || encoded_log_n.starts_with('0') {
+		return Err(Error::InvalidHeader);
+	}
+
+	let log_n: u8 = encoded_log_n.parse().map_err(|_| Error::WorkFactorTooHigh)?;
+
+	if log_n > max_log_n || log_n >= 32 {
+		return Err(Error::WorkFactorTooHigh);
+	}
+
+	let mut wrap_key = scrypt_wrap_key(passphrase, &salt, log_n);
+	let file_key = unwrap_file_key(&wrap_key, &stanza.body);
+	zeroize(&mut wrap_key);
+
+	match file_key {
+		Some(mut file_key) => {
+			let result = read_payload(&header, &file_key);
+			zeroize(&mut file_key);
+
+			result
+		},
+		None => Err(Error::NoMatchingIdentity),
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[test]
+fn test_keys() {
+	let identity = Identity::new(StaticSecret::from_bytes([0x42; 32]));
+	let text = "AGE-SECRET-KEY-1GFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPQ4EGAEX";
+	assert_eq!(identity.to_text(), text);
+	assert_eq!(Identity::from_text(text).unwrap().secret_key().to_bytes(), [0x42; 32]);
+	assert_eq!(Identity::from_text(&text.to_ascii_lowercase()).unwrap_err(), Error::InvalidKey);
+
+	// the recipient is the X25519 public key, encoded with the reference bech32
+	// code from BIP 173 under the "age" prefix
+	let text = "age1zvkyg2lqzraa2lnjvqej32nkuu0ues2s82hzrye869xeexvn73equnujwj";
+	assert_eq!(identity.recipient().to_text(), text);
+	assert_eq!(Recipient::from_text(text).unwrap(), identity.recipient());
+	assert_eq!(Recipient::from_text(&text.to_ascii_uppercase()).unwrap_err(), Error::InvalidKey);
+	assert_eq!(Recipient::from_text(&identity.to_text()).unwrap_err(), Error::InvalidKey);
+}
+
+// the file is fixed by the randomness, and a Python transcription of the
+// decryption side of the age v1 specification at c2sp.org/age, using the
+// cryptography package, accepts its header mac and decrypts it with either
+// identity
+#[test]
+fn test_x25519() {
+	let identity = Identity::new(StaticSecret::from_bytes([0x42; 32]));
+	let other = Identity::new(StaticSecret::from_bytes([0x43; 32]));
+	let third = Identity::new(StaticSecret::from_bytes([0x44; 32]));
+	let identities = [identity.clone()];
+
+	let file = encrypt(&[identity.recipient(), other.recipient()], b"attack at dawn", [0x24; 32]).unwrap();
+
+	let header = "\
+		age-encryption.org/v1\n\
+		-> X25519 0MpI1DPxXU4cBA7dbdfDO6M0bZpQVWfBhEz3vfLA+1s\n\
+		GYI/J54nJRwa8pr4CK+ArZwBEGyaPNU5flYGJ43jn+c\n\
+		-> X25519 VZEFKeGsg3KBnUKJPQXC6keKC1zfGlGrZXDfAA6RWnI\n\
+		GnkrUMo/tr3NsPSppAxqpkiEFIa82ZWtwjWcj1ygWdQ\n\
+		--- NbOrxeveHOZZFXr8ttkpDoj5Z47YFDDCq6e0bDYCkTY\n";
+	let payload: [u8; 46] = hex(
+		"693b1bd5041f3cf53ee337e9d25355bd0aa1681af953f6d60fb4f3940f2a02a5\
+		a586f05ceedad3bb3c92f102b069",
+	);
+
+	assert_eq!(&file[.. header.len()], header.as_bytes());
+	assert_eq!(file[header.len() ..], payload);
+
+	assert_eq!(decrypt(&identities, &file).unwrap(), b"attack at dawn");
+	assert_eq!(decrypt(&[third.clone(), other], &file).unwrap(), b"attack at dawn");
+	assert_eq!(decrypt(&[third], &file), Err(Error::NoMatchingIdentity));
+
+	// a modified header fails the mac, even though the file key is unchanged
+	let mut modified = file.clone();
+	modified[header.find("VZEF").unwrap()] ^= 1;
+	assert_eq!(decrypt(&identities, &modified), Err(Error::InvalidHeaderMac));
+
+	let mut modified = file.clone();
+	*modified.last_mut().unwrap() ^= 1;
+	assert_eq!(decrypt(&identities, &modified), Err(Error::DecryptionFailed));
+
+	assert_eq!(decrypt(&identities, &file[.. header.len() + 16]), Err(Error::DecryptionFailed));
+}
+
+// the file is fixed by the randomness, and the same Python transcription of the
+// age v1 specification decrypts it with the passphrase
+#[test]
+fn test_passphrase() {
+	let file = encrypt_with_passphrase(b"correct horse battery staple", 10, b"attack at dawn", [0x25; 32]);
+
+	let header = "\
+		age-encryption.org/v1\n\
+		-> scrypt fF6CoTWkSVF1YuzovceP4Q 10\n\
+		3itliaSsdAYbWxtvmWzX7/YcDJi5y7akx7jn9RBbcvo\n\
+		--- KDQiqa/I4RMXKSiBTTAwQCn/eX3fwmc3y0yufiz1Wkc\n";
+	let payload: [u8; 46] = hex(
+		"0b950f65ce0679b0cf559f5edaf359a593ce99cc51ea5766cea2c350c7eb4689\
+		a1bd486053b6b222ca9c657c72df",
+	);
+
+	assert_eq!(&file[.. header.len()], header.as_bytes());
+	assert_eq!(file[header.len() ..], payload);
+
+	let decrypted = decrypt_with_passphrase(b"correct horse battery staple", 10, &file);
+	assert_eq!(decrypted.unwrap(), b"attack at dawn");
+
+	let decrypted = decrypt_with_passphrase(b"correct horse battery stapler", 10, &file);
+	assert_eq!(decrypted, Err(Error::NoMatchingIdentity));
+
+	let decrypted = decrypt_with_passphrase(b"correct horse battery staple", 9, &file);
+	assert_eq!(decrypted, Err(Error::WorkFactorTooHigh));
+
+	// identities never match passphrase-encrypted files
+	let identity = Identity::new(StaticSecret::from_bytes([0x42; 32]));
+	assert_eq!(decrypt(&[identity], &file), Err(Error::NoMatchingIdentity));
+}
+
+#[test]
+fn test_chunks() {
+	let identities = [Identity::new(StaticSecret::from_bytes([0x42; 32]))];
+	let recipients = [identities[0].recipient()];
+
+	for len in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 2 * CHUNK_SIZE] {
+		let plaintext: Vec<u8> = (0 .. len).map(|x| x as u8).collect();
+		let file = encrypt(&recipients, &plaintext, [0x26; 32]).unwrap();
+
+		assert_eq!(decrypt(&identities, &file).unwrap(), plaintext);
+
+		// dropping the last chunk leaves a file that seems to end early
+		if len > CHUNK_SIZE {
+			let truncated = &file[.. file.len() - (len - CHUNK_SIZE) - TAG_SIZE];
+			assert_eq!(decrypt(&identities, truncated), Err(Error::DecryptionFailed));
+		}
+	}
+
+	// a file whose last chunk is full, which the same Python transcription of
+	// the age v1 specification decrypts back to the plaintext
+	let plaintext: Vec<u8> = (0 .. 2 * CHUNK_SIZE).map(|x| x as u8).collect();
+	let file = encrypt(&recipients, &plaintext, [0x26; 32]).unwrap();
+	assert_eq!(file.len(), 131288);
+	assert_eq!(crate::sha2::sha256(&file), hex("151d051005ee2751d3a28b91bc85572809a08fe11b9653e4fa825f2240a81f9d"));
+}
@@ -0,0 +1,669 @@
+//! A registry of algorithm identifiers, for protocols that negotiate which
+//! hash function, AEAD, signature scheme or KEM to use at runtime instead of
+//! hard-coding one. Each kind of algorithm has an enum of the ones this crate
+//! implements, with a stable name for putting in messages and config files,
+//! and an object-safe trait that its keys and hashers are used through.
+//!
+//! ```
+//! use libkrypton::agility::{HashAlgorithm, SignatureAlgorithm};
+//!
+//! // the algorithms would come from a peer or a config file
+//! let hash = HashAlgorithm::from_name("sha3-256").unwrap();
+//! let scheme = SignatureAlgorithm::from_name("ed25519").unwrap();
+//!
+//! let signing_key = scheme.signing_key(&[0x42; 32]).unwrap();
+//! let digest = hash.hash(b"message");
+//! let signature = signing_key.sign(&digest);
+//!
+//! assert!(signing_key.verifying_key().verify(&digest, &signature).is_ok());
+//! ```
+//!
+//! Keys are passed in and out as bytes, in the encodings of the concrete
+//! types, such as [`ed25519::SigningKey::to_bytes`]. Algorithms that aren't
+//! ones the caller expects should still be checked against an allowlist, so
+//! that a peer can't downgrade a protocol to a weaker choice.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::boxed::Box;
+use std::vec::Vec;
+
+use crate::blake3;
+use crate::chacha20poly1305::{
+	chacha20poly1305_decrypt,
+	chacha20poly1305_encrypt,
+	xchacha20poly1305_decrypt,
+	xchacha20poly1305_encrypt,
+};
+use crate::ed25519;
+use crate::ed448;
+use crate::kem::ml_kem::{self, MlKem768};
+use crate::kem::{Kem, SharedSecret, X25519HkdfSha256};
+use crate::ml_dsa;
+use crate::sha2::{Sha256, Sha512};
+use crate::sha3::Sha3_256;
+use crate::x25519;
+use crate::zeroize::zeroize;
+
+/// An error returned when looking up an algorithm or using one of its keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// No algorithm of the kind has the name.
+	UnknownAlgorithm,
+	/// A key, nonce, signature or encapsulated key has the wrong length for
+	/// the algorithm.
+	InvalidLength,
+	/// A key or encapsulated key isn't valid for the algorithm.
+	InvalidKey,
+	/// A ciphertext failed to authenticate.
+	DecryptionFailed,
+	/// A signature failed to verify.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::UnknownAlgorithm => "unknown algorithm",
+			Error::InvalidLength => "invalid length for algorithm",
+			Error::InvalidKey => "invalid key for algorithm",
+			Error::DecryptionFailed => "decryption failed",
+			Error::InvalidSignature => "invalid signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+// implements the name lookups shared by the algorithm enums
+macro_rules! names_impl {
+	($type:ident, $($variant:ident => $name:literal,)*) => {
+		impl $type {
+			/// Every algorithm of this kind, in the order they're declared.
+			pub const ALL: &'static [$type] = &[$($type::$variant,)*];
+
+			/// Returns the name of the algorithm, which never changes.
+			pub fn name(self) -> &'static str {
+				match self {
+					$($type::$variant => $name,)*
+				}
+			}
+
+			/// Looks up an algorithm by its name, which is case-sensitive.
+			pub fn from_name(name: &str) -> Result<Self, Error> {
+				match name {
+					$($name => Ok($type::$variant),)*
+					_ => Err(Error::UnknownAlgorithm),
+				}
+			}
+		}
+
+		impl fmt::Display for $type {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str(self.name())
+			}
+		}
+
+		impl core::str::FromStr for $type {
+			type Err = Error;
+
+			fn from_str(name: &str) -> Result<Self, Error> {
+				Self::from_name(name)
+			}
+		}
+	};
+}
+
+/// A hash function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+	Sha256,
+	Sha512,
+	Sha3_256,
+	Blake3,
+}
+
+names_impl!(
+	HashAlgorithm,
+	Sha256 => "sha-256",
+	Sha512 => "sha-512",
+	Sha3_256 => "sha3-256",
+	Blake3 => "blake3",
+);
+
+impl HashAlgorithm {
+	/// Returns the length of the digest, in bytes.
+	pub fn output_len(self) -> usize {
+		match self {
+			HashAlgorithm::Sha512 => 64,
+			_ => 32,
+		}
+	}
+
+	/// Creates a hasher, which input can be added to piece by piece.
+	pub fn hasher(self) -> Box<dyn DynHasher> {
+		match self {
+			HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+			HashAlgorithm::Sha512 => Box::new(Sha512::new()),
+			HashAlgorithm::Sha3_256 => Box::new(Sha3_256::new()),
+			HashAlgorithm::Blake3 => Box::new(blake3::Hasher::new()),
+		}
+	}
+
+	/// Returns the digest of `input`.
+	pub fn hash(self, input: &[u8]) -> Vec<u8> {
+		let mut hasher = self.hasher();
+		hasher.add_bytes(input);
+		hasher.out()
+	}
+}
+
+/// A hasher for a [`HashAlgorithm`], used through a trait object.
+pub trait DynHasher: Send + Sync {
+	/// Returns the algorithm being computed.
+	fn algorithm(&self) -> HashAlgorithm;
+
+	/// Adds bytes to the input being hashed.
+	fn add_bytes(&mut self, bytes: &[u8]);
+
+	/// Finishes hashing, returning the digest.
+	fn out(self: Box<Self>) -> Vec<u8>;
+}
+
+macro_rules! dyn_hasher_impl {
+	($($type:ty => $algorithm:ident,)*) => {
+		$(
+			impl DynHasher for $type {
+				fn algorithm(&self) -> HashAlgorithm {
+					HashAlgorithm::$algorithm
+				}
+
+				fn add_bytes(&mut self, bytes: &[u8]) {
+					<$type>::add_bytes(self, bytes);
+				}
+
+				fn out(self: Box<Self>) -> Vec<u8> {
+					<$type>::out(*self).to_vec()
+				}
+			}
+		)*
+	};
+}
+
+dyn_hasher_impl!(
+	Sha256 => Sha256,
+	Sha512 => Sha512,
+	Sha3_256 => Sha3_256,
+	blake3::Hasher => Blake3,
+);
+
+/// An authenticated encryption scheme with associated data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AeadAlgorithm {
+	ChaCha20Poly1305,
+	XChaCha20Poly1305,
+}
+
+names_impl!(
+	AeadAlgorithm,
+	ChaCha20Poly1305 => "chacha20-poly1305",
+	XChaCha20Poly1305 => "xchacha20-poly1305",
+);
+
+impl AeadAlgorithm {
+	/// Returns the length of a key, in bytes.
+	pub fn key_len(self) -> usize {
+		32
+	}
+
+	/// Returns the length of a nonce, in bytes.
+	pub fn nonce_len(self) -> usize {
+		match self {
+			AeadAlgorithm::ChaCha20Poly1305 => 12,
+			AeadAlgorithm::XChaCha20Poly1305 => 24,
+		}
+	}
+
+	/// Returns the length of a tag, which is how much longer a ciphertext is
+	/// than its plaintext.
+	pub fn tag_len(self) -> usize {
+		16
+	}
+
+	/// Creates an AEAD with `key`, which must be [`key_len`](Self::key_len)
+	/// bytes long.
+	pub fn key(self, key: &[u8]) -> Result<Box<dyn DynAead>, Error> {
+		let key = key.try_into().map_err(|_| Error::InvalidLength)?;
+
+		Ok(Box::new(AeadKey {algorithm: self, key}))
+	}
+}
+
+/// An AEAD along with its key, used through a trait object. The ciphertexts
+/// are the encrypted plaintext followed by the tag.
+pub trait DynAead: Send + Sync {
+	/// Returns the algorithm the key is for.
+	fn algorithm(&self) -> AeadAlgorithm;
+
+	/// Encrypts `plaintext`, authenticating it along with `associated_data`.
+	/// The `nonce` *must not* be reused to encrypt different messages with
+	/// the same key.
+	fn encrypt(&self, nonce: &[u8], associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error>;
+
+	/// Checks and decrypts a `ciphertext` from [`encrypt`](Self::encrypt).
+	fn decrypt(&self, nonce: &[u8], associated_data: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+struct AeadKey {
+	algorithm: AeadAlgorithm,
+	key: [u8; 32],
+}
+
+impl DynAead for AeadKey {
+	fn algorithm(&self) -> AeadAlgorithm {
+		self.algorithm
+	}
+
+	fn encrypt(&self, nonce: &[u8], associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+		if nonce.len() != self.algorithm.nonce_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut out = plaintext.to_vec();
+
+		let tag = match self.algorithm {
+			AeadAlgorithm::ChaCha20Poly1305 => {
+				chacha20poly1305_encrypt(self.key, nonce.try_into().unwrap(), associated_data, &mut out)
+			},
+			AeadAlgorithm::XChaCha20Poly1305 => {
+				xchacha20poly1305_encrypt(self.key, nonce.try_into().unwrap(), associated_data, &mut out)
+			},
+		};
+
+		out.extend_from_slice(&tag);
+		Ok(out)
+	}
+
+	fn decrypt(&self, nonce: &[u8], associated_data: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+		if nonce.len() != self.algorithm.nonce_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let split = ciphertext.len().checked_sub(16).ok_or(Error::DecryptionFailed)?;
+		let tag = ciphertext[split ..].try_into().unwrap();
+		let mut out = ciphertext[.. split].to_vec();
+
+		let result = match self.algorithm {
+			AeadAlgorithm::ChaCha20Poly1305 => {
+				chacha20poly1305_decrypt(self.key, nonce.try_into().unwrap(), associated_data, &mut out, tag)
+			},
+			AeadAlgorithm::XChaCha20Poly1305 => {
+				xchacha20poly1305_decrypt(self.key, nonce.try_into().unwrap(), associated_data, &mut out, tag)
+			},
+		};
+
+		result.map_err(|_| Error::DecryptionFailed)?;
+		Ok(out)
+	}
+}
+
+impl Drop for AeadKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+/// A digital signature scheme.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SignatureAlgorithm {
+	Ed25519,
+	Ed448,
+	MlDsa65,
+}
+
+names_impl!(
+	SignatureAlgorithm,
+	Ed25519 => "ed25519",
+	Ed448 => "ed448",
+	MlDsa65 => "ml-dsa-65",
+);
+
+impl SignatureAlgorithm {
+	/// Decodes a signing key, in the encoding of the algorithm's
+	/// `SigningKey::to_bytes`.
+	pub fn signing_key(self, bytes: &[u8]) -> Result<Box<dyn DynSigningKey>, Error> {
+		Ok(match self {
+			SignatureAlgorithm::Ed25519 => Box::new(ed25519::SigningKey::from_bytes(array(bytes)?)),
+			SignatureAlgorithm::Ed448 => Box::new(ed448::SigningKey::from_bytes(array(bytes)?)),
+			SignatureAlgorithm::MlDsa65 => {
+				Box::new(ml_dsa::SigningKey::from_bytes(&array(bytes)?).map_err(|_| Error::InvalidKey)?)
+			},
+		})
+	}
+
+	/// Decodes a verifying key, in the encoding of the algorithm's
+	/// `VerifyingKey::to_bytes`.
+	pub fn verifying_key(self, bytes: &[u8]) -> Result<Box<dyn DynVerifyingKey>, Error> {
+		Ok(match self {
+			SignatureAlgorithm::Ed25519 => {
+				Box::new(ed25519::VerifyingKey::from_bytes(array(bytes)?).map_err(|_| Error::InvalidKey)?)
+			},
+			SignatureAlgorithm::Ed448 => {
+				Box::new(ed448::VerifyingKey::from_bytes(array(bytes)?).map_err(|_| Error::InvalidKey)?)
+			},
+			SignatureAlgorithm::MlDsa65 => Box::new(ml_dsa::VerifyingKey::from_bytes(&array(bytes)?)),
+		})
+	}
+}
+
+/// A signing key for a [`SignatureAlgorithm`], used through a trait object.
+pub trait DynSigningKey: Send + Sync {
+	/// Returns the algorithm the key is for.
+	fn algorithm(&self) -> SignatureAlgorithm;
+
+	/// Signs `message`, returning the encoded signature.
+	fn sign(&self, message: &[u8]) -> Vec<u8>;
+
+	/// Returns the verifying key for this signing key.
+	fn verifying_key(&self) -> Box<dyn DynVerifyingKey>;
+}
+
+/// A verifying key for a [`SignatureAlgorithm`], used through a trait
+/// object.
+pub trait DynVerifyingKey: Send + Sync {
+	/// Returns the algorithm the key is for.
+	fn algorithm(&self) -> SignatureAlgorithm;
+
+	/// Returns the encoded key.
+	fn to_bytes(&self) -> Vec<u8>;
+
+	/// Checks an encoded `signature` over `message`.
+	fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error>;
+}
+
+macro_rules! dyn_signature_impl {
+	($module:ident, $algorithm:ident, |$bytes:ident| $signature:expr) => {
+		impl DynSigningKey for $module::SigningKey {
+			fn algorithm(&self) -> SignatureAlgorithm {
+				SignatureAlgorithm::$algorithm
+			}
+
+			fn sign(&self, message: &[u8]) -> Vec<u8> {
+				$module::SigningKey::sign(self, message).to_bytes().to_vec()
+			}
+
+			fn verifying_key(&self) -> Box<dyn DynVerifyingKey> {
+				Box::new($module::SigningKey::verifying_key(self))
+			}
+		}
+
+		impl DynVerifyingKey for $module::VerifyingKey {
+			fn algorithm(&self) -> SignatureAlgorithm {
+				SignatureAlgorithm::$algorithm
+			}
+
+			fn to_bytes(&self) -> Vec<u8> {
+				$module::VerifyingKey::to_bytes(self).to_vec()
+			}
+
+			fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+				let $bytes = array(signature).map_err(|_| Error::InvalidSignature)?;
+				let signature = $signature.map_err(|_| Error::InvalidSignature)?;
+
+				match $module::VerifyingKey::verify(self, message, &signature) {
+					true => Ok(()),
+					false => Err(Error::InvalidSignature),
+				}
+			}
+		}
+	};
+}
+
+dyn_signature_impl!(ed25519, Ed25519, |bytes| ed25519::Signature::from_bytes(bytes));
+dyn_signature_impl!(ed448, Ed448, |bytes| ed448::Signature::from_bytes(bytes));
+dyn_signature_impl!(ml_dsa, MlDsa65, |bytes| Ok::<_, Error>(ml_dsa::Signature::from_bytes(bytes)));
+
+/// A key encapsulation mechanism.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KemAlgorithm {
+	X25519HkdfSha256,
+	MlKem768,
+}
+
+names_impl!(
+	KemAlgorithm,
+	X25519HkdfSha256 => "dhkem-x25519-hkdf-sha256",
+	MlKem768 => "ml-kem-768",
+);
+
+impl KemAlgorithm {
+	/// Deterministically derives a key pair from the input keying material
+	/// `ikm`, which must have at least as much entropy as the secret key. See
+	/// [`Kem::derive_key_pair`].
+	pub fn derive_key_pair(self, ikm: &[u8]) -> Box<dyn DynDecapsulationKey> {
+		match self {
+			KemAlgorithm::X25519HkdfSha256 => Box::new(KemKeyPair::<X25519HkdfSha256>::derive(ikm)),
+			KemAlgorithm::MlKem768 => Box::new(KemKeyPair::<MlKem768>::derive(ikm)),
+		}
+	}
+
+	/// Generates a shared secret and encapsulates it to the encoded
+	/// `public_key`, returning the secret and the encoded encapsulated key.
+	/// `randomness` must be freshly generated by a secure random source for
+	/// every call.
+	pub fn encapsulate(self, public_key: &[u8], randomness: [u8; 32]) -> Result<(SharedSecret, Vec<u8>), Error> {
+		match self {
+			KemAlgorithm::X25519HkdfSha256 => encapsulate::<X25519HkdfSha256>(public_key, randomness),
+			KemAlgorithm::MlKem768 => encapsulate::<MlKem768>(public_key, randomness),
+		}
+	}
+}
+
+/// A secret key for a [`KemAlgorithm`], used through a trait object.
+pub trait DynDecapsulationKey: Send + Sync {
+	/// Returns the algorithm the key is for.
+	fn algorithm(&self) -> KemAlgorithm;
+
+	/// Returns the encoded public key, which secrets are encapsulated to.
+	fn public_key(&self) -> Vec<u8>;
+
+	/// Recovers the shared secret from an encoded encapsulated key.
+	fn decapsulate(&self, encapsulated_key: &[u8]) -> Result<SharedSecret, Error>;
+}
+
+// the encodings of the public and encapsulated keys of a KEM
+trait KemEncoding: Kem {
+	const ALGORITHM: KemAlgorithm;
+
+	fn public_key_from_bytes(bytes: &[u8]) -> Result<Self::PublicKey, Error>;
+	fn public_key_to_bytes(public_key: &Self::PublicKey) -> Vec<u8>;
+	fn encapsulated_key_from_bytes(bytes: &[u8]) -> Result<Self::EncapsulatedKey, Error>;
+	fn encapsulated_key_to_bytes(encapsulated_key: &Self::EncapsulatedKey) -> Vec<u8>;
+}
+
+impl KemEncoding for X25519HkdfSha256 {
+	const ALGORITHM: KemAlgorithm = KemAlgorithm::X25519HkdfSha256;
+
+	fn public_key_from_bytes(bytes: &[u8]) -> Result<x25519::PublicKey, Error> {
+		Ok(x25519::PublicKey::from_bytes(array(bytes)?))
+	}
+
+	fn public_key_to_bytes(public_key: &x25519::PublicKey) -> Vec<u8> {
+		public_key.to_bytes().to_vec()
+	}
+
+	fn encapsulated_key_from_bytes(bytes: &[u8]) -> Result<x25519::PublicKey, Error> {
+		Self::public_key_from_bytes(bytes)
+	}
+
+	fn encapsulated_key_to_bytes(encapsulated_key: &x25519::PublicKey) -> Vec<u8> {
+		Self::public_key_to_bytes(encapsulated_key)
+	}
+}
+
+impl KemEncoding for MlKem768 {
+	const ALGORITHM: KemAlgorithm = KemAlgorithm::MlKem768;
+
+	fn public_key_from_bytes(bytes: &[u8]) -> Result<ml_kem::EncapsulationKey, Error> {
+		ml_kem::EncapsulationKey::from_bytes(&array(bytes)?).map_err(|_| Error::InvalidKey)
+	}
+
+	fn public_key_to_bytes(public_key: &ml_kem::EncapsulationKey) -> Vec<u8> {
+		public_key.as_bytes().to_vec()
+	}
+
+	fn encapsulated_key_from_bytes(bytes: &[u8]) -> Result<ml_kem::Ciphertext, Error> {
+		Ok(ml_kem::Ciphertext::from_bytes(array(bytes)?))
+	}
+
+	fn encapsulated_key_to_bytes(encapsulated_key: &ml_kem::Ciphertext) -> Vec<u8> {
+		encapsulated_key.as_bytes().to_vec()
+	}
+}
+
+struct KemKeyPair<K: Kem> {
+	secret_key: K::SecretKey,
+	public_key: K::PublicKey,
+}
+
+impl<K: KemEncoding> KemKeyPair<K> {
+	fn derive(ikm: &[u8]) -> Self {
+		let (secret_key, public_key) = K::derive_key_pair(ikm);
+		Self {secret_key, public_key}
+	}
+}
+
+impl<K: KemEncoding> DynDecapsulationKey for KemKeyPair<K>
+where
+	K::SecretKey: Send + Sync,
+	K::PublicKey: Send + Sync,
+{
+	fn algorithm(&self) -> KemAlgorithm {
+		K::ALGORITHM
+	}
+
+	fn public_key(&self) -> Vec<u8> {
+		K::public_key_to_bytes(&self.public_key)
+	}
+
+	fn decapsulate(&self, encapsulated_key: &[u8]) -> Result<SharedSecret, Error> {
+		let encapsulated_key = K::encapsulated_key_from_bytes(encapsulated_key)?;
+		K::decapsulate(&self.secret_key, &encapsulated_key).map_err(|_| Error::InvalidKey)
+	}
+}
+
+fn encapsulate<K: KemEncoding>(public_key: &[u8], randomness: [u8; 32]) -> Result<(SharedSecret, Vec<u8>), Error> {
+	let public_key = K::public_key_from_bytes(public_key)?;
+	let (shared_secret, encapsulated_key) = K::encapsulate(&public_key, randomness).map_err(|_| Error::InvalidKey)?;
+
+	Ok((shared_secret, K::encapsulated_key_to_bytes(&encapsulated_key)))
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], Error> {
+	bytes.try_into().map_err(|_| Error::InvalidLength)
+}
+
+#[test]
+fn test_names() {
+	for &algorithm in HashAlgorithm::ALL {
+		assert_eq!(algorithm.name().parse(), Ok(algorithm));
+	}
+
+	for &algorithm in AeadAlgorithm::ALL {
+		assert_eq!(AeadAlgorithm::from_name(algorithm.name()), Ok(algorithm));
+	}
+
+	for &algorithm in SignatureAlgorithm::ALL {
+		assert_eq!(SignatureAlgorithm::from_name(algorithm.name()), Ok(algorithm));
+	}
+
+	for &algorithm in KemAlgorithm::ALL {
+		assert_eq!(KemAlgorithm::from_name(algorithm.name()), Ok(algorithm));
+	}
+
+	assert_eq!(HashAlgorithm::from_name("SHA-256"), Err(Error::UnknownAlgorithm));
+	assert_eq!(std::string::ToString::to_string(&KemAlgorithm::MlKem768), "ml-kem-768");
+}
+
+#[test]
+fn test_hashes() {
+	for &algorithm in HashAlgorithm::ALL {
+		let mut hasher = algorithm.hasher();
+		assert_eq!(hasher.algorithm(), algorithm);
+
+		hasher.add_bytes(b"a");
+		hasher.add_bytes(b"bc");
+		let digest = hasher.out();
+
+		assert_eq!(digest.len(), algorithm.output_len());
+		assert_eq!(digest, algorithm.hash(b"abc"));
+	}
+
+	assert_eq!(HashAlgorithm::Sha256.hash(b"abc"), crate::sha2::sha256(b"abc"));
+	assert_eq!(HashAlgorithm::Sha3_256.hash(b"abc"), crate::sha3::sha3_256(b"abc"));
+	assert_eq!(HashAlgorithm::Blake3.hash(b"abc"), blake3::hash(b"abc"));
+}
+
+#[test]
+fn test_aeads() {
+	for &algorithm in AeadAlgorithm::ALL {
+		let aead = algorithm.key(&[7; 32]).unwrap();
+		let nonce = std::vec![9; algorithm.nonce_len()];
+
+		let ciphertext = aead.encrypt(&nonce, b"header", b"hello").unwrap();
+		assert_eq!(ciphertext.len(), 5 + algorithm.tag_len());
+		assert_eq!(aead.decrypt(&nonce, b"header", &ciphertext).unwrap(), b"hello");
+		assert_eq!(aead.decrypt(&nonce, b"footer", &ciphertext), Err(Error::DecryptionFailed));
+		assert_eq!(aead.decrypt(&nonce, b"header", &ciphertext[.. 15]), Err(Error::DecryptionFailed));
+		assert_eq!(aead.encrypt(&nonce[1 ..], b"", b"").err(), Some(Error::InvalidLength));
+	}
+
+	assert!(AeadAlgorithm::ChaCha20Poly1305.key(&[7; 16]).is_err());
+}
+
+#[test]
+fn test_signatures() {
+	let signing_keys = [
+		ed25519::SigningKey::from_bytes([1; 32]).to_bytes().to_vec(),
+		ed448::SigningKey::from_bytes([2; 57]).to_bytes().to_vec(),
+		ml_dsa::SigningKey::from_seed([3; 32]).to_bytes().to_vec(),
+	];
+
+	for (&algorithm, bytes) in SignatureAlgorithm::ALL.iter().zip(&signing_keys) {
+		let signing_key = algorithm.signing_key(bytes).unwrap();
+		let signature = signing_key.sign(b"message");
+
+		// the verifying key round-trips through its encoding
+		let verifying_key = algorithm.verifying_key(&signing_key.verifying_key().to_bytes()).unwrap();
+		assert_eq!(verifying_key.algorithm(), algorithm);
+		assert_eq!(verifying_key.verify(b"message", &signature), Ok(()));
+		assert_eq!(verifying_key.verify(b"massage", &signature), Err(Error::InvalidSignature));
+		assert_eq!(verifying_key.verify(b"message", &signature[1 ..]), Err(Error::InvalidSignature));
+
+		assert!(algorithm.signing_key(&bytes[1 ..]).is_err());
+	}
+}
+
+#[test]
+fn test_kems() {
+	for &algorithm in KemAlgorithm::ALL {
+		let secret_key = algorithm.derive_key_pair(b"input keying material");
+		assert_eq!(secret_key.algorithm(), algorithm);
+
+		let (shared_secret, encapsulated_key) = algorithm.encapsulate(&secret_key.public_key(), [5; 32]).unwrap();
+		let decapsulated = secret_key.decapsulate(&encapsulated_key).unwrap();
+		assert_eq!(decapsulated.as_bytes(), shared_secret.as_bytes());
+
+		assert!(secret_key.decapsulate(&encapsulated_key[1 ..]).is_err());
+		assert!(algorithm.encapsulate(&[0; 3], [5; 32]).is_err());
+	}
+
+	// the same as using the KEM directly
+	let (secret_key, public_key) = X25519HkdfSha256::derive_key_pair(b"ikm");
+	let (shared_secret, encapsulated_key) = KemAlgorithm::X25519HkdfSha256.encapsulate(public_key.as_bytes(), [5; 32]).unwrap();
+	let encapsulated_key = x25519::PublicKey::from_bytes(encapsulated_key.try_into().unwrap());
+	assert_eq!(X25519HkdfSha256::decapsulate(&secret_key, &encapsulated_key).unwrap().as_bytes(), shared_secret.as_bytes());
+}
@@ -0,0 +1,338 @@
+//! Implemented according to [IETF RFC 9106](https://datatracker.ietf.org/doc/html/rfc9106).
+//! Only Argon2id, the variant recommended by the RFC, is implemented. Its
+//! first half pass uses data-independent memory accesses to resist side
+//! channels, and the rest uses data-dependent accesses to resist tradeoff
+//! attacks.
+
+use core::convert::TryInto;
+
+use std::vec;
+use std::vec::Vec;
+
+use crate::blake2::{Blake2b, Blake2bVar};
+
+const VERSION: u32 = 0x13;
+const ARGON2ID: u32 = 2;
+
+const SYNC_POINTS: usize = 4;
+
+type Block = [u64; 128];
+
+// the variable-length hash function H' from section 3.3
+fn long_hash(parts: &[&[u8]], out: &mut [u8]) {
+	let out_len: u32 = out.len().try_into().unwrap();
+
+	if out.len() <= 64 {
+		let mut hasher = Blake2bVar::new(out.len());
+		hasher.add_bytes(&out_len.to_le_bytes());
+
+		for part in parts {
+			hasher.add_bytes(part);
+		}
+
+		hasher.out(out);
+		return;
+	}
+
+	let mut hasher = Blake2b::<64>::new();
+	hasher.add_bytes(&out_len.to_le_bytes());
+
+	for part in parts {
+		hasher.add_bytes(part);
+	}
+
+	let mut v = hasher.out();
+	let mut pos = 0;
+
+	// every intermediate hash contributes its first half, until the rest of
+	// the output fits in one hash, which is sized to fill it exactly
+	loop {
+		out[pos .. pos + 32].copy_from_slice(&v[.. 32]);
+		pos += 32;
+
+		if out.len() - pos <= 64 {
+			break;
+		}
+
+		let mut hasher = Blake2b::<64>::new();
+		hasher.add_bytes(&v);
+		v = hasher.out();
+	}
+
+	let mut hasher = Blake2bVar::new(out.len() - pos);
+	hasher.add_bytes(&v);
+	hasher.out(&mut out[pos ..]);
+}
+
+fn mul_lo(a: u64, b: u64) -> u64 {
+	(a & 0xffffffff).wrapping_mul(b & 0xffffffff)
+}
+
+// the BLAKE2b round function, with the additions replaced by the
+// multiply-hardened ones from section 3.6
+fn mix(v: &mut Block, a: usize, b: usize, c: usize, d: usize) {
+	v[a] = v[a].wrapping_add(v[b]).wrapping_add(mul_lo(v[a], v[b]).wrapping_mul(2));
+	v[d] = (v[d] ^ v[a]).rotate_right(32);
+	v[c] = v[c].wrapping_add(v[d]).wrapping_add(mul_lo(v[c], v[d]).wrapping_mul(2));
+	v[b] = (v[b] ^ v[c]).rotate_right(24);
+
+	v[a] = v[a].wrapping_add(v[b]).wrapping_add(mul_lo(v[a], v[b]).wrapping_mul(2));
+	v[d] = (v[d] ^ v[a]).rotate_right(16);
+	v[c] = v[c].wrapping_add(v[d]).wrapping_add(mul_lo(v[c], v[d]).wrapping_mul(2));
+	v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+// the permutation P, applied to the sixteen words at the given indices
+fn permute(v: &mut Block, i: [usize; 16]) {
+	mix(v, i[0], i[4], i[8], i[12]);
+	mix(v, i[1], i[5], i[9], i[13]);
+	mix(v, i[2], i[6], i[10], i[14]);
+	mix(v, i[3], i[7], i[11], i[15]);
+
+	mix(v, i[0], i[5], i[10], i[15]);
+	mix(v, i[1], i[6], i[11], i[12]);
+	mix(v, i[2], i[7], i[8], i[13]);
+	mix(v, i[3], i[4], i[9], i[14]);
+}
+
+// the compression function G from section 3.5, xoring the result into `out`
+fn compress(out: &mut Block, x: &Block, y: &Block) {
+	let mut r = [0; 128];
+
+	for i in 0 .. 128 {
+		r[i] = x[i] ^ y[i];
+	}
+
+	let mut z = r;
+
+	// the block is an 8x8 matrix of 16 byte registers, which is permuted
+	// row by row and then column by column
+	for row in 0 .. 8 {
+		let base = 16 * row;
+		permute(&mut z, core::array::from_fn(|i| base + i));
+	}
+
+	for column in 0 .. 8 {
+		let base = 2 * column;
+		permute(&mut z, core::array::from_fn(|i| base + 16 * (i / 2) + i % 2));
+	}
+
+	for i in 0 .. 128 {
+		out[i] ^= z[i] ^ r[i];
+	}
+}
+
+// generates the next block of pseudorandom reference indices for
+// data-independent addressing
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+	input[6] += 1;
+
+	let mut tmp = [0; 128];
+	compress(&mut tmp, &[0; 128], input);
+	*addresses = [0; 128];
+	compress(addresses, &[0; 128], &tmp);
+}
+
+fn block_from_bytes(bytes: &[u8; 1024]) -> Block {
+	core::array::from_fn(|i| u64::from_le_bytes(bytes[8 * i .. 8 * (i + 1)].try_into().unwrap()))
+}
+
+/// Computes Argon2id with `passes` passes over `memory_kib` KiB of memory,
+/// split into `lanes` lanes. The optional `secret` and `associated_data` inputs
+/// are usually empty.
+///
+/// # Panics
+/// * Panics if `passes` or `lanes` is 0, or if `lanes` is 2<sup>24</sup> or
+///   more.
+/// * Panics if `memory_kib` is less than `8 * lanes`.
+/// * Panics if `out` is shorter than 4 bytes.
+#[allow(clippy::too_many_arguments)]
+pub fn argon2id(
+	password: &[u8],
+	salt: &[u8],
+	secret: &[u8],
+	associated_data: &[u8],
+	passes: u32,
+	memory_kib: u32,
+	lanes: u32,
+	out: &mut [u8],
+) {
+	assert!(passes >= 1);
+	assert!((1 .. 1 << 24).contains(&lanes));
+	assert!(memory_kib >= 8 * lanes);
+	assert!(out.len() >= 4);
+
+	let out_len: u32 = out.len().try_into().unwrap();
+
+	let mut hasher = Blake2b::<64>::new();
+
+	for param in [lanes, out_len, memory_kib, passes, VERSION, ARGON2ID] {
+		hasher.add_bytes(&param.to_le_bytes());
+	}
+
+	for input in [password, salt, secret, associated_data] {
+		let len: u32 = input.len().try_into().unwrap();
+		hasher.add_bytes(&len.to_le_bytes());
+		hasher.add_bytes(input);
+	}
+
+	let h0 = hasher.out();
+
+	// the memory is rounded down to a multiple of the number of sync points in
+	// every lane
+	let lanes = lanes as usize;
+	let segment_len = memory_kib as usize / (SYNC_POINTS * lanes);
+	let lane_len = SYNC_POINTS * segment_len;
+	let block_count = lanes * lane_len;
+
+	let mut memory: Vec<Block> = vec![[0; 128]; block_count];
+	let mut bytes = [0; 1024];
+
+	for lane in 0 .. lanes {
+		for i in 0 .. 2 {
+			long_hash(&[&h0, &(i as u32).to_le_bytes(), &(lane as u32).to_le_bytes()], &mut bytes);
+			memory[lane * lane_len + i] = block_from_bytes(&bytes);
+		}
+	}
+
+	for pass in 0 .. passes as usize {
+		for slice in 0 .. SYNC_POINTS {
+			for lane in 0 .. lanes {
+				let data_independent = pass == 0 && slice < SYNC_POINTS / 2;
+
+				let mut input = [0; 128];
+				let mut addresses = [0; 128];
+
+				if data_independent {
+					input[.. 6].copy_from_slice(&[
+						pass as u64,
+						lane as u64,
+						slice as u64,
+						block_count as u64,
+						passes as u64,
+						ARGON2ID as u64,
+					]);
+				}
+
+				// the first two blocks of each lane were filled in from the seed
+				let start = if pass == 0 && slice == 0 {
+					if data_independent {
+						next_addresses(&mut input, &mut addresses);
+					}
+
+					2
+				} else {
+					0
+				};
+
+				for index in start .. segment_len {
+					let offset = slice * segment_len + index;
+					let current = lane * lane_len + offset;
+					let previous = lane * lane_len + (offset + lane_len - 1) % lane_len;
+
+					let random = if data_independent {
+						if index % 128 == 0 {
+							next_addresses(&mut input, &mut addresses);
+						}
+
+						addresses[index % 128]
+					} else {
+						memory[previous][0]
+					};
+
+					let ref_lane = if pass == 0 && slice == 0 {
+						lane
+					} else {
+						(random >> 32) as usize % lanes
+					};
+
+					// the blocks that may be referenced are every finished block,
+					// skipping the previous block, and the current segment of other
+					// lanes, since it's being computed at the same time
+					let same_lane = ref_lane == lane;
+
+					let area_len = match (pass, same_lane) {
+						(0, true) => offset - 1,
+						(0, false) => slice * segment_len - (index == 0) as usize,
+						(_, true) => lane_len - segment_len + index - 1,
+						(_, false) => lane_len - segment_len - (index == 0) as usize,
+					};
+
+					// this is biased towards recently computed blocks
+					let j1 = random & 0xffffffff;
+					let x = (j1 * j1) >> 32;
+					let y = (area_len as u64 * x) >> 32;
+					let relative = area_len - 1 - y as usize;
+
+					let area_start = if pass == 0 || slice == SYNC_POINTS - 1 {
+						0
+					} else {
+						(slice + 1) * segment_len
+					};
+
+					let reference = ref_lane * lane_len + (area_start + relative) % lane_len;
+
+					let prev_block = memory[previous];
+					let ref_block = memory[reference];
+
+					// from the second pass onwards, blocks are xored into their old
+					// values instead of replacing them
+					if pass == 0 {
+						memory[current] = [0; 128];
+					}
+
+					compress(&mut memory[current], &prev_block, &ref_block);
+				}
+			}
+		}
+	}
+
+	let mut last = memory[lane_len - 1];
+
+	for lane in 1 .. lanes {
+		for i in 0 .. 128 {
+			last[i] ^= memory[lane * lane_len + lane_len - 1][i];
+		}
+	}
+
+	for i in 0 .. 128 {
+		bytes[8 * i .. 8 * (i + 1)].copy_from_slice(&last[i].to_le_bytes());
+	}
+
+	long_hash(&[&bytes], out);
+
+	// the memory holds values derived from the password
+	for block in memory.iter_mut() {
+		*block = [0; 128];
+	}
+
+	bytes.fill(0);
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 5.3 of RFC 9106
+#[test]
+fn test_rfc9106_vector() {
+	let mut out = [0; 32];
+	argon2id(&[1; 32], &[2; 16], &[3; 8], &[4; 12], 3, 32, 4, &mut out);
+	assert_eq!(out, hex("0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"));
+}
+
+// checked against OpenSSL 3.5's `openssl kdf ... ARGON2ID`
+#[test]
+fn test_single_lane() {
+	let mut out = [0; 32];
+	argon2id(b"password", b"somesalt", &[], &[], 2, 256, 1, &mut out);
+	assert_eq!(out, hex("9dfeb910e80bad0311fee20f9c0e2b12c17987b4cac90c2ef54d5b3021c68bfe"));
+
+	// a long output, and an amount of memory that has to be rounded down
+	let mut out = [0; 100];
+	argon2id(b"password", b"somesalt", &[], &[], 1, 300, 3, &mut out);
+	assert_eq!(out, hex(concat!(
+		"eb7d51bef84919a546b57a7d5bcab0893835232482b1f679c43892de67f234649e3d95ccc658be99cbb67f79bc7d86ae",
+		"17aa758a0d4202ab62dbf6168510824273fa4196c56e171fb205cc559b928a00926d1a736ba5e6fceb8a6107087b9530",
+		"bf3b3b6a",
+	)));
+}
@@ -0,0 +1,948 @@
+//! Asynchronous readers and writers that encrypt, decrypt and hash data as it
+//! passes through, so that async network services can use the crate without
+//! blocking their executor. Each type implements the io traits of
+//! [tokio](https://crates.io/crates/tokio) with the `tokio` feature, and
+//! those of [futures-io](https://crates.io/crates/futures-io) with the
+//! `futures` feature, whenever the reader or writer it wraps does.
+//!
+//! * [`ChaCha20Reader`] and [`ChaCha20Writer`] apply the ChaCha20 keystream,
+//!   which hides the data but doesn't authenticate it.
+//! * [`EncryptWriter`] and [`DecryptReader`] use ChaCha20-Poly1305 in the
+//!   STREAM construction, which splits the data into chunks of
+//!   [`CHUNK_SIZE`] bytes and authenticates each one along with its position
+//!   and whether it's the last, so that chunks can't be reordered, dropped or
+//!   cut off the end without being noticed.
+//! * [`hash_tokio`] and [`hash_futures`] feed everything a reader produces to
+//!   any of the crate's hashers.
+//!
+//! The wrapped readers and writers must be `Unpin`, which anything can be
+//! made by boxing it with `Box::pin`.
+
+use core::future::poll_fn;
+use core::pin::Pin;
+#[cfg(any(feature = "tokio", feature = "chacha20", feature = "chacha20poly1305"))]
+use core::task::ready;
+use core::task::{Context, Poll};
+use std::io;
+#[cfg(any(feature = "chacha20", feature = "chacha20poly1305", all(test, feature = "tokio", feature = "futures")))]
+use std::vec::Vec;
+
+#[cfg(feature = "chacha20")]
+use crate::chacha20::ChaCha20;
+#[cfg(feature = "chacha20poly1305")]
+use crate::chacha20poly1305::{chacha20poly1305_decrypt, chacha20poly1305_encrypt};
+#[cfg(any(feature = "chacha20", feature = "chacha20poly1305"))]
+use crate::zeroize::zeroize;
+
+/// The number of bytes of plaintext in every chunk of an [`EncryptWriter`]
+/// stream but the last, which may be shorter. Each chunk is followed by a
+/// 16-byte tag.
+#[cfg(feature = "chacha20poly1305")]
+pub const CHUNK_SIZE: usize = 65536;
+
+#[cfg(feature = "chacha20poly1305")]
+const TAG_LEN: usize = 16;
+
+#[cfg(feature = "chacha20poly1305")]
+const SEALED_CHUNK_SIZE: usize = CHUNK_SIZE + TAG_LEN;
+
+// how much a ChaCha20Writer encrypts at a time before writing it out
+#[cfg(feature = "chacha20")]
+const WRITE_BUFFER_SIZE: usize = 8192;
+
+// what the wrappers need from what they wrap, so that each wrapper is written
+// once for both runtimes
+trait Source {
+	fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+}
+
+#[cfg_attr(not(any(feature = "chacha20", feature = "chacha20poly1305")), allow(dead_code))]
+trait Sink {
+	fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>>;
+	fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>>;
+	fn poll_close(&mut self, cx: &mut Context) -> Poll<io::Result<()>>;
+}
+
+#[cfg(feature = "tokio")]
+struct Tokio<'a, T>(&'a mut T);
+
+#[cfg(feature = "tokio")]
+impl<T: ::tokio::io::AsyncRead + Unpin> Source for Tokio<'_, T> {
+	fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		let mut buf = ::tokio::io::ReadBuf::new(buf);
+		ready!(Pin::new(&mut *self.0).poll_read(cx, &mut buf))?;
+		Poll::Ready(Ok(buf.filled().len()))
+	}
+}
+
+#[cfg(feature = "tokio")]
+impl<T: ::tokio::io::AsyncWrite + Unpin> Sink for Tokio<'_, T> {
+	fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Pin::new(&mut *self.0).poll_write(cx, buf)
+	}
+
+	fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut *self.0).poll_flush(cx)
+	}
+
+	fn poll_close(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut *self.0).poll_shutdown(cx)
+	}
+}
+
+#[cfg(feature = "futures")]
+struct Futures<'a, T>(&'a mut T);
+
+#[cfg(feature = "futures")]
+impl<T: futures_io::AsyncRead + Unpin> Source for Futures<'_, T> {
+	fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		Pin::new(&mut *self.0).poll_read(cx, buf)
+	}
+}
+
+#[cfg(feature = "futures")]
+impl<T: futures_io::AsyncWrite + Unpin> Sink for Futures<'_, T> {
+	fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Pin::new(&mut *self.0).poll_write(cx, buf)
+	}
+
+	fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut *self.0).poll_flush(cx)
+	}
+
+	fn poll_close(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut *self.0).poll_close(cx)
+	}
+}
+
+// implements the read traits of each runtime for a wrapper with `inner` and
+// `state` fields, where the state has a poll_read taking a Source
+#[cfg(any(feature = "chacha20", feature = "chacha20poly1305"))]
+macro_rules! async_read_impl {
+	($type:ident) => {
+		#[cfg(feature = "tokio")]
+		impl<R: ::tokio::io::AsyncRead + Unpin> ::tokio::io::AsyncRead for $type<R> {
+			fn poll_read(
+				self: Pin<&mut Self>,
+				cx: &mut Context,
+				buf: &mut ::tokio::io::ReadBuf,
+			) -> Poll<io::Result<()>> {
+				let this = self.get_mut();
+				let len = ready!(this.state.poll_read(&mut Tokio(&mut this.inner), cx, buf.initialize_unfilled()))?;
+				buf.advance(len);
+				Poll::Ready(Ok(()))
+			}
+		}
+
+		#[cfg(feature = "futures")]
+		impl<R: futures_io::AsyncRead + Unpin> futures_io::AsyncRead for $type<R> {
+			fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+				let this = self.get_mut();
+				this.state.poll_read(&mut Futures(&mut this.inner), cx, buf)
+			}
+		}
+	};
+}
+
+// implements the write traits of each runtime for a wrapper with `inner` and
+// `state` fields, where the state has poll_write, poll_flush and poll_close
+// taking a Sink
+#[cfg(any(feature = "chacha20", feature = "chacha20poly1305"))]
+macro_rules! async_write_impl {
+	($type:ident) => {
+		#[cfg(feature = "tokio")]
+		impl<W: ::tokio::io::AsyncWrite + Unpin> ::tokio::io::AsyncWrite for $type<W> {
+			fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+				let this = self.get_mut();
+				this.state.poll_write(&mut Tokio(&mut this.inner), cx, buf)
+			}
+
+			fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+				let this = self.get_mut();
+				this.state.poll_flush(&mut Tokio(&mut this.inner), cx)
+			}
+
+			fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+				let this = self.get_mut();
+				this.state.poll_close(&mut Tokio(&mut this.inner), cx)
+			}
+		}
+
+		#[cfg(feature = "futures")]
+		impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for $type<W> {
+			fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+				let this = self.get_mut();
+				this.state.poll_write(&mut Futures(&mut this.inner), cx, buf)
+			}
+
+			fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+				let this = self.get_mut();
+				this.state.poll_flush(&mut Futures(&mut this.inner), cx)
+			}
+
+			fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+				let this = self.get_mut();
+				this.state.poll_close(&mut Futures(&mut this.inner), cx)
+			}
+		}
+	};
+}
+
+// writes out `buffer[*written ..]`, then empties the buffer
+#[cfg(any(feature = "chacha20", feature = "chacha20poly1305"))]
+fn poll_drain<I: Sink>(
+	inner: &mut I,
+	cx: &mut Context,
+	buffer: &mut Vec<u8>,
+	written: &mut usize,
+) -> Poll<io::Result<()>> {
+	while *written < buffer.len() {
+		match ready!(inner.poll_write(cx, &buffer[*written ..]))? {
+			0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+			len => *written += len,
+		}
+	}
+
+	buffer.clear();
+	*written = 0;
+
+	Poll::Ready(Ok(()))
+}
+
+/// A reader that decrypts or encrypts what it reads from another reader with
+/// the ChaCha20 keystream. This doesn't authenticate the data, which
+/// [`DecryptReader`] does.
+#[cfg(feature = "chacha20")]
+pub struct ChaCha20Reader<R> {
+	inner: R,
+	state: CryptReadState,
+}
+
+#[cfg(feature = "chacha20")]
+struct CryptReadState(ChaCha20);
+
+#[cfg(feature = "chacha20")]
+impl<R> ChaCha20Reader<R> {
+	/// Wraps `inner`, starting at the beginning of the keystream for `key`
+	/// and `nonce`. The `nonce` *must not* be reused with the same key.
+	pub fn new(inner: R, key: [u8; 32], nonce: [u8; 12]) -> Self {
+		Self {inner, state: CryptReadState(ChaCha20::new(key, nonce))}
+	}
+
+	/// Returns the wrapped reader.
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+#[cfg(feature = "chacha20")]
+impl CryptReadState {
+	fn poll_read<I: Source>(&mut self, inner: &mut I, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		let len = ready!(inner.poll_read(cx, buf))?;
+
+		if self.0.try_crypt(&mut buf[.. len]).is_err() {
+			zeroize(&mut buf[.. len]);
+			return Poll::Ready(Err(io::Error::other("chacha20 keystream exhausted")));
+		}
+
+		Poll::Ready(Ok(len))
+	}
+}
+
+#[cfg(feature = "chacha20")]
+async_read_impl!(ChaCha20Reader);
+
+/// A writer that encrypts or decrypts what's written to it with the ChaCha20
+/// keystream before writing it to another writer. This doesn't authenticate
+/// the data, which [`EncryptWriter`] does.
+///
+/// Data is accepted before it's written out, so it must be flushed or shut
+/// down to be sure that all of it has reached the wrapped writer.
+#[cfg(feature = "chacha20")]
+pub struct ChaCha20Writer<W> {
+	inner: W,
+	state: CryptWriteState,
+}
+
+#[cfg(feature = "chacha20")]
+struct CryptWriteState {
+	cipher: ChaCha20,
+	// encrypted data that hasn't all been written out yet
+	pending: Vec<u8>,
+	written: usize,
+}
+
+#[cfg(feature = "chacha20")]
+impl<W> ChaCha20Writer<W> {
+	/// Wraps `inner`, starting at the beginning of the keystream for `key`
+	/// and `nonce`. The `nonce` *must not* be reused with the same key.
+	pub fn new(inner: W, key: [u8; 32], nonce: [u8; 12]) -> Self {
+		let state = CryptWriteState {
+			cipher: ChaCha20::new(key, nonce),
+			pending: Vec::with_capacity(WRITE_BUFFER_SIZE),
+			written: 0,
+		};
+
+		Self {inner, state}
+	}
+
+	/// Returns the wrapped writer. Anything that hasn't been flushed yet is
+	/// lost.
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+#[cfg(feature = "chacha20")]
+impl CryptWriteState {
+	fn poll_write<I: Sink>(&mut self, inner: &mut I, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		ready!(poll_drain(inner, cx, &mut self.pending, &mut self.written))?;
+
+		let len = buf.len().min(WRITE_BUFFER_SIZE);
+		self.pending.extend_from_slice(&buf[.. len]);
+
+		if self.cipher.try_crypt(&mut self.pending).is_err() {
+			zeroize(&mut self.pending);
+			self.pending.clear();
+			return Poll::Ready(Err(io::Error::other("chacha20 keystream exhausted")));
+		}
+
+		Poll::Ready(Ok(len))
+	}
+
+	fn poll_flush<I: Sink>(&mut self, inner: &mut I, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(poll_drain(inner, cx, &mut self.pending, &mut self.written))?;
+		inner.poll_flush(cx)
+	}
+
+	fn poll_close<I: Sink>(&mut self, inner: &mut I, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(poll_drain(inner, cx, &mut self.pending, &mut self.written))?;
+		inner.poll_close(cx)
+	}
+}
+
+#[cfg(feature = "chacha20")]
+async_write_impl!(ChaCha20Writer);
+
+// the nonce of a STREAM chunk: the 7-byte prefix, a 32-bit big-endian counter,
+// and a byte that's 1 for the last chunk and 0 otherwise
+#[cfg(feature = "chacha20poly1305")]
+fn chunk_nonce(prefix: &[u8; 7], counter: u64, last: bool) -> io::Result<[u8; 12]> {
+	let counter = u32::try_from(counter).map_err(|_| io::Error::other("too many chunks in stream"))?;
+
+	let mut nonce = [0; 12];
+	nonce[.. 7].copy_from_slice(prefix);
+	nonce[7 .. 11].copy_from_slice(&counter.to_be_bytes());
+	nonce[11] = last as u8;
+
+	Ok(nonce)
+}
+
+/// A writer that encrypts what's written to it with ChaCha20-Poly1305 in the
+/// STREAM construction, writing the chunks to another writer. The stream can
+/// be up to 2<sup>32</sup> chunks, or 256 TiB, long, and is read back with
+/// [`DecryptReader`].
+///
+/// A chunk is only written out once it's full and more data follows it, or
+/// once the writer is shut down, which writes the last chunk. A stream that
+/// isn't shut down can't be decrypted, so it must be shut down even when
+/// nothing was written to it.
+#[cfg(feature = "chacha20poly1305")]
+pub struct EncryptWriter<W> {
+	inner: W,
+	state: EncryptState,
+}
+
+#[cfg(feature = "chacha20poly1305")]
+struct EncryptState {
+	key: [u8; 32],
+	nonce_prefix: [u8; 7],
+	counter: u64,
+	// the plaintext of the current chunk, until it's sealed and holds the
+	// ciphertext and tag being written out
+	buffer: Vec<u8>,
+	sealed: bool,
+	written: usize,
+	finished: bool,
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl<W> EncryptWriter<W> {
+	/// Wraps `inner`, encrypting with `key` and nonces starting with
+	/// `nonce_prefix`. The same prefix *must not* be used for two streams
+	/// with the same key, so a random prefix should only be used with keys
+	/// that encrypt few streams, and a fresh key is best for each one.
+	pub fn new(inner: W, key: [u8; 32], nonce_prefix: [u8; 7]) -> Self {
+		let state = EncryptState {
+			key,
+			nonce_prefix,
+			counter: 0,
+			buffer: Vec::with_capacity(SEALED_CHUNK_SIZE),
+			sealed: false,
+			written: 0,
+			finished: false,
+		};
+
+		Self {inner, state}
+	}
+
+	/// Returns the wrapped writer. Unless the stream was shut down first,
+	/// whatever hasn't been written out yet is lost.
+	pub fn into_inner(self) -> W {
+		// the state zeroizes itself when dropped
+		let Self {inner, state: _} = self;
+		inner
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl EncryptState {
+	fn seal(&mut self, last: bool) -> io::Result<()> {
+		let nonce = chunk_nonce(&self.nonce_prefix, self.counter, last)?;
+		let tag = chacha20poly1305_encrypt(self.key, nonce, &[], &mut self.buffer);
+		self.buffer.extend_from_slice(&tag);
+
+		self.counter += 1;
+		self.sealed = true;
+
+		Ok(())
+	}
+
+	fn poll_drain<I: Sink>(&mut self, inner: &mut I, cx: &mut Context) -> Poll<io::Result<()>> {
+		if self.sealed {
+			ready!(poll_drain(inner, cx, &mut self.buffer, &mut self.written))?;
+			self.sealed = false;
+		}
+
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_write<I: Sink>(&mut self, inner: &mut I, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		ready!(self.poll_drain(inner, cx))?;
+
+		if self.finished {
+			return Poll::Ready(Err(io::Error::other("stream already shut down")));
+		}
+
+		if buf.len() == 0 {
+			return Poll::Ready(Ok(0));
+		}
+
+		// a full chunk followed by more data isn't the last one
+		if self.buffer.len() == CHUNK_SIZE {
+			self.seal(false)?;
+			ready!(self.poll_drain(inner, cx))?;
+		}
+
+		let len = buf.len().min(CHUNK_SIZE - self.buffer.len());
+		self.buffer.extend_from_slice(&buf[.. len]);
+
+		Poll::Ready(Ok(len))
+	}
+
+	fn poll_flush<I: Sink>(&mut self, inner: &mut I, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(self.poll_drain(inner, cx))?;
+		inner.poll_flush(cx)
+	}
+
+	fn poll_close<I: Sink>(&mut self, inner: &mut I, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(self.poll_drain(inner, cx))?;
+
+		if !self.finished {
+			self.seal(true)?;
+			self.finished = true;
+			ready!(self.poll_drain(inner, cx))?;
+		}
+
+		inner.poll_close(cx)
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl Drop for EncryptState {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+		zeroize(&mut self.buffer);
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+async_write_impl!(EncryptWriter);
+
+/// A reader that decrypts a stream written by [`EncryptWriter`] from another
+/// reader. Only data that has been authenticated is returned, one chunk at a
+/// time, and a read returns an error of kind
+/// [`InvalidData`](io::ErrorKind::InvalidData) if a chunk has been tampered
+/// with, or if the stream has been cut short. Data returned before such an
+/// error is authentic, but the stream as a whole must not be trusted until
+/// the reader returns the end of the stream.
+#[cfg(feature = "chacha20poly1305")]
+pub struct DecryptReader<R> {
+	inner: R,
+	state: DecryptState,
+}
+
+#[cfg(feature = "chacha20poly1305")]
+struct DecryptState {
+	key: [u8; 32],
+	nonce_prefix: [u8; 7],
+	counter: u64,
+	// a sealed chunk and the first byte after it, which shows that the chunk
+	// isn't the last one, until the chunk is opened and holds its plaintext
+	buffer: Vec<u8>,
+	filled: usize,
+	plaintext: core::ops::Range<usize>,
+	// whether the byte after the last opened chunk is still at the end of
+	// the buffer
+	lookahead: bool,
+	eof: bool,
+	finished: bool,
+	failed: bool,
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl<R> DecryptReader<R> {
+	/// Wraps `inner`, decrypting with the `key` and `nonce_prefix` the
+	/// stream was encrypted with.
+	pub fn new(inner: R, key: [u8; 32], nonce_prefix: [u8; 7]) -> Self {
+		let state = DecryptState {
+			key,
+			nonce_prefix,
+			counter: 0,
+			buffer: std::vec![0; SEALED_CHUNK_SIZE + 1],
+			filled: 0,
+			plaintext: 0 .. 0,
+			lookahead: false,
+			eof: false,
+			finished: false,
+			failed: false,
+		};
+
+		Self {inner, state}
+	}
+
+	/// Returns the wrapped reader.
+	pub fn into_inner(self) -> R {
+		// the state zeroizes itself when dropped
+		let Self {inner, state: _} = self;
+		inner
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl DecryptState {
+	fn open(&mut self) -> io::Result<()> {
+		let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid or truncated stream");
+
+		// the last chunk is whatever comes before the end of the stream, and
+		// may only be empty if it's the only one
+		let last = self.eof;
+		let len = match last {
+			true => self.filled,
+			false => SEALED_CHUNK_SIZE,
+		};
+
+		if len < TAG_LEN || (last && len == TAG_LEN && self.counter != 0) {
+			return Err(invalid());
+		}
+
+		let nonce = chunk_nonce(&self.nonce_prefix, self.counter, last)?;
+		let (ciphertext, tag) = self.buffer[.. len].split_at_mut(len - TAG_LEN);
+		let tag = (&*tag).try_into().unwrap();
+
+		chacha20poly1305_decrypt(self.key, nonce, &[], ciphertext, tag).map_err(|_| invalid())?;
+
+		self.counter += 1;
+		self.plaintext = 0 .. len - TAG_LEN;
+		self.lookahead = !last;
+		self.finished = last;
+
+		Ok(())
+	}
+
+	fn poll_read<I: Source>(&mut self, inner: &mut I, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		if self.failed {
+			return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, "invalid or truncated stream")));
+		}
+
+		if self.plaintext.is_empty() && !self.finished {
+			if self.lookahead {
+				self.buffer[0] = self.buffer[SEALED_CHUNK_SIZE];
+				self.filled = 1;
+				self.lookahead = false;
+			}
+
+			while self.filled < self.buffer.len() && !self.eof {
+				match ready!(inner.poll_read(cx, &mut self.buffer[self.filled ..]))? {
+					0 => self.eof = true,
+					len => self.filled += len,
+				}
+			}
+
+			if let Err(err) = self.open() {
+				self.failed = true;
+				return Poll::Ready(Err(err));
+			}
+
+			self.filled = 0;
+		}
+
+		let len = buf.len().min(self.plaintext.len());
+		let start = self.plaintext.start;
+		buf[.. len].copy_from_slice(&self.buffer[start .. start + len]);
+		zeroize(&mut self.buffer[start .. start + len]);
+		self.plaintext.start += len;
+
+		Poll::Ready(Ok(len))
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+impl Drop for DecryptState {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+		zeroize(&mut self.buffer);
+	}
+}
+
+#[cfg(feature = "chacha20poly1305")]
+async_read_impl!(DecryptReader);
+
+/// A hash function, MAC or extendable-output function whose input can be
+/// added piece by piece, so that it can be fed from a reader by
+/// [`hash_tokio`] or [`hash_futures`].
+pub trait IncrementalHash {
+	/// Adds `bytes` to the input.
+	fn add_bytes(&mut self, bytes: &[u8]);
+}
+
+macro_rules! incremental_hash_impl {
+	($feature:literal, $($type:ty),* $(,)?) => {
+		$(
+			#[cfg(feature = $feature)]
+			impl IncrementalHash for $type {
+				fn add_bytes(&mut self, bytes: &[u8]) {
+					<$type>::add_bytes(self, bytes);
+				}
+			}
+		)*
+	};
+}
+
+incremental_hash_impl!("blake2", crate::blake2::Blake2bVar, crate::blake2::Blake2sVar);
+incremental_hash_impl!("blake3", crate::blake3::Hasher);
+incremental_hash_impl!("hmac", crate::hmac::HmacSha1, crate::hmac::HmacSha256, crate::hmac::HmacSha512);
+incremental_hash_impl!("sha2", crate::sha2::Sha224, crate::sha2::Sha256, crate::sha2::Sha384, crate::sha2::Sha512);
+incremental_hash_impl!("sha3", crate::sha3::Shake128, crate::sha3::Shake256);
+incremental_hash_impl!("sm3", crate::sm3::Sm3);
+
+#[cfg(feature = "blake2")]
+impl<const N: usize> IncrementalHash for crate::blake2::Blake2b<N> {
+	fn add_bytes(&mut self, bytes: &[u8]) {
+		crate::blake2::Blake2b::add_bytes(self, bytes);
+	}
+}
+
+#[cfg(feature = "blake2")]
+impl<const N: usize> IncrementalHash for crate::blake2::Blake2s<N> {
+	fn add_bytes(&mut self, bytes: &[u8]) {
+		crate::blake2::Blake2s::add_bytes(self, bytes);
+	}
+}
+
+async fn hash_source<I: Source, H: IncrementalHash>(mut source: I, hasher: &mut H) -> io::Result<u64> {
+	let mut buf = [0; 8192];
+	let mut total = 0;
+
+	loop {
+		match poll_fn(|cx| source.poll_read(cx, &mut buf)).await? {
+			0 => return Ok(total),
+			len => {
+				hasher.add_bytes(&buf[.. len]);
+				total += len as u64;
+			}
+		}
+	}
+}
+
+/// Reads everything from a tokio `reader` into `hasher`, returning how many
+/// bytes were read. The hasher is left as it is to be finished.
+#[cfg(feature = "tokio")]
+pub async fn hash_tokio<R, H>(reader: &mut R, hasher: &mut H) -> io::Result<u64>
+where
+	R: ::tokio::io::AsyncRead + Unpin,
+	H: IncrementalHash,
+{
+	hash_source(Tokio(reader), hasher).await
+}
+
+/// Reads everything from a futures-io `reader` into `hasher`, returning how
+/// many bytes were read. The hasher is left as it is to be finished.
+#[cfg(feature = "futures")]
+pub async fn hash_futures<R, H>(reader: &mut R, hasher: &mut H) -> io::Result<u64>
+where
+	R: futures_io::AsyncRead + Unpin,
+	H: IncrementalHash,
+{
+	hash_source(Futures(reader), hasher).await
+}
+
+// drives a future to completion, polling it again whenever it's pending, since
+// the test readers and writers below never need waking
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+fn block_on<F: core::future::Future>(future: F) -> F::Output {
+	let mut future = core::pin::pin!(future);
+	let mut cx = Context::from_waker(core::task::Waker::noop());
+
+	loop {
+		if let Poll::Ready(out) = future.as_mut().poll(&mut cx) {
+			return out;
+		}
+	}
+}
+
+// a reader and writer over a buffer, which only moves a few bytes at a time
+// and is pending every other call, to exercise every path through the
+// wrappers
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+struct Trickle {
+	data: Vec<u8>,
+	pos: usize,
+	calls: usize,
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl Trickle {
+	fn new(data: Vec<u8>) -> Self {
+		Self {data, pos: 0, calls: 0}
+	}
+
+	fn step(&mut self) -> Option<usize> {
+		self.calls += 1;
+
+		match self.calls % 2 {
+			0 => None,
+			_ => Some(1 + self.calls % 4099),
+		}
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl Source for Trickle {
+	fn poll_read(&mut self, _cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		let Some(step) = self.step() else {
+			return Poll::Pending;
+		};
+
+		let len = step.min(buf.len()).min(self.data.len() - self.pos);
+		buf[.. len].copy_from_slice(&self.data[self.pos ..][.. len]);
+		self.pos += len;
+
+		Poll::Ready(Ok(len))
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl Sink for Trickle {
+	fn poll_write(&mut self, _cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		let Some(step) = self.step() else {
+			return Poll::Pending;
+		};
+
+		let len = step.min(buf.len());
+		self.data.extend_from_slice(&buf[.. len]);
+
+		Poll::Ready(Ok(len))
+	}
+
+	fn poll_flush(&mut self, _cx: &mut Context) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_close(&mut self, _cx: &mut Context) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl ::tokio::io::AsyncRead for Trickle {
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ::tokio::io::ReadBuf) -> Poll<io::Result<()>> {
+		let len = ready!(Source::poll_read(self.get_mut(), cx, buf.initialize_unfilled()))?;
+		buf.advance(len);
+		Poll::Ready(Ok(()))
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl ::tokio::io::AsyncWrite for Trickle {
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Sink::poll_write(self.get_mut(), cx, buf)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Sink::poll_flush(self.get_mut(), cx)
+	}
+
+	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Sink::poll_close(self.get_mut(), cx)
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl futures_io::AsyncRead for Trickle {
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		Source::poll_read(self.get_mut(), cx, buf)
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+impl futures_io::AsyncWrite for Trickle {
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Sink::poll_write(self.get_mut(), cx, buf)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Sink::poll_flush(self.get_mut(), cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Sink::poll_close(self.get_mut(), cx)
+	}
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures"))]
+fn test_data(len: usize) -> Vec<u8> {
+	(0 .. len).map(|i| (i * 7 + i / 251) as u8).collect()
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures", feature = "chacha20poly1305"))]
+fn encrypt_stream(plaintext: &[u8]) -> Vec<u8> {
+	use ::tokio::io::AsyncWrite;
+
+	let mut writer = EncryptWriter::new(Trickle::new(Vec::new()), [0x42; 32], [7; 7]);
+	let mut pos = 0;
+
+	while pos < plaintext.len() {
+		pos += block_on(poll_fn(|cx| Pin::new(&mut writer).poll_write(cx, &plaintext[pos ..]))).unwrap();
+	}
+
+	block_on(poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx))).unwrap();
+	writer.into_inner().data
+}
+
+#[cfg(all(test, feature = "tokio", feature = "futures", feature = "chacha20poly1305"))]
+fn decrypt_stream(ciphertext: Vec<u8>) -> io::Result<Vec<u8>> {
+	use futures_io::AsyncRead;
+
+	let mut reader = DecryptReader::new(Trickle::new(ciphertext), [0x42; 32], [7; 7]);
+	let mut out = Vec::new();
+	let mut buf = [0; 10000];
+
+	loop {
+		match block_on(poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf)))? {
+			0 => return Ok(out),
+			len => out.extend_from_slice(&buf[.. len]),
+		}
+	}
+}
+
+#[cfg(all(feature = "tokio", feature = "futures", feature = "chacha20poly1305"))]
+#[test]
+fn test_stream_round_trip() {
+	for len in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 3 * CHUNK_SIZE + 12345] {
+		let plaintext = test_data(len);
+		let ciphertext = encrypt_stream(&plaintext);
+
+		let num_chunks = usize::max(1, len.div_ceil(CHUNK_SIZE));
+		assert_eq!(ciphertext.len(), len + num_chunks * TAG_LEN);
+		assert_eq!(decrypt_stream(ciphertext).unwrap(), plaintext);
+	}
+}
+
+#[cfg(all(feature = "tokio", feature = "futures", feature = "chacha20poly1305"))]
+#[test]
+fn test_stream_rejects_tampering() {
+	let plaintext = test_data(2 * CHUNK_SIZE + 100);
+	let ciphertext = encrypt_stream(&plaintext);
+	let invalid = |ciphertext| decrypt_stream(ciphertext).unwrap_err().kind() == io::ErrorKind::InvalidData;
+
+	// a flipped bit
+	let mut flipped = ciphertext.clone();
+	flipped[CHUNK_SIZE + 50] ^= 1;
+	assert!(invalid(flipped));
+
+	// cut off after a chunk, or partway through one
+	assert!(invalid(ciphertext[.. 2 * SEALED_CHUNK_SIZE].to_vec()));
+	assert!(invalid(ciphertext[.. ciphertext.len() - 1].to_vec()));
+	assert!(invalid(ciphertext[.. 10].to_vec()));
+	assert!(invalid(Vec::new()));
+
+	// chunks swapped
+	let mut swapped = ciphertext[SEALED_CHUNK_SIZE .. 2 * SEALED_CHUNK_SIZE].to_vec();
+	swapped.extend_from_slice(&ciphertext[.. SEALED_CHUNK_SIZE]);
+	swapped.extend_from_slice(&ciphertext[2 * SEALED_CHUNK_SIZE ..]);
+	assert!(invalid(swapped));
+
+	// an extra empty last chunk, which an encrypter never writes
+	let mut extended = ciphertext[.. 2 * SEALED_CHUNK_SIZE].to_vec();
+	let mut empty = Vec::new();
+	let tag = chacha20poly1305_encrypt([0x42; 32], chunk_nonce(&[7; 7], 2, true).unwrap(), &[], &mut empty);
+	extended.extend_from_slice(&tag);
+	assert!(invalid(extended));
+}
+
+#[cfg(all(feature = "tokio", feature = "futures", feature = "chacha20"))]
+#[test]
+fn test_chacha20_reader_and_writer() {
+	use crate::chacha20::ChaCha20;
+
+	let plaintext = test_data(20000);
+	let mut expected = plaintext.clone();
+	ChaCha20::new([1; 32], [2; 12]).crypt(&mut expected);
+
+	let mut writer = ChaCha20Writer::new(Trickle::new(Vec::new()), [1; 32], [2; 12]);
+	let mut pos = 0;
+
+	while pos < plaintext.len() {
+		let write = poll_fn(|cx| futures_io::AsyncWrite::poll_write(Pin::new(&mut writer), cx, &plaintext[pos ..]));
+		pos += block_on(write).unwrap();
+	}
+
+	block_on(poll_fn(|cx| futures_io::AsyncWrite::poll_flush(Pin::new(&mut writer), cx))).unwrap();
+	let ciphertext = writer.into_inner().data;
+	assert_eq!(ciphertext, expected);
+
+	let mut reader = ChaCha20Reader::new(Trickle::new(ciphertext), [1; 32], [2; 12]);
+	let mut decrypted = Vec::new();
+	let mut buf = [0; 1000];
+
+	loop {
+		let mut read_buf = ::tokio::io::ReadBuf::new(&mut buf);
+		let read = poll_fn(|cx| ::tokio::io::AsyncRead::poll_read(Pin::new(&mut reader), cx, &mut read_buf));
+		block_on(read).unwrap();
+
+		match read_buf.filled() {
+			[] => break,
+			filled => decrypted.extend_from_slice(filled),
+		}
+	}
+
+	assert_eq!(decrypted, plaintext);
+}
+
+#[cfg(all(feature = "tokio", feature = "futures", feature = "sha2"))]
+#[test]
+fn test_hash_readers() {
+	use crate::sha2::{sha256, Sha256};
+
+	let data = test_data(100000);
+
+	let mut hasher = Sha256::new();
+	let len = block_on(hash_tokio(&mut Trickle::new(data.clone()), &mut hasher)).unwrap();
+	assert_eq!(len, 100000);
+	assert_eq!(hasher.out(), sha256(&data));
+
+	let mut hasher = Sha256::new();
+	block_on(hash_futures(&mut Trickle::new(data.clone()), &mut hasher)).unwrap();
+	assert_eq!(hasher.out(), sha256(&data));
+}
@@ -0,0 +1,372 @@
+//! Module for constant-time integers modulo an arbitrary odd modulus, such as
+//! the order of a curve group. Unlike [`segmented_int`](crate::segmented_int),
+//! this doesn't rely on the modulus being slightly below a power of two, and
+//! instead reduces using Barrett's method, with a precomputed reciprocal of
+//! the modulus.
+//!
+//! Numbers are stored fully reduced, as little-endian arrays of 64-bit limbs.
+
+use core::marker::PhantomData;
+use core::ops::{Add, Mul, Neg, Sub};
+
+use crate::choice::Choice;
+
+/// The most limbs a modulus can have, which bounds the scratch space needed.
+const MAX_LIMBS: usize = 8;
+
+/// Describes a modulus of `N` limbs, along with the reciprocal that Barrett
+/// reduction needs.
+pub trait BarrettDescriptor<const N: usize> {
+	/// The modulus, whose top limb must be nonzero.
+	const MODULUS: [u64; N];
+	/// floor(2 ** (128 * N) / MODULUS), which is N + 1 limbs long.
+	const MU: &'static [u64];
+}
+
+/// Represents an integer modulo the modulus of `T`, in `N` limbs.
+pub struct BarrettInt<T: BarrettDescriptor<N>, const N: usize> {
+	pub limbs: [u64; N],
+	descriptor: PhantomData<T>,
+}
+
+/// Multiplies two numbers, writing the full product into `out`, which must be
+/// exactly as long as both inputs together.
+fn mul_wide(num_a: &[u64], num_b: &[u64], out: &mut [u64]) {
+	debug_assert_eq!(out.len(), num_a.len() + num_b.len());
+
+	out.fill(0);
+
+	for i in 0 .. num_a.len() {
+		let mut carry = 0u128;
+
+		for j in 0 .. num_b.len() {
+			// at most (2 ** 64 - 1) ** 2 + 2 * (2 ** 64 - 1) = 2 ** 128 - 1
+			let res = num_a[i] as u128 * num_b[j] as u128 + out[i + j] as u128 + carry;
+			out[i + j] = res as u64;
+			carry = res >> 64;
+		}
+
+		out[i + num_b.len()] = carry as u64;
+	}
+}
+
+/// Subtracts `num_b` from `num` in place, modulo 2 ** (64 * `num.len()`),
+/// returning the borrow out of the top limb, which is 1 if `num_b` was
+/// bigger. `num_b` is padded with zeros if it's shorter.
+fn sub_in_place(num: &mut [u64], num_b: &[u64]) -> u64 {
+	let mut borrow = 0;
+
+	for i in 0 .. num.len() {
+		let limb_b = if i < num_b.len() {num_b[i]} else {0};
+		let (diff, borrow_a) = num[i].overflowing_sub(limb_b);
+		let (diff, borrow_b) = diff.overflowing_sub(borrow);
+		num[i] = diff;
+		borrow = (borrow_a | borrow_b) as u64;
+	}
+
+	borrow
+}
+
+/// Adds `num_b` masked by `mask` to `num` in place, discarding the carry out
+/// of the top limb. `num_b` is padded with zeros if it's shorter.
+fn add_masked_in_place(num: &mut [u64], num_b: &[u64], mask: u64) {
+	let mut carry = 0;
+
+	for i in 0 .. num.len() {
+		let limb_b = if i < num_b.len() {num_b[i]} else {0};
+		let res = num[i] as u128 + (limb_b & mask) as u128 + carry;
+		num[i] = res as u64;
+		carry = res >> 64;
+	}
+}
+
+/// Subtracts `modulus` from `num` if `num` is at least `modulus`. Works in
+/// constant time.
+fn subtract_modulus_if_not_less(num: &mut [u64], modulus: &[u64]) {
+	let mut diff = [0; MAX_LIMBS + 1];
+	let diff = &mut diff[.. num.len()];
+	diff.copy_from_slice(num);
+
+	let borrow = sub_in_place(diff, modulus);
+	// all ones if the subtraction didn't wrap, so the difference is kept
+	let mask = borrow.wrapping_sub(1);
+
+	for i in 0 .. num.len() {
+		num[i] = (diff[i] & mask) | (num[i] & !mask);
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Copy for BarrettInt<T, N> {}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Clone for BarrettInt<T, N> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> BarrettInt<T, N> {
+	pub const ZERO: Self = Self::from_reduced_limbs([0; N]);
+
+	pub const ONE: Self = {
+		let mut limbs = [0; N];
+		limbs[0] = 1;
+		Self::from_reduced_limbs(limbs)
+	};
+
+	/// Wraps limbs that are already less than the modulus.
+	pub const fn from_reduced_limbs(limbs: [u64; N]) -> Self {
+		Self {limbs, descriptor: PhantomData}
+	}
+
+	/// Reduces a number of up to `2 * N` limbs.
+	fn reduce_wide(wide: &[u64]) -> Self {
+		const {
+			assert!(N >= 1 && N <= MAX_LIMBS);
+		}
+
+		let mut padded = [0; 2 * MAX_LIMBS];
+		padded[.. wide.len()].copy_from_slice(wide);
+		let wide = &padded[.. 2 * N];
+
+		// estimate the quotient as q = floor(floor(x / 2 ** (64 (N - 1))) mu
+		// / 2 ** (64 (N + 1))), which is never more than the real quotient and
+		// falls short by at most 2
+		let mut q1_times_mu = [0; 2 * MAX_LIMBS + 2];
+		let q1_times_mu = &mut q1_times_mu[.. 2 * N + 2];
+		mul_wide(&wide[N - 1 ..], T::MU, q1_times_mu);
+		let quotient = &q1_times_mu[N + 1 ..];
+
+		let mut quotient_times_modulus = [0; 2 * MAX_LIMBS + 1];
+		let quotient_times_modulus = &mut quotient_times_modulus[.. 2 * N + 1];
+		mul_wide(quotient, &T::MODULUS, quotient_times_modulus);
+
+		// only the bottom N + 1 limbs of x - q m are needed, since it's below
+		// 3 m
+		let mut remainder = [0; MAX_LIMBS + 1];
+		let remainder = &mut remainder[.. N + 1];
+		remainder.copy_from_slice(&wide[.. N + 1]);
+		sub_in_place(remainder, &quotient_times_modulus[.. N + 1]);
+
+		subtract_modulus_if_not_less(remainder, &T::MODULUS);
+		subtract_modulus_if_not_less(remainder, &T::MODULUS);
+
+		debug_assert_eq!(remainder[N], 0);
+
+		let mut limbs = [0; N];
+		limbs.copy_from_slice(&remainder[.. N]);
+
+		Self::from_reduced_limbs(limbs)
+	}
+
+	/// Reduces a little-endian number of up to `16 * N` bytes.
+	pub fn from_bytes_mod_order(bytes: &[u8]) -> Self {
+		assert!(bytes.len() <= 16 * N);
+
+		let mut wide = [0; 2 * MAX_LIMBS];
+
+		for (i, chunk) in bytes.chunks(8).enumerate() {
+			let mut buf = [0; 8];
+			buf[.. chunk.len()].copy_from_slice(chunk);
+			wide[i] = u64::from_le_bytes(buf);
+		}
+
+		Self::reduce_wide(&wide[.. 2 * N])
+	}
+
+	/// Writes the little-endian encoding of this number into `out`, which
+	/// must be between `8 * (N - 1)` and `8 * N` bytes long, followed by
+	/// zeros if it's longer.
+	pub fn write_bytes(&self, out: &mut [u8]) {
+		out.fill(0);
+
+		for (i, chunk) in out.chunks_mut(8).take(N).enumerate() {
+			chunk.copy_from_slice(&self.limbs[i].to_le_bytes()[.. chunk.len()]);
+		}
+	}
+
+	/// Raises this number to the power of a little-endian exponent of `bits`
+	/// bits. Only constant-time if the exponent is public.
+	pub fn pow_public(&self, exponent: &[u64], bits: usize) -> Self {
+		let mut result = Self::ONE;
+
+		for i in (0 .. bits).rev() {
+			result = result * result;
+
+			if (exponent[i / 64] >> (i % 64)) & 0x01 == 1 {
+				result = result * *self;
+			}
+		}
+
+		result
+	}
+
+	/// Inverts this number using Fermat's little theorem, which requires the
+	/// modulus to be prime. Maps 0 to 0. Works in constant time.
+	pub fn invert(&self) -> Self {
+		let mut exponent = T::MODULUS;
+		sub_in_place(&mut exponent, &[2]);
+
+		self.pow_public(&exponent, 64 * N)
+	}
+
+	/// Returns whether `limbs` is less than the modulus, so it's the canonical
+	/// representative of a number. Works in constant time.
+	pub fn is_canonical(limbs: &[u64; N]) -> Choice {
+		let mut diff = *limbs;
+		let borrow = sub_in_place(&mut diff, &T::MODULUS);
+
+		Choice::from(borrow as u8)
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Add for BarrettInt<T, N> {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		// the sum of two reduced numbers is below 2 m, so it only needs one
+		// extra limb and one conditional subtraction
+		let mut sum = [0; MAX_LIMBS + 1];
+		let sum = &mut sum[.. N + 1];
+		sum[.. N].copy_from_slice(&self.limbs);
+		add_masked_in_place(sum, &other.limbs, !0);
+
+		subtract_modulus_if_not_less(sum, &T::MODULUS);
+
+		let mut limbs = [0; N];
+		limbs.copy_from_slice(&sum[.. N]);
+
+		Self::from_reduced_limbs(limbs)
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Sub for BarrettInt<T, N> {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		let mut limbs = self.limbs;
+		let borrow = sub_in_place(&mut limbs, &other.limbs);
+		// adds the modulus back if the subtraction wrapped
+		add_masked_in_place(&mut limbs, &T::MODULUS, borrow.wrapping_neg());
+
+		Self::from_reduced_limbs(limbs)
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Neg for BarrettInt<T, N> {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Self::ZERO - self
+	}
+}
+
+impl<T: BarrettDescriptor<N>, const N: usize> Mul for BarrettInt<T, N> {
+	type Output = Self;
+
+	fn mul(self, other: Self) -> Self {
+		let mut product = [0; 2 * MAX_LIMBS];
+		let product = &mut product[.. 2 * N];
+		mul_wide(&self.limbs, &other.limbs, product);
+
+		Self::reduce_wide(product)
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[cfg(test)]
+fn check_field<T: BarrettDescriptor<4>>(expected: [&str; 8]) {
+	let to_bytes = |num: BarrettInt<T, 4>| {
+		let mut out = [0; 32];
+		num.write_bytes(&mut out);
+		out
+	};
+
+	let num_a = BarrettInt::<T, 4>::from_bytes_mod_order(&hex::<32>("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"));
+	let num_b = BarrettInt::<T, 4>::from_bytes_mod_order(&hex::<32>("6465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f80818283"));
+	let minus_one = -BarrettInt::<T, 4>::ONE;
+
+	assert_eq!(to_bytes(num_a * num_b), hex(expected[0]));
+	assert_eq!(to_bytes(num_a + num_b), hex(expected[1]));
+	assert_eq!(to_bytes(num_a - num_b), hex(expected[2]));
+	assert_eq!(to_bytes(num_b - num_a), hex(expected[3]));
+	assert_eq!(to_bytes(num_a.invert()), hex(expected[4]));
+	assert_eq!(to_bytes(minus_one * minus_one), hex(expected[5]));
+	assert_eq!(to_bytes(BarrettInt::<T, 4>::from_bytes_mod_order(&[0xff; 64])), hex(expected[6]));
+	assert_eq!(to_bytes(minus_one), hex(expected[7]));
+
+	assert_eq!(to_bytes(num_a * num_a.invert()), to_bytes(BarrettInt::ONE));
+	assert_eq!(to_bytes(-BarrettInt::<T, 4>::ZERO), [0; 32]);
+	assert!(bool::from(BarrettInt::<T, 4>::is_canonical(&minus_one.limbs)));
+	assert!(!bool::from(BarrettInt::<T, 4>::is_canonical(&T::MODULUS)));
+}
+
+// checked against python's arbitrary-precision integers
+#[test]
+fn test_p256_field() {
+	struct P256Descriptor;
+
+	// p = 2 ** 256 - 2 ** 224 + 2 ** 192 + 2 ** 96 - 1
+	impl BarrettDescriptor<4> for P256Descriptor {
+		const MODULUS: [u64; 4] = [
+			0xffffffffffffffff,
+			0x00000000ffffffff,
+			0x0000000000000000,
+			0xffffffff00000001,
+		];
+
+		const MU: &'static [u64] = &[
+			0x0000000000000003,
+			0xfffffffeffffffff,
+			0xfffffffefffffffe,
+			0x00000000ffffffff,
+			0x0000000000000001,
+		];
+	}
+
+	check_field::<P256Descriptor>([
+		"0502b624513eef66a4f8751e4f66b740fd39055d3faa9b116d6c40e500e630e3",
+		"6567696b6d6f71737577797b7d7f81838587898b8d8f91939597999b9d9fa1a3",
+		"9c9c9c9c9c9c9c9c9c9c9c9c9d9c9c9c9c9c9c9c9c9c9c9c9d9c9c9c9b9c9c9c",
+		"6363636363636363636363636363636363636363636363636363636363636363",
+		"c366f71ddc048c14d4d4489ca6dcb6ce85267c5855c00fae05f9dcb57b6a878a",
+		"0100000000000000000000000000000000000000000000000000000000000000",
+		"0200000000000000fffffffffbfffffffefffffffffffffffdffffff04000000",
+		"feffffffffffffffffffffff00000000000000000000000001000000ffffffff",
+	]);
+}
+
+#[test]
+fn test_secp256k1_field() {
+	struct Secp256k1Descriptor;
+
+	// p = 2 ** 256 - 2 ** 32 - 977
+	impl BarrettDescriptor<4> for Secp256k1Descriptor {
+		const MODULUS: [u64; 4] = [
+			0xfffffffefffffc2f,
+			0xffffffffffffffff,
+			0xffffffffffffffff,
+			0xffffffffffffffff,
+		];
+
+		const MU: &'static [u64] = &[
+			0x00000001000003d1,
+			0x0000000000000000,
+			0x0000000000000000,
+			0x0000000000000000,
+			0x0000000000000001,
+		];
+	}
+
+	check_field::<Secp256k1Descriptor>([
+		"29459f958c725f0186c9e6085b083c21e3aca904e981fa7d3752f95799e8705d",
+		"6567696b6d6f71737577797b7d7f81838587898b8d8f91939597999b9d9fa1a3",
+		"cc989c9c9b9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c9c",
+		"6363636363636363636363636363636363636363636363636363636363636363",
+		"fa2ca14711c590a2cba6636f55d589fdd724cb373daade36afe7afa621def3f2",
+		"0100000000000000000000000000000000000000000000000000000000000000",
+		"a0900e00a2070000010000000000000000000000000000000000000000000000",
+		"2efcfffffeffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+	]);
+}
@@ -0,0 +1,69 @@
+//! Base64 encoding and decoding, with the standard alphabet used by the
+//! text-based key formats, the unpadded standard alphabet used by age and the
+//! unpadded URL-safe alphabet used by JOSE.
+
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+use crate::encoding::{self, Base64Variant};
+
+/// Appends the padded base64 encoding of `bytes` to `out`.
+pub fn encode(bytes: &[u8], out: &mut String) {
+	encoding::encode_base64(bytes, Base64Variant::Standard, out);
+}
+
+/// Appends the unpadded base64 encoding of `bytes` to `out`.
+pub fn encode_unpadded(bytes: &[u8], out: &mut String) {
+	encoding::encode_base64(bytes, Base64Variant::StandardUnpadded, out);
+}
+
+/// Appends the unpadded base64url encoding of `bytes` to `out`.
+pub fn encode_url(bytes: &[u8], out: &mut String) {
+	encoding::encode_base64(bytes, Base64Variant::UrlSafeUnpadded, out);
+}
+
+/// Decodes padded base64, ignoring any whitespace in between characters.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+	let text: String = text.chars().filter(|x| !x.is_ascii_whitespace()).collect();
+	decode_with(Base64Variant::Standard, &text)
+}
+
+/// Decodes unpadded base64. Whitespace isn't allowed.
+pub fn decode_unpadded(text: &str) -> Option<Vec<u8>> {
+	decode_with(Base64Variant::StandardUnpadded, text)
+}
+
+/// Decodes unpadded base64url. Whitespace isn't allowed.
+pub fn decode_url(text: &str) -> Option<Vec<u8>> {
+	decode_with(Base64Variant::UrlSafeUnpadded, text)
+}
+
+// the decoding is constant-time, since it's used for private keys, and any
+// unused bits in the final character must be zero, so that each input has
+// exactly one valid encoding
+fn decode_with(variant: Base64Variant, text: &str) -> Option<Vec<u8>> {
+	let mut out = vec![0; text.len()];
+	let len = encoding::decode_base64(text, variant, &mut out).ok()?;
+	out.truncate(len);
+
+	Some(out)
+}
+
+#[test]
+fn test_url_round_trip() {
+	for len in 0 .. 8 {
+		let bytes: Vec<u8> = (0 .. len).map(|x| 0xf8 | x).collect();
+
+		let mut encoded = String::new();
+		encode_url(&bytes, &mut encoded);
+
+		assert!(!encoded.contains(['+', '/', '=']));
+		assert_eq!(decode_url(&encoded).unwrap(), bytes);
+	}
+
+	// nonzero trailing bits
+	assert_eq!(decode_url("AB"), None);
+	assert_eq!(decode_url("AA=="), None);
+	assert_eq!(decode_url("A"), None);
+}
@@ -0,0 +1,141 @@
+//! Implemented according to [BIP 173](https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki).
+//! Bech32 encodes bytes with a human-readable prefix and a checksum that
+//! catches typos, and is used for the keys of the age file format. Unlike
+//! BIP 173, there's no limit on the length of the encoding.
+
+use std::string::String;
+use std::vec::Vec;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+	let mut checksum = 1u32;
+
+	for value in values {
+		let top = checksum >> 25;
+		checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+
+		for i in 0 .. 5 {
+			if (top >> i) & 1 == 1 {
+				checksum ^= GENERATOR[i];
+			}
+		}
+	}
+
+	checksum
+}
+
+fn expand_hrp(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+	hrp.bytes().map(|x| x >> 5).chain([0]).chain(hrp.bytes().map(|x| x & 0x1f))
+}
+
+/// Encodes `data` with the lowercase human-readable part `hrp`.
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+	let mut values = Vec::with_capacity((8 * data.len()).div_ceil(5) + 6);
+
+	let mut acc = 0u32;
+	let mut bits = 0;
+
+	for &byte in data {
+		acc = (acc << 8) | byte as u32;
+		bits += 8;
+
+		while bits >= 5 {
+			bits -= 5;
+			values.push(((acc >> bits) & 0x1f) as u8);
+		}
+	}
+
+	if bits != 0 {
+		values.push(((acc << (5 - bits)) & 0x1f) as u8);
+	}
+
+	let checksum = polymod(expand_hrp(hrp).chain(values.iter().copied()).chain([0; 6])) ^ 1;
+
+	for i in 0 .. 6 {
+		values.push(((checksum >> (5 * (5 - i))) & 0x1f) as u8);
+	}
+
+	let mut out = String::with_capacity(hrp.len() + 1 + values.len());
+	out.push_str(hrp);
+	out.push('1');
+	out.extend(values.iter().map(|&x| CHARSET[x as usize] as char));
+	out
+}
+
+/// Decodes a bech32 string, which may be all uppercase or all lowercase,
+/// returning the human-readable part in lowercase along with the data. Any
+/// padding bits at the end of the data must be zero.
+pub fn decode(text: &str) -> Option<(String, Vec<u8>)> {
+	if !text.is_ascii() {
+		return None;
+	}
+
+	let has_lower = text.bytes().any(|x| x.is_ascii_lowercase());
+	let has_upper = text.bytes().any(|x| x.is_ascii_uppercase());
+
+	if has_lower && has_upper {
+		return None;
+	}
+
+	let text = text.to_ascii_lowercase();
+	let separator = text.rfind('1')?;
+	let (hrp, rest) = (&text[.. separator], &text.as_bytes()[separator + 1 ..]);
+
+	if hrp.is_empty() || rest.len() < 6 || hrp.bytes().any(|x| !(33 ..= 126).contains(&x)) {
+		return None;
+	}
+
+	let values = rest
+		.iter()
+		.map(|&char| CHARSET.iter().position(|&x| x == char).map(|x| x as u8))
+		.collect::<Option<Vec<u8>>>()?;
+
+	if polymod(expand_hrp(hrp).chain(values.iter().copied())) != 1 {
+		return None;
+	}
+
+	let mut data = Vec::with_capacity(5 * values.len() / 8);
+
+	let mut acc = 0u32;
+	let mut bits = 0;
+
+	for &value in &values[.. values.len() - 6] {
+		acc = (acc << 5) | value as u32;
+		bits += 5;
+
+		if bits >= 8 {
+			bits -= 8;
+			data.push((acc >> bits) as u8);
+		}
+	}
+
+	if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+		return None;
+	}
+
+	Some((String::from(hrp), data))
+}
+
+// from BIP 173
+#[test]
+fn test_bip173_vectors() {
+	let valid = [
+		"A12UEL5L",
+		"a12uel5l",
+		"abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+		"split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+	];
+
+	for valid in valid {
+		let (hrp, data) = decode(valid).unwrap();
+		assert_eq!(encode(&hrp, &data), valid.to_ascii_lowercase());
+	}
+
+	// mixed case, a bad checksum, no separator and an empty human-readable part
+	for invalid in ["A12uEL5L", "a12uel5m", "pzry9x0s0muk", "1pzry9x0s0muk"] {
+		assert_eq!(decode(invalid), None);
+	}
+}
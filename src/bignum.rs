@@ -0,0 +1,295 @@
+// Arithmetic modulo odd numbers of up to 8192 bits, for RSA and finite-field
+// Diffie-Hellman. Numbers are little-endian arrays of 64-bit limbs, of which
+// only as many as the modulus has are used, and are multiplied in Montgomery
+// form. Everything but parsing the modulus takes time that depends only on
+// the length of the modulus and exponent, so exponents may be secret.
+
+use core::cmp::Ordering;
+
+pub const MAX_LIMBS: usize = 128;
+
+pub type Limbs = [u64; MAX_LIMBS];
+
+// the number of bits of the exponent handled by each multiplication
+const WINDOW_BITS: usize = 4;
+
+#[derive(Clone)]
+pub struct Modulus {
+	limbs: Limbs,
+	len: usize,
+	// -n ** -1 mod 2 ** 64
+	n0_inv: u64,
+	// R ** 2 mod n, where R = 2 ** (64 * len)
+	r2: Limbs,
+}
+
+// the limbs of a big-endian number, if it fits in MAX_LIMBS
+fn limbs_from_be_bytes(bytes: &[u8]) -> Option<Limbs> {
+	let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len());
+	let bytes = &bytes[start ..];
+
+	if bytes.len() > 8 * MAX_LIMBS {
+		return None;
+	}
+
+	let mut limbs = [0; MAX_LIMBS];
+
+	for (i, &byte) in bytes.iter().rev().enumerate() {
+		limbs[i / 8] |= (byte as u64) << (8 * (i % 8));
+	}
+
+	Some(limbs)
+}
+
+impl Modulus {
+	// returns none if the modulus is even, 1, or longer than MAX_LIMBS
+	pub fn from_be_bytes(bytes: &[u8]) -> Option<Self> {
+		let limbs = limbs_from_be_bytes(bytes)?;
+		let len = MAX_LIMBS - limbs.iter().rev().take_while(|&&limb| limb == 0).count();
+
+		if limbs[0] & 1 == 0 || (len == 1 && limbs[0] == 1) {
+			return None;
+		}
+
+		// each step of Newton's method doubles the number of correct bits,
+		// starting from the one bit that's right for any odd number
+		let mut inv: u64 = 1;
+
+		for _ in 0 .. 6 {
+			inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+		}
+
+		let mut modulus = Self {limbs, len, n0_inv: inv.wrapping_neg(), r2: [0; MAX_LIMBS]};
+
+		let mut r2 = [0; MAX_LIMBS];
+		r2[0] = 1;
+
+		for _ in 0 .. 128 * len {
+			modulus.double(&mut r2);
+		}
+
+		modulus.r2 = r2;
+		Some(modulus)
+	}
+
+	pub fn bits(&self) -> usize {
+		64 * self.len - self.limbs[self.len - 1].leading_zeros() as usize
+	}
+
+	// the length of the modulus in bytes, which every number is encoded as
+	pub fn byte_len(&self) -> usize {
+		self.bits().div_ceil(8)
+	}
+
+	// parses a big-endian number, which may have leading zeros, returning
+	// none unless it's less than the modulus
+	pub fn parse(&self, bytes: &[u8]) -> Option<Limbs> {
+		let limbs = limbs_from_be_bytes(bytes)?;
+
+		match compare(&limbs, &self.limbs) {
+			Ordering::Less => Some(limbs),
+			_ => None,
+		}
+	}
+
+	// encodes a number as big-endian bytes, filling all of `out`, which must
+	// be at least byte_len() long
+	pub fn encode(&self, x: &Limbs, out: &mut [u8]) {
+		assert!(out.len() >= self.byte_len());
+
+		for (i, byte) in out.iter_mut().rev().enumerate() {
+			*byte = match i < 8 * self.len {
+				true => (x[i / 8] >> (8 * (i % 8))) as u8,
+				false => 0,
+			};
+		}
+	}
+
+	// sets x = 2 * x mod n, for x < n
+	fn double(&self, x: &mut Limbs) {
+		let mut carry = 0;
+
+		for limb in &mut x[.. self.len] {
+			let top = *limb >> 63;
+			*limb = (*limb << 1) | carry;
+			carry = top;
+		}
+
+		self.reduce_once(x, carry);
+	}
+
+	// subtracts n from carry * R + x if it's at least n, for values below 2 * n
+	fn reduce_once(&self, x: &mut Limbs, carry: u64) {
+		let mut diff = [0; MAX_LIMBS];
+		let mut borrow = 0;
+
+		for i in 0 .. self.len {
+			let (d, b1) = x[i].overflowing_sub(self.limbs[i]);
+			let (d, b2) = d.overflowing_sub(borrow);
+			diff[i] = d;
+			borrow = (b1 | b2) as u64;
+		}
+
+		// the difference is kept unless the subtraction went below zero
+		let mask = 0u64.wrapping_sub(carry | (borrow ^ 1));
+
+		for i in 0 .. self.len {
+			x[i] = (diff[i] & mask) | (x[i] & !mask);
+		}
+	}
+
+	// returns a * b / R mod n, for a, b < n, by coarsely integrated operand
+	// scanning
+	fn mont_mul(&self, a: &Limbs, b: &Limbs) -> Limbs {
+		let n = self.len;
+		let mut t = [0u64; MAX_LIMBS + 2];
+
+		for i in 0 .. n {
+			let mut carry = 0;
+
+			for j in 0 .. n {
+				let sum = t[j] as u128 + a[i] as u128 * b[j] as u128 + carry as u128;
+				t[j] = sum as u64;
+				carry = (sum >> 64) as u64;
+			}
+
+			let sum = t[n] as u128 + carry as u128;
+			t[n] = sum as u64;
+			t[n + 1] = (sum >> 64) as u64;
+
+			// adds the multiple of n that clears the bottom limb, and shifts
+			// it out
+			let m = t[0].wrapping_mul(self.n0_inv);
+			let sum = t[0] as u128 + m as u128 * self.limbs[0] as u128;
+			let mut carry = (sum >> 64) as u64;
+
+			for j in 1 .. n {
+				let sum = t[j] as u128 + m as u128 * self.limbs[j] as u128 + carry as u128;
+				t[j - 1] = sum as u64;
+				carry = (sum >> 64) as u64;
+			}
+
+			let sum = t[n] as u128 + carry as u128;
+			t[n - 1] = sum as u64;
+			t[n] = t[n + 1] + (sum >> 64) as u64;
+		}
+
+		let mut out = [0; MAX_LIMBS];
+		out[.. n].copy_from_slice(&t[.. n]);
+		self.reduce_once(&mut out, t[n]);
+		out
+	}
+
+	// returns base ** exponent mod n, for base < n, where the exponent is
+	// big-endian; only its length is leaked by timing
+	pub fn pow(&self, base: &Limbs, exponent: &[u8]) -> Limbs {
+		let mut one = [0; MAX_LIMBS];
+		one[0] = 1;
+
+		// the powers of the base from 0 to 15, in Montgomery form
+		let mut table = [[0; MAX_LIMBS]; 1 << WINDOW_BITS];
+		table[0] = self.mont_mul(&one, &self.r2);
+		let base = self.mont_mul(base, &self.r2);
+
+		for i in 1 .. table.len() {
+			table[i] = self.mont_mul(&table[i - 1], &base);
+		}
+
+		let mut acc = table[0];
+
+		for &byte in exponent {
+			for window in [byte >> 4, byte & 0x0f] {
+				for _ in 0 .. WINDOW_BITS {
+					acc = self.mont_mul(&acc, &acc);
+				}
+
+				acc = self.mont_mul(&acc, &self.select(&table, window));
+			}
+		}
+
+		self.mont_mul(&acc, &one)
+	}
+
+	// returns table[index] without branching on or indexing by it
+	fn select(&self, table: &[Limbs; 1 << WINDOW_BITS], index: u8) -> Limbs {
+		let mut out = [0; MAX_LIMBS];
+
+		for (i, entry) in table.iter().enumerate() {
+			let mask = 0u64.wrapping_sub(((i as u8 ^ index) == 0) as u64);
+
+			for j in 0 .. self.len {
+				out[j] |= entry[j] & mask;
+			}
+		}
+
+		out
+	}
+}
+
+// compares two numbers, which are public, so this may take variable time
+pub fn compare(a: &Limbs, b: &Limbs) -> Ordering {
+	a.iter().rev().cmp(b.iter().rev())
+}
+
+#[cfg(all(test, feature = "std"))]
+use crate::test_util::hex_vec;
+
+// checked against python's pow
+#[cfg(feature = "std")]
+#[test]
+fn test_pow() {
+	let cases = [
+		("c5", "03", "010001", "93"),
+		(
+			"d23f0824128b2f330c5c7fd0a6a3a4506513270e269e0d37f2a74de452e6b439",
+			"36f675cc81e74ef5e8e25d940ed904759531985d5d9dc9f81818e811892f902b",
+			"6b0d549b6f03675a1600a35a099950d8",
+			"05a758fe6f19514874797d5138e6c4842b68bd9025386d215ffeb17aaab701bb",
+		),
+		(
+			"921e27a1c08a6a63ec24ede6a46b4cb2424a23d5962217beaddbc496cb8e8197\
+			3e0becd7b03898d190f9ebdacc0cb1e29c658cda1495e60af593bd04cf0fd630\
+			f1f29d0da9953f48f1a09f76b5a170b33839263059f28c105d1fb17c2390c192\
+			cfd3ac94af0f21ddb66cad4a268d116ece1738f7d93d9c172411e20b8f",
+			"3f4cbd87ad5c90a9587403e430ec66a78795e761d17731af10506bf2efc6f877\
+			186d76b07e881ed162ae2eb1547f15052434b9b5df9e7769b10f4205b4907a70\
+			c31012f037b64ce4228c38fb2918f135d25f557203301850c5a38fd547923a73\
+			6994e3bf911a61dbe22e44158bae97ba94d0eda82f8f6d05584ef8aa38",
+			"13ab1031d0f646e1f40a097c976bf46c697d2caf82eeeacbe226e875555790f8\
+			2ec1d3fcff2a3af4d46b0a18e8830e07bc1e398f1012bd4acefaecbd389be4bc\
+			fc49b64a0872e6cc3ababced2057ee05cde00902c77ebff206867347214cdd20\
+			55930d6eaf14f4733f3e7d1bfbc7a2ea20b2f14c942e05319acb5c7427",
+			"2a2a9db6f4937648dea9ac4c80b7ec791526e769f1f7f3b9c939366ee81063f2\
+			9bf7c51abc03a0b6d149d81ecebac0baed014b00836fb5559426f2ee7c8e9574\
+			955fcb4e938b5296d8dba34d83bf66b4b54ff66a0dd94bf0643424fe5ad3b45e\
+			713c65bbee4fb75a384274dc816492552989adb78f527246fa3d44ee04",
+		),
+	];
+
+	for (modulus, base, exponent, expected) in cases {
+		let modulus = Modulus::from_be_bytes(&hex_vec(modulus)).unwrap();
+		let base = modulus.parse(&hex_vec(base)).unwrap();
+
+		let mut out = std::vec![0; modulus.byte_len()];
+		modulus.encode(&modulus.pow(&base, &hex_vec(exponent)), &mut out);
+		assert_eq!(out, hex_vec(expected));
+	}
+
+	// x ** 0 = 1, even for x = 0
+	let modulus = Modulus::from_be_bytes(&[0xc5]).unwrap();
+	assert_eq!(modulus.pow(&modulus.parse(&[3]).unwrap(), &[])[0], 1);
+	assert_eq!(modulus.pow(&[0; MAX_LIMBS], &[])[0], 1);
+	assert_eq!(modulus.pow(&[0; MAX_LIMBS], &[5])[0], 0);
+}
+
+#[test]
+fn test_parse() {
+	assert!(Modulus::from_be_bytes(&[0x00, 0xc4]).is_none());
+	assert!(Modulus::from_be_bytes(&[0x00, 0x01]).is_none());
+	assert!(Modulus::from_be_bytes(&[0xff; 8 * MAX_LIMBS + 1]).is_none());
+
+	let modulus = Modulus::from_be_bytes(&[0x00, 0x01, 0x00, 0x01]).unwrap();
+	assert_eq!((modulus.bits(), modulus.byte_len()), (17, 3));
+	assert_eq!(modulus.parse(&[0x00, 0x00, 0x01, 0x00, 0x00]).unwrap()[0], 0x10000);
+	assert!(modulus.parse(&[0x01, 0x00, 0x01]).is_none());
+}
@@ -0,0 +1,442 @@
+//! Implemented according to [IETF RFC 7693](https://www.rfc-editor.org/rfc/rfc7693).
+//! [BLAKE2](https://www.blake2.net/) is a family of hash functions that is
+//! faster than SHA-2 in software while being at least as secure. BLAKE2b
+//! works on 64-bit words and has outputs of up to 64 bytes, and BLAKE2s
+//! works on 32-bit words for smaller platforms, with outputs of up to 32
+//! bytes.
+//!
+//! Both can be keyed, which makes them a MAC without the need for HMAC, and
+//! take a salt and a personalization string, which separate the hashes used
+//! by different applications or for different purposes. The output length is
+//! part of the parameters, so a shorter hash isn't a truncation of a longer
+//! one.
+//!
+//! ```
+//! use libkrypton::blake2::{blake2b, blake2s_keyed, Blake2b};
+//!
+//! let mut hasher = Blake2b::<32>::new();
+//! hasher.add_bytes(b"a");
+//! hasher.add_bytes(b"bc");
+//!
+//! assert_eq!(hasher.out(), blake2b::<32>(b"abc"));
+//!
+//! let tag: [u8; 16] = blake2s_keyed(b"secret key", b"message");
+//! ```
+
+use core::convert::TryInto;
+
+use crate::zeroize::zeroize;
+
+const BLAKE2B_IV: [u64; 8] = [
+	0x6a09e667f3bcc908,
+	0xbb67ae8584caa73b,
+	0x3c6ef372fe94f82b,
+	0xa54ff53a5f1d36f1,
+	0x510e527fade682d1,
+	0x9b05688c2b3e6c1f,
+	0x1f83d9abfb41bd6b,
+	0x5be0cd19137e2179,
+];
+
+const BLAKE2S_IV: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+	[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+	[14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+	[11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+	[7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+	[9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+	[2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+	[12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+	[13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+	[6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+	[10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+macro_rules! blake2_impl {
+	(
+		$name:ident,
+		$var_name:ident,
+		$function:ident,
+		$keyed_function:ident,
+		$compress:ident,
+		$mix:ident,
+		$hash_name:literal,
+		$word:ty,
+		$counter:ty,
+		$iv:ident,
+		$block_size:literal,
+		$max_len:literal,
+		$salt_size:literal,
+		$rounds:literal,
+		$rotations:expr,
+	) => {
+		#[doc = concat!("Returns the ", $hash_name, " digest of the byte slice passed to it, with an output")]
+		#[doc = concat!("length of `N` bytes. `N` must be between 1 and ", $max_len, ".")]
+		pub fn $function<const N: usize>(msg: &[u8]) -> [u8; N] {
+			let mut hasher = $name::new();
+			hasher.add_bytes(msg);
+			hasher.out()
+		}
+
+		#[doc = concat!("Returns the ", $hash_name, " digest of the byte slice passed to it, keyed with")]
+		#[doc = concat!("`key`, with an output length of `N` bytes. This is a MAC, and `key` must be at")]
+		#[doc = concat!("most ", $max_len, " bytes long.")]
+		pub fn $keyed_function<const N: usize>(key: &[u8], msg: &[u8]) -> [u8; N] {
+			let mut hasher = $name::new_keyed(key);
+			hasher.add_bytes(msg);
+			hasher.out()
+		}
+
+		#[doc = concat!("Struct used for computing ", $hash_name, " on data not stored contiguously in")]
+		/// memory, with an output length of `N` bytes.
+		#[derive(Clone)]
+		pub struct $name<const N: usize> {
+			hash_vals: [$word; 8],
+			buffer: [u8; $block_size],
+			buffer_len: usize,
+			bytes_compressed: $counter,
+		}
+
+		impl<const N: usize> $name<N> {
+			#[doc = concat!("Creates a new ", $hash_name, " hasher. Panics if `N` is not between 1 and ", $max_len, ".")]
+			pub fn new() -> Self {
+				Self::with_out_len(N, &[], &[0; $salt_size], &[0; $salt_size])
+			}
+
+			#[doc = concat!("Creates a new ", $hash_name, " hasher keyed with `key`, which must be at most")]
+			#[doc = concat!($max_len, " bytes long. Panics if `key` is too long, or if `N` is not between")]
+			#[doc = concat!("1 and ", $max_len, ".")]
+			pub fn new_keyed(key: &[u8]) -> Self {
+				Self::with_out_len(N, key, &[0; $salt_size], &[0; $salt_size])
+			}
+
+			#[doc = concat!("Creates a new ", $hash_name, " hasher with an optional `key`, which may be")]
+			/// empty, along with a `salt` and a personalization string `personal`.
+			/// Panics if `key` is too long, or if `N` is out of range.
+			pub fn with_params(key: &[u8], salt: &[u8; $salt_size], personal: &[u8; $salt_size]) -> Self {
+				Self::with_out_len(N, key, salt, personal)
+			}
+
+			fn with_out_len(out_len: usize, key: &[u8], salt: &[u8; $salt_size], personal: &[u8; $salt_size]) -> Self {
+				assert!(out_len >= 1 && out_len <= N && N <= $max_len);
+				assert!(key.len() <= $max_len, concat!(stringify!($function), " key too long"));
+
+				// the first word of the parameter block holds the output length, the
+				// key length, and a fanout and depth of 1 for sequential hashing
+				let mut hash_vals = $iv;
+				hash_vals[0] ^= 0x01010000 ^ ((key.len() as $word) << 8) ^ out_len as $word;
+
+				const WORD_SIZE: usize = $salt_size / 2;
+
+				for i in 0 .. 2 {
+					hash_vals[4 + i] ^= <$word>::from_le_bytes(salt[WORD_SIZE * i .. WORD_SIZE * (i + 1)].try_into().unwrap());
+					hash_vals[6 + i] ^= <$word>::from_le_bytes(personal[WORD_SIZE * i .. WORD_SIZE * (i + 1)].try_into().unwrap());
+				}
+
+				let mut out = Self {
+					hash_vals,
+					buffer: [0; $block_size],
+					buffer_len: 0,
+					bytes_compressed: 0,
+				};
+
+				// the key is padded to a full block and hashed before the message
+				if key.len() > 0 {
+					let mut key_block = [0; $block_size];
+					key_block[.. key.len()].copy_from_slice(key);
+					out.add_bytes(&key_block);
+					zeroize(&mut key_block);
+				}
+
+				out
+			}
+
+			/// Adds bytes to the hasher.
+			pub fn add_bytes(&mut self, mut bytes: &[u8]) {
+				while bytes.len() > 0 {
+					// the final block has to be compressed differently, so a full buffer is
+					// only compressed once it's known that more data follows it
+					if self.buffer_len == $block_size {
+						self.bytes_compressed += $block_size;
+						$compress(&mut self.hash_vals, &self.buffer, self.bytes_compressed, false);
+						self.buffer_len = 0;
+					}
+
+					let len = usize::min($block_size - self.buffer_len, bytes.len());
+					self.buffer[self.buffer_len .. self.buffer_len + len].copy_from_slice(&bytes[.. len]);
+					self.buffer_len += len;
+					bytes = &bytes[len ..];
+				}
+			}
+
+			#[doc = concat!("Consumes the hasher and outputs the ", $hash_name, " digest of the concatenation")]
+			/// of all bytes added to it.
+			pub fn out(mut self) -> [u8; N] {
+				self.bytes_compressed += self.buffer_len as $counter;
+
+				for i in self.buffer_len .. $block_size {
+					self.buffer[i] = 0;
+				}
+
+				$compress(&mut self.hash_vals, &self.buffer, self.bytes_compressed, true);
+
+				const WORD_SIZE: usize = $salt_size / 2;
+				let mut full = [0; $max_len];
+
+				for i in 0 .. 8 {
+					full[WORD_SIZE * i .. WORD_SIZE * (i + 1)].copy_from_slice(&self.hash_vals[i].to_le_bytes());
+				}
+
+				zeroize(&mut self.buffer);
+
+				full[.. N].try_into().unwrap()
+			}
+		}
+
+		impl<const N: usize> Default for $name<N> {
+			fn default() -> Self {
+				Self::new()
+			}
+		}
+
+		impl<const N: usize> Drop for $name<N> {
+			fn drop(&mut self) {
+				zeroize(&mut self.hash_vals);
+				zeroize(&mut self.buffer);
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl<const N: usize> ::zeroize::Zeroize for $name<N> {
+			fn zeroize(&mut self) {
+				zeroize(&mut self.hash_vals);
+				zeroize(&mut self.buffer);
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl<const N: usize> ::zeroize::ZeroizeOnDrop for $name<N> {}
+
+		#[doc = concat!("Struct used for computing ", $hash_name, " with an output length that's only known")]
+		/// at runtime.
+		#[derive(Clone)]
+		pub struct $var_name {
+			inner: $name<$max_len>,
+			out_len: usize,
+		}
+
+		impl $var_name {
+			#[doc = concat!("Creates a new ", $hash_name, " hasher with an output length of `out_len` bytes.")]
+			#[doc = concat!("Panics if `out_len` is not between 1 and ", $max_len, ".")]
+			pub fn new(out_len: usize) -> Self {
+				Self {inner: $name::with_out_len(out_len, &[], &[0; $salt_size], &[0; $salt_size]), out_len}
+			}
+
+			#[doc = concat!("Creates a new ", $hash_name, " hasher with an output length of `out_len` bytes,")]
+			/// an optional `key`, which may be empty, a `salt` and a personalization
+			/// string `personal`. Panics if `key` is too long, or if `out_len` is out
+			/// of range.
+			pub fn with_params(out_len: usize, key: &[u8], salt: &[u8; $salt_size], personal: &[u8; $salt_size]) -> Self {
+				Self {inner: $name::with_out_len(out_len, key, salt, personal), out_len}
+			}
+
+			/// Adds bytes to the hasher.
+			pub fn add_bytes(&mut self, bytes: &[u8]) {
+				self.inner.add_bytes(bytes);
+			}
+
+			#[doc = concat!("Consumes the hasher and writes the ", $hash_name, " digest of the concatenation")]
+			/// of all bytes added to it to `out`. Panics if `out` isn't the length
+			#[doc = concat!("passed to [`", stringify!($var_name), "::new`].")]
+			pub fn out(self, out: &mut [u8]) {
+				out.copy_from_slice(&self.inner.out()[.. self.out_len]);
+			}
+		}
+
+		fn $compress(hash_vals: &mut [$word; 8], block: &[u8; $block_size], bytes_compressed: $counter, last: bool) {
+			const WORD_SIZE: usize = $salt_size / 2;
+			let mut msg = [0; 16];
+
+			for i in 0 .. 16 {
+				msg[i] = <$word>::from_le_bytes(block[WORD_SIZE * i .. WORD_SIZE * (i + 1)].try_into().unwrap());
+			}
+
+			let mut v = [0; 16];
+			v[.. 8].copy_from_slice(hash_vals);
+			v[8 ..].copy_from_slice(&$iv);
+
+			v[12] ^= bytes_compressed as $word;
+			v[13] ^= (bytes_compressed >> (8 * WORD_SIZE)) as $word;
+
+			if last {
+				v[14] = !v[14];
+			}
+
+			for round in 0 .. $rounds {
+				let s = &SIGMA[round % 10];
+
+				$mix(&mut v, 0, 4, 8, 12, msg[s[0]], msg[s[1]]);
+				$mix(&mut v, 1, 5, 9, 13, msg[s[2]], msg[s[3]]);
+				$mix(&mut v, 2, 6, 10, 14, msg[s[4]], msg[s[5]]);
+				$mix(&mut v, 3, 7, 11, 15, msg[s[6]], msg[s[7]]);
+
+				$mix(&mut v, 0, 5, 10, 15, msg[s[8]], msg[s[9]]);
+				$mix(&mut v, 1, 6, 11, 12, msg[s[10]], msg[s[11]]);
+				$mix(&mut v, 2, 7, 8, 13, msg[s[12]], msg[s[13]]);
+				$mix(&mut v, 3, 4, 9, 14, msg[s[14]], msg[s[15]]);
+			}
+
+			for i in 0 .. 8 {
+				hash_vals[i] ^= v[i] ^ v[i + 8];
+			}
+		}
+
+		fn $mix(v: &mut [$word; 16], a: usize, b: usize, c: usize, d: usize, x: $word, y: $word) {
+			let [r1, r2, r3, r4] = $rotations;
+
+			v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+			v[d] = (v[d] ^ v[a]).rotate_right(r1);
+			v[c] = v[c].wrapping_add(v[d]);
+			v[b] = (v[b] ^ v[c]).rotate_right(r2);
+
+			v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+			v[d] = (v[d] ^ v[a]).rotate_right(r3);
+			v[c] = v[c].wrapping_add(v[d]);
+			v[b] = (v[b] ^ v[c]).rotate_right(r4);
+		}
+	};
+}
+
+blake2_impl!(
+	Blake2b, Blake2bVar, blake2b, blake2b_keyed, compress_b, mix_b, "BLAKE2b",
+	u64, u128, BLAKE2B_IV, 128, 64, 16, 12, [32, 24, 16, 63],
+);
+
+blake2_impl!(
+	Blake2s, Blake2sVar, blake2s, blake2s_keyed, compress_s, mix_s, "BLAKE2s",
+	u32, u64, BLAKE2S_IV, 64, 32, 8, 10, [16, 12, 8, 7],
+);
+
+#[test]
+fn test_rfc7693_abc() {
+	assert_eq!(blake2b::<64>(b"abc"), [
+		0xba, 0x80, 0xa5, 0x3f, 0x98, 0x1c, 0x4d, 0x0d,
+		0x6a, 0x27, 0x97, 0xb6, 0x9f, 0x12, 0xf6, 0xe9,
+		0x4c, 0x21, 0x2f, 0x14, 0x68, 0x5a, 0xc4, 0xb7,
+		0x4b, 0x12, 0xbb, 0x6f, 0xdb, 0xff, 0xa2, 0xd1,
+		0x7d, 0x87, 0xc5, 0x39, 0x2a, 0xab, 0x79, 0x2d,
+		0xc2, 0x52, 0xd5, 0xde, 0x45, 0x33, 0xcc, 0x95,
+		0x18, 0xd3, 0x8a, 0xa8, 0xdb, 0xf1, 0x92, 0x5a,
+		0xb9, 0x23, 0x86, 0xed, 0xd4, 0x00, 0x99, 0x23,
+	]);
+}
+
+#[test]
+fn test_block_boundaries() {
+	// exactly one block, which must be compressed as the final block
+	assert_eq!(blake2b::<32>(&[0x5a; 128]), [
+		0x1f, 0x7d, 0x46, 0x42, 0xe9, 0x6d, 0x74, 0x6b,
+		0xaf, 0xdc, 0x2f, 0x63, 0xef, 0x0c, 0xf4, 0xe5,
+		0x08, 0x36, 0x16, 0xed, 0xff, 0x9f, 0x1b, 0xa3,
+		0xe4, 0xf9, 0x01, 0xdc, 0x73, 0x16, 0x64, 0xc6,
+	]);
+
+	assert_eq!(blake2b::<32>(&[0x5a; 300]), [
+		0x34, 0x04, 0x5c, 0x8e, 0xfd, 0x30, 0xb0, 0x72,
+		0xe6, 0x50, 0x86, 0xb1, 0x15, 0x01, 0xbf, 0x85,
+		0xe9, 0xef, 0x8c, 0x9f, 0xaa, 0x55, 0x4e, 0x16,
+		0x7c, 0x59, 0xad, 0x12, 0x16, 0x4e, 0xd3, 0x5d,
+	]);
+}
+
+#[test]
+fn test_var_matches_fixed() {
+	let mut out = [0; 32];
+	let mut hasher = Blake2bVar::new(32);
+	hasher.add_bytes(b"abc");
+	hasher.out(&mut out);
+	assert_eq!(out, blake2b::<32>(b"abc"));
+}
+
+#[test]
+fn test_incremental_matches_oneshot() {
+	let msg = [0x5a; 300];
+
+	for split in [0, 1, 127, 128, 129, 256, 300] {
+		let mut hasher = Blake2b::<32>::new();
+		hasher.add_bytes(&msg[.. split]);
+		hasher.add_bytes(&msg[split ..]);
+		assert_eq!(hasher.out(), blake2b::<32>(&msg));
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix B of RFC 7693, and checked against python's hashlib.blake2s at
+// the block boundaries
+#[test]
+fn test_blake2s() {
+	assert_eq!(blake2s::<32>(b"abc"), hex("508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"));
+	assert_eq!(blake2s::<32>(&[0x5a; 64]), hex("2fac0f29ee5f196dec49e7d1345f5420b89f10f2657324cc7fbec317363e2cd3"));
+	assert_eq!(blake2s::<16>(&[0x5a; 200]), hex("8ddcf2adef9a311091b9bb2e413b0a0c"));
+
+	let msg = [0x5a; 200];
+
+	for split in [0, 1, 63, 64, 65, 128, 200] {
+		let mut hasher = Blake2s::<16>::new();
+		hasher.add_bytes(&msg[.. split]);
+		hasher.add_bytes(&msg[split ..]);
+		assert_eq!(hasher.out(), blake2s::<16>(&msg));
+	}
+}
+
+// the empty messages are the first keyed test vectors from the BLAKE2
+// reference implementation, and the rest were checked against python's
+// hashlib.blake2b and hashlib.blake2s
+#[test]
+fn test_keyed() {
+	let mut key = [0; 64];
+
+	for i in 0 .. 64 {
+		key[i] = i as u8;
+	}
+
+	assert_eq!(blake2b_keyed::<64>(&key, b""), hex(
+		"10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786\
+		b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568",
+	));
+
+	assert_eq!(blake2s_keyed::<32>(&key[.. 32], b""), hex("48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49"));
+
+	assert_eq!(blake2b_keyed::<64>(b"secret key", b"hello"), hex(
+		"44b1d71824423b0d4f3a005871b2cb25b59f2436d254a529402323e17fa639e0\
+		e82f6de4bce6d9498a8136c411c1cb41295b1a2fd2c622305c353210f503cc8e",
+	));
+
+	assert_eq!(blake2s_keyed::<32>(b"secret key", b"hello"), hex("08a0539a20f89304b748bfba8aba716952839d7f7266f18ba3036683344c38cc"));
+}
+
+// checked against the salt and person parameters of python's hashlib.blake2b
+// and hashlib.blake2s
+#[test]
+fn test_salt_and_personal() {
+	let mut hasher = Blake2b::<20>::with_params(b"k", b"0123456789abcdef", b"libkrypton tests");
+	hasher.add_bytes(b"hello");
+	assert_eq!(hasher.out(), hex("b5ede11ab9b9557d1e64c627cca28991f4cd4597"));
+
+	let mut out = [0; 20];
+	let mut hasher = Blake2sVar::with_params(20, b"k", b"01234567", b"personal");
+	hasher.add_bytes(b"hello");
+	hasher.out(&mut out);
+	assert_eq!(out, hex("4a75e730d4a85a6f414990c82ac53679a604ffa7"));
+}
+
+#[test]
+#[should_panic]
+fn test_key_too_long() {
+	blake2s_keyed::<32>(&[0; 33], b"");
+}
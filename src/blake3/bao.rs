@@ -0,0 +1,388 @@
+//! Verified streaming in the style of [Bao](https://github.com/oconnor663/bao),
+//! which interleaves content with the parent nodes of its BLAKE3 tree, so
+//! that a recipient who knows only the BLAKE3 hash of the content can check
+//! each 1 KiB chunk as it arrives, rather than only after the whole download.
+//! A [`Decoder`] never outputs content that hasn't been verified, and stops
+//! at the first corrupted chunk.
+//!
+//! The encoding is Bao's combined encoding: the content length as an 8-byte
+//! little-endian integer, followed by the tree in pre-order, where each
+//! parent node is the 64-byte concatenation of its children's chaining
+//! values, and each leaf is a chunk of content. The encoding is 64 bytes
+//! longer than the content for each chunk after the first, plus the header.
+//!
+//! ```
+//! use libkrypton::blake3::bao::{encode, Decoder};
+//! use libkrypton::blake3::hash;
+//!
+//! let content = [0x42; 5000];
+//! let (encoded, root_hash) = encode(&content);
+//! assert_eq!(root_hash, hash(&content));
+//!
+//! // the recipient only needs the root hash
+//! let mut decoder = Decoder::new(&root_hash);
+//! let mut verified = Vec::new();
+//!
+//! for piece in encoded.chunks(700) {
+//!     decoder.add_bytes(piece, &mut verified).unwrap();
+//! }
+//!
+//! decoder.finish().unwrap();
+//! assert_eq!(verified, content);
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+use super::{parent_output, words_from_le_bytes, ChunkState, Output, CHUNK_LEN, IV, OUT_LEN};
+
+const HEADER_LEN: usize = 8;
+const PARENT_LEN: usize = 2 * OUT_LEN;
+
+/// An error returned when decoding fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A chunk or parent node doesn't match the hash it should have, so the
+	/// encoding was corrupted, or is for different content. Everything output
+	/// before this error was verified.
+	HashMismatch,
+	/// The encoding ended before all of the content was verified.
+	Truncated,
+	/// There are more bytes after the end of the encoding.
+	TrailingData,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::HashMismatch => "bao encoding doesn't match the hash",
+			Error::Truncated => "bao encoding is truncated",
+			Error::TrailingData => "trailing data after bao encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+fn chunk_count(content_len: u64) -> u64 {
+	u64::max(1, content_len.div_ceil(CHUNK_LEN as u64))
+}
+
+// the left subtree of a parent is the largest power of two number of chunks
+// that leaves at least one chunk for the right subtree
+fn left_chunks(chunks: u64) -> u64 {
+	1 << (63 - (chunks - 1).leading_zeros())
+}
+
+fn node_hash(output: &Output, is_root: bool) -> [u8; OUT_LEN] {
+	if is_root {
+		return output.root_output_block(0)[.. OUT_LEN].try_into().unwrap();
+	}
+
+	let mut out = [0; OUT_LEN];
+
+	for (bytes, word) in out.chunks_mut(4).zip(output.chaining_value()) {
+		bytes.copy_from_slice(&word.to_le_bytes());
+	}
+
+	out
+}
+
+fn chunk_hash(chunk: &[u8], chunk_counter: u64, is_root: bool) -> [u8; OUT_LEN] {
+	let mut chunk_state = ChunkState::new(&IV, chunk_counter, 0);
+	chunk_state.update(chunk);
+	node_hash(&chunk_state.output(), is_root)
+}
+
+fn parent_hash(parent: &[u8], is_root: bool) -> [u8; OUT_LEN] {
+	let left = words_from_le_bytes(&parent[.. OUT_LEN]);
+	let right = words_from_le_bytes(&parent[OUT_LEN ..]);
+	node_hash(&parent_output(&left, &right, &IV, 0), is_root)
+}
+
+fn encode_subtree(content: &[u8], chunk_counter: u64, is_root: bool, out: &mut Vec<u8>) -> [u8; OUT_LEN] {
+	if content.len() <= CHUNK_LEN {
+		out.extend_from_slice(content);
+		return chunk_hash(content, chunk_counter, is_root);
+	}
+
+	let left_chunks = left_chunks(chunk_count(content.len() as u64));
+	let (left, right) = content.split_at(left_chunks as usize * CHUNK_LEN);
+
+	let parent_pos = out.len();
+	out.extend_from_slice(&[0; PARENT_LEN]);
+
+	let left_hash = encode_subtree(left, chunk_counter, false, out);
+	let right_hash = encode_subtree(right, chunk_counter + left_chunks, false, out);
+
+	let parent = &mut out[parent_pos .. parent_pos + PARENT_LEN];
+	parent[.. OUT_LEN].copy_from_slice(&left_hash);
+	parent[OUT_LEN ..].copy_from_slice(&right_hash);
+
+	parent_hash(parent, is_root)
+}
+
+/// Encodes `content`, returning the encoding and the root hash, which is the
+/// same as the BLAKE3 hash of `content`.
+pub fn encode(content: &[u8]) -> (Vec<u8>, [u8; OUT_LEN]) {
+	let chunks = chunk_count(content.len() as u64) as usize;
+
+	let mut out = Vec::with_capacity(HEADER_LEN + content.len() + PARENT_LEN * (chunks - 1));
+	out.extend_from_slice(&(content.len() as u64).to_le_bytes());
+
+	let root_hash = encode_subtree(content, 0, true, &mut out);
+
+	(out, root_hash)
+}
+
+/// Decodes a whole encoding at once, checking it against `root_hash`.
+pub fn decode(encoded: &[u8], root_hash: &[u8; OUT_LEN]) -> Result<Vec<u8>, Error> {
+	let mut decoder = Decoder::new(root_hash);
+	let mut out = Vec::new();
+
+	decoder.add_bytes(encoded, &mut out)?;
+	decoder.finish()?;
+
+	Ok(out)
+}
+
+// a node of the tree that hasn't been read yet, along with the hash it must
+// have and the chunks it covers
+struct Node {
+	hash: [u8; OUT_LEN],
+	chunk_counter: u64,
+	chunks: u64,
+	is_root: bool,
+}
+
+/// An incremental decoder, which verifies an encoding as it arrives in
+/// pieces of any size, and outputs each chunk of content once it's verified.
+pub struct Decoder {
+	root_hash: [u8; OUT_LEN],
+	content_len: Option<u64>,
+	buffer: Vec<u8>,
+	stack: Vec<Node>,
+	error: Option<Error>,
+}
+
+impl Decoder {
+	/// Creates a decoder for content with the BLAKE3 hash `root_hash`.
+	pub fn new(root_hash: &[u8; OUT_LEN]) -> Self {
+		Self {
+			root_hash: *root_hash,
+			content_len: None,
+			buffer: Vec::with_capacity(CHUNK_LEN),
+			stack: Vec::new(),
+			error: None,
+		}
+	}
+
+	/// Returns the length of the content, once the header has been read.
+	/// The length isn't verified until the last chunk is.
+	pub fn content_len(&self) -> Option<u64> {
+		self.content_len
+	}
+
+	// the number of bytes needed for the next part of the encoding, or none
+	// if the encoding is complete
+	fn needed(&self) -> Option<usize> {
+		let content_len = match self.content_len {
+			Some(content_len) => content_len,
+			None => return Some(HEADER_LEN),
+		};
+
+		let node = self.stack.last()?;
+
+		if node.chunks > 1 {
+			return Some(PARENT_LEN);
+		}
+
+		let start = node.chunk_counter * CHUNK_LEN as u64;
+		Some(u64::min(CHUNK_LEN as u64, content_len - start) as usize)
+	}
+
+	fn process(&mut self, mut bytes: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+		loop {
+			let needed = match self.needed() {
+				Some(needed) => needed,
+				None if bytes.len() > 0 => return Err(Error::TrailingData),
+				None => return Ok(()),
+			};
+
+			let len = usize::min(needed - self.buffer.len(), bytes.len());
+			self.buffer.extend_from_slice(&bytes[.. len]);
+			bytes = &bytes[len ..];
+
+			if self.buffer.len() < needed {
+				return Ok(());
+			}
+
+			match self.stack.pop() {
+				None => {
+					let content_len = u64::from_le_bytes(self.buffer[..].try_into().unwrap());
+					self.content_len = Some(content_len);
+
+					self.stack.push(Node {
+						hash: self.root_hash,
+						chunk_counter: 0,
+						chunks: chunk_count(content_len),
+						is_root: true,
+					});
+				},
+				Some(node) if node.chunks > 1 => {
+					if parent_hash(&self.buffer, node.is_root) != node.hash {
+						return Err(Error::HashMismatch);
+					}
+
+					let left_chunks = left_chunks(node.chunks);
+
+					self.stack.push(Node {
+						hash: self.buffer[OUT_LEN ..].try_into().unwrap(),
+						chunk_counter: node.chunk_counter + left_chunks,
+						chunks: node.chunks - left_chunks,
+						is_root: false,
+					});
+
+					self.stack.push(Node {
+						hash: self.buffer[.. OUT_LEN].try_into().unwrap(),
+						chunk_counter: node.chunk_counter,
+						chunks: left_chunks,
+						is_root: false,
+					});
+				},
+				Some(node) => {
+					if chunk_hash(&self.buffer, node.chunk_counter, node.is_root) != node.hash {
+						return Err(Error::HashMismatch);
+					}
+
+					out.extend_from_slice(&self.buffer);
+				},
+			}
+
+			self.buffer.clear();
+		}
+	}
+
+	/// Adds the next `bytes` of the encoding, appending any content that is
+	/// verified as a result to `out`. Once an error is returned, every later
+	/// call returns it too.
+	pub fn add_bytes(&mut self, bytes: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+		if let Some(error) = self.error {
+			return Err(error);
+		}
+
+		let result = self.process(bytes, out);
+		self.error = result.err();
+
+		result
+	}
+
+	/// Checks that the whole encoding has been read, and so all of the
+	/// content has been verified.
+	pub fn finish(self) -> Result<(), Error> {
+		if let Some(error) = self.error {
+			return Err(error);
+		}
+
+		match self.needed() {
+			Some(_) => Err(Error::Truncated),
+			None => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+fn test_content(len: usize) -> Vec<u8> {
+	(0 .. len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_round_trip() {
+	for len in [0, 1, 1023, 1024, 1025, 2048, 2049, 4096, 5000, 9000] {
+		let content = test_content(len);
+		let (encoded, root_hash) = encode(&content);
+
+		assert_eq!(root_hash, super::hash(&content));
+		assert_eq!(encoded.len(), HEADER_LEN + len + PARENT_LEN * (chunk_count(len as u64) as usize - 1));
+		assert_eq!(decode(&encoded, &root_hash).unwrap(), content);
+
+		let mut decoder = Decoder::new(&root_hash);
+		let mut out = Vec::new();
+
+		for piece in encoded.chunks(37) {
+			decoder.add_bytes(piece, &mut out).unwrap();
+		}
+
+		assert_eq!(decoder.content_len(), Some(len as u64));
+		decoder.finish().unwrap();
+		assert_eq!(out, content);
+	}
+}
+
+// three chunks form a parent over the first two, and a root over that and
+// the last
+#[test]
+fn test_layout() {
+	let content = test_content(2049);
+	let (encoded, root_hash) = encode(&content);
+
+	let left_words = super::subtree_chaining_value(&content[.. 2048], 0, &IV, 0, false);
+	let mut left = [0; OUT_LEN];
+
+	for (bytes, word) in left.chunks_mut(4).zip(left_words) {
+		bytes.copy_from_slice(&word.to_le_bytes());
+	}
+
+	assert_eq!(encoded[.. 8], 2049u64.to_le_bytes());
+	assert_eq!(encoded[8 .. 40], left);
+	assert_eq!(encoded[40 .. 72], chunk_hash(&content[2048 ..], 2, false));
+	assert_eq!(encoded[72 .. 104], chunk_hash(&content[.. 1024], 0, false));
+	assert_eq!(encoded[104 .. 136], chunk_hash(&content[1024 .. 2048], 1, false));
+	assert_eq!(encoded[136 ..], content[..]);
+	assert_eq!(parent_hash(&encoded[8 .. 72], true), root_hash);
+}
+
+#[test]
+fn test_corruption() {
+	let content = test_content(5000);
+	let (encoded, root_hash) = encode(&content);
+
+	// the fourth chunk follows the header, four parents and three chunks
+	let mut corrupted = encoded.clone();
+	corrupted[HEADER_LEN + 4 * PARENT_LEN + 3 * CHUNK_LEN + 5] ^= 1;
+
+	let mut decoder = Decoder::new(&root_hash);
+	let mut out = Vec::new();
+	let mut result = Ok(());
+
+	for piece in corrupted.chunks(100) {
+		result = decoder.add_bytes(piece, &mut out);
+
+		if result.is_err() {
+			break;
+		}
+	}
+
+	assert_eq!(result, Err(Error::HashMismatch));
+	assert_eq!(out, content[.. 3 * CHUNK_LEN]);
+	assert_eq!(decoder.add_bytes(&[], &mut out), Err(Error::HashMismatch));
+	assert_eq!(decoder.finish(), Err(Error::HashMismatch));
+
+	for i in [0, 8, 100, encoded.len() - 1] {
+		let mut corrupted = encoded.clone();
+		corrupted[i] ^= 1;
+		assert!(decode(&corrupted, &root_hash).is_err());
+	}
+
+	assert_eq!(decode(&encoded, &super::hash(b"other")), Err(Error::HashMismatch));
+	assert_eq!(decode(&encoded[.. encoded.len() - 1], &root_hash), Err(Error::Truncated));
+	assert_eq!(decode(&encoded[.. 4], &root_hash), Err(Error::Truncated));
+
+	let mut extended = encoded.clone();
+	extended.push(0);
+	assert_eq!(decode(&extended, &root_hash), Err(Error::TrailingData));
+}
@@ -0,0 +1,580 @@
+//! Implemented according to the
+//! [BLAKE3 specification](https://github.com/BLAKE3-team/BLAKE3-specs/blob/master/blake3.pdf).
+//! BLAKE3 is a hash function built from the BLAKE2s compression function
+//! with fewer rounds, arranged in a binary tree over 1 KiB chunks of the
+//! input, so that large inputs can be hashed in parallel. With the `rayon`
+//! feature, [`Hasher::add_bytes_rayon`] hashes subtrees on multiple threads.
+//!
+//! Besides plain hashing, BLAKE3 has a keyed mode, which is a MAC, and a key
+//! derivation mode, which derives keys from key material and a hardcoded,
+//! globally unique context string. Any of them can produce output of any
+//! length.
+//!
+//! ```
+//! use libkrypton::blake3::{hash, Hasher};
+//!
+//! let mut hasher = Hasher::new();
+//! hasher.add_bytes(b"a");
+//! hasher.add_bytes(b"bc");
+//!
+//! assert_eq!(hasher.out(), hash(b"abc"));
+//! ```
+//!
+//! The [`bao`] module uses the tree structure to verify streamed content
+//! incrementally against a BLAKE3 hash.
+
+use core::convert::TryInto;
+
+#[cfg(feature = "std")]
+pub mod bao;
+
+/// The length of a BLAKE3 key, and of its default output.
+pub const KEY_LEN: usize = 32;
+
+/// The length of the default BLAKE3 output.
+pub const OUT_LEN: usize = 32;
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+const KEYED_HASH: u32 = 1 << 4;
+const DERIVE_KEY_CONTEXT: u32 = 1 << 5;
+const DERIVE_KEY_MATERIAL: u32 = 1 << 6;
+
+const IV: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+	state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+	state[d] = (state[d] ^ state[a]).rotate_right(16);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_right(12);
+
+	state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+	state[d] = (state[d] ^ state[a]).rotate_right(8);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+fn compress(chaining_value: &[u32; 8], block: &[u32; 16], counter: u64, block_len: u32, flags: u32) -> [u32; 16] {
+	let mut state = [0; 16];
+	state[.. 8].copy_from_slice(chaining_value);
+	state[8 .. 12].copy_from_slice(&IV[.. 4]);
+	state[12] = counter as u32;
+	state[13] = (counter >> 32) as u32;
+	state[14] = block_len;
+	state[15] = flags;
+
+	let mut m = *block;
+
+	for round in 0 .. 7 {
+		g(&mut state, 0, 4, 8, 12, m[0], m[1]);
+		g(&mut state, 1, 5, 9, 13, m[2], m[3]);
+		g(&mut state, 2, 6, 10, 14, m[4], m[5]);
+		g(&mut state, 3, 7, 11, 15, m[6], m[7]);
+
+		g(&mut state, 0, 5, 10, 15, m[8], m[9]);
+		g(&mut state, 1, 6, 11, 12, m[10], m[11]);
+		g(&mut state, 2, 7, 8, 13, m[12], m[13]);
+		g(&mut state, 3, 4, 9, 14, m[14], m[15]);
+
+		if round < 6 {
+			m = MSG_PERMUTATION.map(|i| m[i]);
+		}
+	}
+
+	for i in 0 .. 8 {
+		state[i] ^= state[i + 8];
+		state[i + 8] ^= chaining_value[i];
+	}
+
+	state
+}
+
+fn words_from_le_bytes<const N: usize>(bytes: &[u8]) -> [u32; N] {
+	let mut out = [0; N];
+
+	for i in 0 .. N {
+		out[i] = u32::from_le_bytes(bytes[4 * i .. 4 * (i + 1)].try_into().unwrap());
+	}
+
+	out
+}
+
+fn first_8_words(words: [u32; 16]) -> [u32; 8] {
+	words[.. 8].try_into().unwrap()
+}
+
+// the inputs to the final compression of a node, kept so that the root node
+// can be compressed again with the ROOT flag and any output block counter
+#[derive(Clone)]
+struct Output {
+	input_chaining_value: [u32; 8],
+	block: [u32; 16],
+	counter: u64,
+	block_len: u32,
+	flags: u32,
+}
+
+impl Output {
+	fn chaining_value(&self) -> [u32; 8] {
+		first_8_words(compress(&self.input_chaining_value, &self.block, self.counter, self.block_len, self.flags))
+	}
+
+	fn root_output_block(&self, output_block_counter: u64) -> [u8; 64] {
+		let words = compress(&self.input_chaining_value, &self.block, output_block_counter, self.block_len, self.flags | ROOT);
+		let mut out = [0; 64];
+
+		for i in 0 .. 16 {
+			out[4 * i .. 4 * (i + 1)].copy_from_slice(&words[i].to_le_bytes());
+		}
+
+		out
+	}
+}
+
+#[derive(Clone)]
+struct ChunkState {
+	chaining_value: [u32; 8],
+	chunk_counter: u64,
+	block: [u8; BLOCK_LEN],
+	block_len: usize,
+	blocks_compressed: usize,
+	flags: u32,
+}
+
+impl ChunkState {
+	fn new(key: &[u32; 8], chunk_counter: u64, flags: u32) -> Self {
+		Self {
+			chaining_value: *key,
+			chunk_counter,
+			block: [0; BLOCK_LEN],
+			block_len: 0,
+			blocks_compressed: 0,
+			flags,
+		}
+	}
+
+	fn len(&self) -> usize {
+		BLOCK_LEN * self.blocks_compressed + self.block_len
+	}
+
+	fn start_flag(&self) -> u32 {
+		match self.blocks_compressed {
+			0 => CHUNK_START,
+			_ => 0,
+		}
+	}
+
+	fn update(&mut self, mut input: &[u8]) {
+		while input.len() > 0 {
+			// like BLAKE2, a full block is only compressed once it's known that
+			// more input follows it, since the last block is compressed differently
+			if self.block_len == BLOCK_LEN {
+				let block = words_from_le_bytes(&self.block);
+				let flags = self.flags | self.start_flag();
+				self.chaining_value = first_8_words(compress(&self.chaining_value, &block, self.chunk_counter, BLOCK_LEN as u32, flags));
+				self.blocks_compressed += 1;
+				self.block = [0; BLOCK_LEN];
+				self.block_len = 0;
+			}
+
+			let len = usize::min(BLOCK_LEN - self.block_len, input.len());
+			self.block[self.block_len .. self.block_len + len].copy_from_slice(&input[.. len]);
+			self.block_len += len;
+			input = &input[len ..];
+		}
+	}
+
+	fn output(&self) -> Output {
+		Output {
+			input_chaining_value: self.chaining_value,
+			block: words_from_le_bytes(&self.block),
+			counter: self.chunk_counter,
+			block_len: self.block_len as u32,
+			flags: self.flags | self.start_flag() | CHUNK_END,
+		}
+	}
+}
+
+fn parent_output(left: &[u32; 8], right: &[u32; 8], key: &[u32; 8], flags: u32) -> Output {
+	let mut block = [0; 16];
+	block[.. 8].copy_from_slice(left);
+	block[8 ..].copy_from_slice(right);
+
+	Output {
+		input_chaining_value: *key,
+		block,
+		counter: 0,
+		block_len: BLOCK_LEN as u32,
+		flags: PARENT | flags,
+	}
+}
+
+// returns the chaining value of a complete subtree which isn't the root, with
+// a power of two number of chunks
+fn subtree_chaining_value(input: &[u8], chunk_counter: u64, key: &[u32; 8], flags: u32, parallel: bool) -> [u32; 8] {
+	if input.len() == CHUNK_LEN {
+		let mut chunk_state = ChunkState::new(key, chunk_counter, flags);
+		chunk_state.update(input);
+		return chunk_state.output().chaining_value();
+	}
+
+	let (left, right) = input.split_at(input.len() / 2);
+	let right_counter = chunk_counter + (left.len() / CHUNK_LEN) as u64;
+
+	let (left_cv, right_cv) = join(
+		|| subtree_chaining_value(left, chunk_counter, key, flags, parallel),
+		|| subtree_chaining_value(right, right_counter, key, flags, parallel),
+		parallel,
+	);
+
+	parent_output(&left_cv, &right_cv, key, flags).chaining_value()
+}
+
+#[cfg(feature = "rayon")]
+fn join<A, B>(a: A, b: B, parallel: bool) -> ([u32; 8], [u32; 8])
+where
+	A: FnOnce() -> [u32; 8] + Send,
+	B: FnOnce() -> [u32; 8] + Send,
+{
+	match parallel {
+		true => rayon::join(a, b),
+		false => (a(), b()),
+	}
+}
+
+#[cfg(not(feature = "rayon"))]
+fn join<A, B>(a: A, b: B, _parallel: bool) -> ([u32; 8], [u32; 8])
+where
+	A: FnOnce() -> [u32; 8],
+	B: FnOnce() -> [u32; 8],
+{
+	(a(), b())
+}
+
+/// Returns the BLAKE3 hash of the byte slice passed to it.
+pub fn hash(input: &[u8]) -> [u8; OUT_LEN] {
+	let mut hasher = Hasher::new();
+	hasher.add_bytes(input);
+	hasher.out()
+}
+
+/// Returns the keyed BLAKE3 hash of the byte slice passed to it, which is a
+/// MAC with the given `key`.
+pub fn keyed_hash(key: &[u8; KEY_LEN], input: &[u8]) -> [u8; OUT_LEN] {
+	let mut hasher = Hasher::new_keyed(key);
+	hasher.add_bytes(input);
+	hasher.out()
+}
+
+/// Derives a 32-byte key from `key_material`, in the given `context`. The
+/// context should be hardcoded, globally unique and application-specific,
+/// such as `"example.com 2024-01-01 session tokens v1"`, and never contain
+/// variable or secret data, which belongs in `key_material` instead.
+pub fn derive_key(context: &str, key_material: &[u8]) -> [u8; OUT_LEN] {
+	let mut hasher = Hasher::new_derive_key(context);
+	hasher.add_bytes(key_material);
+	hasher.out()
+}
+
+/// A BLAKE3 hasher, in any of its three modes. Input is passed in piece by
+/// piece with [`Hasher::add_bytes`].
+#[derive(Clone)]
+pub struct Hasher {
+	key: [u32; 8],
+	flags: u32,
+	chunk_state: ChunkState,
+	// the chaining values of complete subtrees, which are merged lazily so
+	// that the last one is never merged before it's known not to be the root
+	cv_stack: [[u32; 8]; 54],
+	cv_stack_len: usize,
+}
+
+impl Hasher {
+	fn with_key_and_flags(key: [u32; 8], flags: u32) -> Self {
+		Self {
+			key,
+			flags,
+			chunk_state: ChunkState::new(&key, 0, flags),
+			cv_stack: [[0; 8]; 54],
+			cv_stack_len: 0,
+		}
+	}
+
+	/// Creates a new hasher for the plain hash mode.
+	pub fn new() -> Self {
+		Self::with_key_and_flags(IV, 0)
+	}
+
+	/// Creates a new hasher for the keyed hash mode.
+	pub fn new_keyed(key: &[u8; KEY_LEN]) -> Self {
+		Self::with_key_and_flags(words_from_le_bytes(key), KEYED_HASH)
+	}
+
+	/// Creates a new hasher for the key derivation mode, in the given
+	/// `context`. See [`derive_key`] for how to choose the context.
+	pub fn new_derive_key(context: &str) -> Self {
+		let mut context_hasher = Self::with_key_and_flags(IV, DERIVE_KEY_CONTEXT);
+		context_hasher.add_bytes(context.as_bytes());
+		let context_key = context_hasher.out();
+
+		Self::with_key_and_flags(words_from_le_bytes(&context_key), DERIVE_KEY_MATERIAL)
+	}
+
+	fn push_chaining_value(&mut self, chaining_value: [u32; 8], chunk_counter: u64) {
+		self.merge_cv_stack(chunk_counter);
+		self.cv_stack[self.cv_stack_len] = chaining_value;
+		self.cv_stack_len += 1;
+	}
+
+	// after `total_chunks` chunks, each set bit of the count is a complete
+	// subtree, so any more chaining values on the stack can be merged
+	fn merge_cv_stack(&mut self, total_chunks: u64) {
+		while self.cv_stack_len > total_chunks.count_ones() as usize {
+			let right = self.cv_stack[self.cv_stack_len - 1];
+			let left = self.cv_stack[self.cv_stack_len - 2];
+			self.cv_stack_len -= 1;
+			self.cv_stack[self.cv_stack_len - 1] = parent_output(&left, &right, &self.key, self.flags).chaining_value();
+		}
+	}
+
+	fn update(&mut self, mut input: &[u8], parallel: bool) {
+		if self.chunk_state.len() > 0 {
+			let len = usize::min(CHUNK_LEN - self.chunk_state.len(), input.len());
+			self.chunk_state.update(&input[.. len]);
+			input = &input[len ..];
+
+			if input.len() == 0 {
+				return;
+			}
+
+			// the chunk is full, and more input follows it
+			let chunk_counter = self.chunk_state.chunk_counter;
+			self.push_chaining_value(self.chunk_state.output().chaining_value(), chunk_counter);
+			self.chunk_state = ChunkState::new(&self.key, chunk_counter + 1, self.flags);
+		}
+
+		// hash whole subtrees at once, as large as their alignment allows, and
+		// always leave some input for the chunk state, so that the root is
+		// never part of a subtree
+		while input.len() > CHUNK_LEN {
+			let chunk_counter = self.chunk_state.chunk_counter;
+			let max_chunks = ((input.len() - 1) / CHUNK_LEN) as u64;
+			let mut subtree_chunks = 1 << (63 - max_chunks.leading_zeros());
+
+			while chunk_counter & (subtree_chunks - 1) != 0 {
+				subtree_chunks /= 2;
+			}
+
+			let subtree_len = subtree_chunks as usize * CHUNK_LEN;
+			let chaining_value = subtree_chaining_value(&input[.. subtree_len], chunk_counter, &self.key, self.flags, parallel);
+
+			self.push_chaining_value(chaining_value, chunk_counter);
+			self.chunk_state = ChunkState::new(&self.key, chunk_counter + subtree_chunks, self.flags);
+			input = &input[subtree_len ..];
+		}
+
+		if input.len() > 0 {
+			self.merge_cv_stack(self.chunk_state.chunk_counter);
+			self.chunk_state.update(input);
+		}
+	}
+
+	/// Adds bytes to the input being hashed.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.update(bytes, false);
+	}
+
+	/// Adds bytes to the input being hashed, hashing large inputs on multiple
+	/// threads with rayon. This is only faster for inputs of at least tens of
+	/// kilobytes, and gives the same result as [`Hasher::add_bytes`].
+	#[cfg(feature = "rayon")]
+	pub fn add_bytes_rayon(&mut self, bytes: &[u8]) {
+		self.update(bytes, true);
+	}
+
+	fn root_output(&self) -> Output {
+		let mut output = self.chunk_state.output();
+
+		for i in (0 .. self.cv_stack_len).rev() {
+			output = parent_output(&self.cv_stack[i], &output.chaining_value(), &self.key, self.flags);
+		}
+
+		output
+	}
+
+	/// Finishes hashing, returning a reader that output of any length can be
+	/// read from incrementally.
+	pub fn into_reader(self) -> OutputReader {
+		OutputReader {output: self.root_output(), position: 0}
+	}
+
+	/// Finishes hashing, writing as many bytes of output as fit in `out`.
+	pub fn out_into(self, out: &mut [u8]) {
+		self.into_reader().read(out);
+	}
+
+	/// Finishes hashing, returning the default 32 bytes of output.
+	pub fn out(self) -> [u8; OUT_LEN] {
+		let mut out = [0; OUT_LEN];
+		self.out_into(&mut out);
+		out
+	}
+}
+
+impl Default for Hasher {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The output of a [`Hasher`], which can be read in pieces. Shorter outputs
+/// are prefixes of longer ones.
+#[derive(Clone)]
+pub struct OutputReader {
+	output: Output,
+	position: u64,
+}
+
+impl OutputReader {
+	/// Fills `out` with the next bytes of output.
+	pub fn read(&mut self, mut out: &mut [u8]) {
+		while out.len() > 0 {
+			let block = self.output.root_output_block(self.position / 64);
+			let offset = (self.position % 64) as usize;
+			let len = usize::min(64 - offset, out.len());
+
+			out[.. len].copy_from_slice(&block[offset .. offset + len]);
+			out = &mut out[len ..];
+			self.position += len as u64;
+		}
+	}
+
+	/// Returns the position in the output that the next byte will be read
+	/// from.
+	pub fn position(&self) -> u64 {
+		self.position
+	}
+
+	/// Moves to `position` in the output, so that output can be read in any
+	/// order.
+	pub fn set_position(&mut self, position: u64) {
+		self.position = position;
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[cfg(test)]
+fn test_input() -> [u8; 102400] {
+	let mut input = [0; 102400];
+
+	for i in 0 .. input.len() {
+		input[i] = (i % 251) as u8;
+	}
+
+	input
+}
+
+// from the official BLAKE3 test vectors, truncated to the default output length
+#[test]
+fn test_vectors() {
+	let key = b"whats the Elvish word for friend";
+	let context = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+	let expected = [
+		(0, "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262", "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26", "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d"),
+		(1, "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213", "6d7878dfff2f485635d39013278ae14f1454b8c0a3a2d34bc1ab38228a80c95b", "b3e2e340a117a499c6cf2398a19ee0d29cca2bb7404c73063382693bf66cb06c"),
+		(1023, "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11", "c951ecdf03288d0fcc96ee3413563d8a6d3589547f2c2fb36d9786470f1b9d6e", "74a16c1c3d44368a86e1ca6df64be6a2f64cce8f09220787450722d85725dea5"),
+		(1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7", "75c46f6f3d9eb4f55ecaaee480db732e6c2105546f1e675003687c31719c7ba4", "7356cd7720d5b66b6d0697eb3177d9f8d73a4a5c5e968896eb6a689684302706"),
+		(1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444", "357dc55de0c7e382c900fd6e320acc04146be01db6a8ce7210b7189bd664ea69", "effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb"),
+		(2048, "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a", "879cf1fa2ea0e79126cb1063617a05b6ad9d0b696d0d757cf053439f60a99dd1", "7b2945cb4fef70885cc5d78a87bf6f6207dd901ff239201351ffac04e1088a23"),
+		(3073, "7124b49501012f81cc7f11ca069ec9226cecb8a2c850cfe644e327d22d3e1cd3", "68dede9bef00ba89e43f31a6825f4cf433389fedae75c04ee9f0cf16a427c95a", "72613c9ec9ff7e40f8f5c173784c532ad852e827dba2bf85b2ab4b76f7079081"),
+		(7169, "a003fc7a51754a9b3c7fae0367ab3d782dccf28855a03d435f8cfe74605e7817", "ed9b1a922c046fdb3d423ae34e143b05ca1bf28b710432857bf738bcedbfa511", "554b0a5efea9ef183f2f9b931b7497995d9eb26f5c5c6dad2b97d62fc5ac31d9"),
+		(8193, "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b", "954a2a75420c8d6547e3ba5b98d963e6fa6491addc8c023189cc519821b4a1f5", "af1e0346e389b17c23200270a64aa4e1ead98c61695d917de7d5b00491c9b0f1"),
+		(31744, "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47", "efa53b389ab67c593dba624d898d0f7353ab99e4ac9d42302ee64cbf9939a419", "39772aef80e0ebe60596361e45b061e8f417429d529171b6764468c22928e28e"),
+		(102400, "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085", "1c35d1a5811083fd7119f5d5d1ba027b4d01c0c6c49fb6ff2cf75393ea5db4a7", "4652cff7a3f385a6103b5c260fc1593e13c778dbe608efb092fe7ee69df6e9c6"),
+	];
+
+	let input = test_input();
+
+	for (len, hash_out, keyed_hash_out, derive_key_out) in expected {
+		assert_eq!(hash(&input[.. len]), hex(hash_out));
+		assert_eq!(keyed_hash(key, &input[.. len]), hex(keyed_hash_out));
+		assert_eq!(derive_key(context, &input[.. len]), hex(derive_key_out));
+	}
+}
+
+// from the official BLAKE3 test vectors
+#[test]
+fn test_extended_output() {
+	let input = test_input();
+
+	let expected: [u8; 131] = hex(concat!(
+		"bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085e01c59dab908c04c3342b816941a26d6",
+		"9c2605ebee5ec5291cc55e15b76146e6745f0601156c3596cb75065a9c57f35585a52e1ac70f69131c23d611ce11ee4a",
+		"b1ec2c009012d236648e77be9295dd0426f29b764d65de58eb7d01dd42248204f45f8e",
+	));
+
+	let mut hasher = Hasher::new();
+	hasher.add_bytes(&input);
+
+	let mut out = [0; 131];
+	hasher.clone().out_into(&mut out);
+	assert_eq!(out, expected);
+
+	// reading in pieces, and out of order
+	let mut reader = hasher.into_reader();
+	let mut out = [0; 131];
+	reader.set_position(100);
+	reader.read(&mut out[100 ..]);
+	assert_eq!(reader.position(), 131);
+
+	reader.set_position(0);
+	reader.read(&mut out[.. 1]);
+	reader.read(&mut out[1 .. 100]);
+	assert_eq!(out, expected);
+}
+
+#[test]
+fn test_incremental_matches_oneshot() {
+	let input = test_input();
+	let input = &input[.. 9000];
+
+	for split in [0, 1, 64, 1023, 1024, 1025, 2048, 4096, 5000, 9000] {
+		let mut hasher = Hasher::new();
+		hasher.add_bytes(&input[.. split]);
+		hasher.add_bytes(&input[split ..]);
+		assert_eq!(hasher.out(), hash(input));
+	}
+
+	let mut hasher = Hasher::new();
+
+	for chunk in input.chunks(100) {
+		hasher.add_bytes(chunk);
+	}
+
+	assert_eq!(hasher.out(), hash(input));
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn test_rayon() {
+	let input = test_input();
+
+	for split in [0, 1, 3072, 50000] {
+		let mut hasher = Hasher::new();
+		hasher.add_bytes_rayon(&input[.. split]);
+		hasher.add_bytes_rayon(&input[split ..]);
+		assert_eq!(hasher.out(), hash(&input));
+	}
+}
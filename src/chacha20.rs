@@ -1,12 +1,22 @@
 //! Implemented according to [IETF RFC 8439](https://datatracker.ietf.org/doc/html/rfc8439).
 //! ChaCha20 is typically used as a symmetric stream cipher with a 256-bit key
-//! and a 96-bit nonce. See the [`ChaCha20`] docs for usage.
+//! and a 96-bit nonce. See the [`ChaCha20`] docs for usage. ChaCha12, with
+//! 12 rounds instead of 20, is also available for constructions such as
+//! Adiantum that trade some of the security margin for speed.
+//!
+//! [`ChaCha20::new_legacy`] gives Bernstein's original layout instead, with a
+//! 64-bit block counter and a 64-bit nonce, which some older protocols use.
+//! Its stream is long enough that it never runs out in practice, unlike the
+//! 256 GiB stream of the RFC 8439 layout.
 
 use core::convert::TryInto;
 
 #[cfg(feature = "std")]
 use std::io::{self, Read, Seek, SeekFrom};
 
+use crate::zeroize::zeroize;
+use crate::Error;
+
 fn quarter_round(a: &mut u32, b: &mut u32, c: &mut u32, d: &mut u32) {
 	*a = a.wrapping_add(*b);
 	*d ^= *a;
@@ -44,10 +54,10 @@ fn double_round(state: &mut [u32; 16]) {
 	quarter_round(s3, s4, s9, se);
 }
 
-fn process_state(input: &[u32; 16], output: &mut [u32; 16]) {
+fn process_state(input: &[u32; 16], output: &mut [u32; 16], double_rounds: u8) {
 	output.copy_from_slice(input);
 
-	for _ in 0 .. 10 {
+	for _ in 0 .. double_rounds {
 		double_round(output);
 	}
 
@@ -64,10 +74,22 @@ pub struct ChaCha20 {
 	inner_state: [u32; 16],
 	outer_state: [u32; 16],
 	position_in_block: u8,
+	double_rounds: u8,
+	// whether the block counter takes up two words of the state, as in the
+	// original layout, rather than one
+	long_counter: bool,
 }
 
 const MAGIC: [&[u8; 4]; 4] = [b"expa", b"nd 3", b"2-by", b"te k"];
 
+// the length of the stream in bytes, 2 ** 32 blocks of 64 bytes
+const STREAM_LEN: u64 = 1 << 38;
+
+// the length of the stream with a 64-bit counter, which is 2 ** 70 bytes, but
+// positions are u64s, so only the bytes before the last possible position
+// can be reached
+const LEGACY_STREAM_LEN: u64 = u64::MAX;
+
 const K0: u32 = u32::from_le_bytes(*MAGIC[0]);
 const K1: u32 = u32::from_le_bytes(*MAGIC[1]);
 const K2: u32 = u32::from_le_bytes(*MAGIC[2]);
@@ -77,6 +99,29 @@ impl ChaCha20 {
 	/// Initializes a new ChaCha20 stream at position 0.
 	/// The nonce here *must not* be reused to encrypt different messages.
 	pub fn new(key: [u8; 32], nonce: [u8; 12]) -> Self {
+		Self::with_double_rounds(key, &nonce, 10)
+	}
+
+	/// Initializes a new stream at position 0 with the original ChaCha20
+	/// layout from Bernstein's reference implementation, which has a 64-bit
+	/// block counter and a 64-bit nonce, as used by e.g. the
+	/// `chacha20-poly1305@openssh.com` cipher of OpenSSH. The stream is
+	/// 2<sup>64</sup> - 1 bytes long, the most a position can address.
+	/// The nonce here *must not* be reused to encrypt different messages.
+	pub fn new_legacy(key: [u8; 32], nonce: [u8; 8]) -> Self {
+		Self::with_double_rounds(key, &nonce, 10)
+	}
+
+	/// Initializes a new ChaCha12 stream at position 0, which works like
+	/// ChaCha20 with 12 rounds instead of 20.
+	/// The nonce here *must not* be reused to encrypt different messages.
+	pub fn new_chacha12(key: [u8; 32], nonce: [u8; 12]) -> Self {
+		Self::with_double_rounds(key, &nonce, 6)
+	}
+
+	// the nonce takes up the last three or two words of the state, and the
+	// counter the rest
+	fn with_double_rounds(key: [u8; 32], nonce: &[u8], double_rounds: u8) -> Self {
 		let mut inner_state = [
 			K0, K1, K2, K3,
 			0, 0, 0, 0,
@@ -89,29 +134,63 @@ impl ChaCha20 {
 			inner_state[4 + i] = u32::from_le_bytes(bytes);
 		}
 
-		for i in 0 .. 3 {
-			let bytes: [u8; 4] = nonce[4 * i .. 4 * (i + 1)].try_into().unwrap();
-			inner_state[13 + i] = u32::from_le_bytes(bytes);
+		let nonce_start = 16 - nonce.len() / 4;
+
+		for (i, bytes) in nonce.chunks_exact(4).enumerate() {
+			inner_state[nonce_start + i] = u32::from_le_bytes(bytes.try_into().unwrap());
 		}
 
 		let mut outer_state = [0; 16];
-		process_state(&inner_state, &mut outer_state);
+		process_state(&inner_state, &mut outer_state, double_rounds);
 
 		Self {
 			inner_state,
 			outer_state,
 			position_in_block: 0,
+			double_rounds,
+			long_counter: nonce_start == 14,
+		}
+	}
+
+	fn keystream_len(&self) -> u64 {
+		match self.long_counter {
+			true => LEGACY_STREAM_LEN,
+			false => STREAM_LEN,
+		}
+	}
+
+	fn block_counter(&self) -> u64 {
+		match self.long_counter {
+			true => self.inner_state[12] as u64 | (self.inner_state[13] as u64) << 32,
+			false => self.inner_state[12] as u64,
+		}
+	}
+
+	// the counter must fit in the counter words
+	fn set_block_counter(&mut self, block: u64) {
+		self.inner_state[12] = block as u32;
+
+		if self.long_counter {
+			self.inner_state[13] = (block >> 32) as u32;
 		}
 	}
 
+	// moves on to the next block, which the caller checks exists, without
+	// changing the position in it
+	fn next_block(&mut self) {
+		self.set_block_counter(self.block_counter() + 1);
+		process_state(&self.inner_state, &mut self.outer_state, self.double_rounds);
+	}
+
 	/// Encrypts or decrypts data using bytes drawn from the current location of the stream.
 	/// Since ChaCha20 is a stream cipher using xor, the same function can be used
 	/// for both encryption and decryption of data.
-
+	///
 	/// # Panics
-	/// * Panics if the ChaCha20 instance runs out of bytes to encrypt/decrypt with.
-	///   In this case, the buffer's contents are unspecified.
-
+	/// * Panics if the ChaCha20 instance runs out of bytes to encrypt/decrypt with,
+	///   leaving the buffer unchanged. See [`ChaCha20::try_crypt`] for a version
+	///   that returns an error instead.
+	///
 	/// # Examples
 	/// ```
 	/// # use libkrypton::chacha20::ChaCha20;
@@ -133,7 +212,21 @@ impl ChaCha20 {
 	///
 	/// assert!(data == *b"hello");
 	/// ```
-	pub fn crypt(&mut self, mut data: &mut [u8]) {
+	pub fn crypt(&mut self, data: &mut [u8]) {
+		self.try_crypt(data).expect("chacha20 keystream exhausted");
+	}
+
+	/// Like [`ChaCha20::crypt`], but returns [`Error::KeystreamExhausted`]
+	/// instead of panicking if there isn't enough of the stream left for all
+	/// of `data`, in which case neither the data nor the position of the
+	/// stream is changed.
+	pub fn try_crypt(&mut self, mut data: &mut [u8]) -> Result<(), Error> {
+		let remaining = self.keystream_len() - self.get_pos();
+
+		if data.len() as u64 > remaining {
+			return Err(Error::KeystreamExhausted);
+		}
+
 		let mut buf = [0; 1024];
 
 		while data.len() != 0 {
@@ -149,6 +242,8 @@ impl ChaCha20 {
 
 			data = &mut data[consuming ..];
 		}
+
+		Ok(())
 	}
 
 	/// This is provided as an alternative to the [`std::io::Seek`] implementation.
@@ -156,23 +251,27 @@ impl ChaCha20 {
 	/// If the position is greater than the length of the stream,
 	/// it gets clamped down to the length of the stream.
 	pub fn set_pos(&mut self, pos: u64) {
-		match (pos / 64).try_into().ok() {
-			Some(block) => {
-				self.inner_state[12] = block;
-				self.position_in_block = (pos % 64) as u8;
-			}
+		let pos = pos.min(self.keystream_len());
 
-			None => {
-				self.inner_state[12] = u32::MAX;
+		// the end of the RFC 8439 stream is just past the last block, which
+		// is the end of that block rather than the start of the next
+		match pos == STREAM_LEN && !self.long_counter {
+			true => {
+				self.set_block_counter(pos / 64 - 1);
 				self.position_in_block = 64;
 			}
+
+			false => {
+				self.set_block_counter(pos / 64);
+				self.position_in_block = (pos % 64) as u8;
+			}
 		}
 
-		process_state(&self.inner_state, &mut self.outer_state);
+		process_state(&self.inner_state, &mut self.outer_state, self.double_rounds);
 	}
 
 	pub fn get_pos(&self) -> u64 {
-		self.inner_state[12] as u64 * 64 + self.position_in_block as u64
+		self.block_counter() * 64 + self.position_in_block as u64
 	}
 
 	/// Function used as an alternative to the [`std::io::Read`] implementation,
@@ -183,6 +282,12 @@ impl ChaCha20 {
 	/// the number of bytes read into the buffer and will return 0 if it cannot
 	/// provide any more bytes.
 	pub fn read_infallible(&mut self, mut buf: &mut [u8]) -> usize {
+		let remaining = self.keystream_len() - self.get_pos();
+
+		if buf.len() as u64 > remaining {
+			buf = &mut buf[.. remaining as usize];
+		}
+
 		let mut written = 0;
 
 		// write until we get to the end of the block
@@ -197,12 +302,7 @@ impl ChaCha20 {
 
 		// write whole 64-byte chunks while we can
 		while buf.len() >= 64 {
-			if self.inner_state[12] == u32::MAX {
-				return written;
-			}
-
-			self.inner_state[12] += 1;
-			process_state(&self.inner_state, &mut self.outer_state);
+			self.next_block();
 
 			for i in 0 .. 16 {
 				let [a, b, c, d] = self.outer_state[i].to_le_bytes();
@@ -232,19 +332,81 @@ impl ChaCha20 {
 	}
 }
 
+impl Drop for ChaCha20 {
+	fn drop(&mut self) {
+		zeroize(&mut self.inner_state);
+		zeroize(&mut self.outer_state);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for ChaCha20 {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.inner_state);
+		zeroize(&mut self.outer_state);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for ChaCha20 {}
+
+/// Derives a subkey from a `key` and a 128-bit `nonce` using HChaCha20, as
+/// specified in the [XChaCha20 draft](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha).
+/// This is what XChaCha20 uses to extend the nonce of ChaCha20 to 192 bits.
+pub fn hchacha20(key: [u8; 32], nonce: [u8; 16]) -> [u8; 32] {
+	hchacha(key, nonce, 10)
+}
+
+/// Derives a subkey from a `key` and a 128-bit `nonce` using HChaCha12, the
+/// variant of [`hchacha20`] with 12 rounds, as used by XChaCha12 in Adiantum.
+pub fn hchacha12(key: [u8; 32], nonce: [u8; 16]) -> [u8; 32] {
+	hchacha(key, nonce, 6)
+}
+
+fn hchacha(key: [u8; 32], nonce: [u8; 16], double_rounds: u8) -> [u8; 32] {
+	let mut state = [
+		K0, K1, K2, K3,
+		0, 0, 0, 0,
+		0, 0, 0, 0,
+		0, 0, 0, 0,
+	];
+
+	for i in 0 .. 8 {
+		let bytes: [u8; 4] = key[4 * i .. 4 * (i + 1)].try_into().unwrap();
+		state[4 + i] = u32::from_le_bytes(bytes);
+	}
+
+	for i in 0 .. 4 {
+		let bytes: [u8; 4] = nonce[4 * i .. 4 * (i + 1)].try_into().unwrap();
+		state[12 + i] = u32::from_le_bytes(bytes);
+	}
+
+	for _ in 0 .. double_rounds {
+		double_round(&mut state);
+	}
+
+	let mut out = [0; 32];
+
+	for i in 0 .. 4 {
+		out[4 * i .. 4 * (i + 1)].copy_from_slice(&state[i].to_le_bytes());
+		out[16 + 4 * i .. 16 + 4 * (i + 1)].copy_from_slice(&state[12 + i].to_le_bytes());
+	}
+
+	zeroize(&mut state);
+	out
+}
+
 impl Iterator for ChaCha20 {
 	type Item = u8;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		if self.position_in_block == 64 {
-			if self.inner_state[12] == u32::MAX {
-				return None;
-			}
+		if self.get_pos() == self.keystream_len() {
+			return None;
+		}
 
-			self.inner_state[12] += 1;
+		if self.position_in_block == 64 {
+			self.next_block();
 			self.position_in_block = 0;
-
-			process_state(&self.inner_state, &mut self.outer_state);
 		}
 
 		let position = usize::from(self.position_in_block);
@@ -266,7 +428,7 @@ impl Read for ChaCha20 {
 fn offset_u64(a: u64, b: i64) -> u64 {
 	match b {
 		0 => a,
-		1 ..= i64::MAX => a + b as u64,
+		1 ..= i64::MAX => a.saturating_add(b as u64),
 		i64::MIN ..= -1 => a - ((-b) as u64),
 	}
 }
@@ -287,7 +449,7 @@ impl Seek for ChaCha20 {
 			}
 
 			SeekFrom::End(diff) => {
-				let end = 64 * (u32::MAX as u64 + 1);
+				let end = self.keystream_len();
 				self.set_pos(offset_u64(end, diff));
 				Ok(self.get_pos())
 			}
@@ -353,6 +515,40 @@ fn rfc8439_quarter_round_test_vector() {
 	assert_eq!(d, 0x5881c4bb);
 }
 
+// checked against a python transcription of the ChaCha block function, which
+// matches the ChaCha20 class of python's cryptography package at 20 rounds
+#[test]
+fn chacha12_and_hchacha12() {
+	let key = [
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+		0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+		0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+	];
+
+	let nonce = [
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+	];
+
+	assert_eq!(hchacha12(key, nonce), [
+		0x9f, 0x54, 0x5c, 0x95, 0x11, 0x41, 0x4b, 0x94,
+		0x34, 0x8e, 0xcc, 0x20, 0x0b, 0xb6, 0x9d, 0xd8,
+		0x1a, 0xfa, 0x38, 0xc5, 0xed, 0xb9, 0xdd, 0x97,
+		0x19, 0x37, 0x2f, 0xb0, 0x45, 0xcb, 0x9d, 0x0d,
+	]);
+
+	let mut block = [0; 32];
+	ChaCha20::new_chacha12(key, [0; 12]).crypt(&mut block);
+
+	assert_eq!(block, [
+		0xf2, 0x31, 0xf9, 0xff, 0xd1, 0x7a, 0xc6, 0x5e,
+		0x44, 0x05, 0xf3, 0x25, 0xd7, 0xe9, 0x40, 0xaa,
+		0x49, 0x13, 0x60, 0x1f, 0xc2, 0xbe, 0x46, 0xbc,
+		0xe9, 0xc3, 0xca, 0xc3, 0xd9, 0x1a, 0x1a, 0x36,
+	]);
+}
+
 #[test]
 #[cfg(feature = "std")]
 fn check_read_vs_iterator() {
@@ -413,3 +609,86 @@ fn check_seek_to_end_and_read() {
 
 	assert!(stream.read(&mut [0; 64]).unwrap() == 7);
 }
+
+#[test]
+fn check_try_crypt_at_end() {
+	let mut stream = ChaCha20::new([0; 32], [0; 12]);
+	stream.set_pos(STREAM_LEN - 7);
+
+	let mut data = [0; 8];
+	assert_eq!(stream.try_crypt(&mut data), Err(Error::KeystreamExhausted));
+	assert_eq!(data, [0; 8]);
+	assert_eq!(stream.get_pos(), STREAM_LEN - 7);
+
+	assert_eq!(stream.try_crypt(&mut data[.. 7]), Ok(()));
+	assert_ne!(data[.. 7], [0; 7]);
+	assert_eq!(stream.try_crypt(&mut []), Ok(()));
+	assert_eq!(stream.try_crypt(&mut [0]), Err(Error::KeystreamExhausted));
+}
+
+// checked against the ChaCha20 class of python's cryptography package, whose
+// 16-byte nonce is the 64-bit counter followed by the 64-bit nonce here
+#[test]
+fn legacy_test_vector() {
+	let mut key = [0; 32];
+	let mut nonce = [0; 8];
+
+	for i in 0 .. 32 {
+		key[i] = i as u8;
+	}
+
+	for i in 0 .. 8 {
+		nonce[i] = 0x40 + i as u8;
+	}
+
+	let mut expected = [0; 64];
+
+	let mut block = [0; 32];
+	ChaCha20::new_legacy(key, nonce).crypt(&mut block);
+	crate::encoding::decode_hex("db6446e45a5708683c5eaa79221070e72158caa830dbd72aa22aa694cebff358", &mut expected[.. 32]).unwrap();
+	assert_eq!(block, expected[.. 32]);
+
+	// the counter carries into the second word, where the RFC 8439 layout's
+	// stream would end
+	let mut stream = ChaCha20::new_legacy(key, nonce);
+	stream.set_pos(STREAM_LEN - 32);
+
+	let mut block = [0; 64];
+	stream.crypt(&mut block);
+	crate::encoding::decode_hex(
+		"06937ac8f657e88e5ea479ab6fdf8b467e88cc8212c1de51fec1ca986fd82f7b\
+		bcac3b49ac38f3e6e3fef2116bf0c95c9dc8efd54aa997552e06fd6791871ce2",
+		&mut expected,
+	).unwrap();
+	assert_eq!(block, expected);
+	assert_eq!(stream.get_pos(), STREAM_LEN + 32);
+
+	// the same bytes come from the iterator
+	stream.set_pos(STREAM_LEN - 32);
+	assert!(Iterator::take(stream, 64).eq(expected));
+}
+
+#[test]
+fn legacy_try_crypt_at_end() {
+	let mut stream = ChaCha20::new_legacy([0; 32], [0; 8]);
+	stream.set_pos(u64::MAX - 7);
+
+	let mut data = [0; 8];
+	assert_eq!(stream.try_crypt(&mut data), Err(Error::KeystreamExhausted));
+	assert_eq!(stream.try_crypt(&mut data[.. 7]), Ok(()));
+	assert_eq!(stream.get_pos(), u64::MAX);
+	assert_eq!(stream.next(), None);
+
+	stream.set_pos(u64::MAX);
+	assert_eq!(stream.get_pos(), u64::MAX);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn legacy_seek_to_end_and_read() {
+	let mut stream = ChaCha20::new_legacy([0; 32], [0; 8]);
+
+	assert_eq!(stream.seek(SeekFrom::End(-7)).unwrap(), u64::MAX - 7);
+	assert_eq!(stream.read(&mut [0; 64]).unwrap(), 7);
+	assert_eq!(stream.seek(SeekFrom::Current(1)).unwrap(), u64::MAX);
+}
@@ -0,0 +1,259 @@
+//! Implemented according to [IETF RFC 8439](https://datatracker.ietf.org/doc/html/rfc8439)
+//! and the [XChaCha20 draft](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha).
+//! ChaCha20-Poly1305 is an authenticated encryption scheme with associated
+//! data (AEAD): it encrypts a message and authenticates both the ciphertext
+//! and some unencrypted associated data, such as a header.
+//!
+//! ChaCha20-Poly1305 takes a 96-bit nonce, which is too short to be chosen at
+//! random. XChaCha20-Poly1305 takes a 192-bit nonce instead, which can safely
+//! be generated by a secure random source for every message.
+//!
+//! Messages are encrypted in place, with the tag returned separately.
+
+use core::convert::TryInto;
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20::{hchacha20, ChaCha20};
+use crate::poly1305::Poly1305;
+use crate::zeroize::zeroize;
+
+/// An error returned when decrypting a message fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The tag is not valid for the ciphertext and associated data, meaning
+	/// the message was corrupted, forged, or encrypted with a different key.
+	InvalidTag,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidTag => "invalid authentication tag",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// returns the keystream positioned after the block used for the poly1305 key,
+// along with the poly1305 instance
+fn init(key: [u8; 32], nonce: [u8; 12]) -> (ChaCha20, Poly1305) {
+	let mut stream = ChaCha20::new(key, nonce);
+
+	let mut poly1305_key = [0; 32];
+	stream.read_infallible(&mut poly1305_key);
+	stream.set_pos(64);
+
+	let poly1305 = Poly1305::new(
+		poly1305_key[0 .. 16].try_into().unwrap(),
+		poly1305_key[16 .. 32].try_into().unwrap(),
+	);
+
+	zeroize(&mut poly1305_key);
+
+	(stream, poly1305)
+}
+
+fn authenticate(mut poly1305: Poly1305, associated_data: &[u8], ciphertext: &[u8]) -> Poly1305 {
+	poly1305.add_bytes(associated_data);
+	poly1305.pad_to_block();
+	poly1305.add_bytes(ciphertext);
+	poly1305.pad_to_block();
+	poly1305.add_bytes(&(associated_data.len() as u64).to_le_bytes());
+	poly1305.add_bytes(&(ciphertext.len() as u64).to_le_bytes());
+	poly1305
+}
+
+/// Encrypts `buffer` in place with ChaCha20-Poly1305, returning the tag that
+/// authenticates it along with `associated_data`. The `nonce` *must not* be
+/// reused to encrypt different messages with the same key.
+///
+/// # Panics
+/// * Panics if `buffer` is longer than the ChaCha20 stream, about 256 GiB.
+pub fn chacha20poly1305_encrypt(
+	key: [u8; 32],
+	nonce: [u8; 12],
+	associated_data: &[u8],
+	buffer: &mut [u8],
+) -> [u8; 16] {
+	let (mut stream, poly1305) = init(key, nonce);
+	stream.crypt(buffer);
+
+	authenticate(poly1305, associated_data, buffer).out()
+}
+
+/// Checks the `tag` of a message encrypted with ChaCha20-Poly1305, then
+/// decrypts `buffer` in place. If the tag is invalid, returns
+/// [`Error::InvalidTag`] and leaves `buffer` unchanged.
+pub fn chacha20poly1305_decrypt(
+	key: [u8; 32],
+	nonce: [u8; 12],
+	associated_data: &[u8],
+	buffer: &mut [u8],
+	tag: [u8; 16],
+) -> Result<(), Error> {
+	let (mut stream, poly1305) = init(key, nonce);
+
+	if !authenticate(poly1305, associated_data, buffer).verify(tag) {
+		return Err(Error::InvalidTag);
+	}
+
+	stream.crypt(buffer);
+
+	Ok(())
+}
+
+fn xchacha20_subkey(key: [u8; 32], nonce: &[u8; 24]) -> ([u8; 32], [u8; 12]) {
+	let subkey = hchacha20(key, nonce[0 .. 16].try_into().unwrap());
+
+	let mut subnonce = [0; 12];
+	subnonce[4 .. 12].copy_from_slice(&nonce[16 .. 24]);
+
+	(subkey, subnonce)
+}
+
+/// Encrypts `buffer` in place with XChaCha20-Poly1305, returning the tag that
+/// authenticates it along with `associated_data`. The `nonce` *must not* be
+/// reused to encrypt different messages with the same key, but it's long
+/// enough to be generated at random.
+///
+/// # Panics
+/// * Panics if `buffer` is longer than the ChaCha20 stream, about 256 GiB.
+pub fn xchacha20poly1305_encrypt(
+	key: [u8; 32],
+	nonce: [u8; 24],
+	associated_data: &[u8],
+	buffer: &mut [u8],
+) -> [u8; 16] {
+	let (mut subkey, subnonce) = xchacha20_subkey(key, &nonce);
+	let tag = chacha20poly1305_encrypt(subkey, subnonce, associated_data, buffer);
+	zeroize(&mut subkey);
+
+	tag
+}
+
+/// Encrypts `buffer` in place with XChaCha20-Poly1305 like
+/// [`xchacha20poly1305_encrypt`], with a nonce generated by `rng`, which must
+/// be a cryptographically secure random number generator. Returns the nonce,
+/// which has to be sent along with the ciphertext, and the tag.
+///
+/// # Panics
+/// * Panics if `buffer` is longer than the ChaCha20 stream, about 256 GiB.
+#[cfg(feature = "rand_core")]
+pub fn xchacha20poly1305_encrypt_with_rng<R: RngCore + CryptoRng>(
+	key: [u8; 32],
+	rng: &mut R,
+	associated_data: &[u8],
+	buffer: &mut [u8],
+) -> ([u8; 24], [u8; 16]) {
+	let mut nonce = [0; 24];
+	rng.fill_bytes(&mut nonce);
+
+	(nonce, xchacha20poly1305_encrypt(key, nonce, associated_data, buffer))
+}
+
+/// Checks the `tag` of a message encrypted with XChaCha20-Poly1305, then
+/// decrypts `buffer` in place. If the tag is invalid, returns
+/// [`Error::InvalidTag`] and leaves `buffer` unchanged.
+pub fn xchacha20poly1305_decrypt(
+	key: [u8; 32],
+	nonce: [u8; 24],
+	associated_data: &[u8],
+	buffer: &mut [u8],
+	tag: [u8; 16],
+) -> Result<(), Error> {
+	let (mut subkey, subnonce) = xchacha20_subkey(key, &nonce);
+	let result = chacha20poly1305_decrypt(subkey, subnonce, associated_data, buffer, tag);
+	zeroize(&mut subkey);
+
+	result
+}
+
+#[cfg(test)]
+const TEST_PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 2.8.2 of RFC 8439
+#[test]
+fn test_chacha20poly1305() {
+	let key = hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+	let nonce = hex("070000004041424344454647");
+	let associated_data: [u8; 12] = hex("50515253c0c1c2c3c4c5c6c7");
+	let ciphertext: [u8; 114] = hex(
+		"d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+		3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+		92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+		3ff4def08e4b7a9de576d26586cec64b6116",
+	);
+	let tag = hex("1ae10b594f09e26a7e902ecbd0600691");
+
+	let mut buffer: [u8; 114] = TEST_PLAINTEXT.try_into().unwrap();
+	assert_eq!(chacha20poly1305_encrypt(key, nonce, &associated_data, &mut buffer), tag);
+	assert_eq!(buffer, ciphertext);
+
+	assert_eq!(chacha20poly1305_decrypt(key, nonce, &associated_data, &mut buffer, tag), Ok(()));
+	assert_eq!(buffer[..], TEST_PLAINTEXT[..]);
+
+	let mut buffer = ciphertext;
+	assert_eq!(chacha20poly1305_decrypt(key, nonce, b"", &mut buffer, tag), Err(Error::InvalidTag));
+	assert_eq!(buffer, ciphertext);
+}
+
+// from appendix A.3.1 of the XChaCha20 draft
+#[test]
+fn test_xchacha20poly1305() {
+	let key = hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+	let nonce = hex("404142434445464748494a4b4c4d4e4f5051525354555657");
+	let associated_data: [u8; 12] = hex("50515253c0c1c2c3c4c5c6c7");
+	let ciphertext: [u8; 114] = hex(
+		"bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+		731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+		2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+		21f9664c97637da9768812f615c68b13b52e",
+	);
+	let tag = hex("c0875924c1c7987947deafd8780acf49");
+
+	let mut buffer: [u8; 114] = TEST_PLAINTEXT.try_into().unwrap();
+	assert_eq!(xchacha20poly1305_encrypt(key, nonce, &associated_data, &mut buffer), tag);
+	assert_eq!(buffer, ciphertext);
+
+	assert_eq!(xchacha20poly1305_decrypt(key, nonce, &associated_data, &mut buffer, tag), Ok(()));
+	assert_eq!(buffer[..], TEST_PLAINTEXT[..]);
+
+	let mut wrong_tag = tag;
+	wrong_tag[0] ^= 1;
+	let mut buffer = ciphertext;
+	assert_eq!(
+		xchacha20poly1305_decrypt(key, nonce, &associated_data, &mut buffer, wrong_tag),
+		Err(Error::InvalidTag),
+	);
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn test_xchacha20poly1305_with_rng() {
+	use crate::test_util::CountingRng;
+
+	let key = [0x42; 32];
+	let mut rng = CountingRng(0x40);
+
+	let mut buffer: [u8; 114] = TEST_PLAINTEXT.try_into().unwrap();
+	let (nonce, tag) = xchacha20poly1305_encrypt_with_rng(key, &mut rng, b"", &mut buffer);
+	assert_eq!(nonce, hex("404142434445464748494a4b4c4d4e4f5051525354555657"));
+
+	let mut expected: [u8; 114] = TEST_PLAINTEXT.try_into().unwrap();
+	assert_eq!(xchacha20poly1305_encrypt(key, nonce, b"", &mut expected), tag);
+	assert_eq!(buffer, expected);
+
+	// the next call gets a fresh nonce
+	let (next_nonce, _) = xchacha20poly1305_encrypt_with_rng(key, &mut rng, b"", &mut buffer);
+	assert_ne!(next_nonce, nonce);
+}
@@ -0,0 +1,250 @@
+//! A symmetric key ratchet, the KDF chain from
+//! [the Double Ratchet specification](https://signal.org/docs/specifications/doubleratchet/#symmetric-key-ratchet).
+//! Each step derives a message key and the next chain key from the current
+//! chain key with HMAC-SHA256, and the old chain key is overwritten, so a
+//! chain key that's compromised later reveals no earlier message keys.
+//!
+//! On its own, a chain suits one-way streams such as sensor telemetry, where
+//! a device and its collector share a starting key and each message is
+//! encrypted with the next message key. It's also the symmetric half of the
+//! [Double Ratchet](crate::ratchet), which adds Diffie-Hellman steps for
+//! recovering from a compromise.
+//!
+//! ```
+//! use libkrypton::chain_key::ChainKey;
+//!
+//! let mut sender = ChainKey::new([0x42; 32]);
+//! let mut receiver = ChainKey::new([0x42; 32]);
+//!
+//! let first = sender.next_message_key();
+//! let second = sender.next_message_key();
+//!
+//! // the first message was lost, so its key is skipped over
+//! let received = receiver.skip_to(second.index(), 100).unwrap();
+//! assert_eq!(received.as_bytes(), second.as_bytes());
+//! ```
+
+use core::fmt;
+
+use crate::hmac::hmac_sha256;
+use crate::zeroize::zeroize;
+
+/// An error returned by [`ChainKey::skip_to`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The chain has already moved past the index, so its message key has
+	/// been deleted.
+	IndexPassed,
+	/// The index is further ahead than the maximum allowed number of skipped
+	/// message keys.
+	TooManySkipped,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::IndexPassed => "chain key already moved past index",
+			Error::TooManySkipped => "too many skipped message keys",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// A chain key, along with the index of the next message key it gives.
+/// Zeroized when dropped.
+#[derive(Clone)]
+pub struct ChainKey {
+	key: [u8; 32],
+	index: u32,
+}
+
+impl ChainKey {
+	/// Starts a chain from a shared secret key.
+	pub fn new(key: [u8; 32]) -> Self {
+		Self::from_parts(key, 0)
+	}
+
+	/// Restores a chain from the key and index returned by
+	/// [`ChainKey::to_bytes`] and [`ChainKey::index`].
+	pub fn from_parts(key: [u8; 32], index: u32) -> Self {
+		Self {key, index}
+	}
+
+	/// Returns the current chain key, for storing the chain. It must be
+	/// stored in place of the previous one, or it keeps none of its forward
+	/// secrecy.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.key
+	}
+
+	/// Returns the index of the next message key.
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+
+	/// Returns the next message key, and advances the chain past it.
+	///
+	/// # Panics
+	/// * Panics if the chain has given out 2<sup>32</sup> - 1 message keys,
+	///   after which the index would wrap around.
+	pub fn next_message_key(&mut self) -> MessageKey {
+		assert!(self.index != u32::MAX, "chain key exhausted");
+
+		let message_key = MessageKey {
+			key: hmac_sha256(&self.key, &[0x01]),
+			index: self.index,
+		};
+
+		self.key = hmac_sha256(&self.key, &[0x02]);
+		self.index += 1;
+
+		message_key
+	}
+
+	/// Returns the message key at `index`, deleting every message key before
+	/// it that hasn't been given out yet. Messages that arrive out of order
+	/// need those keys to be kept with [`ChainKey::next_message_key`]
+	/// instead. Fails if more than `max_skip` message keys would be deleted,
+	/// which limits the work a forged index can cause.
+	///
+	/// # Panics
+	/// * Panics if `index` is `u32::MAX`.
+	pub fn skip_to(&mut self, index: u32, max_skip: u32) -> Result<MessageKey, Error> {
+		if index < self.index {
+			return Err(Error::IndexPassed);
+		}
+
+		if index - self.index > max_skip {
+			return Err(Error::TooManySkipped);
+		}
+
+		while self.index < index {
+			self.next_message_key();
+		}
+
+		Ok(self.next_message_key())
+	}
+}
+
+impl Drop for ChainKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for ChainKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for ChainKey {}
+
+// deliberately doesn't print the key
+impl fmt::Debug for ChainKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ChainKey")
+			.field("index", &self.index)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A key for a single message, such as to derive an AEAD key and nonce from.
+/// Zeroized when dropped.
+pub struct MessageKey {
+	key: [u8; 32],
+	index: u32,
+}
+
+impl MessageKey {
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.key
+	}
+
+	/// Returns the index of the message key in its chain, which is sent
+	/// along with the message so the receiver can find the same key.
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+}
+
+impl Drop for MessageKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for MessageKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for MessageKey {}
+
+// deliberately doesn't print the key
+impl fmt::Debug for MessageKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("MessageKey")
+			.field("index", &self.index)
+			.finish_non_exhaustive()
+	}
+}
+
+#[test]
+fn test_known_answer() {
+	let mut chain = ChainKey::new([0x42; 32]);
+
+	let first = chain.next_message_key();
+	assert_eq!(first.index(), 0);
+	assert_eq!(first.as_bytes(), &hmac_sha256(&[0x42; 32], &[0x01]));
+
+	let second = chain.next_message_key();
+	assert_eq!(second.index(), 1);
+	assert_eq!(second.as_bytes(), &hmac_sha256(&hmac_sha256(&[0x42; 32], &[0x02]), &[0x01]));
+	assert_eq!(chain.index(), 2);
+}
+
+#[test]
+fn test_restore() {
+	let mut chain = ChainKey::new([7; 32]);
+	chain.next_message_key();
+
+	let mut restored = ChainKey::from_parts(chain.to_bytes(), chain.index());
+	assert_eq!(restored.next_message_key().as_bytes(), chain.next_message_key().as_bytes());
+}
+
+#[test]
+fn test_skip_to() {
+	let mut sender = ChainKey::new([7; 32]);
+	let mut receiver = ChainKey::new([7; 32]);
+
+	let keys = [(); 6].map(|_| sender.next_message_key());
+
+	assert_eq!(receiver.skip_to(2, 2).unwrap().as_bytes(), keys[2].as_bytes());
+	assert_eq!(receiver.skip_to(3, 0).unwrap().as_bytes(), keys[3].as_bytes());
+	assert_eq!(receiver.skip_to(3, 10).unwrap_err(), Error::IndexPassed);
+	assert_eq!(receiver.skip_to(5, 0).unwrap_err(), Error::TooManySkipped);
+
+	// a failed skip doesn't advance the chain
+	assert_eq!(receiver.index(), 4);
+	assert_eq!(receiver.skip_to(5, 1).unwrap().as_bytes(), keys[5].as_bytes());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_exhausted() {
+	let mut chain = ChainKey::from_parts([7; 32], u32::MAX - 1);
+	assert_eq!(chain.next_message_key().index(), u32::MAX - 1);
+
+	let result = std::panic::catch_unwind(move || chain.next_message_key());
+	assert!(result.is_err());
+}
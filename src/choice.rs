@@ -0,0 +1,325 @@
+//! Types for constant-time code, so that secret conditions are never turned
+//! into branches. These follow the [subtle](https://crates.io/crates/subtle)
+//! crate: comparisons return a [`Choice`] rather than a `bool`, selections
+//! are done with masks, and a value that may or may not exist is a
+//! [`CtOption`], which only becomes an `Option` once it's safe to branch on.
+//!
+//! ```
+//! use libkrypton::choice::{ConditionallySelectable, ConstantTimeEq};
+//!
+//! let tag = [0x42u8; 16];
+//! assert!(bool::from(tag.ct_eq(&[0x42; 16])));
+//!
+//! let mut a = [1u8; 4];
+//! let mut b = [2u8; 4];
+//! <[u8; 4]>::conditional_swap(&mut a, &mut b, 1.into());
+//! assert_eq!((a, b), ([2; 4], [1; 4]));
+//! ```
+
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+/// Either 0 or 1, used to choose between values without branching. Kept as a
+/// byte rather than a `bool` so that the compiler has no reason to branch on
+/// it.
+#[derive(Copy, Clone, Debug)]
+pub struct Choice(u8);
+
+impl Choice {
+	/// Returns the choice as 0 or 1.
+	pub fn unwrap_u8(self) -> u8 {
+		self.0
+	}
+}
+
+impl From<u8> for Choice {
+	/// `bit` should never be anything besides 0 or 1.
+	fn from(bit: u8) -> Self {
+		debug_assert!(bit <= 1);
+
+		// hides the value from the optimizer, which could otherwise work out
+		// that it's a bool and branch on it
+		Self(core::hint::black_box(bit))
+	}
+}
+
+impl From<Choice> for bool {
+	fn from(choice: Choice) -> Self {
+		choice.0 != 0
+	}
+}
+
+impl BitAnd for Choice {
+	type Output = Self;
+
+	fn bitand(self, other: Self) -> Self {
+		Self(self.0 & other.0)
+	}
+}
+
+impl BitAndAssign for Choice {
+	fn bitand_assign(&mut self, other: Self) {
+		*self = *self & other;
+	}
+}
+
+impl BitOr for Choice {
+	type Output = Self;
+
+	fn bitor(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+}
+
+impl BitOrAssign for Choice {
+	fn bitor_assign(&mut self, other: Self) {
+		*self = *self | other;
+	}
+}
+
+impl BitXor for Choice {
+	type Output = Self;
+
+	fn bitxor(self, other: Self) -> Self {
+		Self(self.0 ^ other.0)
+	}
+}
+
+impl Not for Choice {
+	type Output = Self;
+
+	fn not(self) -> Self {
+		Self(self.0 ^ 1)
+	}
+}
+
+/// Equality that works in constant time.
+pub trait ConstantTimeEq {
+	/// Returns whether the two values are equal, without branching on them.
+	fn ct_eq(&self, other: &Self) -> Choice;
+
+	/// Returns whether the two values differ, without branching on them.
+	fn ct_ne(&self, other: &Self) -> Choice {
+		!self.ct_eq(other)
+	}
+}
+
+macro_rules! int_impl {
+	($($int:ty),*) => {
+		$(
+			impl ConstantTimeEq for $int {
+				fn ct_eq(&self, other: &Self) -> Choice {
+					// the top bit of x | -x is set unless x is 0
+					let diff = self ^ other;
+					let not_equal = (diff | diff.wrapping_neg()) >> (<$int>::BITS - 1);
+
+					Choice::from(1 - not_equal as u8)
+				}
+			}
+
+			impl ConditionallySelectable for $int {
+				fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+					let mask = (0 as $int).wrapping_sub(choice.unwrap_u8() as $int);
+					a ^ (mask & (a ^ b))
+				}
+			}
+		)*
+	};
+}
+
+int_impl!(u8, u16, u32, u64, u128);
+
+impl<T: ConstantTimeEq> ConstantTimeEq for [T] {
+	/// Slices of different lengths are never equal. Only their contents are
+	/// compared in constant time, not their lengths.
+	fn ct_eq(&self, other: &Self) -> Choice {
+		if self.len() != other.len() {
+			return Choice::from(0);
+		}
+
+		let mut equal = Choice::from(1);
+
+		for (a, b) in self.iter().zip(other) {
+			equal &= a.ct_eq(b);
+		}
+
+		equal
+	}
+}
+
+impl<T: ConstantTimeEq, const N: usize> ConstantTimeEq for [T; N] {
+	fn ct_eq(&self, other: &Self) -> Choice {
+		self[..].ct_eq(&other[..])
+	}
+}
+
+/// Selection between two values that works in constant time.
+pub trait ConditionallySelectable: Copy {
+	/// Returns `b` if `choice` is set, and `a` otherwise.
+	fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self;
+
+	/// Sets this value to `other` if `choice` is set, and leaves it alone
+	/// otherwise.
+	fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+		*self = Self::conditional_select(self, other, choice);
+	}
+
+	/// Swaps the two values if `choice` is set, and does nothing otherwise.
+	fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+		let a_copy = *a;
+		a.conditional_assign(b, choice);
+		b.conditional_assign(&a_copy, choice);
+	}
+}
+
+impl<T: ConditionallySelectable, const N: usize> ConditionallySelectable for [T; N] {
+	fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+		let mut out = *a;
+
+		for (value, other) in out.iter_mut().zip(b) {
+			value.conditional_assign(other, choice);
+		}
+
+		out
+	}
+}
+
+/// A value that may not exist, like an `Option`, but whose existence is a
+/// [`Choice`], so that computing it doesn't branch on whether it exists. It
+/// should only be turned into an `Option`, which can be branched on, once
+/// that's no longer secret.
+#[derive(Copy, Clone, Debug)]
+pub struct CtOption<T> {
+	value: T,
+	is_some: Choice,
+}
+
+impl<T> CtOption<T> {
+	/// Creates a `CtOption` holding `value` if `is_some` is set. The value
+	/// must be there either way, even though it's meaningless when it isn't
+	/// set.
+	pub fn new(value: T, is_some: Choice) -> Self {
+		Self {value, is_some}
+	}
+
+	/// Returns whether there's a value.
+	pub fn is_some(&self) -> Choice {
+		self.is_some
+	}
+
+	/// Returns whether there's no value.
+	pub fn is_none(&self) -> Choice {
+		!self.is_some
+	}
+
+	/// Returns the value, panicking with `message` if there isn't one.
+	///
+	/// # Panics
+	/// * Panics if there's no value.
+	pub fn expect(self, message: &str) -> T {
+		assert!(bool::from(self.is_some), "{}", message);
+
+		self.value
+	}
+
+	/// Returns the value, panicking if there isn't one.
+	///
+	/// # Panics
+	/// * Panics if there's no value.
+	pub fn unwrap(self) -> T {
+		assert!(bool::from(self.is_some), "called unwrap on an empty CtOption");
+
+		self.value
+	}
+
+	/// Applies `function` to the value, whether or not there is one, keeping
+	/// whether there is.
+	pub fn map<U, F: FnOnce(T) -> U>(self, function: F) -> CtOption<U> {
+		CtOption::new(function(self.value), self.is_some)
+	}
+
+	/// Applies `function` to the value, whether or not there is one, giving a
+	/// value only if both had one.
+	pub fn and_then<U, F: FnOnce(T) -> CtOption<U>>(self, function: F) -> CtOption<U> {
+		let is_some = self.is_some;
+		let out = function(self.value);
+
+		CtOption::new(out.value, out.is_some & is_some)
+	}
+
+	/// Turns this into an `Option`. This branches on whether there's a value,
+	/// so it should only be done once that's public.
+	pub fn into_option(self) -> Option<T> {
+		match bool::from(self.is_some) {
+			true => Some(self.value),
+			false => None,
+		}
+	}
+}
+
+impl<T: ConditionallySelectable> CtOption<T> {
+	/// Returns the value, or `default` if there isn't one, in constant time.
+	pub fn unwrap_or(self, default: T) -> T {
+		T::conditional_select(&default, &self.value, self.is_some)
+	}
+
+	/// Returns this if it has a value, and `other` otherwise, in constant
+	/// time.
+	pub fn or_else<F: FnOnce() -> Self>(self, other: F) -> Self {
+		let other = other();
+		let value = T::conditional_select(&other.value, &self.value, self.is_some);
+
+		Self::new(value, self.is_some | other.is_some)
+	}
+}
+
+impl<T> From<CtOption<T>> for Option<T> {
+	fn from(option: CtOption<T>) -> Self {
+		option.into_option()
+	}
+}
+
+#[test]
+fn test_ct_eq() {
+	assert!(bool::from(0u8.ct_eq(&0)));
+	assert!(!bool::from(0x80u8.ct_eq(&0)));
+	assert!(bool::from(u64::MAX.ct_eq(&u64::MAX)));
+	assert!(bool::from(1u64.ct_ne(&(1 << 63))));
+
+	assert!(bool::from([1u8, 2, 3].ct_eq(&[1, 2, 3])));
+	assert!(!bool::from([1u8, 2, 3].ct_eq(&[1, 2, 4])));
+	assert!(!bool::from([1u8, 2, 3][..].ct_eq(&[1, 2][..])));
+	assert!(bool::from([0u8; 0][..].ct_eq(&[][..])));
+}
+
+#[test]
+fn test_conditional_select() {
+	assert_eq!(u32::conditional_select(&5, &7, Choice::from(0)), 5);
+	assert_eq!(u32::conditional_select(&5, &7, Choice::from(1)), 7);
+
+	let mut a = [1u16, 2];
+	let mut b = [3u16, 4];
+	<[u16; 2]>::conditional_swap(&mut a, &mut b, Choice::from(0));
+	assert_eq!((a, b), ([1, 2], [3, 4]));
+	<[u16; 2]>::conditional_swap(&mut a, &mut b, Choice::from(1));
+	assert_eq!((a, b), ([3, 4], [1, 2]));
+
+	a.conditional_assign(&b, Choice::from(1));
+	assert_eq!(a, [1, 2]);
+}
+
+#[test]
+fn test_ct_option() {
+	let some = CtOption::new(3u8, Choice::from(1));
+	let none = CtOption::new(9u8, Choice::from(0));
+
+	assert_eq!(some.into_option(), Some(3));
+	assert_eq!(Option::<u8>::from(none), None);
+	assert_eq!(some.unwrap_or(0), 3);
+	assert_eq!(none.unwrap_or(0), 0);
+
+	assert_eq!(some.map(|x| x + 1).into_option(), Some(4));
+	assert_eq!(some.and_then(|x| CtOption::new(x, Choice::from(0))).into_option(), None);
+	assert_eq!(none.and_then(|x| CtOption::new(x, Choice::from(1))).into_option(), None);
+	assert_eq!(none.or_else(|| some).into_option(), Some(3));
+	assert_eq!(some.or_else(|| none).into_option(), Some(3));
+}
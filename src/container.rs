@@ -0,0 +1,443 @@
+//! Encrypted containers with random access, for databases and virtual disks
+//! that need authenticated encryption without rewriting the whole file.
+//!
+//! The contents are split into fixed-size sectors, each encrypted on its own
+//! with XChaCha20-Poly1305, so reading or writing anywhere only touches the
+//! sectors involved. The nonce of a sector is its index and a counter of how
+//! many times it has been written, which are kept with its tag in a metadata
+//! region after the header:
+//!
+//! ```text
+//! header | counter and tag of each sector | data of each sector
+//! ```
+//!
+//! The header holds the sector size and count and a random container ID, from
+//! which the encryption key is derived, so one key can safely be used for
+//! many containers. Every sector is bound to its position and to the header,
+//! so sectors can't be moved around or between containers. A sector can still
+//! be rolled back to an older version of itself together with its metadata,
+//! which would need a tree of hashes over all sectors to detect.
+//!
+//! Writes aren't atomic: if a write is interrupted, the sectors it was
+//! writing may fail to decrypt afterwards. Databases should keep their own
+//! journal on top of the container.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::vec;
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20::hchacha20;
+use crate::chacha20poly1305::{xchacha20poly1305_decrypt, xchacha20poly1305_encrypt};
+use crate::zeroize::zeroize;
+
+const MAGIC: [u8; 8] = *b"lkcont01";
+
+// the magic bytes, sector size, sector count and container ID, followed by a
+// tag over them that also checks the key
+const HEADER_FIELDS_LEN: usize = 8 + 4 + 8 + 16;
+const HEADER_LEN: usize = HEADER_FIELDS_LEN + 16;
+
+// the write counter and tag of each sector
+const METADATA_LEN: usize = 8 + 16;
+
+// no sector has this index, so the header's tag can use it
+const HEADER_INDEX: u64 = u64::MAX;
+
+/// A container of encrypted sectors, kept in `S`, which is usually a file.
+pub struct Container<S> {
+	storage: S,
+	secrets: Secrets,
+	header: [u8; HEADER_FIELDS_LEN],
+	sector_size: usize,
+	sector_count: u64,
+}
+
+// the key and the plaintext of the sector being worked on, kept apart from
+// the storage so that it can be moved out while these are still zeroized
+struct Secrets {
+	key: [u8; 32],
+	buffer: Vec<u8>,
+}
+
+impl Drop for Secrets {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+		zeroize(&mut self.buffer);
+	}
+}
+
+impl<S: Read + Write + Seek> Container<S> {
+	/// Creates a container in `storage` holding `sector_count` sectors of
+	/// `sector_size` bytes, all zeros to begin with. The `randomness` becomes
+	/// the container ID, and must be freshly generated by a secure random
+	/// source.
+	///
+	/// Every sector is written once, so creating a large container takes as
+	/// long as writing all of it.
+	pub fn create(
+		storage: S,
+		key: [u8; 32],
+		sector_size: u32,
+		sector_count: u64,
+		randomness: [u8; 16],
+	) -> io::Result<Self> {
+		if !is_valid_layout(sector_size, sector_count) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid container layout"));
+		}
+
+		let mut header = [0; HEADER_FIELDS_LEN];
+		header[0 .. 8].copy_from_slice(&MAGIC);
+		header[8 .. 12].copy_from_slice(&sector_size.to_be_bytes());
+		header[12 .. 20].copy_from_slice(&sector_count.to_be_bytes());
+		header[20 .. 36].copy_from_slice(&randomness);
+
+		let mut container = Self::new(storage, key, header);
+
+		let tag = container.seal(HEADER_INDEX, 0, &mut []);
+		container.storage.seek(SeekFrom::Start(0))?;
+		container.storage.write_all(&header)?;
+		container.storage.write_all(&tag)?;
+
+		for index in 0 .. sector_count {
+			container.secrets.buffer.fill(0);
+			container.write_buffer(index, 1)?;
+		}
+
+		container.storage.flush()?;
+		Ok(container)
+	}
+
+	/// Creates a container like [`create`](Self::create), with an ID
+	/// generated by `rng`, which must be a cryptographically secure random
+	/// number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn create_with_rng<R: RngCore + CryptoRng>(
+		storage: S,
+		key: [u8; 32],
+		sector_size: u32,
+		sector_count: u64,
+		rng: &mut R,
+	) -> io::Result<Self> {
+		let mut randomness = [0; 16];
+		rng.fill_bytes(&mut randomness);
+
+		Self::create(storage, key, sector_size, sector_count, randomness)
+	}
+
+	/// Opens an existing container in `storage`. Returns an error of kind
+	/// [`InvalidData`](io::ErrorKind::InvalidData) if the key is wrong or the
+	/// header has been tampered with.
+	pub fn open(mut storage: S, key: [u8; 32]) -> io::Result<Self> {
+		let mut header = [0; HEADER_FIELDS_LEN];
+		let mut tag = [0; 16];
+
+		storage.seek(SeekFrom::Start(0))?;
+		storage.read_exact(&mut header)?;
+		storage.read_exact(&mut tag)?;
+
+		let sector_size = u32::from_be_bytes(header[8 .. 12].try_into().unwrap());
+		let sector_count = u64::from_be_bytes(header[12 .. 20].try_into().unwrap());
+
+		if header[0 .. 8] != MAGIC || !is_valid_layout(sector_size, sector_count) {
+			return Err(invalid_data());
+		}
+
+		let container = Self::new(storage, key, header);
+		container.open_sealed(HEADER_INDEX, 0, &mut [], tag)?;
+
+		Ok(container)
+	}
+
+	fn new(storage: S, key: [u8; 32], header: [u8; HEADER_FIELDS_LEN]) -> Self {
+		let sector_size = u32::from_be_bytes(header[8 .. 12].try_into().unwrap());
+		let sector_count = u64::from_be_bytes(header[12 .. 20].try_into().unwrap());
+
+		// the container ID takes the place of the first half of an XChaCha20
+		// nonce, giving each container a key of its own
+		let key = hchacha20(key, header[20 .. 36].try_into().unwrap());
+
+		let secrets = Secrets {key, buffer: vec![0; sector_size as usize]};
+
+		Self {storage, secrets, header, sector_size: sector_size as usize, sector_count}
+	}
+
+	/// Returns the size of each sector in bytes.
+	pub fn sector_size(&self) -> usize {
+		self.sector_size
+	}
+
+	/// Returns the number of sectors.
+	pub fn sector_count(&self) -> u64 {
+		self.sector_count
+	}
+
+	/// Returns the capacity of the container in bytes.
+	pub fn len(&self) -> u64 {
+		self.sector_count * self.sector_size as u64
+	}
+
+	/// Returns whether the container has no sectors.
+	pub fn is_empty(&self) -> bool {
+		self.sector_count == 0
+	}
+
+	/// Fills `buf` with the contents starting at `offset`. Returns an error of
+	/// kind [`InvalidData`](io::ErrorKind::InvalidData) if any of the sectors
+	/// read have been tampered with, or of kind
+	/// [`InvalidInput`](io::ErrorKind::InvalidInput) if the range goes past
+	/// the end of the container.
+	pub fn read_at(&mut self, mut offset: u64, mut buf: &mut [u8]) -> io::Result<()> {
+		self.check_range(offset, buf.len())?;
+
+		while !buf.is_empty() {
+			let index = offset / self.sector_size as u64;
+			let start = (offset % self.sector_size as u64) as usize;
+			let len = usize::min(buf.len(), self.sector_size - start);
+
+			self.read_buffer(index)?;
+			buf[.. len].copy_from_slice(&self.secrets.buffer[start .. start + len]);
+
+			buf = &mut buf[len ..];
+			offset += len as u64;
+		}
+
+		zeroize(&mut self.secrets.buffer);
+		Ok(())
+	}
+
+	/// Writes `data` to the contents starting at `offset`. Sectors that are
+	/// only partly overwritten are read first, so this fails like
+	/// [`read_at`](Self::read_at) if they've been tampered with.
+	pub fn write_at(&mut self, mut offset: u64, mut data: &[u8]) -> io::Result<()> {
+		self.check_range(offset, data.len())?;
+
+		while !data.is_empty() {
+			let index = offset / self.sector_size as u64;
+			let start = (offset % self.sector_size as u64) as usize;
+			let len = usize::min(data.len(), self.sector_size - start);
+
+			let counter = match len == self.sector_size {
+				true => self.read_counter(index)?,
+				false => self.read_buffer(index)?,
+			};
+
+			let counter = counter.checked_add(1).ok_or_else(|| io::Error::other("sector written too many times"))?;
+
+			self.secrets.buffer[start .. start + len].copy_from_slice(&data[.. len]);
+			self.write_buffer(index, counter)?;
+
+			data = &data[len ..];
+			offset += len as u64;
+		}
+
+		zeroize(&mut self.secrets.buffer);
+		Ok(())
+	}
+
+	/// Flushes the underlying storage.
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.storage.flush()
+	}
+
+	/// Returns the underlying storage.
+	pub fn into_inner(self) -> S {
+		self.storage
+	}
+
+	fn check_range(&self, offset: u64, len: usize) -> io::Result<()> {
+		match offset.checked_add(len as u64) {
+			Some(end) if end <= self.len() => Ok(()),
+			_ => Err(io::Error::new(io::ErrorKind::InvalidInput, "range outside of container")),
+		}
+	}
+
+	fn metadata_offset(&self, index: u64) -> u64 {
+		HEADER_LEN as u64 + index * METADATA_LEN as u64
+	}
+
+	fn data_offset(&self, index: u64) -> u64 {
+		self.metadata_offset(self.sector_count) + index * self.sector_size as u64
+	}
+
+	// binds a sector to the header and its position in the container
+	fn nonce_and_associated_data(&self, index: u64, counter: u64) -> ([u8; 24], [u8; HEADER_FIELDS_LEN + 8]) {
+		let mut nonce = [0; 24];
+		nonce[0 .. 8].copy_from_slice(&index.to_be_bytes());
+		nonce[8 .. 16].copy_from_slice(&counter.to_be_bytes());
+
+		let mut associated_data = [0; HEADER_FIELDS_LEN + 8];
+		associated_data[.. HEADER_FIELDS_LEN].copy_from_slice(&self.header);
+		associated_data[HEADER_FIELDS_LEN ..].copy_from_slice(&index.to_be_bytes());
+
+		(nonce, associated_data)
+	}
+
+	fn seal(&self, index: u64, counter: u64, buffer: &mut [u8]) -> [u8; 16] {
+		let (nonce, associated_data) = self.nonce_and_associated_data(index, counter);
+		xchacha20poly1305_encrypt(self.secrets.key, nonce, &associated_data, buffer)
+	}
+
+	fn open_sealed(&self, index: u64, counter: u64, buffer: &mut [u8], tag: [u8; 16]) -> io::Result<()> {
+		let (nonce, associated_data) = self.nonce_and_associated_data(index, counter);
+		xchacha20poly1305_decrypt(self.secrets.key, nonce, &associated_data, buffer, tag).map_err(|_| invalid_data())
+	}
+
+	fn read_metadata(&mut self, index: u64) -> io::Result<(u64, [u8; 16])> {
+		let mut metadata = [0; METADATA_LEN];
+		self.storage.seek(SeekFrom::Start(self.metadata_offset(index)))?;
+		self.storage.read_exact(&mut metadata)?;
+
+		let counter = u64::from_be_bytes(metadata[0 .. 8].try_into().unwrap());
+		Ok((counter, metadata[8 ..].try_into().unwrap()))
+	}
+
+	fn read_counter(&mut self, index: u64) -> io::Result<u64> {
+		Ok(self.read_metadata(index)?.0)
+	}
+
+	// decrypts a sector into the buffer, returning its counter
+	fn read_buffer(&mut self, index: u64) -> io::Result<u64> {
+		let (counter, tag) = self.read_metadata(index)?;
+
+		let mut buffer = core::mem::take(&mut self.secrets.buffer);
+		self.storage.seek(SeekFrom::Start(self.data_offset(index)))?;
+		let result = self.storage.read_exact(&mut buffer)
+			.and_then(|_| self.open_sealed(index, counter, &mut buffer, tag));
+
+		self.secrets.buffer = buffer;
+		result.map(|_| counter)
+	}
+
+	// encrypts the buffer into a sector with a counter it's never had before
+	fn write_buffer(&mut self, index: u64, counter: u64) -> io::Result<()> {
+		let mut buffer = core::mem::take(&mut self.secrets.buffer);
+		let tag = self.seal(index, counter, &mut buffer);
+
+		let mut metadata = [0; METADATA_LEN];
+		metadata[0 .. 8].copy_from_slice(&counter.to_be_bytes());
+		metadata[8 ..].copy_from_slice(&tag);
+
+		// the new counter goes in first, so that a write cut short never
+		// leaves the counter behind the data, which would reuse the nonce
+		// the next time the sector is written
+		let result = self.storage.seek(SeekFrom::Start(self.metadata_offset(index)))
+			.and_then(|_| self.storage.write_all(&metadata))
+			.and_then(|_| self.storage.seek(SeekFrom::Start(self.data_offset(index))))
+			.and_then(|_| self.storage.write_all(&buffer));
+
+		self.secrets.buffer = buffer;
+		result
+	}
+}
+
+impl<S> core::fmt::Debug for Container<S> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		f.debug_struct("Container")
+			.field("sector_size", &self.sector_size)
+			.field("sector_count", &self.sector_count)
+			.finish_non_exhaustive()
+	}
+}
+
+// whether every sector has an index other than the header's, and the whole
+// container fits in a u64
+fn is_valid_layout(sector_size: u32, sector_count: u64) -> bool {
+	let len = (sector_size as u64 + METADATA_LEN as u64)
+		.checked_mul(sector_count)
+		.and_then(|len| len.checked_add(HEADER_LEN as u64));
+
+	sector_size != 0 && sector_count != HEADER_INDEX && len.is_some()
+}
+
+fn invalid_data() -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, "invalid or tampered container")
+}
+
+#[cfg(test)]
+fn test_container() -> Container<io::Cursor<Vec<u8>>> {
+	Container::create(io::Cursor::new(Vec::new()), [1; 32], 64, 4, [2; 16]).unwrap()
+}
+
+#[test]
+fn test_read_and_write() {
+	let mut container = test_container();
+	assert_eq!(container.len(), 256);
+
+	let mut buf = [0xff; 256];
+	container.read_at(0, &mut buf).unwrap();
+	assert_eq!(buf, [0; 256]);
+
+	// across a sector boundary, then a whole sector
+	let data: Vec<u8> = (0 .. 100).collect();
+	container.write_at(50, &data).unwrap();
+	container.write_at(192, &[7; 64]).unwrap();
+
+	let storage = container.into_inner();
+	assert!(!storage.get_ref().windows(8).any(|x| x == &data[10 .. 18]));
+
+	let mut container = Container::open(storage, [1; 32]).unwrap();
+	container.read_at(0, &mut buf).unwrap();
+	assert_eq!(buf[.. 50], [0; 50]);
+	assert_eq!(buf[50 .. 150], data[..]);
+	assert_eq!(buf[150 .. 192], [0; 42]);
+	assert_eq!(buf[192 ..], [7; 64]);
+
+	let mut small = [0; 3];
+	container.read_at(98, &mut small).unwrap();
+	assert_eq!(small, [48, 49, 50]);
+
+	let invalid_input = |result: io::Result<()>| result.unwrap_err().kind() == io::ErrorKind::InvalidInput;
+	assert!(invalid_input(container.read_at(250, &mut [0; 7])));
+	assert!(invalid_input(container.write_at(u64::MAX, &[0])));
+
+	let result = Container::create(io::Cursor::new(Vec::new()), [1; 32], 0, 4, [2; 16]);
+	assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+	let result = Container::create(io::Cursor::new(Vec::new()), [1; 32], 4096, u64::MAX / 4096, [2; 16]);
+	assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_tampering() {
+	let mut container = test_container();
+	container.write_at(0, &[9; 256]).unwrap();
+	let storage = container.into_inner().into_inner();
+
+	let open = |storage: &Vec<u8>, key| Container::open(io::Cursor::new(storage.clone()), key);
+	let invalid = |result: io::Result<()>| result.unwrap_err().kind() == io::ErrorKind::InvalidData;
+
+	// the wrong key and a changed header are caught on opening
+	assert!(invalid(open(&storage, [3; 32]).map(|_| ())));
+
+	let mut changed = storage.clone();
+	changed[11] ^= 1;
+	assert!(invalid(open(&changed, [1; 32]).map(|_| ())));
+
+	// a changed sector only breaks reads of that sector
+	let data_start = HEADER_LEN + 4 * METADATA_LEN;
+	let mut changed = storage.clone();
+	changed[data_start + 70] ^= 1;
+
+	let mut container = open(&changed, [1; 32]).unwrap();
+	assert!(invalid(container.read_at(64, &mut [0; 1])));
+	assert!(invalid(container.write_at(100, &[0; 1])));
+	container.read_at(0, &mut [0; 64]).unwrap();
+	container.write_at(64, &[0; 64]).unwrap();
+	container.read_at(64, &mut [0; 64]).unwrap();
+
+	// and sectors can't be swapped
+	let mut swapped = storage.clone();
+	let (first, second) = (HEADER_LEN, HEADER_LEN + METADATA_LEN);
+	let metadata: Vec<u8> = swapped[first .. second].to_vec();
+	swapped.copy_within(second .. second + METADATA_LEN, first);
+	swapped[second .. second + METADATA_LEN].copy_from_slice(&metadata);
+
+	let data: Vec<u8> = swapped[data_start .. data_start + 64].to_vec();
+	swapped.copy_within(data_start + 64 .. data_start + 128, data_start);
+	swapped[data_start + 64 .. data_start + 128].copy_from_slice(&data);
+
+	let mut container = open(&swapped, [1; 32]).unwrap();
+	assert!(invalid(container.read_at(0, &mut [0; 1])));
+}
@@ -0,0 +1,503 @@
+//! Implemented according to [NIST SP 800-90A Rev. 1](https://csrc.nist.gov/pubs/sp/800/90/a/r1/final),
+//! section 10.2. CTR_DRBG is a deterministic random bit generator built on
+//! AES-256 in counter mode: once seeded with entropy, it produces a stream of
+//! pseudorandom bytes, and updates its key after every request so that
+//! earlier output can't be recovered from a later state.
+//!
+//! The derivation function, which compresses seed material of any length
+//! with AES, is used by [`CtrDrbg::new`]. [`CtrDrbg::new_without_df`] skips it,
+//! but needs exactly 48 bytes of full-entropy input instead.
+
+use core::fmt;
+
+use crate::aes::Aes;
+use crate::zeroize::zeroize;
+
+const KEY_SIZE: usize = 32;
+const BLOCK_SIZE: usize = 16;
+const SEED_SIZE: usize = KEY_SIZE + BLOCK_SIZE;
+
+/// The number of requests allowed between reseeds.
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// The most bytes that can be requested from a single call to
+/// [`CtrDrbg::generate`].
+pub const MAX_REQUEST_SIZE: usize = 1 << 16;
+
+/// An error returned when generating random bytes fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// [`RESEED_INTERVAL`] requests have been made since the generator was
+	/// last seeded, so it must be reseeded before generating any more.
+	ReseedRequired,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::ReseedRequired => "ctr-drbg must be reseeded",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Runs the BCC function over blocks of data, chaining them through AES in the
+/// way of CBC-MAC. The blocks are fed in one byte at a time, so the input
+/// never has to be concatenated in memory.
+struct Bcc<'a> {
+	aes: &'a Aes,
+	chaining: [u8; BLOCK_SIZE],
+	pos: usize,
+}
+
+impl<'a> Bcc<'a> {
+	fn new(aes: &'a Aes) -> Self {
+		Self {aes, chaining: [0; BLOCK_SIZE], pos: 0}
+	}
+
+	fn add_bytes(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.chaining[self.pos] ^= byte;
+			self.pos += 1;
+
+			if self.pos == BLOCK_SIZE {
+				self.aes.encrypt_block(&mut self.chaining);
+				self.pos = 0;
+			}
+		}
+	}
+
+	fn out(self) -> [u8; BLOCK_SIZE] {
+		debug_assert_eq!(self.pos, 0);
+
+		self.chaining
+	}
+}
+
+/// The derivation function Block_Cipher_df from section 10.3.2, which
+/// compresses the concatenation of `inputs` into 48 bytes of seed material.
+fn block_cipher_df(inputs: &[&[u8]]) -> [u8; SEED_SIZE] {
+	let input_len: usize = inputs.iter().map(|input| input.len()).sum();
+	let total_len = 8 + input_len + 1;
+	let padding = [0; BLOCK_SIZE];
+	let padding = &padding[.. (BLOCK_SIZE - total_len % BLOCK_SIZE) % BLOCK_SIZE];
+
+	let mut df_key = [0; KEY_SIZE];
+
+	for i in 0 .. KEY_SIZE {
+		df_key[i] = i as u8;
+	}
+
+	let aes = Aes::new_256(df_key);
+	let mut temp = [0; SEED_SIZE];
+
+	for (i, chunk) in temp.chunks_mut(BLOCK_SIZE).enumerate() {
+		let mut iv = [0; BLOCK_SIZE];
+		iv[0 .. 4].copy_from_slice(&(i as u32).to_be_bytes());
+
+		// S = L || N || input || 0x80 || padding, where L is the length of the
+		// input and N is the length of the output
+		let mut bcc = Bcc::new(&aes);
+		bcc.add_bytes(&iv);
+		bcc.add_bytes(&(input_len as u32).to_be_bytes());
+		bcc.add_bytes(&(SEED_SIZE as u32).to_be_bytes());
+
+		for input in inputs {
+			bcc.add_bytes(input);
+		}
+
+		bcc.add_bytes(&[0x80]);
+		bcc.add_bytes(padding);
+
+		chunk.copy_from_slice(&bcc.out());
+	}
+
+	let mut key = [0; KEY_SIZE];
+	key.copy_from_slice(&temp[.. KEY_SIZE]);
+	let mut block = [0; BLOCK_SIZE];
+	block.copy_from_slice(&temp[KEY_SIZE ..]);
+
+	let aes = Aes::new_256(key);
+	let mut out = [0; SEED_SIZE];
+
+	for chunk in out.chunks_mut(BLOCK_SIZE) {
+		aes.encrypt_block(&mut block);
+		chunk.copy_from_slice(&block);
+	}
+
+	zeroize(&mut temp);
+	zeroize(&mut key);
+	zeroize(&mut block);
+
+	out
+}
+
+// pads input of at most 48 bytes with zeros, for use without the
+// derivation function
+fn pad_to_seed_size(input: &[u8]) -> [u8; SEED_SIZE] {
+	assert!(input.len() <= SEED_SIZE);
+
+	let mut out = [0; SEED_SIZE];
+	out[.. input.len()].copy_from_slice(input);
+
+	out
+}
+
+/// An AES-256 CTR_DRBG instance.
+#[derive(Clone)]
+pub struct CtrDrbg {
+	aes: Aes,
+	v: [u8; BLOCK_SIZE],
+	reseed_counter: u64,
+	use_df: bool,
+}
+
+impl CtrDrbg {
+	/// Instantiates the generator with the derivation function, from at
+	/// least 32 bytes of `entropy`, a `nonce` of at least 16 bytes that's
+	/// never reused with the same entropy source, such as a timestamp, and an
+	/// optional `personalization` string that distinguishes this instance
+	/// from others.
+	///
+	/// # Panics
+	/// * Panics if `entropy` is shorter than 32 bytes or `nonce` is shorter
+	///   than 16 bytes.
+	pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+		assert!(entropy.len() >= KEY_SIZE);
+		assert!(nonce.len() >= BLOCK_SIZE);
+
+		let mut seed = block_cipher_df(&[entropy, nonce, personalization]);
+		let drbg = Self::from_seed(&seed, true);
+		zeroize(&mut seed);
+
+		drbg
+	}
+
+	/// Instantiates the generator without the derivation function, from 48
+	/// bytes of full `entropy`, which must come straight from a source that
+	/// outputs uniformly random bytes. The optional `personalization` string
+	/// is at most 48 bytes long.
+	///
+	/// # Panics
+	/// * Panics if `personalization` is longer than 48 bytes.
+	pub fn new_without_df(entropy: &[u8; 48], personalization: &[u8]) -> Self {
+		let mut seed = pad_to_seed_size(personalization);
+
+		for i in 0 .. SEED_SIZE {
+			seed[i] ^= entropy[i];
+		}
+
+		let drbg = Self::from_seed(&seed, false);
+		zeroize(&mut seed);
+
+		drbg
+	}
+
+	fn from_seed(seed: &[u8; SEED_SIZE], use_df: bool) -> Self {
+		let mut drbg = Self {
+			aes: Aes::new_256([0; KEY_SIZE]),
+			v: [0; BLOCK_SIZE],
+			reseed_counter: 1,
+			use_df,
+		};
+
+		drbg.update(seed);
+
+		drbg
+	}
+
+	// increments V as a 128-bit big-endian counter
+	fn increment_v(&mut self) {
+		for byte in self.v.iter_mut().rev() {
+			*byte = byte.wrapping_add(1);
+
+			if *byte != 0 {
+				break;
+			}
+		}
+	}
+
+	/// The update function CTR_DRBG_Update from section 10.2.1.2.
+	fn update(&mut self, provided_data: &[u8; SEED_SIZE]) {
+		let mut temp = [0; SEED_SIZE];
+
+		for chunk in temp.chunks_mut(BLOCK_SIZE) {
+			self.increment_v();
+			let mut block = self.v;
+			self.aes.encrypt_block(&mut block);
+			chunk.copy_from_slice(&block);
+		}
+
+		for i in 0 .. SEED_SIZE {
+			temp[i] ^= provided_data[i];
+		}
+
+		let mut key = [0; KEY_SIZE];
+		key.copy_from_slice(&temp[.. KEY_SIZE]);
+		self.aes = Aes::new_256(key);
+		self.v.copy_from_slice(&temp[KEY_SIZE ..]);
+
+		zeroize(&mut temp);
+		zeroize(&mut key);
+	}
+
+	// turns additional input into seed material, or returns None if there is
+	// none
+	fn process_additional_input(&self, additional_input: &[u8]) -> Option<[u8; SEED_SIZE]> {
+		if additional_input.len() == 0 {
+			None
+		} else if self.use_df {
+			Some(block_cipher_df(&[additional_input]))
+		} else {
+			Some(pad_to_seed_size(additional_input))
+		}
+	}
+
+	/// Reseeds the generator with fresh `entropy` and optional
+	/// `additional_input`. With the derivation function, `entropy` is at
+	/// least 32 bytes long. Without it, `entropy` is exactly 48 bytes of full
+	/// entropy, and `additional_input` is at most 48 bytes.
+	///
+	/// # Panics
+	/// * Panics if `entropy` or `additional_input` has the wrong length for
+	///   whether the derivation function is used.
+	pub fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) {
+		let mut seed = if self.use_df {
+			assert!(entropy.len() >= KEY_SIZE);
+
+			block_cipher_df(&[entropy, additional_input])
+		} else {
+			assert!(entropy.len() == SEED_SIZE);
+
+			let mut seed = pad_to_seed_size(additional_input);
+
+			for i in 0 .. SEED_SIZE {
+				seed[i] ^= entropy[i];
+			}
+
+			seed
+		};
+
+		self.update(&seed);
+		self.reseed_counter = 1;
+
+		zeroize(&mut seed);
+	}
+
+	/// Fills `out` with pseudorandom bytes, mixing in the optional
+	/// `additional_input` first. Without the derivation function,
+	/// `additional_input` is at most 48 bytes.
+	///
+	/// # Panics
+	/// * Panics if `out` is longer than [`MAX_REQUEST_SIZE`] bytes.
+	/// * Panics if `additional_input` is longer than 48 bytes without the
+	///   derivation function.
+	pub fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), Error> {
+		assert!(out.len() <= MAX_REQUEST_SIZE);
+
+		if self.reseed_counter > RESEED_INTERVAL {
+			return Err(Error::ReseedRequired);
+		}
+
+		let mut additional_input = self.process_additional_input(additional_input);
+
+		if let Some(additional_input) = &additional_input {
+			self.update(additional_input);
+		}
+
+		for chunk in out.chunks_mut(BLOCK_SIZE) {
+			self.increment_v();
+			let mut block = self.v;
+			self.aes.encrypt_block(&mut block);
+			chunk.copy_from_slice(&block[.. chunk.len()]);
+			zeroize(&mut block);
+		}
+
+		let mut provided_data = additional_input.unwrap_or([0; SEED_SIZE]);
+		self.update(&provided_data);
+		self.reseed_counter += 1;
+
+		zeroize(&mut provided_data);
+
+		if let Some(additional_input) = &mut additional_input {
+			zeroize(additional_input);
+		}
+
+		Ok(())
+	}
+
+	/// Derives a child generator for a separate use, such as nonces or key
+	/// generation, so that the uses can't end up sharing output. The child
+	/// is instantiated with the derivation function from 48 bytes of this
+	/// generator's output, with `label` as its personalization string, so
+	/// forking twice gives different children even with the same label.
+	pub fn fork(&mut self, label: &[u8]) -> Result<Self, Error> {
+		let mut seed = [0; SEED_SIZE];
+		self.generate(&mut seed, b"")?;
+
+		let child = Self::new(&seed[.. KEY_SIZE], &seed[KEY_SIZE ..], label);
+		zeroize(&mut seed);
+
+		Ok(child)
+	}
+}
+
+impl Drop for CtrDrbg {
+	fn drop(&mut self) {
+		zeroize(&mut self.v);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for CtrDrbg {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.aes);
+		zeroize(&mut self.v);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for CtrDrbg {}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[cfg(test)]
+fn counting_bytes<const N: usize>(start: u8) -> [u8; N] {
+	let mut out = [0; N];
+
+	for i in 0 .. N {
+		out[i] = start + i as u8;
+	}
+
+	out
+}
+
+// from the AES-256 use df section of CTR_DRBG.rsp in NIST's CAVP test vectors,
+// count 0 without prediction resistance. The first output is discarded.
+#[test]
+fn test_cavp_with_df() {
+	let mut drbg = CtrDrbg::new(
+		&hex::<32>("36401940fa8b1fba91a1661f211d78a0b9389a74e5bccfece8d766af1a6d3b14"),
+		&hex::<16>("496f25b0f1301b4f501be30380a137eb"),
+		b"",
+	);
+
+	let mut out = [0; 64];
+	drbg.generate(&mut out, b"").unwrap();
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("5862eb38bd558dd978a696e6df164782ddd887e7e9a6c9f3f1fbafb78941b535a64912dfd224c6dc7454e5250b3d97165e16260c2faf1cc7735cb75fb4f07e1d"));
+}
+
+// from the AES-256 no df section of CTR_DRBG.rsp in NIST's CAVP test vectors,
+// count 0 without prediction resistance. The first output is discarded.
+#[test]
+fn test_cavp_without_df() {
+	let mut drbg = CtrDrbg::new_without_df(
+		&hex("df5d73faa468649edda33b5cca79b0b05600419ccb7a879ddfec9db32ee494e5531b51de16a30f769262474c73bec010"),
+		b"",
+	);
+
+	let mut out = [0; 64];
+	drbg.generate(&mut out, b"").unwrap();
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("d1c07cd95af8a7f11012c84ce48bb8cb87189e99d40fccb1771c619bdf82ab2280b1dc2f2581f39164f7ac0c510494b3a43c41b7db17514c87b107ae793e01c5"));
+}
+
+// covers the personalization string, additional input and reseeding, which
+// the vectors above leave empty. Checked against OpenSSL 3.5's CTR-DRBG with
+// AES-256-CTR, fed the same entropy through its TEST-RAND source.
+#[test]
+fn test_with_df() {
+	let mut drbg = CtrDrbg::new(
+		&counting_bytes::<32>(0x00),
+		&counting_bytes::<16>(0x20),
+		b"libkrypton ctr-drbg test",
+	);
+
+	let mut out = [0; 64];
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("6682028a846652a6c2b99c7c302eba1c2e124d58a0233aa9f1307e71802f53c9c42e435f2e95cc0ea40d7b61fd867edae66fff992619f91f095258e9ac6e5855"));
+
+	drbg.generate(&mut out, b"additional input").unwrap();
+	assert_eq!(out, hex("1fdc955e3c5fdcf5a3b831328c2b41f0a05030dac1fe8b9ee36d0da355df967de260824c67ef233fbfe033710ca1d13bb701f35f55de2365426cbab72df85b1a"));
+
+	drbg.reseed(&counting_bytes::<32>(0x40), b"reseed");
+
+	let mut out = [0; 37];
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("45f69c2c5b8e0437921c6b0a23c3105d520cba8d1957d1cb412f1c38a64ac6fa0f136a99d9"));
+}
+
+// checked against OpenSSL 3.5 in the same way
+#[test]
+fn test_without_df() {
+	let mut drbg = CtrDrbg::new_without_df(&counting_bytes(0x00), b"personal");
+
+	let mut out = [0; 64];
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("e6ce17ef7b916a77bfc679af21332803c8950a98bf66d74c7a27d212fb86e5644e67b5cc3826d89372fcad798f08b82a0ab32d213e338b0b3d024a7c4da51467"));
+
+	drbg.generate(&mut out, b"additional input").unwrap();
+	assert_eq!(out, hex("e8f0d635a34c9336fa08fe85886e7d0e57ea86ee7c0da3646f7d1e535d41a57dcee37c69074828d7fe50972c2b7b7bc2639534ccfccbf0c20a54a093be950baa"));
+
+	drbg.reseed(&counting_bytes::<48>(0x40), b"reseed");
+
+	let mut out = [0; 37];
+	drbg.generate(&mut out, b"").unwrap();
+	assert_eq!(out, hex("b3bed7f34b12eb558a02914714fad113614aa7bafc95965caea7a4d92e15ba41989af68d1a"));
+}
+
+#[test]
+fn test_reseed_required() {
+	let mut drbg = CtrDrbg::new(&[0x11; 32], &[0x22; 16], b"");
+	drbg.reseed_counter = RESEED_INTERVAL;
+
+	let mut out = [0; 16];
+	assert_eq!(drbg.generate(&mut out, b""), Ok(()));
+	assert_eq!(drbg.generate(&mut out, b""), Err(Error::ReseedRequired));
+
+	drbg.reseed(&[0x33; 32], b"");
+	assert_eq!(drbg.generate(&mut out, b""), Ok(()));
+}
+
+#[test]
+fn test_fork() {
+	let mut parent = CtrDrbg::new(&[0x11; 32], &[0x22; 16], b"");
+	let mut copy = parent.clone();
+
+	let mut nonces = parent.fork(b"nonces").unwrap();
+	let mut keys = parent.fork(b"keys").unwrap();
+	let mut keys_again = parent.fork(b"keys").unwrap();
+
+	// a child is instantiated from the parent's output, personalized with the label
+	let mut seed = [0; 48];
+	copy.generate(&mut seed, b"").unwrap();
+	let mut expected = CtrDrbg::new(&seed[.. 32], &seed[32 ..], b"nonces");
+
+	let next = |drbg: &mut CtrDrbg| {
+		let mut out = [0; 32];
+		drbg.generate(&mut out, b"").unwrap();
+		out
+	};
+
+	let outputs = [
+		next(&mut nonces),
+		next(&mut keys),
+		next(&mut keys_again),
+		next(&mut parent),
+		next(&mut expected),
+	];
+
+	assert_eq!(outputs[0], outputs[4]);
+
+	for i in 0 .. 4 {
+		for j in 0 .. i {
+			assert_ne!(outputs[i], outputs[j]);
+		}
+	}
+}
@@ -0,0 +1,489 @@
+//! An AVX2 backend for the curve arithmetic, which works on four field
+//! elements at once. The Edwards point formulas and the X25519 ladder step
+//! both have groups of independent multiplications, which are done together
+//! in the four lanes of a vector.
+//!
+//! Each lane holds a field element as 10 limbs of alternately 26 and 25 bits,
+//! so that products of limbs fit in the 32x32 -> 64 bit multiplications AVX2
+//! provides. Limb `i` of all four elements is stored in the `i`th vector.
+
+use core::arch::x86_64::*;
+
+#[cfg(feature = "ed25519")]
+use super::ed25519::{Point, ED25519_D, NEUTRAL_POINT};
+use super::num::Num;
+
+/// Returns whether the running CPU supports AVX2. Without the standard
+/// library, this can only be known at compile time.
+pub(super) fn is_available() -> bool {
+	#[cfg(feature = "std")]
+	{
+		std::is_x86_feature_detected!("avx2")
+	}
+
+	#[cfg(not(feature = "std"))]
+	{
+		cfg!(target_feature = "avx2")
+	}
+}
+
+const LOW_26_BITS: i64 = (1 << 26) - 1;
+const LOW_25_BITS: i64 = (1 << 25) - 1;
+
+// 4 * p in the 26/25-bit limb representation, added before subtracting so
+// that no limb goes negative
+const FOUR_P: [i64; 10] = [
+	4 * ((1 << 26) - 19),
+	4 * LOW_25_BITS,
+	4 * LOW_26_BITS,
+	4 * LOW_25_BITS,
+	4 * LOW_26_BITS,
+	4 * LOW_25_BITS,
+	4 * LOW_26_BITS,
+	4 * LOW_25_BITS,
+	4 * LOW_26_BITS,
+	4 * LOW_25_BITS,
+];
+
+#[derive(Copy, Clone)]
+struct FieldX4([__m256i; 10]);
+
+impl FieldX4 {
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn from_nums(nums: [Num; 4]) -> Self {
+		let mut limbs = [_mm256_setzero_si256(); 10];
+
+		for i in 0 .. 5 {
+			let low = |lane: usize| (nums[lane].segments[i] as i64) & LOW_26_BITS;
+			let high = |lane: usize| (nums[lane].segments[i] >> 26) as i64;
+
+			limbs[2 * i] = _mm256_setr_epi64x(low(0), low(1), low(2), low(3));
+			limbs[2 * i + 1] = _mm256_setr_epi64x(high(0), high(1), high(2), high(3));
+		}
+
+		Self(limbs)
+	}
+
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn to_nums(self) -> [Num; 4] {
+		let limbs = self.0;
+		let mut nums = [Num::ZERO; 4];
+
+		for i in 0 .. 5 {
+			let low = limbs[2 * i];
+			let high = limbs[2 * i + 1];
+
+			let lanes = [
+				(_mm256_extract_epi64::<0>(low), _mm256_extract_epi64::<0>(high)),
+				(_mm256_extract_epi64::<1>(low), _mm256_extract_epi64::<1>(high)),
+				(_mm256_extract_epi64::<2>(low), _mm256_extract_epi64::<2>(high)),
+				(_mm256_extract_epi64::<3>(low), _mm256_extract_epi64::<3>(high)),
+			];
+
+			for (lane, (low, high)) in lanes.into_iter().enumerate() {
+				nums[lane].segments[i] = low as u128 + ((high as u128) << 26);
+			}
+		}
+
+		nums
+	}
+
+	// propagates carries so that every limb fits in 26 or 25 bits again, apart
+	// from a possible small excess in the two limbs carried into last
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn carry(mut limbs: [__m256i; 10]) -> Self {
+		let mask_26 = _mm256_set1_epi64x(LOW_26_BITS);
+		let mask_25 = _mm256_set1_epi64x(LOW_25_BITS);
+
+		let carry_26 = |limbs: &mut [__m256i; 10], i: usize| {
+			let carry = _mm256_srli_epi64::<26>(limbs[i]);
+			limbs[i] = _mm256_and_si256(limbs[i], mask_26);
+			carry
+		};
+		let carry_25 = |limbs: &mut [__m256i; 10], i: usize| {
+			let carry = _mm256_srli_epi64::<25>(limbs[i]);
+			limbs[i] = _mm256_and_si256(limbs[i], mask_25);
+			carry
+		};
+
+		// two chains starting at limbs 0 and 4 run side by side, which halves
+		// the length of the dependency chain
+		for i in (0 .. 4).step_by(2) {
+			let carry_low = carry_26(&mut limbs, i);
+			let carry_high = carry_26(&mut limbs, i + 4);
+			limbs[i + 1] = _mm256_add_epi64(limbs[i + 1], carry_low);
+			limbs[i + 5] = _mm256_add_epi64(limbs[i + 5], carry_high);
+
+			let carry_low = carry_25(&mut limbs, i + 1);
+			let carry_high = carry_25(&mut limbs, i + 5);
+			limbs[i + 2] = _mm256_add_epi64(limbs[i + 2], carry_low);
+			limbs[i + 6] = _mm256_add_epi64(limbs[i + 6], carry_high);
+		}
+
+		let carry_low = carry_26(&mut limbs, 4);
+		let carry_high = carry_26(&mut limbs, 8);
+		limbs[5] = _mm256_add_epi64(limbs[5], carry_low);
+		limbs[9] = _mm256_add_epi64(limbs[9], carry_high);
+
+		// the carry out of the top limb wraps around multiplied by 19, which
+		// is computed with shifts since it may not fit in 32 bits
+		let carry = carry_25(&mut limbs, 9);
+		let carry_19 = _mm256_add_epi64(
+			_mm256_add_epi64(_mm256_slli_epi64::<4>(carry), _mm256_slli_epi64::<1>(carry)),
+			carry,
+		);
+		limbs[0] = _mm256_add_epi64(limbs[0], carry_19);
+
+		let carry = carry_26(&mut limbs, 0);
+		limbs[1] = _mm256_add_epi64(limbs[1], carry);
+
+		Self(limbs)
+	}
+
+	// doesn't carry, since the limbs of a sum of two carried numbers are still
+	// small enough to multiply, or to subtract something from
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn add(self, other: Self) -> Self {
+		let mut limbs = self.0;
+
+		for i in 0 .. 10 {
+			limbs[i] = _mm256_add_epi64(limbs[i], other.0[i]);
+		}
+
+		Self(limbs)
+	}
+
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn sub(self, other: Self) -> Self {
+		let mut limbs = self.0;
+
+		for i in 0 .. 10 {
+			let four_p = _mm256_set1_epi64x(FOUR_P[i]);
+			limbs[i] = _mm256_sub_epi64(_mm256_add_epi64(limbs[i], four_p), other.0[i]);
+		}
+
+		Self::carry(limbs)
+	}
+
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn mul(self, other: Self) -> Self {
+		let f = self.0;
+		let g = other.0;
+
+		// products past the top limb wrap around multiplied by 19, so limb k of
+		// the product is the sum of f[i] * g_wrapped[k + 9 - i]
+		let nineteen = _mm256_set1_epi64x(19);
+		let mut g_wrapped = [_mm256_setzero_si256(); 19];
+
+		for j in 0 .. 10 {
+			g_wrapped[j + 9] = g[j];
+
+			if j > 0 {
+				g_wrapped[j - 1] = _mm256_mul_epu32(g[j], nineteen);
+			}
+		}
+
+		// two odd limbs sit half a bit higher than their sum's limb, which can
+		// only be even, so the odd limbs are doubled for the even sums
+		let mut f_odd_doubled = f;
+
+		for i in (1 .. 10).step_by(2) {
+			f_odd_doubled[i] = _mm256_add_epi64(f[i], f[i]);
+		}
+
+		let mut h = [_mm256_setzero_si256(); 10];
+
+		for k in 0 .. 10 {
+			let f = if k % 2 == 0 {&f_odd_doubled} else {&f};
+
+			for i in 0 .. 10 {
+				h[k] = _mm256_add_epi64(h[k], _mm256_mul_epu32(f[i], g_wrapped[k + 9 - i]));
+			}
+		}
+
+		Self::carry(h)
+	}
+
+	#[cfg(feature = "ed25519")]
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn square(self) -> Self {
+		let f = self.0;
+
+		// the same as for multiplication, but each product of two different
+		// limbs only needs to be computed once and doubled
+		let nineteen = _mm256_set1_epi64x(19);
+		let mut f_wrapped = [_mm256_setzero_si256(); 19];
+
+		for j in 0 .. 10 {
+			f_wrapped[j + 9] = f[j];
+
+			if j > 0 {
+				f_wrapped[j - 1] = _mm256_mul_epu32(f[j], nineteen);
+			}
+		}
+
+		let mut f_doubled = f;
+		let mut f_odd_doubled = f;
+		let mut f_odd_quadrupled = f;
+
+		for i in 0 .. 10 {
+			f_doubled[i] = _mm256_add_epi64(f[i], f[i]);
+
+			if i % 2 == 1 {
+				f_odd_doubled[i] = f_doubled[i];
+				f_odd_quadrupled[i] = _mm256_add_epi64(f_doubled[i], f_doubled[i]);
+			} else {
+				f_odd_quadrupled[i] = f_doubled[i];
+			}
+		}
+
+		let mut h = [_mm256_setzero_si256(); 10];
+
+		for k in 0 .. 10 {
+			// the unordered pairs of limbs summing to k mod 10, starting from the
+			// middle and working outwards in both directions
+			let num_pairs = if k % 2 == 0 {6} else {5};
+
+			for m in 0 .. num_pairs {
+				let i = (usize::div_ceil(k, 2) + m) % 10;
+				let j = (k + 10 - i) % 10;
+
+				let f_i = match (i == j, k % 2 == 0) {
+					(true, _) => f_odd_doubled[i],
+					(false, true) => f_odd_quadrupled[i],
+					(false, false) => f_doubled[i],
+				};
+
+				h[k] = _mm256_add_epi64(h[k], _mm256_mul_epu32(f_i, f_wrapped[k + 9 - i]));
+			}
+		}
+
+		Self::carry(h)
+	}
+
+	// rearranges the lanes, so that lane i of the result is lane `lanes[i]`
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn permute(self, lanes: [i32; 4]) -> Self {
+		let indices = _mm256_setr_epi32(
+			2 * lanes[0], 2 * lanes[0] + 1,
+			2 * lanes[1], 2 * lanes[1] + 1,
+			2 * lanes[2], 2 * lanes[2] + 1,
+			2 * lanes[3], 2 * lanes[3] + 1,
+		);
+
+		let mut limbs = self.0;
+
+		for limb in &mut limbs {
+			*limb = _mm256_permutevar8x32_epi32(*limb, indices);
+		}
+
+		Self(limbs)
+	}
+
+	// takes each lane from `other` where `mask` is all ones, and from `self`
+	// where it's all zeros
+	#[inline]
+	#[target_feature(enable = "avx2")]
+	fn select(self, other: Self, mask: __m256i) -> Self {
+		let mut limbs = self.0;
+
+		for i in 0 .. 10 {
+			limbs[i] = _mm256_or_si256(
+				_mm256_andnot_si256(mask, limbs[i]),
+				_mm256_and_si256(mask, other.0[i]),
+			);
+		}
+
+		Self(limbs)
+	}
+}
+
+// a mask selecting the given lanes
+#[inline]
+#[target_feature(enable = "avx2")]
+fn lane_mask(lanes: [bool; 4]) -> __m256i {
+	let lane = |i: usize| -(lanes[i] as i64);
+	_mm256_setr_epi64x(lane(0), lane(1), lane(2), lane(3))
+}
+
+// a point in extended coordinates, with (x, y, z, t) in the four lanes
+#[cfg(feature = "ed25519")]
+type PointX4 = FieldX4;
+
+// a point prepared for adding, with (y - x, y + x, 2 * d * t, 2 * z) in the
+// four lanes
+#[cfg(feature = "ed25519")]
+type CachedPointX4 = FieldX4;
+
+#[cfg(feature = "ed25519")]
+#[inline]
+#[target_feature(enable = "avx2")]
+fn to_cached(point: Point) -> CachedPointX4 {
+	let Point {x, y, z, t} = point;
+	let t_d = t * ED25519_D;
+
+	FieldX4::from_nums([y - x, y + x, t_d + t_d, z + z])
+}
+
+#[cfg(feature = "ed25519")]
+#[inline]
+#[target_feature(enable = "avx2")]
+fn point_add(point_a: PointX4, point_b: CachedPointX4) -> PointX4 {
+	// (y - x, y + x, t, z) times the cached point gives (a, b, c, d)
+	let y_y_t_z = point_a.permute([1, 1, 3, 2]);
+	let x_x_0_0 = point_a
+		.permute([0, 0, 0, 0])
+		.select(FieldX4([_mm256_setzero_si256(); 10]), lane_mask([false, false, true, true]));
+	let sum = y_y_t_z.add(x_x_0_0);
+	let diff = y_y_t_z.sub(x_x_0_0);
+	let prepared = sum.select(diff, lane_mask([true, false, false, false]));
+
+	let a_b_c_d = prepared.mul(point_b);
+
+	let b_a_d_c = a_b_c_d.permute([1, 0, 3, 2]);
+	let h_h_g_g = a_b_c_d.add(b_a_d_c);
+	let e_ne_f_nf = b_a_d_c.sub(a_b_c_d);
+
+	// (e, g, f, e) times (f, h, g, h) gives (x, y, z, t)
+	let left = e_ne_f_nf
+		.permute([0, 0, 2, 0])
+		.select(h_h_g_g.permute([0, 2, 0, 0]), lane_mask([false, true, false, false]));
+	let right = h_h_g_g
+		.permute([0, 0, 2, 0])
+		.select(e_ne_f_nf.permute([2, 0, 0, 0]), lane_mask([true, false, false, false]));
+
+	left.mul(right)
+}
+
+#[cfg(feature = "ed25519")]
+#[inline]
+#[target_feature(enable = "avx2")]
+fn point_double(point: PointX4) -> PointX4 {
+	// squaring (x, y, z, x + y) gives (a, b, c / 2, (x + y)^2)
+	let x_y_z_x = point.permute([0, 1, 2, 0]);
+	let x_y_z_y = point.permute([0, 1, 2, 1]);
+	let x_y_z_xy = x_y_z_x.select(x_y_z_x.add(x_y_z_y), lane_mask([false, false, false, true]));
+
+	let squares = x_y_z_xy.square();
+
+	let a_a_ch_a = squares.permute([0, 0, 2, 0]);
+	let b_b_ch_b = squares.permute([1, 1, 2, 1]);
+	let h_h_c_h = a_a_ch_a.add(b_b_ch_b);
+	let g_g_0_g = a_a_ch_a.sub(b_b_ch_b);
+	let e_e_x_e = h_h_c_h.sub(squares.permute([3, 3, 3, 3]));
+	let f_f_f_f = h_h_c_h.permute([2, 2, 2, 2]).add(g_g_0_g.permute([0, 0, 0, 0]));
+
+	// (e, g, f, e) times (f, h, g, h) gives (x, y, z, t)
+	let left = e_e_x_e
+		.select(g_g_0_g, lane_mask([false, true, false, false]))
+		.select(f_f_f_f, lane_mask([false, false, true, false]));
+	let right = h_h_c_h
+		.select(f_f_f_f, lane_mask([true, false, false, false]))
+		.select(g_g_0_g.permute([0, 0, 0, 0]), lane_mask([false, false, true, false]));
+
+	left.mul(right)
+}
+
+#[cfg(feature = "ed25519")]
+/// The same as [`super::ed25519::ed25519_mult`], using AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) fn ed25519_mult(scalar: &[u8], num_bits: usize, point: Point) -> Point {
+	let mut swapped = 0;
+
+	let mut point_active = to_cached(NEUTRAL_POINT);
+	let mut point_inactive = to_cached(point);
+
+	let Point {x, y, z, t} = NEUTRAL_POINT;
+	let mut accum = FieldX4::from_nums([x, y, z, t]);
+
+	for current_bit in (0 .. num_bits).rev() {
+		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
+		let swap = _mm256_set1_epi64x(-((swapped ^ current_bit) as i64));
+		let new_active = point_active.select(point_inactive, swap);
+		point_inactive = point_inactive.select(point_active, swap);
+		point_active = new_active;
+		swapped = current_bit;
+
+		accum = point_double(accum);
+		accum = point_add(accum, point_active);
+	}
+
+	let [x, y, z, t] = accum.to_nums();
+
+	Point {x, y, z, t}
+}
+
+#[cfg(feature = "x25519")]
+/// The same as the X25519 Montgomery ladder, using AVX2. Returns the result
+/// in projective coordinates. The scalar must already be clamped.
+#[target_feature(enable = "avx2")]
+pub(super) fn ladder(scalar: &[u8; 32], point: Num, a24: Num) -> (Num, Num) {
+	let x1 = FieldX4::from_nums([point, Num::ZERO, Num::ZERO, Num::ZERO]);
+	let a24 = FieldX4::from_nums([Num::ZERO, Num::ZERO, Num::ZERO, a24]);
+
+	// (x2, z2, x3, z3) in the four lanes
+	let mut state = FieldX4::from_nums([Num::ONE, Num::ZERO, point, Num::ONE]);
+
+	let mut swapped = 0;
+
+	for current_bit in (0 .. 255).rev() {
+		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
+		let swap = _mm256_set1_epi64x(-((swapped ^ current_bit) as i64));
+		state = state.select(state.permute([2, 3, 0, 1]), swap);
+		swapped = current_bit;
+
+		// (a, b, d, c) times (a, b, a, b) gives (aa, bb, da, cb)
+		let x2_x2_x3_x3 = state.permute([0, 0, 2, 2]);
+		let z2_z2_z3_z3 = state.permute([1, 1, 3, 3]);
+		let a_a_c_c = x2_x2_x3_x3.add(z2_z2_z3_z3);
+		let b_b_d_d = x2_x2_x3_x3.sub(z2_z2_z3_z3);
+		let a_b_d_c = a_a_c_c.select(b_b_d_d, lane_mask([false, true, true, false]));
+
+		let products = a_b_d_c.mul(a_b_d_c.permute([0, 1, 0, 1]));
+
+		let swapped_products = products.permute([1, 0, 3, 2]);
+		let sums = products.add(swapped_products);
+		let diffs = products.sub(swapped_products);
+
+		// (dapcb, damcb, aa, e) times (dapcb, damcb, bb, a24) gives
+		// (x3, damcb^2, x2, a24 * e)
+		let dapcb_damcb = sums
+			.permute([2, 2, 2, 2])
+			.select(diffs.permute([2, 2, 2, 2]), lane_mask([false, true, false, false]));
+		let left = dapcb_damcb
+			.select(products.permute([0, 0, 0, 0]), lane_mask([false, false, true, false]))
+			.select(diffs.permute([0, 0, 0, 0]), lane_mask([false, false, false, true]));
+		let right = dapcb_damcb
+			.select(products.permute([1, 1, 1, 1]), lane_mask([false, false, true, false]))
+			.select(a24, lane_mask([false, false, false, true]));
+
+		let second = left.mul(right);
+
+		// (damcb^2, e) times (x1, aa + a24 * e) gives (z3, z2)
+		let left = second
+			.permute([1, 1, 1, 1])
+			.select(diffs.permute([0, 0, 0, 0]), lane_mask([false, true, false, false]));
+		let aa_plus_a24_e = products.permute([0, 0, 0, 0]).add(second.permute([3, 3, 3, 3]));
+		let right = x1.select(aa_plus_a24_e, lane_mask([false, true, true, true]));
+
+		let third = left.mul(right);
+
+		state = second
+			.permute([2, 2, 0, 0])
+			.select(third.permute([1, 1, 0, 0]), lane_mask([false, true, false, true]));
+	}
+
+	state = state.select(state.permute([2, 3, 0, 1]), _mm256_set1_epi64x(-(swapped as i64)));
+
+	let [x2, z2, _, _] = state.to_nums();
+
+	(x2, z2)
+}
@@ -0,0 +1,199 @@
+//! Precomputed multiples of the ed25519 base point, used to speed up scalar
+//! multiplications by the base point. The table is computed at compile time,
+//! so the field arithmetic here is a `const fn` copy of the arithmetic in `num`.
+
+use super::ed25519::{BASE_POINT, ED25519_D};
+use super::num::Num;
+
+/// A point stored as (y + x, y - x, 2 * d * x * y), with z = 1.
+/// This is the representation that makes mixed point addition cheapest.
+#[derive(Copy, Clone)]
+pub struct AffineNielsPoint {
+	pub y_plus_x: Num,
+	pub y_minus_x: Num,
+	pub xy2d: Num,
+}
+
+/// `BASE_TABLE[i][j]` is `(j + 1) * 256 ** i` times the base point.
+pub static BASE_TABLE: [[AffineNielsPoint; 8]; 32] = compute_table();
+
+type Limbs = [u128; 5];
+
+const LOW_51_BITS: u128 = 0x0007_ffff_ffff_ffff;
+
+// 4 * p, added before subtracting so that no limb underflows
+const FOUR_P: Limbs = [
+	0x001f_ffff_ffff_ffb4,
+	0x001f_ffff_ffff_fffc,
+	0x001f_ffff_ffff_fffc,
+	0x001f_ffff_ffff_fffc,
+	0x001f_ffff_ffff_fffc,
+];
+
+const fn carry(mut a: Limbs) -> Limbs {
+	let mut pass = 0;
+
+	while pass < 3 {
+		let mut carry = 0;
+		let mut i = 0;
+
+		while i < 5 {
+			a[i] += carry;
+			carry = a[i] >> 51;
+			a[i] &= LOW_51_BITS;
+			i += 1;
+		}
+
+		a[0] += carry * 19;
+		pass += 1;
+	}
+
+	a
+}
+
+const fn add(a: Limbs, b: Limbs) -> Limbs {
+	carry([a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3], a[4] + b[4]])
+}
+
+const fn sub(a: Limbs, b: Limbs) -> Limbs {
+	carry([
+		a[0] + FOUR_P[0] - b[0],
+		a[1] + FOUR_P[1] - b[1],
+		a[2] + FOUR_P[2] - b[2],
+		a[3] + FOUR_P[3] - b[3],
+		a[4] + FOUR_P[4] - b[4],
+	])
+}
+
+const fn mul(a: Limbs, b: Limbs) -> Limbs {
+	carry([
+		a[0] * b[0] + 19 * (a[1] * b[4] + a[2] * b[3] + a[3] * b[2] + a[4] * b[1]),
+		a[0] * b[1] + a[1] * b[0] + 19 * (a[2] * b[4] + a[3] * b[3] + a[4] * b[2]),
+		a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + 19 * (a[3] * b[4] + a[4] * b[3]),
+		a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + 19 * a[4] * b[4],
+		a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+	])
+}
+
+// computes a ** (p - 2), the same way as Num::recip
+const fn recip(a: Limbs) -> Limbs {
+	let mut acc = [1, 0, 0, 0, 0];
+	let mut i = 0;
+
+	while i < 250 {
+		acc = mul(mul(acc, acc), a);
+		i += 1;
+	}
+
+	// the remaining bits of p - 2 are 01011
+	acc = mul(acc, acc);
+	acc = mul(mul(acc, acc), a);
+	acc = mul(acc, acc);
+	acc = mul(mul(acc, acc), a);
+	acc = mul(mul(acc, acc), a);
+
+	acc
+}
+
+// the extended coordinates (x, y, z, t) of a point
+type ConstPoint = [Limbs; 4];
+
+const fn point_add(a: ConstPoint, b: ConstPoint) -> ConstPoint {
+	let [x_a, y_a, z_a, t_a] = a;
+	let [x_b, y_b, z_b, t_b] = b;
+
+	let a = mul(sub(y_a, x_a), sub(y_b, x_b));
+	let b = mul(add(y_a, x_a), add(y_b, x_b));
+	let c_half = mul(mul(t_a, ED25519_D.segments), t_b);
+	let c = add(c_half, c_half);
+	let d_half = mul(z_a, z_b);
+	let d = add(d_half, d_half);
+	let e = sub(b, a);
+	let f = sub(d, c);
+	let g = add(d, c);
+	let h = add(b, a);
+
+	[mul(e, f), mul(g, h), mul(f, g), mul(e, h)]
+}
+
+const fn point_double(point: ConstPoint) -> ConstPoint {
+	point_add(point, point)
+}
+
+const fn to_affine_niels(point: ConstPoint, z_inv: Limbs) -> AffineNielsPoint {
+	let [x, y, _, _] = point;
+
+	let x = mul(x, z_inv);
+	let y = mul(y, z_inv);
+	let xy = mul(x, y);
+
+	AffineNielsPoint {
+		y_plus_x: Num {segments: add(y, x)},
+		y_minus_x: Num {segments: sub(y, x)},
+		xy2d: Num {segments: mul(add(xy, xy), ED25519_D.segments)},
+	}
+}
+
+const fn compute_table() -> [[AffineNielsPoint; 8]; 32] {
+	let mut points: [ConstPoint; 256] = [[[0; 5]; 4]; 256];
+
+	let mut window_base: ConstPoint = [
+		BASE_POINT.x.segments,
+		BASE_POINT.y.segments,
+		BASE_POINT.z.segments,
+		BASE_POINT.t.segments,
+	];
+
+	let mut i = 0;
+
+	while i < 32 {
+		let mut multiple = window_base;
+		let mut j = 0;
+
+		while j < 8 {
+			points[8 * i + j] = multiple;
+			multiple = point_add(multiple, window_base);
+			j += 1;
+		}
+
+		let mut k = 0;
+
+		while k < 8 {
+			window_base = point_double(window_base);
+			k += 1;
+		}
+
+		i += 1;
+	}
+
+	// inverting every z coordinate separately is too slow for const eval,
+	// so this uses Montgomery's trick to only perform a single inversion
+	let mut prefix_products = [[0; 5]; 256];
+	let mut acc = [1, 0, 0, 0, 0];
+	let mut i = 0;
+
+	while i < 256 {
+		prefix_products[i] = acc;
+		acc = mul(acc, points[i][2]);
+		i += 1;
+	}
+
+	let mut acc_inv = recip(acc);
+
+	let zero = Num {segments: [0; 5]};
+	let empty = AffineNielsPoint {y_plus_x: zero, y_minus_x: zero, xy2d: zero};
+	let mut out = [[empty; 8]; 32];
+	let mut i = 256;
+
+	while i > 0 {
+		i -= 1;
+
+		// acc_inv is currently the inverse of the product of the first i + 1 z coordinates
+		let z_inv = mul(acc_inv, prefix_products[i]);
+		acc_inv = mul(acc_inv, points[i][2]);
+
+		out[i / 8][i % 8] = to_affine_niels(points[i], z_inv);
+	}
+
+	out
+}
@@ -0,0 +1,550 @@
+//! Blind Schnorr signatures over the Edwards25519 curve.
+//! A user gets a signer to sign a message without the signer learning the
+//! message, or being able to link the final signature to the signing session
+//! it came from. This is the building block of privacy-preserving token
+//! issuance: the signer can sign one token per authenticated request, and the
+//! tokens can later be redeemed anonymously.
+//!
+//! The unblinded result has the same encoding as an Ed25519 signature, but
+//! its challenge is domain separated, so it only verifies with [`verify`].
+//!
+//! A session takes one round trip:
+//! 1. The signer calls [`SignerNonce::generate`] and sends the [`Commitment`]
+//!    to the user.
+//! 2. The user calls [`blind`] and sends the [`BlindedChallenge`] back.
+//! 3. The signer calls [`sign_blinded`] and sends the [`BlindedSignature`]
+//!    to the user.
+//! 4. The user calls [`unblind`] to get the final signature.
+//!
+//! # Concurrent sessions
+//!
+//! Blind Schnorr signatures are only secure if the signer completes each
+//! session before starting the next one. With a few hundred sessions open at
+//! once, a user can forge an extra signature using the
+//! [ROS attack](https://eprint.iacr.org/2020/945), so signers should strictly
+//! limit how many sessions are open at the same time.
+//!
+//! # Security
+//!
+//! The signer answers whatever challenge the user sends, without seeing what
+//! it was computed from, so its key is a Schnorr signing oracle. If the same
+//! key were trusted by anything else, such as an Ed25519 verifier, users
+//! could get signatures over messages of their choice for it. The signer's
+//! key is therefore a separate [`BlindSigningKey`] type, derived from its seed
+//! differently than an Ed25519 key, so that even the same seed gives an
+//! unrelated key. It must still only ever be used for blind issuance.
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha512;
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	mul_num_mod_l,
+	num_mod_l_from_32_bytes,
+	num_mod_l_from_64_bytes,
+	num_mod_l_to_bytes,
+};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	is_torsion_free,
+	point_add,
+	points_equal,
+	scalar_mult,
+	verify_less_than_l,
+	Point,
+	Signature,
+};
+
+/// An error returned by the blind signature protocol functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The bytes passed in are not a valid encoding of a protocol message.
+	InvalidEncoding,
+	/// The signer's public key isn't a point in the prime-order subgroup.
+	InvalidPublicKey,
+	/// The signer's response is not valid for the blinded challenge.
+	InvalidResponse,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid blind signature protocol message",
+			Error::InvalidPublicKey => "invalid blind signature public key",
+			Error::InvalidResponse => "invalid blind signature response",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> [u64; 4] {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(b"libkrypton/blind signature/");
+	hasher.add_bytes(tag);
+	hasher.add_bytes(&[0]);
+
+	for part in parts {
+		hasher.add_bytes(part);
+	}
+
+	num_mod_l_from_64_bytes(&hasher.out())
+}
+
+// the challenge of a final signature, which differs from the Ed25519 one so
+// that the signatures are only valid for blind signature keys
+fn challenge(big_r: &[u8; 32], pub_key: &BlindVerifyingKey, message: &[u8]) -> [u64; 4] {
+	hash_to_scalar(b"challenge", &[big_r, &pub_key.compressed, message])
+}
+
+/// A private key for issuing blind signatures. Any 32 bytes are a valid
+/// private key, so these should be generated by a secure random source. See
+/// the [module documentation](self#security) for why this isn't an Ed25519
+/// key.
+pub struct BlindSigningKey {
+	seed: [u8; 32],
+	secret_scalar: [u8; 32],
+	verifying_key: BlindVerifyingKey,
+}
+
+impl BlindSigningKey {
+	/// Creates a signing key from its 32-byte representation.
+	pub fn from_bytes(seed: [u8; 32]) -> Self {
+		let secret_scalar = num_mod_l_to_bytes(hash_to_scalar(b"secret key", &[&seed]));
+		let point = basepoint_mult(&secret_scalar);
+
+		let verifying_key = BlindVerifyingKey {
+			compressed: compress(point),
+			point,
+		};
+
+		Self {seed, secret_scalar, verifying_key}
+	}
+
+	/// Generates a new signing key using `rng`, which must be a
+	/// cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		let key = Self::from_bytes(seed);
+		zeroize(&mut seed);
+
+		key
+	}
+
+	/// Returns the 32-byte representation of this signing key. This must be
+	/// kept secret.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.seed
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> BlindVerifyingKey {
+		self.verifying_key
+	}
+}
+
+impl Drop for BlindSigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for BlindSigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for BlindSigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for BlindSigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BlindSigningKey")
+			.field("verifying_key", &self.verifying_key)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A public key for verifying blind signatures. Constructing one checks that
+/// the key is a point in the prime-order subgroup.
+#[derive(Copy, Clone)]
+pub struct BlindVerifyingKey {
+	compressed: [u8; 32],
+	point: Point,
+}
+
+impl BlindVerifyingKey {
+	/// Creates a verifying key from its 32-byte representation, returning an
+	/// error if it's not a point in the prime-order subgroup.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		match decompress(bytes) {
+			Some(point) if is_torsion_free(point) => Ok(Self {compressed: bytes, point}),
+			_ => Err(Error::InvalidPublicKey),
+		}
+	}
+
+	/// Returns the 32-byte representation of this verifying key.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.compressed
+	}
+}
+
+impl PartialEq for BlindVerifyingKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.compressed == other.compressed
+	}
+}
+
+impl Eq for BlindVerifyingKey {}
+
+impl fmt::Debug for BlindVerifyingKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("BlindVerifyingKey").field(&self.compressed).finish()
+	}
+}
+
+/// The signer's secret nonce for a single session. It is consumed by
+/// [`sign_blinded`] so it can't accidentally be reused, since answering two
+/// challenges with the same nonce reveals the private key.
+pub struct SignerNonce {
+	k: [u8; 32],
+}
+
+impl SignerNonce {
+	/// Generates a nonce for a new session. `randomness` must be freshly
+	/// generated by a secure random source for every session; it's mixed
+	/// with the private key, but reusing it is as bad as reusing the nonce.
+	pub fn generate(priv_key: &BlindSigningKey, randomness: [u8; 32]) -> (Self, Commitment) {
+		let k = num_mod_l_to_bytes(hash_to_scalar(b"signer nonce", &[&randomness, &priv_key.seed]));
+		let commitment = compress(basepoint_mult(&k));
+
+		(Self {k}, Commitment(commitment))
+	}
+
+	/// Like [`SignerNonce::generate`], but generates the randomness with
+	/// `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate_with_rng<R: RngCore + CryptoRng>(priv_key: &BlindSigningKey, rng: &mut R) -> (Self, Commitment) {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::generate(priv_key, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+}
+
+impl Drop for SignerNonce {
+	fn drop(&mut self) {
+		zeroize(&mut self.k);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SignerNonce {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.k);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SignerNonce {}
+
+// deliberately doesn't print the nonce
+impl fmt::Debug for SignerNonce {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SignerNonce").finish_non_exhaustive()
+	}
+}
+
+/// The signer's commitment to its nonce, sent to the user.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Commitment([u8; 32]);
+
+impl Commitment {
+	/// Creates a commitment from its 32-byte representation, returning an
+	/// error if it's not a point in the prime-order subgroup.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		match decompress(bytes) {
+			Some(point) if is_torsion_free(point) => Ok(Self(bytes)),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+
+	/// Returns the 32-byte representation of this commitment.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+}
+
+/// The challenge the user sends to the signer, which hides the message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlindedChallenge([u8; 32]);
+
+impl BlindedChallenge {
+	/// Creates a blinded challenge from its 32-byte representation, returning
+	/// an error if it's not a canonically encoded scalar.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		if !verify_less_than_l(&bytes) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 32-byte representation of this blinded challenge.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+}
+
+/// The signer's response to a blinded challenge.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlindedSignature([u8; 32]);
+
+impl BlindedSignature {
+	/// Creates a blinded signature from its 32-byte representation,
+	/// returning an error if it's not a canonically encoded scalar.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		if !verify_less_than_l(&bytes) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 32-byte representation of this blinded signature.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+}
+
+/// The values the user needs to keep between [`blind`] and [`unblind`].
+/// These would let the signer link the final signature to the session, so
+/// they shouldn't be shared.
+pub struct BlindingState {
+	pub_key: BlindVerifyingKey,
+	commitment: Commitment,
+	blinded_challenge: BlindedChallenge,
+	alpha: [u8; 32],
+	big_r: [u8; 32],
+}
+
+impl Drop for BlindingState {
+	fn drop(&mut self) {
+		zeroize(&mut self.alpha);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for BlindingState {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.alpha);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for BlindingState {}
+
+// deliberately doesn't print the blinding factor
+impl fmt::Debug for BlindingState {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BlindingState").finish_non_exhaustive()
+	}
+}
+
+/// Blinds `message` for signing by the owner of `pub_key`, given the
+/// signer's commitment. `randomness` must be freshly generated by a secure
+/// random source, since it's what hides the message from the signer.
+pub fn blind(
+	pub_key: &BlindVerifyingKey,
+	commitment: &Commitment,
+	message: &[u8],
+	randomness: [u8; 32],
+) -> (BlindingState, BlindedChallenge) {
+	let alpha = hash_to_scalar(b"alpha", &[&randomness]);
+	let beta = hash_to_scalar(b"beta", &[&randomness]);
+
+	// R = R' + alpha * B + beta * A
+	let signer_r = decompress(commitment.0).unwrap();
+	let big_r = point_add(
+		point_add(signer_r, basepoint_mult(&num_mod_l_to_bytes(alpha))),
+		scalar_mult(beta, pub_key.point),
+	);
+	let big_r = compress(big_r);
+
+	let challenge = challenge(&big_r, pub_key, message);
+	let blinded_challenge = BlindedChallenge(num_mod_l_to_bytes(add_num_mod_l(challenge, beta)));
+
+	let state = BlindingState {
+		pub_key: *pub_key,
+		commitment: *commitment,
+		blinded_challenge,
+		alpha: num_mod_l_to_bytes(alpha),
+		big_r,
+	};
+
+	(state, blinded_challenge)
+}
+
+/// Like [`blind`], but generates the randomness with `rng`, which must be a
+/// cryptographically secure random number generator.
+#[cfg(feature = "rand_core")]
+pub fn blind_with_rng<R: RngCore + CryptoRng>(
+	pub_key: &BlindVerifyingKey,
+	commitment: &Commitment,
+	message: &[u8],
+	rng: &mut R,
+) -> (BlindingState, BlindedChallenge) {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = blind(pub_key, commitment, message, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Answers the user's blinded challenge, consuming the nonce for this
+/// session.
+pub fn sign_blinded(
+	priv_key: &BlindSigningKey,
+	nonce: SignerNonce,
+	challenge: &BlindedChallenge,
+) -> BlindedSignature {
+	// s' = k + c' * x
+	let s = add_num_mod_l(
+		num_mod_l_from_32_bytes(&nonce.k),
+		mul_num_mod_l(num_mod_l_from_32_bytes(&challenge.0), num_mod_l_from_32_bytes(&priv_key.secret_scalar)),
+	);
+
+	BlindedSignature(num_mod_l_to_bytes(s))
+}
+
+/// Checks the signer's response and unblinds it into a signature over the
+/// message passed to [`blind`], which can be checked with [`verify`].
+pub fn unblind(state: BlindingState, response: &BlindedSignature) -> Result<Signature, Error> {
+	// s' * B = R' + c' * A
+	let left_side = basepoint_mult(&response.0);
+	let right_side = point_add(
+		decompress(state.commitment.0).unwrap(),
+		scalar_mult(num_mod_l_from_32_bytes(&state.blinded_challenge.0), state.pub_key.point),
+	);
+
+	if !bool::from(points_equal(left_side, right_side)) {
+		return Err(Error::InvalidResponse);
+	}
+
+	let s = add_num_mod_l(num_mod_l_from_32_bytes(&response.0), num_mod_l_from_32_bytes(&state.alpha));
+
+	let mut out = [0; 64];
+	out[0 .. 32].copy_from_slice(&state.big_r);
+	out[32 .. 64].copy_from_slice(&num_mod_l_to_bytes(s));
+
+	// s is reduced mod l, so this can't fail
+	Ok(Signature::from_bytes(out).unwrap())
+}
+
+/// Verifies a blind `signature` over a `message`, as returned by [`unblind`].
+pub fn verify(pub_key: &BlindVerifyingKey, message: &[u8], signature: &Signature) -> bool {
+	let big_r_bytes = signature.as_bytes()[0 .. 32].try_into().unwrap();
+	let big_r = match decompress(big_r_bytes) {
+		Some(val) => val,
+		None => return false,
+	};
+
+	// the signature type guarantees that this is less than l
+	let big_s: [u8; 32] = signature.as_bytes()[32 .. 64].try_into().unwrap();
+
+	// s * B = R + c * A
+	let left_side = basepoint_mult(&big_s);
+	let right_side = point_add(big_r, scalar_mult(challenge(&big_r_bytes, pub_key, message), pub_key.point));
+
+	points_equal(left_side, right_side).into()
+}
+
+#[test]
+fn test_blind_signing() {
+	let priv_key = BlindSigningKey::from_bytes([0x42; 32]);
+	let pub_key = priv_key.verifying_key();
+	let message = b"token 1234";
+
+	let (nonce, commitment) = SignerNonce::generate(&priv_key, [1; 32]);
+	let commitment = Commitment::from_bytes(commitment.to_bytes()).unwrap();
+
+	let (state, challenge) = blind(&pub_key, &commitment, message, [2; 32]);
+	let challenge = BlindedChallenge::from_bytes(challenge.to_bytes()).unwrap();
+
+	let response = sign_blinded(&priv_key, nonce, &challenge);
+	let signature = unblind(state, &response).unwrap();
+
+	assert!(verify(&pub_key, message, &signature));
+	assert!(!verify(&pub_key, b"token 1235", &signature));
+
+	// the signer never saw anything from the final signature
+	assert_ne!(signature.as_bytes()[0 .. 32], commitment.to_bytes());
+	assert_ne!(signature.as_bytes()[32 .. 64], response.to_bytes());
+}
+
+#[test]
+fn test_separate_from_ed25519() {
+	use super::ed25519::{SigningKey, VerifyingKey};
+
+	let priv_key = BlindSigningKey::from_bytes([0x42; 32]);
+	let pub_key = priv_key.verifying_key();
+
+	// the same seed gives an unrelated key
+	let ed25519_key = SigningKey::from_bytes([0x42; 32]);
+	assert_ne!(pub_key.to_bytes(), ed25519_key.verifying_key().to_bytes());
+
+	// and a blind signature isn't an Ed25519 signature under the blind key
+	let (nonce, commitment) = SignerNonce::generate(&priv_key, [1; 32]);
+	let (state, challenge) = blind(&pub_key, &commitment, b"message", [2; 32]);
+	let signature = unblind(state, &sign_blinded(&priv_key, nonce, &challenge)).unwrap();
+
+	assert!(verify(&pub_key, b"message", &signature));
+	assert!(!VerifyingKey::from_bytes(pub_key.to_bytes()).unwrap().verify(b"message", &signature));
+}
+
+#[test]
+fn test_rejects_invalid_response() {
+	let priv_key = BlindSigningKey::from_bytes([0x42; 32]);
+	let other_key = BlindSigningKey::from_bytes([0x43; 32]);
+
+	let (nonce, commitment) = SignerNonce::generate(&other_key, [1; 32]);
+	let (state, challenge) = blind(&priv_key.verifying_key(), &commitment, b"message", [2; 32]);
+
+	let response = sign_blinded(&other_key, nonce, &challenge);
+	assert_eq!(unblind(state, &response).unwrap_err(), Error::InvalidResponse);
+
+	let mut identity = [0; 32];
+	identity[0] = 1;
+	assert!(Commitment::from_bytes(identity).is_ok());
+
+	// a point of order 2
+	let mut small_order = [0xff; 32];
+	small_order[0] = 0xec;
+	small_order[31] = 0x7f;
+	assert_eq!(Commitment::from_bytes(small_order), Err(Error::InvalidEncoding));
+	assert_eq!(BlindVerifyingKey::from_bytes(small_order).unwrap_err(), Error::InvalidPublicKey);
+}
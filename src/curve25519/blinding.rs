@@ -0,0 +1,114 @@
+//! Countermeasures against power and electromagnetic analysis, used by the
+//! hardened variants of Ed25519 signing and X25519 key exchange.
+//!
+//! Constant-time code keeps secrets out of timings, but the power drawn while
+//! a scalar multiplication runs can still depend on the bits of the scalar and
+//! the coordinates being computed with. Averaging many traces of the same
+//! secret scalar with the same point can then recover it. Two random values
+//! are mixed in to stop this:
+//!
+//! * Scalar blinding: a random multiple of the group order l is added to the
+//!   scalar, which doesn't change the result, since l times any point of the
+//!   prime-order subgroup is the neutral point, but changes the bits the
+//!   multiplication steps through each time.
+//! * Point randomization: the projective coordinates of the input point are
+//!   all multiplied by a random field element, which gives the same point but
+//!   different intermediate values each time. The element is zero with
+//!   negligible probability, about 2 ** -255.
+
+use rand_core::{CryptoRng, RngCore};
+
+// l = 2 ** 252 + 27742317777372353535851937790883648493
+const L: [u64; 4] = [
+	0x5812631a5cf5d3ed,
+	0x14def9dea2f79cd6,
+	0x0000000000000000,
+	0x1000000000000000,
+];
+
+/// The length of a blinded scalar, which is a 256-bit scalar plus a 64-bit
+/// multiple of at most 8l.
+pub const BLINDED_LEN: usize = 40;
+
+/// The number of bits of a blinded scalar that can be set.
+pub const BLINDED_BITS: usize = 320;
+
+/// Returns `scalar` plus a random multiple of `cofactor` times l, as a
+/// little-endian integer. Points with a small-order component, like the ones
+/// X25519 accepts, need a `cofactor` of 8 so that the added multiple of l
+/// still takes them to the neutral point.
+pub fn blind_scalar<R: RngCore + CryptoRng>(scalar: &[u8; 32], cofactor: u64, rng: &mut R) -> [u8; BLINDED_LEN] {
+	debug_assert!(cofactor <= 8);
+
+	let mask = rng.next_u64() as u128;
+	let mut limbs = [0u64; BLINDED_LEN / 8];
+
+	// mask * l fits in 316 bits
+	let mut carry = 0;
+
+	for i in 0 .. 4 {
+		let product = mask * L[i] as u128 + carry;
+		limbs[i] = product as u64;
+		carry = product >> 64;
+	}
+
+	limbs[4] = carry as u64;
+
+	// times the cofactor fits in 319 bits
+	let mut carry = 0;
+
+	for limb in limbs.iter_mut() {
+		let product = *limb as u128 * cofactor as u128 + carry;
+		*limb = product as u64;
+		carry = product >> 64;
+	}
+
+	// plus the scalar fits in 320 bits
+	let mut carry = 0;
+
+	for i in 0 .. 5 {
+		let scalar_limb = match i {
+			0 .. 4 => u64::from_le_bytes(scalar[i * 8 .. i * 8 + 8].try_into().unwrap()),
+			_ => 0,
+		};
+
+		let sum = limbs[i] as u128 + scalar_limb as u128 + carry;
+		limbs[i] = sum as u64;
+		carry = sum >> 64;
+	}
+
+	let mut out = [0; BLINDED_LEN];
+
+	for i in 0 .. 5 {
+		out[i * 8 .. i * 8 + 8].copy_from_slice(&limbs[i].to_le_bytes());
+	}
+
+	out
+}
+
+#[test]
+fn test_blind_scalar() {
+	use crate::test_util::FixedRng;
+
+	// a mask of zero leaves the scalar as it was
+	let scalar = [0xff; 32];
+	let blinded = blind_scalar(&scalar, 8, &mut FixedRng::new(&[0]));
+	assert_eq!(blinded[.. 32], scalar);
+	assert_eq!(blinded[32 ..], [0; 8]);
+
+	// 0 + 1 * l is l
+	let blinded = blind_scalar(&[0; 32], 1, &mut FixedRng::new(&1u64.to_le_bytes()));
+	assert_eq!(blinded[.. 8], 0x5812631a5cf5d3edu64.to_le_bytes());
+	assert_eq!(blinded[24 .. 32], 0x1000000000000000u64.to_le_bytes());
+
+	// the largest mask and cofactor, with a scalar of all ones, uses every bit
+	// up to BLINDED_BITS without overflowing
+	let blinded = blind_scalar(&[0xff; 32], 8, &mut FixedRng::new(&[0xff]));
+	assert_eq!(blinded, [
+		0x97, 0x60, 0x51, 0x18, 0x2d, 0xe7, 0x6c, 0x3f,
+		0xb5, 0xb8, 0xf1, 0xcf, 0xdd, 0x49, 0x9b, 0x19,
+		0xb2, 0xe6, 0xbc, 0x17, 0xf5, 0xce, 0xf7, 0xa6,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80,
+	]);
+}
@@ -0,0 +1,702 @@
+//! Distributed key generation for threshold Ed25519 signatures, following
+//! the Pedersen DKG with proofs of knowledge from the
+//! [FROST paper](https://eprint.iacr.org/2020/852), with Feldman commitments
+//! so every share can be checked. A group of `max_signers` participants ends
+//! up with shares of a secret key that no one ever learns, any `threshold` of
+//! which can sign for the group key. The shares are ordinary Shamir shares
+//! with the participants' identifiers as their x-coordinates, as used by
+//! FROST(Ed25519, SHA-512) from [IETF RFC 9591](https://datatracker.ietf.org/doc/html/rfc9591).
+//!
+//! Key generation takes two rounds of communication:
+//! 1. Each participant calls [`part1`] and broadcasts the resulting
+//!    [`Round1Package`] to everyone else.
+//! 2. Once every round 1 package has been received, each participant calls
+//!    [`part2`], which checks them, and sends each other participant their
+//!    own [`Round2Package`] over a confidential and authenticated channel.
+//! 3. Once every round 2 package has been received, each participant calls
+//!    [`part3`], which checks the shares against the commitments from round
+//!    1 and combines them into a [`KeyPackage`].
+//!
+//! If [`part3`] finds an invalid share, the receiver broadcasts a
+//! [`Complaint`], and the accused participant has to reveal the share it
+//! sent. Everyone then checks it with [`resolve_complaint`] and disqualifies
+//! either the accused or the accuser, before calling [`part3`] again with
+//! only the round 2 packages of qualified participants.
+//!
+//! The round 1 broadcast must be reliable: every participant has to receive
+//! the same package from each sender, which usually means echoing them.
+
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha512;
+use crate::zeroize::zeroize;
+
+use super::ed25519::VerifyingKey;
+use super::point::EdwardsPoint;
+use super::scalar::Scalar;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+/// An error returned by the key generation functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The threshold is 0 or more than the number of participants, or an
+	/// identifier is 0 or more than the number of participants.
+	InvalidParameters,
+	/// A package isn't canonically encoded, or has the wrong number of
+	/// commitments.
+	InvalidEncoding,
+	/// The packages passed in come from unknown or repeated participants, or
+	/// some are missing.
+	InvalidParticipants,
+	/// The proof of knowledge in the round 1 package of `culprit` is
+	/// invalid, so it should be disqualified.
+	InvalidProof {
+		/// The identifier of the participant that sent the package.
+		culprit: u16,
+	},
+	/// The share sent by `culprit` doesn't match its commitments, so a
+	/// [`Complaint`] should be broadcast against it.
+	InvalidShare {
+		/// The identifier of the participant that sent the share.
+		culprit: u16,
+	},
+	/// Fewer than `threshold` participants are left.
+	TooFewParticipants,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidParameters => "invalid dkg threshold or identifier",
+			Error::InvalidEncoding => "invalid dkg package",
+			Error::InvalidParticipants => "unknown, repeated or missing dkg participants",
+			Error::InvalidProof {..} => "invalid dkg proof of knowledge",
+			Error::InvalidShare {..} => "invalid dkg share",
+			Error::TooFewParticipants => "too few dkg participants left",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+fn identifier_scalar(identifier: u16) -> Scalar {
+	let mut bytes = [0; 32];
+	bytes[.. 2].copy_from_slice(&identifier.to_le_bytes());
+	Scalar::from_bytes_mod_order(&bytes)
+}
+
+fn hash_to_scalar(prefix: &[&[u8]], parts: &[&[u8]]) -> Scalar {
+	let mut hasher = Sha512::new();
+	prefix.iter().chain(parts).for_each(|x| hasher.add_bytes(x));
+	Scalar::from_bytes_mod_order_wide(&hasher.out())
+}
+
+// the challenge of the proof of knowledge of the constant coefficient
+fn challenge(identifier: u16, commitment: &EdwardsPoint, r: &EdwardsPoint) -> Scalar {
+	hash_to_scalar(&[CONTEXT_STRING, b"dkg"], &[
+		&identifier_scalar(identifier).to_bytes(),
+		&commitment.compress(),
+		&r.compress(),
+	])
+}
+
+// evaluates the polynomial with the given coefficients at x
+fn evaluate_polynomial(coefficients: &[Scalar], x: Scalar) -> Scalar {
+	coefficients.iter().rev().fold(Scalar::ZERO, |acc, &coefficient| acc * x + coefficient)
+}
+
+// evaluates the polynomial committed to by `commitments` at x, in the exponent
+fn evaluate_commitments(commitments: &[EdwardsPoint], x: Scalar) -> EdwardsPoint {
+	let x = x.to_bytes();
+	commitments.iter().rev().fold(EdwardsPoint::IDENTITY, |acc, &commitment| acc.mul(&x) + commitment)
+}
+
+fn check_parameters(identifier: u16, max_signers: u16, threshold: u16) -> Result<(), Error> {
+	if threshold == 0 || threshold > max_signers || identifier == 0 || identifier > max_signers {
+		return Err(Error::InvalidParameters);
+	}
+
+	Ok(())
+}
+
+/// The package each participant broadcasts in round 1: commitments to the
+/// coefficients of its secret polynomial, and a proof of knowledge of the
+/// constant coefficient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round1Package {
+	commitments: Vec<EdwardsPoint>,
+	proof_r: EdwardsPoint,
+	proof_mu: Scalar,
+}
+
+impl Round1Package {
+	/// Parses a package from its encoding: `threshold` commitments followed
+	/// by the proof, 32 bytes each. Every point must be in the prime-order
+	/// subgroup.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() < 96 || !bytes.len().is_multiple_of(32) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let (points, proof_mu) = bytes.split_at(bytes.len() - 32);
+
+		let mut points = points.chunks(32)
+			.map(|x| match EdwardsPoint::decompress(x.try_into().unwrap()) {
+				Some(point) if point.is_torsion_free() => Ok(point),
+				_ => Err(Error::InvalidEncoding),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let proof_r = points.pop().unwrap();
+		let proof_mu = Scalar::from_canonical_bytes(proof_mu.try_into().unwrap()).ok_or(Error::InvalidEncoding)?;
+
+		Ok(Self {commitments: points, proof_r, proof_mu})
+	}
+
+	/// Encodes this package.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut out = Vec::with_capacity(32 * (self.commitments.len() + 2));
+
+		for commitment in &self.commitments {
+			out.extend_from_slice(&commitment.compress());
+		}
+
+		out.extend_from_slice(&self.proof_r.compress());
+		out.extend_from_slice(&self.proof_mu.to_bytes());
+		out
+	}
+
+	/// Returns the commitments to the coefficients, starting with the
+	/// constant one, which is the participant's contribution to the group
+	/// key.
+	pub fn commitments(&self) -> &[EdwardsPoint] {
+		&self.commitments
+	}
+
+	fn verify_proof(&self, identifier: u16) -> bool {
+		let c = challenge(identifier, &self.commitments[0], &self.proof_r);
+		EdwardsPoint::mul_base(&self.proof_mu.to_bytes()) - self.commitments[0].mul(&c.to_bytes()) == self.proof_r
+	}
+}
+
+/// A participant's secret state between [`part1`] and [`part2`], holding the
+/// coefficients of its secret polynomial.
+pub struct Round1Secret {
+	identifier: u16,
+	max_signers: u16,
+	coefficients: Vec<[u8; 32]>,
+	package: Round1Package,
+}
+
+impl Drop for Round1Secret {
+	fn drop(&mut self) {
+		self.coefficients.iter_mut().for_each(|x| zeroize(x));
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Round1Secret {
+	fn zeroize(&mut self) {
+		self.coefficients.iter_mut().for_each(|x| zeroize(x));
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Round1Secret {}
+
+// deliberately doesn't print the coefficients
+impl fmt::Debug for Round1Secret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Round1Secret")
+			.field("identifier", &self.identifier)
+			.field("max_signers", &self.max_signers)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Starts key generation as the participant `identifier`, from 1 to
+/// `max_signers`, for a group where any `threshold` participants can sign.
+/// `randomness` must be freshly generated by a secure random source, since
+/// the secret polynomial is derived from it. Returns the secret state to
+/// keep for [`part2`], and the package to broadcast.
+pub fn part1(
+	identifier: u16,
+	max_signers: u16,
+	threshold: u16,
+	randomness: [u8; 32],
+) -> Result<(Round1Secret, Round1Package), Error> {
+	check_parameters(identifier, max_signers, threshold)?;
+
+	let prefix: [&[u8]; 2] = [b"libkrypton/dkg/", &identifier.to_be_bytes()];
+
+	let coefficients = (0 .. threshold)
+		.map(|i| hash_to_scalar(&prefix, &[b"coefficient", &randomness, &i.to_be_bytes()]))
+		.collect::<Vec<_>>();
+
+	let commitments = coefficients.iter()
+		.map(|x| EdwardsPoint::mul_base(&x.to_bytes()))
+		.collect::<Vec<_>>();
+
+	// a Schnorr proof of knowledge of the constant coefficient, which stops
+	// rogue-key attacks on the group key
+	let k = hash_to_scalar(&prefix, &[b"proof nonce", &randomness]);
+	let proof_r = EdwardsPoint::mul_base(&k.to_bytes());
+	let proof_mu = k + coefficients[0] * challenge(identifier, &commitments[0], &proof_r);
+
+	let package = Round1Package {commitments, proof_r, proof_mu};
+
+	let secret = Round1Secret {
+		identifier,
+		max_signers,
+		coefficients: coefficients.iter().map(|x| x.to_bytes()).collect(),
+		package: package.clone(),
+	};
+
+	Ok((secret, package))
+}
+
+/// Like [`part1`], but generates the randomness with `rng`, which must be a
+/// cryptographically secure random number generator.
+#[cfg(feature = "rand_core")]
+pub fn part1_with_rng<R: RngCore + CryptoRng>(
+	identifier: u16,
+	max_signers: u16,
+	threshold: u16,
+	rng: &mut R,
+) -> Result<(Round1Secret, Round1Package), Error> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = part1(identifier, max_signers, threshold, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// The secret share of one participant's polynomial sent to another in
+/// round 2. It must only be sent over a confidential channel.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Round2Package(Scalar);
+
+impl Round2Package {
+	/// Parses a package from its 32-byte encoding, which must be a canonical
+	/// scalar.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		Scalar::from_canonical_bytes(bytes).map(Self).ok_or(Error::InvalidEncoding)
+	}
+
+	/// Encodes this package as 32 bytes.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0.to_bytes()
+	}
+}
+
+// deliberately doesn't print the share
+impl fmt::Debug for Round2Package {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Round2Package(..)")
+	}
+}
+
+/// A participant's secret state between [`part2`] and [`part3`], holding its
+/// share of its own polynomial and everyone's commitments.
+pub struct Round2Secret {
+	identifier: u16,
+	max_signers: u16,
+	own_share: [u8; 32],
+	packages: Vec<(u16, Round1Package)>,
+}
+
+impl Drop for Round2Secret {
+	fn drop(&mut self) {
+		zeroize(&mut self.own_share);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Round2Secret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.own_share);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Round2Secret {}
+
+// deliberately doesn't print the share
+impl fmt::Debug for Round2Secret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Round2Secret")
+			.field("identifier", &self.identifier)
+			.field("max_signers", &self.max_signers)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Checks the round 1 packages received from every other participant,
+/// along with their identifiers, and computes the shares to send to them.
+/// Returns the secret state to keep for [`part3`], and the package to send
+/// to each other participant.
+pub fn part2(
+	secret: Round1Secret,
+	received: &[(u16, Round1Package)],
+) -> Result<(Round2Secret, Vec<(u16, Round2Package)>), Error> {
+	let threshold = secret.package.commitments.len();
+
+	if received.len() != secret.max_signers as usize - 1 {
+		return Err(Error::InvalidParticipants);
+	}
+
+	for (i, (identifier, package)) in received.iter().enumerate() {
+		let known = (1 ..= secret.max_signers).contains(identifier) && *identifier != secret.identifier;
+		let repeated = received[.. i].iter().any(|(x, _)| x == identifier);
+
+		if !known || repeated {
+			return Err(Error::InvalidParticipants);
+		}
+
+		if package.commitments.len() != threshold {
+			return Err(Error::InvalidEncoding);
+		}
+
+		if !package.verify_proof(*identifier) {
+			return Err(Error::InvalidProof {culprit: *identifier});
+		}
+	}
+
+	let coefficients = secret.coefficients.iter()
+		.map(Scalar::from_bytes_mod_order)
+		.collect::<Vec<_>>();
+
+	let shares = received.iter()
+		.map(|(identifier, _)| (*identifier, Round2Package(evaluate_polynomial(&coefficients, identifier_scalar(*identifier)))))
+		.collect();
+
+	let mut packages = received.to_vec();
+	packages.push((secret.identifier, secret.package.clone()));
+
+	let round2_secret = Round2Secret {
+		identifier: secret.identifier,
+		max_signers: secret.max_signers,
+		own_share: evaluate_polynomial(&coefficients, identifier_scalar(secret.identifier)).to_bytes(),
+		packages,
+	};
+
+	Ok((round2_secret, shares))
+}
+
+/// A participant's result of key generation: its identifier and secret
+/// signing share, along with the group's public keys.
+pub struct KeyPackage {
+	identifier: u16,
+	signing_share: [u8; 32],
+	public_key_package: PublicKeyPackage,
+}
+
+impl KeyPackage {
+	/// Returns this participant's identifier.
+	pub fn identifier(&self) -> u16 {
+		self.identifier
+	}
+
+	/// Returns this participant's secret signing share.
+	pub fn signing_share(&self) -> Scalar {
+		Scalar::from_bytes_mod_order(&self.signing_share)
+	}
+
+	/// Returns the group's public keys.
+	pub fn public_key_package(&self) -> &PublicKeyPackage {
+		&self.public_key_package
+	}
+}
+
+impl Drop for KeyPackage {
+	fn drop(&mut self) {
+		zeroize(&mut self.signing_share);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for KeyPackage {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.signing_share);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for KeyPackage {}
+
+// deliberately doesn't print the signing share
+impl fmt::Debug for KeyPackage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("KeyPackage")
+			.field("identifier", &self.identifier)
+			.field("public_key_package", &self.public_key_package)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The group's public keys, which every participant computes identically:
+/// the group verifying key and each participant's verifying share, the
+/// public key matching its signing share.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKeyPackage {
+	threshold: u16,
+	verifying_key: VerifyingKey,
+	verifying_shares: Vec<EdwardsPoint>,
+}
+
+impl PublicKeyPackage {
+	/// Returns the number of participants needed to sign.
+	pub fn threshold(&self) -> u16 {
+		self.threshold
+	}
+
+	/// Returns the group's public key, which signatures made by any
+	/// `threshold` participants verify under.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+
+	/// Returns the verifying share of the participant `identifier`, or
+	/// [`None`] if there's no such participant.
+	pub fn verifying_share(&self, identifier: u16) -> Option<EdwardsPoint> {
+		self.verifying_shares.get((identifier as usize).checked_sub(1)?).copied()
+	}
+}
+
+/// Checks the round 2 packages received from other participants, along
+/// with their identifiers, and combines them into this participant's key
+/// package. Every participant that isn't disqualified must be included;
+/// the first time this is called, that's all of them.
+pub fn part3(secret: &Round2Secret, received: &[(u16, Round2Package)]) -> Result<KeyPackage, Error> {
+	let threshold = secret.packages[0].1.commitments.len() as u16;
+
+	// the participants that are taking part, starting with this one
+	let mut qualified = Vec::with_capacity(received.len() + 1);
+	qualified.push(secret.packages.last().unwrap());
+
+	let mut signing_share = Scalar::from_bytes_mod_order(&secret.own_share);
+	let own_identifier = identifier_scalar(secret.identifier);
+
+	for (i, (identifier, share)) in received.iter().enumerate() {
+		let package = secret.packages.iter()
+			.find(|(x, _)| x == identifier && *x != secret.identifier)
+			.ok_or(Error::InvalidParticipants)?;
+
+		if received[.. i].iter().any(|(x, _)| x == identifier) {
+			return Err(Error::InvalidParticipants);
+		}
+
+		if EdwardsPoint::mul_base(&share.0.to_bytes()) != evaluate_commitments(&package.1.commitments, own_identifier) {
+			return Err(Error::InvalidShare {culprit: *identifier});
+		}
+
+		signing_share = signing_share + share.0;
+		qualified.push(package);
+	}
+
+	if qualified.len() < threshold as usize {
+		return Err(Error::TooFewParticipants);
+	}
+
+	let group_key = qualified.iter().fold(EdwardsPoint::IDENTITY, |acc, (_, package)| acc + package.commitments[0]);
+	let verifying_key = VerifyingKey::from_bytes(group_key.compress()).map_err(|_| Error::InvalidEncoding)?;
+
+	let verifying_shares = (1 ..= secret.max_signers)
+		.map(|identifier| {
+			let x = identifier_scalar(identifier);
+			qualified.iter().fold(EdwardsPoint::IDENTITY, |acc, (_, package)| acc + evaluate_commitments(&package.commitments, x))
+		})
+		.collect();
+
+	Ok(KeyPackage {
+		identifier: secret.identifier,
+		signing_share: signing_share.to_bytes(),
+		public_key_package: PublicKeyPackage {threshold, verifying_key, verifying_shares},
+	})
+}
+
+/// A complaint that `accuser` received an invalid share from `accused`,
+/// broadcast after [`part3`] returns [`Error::InvalidShare`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Complaint {
+	/// The participant that received the invalid share.
+	pub accuser: u16,
+	/// The participant that sent it.
+	pub accused: u16,
+}
+
+impl Complaint {
+	/// Parses a complaint from its 4-byte encoding.
+	pub fn from_bytes(bytes: [u8; 4]) -> Self {
+		Self {
+			accuser: u16::from_be_bytes([bytes[0], bytes[1]]),
+			accused: u16::from_be_bytes([bytes[2], bytes[3]]),
+		}
+	}
+
+	/// Encodes this complaint as 4 bytes.
+	pub fn to_bytes(&self) -> [u8; 4] {
+		let [a, b] = self.accuser.to_be_bytes();
+		let [c, d] = self.accused.to_be_bytes();
+		[a, b, c, d]
+	}
+}
+
+/// Resolves a complaint given the round 1 package of the accused and the
+/// share it revealed in response. Returns `true` if the share is invalid,
+/// so the accused must be disqualified, or `false` if it's valid, so the
+/// accuser must be disqualified instead. An accused participant that
+/// doesn't reveal its share must be disqualified too.
+pub fn resolve_complaint(complaint: &Complaint, accused_package: &Round1Package, revealed_share: &Round2Package) -> bool {
+	let expected = evaluate_commitments(&accused_package.commitments, identifier_scalar(complaint.accuser));
+	EdwardsPoint::mul_base(&revealed_share.0.to_bytes()) != expected
+}
+
+#[cfg(test)]
+fn run_part1(max_signers: u16, threshold: u16) -> Vec<(Round1Secret, Round1Package)> {
+	(1 ..= max_signers)
+		.map(|i| part1(i, max_signers, threshold, [i as u8; 32]).unwrap())
+		.collect()
+}
+
+#[cfg(test)]
+fn others(round1: &[(Round1Secret, Round1Package)], identifier: u16) -> Vec<(u16, Round1Package)> {
+	round1.iter()
+		.filter(|(secret, _)| secret.identifier != identifier)
+		.map(|(secret, package)| (secret.identifier, package.clone()))
+		.collect()
+}
+
+// the secret that the shares of the given participants interpolate to
+#[cfg(test)]
+fn interpolate(shares: &[(u16, Scalar)]) -> Scalar {
+	shares.iter().fold(Scalar::ZERO, |acc, &(i, share)| {
+		let lagrange = shares.iter()
+			.filter(|(j, _)| *j != i)
+			.fold(Scalar::ONE, |acc, &(j, _)| {
+				acc * identifier_scalar(j) * (identifier_scalar(j) - identifier_scalar(i)).invert()
+			});
+
+		acc + lagrange * share
+	})
+}
+
+#[test]
+fn test_dkg() {
+	let (max_signers, threshold) = (5, 3);
+	let round1 = run_part1(max_signers, threshold);
+
+	let round1_packages = (1 ..= max_signers).map(|i| others(&round1, i)).collect::<Vec<_>>();
+	let mut round2 = Vec::new();
+
+	for ((secret, package), received) in round1.into_iter().zip(&round1_packages) {
+		assert_eq!(Round1Package::from_bytes(&package.to_bytes()).unwrap(), package);
+		round2.push(part2(secret, received).unwrap());
+	}
+
+	let key_packages = (1 ..= max_signers)
+		.map(|i| {
+			let received = round2.iter()
+				.filter(|(secret, _)| secret.identifier != i)
+				.map(|(secret, shares)| {
+					let share = shares.iter().find(|(x, _)| *x == i).unwrap().1;
+					(secret.identifier, Round2Package::from_bytes(share.to_bytes()).unwrap())
+				})
+				.collect::<Vec<_>>();
+
+			part3(&round2[i as usize - 1].0, &received).unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	let public = key_packages[0].public_key_package();
+
+	for key_package in &key_packages {
+		assert_eq!(key_package.public_key_package(), public);
+
+		let verifying_share = EdwardsPoint::mul_base(&key_package.signing_share().to_bytes());
+		assert_eq!(public.verifying_share(key_package.identifier()), Some(verifying_share));
+	}
+
+	// any threshold participants can recover the group's secret key, but
+	// fewer can't
+	let shares = key_packages.iter().map(|x| (x.identifier(), x.signing_share())).collect::<Vec<_>>();
+
+	for subset in [&shares[.. 3], &shares[2 ..], &[shares[0], shares[2], shares[4]]] {
+		let group_secret = interpolate(subset);
+		assert_eq!(EdwardsPoint::mul_base(&group_secret.to_bytes()).compress(), public.verifying_key().to_bytes());
+	}
+
+	let group_secret = interpolate(&shares[.. 2]);
+	assert_ne!(EdwardsPoint::mul_base(&group_secret.to_bytes()).compress(), public.verifying_key().to_bytes());
+}
+
+#[test]
+fn test_invalid_packages() {
+	let mut round1 = run_part1(3, 2);
+	let mut received = others(&round1, 1);
+
+	// a proof made for another identifier is rejected
+	received[0].1 = part1(1, 3, 2, [9; 32]).unwrap().1;
+
+	let (secret, _) = round1.remove(0);
+	assert_eq!(part2(secret, &received).unwrap_err(), Error::InvalidProof {culprit: 2});
+
+	assert_eq!(part1(0, 3, 2, [0; 32]).unwrap_err(), Error::InvalidParameters);
+	assert_eq!(part1(1, 3, 4, [0; 32]).unwrap_err(), Error::InvalidParameters);
+	assert_eq!(Round1Package::from_bytes(&[0; 64]).unwrap_err(), Error::InvalidEncoding);
+}
+
+#[test]
+fn test_complaints() {
+	let round1 = run_part1(3, 2);
+	let packages = round1.iter().map(|(_, package)| package.clone()).collect::<Vec<_>>();
+	let mut round2 = round1.into_iter().map(|(secret, _)| {
+		let received = others_from(&packages, secret.identifier);
+		part2(secret, &received).unwrap()
+	}).collect::<Vec<_>>();
+
+	// participant 2 sends participant 1 a bad share
+	let bad_share = Round2Package(round2[1].1[0].1.0 + Scalar::ONE);
+	round2[1].1[0].1 = bad_share;
+
+	let received = vec![(2, bad_share), (3, round2[2].1[0].1)];
+	let error = part3(&round2[0].0, &received).unwrap_err();
+	assert_eq!(error, Error::InvalidShare {culprit: 2});
+
+	let complaint = Complaint {accuser: 1, accused: 2};
+	assert_eq!(Complaint::from_bytes(complaint.to_bytes()), complaint);
+
+	// revealing the bad share disqualifies participant 2, while the real one
+	// would have disqualified participant 1
+	assert!(resolve_complaint(&complaint, &packages[1], &bad_share));
+	let good_share = Round2Package(bad_share.0 - Scalar::ONE);
+	assert!(!resolve_complaint(&complaint, &packages[1], &good_share));
+
+	// the others carry on without participant 2
+	let key_1 = part3(&round2[0].0, &received[1 ..]).unwrap();
+	let key_3 = part3(&round2[2].0, &[(1, round2[0].1[1].1)]).unwrap();
+	assert_eq!(key_1.public_key_package(), key_3.public_key_package());
+
+	let group_secret = interpolate(&[(1, key_1.signing_share()), (3, key_3.signing_share())]);
+	assert_eq!(EdwardsPoint::mul_base(&group_secret.to_bytes()).compress(), key_1.public_key_package().verifying_key().to_bytes());
+
+	assert_eq!(part3(&round2[2].0, &[]).unwrap_err(), Error::TooFewParticipants);
+}
+
+#[cfg(test)]
+fn others_from(packages: &[Round1Package], identifier: u16) -> Vec<(u16, Round1Package)> {
+	(1 ..= packages.len() as u16)
+		.filter(|&i| i != identifier)
+		.map(|i| (i, packages[i as usize - 1].clone()))
+		.collect()
+}
@@ -0,0 +1,259 @@
+//! Implemented according to [IETF RFC 9381](https://datatracker.ietf.org/doc/html/rfc9381),
+//! using the ECVRF-EDWARDS25519-SHA512-TAI ciphersuite.
+//! A verifiable random function maps an input to a pseudorandom output using
+//! a private key, along with a proof that lets anyone with the public key
+//! check that the output was computed correctly. Unlike a signature, there is
+//! exactly one valid output for each key and input.
+//!
+//! VRF keys are ordinary Ed25519 keys.
+
+use core::convert::TryInto;
+
+use crate::sha2::Sha512;
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	mul_num_mod_l,
+	num_mod_l_from_32_bytes,
+	num_mod_l_from_64_bytes,
+	num_mod_l_to_bytes,
+};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	ed25519_mult,
+	mul_by_cofactor,
+	negate,
+	point_add,
+	points_equal,
+	verify_less_than_l,
+	Error,
+	Point,
+	SigningKey,
+	VerifyingKey,
+	NEUTRAL_POINT,
+};
+
+const SUITE_STRING: u8 = 0x03;
+
+/// A VRF proof, which can be turned into the VRF output with
+/// [`Proof::to_hash`] once it has been verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Proof([u8; 80]);
+
+impl Proof {
+	/// Creates a proof from its 80-byte representation, returning an error if
+	/// it's not canonically encoded.
+	pub fn from_bytes(bytes: [u8; 80]) -> Result<Self, Error> {
+		let gamma = bytes[0 .. 32].try_into().unwrap();
+		let s = bytes[48 .. 80].try_into().unwrap();
+
+		if decompress(gamma).is_none() || !verify_less_than_l(s) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 80-byte representation of this proof.
+	pub fn to_bytes(&self) -> [u8; 80] {
+		self.0
+	}
+
+	/// Computes the VRF output from this proof. This should only be used on
+	/// proofs that were created by [`ecvrf_prove`] or checked with
+	/// [`ecvrf_verify`], which already returns the output.
+	pub fn to_hash(&self) -> [u8; 64] {
+		// the gamma point was checked when the proof was constructed
+		let gamma = decompress(self.0[0 .. 32].try_into().unwrap()).unwrap();
+		proof_to_hash(gamma)
+	}
+}
+
+impl TryFrom<&[u8]> for Proof {
+	type Error = Error;
+
+	fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+		match bytes.try_into() {
+			Ok(bytes) => Self::from_bytes(bytes),
+			Err(_) => Err(Error::InvalidLength),
+		}
+	}
+}
+
+fn proof_to_hash(gamma: Point) -> [u8; 64] {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&[SUITE_STRING, 0x03]);
+	hasher.add_bytes(&compress(mul_by_cofactor(gamma)));
+	hasher.add_bytes(&[0x00]);
+	hasher.out()
+}
+
+// hashes the input to a point using the try-and-increment method
+// not constant-time, but only the public key and the input are involved
+fn encode_to_curve(pub_key: &[u8; 32], alpha: &[u8]) -> Point {
+	for counter in 0 ..= 255 {
+		let mut hasher = Sha512::new();
+		hasher.add_bytes(&[SUITE_STRING, 0x01]);
+		hasher.add_bytes(pub_key);
+		hasher.add_bytes(alpha);
+		hasher.add_bytes(&[counter, 0x00]);
+
+		let digest = hasher.out();
+
+		if let Some(point) = decompress(digest[0 .. 32].try_into().unwrap()) {
+			return mul_by_cofactor(point);
+		}
+	}
+
+	// each attempt succeeds with probability about 1/2
+	unreachable!("failed to hash to curve 256 times in a row")
+}
+
+fn generate_challenge(points: [&[u8; 32]; 5]) -> [u8; 16] {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&[SUITE_STRING, 0x02]);
+
+	for point in points {
+		hasher.add_bytes(point);
+	}
+
+	hasher.add_bytes(&[0x00]);
+
+	hasher.out()[0 .. 16].try_into().unwrap()
+}
+
+/// Computes a VRF proof for the input `alpha`. The VRF output is
+/// [`Proof::to_hash`] of the result.
+pub fn ecvrf_prove(priv_key: &SigningKey, alpha: &[u8]) -> Proof {
+	let pub_key = priv_key.verifying_key();
+	let secret_scalar = priv_key.secret_scalar();
+
+	let h = encode_to_curve(pub_key.as_bytes(), alpha);
+	let h_bytes = compress(h);
+	let gamma = compress(ed25519_mult(secret_scalar, 255, h));
+
+	let mut nonce_hasher = Sha512::new();
+	nonce_hasher.add_bytes(priv_key.prefix());
+	nonce_hasher.add_bytes(&h_bytes);
+
+	let k = num_mod_l_from_64_bytes(&nonce_hasher.out());
+	let mut k_bytes = num_mod_l_to_bytes(k);
+
+	let c = generate_challenge([
+		pub_key.as_bytes(),
+		&h_bytes,
+		&gamma,
+		&compress(basepoint_mult(&k_bytes)),
+		&compress(ed25519_mult(&k_bytes, 253, h)),
+	]);
+
+	let mut c_bytes = [0; 32];
+	c_bytes[0 .. 16].copy_from_slice(&c);
+
+	let x = num_mod_l_from_32_bytes(secret_scalar);
+	let s = add_num_mod_l(k, mul_num_mod_l(num_mod_l_from_32_bytes(&c_bytes), x));
+
+	zeroize(&mut k_bytes);
+
+	let mut out = [0; 80];
+	out[0 .. 32].copy_from_slice(&gamma);
+	out[32 .. 48].copy_from_slice(&c);
+	out[48 .. 80].copy_from_slice(&num_mod_l_to_bytes(s));
+
+	Proof(out)
+}
+
+/// Verifies a VRF proof for the input `alpha`, returning the VRF output if
+/// it's valid. Public keys of small order are rejected.
+/// Not constant-time, since it doesn't involve secret data.
+pub fn ecvrf_verify(pub_key: &VerifyingKey, alpha: &[u8], proof: &Proof) -> Result<[u8; 64], Error> {
+	let y = pub_key.point;
+
+	if points_equal(mul_by_cofactor(y), NEUTRAL_POINT).into() {
+		return Err(Error::InvalidPublicKey);
+	}
+
+	let gamma = decompress(proof.0[0 .. 32].try_into().unwrap()).unwrap();
+	let c: [u8; 16] = proof.0[32 .. 48].try_into().unwrap();
+	let s: [u8; 32] = proof.0[48 .. 80].try_into().unwrap();
+
+	let h = encode_to_curve(pub_key.as_bytes(), alpha);
+
+	// u = s * B - c * Y, v = s * H - c * Gamma
+	let u = point_add(basepoint_mult(&s), ed25519_mult(&c, 128, negate(y)));
+	let v = point_add(ed25519_mult(&s, 253, h), ed25519_mult(&c, 128, negate(gamma)));
+
+	let expected_c = generate_challenge([
+		pub_key.as_bytes(),
+		&compress(h),
+		proof.0[0 .. 32].try_into().unwrap(),
+		&compress(u),
+		&compress(v),
+	]);
+
+	if c != expected_c {
+		return Err(Error::InvalidSignature);
+	}
+
+	Ok(proof_to_hash(gamma))
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix B.3 of the RFC
+#[test]
+fn test_rfc9381_vectors() {
+	let vectors: [(&str, &[u8], &str, &str); 3] = [
+		(
+			"9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+			b"",
+			"8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+			"90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+		),
+		(
+			"4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+			&[0x72],
+			"f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+			"eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+		),
+		(
+			"c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+			&[0xaf, 0x82],
+			"9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+			"645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+		),
+	];
+
+	for (priv_key, alpha, proof, beta) in vectors {
+		let priv_key = SigningKey::from_bytes(hex(priv_key));
+		let pub_key = priv_key.verifying_key();
+
+		let proof_bytes = hex(proof);
+		let beta = hex(beta);
+
+		let computed = ecvrf_prove(&priv_key, alpha);
+		assert_eq!(computed.to_bytes(), proof_bytes);
+		assert_eq!(computed.to_hash(), beta);
+
+		let parsed = Proof::from_bytes(proof_bytes).unwrap();
+		assert_eq!(ecvrf_verify(&pub_key, alpha, &parsed), Ok(beta));
+		assert_eq!(ecvrf_verify(&pub_key, b"other", &parsed), Err(Error::InvalidSignature));
+	}
+}
+
+#[test]
+fn test_rejects_small_order_keys() {
+	let priv_key = SigningKey::from_bytes([0x11; 32]);
+	let proof = ecvrf_prove(&priv_key, b"input");
+
+	// the identity point
+	let mut identity = [0; 32];
+	identity[0] = 1;
+
+	let small_order_key = VerifyingKey::from_bytes(identity).unwrap();
+	assert_eq!(ecvrf_verify(&small_order_key, b"input", &proof), Err(Error::InvalidPublicKey));
+}
@@ -1,8 +1,17 @@
 //! Implemented according to [IETF RFC 8032](https://datatracker.ietf.org/doc/html/rfc8032).
 
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
+use core::fmt;
 
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 use crate::sha2::{sha512, Sha512};
+use crate::zeroize::zeroize;
 
 use super::arith_mod_l::{
 	add_num_mod_l,
@@ -11,18 +20,23 @@ use super::arith_mod_l::{
 	num_mod_l_from_64_bytes,
 	num_mod_l_to_bytes,
 };
-use super::conditional_swap;
+#[cfg(feature = "rand_core")]
+use super::arith_mod_l::neg_num_mod_l;
+#[cfg(feature = "basepoint-table")]
+use super::basepoint_table::{AffineNielsPoint, BASE_TABLE};
+#[cfg(feature = "rand_core")]
+use super::blinding::{blind_scalar, BLINDED_BITS};
 use super::num::Num;
 
 #[derive(Copy, Clone)]
-struct Point {
-	x: Num,
-	y: Num,
-	z: Num,
-	t: Num,
+pub(super) struct Point {
+	pub(super) x: Num,
+	pub(super) y: Num,
+	pub(super) z: Num,
+	pub(super) t: Num,
 }
 
-const ED25519_D: Num = Num {
+pub(super) const ED25519_D: Num = Num {
 	segments: [
 		0x34dca135978a3,
 		0x1a8283b156ebd,
@@ -32,17 +46,7 @@ const ED25519_D: Num = Num {
 	],
 };
 
-const SQRT_MINUS_1: Num = Num {
-	segments: [
-		0x61b274a0ea0b0,
-		0x0d5a5fc8f189d,
-		0x7ef5e9cbd0c60,
-		0x78595a6804c9e,
-		0x2b8324804fc1d,
-	],
-};
-
-const BASE_POINT: Point = Point {
+pub(super) const BASE_POINT: Point = Point {
 	x: Num {
 		segments: [
 			0x62d608f25d51a,
@@ -73,10 +77,12 @@ const BASE_POINT: Point = Point {
 	},
 };
 
-fn compress(point: Point) -> [u8; 32] {
-	let x = point.x / point.z;
+pub(super) fn compress(point: Point) -> [u8; 32] {
+	let mut x = point.x / point.z;
 	let mut y = point.y / point.z;
 
+	// the sign is the parity of the canonical representative of x
+	x.full_modular_reduction();
 	y.full_modular_reduction();
 
 	let mut out = y.to_bytes();
@@ -90,7 +96,7 @@ fn compress(point: Point) -> [u8; 32] {
 }
 
 // does not work in constant time, should never be used on secret data
-fn decompress(mut compressed: [u8; 32]) -> Option<Point> {
+pub(super) fn decompress(mut compressed: [u8; 32]) -> Option<Point> {
 	let x_sign = compressed[31] & 0x80 != 0;
 	compressed[31] &= 0x7f;
 
@@ -102,58 +108,27 @@ fn decompress(mut compressed: [u8; 32]) -> Option<Point> {
 	let y = Num::from_bytes(compressed);
 	let y_squared = y * y;
 
-	let mut u = y_squared - Num::ONE;
+	// x^2 = (y^2 - 1) / (d y^2 + 1)
+	let u = y_squared - Num::ONE;
 	let v = ED25519_D * y_squared + Num::ONE;
 
-	let v_cubed = v * v * v;
-	let v_to_the_7 = v_cubed * v_cubed * v;
-
-	// exponentiate u v^7 to (p - 5) / 8
-
-	let factor = u * v_to_the_7;
-
-	let mut accum = Num::ONE;
-
-	for _ in 0 .. 250 {
-		accum *= accum;
-		accum *= factor;
-	}
-
-	accum *= accum;
-	accum *= accum;
-	accum *= factor;
-
-	let mut x = u * v_cubed * accum;
-	let mut vxx = v * x * x;
-
-	let mut neg_u = -u;
+	let (is_square, x) = Num::sqrt_ratio(&u, &v);
 
-	// we're gonna be comparing these
-	// so we canonicalize them by performing a full modular reduction
-	u.full_modular_reduction();
-	neg_u.full_modular_reduction();
-	vxx.full_modular_reduction();
+	// the root is nonnegative, so x = 0 can't take on the sign bit
+	let x_sign = Choice::from(x_sign as u8);
+	let is_valid = is_square & !(x.ct_eq(&Num::ZERO) & x_sign);
 
-	if vxx.segments == neg_u.segments {
-		x *= SQRT_MINUS_1;
-	} else if vxx.segments != u.segments {
-		return None;
-	}
-
-	if x.segments == [0, 0, 0, 0, 0] && x_sign {
-		return None;
-	}
-
-	if (x.segments[0] & 0x01 != 0) ^ x_sign {
-		x = -x;
-	}
+	let mut x = Num::conditional_select(&x, &-x, x_sign);
+	x.full_modular_reduction();
 
-	Some(Point {
+	let point = Point {
 		x,
 		y,
 		z: Num::ONE,
 		t: x * y,
-	})
+	};
+
+	CtOption::new(point, is_valid).into()
 }
 
 // helper function for decompress, not constant-time
@@ -179,22 +154,23 @@ fn verify_less_than_p(compressed: &[u8; 32]) -> bool {
 	return false;
 }
 
+// the order of the prime-order subgroup, in little-endian
+const L_BYTES: [u8; 32] = [
+	0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+	0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
 // TODO: you can probably rewrite the above function to me more like this one
 // helper function for verifying signarutes, not constant-time
-fn verify_less_than_l(value: &[u8; 32]) -> bool {
-	let limit = [
-		0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
-		0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
-		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
-	];
-
+pub(super) fn verify_less_than_l(value: &[u8; 32]) -> bool {
 	for i in (0 .. 32).rev() {
-		if value[i] > limit[i] {
+		if value[i] > L_BYTES[i] {
 			return false;
 		}
 
-		if value[i] < limit[i] {
+		if value[i] < L_BYTES[i] {
 			return true;
 		}
 	}
@@ -202,14 +178,24 @@ fn verify_less_than_l(value: &[u8; 32]) -> bool {
 	return false;
 }
 
-const NEUTRAL_POINT: Point = Point {
+pub(super) const NEUTRAL_POINT: Point = Point {
 	x: Num::ZERO,
 	y: Num::ONE,
 	z: Num::ONE,
 	t: Num::ZERO,
 };
 
-fn ed25519_mult(scalar: &[u8], num_bits: usize, point: Point) -> Point {
+pub(super) fn ed25519_mult(scalar: &[u8], num_bits: usize, point: Point) -> Point {
+	#[cfg(all(target_arch = "x86_64", not(feature = "fiat-crypto")))]
+	if super::avx2::is_available() {
+		// SAFETY: the CPU was just checked to support AVX2
+		return unsafe {super::avx2::ed25519_mult(scalar, num_bits, point)};
+	}
+
+	ed25519_mult_portable(scalar, num_bits, point)
+}
+
+fn ed25519_mult_portable(scalar: &[u8], num_bits: usize, point: Point) -> Point {
 	let mut swapped = 0;
 
 	let mut point_active = NEUTRAL_POINT;
@@ -219,10 +205,10 @@ fn ed25519_mult(scalar: &[u8], num_bits: usize, point: Point) -> Point {
 
 	for current_bit in (0 .. num_bits).rev() {
 		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
-		conditional_swap_points(
-			swapped ^ current_bit,
+		Point::conditional_swap(
 			&mut point_active,
 			&mut point_inactive,
+			Choice::from(swapped ^ current_bit),
 		);
 		swapped = current_bit;
 
@@ -233,16 +219,156 @@ fn ed25519_mult(scalar: &[u8], num_bits: usize, point: Point) -> Point {
 	accum
 }
 
-fn conditional_swap_points(swap: u8, point_a: &mut Point, point_b: &mut Point) {
-	conditional_swap(swap, &mut point_a.x, &mut point_b.x);
-	conditional_swap(swap, &mut point_a.y, &mut point_b.y);
-	conditional_swap(swap, &mut point_a.z, &mut point_b.z);
-	conditional_swap(swap, &mut point_a.t, &mut point_b.t);
+// multiplies a point by a scalar that has been reduced mod l
+pub(super) fn scalar_mult(scalar: [u64; 4], point: Point) -> Point {
+	ed25519_mult(&num_mod_l_to_bytes(scalar), 253, point)
+}
+
+pub(super) fn mul_by_cofactor(point: Point) -> Point {
+	point_double(point_double(point_double(point)))
+}
+
+pub(super) fn negate(point: Point) -> Point {
+	Point {x: -point.x, y: point.y, z: point.z, t: -point.t}
+}
+
+// multiplies every coordinate by `z`, which gives another representation of
+// the same point
+#[cfg(feature = "rand_core")]
+fn rescale(point: Point, z: Num) -> Point {
+	Point {x: point.x * z, y: point.y * z, z: point.z * z, t: point.t * z}
+}
+
+// compares two points in constant time, by cross-multiplying with the other
+// point's z rather than converting either to affine coordinates
+pub(super) fn points_equal(point_a: Point, point_b: Point) -> Choice {
+	let x_equal = (point_a.x * point_b.z).ct_eq(&(point_b.x * point_a.z));
+	let y_equal = (point_a.y * point_b.z).ct_eq(&(point_b.y * point_a.z));
+
+	x_equal & y_equal
+}
+
+// checks that a point has no small-order component, meaning it's in the
+// subgroup generated by the base point
+pub(super) fn is_torsion_free(point: Point) -> bool {
+	points_equal(ed25519_mult(&L_BYTES, 253, point), NEUTRAL_POINT).into()
+}
+
+// multiplies the base point by a scalar less than 2 ** 255
+#[cfg(not(feature = "basepoint-table"))]
+pub(super) fn basepoint_mult(scalar: &[u8; 32]) -> Point {
+	ed25519_mult(scalar, 255, BASE_POINT)
+}
+
+// multiplies the base point by a scalar less than 2 ** 255, using the
+// precomputed table of multiples of the base point
+#[cfg(feature = "basepoint-table")]
+pub(super) fn basepoint_mult(scalar: &[u8; 32]) -> Point {
+	// recode the scalar as 64 signed base-16 digits between -8 and 8
+	// this only works if the top bit of the scalar is clear
+	let mut digits = [0i8; 64];
+
+	for i in 0 .. 32 {
+		digits[2 * i] = (scalar[i] & 0x0f) as i8;
+		digits[2 * i + 1] = (scalar[i] >> 4) as i8;
+	}
+
+	let mut carry = 0;
+
+	for i in 0 .. 63 {
+		digits[i] += carry;
+		carry = (digits[i] + 8) >> 4;
+		digits[i] -= carry << 4;
+	}
+
+	digits[63] += carry;
+
+	// the table only holds multiples of 256 ** i, so the odd digits
+	// get added first and then multiplied by 16 all at once
+	let mut accum = NEUTRAL_POINT;
+
+	for i in (1 .. 64).step_by(2) {
+		accum = point_add_niels(accum, select_niels(i / 2, digits[i]));
+	}
+
+	for _ in 0 .. 4 {
+		accum = point_double(accum);
+	}
+
+	for i in (0 .. 64).step_by(2) {
+		accum = point_add_niels(accum, select_niels(i / 2, digits[i]));
+	}
+
+	accum
+}
+
+// looks up `digit` times 256 ** `window` times the base point in constant time
+#[cfg(feature = "basepoint-table")]
+fn select_niels(window: usize, digit: i8) -> AffineNielsPoint {
+	let negative = (digit as u8) >> 7;
+	let abs = (digit as i16 * (1 - 2 * negative as i16)) as u8;
+
+	let mut out = AffineNielsPoint {
+		y_plus_x: Num::ONE,
+		y_minus_x: Num::ONE,
+		xy2d: Num::ZERO,
+	};
+
+	for (i, entry) in BASE_TABLE[window].iter().enumerate() {
+		let diff = (abs ^ (i as u8 + 1)) as u32;
+		let equal = Choice::from((diff.wrapping_sub(1) >> 31) as u8);
+
+		out.y_plus_x.conditional_assign(&entry.y_plus_x, equal);
+		out.y_minus_x.conditional_assign(&entry.y_minus_x, equal);
+		out.xy2d.conditional_assign(&entry.xy2d, equal);
+	}
+
+	// negating a point negates x, which swaps y + x and y - x
+	let negative = Choice::from(negative);
+	let neg_xy2d = -out.xy2d;
+	Num::conditional_swap(&mut out.y_plus_x, &mut out.y_minus_x, negative);
+	out.xy2d.conditional_assign(&neg_xy2d, negative);
+
+	out
+}
+
+#[cfg(feature = "basepoint-table")]
+fn point_add_niels(point_a: Point, point_b: AffineNielsPoint) -> Point {
+	let Point {x, y, z, t} = point_a;
+
+	let a = (y - x) * point_b.y_minus_x;
+	let b = (y + x) * point_b.y_plus_x;
+	let c = t * point_b.xy2d;
+	let d = z + z;
+	let e = b - a;
+	let f = d - c;
+	let g = d + c;
+	let h = b + a;
+
+	Point {x: e * f, y: g * h, t: e * h, z: f * g}
+}
+
+impl ConditionallySelectable for Point {
+	fn conditional_select(point_a: &Self, point_b: &Self, choice: Choice) -> Self {
+		Self {
+			x: Num::conditional_select(&point_a.x, &point_b.x, choice),
+			y: Num::conditional_select(&point_a.y, &point_b.y, choice),
+			z: Num::conditional_select(&point_a.z, &point_b.z, choice),
+			t: Num::conditional_select(&point_a.t, &point_b.t, choice),
+		}
+	}
+
+	fn conditional_swap(point_a: &mut Self, point_b: &mut Self, choice: Choice) {
+		Num::conditional_swap(&mut point_a.x, &mut point_b.x, choice);
+		Num::conditional_swap(&mut point_a.y, &mut point_b.y, choice);
+		Num::conditional_swap(&mut point_a.z, &mut point_b.z, choice);
+		Num::conditional_swap(&mut point_a.t, &mut point_b.t, choice);
+	}
 }
 
 // these functions are out of the RFC, including most of the variable naming
 
-fn point_add(point_a: Point, point_b: Point) -> Point {
+pub(super) fn point_add(point_a: Point, point_b: Point) -> Point {
 	let Point {x: x_a, y: y_a, z: z_a, t: t_a} = point_a;
 	let Point {x: x_b, y: y_b, z: z_b, t: t_b} = point_b;
 
@@ -260,7 +386,7 @@ fn point_add(point_a: Point, point_b: Point) -> Point {
 	Point {x: e * f, y: g * h, t: e * h, z: f * g}
 }
 
-fn point_double(point: Point) -> Point {
+pub(super) fn point_double(point: Point) -> Point {
 	let Point {x, y, z, ..} = point;
 
 	let a = x * x;
@@ -276,57 +402,453 @@ fn point_double(point: Point) -> Point {
 	Point {x: e * f, y: g * h, t: e * h, z: f * g}
 }
 
-fn clamp_and_mult(buffer: &mut [u8; 32]) -> [u8; 32] {
-	buffer[0] &= 0xf8;
-	buffer[31] &= 0x7f;
-	buffer[31] |= 0x40;
+/// An error returned when bytes can't be interpreted as an Ed25519 key or
+/// signature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The byte slice passed in was not the expected length.
+	InvalidLength,
+	/// The bytes passed in do not encode a point on the curve.
+	InvalidPublicKey,
+	/// The bytes passed in are not a canonically encoded signature.
+	InvalidSignature,
+	/// The message passed to a [`StreamingSigner`] differed between passes.
+	MessageMismatch,
+	/// The bytes or text passed in are not a valid encoding of a key.
+	InvalidEncoding,
+	/// An encrypted key could not be decrypted, usually because the
+	/// passphrase was incorrect.
+	DecryptionFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidLength => "invalid length",
+			Error::InvalidPublicKey => "invalid ed25519 public key",
+			Error::InvalidSignature => "invalid ed25519 signature",
+			Error::MessageMismatch => "message differed between signing passes",
+			Error::InvalidEncoding => "invalid key encoding",
+			Error::DecryptionFailed => "failed to decrypt key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// An Ed25519 private key, used to create signatures. Any 32 bytes are a valid
+/// private key, so these should be generated by a secure random source.
+///
+/// The secret scalar and nonce prefix are derived from the seed once, when the
+/// key is created, so reusing a signing key for many signatures is cheaper
+/// than creating it again for each one.
+#[derive(Clone)]
+pub struct SigningKey {
+	seed: [u8; 32],
+	secret_scalar: [u8; 32],
+	prefix: [u8; 32],
+	verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+	/// Creates a signing key from its 32-byte representation.
+	pub fn from_bytes(seed: [u8; 32]) -> Self {
+		let (secret_scalar, prefix) = expand_priv_key(&seed);
+		let point = basepoint_mult(&secret_scalar);
 
-	let pub_key_point = ed25519_mult(buffer, 255, BASE_POINT);
+		let verifying_key = VerifyingKey {
+			compressed: compress(point),
+			point,
+		};
 
-	compress(pub_key_point)
+		Self {seed, secret_scalar, prefix, verifying_key}
+	}
+
+	/// Returns the 32-byte representation of this signing key. This must be
+	/// kept secret.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.seed
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+
+	/// Converts this signing key to an X25519 private key, for use with
+	/// [`x25519`](crate::x25519). The X25519 public key corresponding to the
+	/// result is [`VerifyingKey::to_x25519`] of this key's verifying key.
+	pub fn to_x25519(&self) -> [u8; 32] {
+		// x25519 clamps the scalar anyways, but the clamped scalar is returned
+		// so the output matches that of other implementations
+		self.secret_scalar
+	}
+
+	// the clamped secret scalar, derived from the seed
+	pub(super) fn secret_scalar(&self) -> &[u8; 32] {
+		&self.secret_scalar
+	}
+
+	// the prefix used to generate nonces, derived from the seed
+	pub(super) fn prefix(&self) -> &[u8; 32] {
+		&self.prefix
+	}
+
+	/// Starts creating a signature over a message that is passed in piece by
+	/// piece. See [`StreamingSigner`].
+	pub fn streaming_signer(&self) -> StreamingSigner<'_> {
+		StreamingSigner::new(self)
+	}
+
+	/// Signs a `message` using Ed25519. See [`ed25519_sign`].
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		ed25519_sign(self, message)
+	}
+
+	/// Signs a `message` using Ed25519, with countermeasures against power
+	/// and electromagnetic analysis. See [`ed25519_sign_hardened`].
+	#[cfg(feature = "rand_core")]
+	pub fn sign_hardened<R: RngCore + CryptoRng>(&self, message: &[u8], rng: &mut R) -> Signature {
+		ed25519_sign_hardened(self, message, rng)
+	}
+}
+
+impl TryFrom<&[u8]> for SigningKey {
+	type Error = Error;
+
+	fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+		match bytes.try_into() {
+			Ok(bytes) => Ok(Self::from_bytes(bytes)),
+			Err(_) => Err(Error::InvalidLength),
+		}
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+		zeroize(&mut self.prefix);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+		zeroize(&mut self.prefix);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey")
+			.field("verifying_key", &self.verifying_key)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(SigningKey, 32, Hex, |key| key.to_bytes(), |bytes| SigningKey::from_bytes(bytes));
+
+/// An Ed25519 key pair. This is a convenience wrapper around a [`SigningKey`],
+/// which already holds its public key, for constructing keys from a seed or a
+/// random number generator.
+#[derive(Clone, Debug)]
+pub struct Keypair {
+	signing_key: SigningKey,
+}
+
+impl Keypair {
+	/// Creates a key pair from a 32-byte seed, which must be kept secret.
+	/// This is the same as the 32-byte representation of the signing key.
+	pub fn from_seed(mut seed: [u8; 32]) -> Self {
+		let signing_key = SigningKey::from_bytes(seed);
+		zeroize(&mut seed);
+
+		Self {signing_key}
+	}
+
+	/// Generates a new key pair using a cryptographically secure random number
+	/// generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		let keypair = Self::from_seed(seed);
+		zeroize(&mut seed);
+
+		keypair
+	}
+
+	/// Returns the signing key of this key pair.
+	pub fn signing_key(&self) -> &SigningKey {
+		&self.signing_key
+	}
+
+	/// Returns the verifying key of this key pair.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.signing_key.verifying_key
+	}
+
+	/// Signs a `message` using Ed25519. See [`ed25519_sign`].
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		self.signing_key.sign(message)
+	}
+
+	/// Verifies a `signature` over a `message` using Ed25519. See
+	/// [`ed25519_verify`].
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		self.signing_key.verifying_key.verify(message, signature)
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Keypair {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.signing_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Keypair {}
+
+impl From<SigningKey> for Keypair {
+	fn from(signing_key: SigningKey) -> Self {
+		Self {signing_key}
+	}
+}
+
+impl From<Keypair> for SigningKey {
+	fn from(keypair: Keypair) -> Self {
+		keypair.signing_key
+	}
+}
+
+/// An Ed25519 public key, used to verify signatures. Constructing one checks
+/// that the key is a valid point on the curve.
+#[derive(Copy, Clone)]
+pub struct VerifyingKey {
+	compressed: [u8; 32],
+	pub(super) point: Point,
+}
+
+impl VerifyingKey {
+	/// Creates a verifying key from its 32-byte representation, returning an
+	/// error if the bytes do not encode a point on the curve.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		match decompress(bytes) {
+			Some(point) => Ok(Self {compressed: bytes, point}),
+			None => Err(Error::InvalidPublicKey),
+		}
+	}
+
+	/// Returns the 32-byte representation of this verifying key.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.compressed
+	}
+
+	/// Returns a reference to the 32-byte representation of this verifying key.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.compressed
+	}
+
+	/// Converts this verifying key to an X25519 public key using the birational
+	/// map from the Edwards curve to the Montgomery curve, u = (1 + y) / (1 - y).
+	pub fn to_x25519(&self) -> [u8; 32] {
+		let Point {y, z, ..} = self.point;
+
+		let mut u = (z + y) / (z - y);
+		u.full_modular_reduction();
+		u.to_bytes()
+	}
+
+	/// Converts an X25519 public key to a verifying key using the birational
+	/// map from the Montgomery curve to the Edwards curve, y = (u - 1) / (u + 1).
+	/// X25519 public keys don't carry the sign of the x-coordinate, so it needs
+	/// to be passed in separately as `x_sign`. For a key produced by
+	/// [`VerifyingKey::to_x25519`], this is the top bit of the original key.
+	pub fn from_x25519(pub_key: [u8; 32], x_sign: bool) -> Result<Self, Error> {
+		let u = Num::from_bytes(pub_key);
+		let u_plus_one = u + Num::ONE;
+
+		// u = -1 maps to the point at infinity, which can't be encoded
+		if u_plus_one.ct_eq(&Num::ZERO).into() {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		let mut y = (u - Num::ONE) / u_plus_one;
+		y.full_modular_reduction();
+
+		let mut compressed = y.to_bytes();
+
+		if x_sign {
+			compressed[31] |= 0x80;
+		}
+
+		Self::from_bytes(compressed)
+	}
+
+	/// Verifies an Ed25519 `signature` over a `message`. See [`ed25519_verify`].
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		ed25519_verify(self, message, signature)
+	}
+
+	/// Starts verifying a `signature` over a message that is passed in piece
+	/// by piece. See [`StreamingVerifier`].
+	pub fn streaming_verifier(&self, signature: &Signature) -> StreamingVerifier {
+		StreamingVerifier::new(*self, *signature)
+	}
+}
+
+impl TryFrom<&[u8]> for VerifyingKey {
+	type Error = Error;
+
+	fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+		match bytes.try_into() {
+			Ok(bytes) => Self::from_bytes(bytes),
+			Err(_) => Err(Error::InvalidLength),
+		}
+	}
+}
+
+impl PartialEq for VerifyingKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.compressed == other.compressed
+	}
+}
+
+impl Eq for VerifyingKey {}
+
+impl fmt::Debug for VerifyingKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("VerifyingKey").field(&self.compressed).finish()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(VerifyingKey, 32, Hex, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(bytes));
+
+text_impl!(VerifyingKey, 32, Hex, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(bytes));
+
+/// An Ed25519 signature. Constructing one checks that the scalar half of the
+/// signature is canonically encoded; the point half is checked on verification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature([u8; 64]);
+
+impl Signature {
+	/// Creates a signature from its 64-byte representation, returning an
+	/// error if it is not canonically encoded.
+	pub fn from_bytes(bytes: [u8; 64]) -> Result<Self, Error> {
+		if !verify_less_than_l(bytes[32 .. 64].try_into().unwrap()) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 64-byte representation of this signature.
+	pub fn to_bytes(&self) -> [u8; 64] {
+		self.0
+	}
+
+	/// Returns a reference to the 64-byte representation of this signature.
+	pub fn as_bytes(&self) -> &[u8; 64] {
+		&self.0
+	}
+}
+
+impl TryFrom<[u8; 64]> for Signature {
+	type Error = Error;
+
+	fn try_from(bytes: [u8; 64]) -> Result<Self, Error> {
+		Self::from_bytes(bytes)
+	}
+}
+
+impl From<Signature> for [u8; 64] {
+	fn from(signature: Signature) -> Self {
+		signature.0
+	}
+}
+
+impl TryFrom<&[u8]> for Signature {
+	type Error = Error;
+
+	fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+		match bytes.try_into() {
+			Ok(bytes) => Self::from_bytes(bytes),
+			Err(_) => Err(Error::InvalidLength),
+		}
+	}
 }
 
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Signature, 64, Hex, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(bytes));
+
+text_impl!(Signature, 64, Hex, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(bytes));
+
 /// Returns your public key given your `priv_key`.
-pub fn ed25519_derive_pub_key(priv_key: [u8; 32]) -> [u8; 32] {
-	let digest: [u8; 64] = sha512(&priv_key);
-	let mut secret_scalar: [u8; 32] = digest[0 .. 32].try_into().unwrap();
-	clamp_and_mult(&mut secret_scalar)
+pub fn ed25519_derive_pub_key(priv_key: &SigningKey) -> VerifyingKey {
+	priv_key.verifying_key
 }
 
-fn ed25519_sign_core(
-	priv_key: [u8; 32],
-	dom2_prefix: &[u8],
-	context: &[u8],
-	message: &[u8],
-) -> [u8; 64] {
-	let digest: [u8; 64] = sha512(&priv_key);
+// returns the clamped secret scalar and the prefix used to generate nonces
+pub(super) fn expand_priv_key(seed: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+	let mut digest: [u8; 64] = sha512(seed);
 	let mut secret_scalar: [u8; 32] = digest[0 .. 32].try_into().unwrap();
-	let pub_key = clamp_and_mult(&mut secret_scalar);
+	secret_scalar[0] &= 0xf8;
+	secret_scalar[31] &= 0x7f;
+	secret_scalar[31] |= 0x40;
 
-	let prefix: &[u8; 32] = &digest[32 .. 64].try_into().unwrap();
+	let prefix = digest[32 .. 64].try_into().unwrap();
+	zeroize(&mut digest);
 
-	let mut r_hasher = Sha512::new();
-	r_hasher.add_bytes(dom2_prefix);
-	r_hasher.add_bytes(context);
-	r_hasher.add_bytes(prefix);
-	r_hasher.add_bytes(message);
+	(secret_scalar, prefix)
+}
 
-	let little_r = r_hasher.out();
-	let little_r_num = num_mod_l_from_64_bytes(&little_r);
+// computes R given the digest of the nonce prefix and the message
+fn ed25519_sign_commit(little_r: &[u8; 64]) -> ([u64; 4], [u8; 32]) {
+	let little_r_num = num_mod_l_from_64_bytes(little_r);
 	let little_r_bytes = num_mod_l_to_bytes(little_r_num);
-	let big_r = ed25519_mult(&little_r_bytes, 253, BASE_POINT);
-	let big_r = compress(big_r);
+	let big_r = basepoint_mult(&little_r_bytes);
+
+	(little_r_num, compress(big_r))
+}
 
+fn ed25519_k_hasher(
+	dom2_prefix: &[u8],
+	context: &[u8],
+	big_r: &[u8; 32],
+	pub_key: &[u8; 32],
+) -> Sha512 {
 	let mut k_hasher = Sha512::new();
 	k_hasher.add_bytes(dom2_prefix);
 	k_hasher.add_bytes(context);
-	k_hasher.add_bytes(&big_r);
-	k_hasher.add_bytes(&pub_key);
-	k_hasher.add_bytes(message);
-	let k = k_hasher.out();
-	let k = num_mod_l_from_64_bytes(&k);
+	k_hasher.add_bytes(big_r);
+	k_hasher.add_bytes(pub_key);
+	k_hasher
+}
+
+// computes S and assembles the signature
+fn ed25519_sign_finish(
+	secret_scalar: &[u8; 32],
+	little_r_num: [u64; 4],
+	big_r: [u8; 32],
+	k: &[u8; 64],
+) -> Signature {
+	let k = num_mod_l_from_64_bytes(k);
 
-	let little_s_num = num_mod_l_from_32_bytes(&secret_scalar);
+	let little_s_num = num_mod_l_from_32_bytes(secret_scalar);
 	let big_s = add_num_mod_l(little_r_num, mul_num_mod_l(k, little_s_num));
 	let big_s = num_mod_l_to_bytes(big_s);
 
@@ -335,67 +857,349 @@ fn ed25519_sign_core(
 	out[0 .. 32].copy_from_slice(&big_r);
 	out[32 .. 64].copy_from_slice(&big_s);
 
-	out
+	Signature(out)
 }
 
-// core algorithm for verifying ed25519 signatures
-// not constant-time since it doesn't involve secret data
-fn ed25519_verify_core(
-	pub_key: [u8; 32],
-	signature: [u8; 64],
+fn ed25519_sign_core(
+	priv_key: &SigningKey,
 	dom2_prefix: &[u8],
 	context: &[u8],
 	message: &[u8],
-) -> bool {
-	let pub_key_point = match decompress(pub_key) {
-		Some(val) => val,
-		None => return false,
-	};
+) -> Signature {
+	let mut r_hasher = Sha512::new();
+	r_hasher.add_bytes(dom2_prefix);
+	r_hasher.add_bytes(context);
+	r_hasher.add_bytes(&priv_key.prefix);
+	r_hasher.add_bytes(message);
+
+	let (little_r_num, big_r) = ed25519_sign_commit(&r_hasher.out());
+
+	let pub_key = &priv_key.verifying_key.compressed;
+	let mut k_hasher = ed25519_k_hasher(dom2_prefix, context, &big_r, pub_key);
+	k_hasher.add_bytes(message);
 
-	let big_r_bytes = signature[0 .. 32].try_into().unwrap();
+	ed25519_sign_finish(&priv_key.secret_scalar, little_r_num, big_r, &k_hasher.out())
+}
+
+// core algorithm for verifying ed25519 signatures, given the digest k
+// not constant-time since it doesn't involve secret data
+fn ed25519_verify_finish(
+	pub_key: &VerifyingKey,
+	signature: &Signature,
+	k: &[u8; 64],
+) -> bool {
+	let big_r_bytes = signature.0[0 .. 32].try_into().unwrap();
 	let big_r = match decompress(big_r_bytes) {
 		Some(val) => val,
 		None => return false,
 	};
 
-	let big_s: [u8; 32] = signature[32 .. 64].try_into().unwrap();
-
-	if !verify_less_than_l(&big_s) {
-		return false;
-	}
-
-	let mut hasher = Sha512::new();
-	hasher.add_bytes(dom2_prefix);
-	hasher.add_bytes(context);
-	hasher.add_bytes(&big_r_bytes);
-	hasher.add_bytes(&pub_key);
-	hasher.add_bytes(message);
+	// the signature type guarantees that this is less than l
+	let big_s: [u8; 32] = signature.0[32 .. 64].try_into().unwrap();
 
-	let k = hasher.out();
-	let k = num_mod_l_to_bytes(num_mod_l_from_64_bytes(&k));
+	let k = num_mod_l_to_bytes(num_mod_l_from_64_bytes(k));
 
-	let left_side = ed25519_mult(&big_s, 253, BASE_POINT);
+	let left_side = basepoint_mult(&big_s);
 	let right_side = point_add(
 		big_r,
-		ed25519_mult(&k, 253, pub_key_point),
+		ed25519_mult(&k, 253, pub_key.point),
 	);
 
-	compress(left_side) == compress(right_side)
+	points_equal(left_side, right_side).into()
+}
+
+fn ed25519_verify_core(
+	pub_key: &VerifyingKey,
+	signature: &Signature,
+	dom2_prefix: &[u8],
+	context: &[u8],
+	message: &[u8],
+) -> bool {
+	let big_r = signature.0[0 .. 32].try_into().unwrap();
+	let mut k_hasher = ed25519_k_hasher(dom2_prefix, context, big_r, &pub_key.compressed);
+	k_hasher.add_bytes(message);
+
+	ed25519_verify_finish(pub_key, signature, &k_hasher.out())
+}
+
+/// Creates an Ed25519 signature over a message that is passed in piece by
+/// piece, for messages that are too large to hold in memory all at once.
+/// Created by [`SigningKey::streaming_signer`].
+///
+/// Ed25519 needs to hash the message twice, so the whole message has to be
+/// passed in once, then [`StreamingSigner::second_pass`] needs to be called,
+/// and then the exact same message has to be passed in again.
+///
+/// # Examples
+/// ```
+/// # use libkrypton::ed25519::SigningKey;
+/// #
+/// # let priv_key = SigningKey::from_bytes([0; 32]);
+/// let chunks: [&[u8]; 2] = [b"hello ", b"world"];
+///
+/// let mut signer = priv_key.streaming_signer();
+/// chunks.iter().for_each(|chunk| signer.add_bytes(chunk));
+///
+/// let mut signer = signer.second_pass();
+/// chunks.iter().for_each(|chunk| signer.add_bytes(chunk));
+///
+/// let signature = signer.finish().unwrap();
+///
+/// assert!(signature == priv_key.sign(b"hello world"));
+/// ```
+pub struct StreamingSigner<'a> {
+	priv_key: &'a SigningKey,
+	r_hasher: Sha512,
+	message_hasher: Sha512,
+}
+
+impl<'a> StreamingSigner<'a> {
+	fn new(priv_key: &'a SigningKey) -> Self {
+		let mut r_hasher = Sha512::new();
+		r_hasher.add_bytes(&priv_key.prefix);
+
+		Self {
+			priv_key,
+			r_hasher,
+			message_hasher: Sha512::new(),
+		}
+	}
+
+	/// Adds bytes of the message during the first pass.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.r_hasher.add_bytes(bytes);
+		self.message_hasher.add_bytes(bytes);
+	}
+
+	/// Finishes the first pass over the message. The same message must then be
+	/// passed to the returned [`StreamingSignerSecondPass`].
+	pub fn second_pass(self) -> StreamingSignerSecondPass<'a> {
+		let (little_r_num, big_r) = ed25519_sign_commit(&self.r_hasher.out());
+		let pub_key = &self.priv_key.verifying_key.compressed;
+
+		StreamingSignerSecondPass {
+			priv_key: self.priv_key,
+			little_r_num,
+			big_r,
+			k_hasher: ed25519_k_hasher(&[], &[], &big_r, pub_key),
+			message_hasher: Sha512::new(),
+			first_pass_digest: self.message_hasher.out(),
+		}
+	}
+}
+
+/// The second pass over the message of a [`StreamingSigner`].
+pub struct StreamingSignerSecondPass<'a> {
+	priv_key: &'a SigningKey,
+	little_r_num: [u64; 4],
+	big_r: [u8; 32],
+	k_hasher: Sha512,
+	message_hasher: Sha512,
+	first_pass_digest: [u8; 64],
+}
+
+impl<'a> StreamingSignerSecondPass<'a> {
+	/// Adds bytes of the message during the second pass.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.k_hasher.add_bytes(bytes);
+		self.message_hasher.add_bytes(bytes);
+	}
+
+	/// Finishes the signature. Signing two different messages with the same
+	/// first pass would reveal the private key, so this returns
+	/// [`Error::MessageMismatch`] instead of a signature if the message passed
+	/// in during the second pass differs from the one passed in during the first.
+	pub fn finish(self) -> Result<Signature, Error> {
+		let second_pass_digest = self.message_hasher.out();
+
+		let mut difference = 0;
+
+		for i in 0 .. 64 {
+			difference |= self.first_pass_digest[i] ^ second_pass_digest[i];
+		}
+
+		if difference != 0 {
+			return Err(Error::MessageMismatch);
+		}
+
+		Ok(ed25519_sign_finish(
+			&self.priv_key.secret_scalar,
+			self.little_r_num,
+			self.big_r,
+			&self.k_hasher.out(),
+		))
+	}
+}
+
+/// Verifies an Ed25519 signature over a message that is passed in piece by
+/// piece. Unlike signing, this only requires a single pass over the message.
+/// Created by [`VerifyingKey::streaming_verifier`].
+pub struct StreamingVerifier {
+	pub_key: VerifyingKey,
+	signature: Signature,
+	k_hasher: Sha512,
+}
+
+impl StreamingVerifier {
+	fn new(pub_key: VerifyingKey, signature: Signature) -> Self {
+		let big_r = signature.0[0 .. 32].try_into().unwrap();
+		let k_hasher = ed25519_k_hasher(&[], &[], big_r, &pub_key.compressed);
+
+		Self {pub_key, signature, k_hasher}
+	}
+
+	/// Adds bytes of the message.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.k_hasher.add_bytes(bytes);
+	}
+
+	/// Returns whether the signature is valid for the concatenation of all
+	/// bytes added. See [`ed25519_verify`].
+	pub fn finish(self) -> bool {
+		ed25519_verify_finish(&self.pub_key, &self.signature, &self.k_hasher.out())
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'a> Write for StreamingSigner<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.add_bytes(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'a> Write for StreamingSignerSecondPass<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.add_bytes(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+impl Write for StreamingVerifier {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.add_bytes(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(feature = "signature")]
+impl signature::Signer<Signature> for SigningKey {
+	fn try_sign(&self, message: &[u8]) -> Result<Signature, signature::Error> {
+		Ok(ed25519_sign(self, message))
+	}
+}
+
+#[cfg(feature = "signature")]
+impl signature::Verifier<Signature> for VerifyingKey {
+	fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), signature::Error> {
+		match ed25519_verify(self, message, signature) {
+			true => Ok(()),
+			false => Err(signature::Error::new()),
+		}
+	}
+}
+
+#[cfg(feature = "signature")]
+impl signature::Keypair for SigningKey {
+	type VerifyingKey = VerifyingKey;
+
+	fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+}
+
+#[cfg(feature = "signature")]
+impl signature::SignatureEncoding for Signature {
+	type Repr = [u8; 64];
 }
 
 /// Creates an Ed25519 digital signature, given a `priv_key` and `message`.
 /// This signature can be verified by anyone, given your public key,
 /// the the original `message`, and the signature.
-pub fn ed25519_sign(priv_key: [u8; 32], message: &[u8]) -> [u8; 64] {
+pub fn ed25519_sign(priv_key: &SigningKey, message: &[u8]) -> Signature {
 	ed25519_sign_core(priv_key, &[], &[], message)
 }
 
+/// Creates the same signature as [`ed25519_sign`], with countermeasures
+/// against power and electromagnetic analysis, for devices that an attacker
+/// may be able to take measurements of, such as smart cards and other
+/// embedded targets. The nonce is blinded with a random multiple of the group
+/// order before it's multiplied by a base point in randomized projective
+/// coordinates, and the secret scalar is split into two random shares that
+/// are each multiplied by the challenge on their own. The blinded nonce is
+/// longer, so this takes about a quarter more time than [`ed25519_sign`],
+/// and it never uses the precomputed table of the `basepoint-table` feature.
+/// The hashing of the key and message isn't protected.
+///
+/// The randomness from `rng` only hides the computation, so the signature
+/// stays deterministic, and a weak generator weakens the countermeasures
+/// without weakening the signature.
+#[cfg(feature = "rand_core")]
+pub fn ed25519_sign_hardened<R: RngCore + CryptoRng>(
+	priv_key: &SigningKey,
+	message: &[u8],
+	rng: &mut R,
+) -> Signature {
+	let mut r_hasher = Sha512::new();
+	r_hasher.add_bytes(&priv_key.prefix);
+	r_hasher.add_bytes(message);
+
+	let little_r_num = num_mod_l_from_64_bytes(&r_hasher.out());
+	let mut little_r_bytes = num_mod_l_to_bytes(little_r_num);
+	let mut blinded = blind_scalar(&little_r_bytes, 1, rng);
+	let base_point = rescale(BASE_POINT, Num::random(rng));
+	let big_r = compress(ed25519_mult(&blinded, BLINDED_BITS, base_point));
+
+	zeroize(&mut little_r_bytes);
+	zeroize(&mut blinded);
+
+	let pub_key = &priv_key.verifying_key.compressed;
+	let mut k_hasher = ed25519_k_hasher(&[], &[], &big_r, pub_key);
+	k_hasher.add_bytes(message);
+	let k = num_mod_l_from_64_bytes(&k_hasher.out());
+
+	// s = share_a + share_b, so k * s = k * share_a + k * share_b
+	let mut random = [0; 64];
+	rng.fill_bytes(&mut random);
+	let mut share_a = num_mod_l_from_64_bytes(&random);
+	zeroize(&mut random);
+
+	let little_s_num = num_mod_l_from_32_bytes(&priv_key.secret_scalar);
+	let mut share_b = add_num_mod_l(little_s_num, neg_num_mod_l(share_a));
+	let k_s = add_num_mod_l(mul_num_mod_l(k, share_a), mul_num_mod_l(k, share_b));
+	let big_s = num_mod_l_to_bytes(add_num_mod_l(little_r_num, k_s));
+
+	zeroize(&mut share_a);
+	zeroize(&mut share_b);
+
+	let mut out = [0; 64];
+
+	out[0 .. 32].copy_from_slice(&big_r);
+	out[32 .. 64].copy_from_slice(&big_s);
+
+	Signature(out)
+}
+
 /// Verifies an Ed25519 digital signature. This implementation is *not*
 /// constant-time, as it does not involve secret data.
 pub fn ed25519_verify(
-	pub_key: [u8; 32],
+	pub_key: &VerifyingKey,
 	message: &[u8],
-	signature: [u8; 64],
+	signature: &Signature,
 ) -> bool {
 	ed25519_verify_core(pub_key, signature, &[], &[], message)
 }
@@ -406,10 +1210,10 @@ pub fn ed25519_verify(
 /// `context` should not be empty, nor should it depend on the content of
 /// the `message`.
 pub fn ed25519ctx_sign(
-	priv_key: [u8; 32],
+	priv_key: &SigningKey,
 	message: &[u8],
 	context: &[u8],
-) -> [u8; 64] {
+) -> Signature {
 	assert!(context.len() <= 255);
 
 	let mut dom2_prefix = [0; 34];
@@ -423,10 +1227,10 @@ pub fn ed25519ctx_sign(
 /// Verifies an Ed25519ctx digital signature. This implementation is *not*
 /// constant-time, as it does not involve secret data.
 pub fn ed25519ctx_verify(
-	pub_key: [u8; 32],
+	pub_key: &VerifyingKey,
 	message: &[u8],
 	context: &[u8],
-	signature: [u8; 64],
+	signature: &Signature,
 ) -> bool {
 	assert!(context.len() <= 255);
 
@@ -445,10 +1249,10 @@ pub fn ed25519ctx_verify(
 /// are discovered. For this reason, it's not recommended to use this, unless
 /// for some reason, Ed25519 is not suitable for your use-case.
 pub fn ed25519ph_sign(
-	priv_key: [u8; 32],
+	priv_key: &SigningKey,
 	message: &[u8],
 	context: &[u8],
-) -> [u8; 64] {
+) -> Signature {
 	assert!(context.len() <= 255);
 
 	let mut dom2_prefix = [0; 34];
@@ -456,7 +1260,7 @@ pub fn ed25519ph_sign(
 	dom2_prefix[32] = 0x01;
 	dom2_prefix[33] = context.len().try_into().unwrap();
 
-	let message = sha512(&message);
+	let message = sha512(message);
 
 	ed25519_sign_core(priv_key, &dom2_prefix, context, &message)
 }
@@ -464,10 +1268,10 @@ pub fn ed25519ph_sign(
 /// Verifies an Ed25519ph digital signature. This implementation is *not*
 /// constant-time, as it does not involve secret data.
 pub fn ed25519ph_verify(
-	pub_key: [u8; 32],
+	pub_key: &VerifyingKey,
 	message: &[u8],
 	context: &[u8],
-	signature: [u8; 64],
+	signature: &Signature,
 ) -> bool {
 	assert!(context.len() <= 255);
 
@@ -476,7 +1280,7 @@ pub fn ed25519ph_verify(
 	dom2_prefix[32] = 0x01;
 	dom2_prefix[33] = context.len().try_into().unwrap();
 
-	let message = sha512(&message);
+	let message = sha512(message);
 
 	ed25519_verify_core(pub_key, signature, &dom2_prefix, context, &message)
 }
@@ -512,7 +1316,9 @@ fn ed25519_test_vector_1() {
 		0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[];
 
@@ -527,9 +1333,11 @@ fn ed25519_test_vector_1() {
 		0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b,
 	];
 
-	assert_eq!(ed25519_sign(priv_key, message), signature);
+	assert_eq!(ed25519_sign(&priv_key, message).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
 
-	assert!(ed25519_verify(pub_key, message, signature));
+	assert!(ed25519_verify(&pub_key, message, &signature));
 }
 
 #[test]
@@ -548,7 +1356,9 @@ fn ed25519_test_vector_2() {
 		0xc0, 0xcd, 0x55, 0xf1, 0x2a, 0xf4, 0x66, 0x0c,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[0x72];
 
@@ -563,9 +1373,11 @@ fn ed25519_test_vector_2() {
 		0xb0, 0x0d, 0x29, 0x16, 0x12, 0xbb, 0x0c, 0x00,
 	];
 
-	assert_eq!(ed25519_sign(priv_key, message), signature);
+	assert_eq!(ed25519_sign(&priv_key, message).to_bytes(), signature);
 
-	assert!(ed25519_verify(pub_key, message, signature));
+	let signature = Signature::from_bytes(signature).unwrap();
+
+	assert!(ed25519_verify(&pub_key, message, &signature));
 }
 
 #[test]
@@ -584,7 +1396,9 @@ fn ed25519_test_vector_3() {
 		0x5d, 0xeb, 0x91, 0x15, 0x48, 0x90, 0x80, 0x25,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[0xaf, 0x82];
 
@@ -599,9 +1413,11 @@ fn ed25519_test_vector_3() {
 		0xc0, 0x27, 0xbe, 0xce, 0xea, 0x1e, 0xc4, 0x0a,
 	];
 
-	assert_eq!(ed25519_sign(priv_key, message), signature);
+	assert_eq!(ed25519_sign(&priv_key, message).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
 
-	assert!(ed25519_verify(pub_key, message, signature));
+	assert!(ed25519_verify(&pub_key, message, &signature));
 }
 
 #[test]
@@ -620,7 +1436,9 @@ fn ed25519_test_vector_1024() {
 		0x1f, 0xef, 0x7c, 0x59, 0x7f, 0x1d, 0x42, 0x6e,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0x08, 0xb8, 0xb2, 0xb7, 0x33, 0x42, 0x42, 0x43,
@@ -764,9 +1582,11 @@ fn ed25519_test_vector_1024() {
 		0xac, 0x00, 0xa9, 0x70, 0x4a, 0x18, 0x8a, 0x03,
 	];
 
-	assert_eq!(ed25519_sign(priv_key, message), signature);
+	assert_eq!(ed25519_sign(&priv_key, message).to_bytes(), signature);
 
-	assert!(ed25519_verify(pub_key, message, signature));
+	let signature = Signature::from_bytes(signature).unwrap();
+
+	assert!(ed25519_verify(&pub_key, message, &signature));
 }
 
 #[test]
@@ -785,7 +1605,9 @@ fn ed25519_test_vector_sha_abc() {
 		0xeb, 0xf8, 0x19, 0x68, 0x34, 0x67, 0xe2, 0xbf,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba,
@@ -809,9 +1631,11 @@ fn ed25519_test_vector_sha_abc() {
 		0xd9, 0xbe, 0xf1, 0x17, 0x73, 0x31, 0xa7, 0x04,
 	];
 
-	assert_eq!(ed25519_sign(priv_key, message), signature);
+	assert_eq!(ed25519_sign(&priv_key, message).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
 
-	assert!(ed25519_verify(pub_key, message, signature));
+	assert!(ed25519_verify(&pub_key, message, &signature));
 }
 
 #[test]
@@ -830,7 +1654,9 @@ fn ed25519ctx_test_vector_foo() {
 		0x86, 0x0c, 0xfe, 0xe0, 0xab, 0x24, 0x82, 0x92,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0xf7, 0x26, 0x93, 0x6d, 0x19, 0xc8, 0x00, 0x49,
@@ -850,9 +1676,11 @@ fn ed25519ctx_test_vector_foo() {
 		0x1f, 0x4b, 0x88, 0x8e, 0x4e, 0x7e, 0xdb, 0x0d,
 	];
 
-	assert_eq!(ed25519ctx_sign(priv_key, message, context), signature);
+	assert_eq!(ed25519ctx_sign(&priv_key, message, context).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
 
-	assert!(ed25519ctx_verify(pub_key, message, context, signature));
+	assert!(ed25519ctx_verify(&pub_key, message, context, &signature));
 }
 
 #[test]
@@ -871,7 +1699,9 @@ fn ed25519ctx_test_vector_bar() {
 		0x86, 0x0c, 0xfe, 0xe0, 0xab, 0x24, 0x82, 0x92,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0xf7, 0x26, 0x93, 0x6d, 0x19, 0xc8, 0x00, 0x49,
@@ -891,9 +1721,11 @@ fn ed25519ctx_test_vector_bar() {
 		0x91, 0xc2, 0x04, 0x3d, 0x4e, 0xb3, 0xe9, 0x0d,
 	];
 
-	assert_eq!(ed25519ctx_sign(priv_key, message, context), signature);
+	assert_eq!(ed25519ctx_sign(&priv_key, message, context).to_bytes(), signature);
 
-	assert!(ed25519ctx_verify(pub_key, message, context, signature));
+	let signature = Signature::from_bytes(signature).unwrap();
+
+	assert!(ed25519ctx_verify(&pub_key, message, context, &signature));
 }
 
 #[test]
@@ -912,7 +1744,9 @@ fn ed25519ctx_test_vector_foo2() {
 		0x86, 0x0c, 0xfe, 0xe0, 0xab, 0x24, 0x82, 0x92,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0x50, 0x8e, 0x9e, 0x68, 0x82, 0xb9, 0x79, 0xfe,
@@ -932,9 +1766,11 @@ fn ed25519ctx_test_vector_foo2() {
 		0x26, 0x26, 0x9d, 0x89, 0x45, 0xf8, 0x4b, 0x0b,
 	];
 
-	assert_eq!(ed25519ctx_sign(priv_key, message, context), signature);
+	assert_eq!(ed25519ctx_sign(&priv_key, message, context).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
 
-	assert!(ed25519ctx_verify(pub_key, message, context, signature));
+	assert!(ed25519ctx_verify(&pub_key, message, context, &signature));
 }
 
 #[test]
@@ -953,7 +1789,9 @@ fn ed25519ctx_test_vector_foo3() {
 		0xb4, 0xb0, 0xdd, 0xa1, 0x0a, 0xd7, 0xd7, 0x72,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[
 		0xf7, 0x26, 0x93, 0x6d, 0x19, 0xc8, 0x00, 0x49,
@@ -973,9 +1811,11 @@ fn ed25519ctx_test_vector_foo3() {
 		0xa2, 0xb2, 0xe0, 0xdc, 0x0a, 0xd8, 0x96, 0x0f,
 	];
 
-	assert_eq!(ed25519ctx_sign(priv_key, message, context), signature);
+	assert_eq!(ed25519ctx_sign(&priv_key, message, context).to_bytes(), signature);
 
-	assert!(ed25519ctx_verify(pub_key, message, context, signature));
+	let signature = Signature::from_bytes(signature).unwrap();
+
+	assert!(ed25519ctx_verify(&pub_key, message, context, &signature));
 }
 
 #[test]
@@ -994,7 +1834,9 @@ fn ed25519ph_test_vector_abc() {
 		0xeb, 0xf8, 0x19, 0x68, 0x34, 0x67, 0xe2, 0xbf,
 	];
 
-	assert_eq!(ed25519_derive_pub_key(priv_key), pub_key);
+	let priv_key = SigningKey::from_bytes(priv_key);
+	assert_eq!(ed25519_derive_pub_key(&priv_key).to_bytes(), pub_key);
+	let pub_key = VerifyingKey::from_bytes(pub_key).unwrap();
 
 	let message = &[0x61, 0x62, 0x63];
 	let context = &[];
@@ -1010,7 +1852,236 @@ fn ed25519ph_test_vector_abc() {
 		0xaa, 0xd1, 0x1c, 0x2a, 0x26, 0x08, 0x34, 0x06,
 	];
 
-	assert_eq!(ed25519ph_sign(priv_key, message, context), signature);
+	assert_eq!(ed25519ph_sign(&priv_key, message, context).to_bytes(), signature);
+
+	let signature = Signature::from_bytes(signature).unwrap();
+
+	assert!(ed25519ph_verify(&pub_key, message, context, &signature));
+}
+
+#[test]
+fn test_typed_keys_reject_invalid_bytes() {
+	let priv_key = SigningKey::from_bytes([0x42; 32]);
+	let pub_key = priv_key.verifying_key();
+	let signature = priv_key.sign(b"message");
+
+	assert_eq!(SigningKey::try_from(&[0; 31][..]).unwrap_err(), Error::InvalidLength);
+	assert_eq!(VerifyingKey::try_from(&pub_key.to_bytes()[..]), Ok(pub_key));
+	assert_eq!(Signature::try_from(&signature.to_bytes()[..]), Ok(signature));
+
+	// y = 2 is not the y-coordinate of any point on the curve
+	let mut not_a_point = [0; 32];
+	not_a_point[0] = 2;
+	assert_eq!(VerifyingKey::from_bytes(not_a_point), Err(Error::InvalidPublicKey));
+
+	// a signature with s = l is not canonically encoded
+	let mut non_canonical = signature.to_bytes();
+	non_canonical[32 .. 64].copy_from_slice(&[
+		0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+		0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+	]);
+	assert_eq!(Signature::from_bytes(non_canonical), Err(Error::InvalidSignature));
+
+	assert!(pub_key.verify(b"message", &signature));
+	assert!(!pub_key.verify(b"massage", &signature));
+}
+
+#[test]
+#[cfg(feature = "x25519")]
+fn test_x25519_conversion() {
+	use crate::x25519::x25519_derive_pub_key;
+
+	for i in 0 .. 8 {
+		let priv_key = SigningKey::from_bytes([i; 32]);
+		let pub_key = priv_key.verifying_key();
+
+		let x25519_priv_key = priv_key.to_x25519();
+		let x25519_pub_key = pub_key.to_x25519();
+
+		assert_eq!(x25519_derive_pub_key(x25519_priv_key), x25519_pub_key);
+
+		let x_sign = pub_key.to_bytes()[31] & 0x80 != 0;
+		let round_trip = VerifyingKey::from_x25519(x25519_pub_key, x_sign).unwrap();
+
+		assert_eq!(round_trip, pub_key);
+	}
+}
+
+#[test]
+#[cfg(all(feature = "basepoint-table", feature = "chacha20"))]
+fn test_basepoint_table_matches_ladder() {
+	use crate::chacha20::ChaCha20;
+
+	let mut stream = ChaCha20::new([0x17; 32], [0; 12]);
+
+	for _ in 0 .. 32 {
+		let mut scalar = [0; 32];
+		scalar.iter_mut().for_each(|x| *x = stream.next().unwrap());
+		scalar[31] &= 0x7f;
 
-	assert!(ed25519ph_verify(pub_key, message, context, signature));
+		let from_table = compress(basepoint_mult(&scalar));
+		let from_ladder = compress(ed25519_mult(&scalar, 255, BASE_POINT));
+
+		assert_eq!(from_table, from_ladder);
+	}
+}
+
+#[test]
+fn test_streaming_sign_and_verify() {
+	let priv_key = SigningKey::from_bytes([0x99; 32]);
+	let pub_key = priv_key.verifying_key();
+	let message = [0x5a; 1000];
+
+	let mut signer = priv_key.streaming_signer();
+	message.chunks(7).for_each(|chunk| signer.add_bytes(chunk));
+	let mut signer = signer.second_pass();
+	message.chunks(300).for_each(|chunk| signer.add_bytes(chunk));
+	let signature = signer.finish().unwrap();
+
+	assert_eq!(signature, priv_key.sign(&message));
+
+	let mut verifier = pub_key.streaming_verifier(&signature);
+	message.chunks(128).for_each(|chunk| verifier.add_bytes(chunk));
+	assert!(verifier.finish());
+
+	let mut verifier = pub_key.streaming_verifier(&signature);
+	verifier.add_bytes(&message[1 ..]);
+	assert!(!verifier.finish());
+
+	let mut signer = priv_key.streaming_signer();
+	signer.add_bytes(&message);
+	let mut signer = signer.second_pass();
+	signer.add_bytes(&message[1 ..]);
+	assert_eq!(signer.finish(), Err(Error::MessageMismatch));
+}
+
+#[test]
+#[cfg(feature = "signature")]
+fn test_signature_traits() {
+	use signature::{Keypair, Signer, Verifier};
+
+	let priv_key = SigningKey::from_bytes([0x3c; 32]);
+	let pub_key = Keypair::verifying_key(&priv_key);
+
+	let signature: Signature = priv_key.try_sign(b"message").unwrap();
+
+	assert!(Verifier::verify(&pub_key, b"message", &signature).is_ok());
+	assert!(Verifier::verify(&pub_key, b"massage", &signature).is_err());
+}
+
+#[test]
+fn test_compress_and_decompress_identity() {
+	let mut identity = [0; 32];
+	identity[0] = 1;
+
+	assert_eq!(compress(decompress(identity).unwrap()), identity);
+
+	// x = 0 with the sign bit set is not a canonical encoding
+	identity[31] = 0x80;
+	assert!(decompress(identity).is_none());
+}
+
+#[test]
+fn test_keypair_from_seed() {
+	let keypair = Keypair::from_seed([0x5e; 32]);
+	let priv_key = SigningKey::from_bytes([0x5e; 32]);
+
+	assert_eq!(keypair.verifying_key(), priv_key.verifying_key());
+	assert_eq!(keypair.signing_key().to_bytes(), [0x5e; 32]);
+
+	let signature = keypair.sign(b"message");
+	assert!(signature == priv_key.sign(b"message"));
+	assert!(keypair.verify(b"message", &signature));
+	assert!(!keypair.verify(b"massage", &signature));
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn test_keypair_generate() {
+	use crate::test_util::CountingRng;
+
+	let mut rng = CountingRng(0);
+	let first = Keypair::generate(&mut rng);
+	let second = Keypair::generate(&mut rng);
+
+	let expected_seed: [u8; 32] = core::array::from_fn(|i| i as u8);
+
+	assert_eq!(first.signing_key().to_bytes(), expected_seed);
+	assert_ne!(first.verifying_key(), second.verifying_key());
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn test_sign_hardened_matches_sign() {
+	// hands out the bytes of a hash chain, which is of course not actually
+	// cryptographically secure
+	struct HashRng([u8; 64], usize);
+
+	impl RngCore for HashRng {
+		fn next_u32(&mut self) -> u32 {
+			rand_core::impls::next_u32_via_fill(self)
+		}
+
+		fn next_u64(&mut self) -> u64 {
+			rand_core::impls::next_u64_via_fill(self)
+		}
+
+		fn fill_bytes(&mut self, dest: &mut [u8]) {
+			for byte in dest {
+				if self.1 == 64 {
+					self.0 = sha512(&self.0);
+					self.1 = 0;
+				}
+
+				*byte = self.0[self.1];
+				self.1 += 1;
+			}
+		}
+
+		fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+			self.fill_bytes(dest);
+			Ok(())
+		}
+	}
+
+	impl CryptoRng for HashRng {}
+
+	let mut rng = HashRng([0; 64], 64);
+
+	for i in 0 .. 8u8 {
+		let priv_key = SigningKey::from_bytes([i; 32]);
+		let message = [i; 37];
+
+		assert_eq!(priv_key.sign_hardened(&message, &mut rng), priv_key.sign(&message));
+	}
+
+	// the portable ladder gets the same result from the blinded scalar and
+	// randomized base point, whether or not the AVX2 one is used above
+	let scalar = num_mod_l_to_bytes(num_mod_l_from_64_bytes(&sha512(b"scalar")));
+	let blinded = blind_scalar(&scalar, 1, &mut rng);
+	let base_point = rescale(BASE_POINT, Num::random(&mut rng));
+
+	assert_eq!(
+		compress(ed25519_mult_portable(&blinded, BLINDED_BITS, base_point)),
+		compress(basepoint_mult(&scalar)),
+	);
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "fiat-crypto")))]
+#[test]
+fn test_avx2_matches_portable() {
+	if !super::avx2::is_available() {
+		return;
+	}
+
+	for i in 0 .. 16u8 {
+		let scalar = sha512(&[i]);
+		let point = ed25519_mult_portable(&[i + 1], 8, BASE_POINT);
+		// SAFETY: the CPU was just checked to support AVX2
+		let avx2 = unsafe {super::avx2::ed25519_mult(&scalar, 256, point)};
+
+		assert_eq!(compress(avx2), compress(ed25519_mult_portable(&scalar, 256, point)));
+	}
 }
@@ -0,0 +1,252 @@
+//! Elligator 2 encoding of X25519 public keys, as described in
+//! [the Elligator paper](https://elligator.cr.yp.to/elligator-20130828.pdf).
+//! About half of all X25519 public keys have a representative, a 32-byte
+//! string that's indistinguishable from random bytes and can be mapped back
+//! to the public key. This lets handshakes hide the fact that they're
+//! exchanging public keys at all, like in obfs4.
+//!
+//! Public keys computed with [`x25519_derive_pub_key`](crate::x25519::x25519_derive_pub_key)
+//! always lie in the prime-order subgroup, which makes their representatives
+//! distinguishable from random. Keys whose public key will be encoded should
+//! be generated with [`elligator2_key_pair`], which also adds a random
+//! low-order component to the public key. Key exchanges with these public
+//! keys produce the same shared secrets as with ordinary ones.
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, CtOption};
+use crate::sha2::Sha512;
+use crate::zeroize::zeroize;
+
+use super::ed25519::{basepoint_mult, decompress, ed25519_mult, point_add};
+use super::num::Num;
+
+const A: Num = Num {segments: [486662, 0, 0, 0, 0]};
+
+// a point of order 8 on the edwards curve
+const LOW_ORDER_POINT: [u8; 32] = [
+	0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0,
+	0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98, 0xf0,
+	0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+	0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x05,
+];
+
+fn canonical_bytes(mut num: Num) -> [u8; 32] {
+	num.full_modular_reduction();
+	num.to_bytes()
+}
+
+// returns 1 if the canonical representative of the number is odd
+fn is_odd(num: Num) -> u8 {
+	canonical_bytes(num)[0] & 0x01
+}
+
+/// Maps a 32-byte representative to the X25519 public key it represents.
+/// Every 32-byte string maps to a public key, and the top two bits of the
+/// representative are ignored. Works in constant time.
+pub fn elligator2_map(representative: [u8; 32]) -> [u8; 32] {
+	let mut representative = representative;
+	representative[31] &= 0x3f;
+
+	let r = Num::from_bytes(representative);
+
+	// w = -A / (1 + 2 r^2), which is never a division by zero since -1/2 is
+	// not a square
+	let w = -A / (Num::ONE + (r * r + r * r));
+	let curve = w * (w * w + A * w + Num::ONE);
+
+	// if w is not the u-coordinate of a point on the curve, -w - A is
+	let (is_square, _) = Num::sqrt_ratio(&curve, &Num::ONE);
+	let u = Num::conditional_select(&(-w - A), &w, is_square);
+
+	canonical_bytes(u)
+}
+
+/// Computes a representative of an X25519 public key, or returns [`None`]
+/// if it doesn't have one, which is the case for about half of all public
+/// keys. `tweak` should be a random byte: its lowest bit chooses between the
+/// two representatives, and its top two bits fill in the top two bits of
+/// the representative, which would otherwise always be zero.
+///
+/// The public key should come from [`elligator2_key_pair`]; otherwise the
+/// representative can be distinguished from random bytes. Works in constant
+/// time, except for whether a representative was found.
+pub fn elligator2_unmap(pub_key: [u8; 32], tweak: u8) -> Option<[u8; 32]> {
+	let u = Num::from_bytes(pub_key);
+	let u_plus_a = u + A;
+
+	// a representative exists iff -2 u (u + A) is a nonzero square
+	let (is_square, isr) = Num::sqrt_ratio(&Num::ONE, &-(u * u_plus_a + u * u_plus_a));
+
+	// r is either sqrt(-u / (2 (u + A))) or sqrt(-(u + A) / (2 u))
+	let mut numerator = u;
+	numerator.conditional_assign(&u_plus_a, Choice::from(tweak & 0x01));
+
+	// choose the root that is at most (p - 1) / 2, so it fits in 254 bits
+	let mut r = numerator * isr;
+	let negated = -r;
+	r.conditional_assign(&negated, Choice::from(is_odd(r + r)));
+
+	let mut out = canonical_bytes(r);
+	out[31] |= tweak & 0xc0;
+
+	CtOption::new(out, is_square).into()
+}
+
+/// Computes the public key used with Elligator 2 for an X25519 private key.
+/// This differs from the ordinary public key by a low-order component that
+/// depends on the private key, which X25519 ignores, so the two public keys
+/// produce the same shared secrets. Works in constant time.
+pub fn elligator2_derive_pub_key(priv_key: [u8; 32]) -> [u8; 32] {
+	let mut scalar = priv_key;
+	scalar[0] &= 0xf8;
+	scalar[31] &= 0x7f;
+	scalar[31] |= 0x40;
+
+	// X25519 clears the low three bits of the private key, so they're free
+	// to choose the low-order component
+	let low_order_point = decompress(LOW_ORDER_POINT).unwrap();
+	let low_order = ed25519_mult(&[priv_key[0] & 0x07], 3, low_order_point);
+	let point = point_add(basepoint_mult(&scalar), low_order);
+
+	zeroize(&mut scalar);
+
+	// the birational map from the edwards curve to the montgomery curve
+	canonical_bytes((point.z + point.y) / (point.z - point.y))
+}
+
+/// Deterministically generates an X25519 private key whose public key has a
+/// representative from a 32-byte `seed`, returning the private key and the
+/// representative. The seed must be generated by a secure random source.
+///
+/// The public key can be recovered from the representative with
+/// [`elligator2_map`], and is the same as [`elligator2_derive_pub_key`] of
+/// the private key.
+pub fn elligator2_key_pair(seed: [u8; 32]) -> ([u8; 32], [u8; 32]) {
+	let mut counter: u64 = 0;
+
+	// each attempt succeeds with probability about 1/2
+	loop {
+		let mut hasher = Sha512::new();
+		hasher.add_bytes(b"libkrypton/elligator2/key pair\0");
+		hasher.add_bytes(&seed);
+		hasher.add_bytes(&counter.to_le_bytes());
+
+		let mut digest = hasher.out();
+		let mut priv_key = [0; 32];
+		priv_key.copy_from_slice(&digest[0 .. 32]);
+		let tweak = digest[32];
+
+		zeroize(&mut digest);
+
+		if let Some(representative) = elligator2_unmap(elligator2_derive_pub_key(priv_key), tweak) {
+			return (priv_key, representative);
+		}
+
+		zeroize(&mut priv_key);
+		counter += 1;
+	}
+}
+
+/// Like [`elligator2_key_pair`], but generates the seed with `rng`, which must
+/// be a cryptographically secure random number generator.
+#[cfg(feature = "rand_core")]
+pub fn elligator2_key_pair_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> ([u8; 32], [u8; 32]) {
+	let mut seed = [0; 32];
+	rng.fill_bytes(&mut seed);
+
+	let result = elligator2_key_pair(seed);
+	zeroize(&mut seed);
+
+	result
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[test]
+fn test_map() {
+	let vectors = [
+		(
+			"0000000000000000000000000000000000000000000000000000000000000000",
+			"0000000000000000000000000000000000000000000000000000000000000000",
+		),
+		(
+			"ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+			"80e5132b658f7f451b2b658f7f451b2b658f7f451b2b658f7f451b2b658f7f45",
+		),
+		(
+			"000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+			"5f3520001c6c9936a31206afe7c7ac224e8861619bf98872444915899d95f46e",
+		),
+	];
+
+	for (representative, pub_key) in vectors {
+		assert_eq!(elligator2_map(hex(representative)), hex(pub_key));
+	}
+}
+
+#[test]
+fn test_key_pair() {
+	let vectors = [
+		(
+			[0x11; 32],
+			"f3e15a76b8b979f1a0ef4b3b6083106e5e7b3b9922214be7a8048ab0125d5456",
+			"325feeb0b09434b37513ffeddb3aeb9d03b96e7d2c032010931c18aecb2b4c9d",
+			"4f68825454803efdf2e0b7d589a381d313d669ba491be330329dadc95c86572f",
+		),
+		(
+			[0x22; 32],
+			"86acc5437ae616a7de0336d09795e833e745e0b2d98c209a93b2d3fe22fdb78a",
+			"929002307db175d5e76825326308596ea1cb1c35174bc6b1989a9ba23127ae58",
+			"59e6c4fdc354ff2d4305a6f271a248299414d13e3a02fea3e3b2cb7507f2121a",
+		),
+	];
+
+	for (seed, priv_key, representative, pub_key) in vectors {
+		let (computed_priv_key, computed_representative) = elligator2_key_pair(seed);
+
+		assert_eq!(computed_priv_key, hex(priv_key));
+		assert_eq!(computed_representative, hex(representative));
+		assert_eq!(elligator2_derive_pub_key(computed_priv_key), hex(pub_key));
+		assert_eq!(elligator2_map(computed_representative), hex(pub_key));
+	}
+}
+
+#[test]
+fn test_same_shared_secret() {
+	use super::x25519::{x25519_derive_pub_key, x25519_derive_secret};
+
+	let (priv_key, representative) = elligator2_key_pair([0x11; 32]);
+	let pub_key = elligator2_map(representative);
+	assert_ne!(pub_key, x25519_derive_pub_key(priv_key));
+
+	let other_priv_key = [0x44; 32];
+	let other_pub_key = x25519_derive_pub_key(other_priv_key);
+
+	assert_eq!(
+		x25519_derive_secret(other_priv_key, pub_key),
+		x25519_derive_secret(priv_key, other_pub_key),
+	);
+}
+
+#[test]
+fn test_unmap_round_trip() {
+	let mut found = 0;
+
+	for i in 0 .. 16 {
+		let pub_key = elligator2_derive_pub_key([i; 32]);
+
+		for tweak in [0x00, 0x01, 0x40, 0x81, 0xc0] {
+			if let Some(representative) = elligator2_unmap(pub_key, tweak) {
+				assert_eq!(representative[31] & 0xc0, tweak & 0xc0);
+				assert_eq!(elligator2_map(representative), pub_key);
+				found += 1;
+			}
+		}
+	}
+
+	// about half of all public keys have a representative
+	assert!(found > 0 && found < 80);
+}
@@ -0,0 +1,84 @@
+//! Field arithmetic backed by the formally verified code that
+//! [fiat-crypto](https://github.com/mit-plv/fiat-crypto) generates, used in
+//! place of the generic segmented integer arithmetic when the `fiat-crypto`
+//! feature is enabled.
+//!
+//! Both use five 51-bit limbs, so numbers only need to change integer type on
+//! the way in and out. Numbers going in and coming out have limbs of at most
+//! 2 ** 51, which are fiat-crypto's tight bounds.
+
+use fiat_crypto::curve25519_64::{
+	fiat_25519_add,
+	fiat_25519_carry,
+	fiat_25519_carry_mul,
+	fiat_25519_loose_field_element,
+	fiat_25519_opp,
+	fiat_25519_relax,
+	fiat_25519_tight_field_element,
+};
+
+// every number that reaches here already has limbs of at most 2 ** 51:
+// from_bytes and the constants only ever fill 51 bits of each limb,
+// fiat_25519_carry and fiat_25519_carry_mul return tight limbs, reduction
+// by the generic code masks every limb to 51 bits, the precomputed base point
+// table has every limb under 2 ** 51, and selecting between numbers never
+// grows a limb.
+// fiat-crypto's proofs say nothing about larger limbs, so the bound is still
+// checked in release builds, rather than risk silently wrong results.
+fn to_tight<const N: usize>(segments: &[u128; N]) -> fiat_25519_tight_field_element {
+	assert_eq!(N, 5);
+
+	let mut out = fiat_25519_tight_field_element([0; 5]);
+
+	for i in 0 .. 5 {
+		assert!(segments[i] <= 1 << 51, "limb outside fiat-crypto's tight bounds");
+		out[i] = segments[i] as u64;
+	}
+
+	out
+}
+
+fn to_loose<const N: usize>(segments: &[u128; N]) -> fiat_25519_loose_field_element {
+	let mut out = fiat_25519_loose_field_element([0; 5]);
+	fiat_25519_relax(&mut out, &to_tight(segments));
+
+	out
+}
+
+fn from_tight<const N: usize>(num: &fiat_25519_tight_field_element) -> [u128; N] {
+	let mut out = [0; N];
+
+	for i in 0 .. 5 {
+		out[i] = num[i] as u128;
+	}
+
+	out
+}
+
+fn carry<const N: usize>(num: &fiat_25519_loose_field_element) -> [u128; N] {
+	let mut out = fiat_25519_tight_field_element([0; 5]);
+	fiat_25519_carry(&mut out, num);
+
+	from_tight(&out)
+}
+
+pub(super) fn add<const N: usize>(num_a: &[u128; N], num_b: &[u128; N]) -> [u128; N] {
+	let mut sum = fiat_25519_loose_field_element([0; 5]);
+	fiat_25519_add(&mut sum, &to_tight(num_a), &to_tight(num_b));
+
+	carry(&sum)
+}
+
+pub(super) fn mul<const N: usize>(num_a: &[u128; N], num_b: &[u128; N]) -> [u128; N] {
+	let mut product = fiat_25519_tight_field_element([0; 5]);
+	fiat_25519_carry_mul(&mut product, &to_loose(num_a), &to_loose(num_b));
+
+	from_tight(&product)
+}
+
+pub(super) fn neg<const N: usize>(num: &[u128; N]) -> [u128; N] {
+	let mut negated = fiat_25519_loose_field_element([0; 5]);
+	fiat_25519_opp(&mut negated, &to_tight(num));
+
+	carry(&negated)
+}
@@ -1,18 +1,46 @@
+#[cfg(feature = "ed25519")]
+pub mod blind_signature;
+#[cfg(feature = "ed25519")]
+pub mod ecvrf;
+#[cfg(feature = "ed25519")]
 pub mod ed25519;
+#[cfg(all(feature = "ed25519", feature = "x25519"))]
+pub mod elligator2;
+#[cfg(feature = "ed25519")]
+pub mod musig2;
+#[cfg(feature = "ed25519")]
+pub mod oprf;
+#[cfg(feature = "ed25519")]
+pub mod point;
+#[cfg(feature = "ed25519")]
+pub mod poseidon;
+#[cfg(feature = "ed25519")]
+pub mod ristretto;
+#[cfg(feature = "ed25519")]
+pub mod scalar;
+#[cfg(all(feature = "ed25519", feature = "hkdf"))]
+pub mod spake2;
+#[cfg(feature = "x25519")]
 pub mod x25519;
 
+#[cfg(feature = "ed25519")]
 mod arith_mod_l;
+#[cfg(all(target_arch = "x86_64", not(feature = "fiat-crypto")))]
+mod avx2;
+#[cfg(feature = "basepoint-table")]
+mod basepoint_table;
+#[cfg(feature = "rand_core")]
+mod blinding;
+#[cfg(feature = "fiat-crypto")]
+mod fiat;
 mod num;
-
-/// Swaps the two numbers given if `swap` is 1, does nothing if `swap` is 0.
-/// `swap` should never be anything besides 0 or 1.
-/// Works in constant time.
-fn conditional_swap(swap: u8, num_a: &mut num::Num, num_b: &mut num::Num) {
-	let mask = 0u128.wrapping_sub(swap as u128);
-
-	for i in 0 .. 5 {
-		let temp = mask & (num_a.segments[i] ^ num_b.segments[i]);
-		num_a.segments[i] ^= temp;
-		num_b.segments[i] ^= temp;
-	}
-}
+#[cfg(all(feature = "std", feature = "aes", feature = "chacha20", feature = "ed25519", feature = "poly1305"))]
+mod openssh;
+#[cfg(any(feature = "ed25519", feature = "x25519"))]
+mod pkcs8;
+#[cfg(feature = "ed25519")]
+mod sodium;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod dkg;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod ring_signature;
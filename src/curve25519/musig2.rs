@@ -0,0 +1,569 @@
+//! Two-round n-of-n multi-signatures over Ed25519, following the
+//! [MuSig2](https://eprint.iacr.org/2020/1261) protocol.
+//! A group of signers aggregates their public keys into a single key, and
+//! then jointly produces a signature that is an ordinary Ed25519 signature
+//! under the aggregate key, verifiable with [`VerifyingKey::verify`].
+//!
+//! Signing a message takes two rounds of communication:
+//! 1. Each signer calls [`SecretNonce::generate`] and sends the resulting
+//!    [`PublicNonce`] to the others. The nonces can be exchanged before the
+//!    message is known.
+//! 2. Once every public nonce has been received, they're combined with
+//!    [`aggregate_nonces`], and each signer calls [`partial_sign`] and sends
+//!    the resulting [`PartialSignature`] to whoever combines them with
+//!    [`aggregate_partial_signatures`].
+//!
+//! All signers have to pass their keys and nonces to the aggregation
+//! functions in the same order.
+
+use core::convert::TryInto;
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha512;
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	mul_num_mod_l,
+	num_mod_l_from_32_bytes,
+	num_mod_l_from_64_bytes,
+	num_mod_l_to_bytes,
+};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	is_torsion_free,
+	point_add,
+	points_equal,
+	scalar_mult,
+	verify_less_than_l,
+	Point,
+	Signature,
+	SigningKey,
+	VerifyingKey,
+	NEUTRAL_POINT,
+};
+
+/// An error returned by the MuSig2 protocol functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// No public keys were passed in to aggregate.
+	NoKeys,
+	/// A public key has a small-order component, or the keys aggregated to
+	/// the identity.
+	InvalidPublicKey,
+	/// A public nonce or partial signature is not canonically encoded.
+	InvalidEncoding,
+	/// The signing key is not one of the keys that were aggregated, or the
+	/// secret nonce was generated for a different key.
+	KeyMismatch,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::NoKeys => "no public keys to aggregate",
+			Error::InvalidPublicKey => "invalid public key for aggregation",
+			Error::InvalidEncoding => "invalid musig2 nonce or partial signature",
+			Error::KeyMismatch => "signing key is not part of the aggregate key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// hashes the given byte strings with a domain separation tag, reducing the
+// result to a scalar
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> [u64; 4] {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(b"libkrypton/musig2/");
+	hasher.add_bytes(tag);
+	hasher.add_bytes(&[0]);
+
+	for part in parts {
+		hasher.add_bytes(part);
+	}
+
+	num_mod_l_from_64_bytes(&hasher.out())
+}
+
+/// The result of aggregating the public keys of a group of signers.
+#[derive(Copy, Clone, Debug)]
+pub struct KeyAggContext<'a> {
+	keys: &'a [VerifyingKey],
+	list_hash: [u8; 64],
+	aggregate_key: VerifyingKey,
+}
+
+impl<'a> KeyAggContext<'a> {
+	/// Aggregates the public keys of every signer into a single public key.
+	/// The keys are hashed in the order given, so every signer has to use the
+	/// same order; sorting the keys is an easy way to achieve that.
+	pub fn new(keys: &'a [VerifyingKey]) -> Result<Self, Error> {
+		if keys.len() == 0 {
+			return Err(Error::NoKeys);
+		}
+
+		let mut list_hasher = Sha512::new();
+		list_hasher.add_bytes(b"libkrypton/musig2/key list\0");
+
+		for key in keys {
+			if !is_torsion_free(key.point) {
+				return Err(Error::InvalidPublicKey);
+			}
+
+			list_hasher.add_bytes(key.as_bytes());
+		}
+
+		let list_hash = list_hasher.out();
+
+		let mut aggregate = NEUTRAL_POINT;
+
+		for key in keys {
+			let coefficient = key_coefficient(&list_hash, key);
+			aggregate = point_add(aggregate, scalar_mult(coefficient, key.point));
+		}
+
+		let aggregate = compress(aggregate);
+
+		if aggregate == compress(NEUTRAL_POINT) {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		// this can't fail, since the point was just compressed
+		let aggregate_key = VerifyingKey::from_bytes(aggregate).unwrap();
+
+		Ok(Self {keys, list_hash, aggregate_key})
+	}
+
+	/// Returns the aggregate public key, which verifies the final signatures.
+	pub fn aggregate_key(&self) -> VerifyingKey {
+		self.aggregate_key
+	}
+
+	fn coefficient(&self, key: &VerifyingKey) -> Result<[u64; 4], Error> {
+		if !self.keys.contains(key) {
+			return Err(Error::KeyMismatch);
+		}
+
+		Ok(key_coefficient(&self.list_hash, key))
+	}
+}
+
+fn key_coefficient(list_hash: &[u8; 64], key: &VerifyingKey) -> [u64; 4] {
+	hash_to_scalar(b"key coefficient", &[list_hash, key.as_bytes()])
+}
+
+/// The secret half of a signer's nonce pair for a single signing session. It
+/// is consumed by [`partial_sign`] so it can't accidentally be reused, since
+/// signing two messages with the same nonce reveals the private key.
+pub struct SecretNonce {
+	r_1: [u8; 32],
+	r_2: [u8; 32],
+	pub_key: VerifyingKey,
+}
+
+impl SecretNonce {
+	/// Generates a nonce pair for signing with `priv_key` under the aggregate
+	/// key of `ctx`. `randomness` must be freshly generated by a secure random
+	/// source every time, but it's mixed with the private key and `message`
+	/// (which can be left empty if it isn't known yet) as a safeguard against
+	/// a weak random source.
+	pub fn generate(
+		priv_key: &SigningKey,
+		ctx: &KeyAggContext,
+		message: &[u8],
+		randomness: [u8; 32],
+	) -> (Self, PublicNonce) {
+		let mut seed = priv_key.to_bytes();
+		let message_len = (message.len() as u64).to_le_bytes();
+		let aggregate_key = ctx.aggregate_key.as_bytes();
+
+		let mut r = [[0; 32]; 2];
+		let mut public = [0; 64];
+
+		for i in 0 .. 2 {
+			let r_i = hash_to_scalar(b"nonce", &[
+				&randomness,
+				&seed,
+				aggregate_key,
+				&message_len,
+				message,
+				&[i as u8],
+			]);
+
+			r[i] = num_mod_l_to_bytes(r_i);
+			public[32 * i .. 32 * (i + 1)].copy_from_slice(&compress(basepoint_mult(&r[i])));
+		}
+
+		zeroize(&mut seed);
+
+		let secret = Self {
+			r_1: r[0],
+			r_2: r[1],
+			pub_key: priv_key.verifying_key(),
+		};
+
+		zeroize(&mut r[0]);
+		zeroize(&mut r[1]);
+
+		(secret, PublicNonce(public))
+	}
+
+	/// Like [`SecretNonce::generate`], but generates the randomness with
+	/// `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate_with_rng<R: RngCore + CryptoRng>(
+		priv_key: &SigningKey,
+		ctx: &KeyAggContext,
+		message: &[u8],
+		rng: &mut R,
+	) -> (Self, PublicNonce) {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::generate(priv_key, ctx, message, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+}
+
+impl Drop for SecretNonce {
+	fn drop(&mut self) {
+		zeroize(&mut self.r_1);
+		zeroize(&mut self.r_2);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SecretNonce {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.r_1);
+		zeroize(&mut self.r_2);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SecretNonce {}
+
+// deliberately doesn't print the nonces
+impl fmt::Debug for SecretNonce {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SecretNonce")
+			.field("pub_key", &self.pub_key)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The public half of a signer's nonce pair, sent to the other signers in
+/// the first round.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublicNonce([u8; 64]);
+
+impl PublicNonce {
+	/// Creates a public nonce from its 64-byte representation, returning an
+	/// error if it doesn't encode two points in the prime-order subgroup.
+	pub fn from_bytes(bytes: [u8; 64]) -> Result<Self, Error> {
+		decode_nonce_points(&bytes)?;
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 64-byte representation of this public nonce.
+	pub fn to_bytes(&self) -> [u8; 64] {
+		self.0
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(PublicNonce, 64, Hex, |nonce| nonce.to_bytes(), try |bytes| PublicNonce::from_bytes(bytes));
+
+fn decode_nonce_points(bytes: &[u8; 64]) -> Result<(Point, Point), Error> {
+	let mut points = [NEUTRAL_POINT; 2];
+
+	for i in 0 .. 2 {
+		points[i] = decompress(bytes[32 * i .. 32 * (i + 1)].try_into().unwrap())
+			.filter(|&point| is_torsion_free(point))
+			.ok_or(Error::InvalidEncoding)?;
+	}
+
+	Ok((points[0], points[1]))
+}
+
+/// The sum of every signer's public nonce.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AggregateNonce([u8; 64]);
+
+impl AggregateNonce {
+	/// Returns the 64-byte representation of this aggregate nonce.
+	pub fn to_bytes(&self) -> [u8; 64] {
+		self.0
+	}
+}
+
+/// Combines the public nonces of every signer, in the same order as the keys
+/// passed to [`KeyAggContext::new`].
+pub fn aggregate_nonces(nonces: &[PublicNonce]) -> AggregateNonce {
+	let mut sums = [NEUTRAL_POINT; 2];
+
+	for nonce in nonces {
+		// public nonces are checked when they're constructed
+		let (r_1, r_2) = decode_nonce_points(&nonce.0).unwrap();
+
+		sums[0] = point_add(sums[0], r_1);
+		sums[1] = point_add(sums[1], r_2);
+	}
+
+	let mut out = [0; 64];
+	out[0 .. 32].copy_from_slice(&compress(sums[0]));
+	out[32 .. 64].copy_from_slice(&compress(sums[1]));
+
+	AggregateNonce(out)
+}
+
+/// A single signer's share of the final signature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PartialSignature([u8; 32]);
+
+impl PartialSignature {
+	/// Creates a partial signature from its 32-byte representation, returning
+	/// an error if it's not a canonically encoded scalar.
+	pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, Error> {
+		if !verify_less_than_l(&bytes) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 32-byte representation of this partial signature.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(PartialSignature, 32, Hex, |signature| signature.to_bytes(), try |bytes| PartialSignature::from_bytes(bytes));
+
+// the values shared by every signer in a signing session
+struct Session {
+	nonce_coefficient: [u64; 4],
+	big_r: [u8; 32],
+	challenge: [u64; 4],
+}
+
+impl Session {
+	fn new(ctx: &KeyAggContext, aggregate_nonce: &AggregateNonce, message: &[u8]) -> Self {
+		let aggregate_key = ctx.aggregate_key.as_bytes();
+
+		let nonce_coefficient = hash_to_scalar(b"nonce coefficient", &[
+			&aggregate_nonce.0,
+			aggregate_key,
+			message,
+		]);
+
+		// the aggregate nonce was computed from checked public nonces
+		let (r_1, r_2) = decode_nonce_points(&aggregate_nonce.0).unwrap();
+		let big_r = compress(point_add(r_1, scalar_mult(nonce_coefficient, r_2)));
+
+		// the same challenge as ordinary ed25519, so that the final signature
+		// verifies as one
+		let mut challenge_hasher = Sha512::new();
+		challenge_hasher.add_bytes(&big_r);
+		challenge_hasher.add_bytes(aggregate_key);
+		challenge_hasher.add_bytes(message);
+
+		let challenge = num_mod_l_from_64_bytes(&challenge_hasher.out());
+
+		Self {nonce_coefficient, big_r, challenge}
+	}
+}
+
+/// Computes this signer's partial signature over `message`, consuming the
+/// secret nonce generated for this session.
+pub fn partial_sign(
+	priv_key: &SigningKey,
+	secret_nonce: SecretNonce,
+	ctx: &KeyAggContext,
+	aggregate_nonce: &AggregateNonce,
+	message: &[u8],
+) -> Result<PartialSignature, Error> {
+	let pub_key = priv_key.verifying_key();
+
+	if secret_nonce.pub_key != pub_key {
+		return Err(Error::KeyMismatch);
+	}
+
+	let key_coefficient = ctx.coefficient(&pub_key)?;
+	let session = Session::new(ctx, aggregate_nonce, message);
+
+	let x = num_mod_l_from_32_bytes(priv_key.secret_scalar());
+
+	let r_1 = num_mod_l_from_32_bytes(&secret_nonce.r_1);
+	let r_2 = num_mod_l_from_32_bytes(&secret_nonce.r_2);
+
+	// s = r_1 + b * r_2 + c * a * x
+	let s = add_num_mod_l(
+		add_num_mod_l(r_1, mul_num_mod_l(session.nonce_coefficient, r_2)),
+		mul_num_mod_l(session.challenge, mul_num_mod_l(key_coefficient, x)),
+	);
+
+	Ok(PartialSignature(num_mod_l_to_bytes(s)))
+}
+
+/// Checks a single signer's partial signature, which identifies the signer
+/// at fault if the final signature turns out to be invalid.
+/// Not constant-time, since it doesn't involve secret data.
+pub fn partial_verify(
+	pub_key: &VerifyingKey,
+	public_nonce: &PublicNonce,
+	partial_signature: &PartialSignature,
+	ctx: &KeyAggContext,
+	aggregate_nonce: &AggregateNonce,
+	message: &[u8],
+) -> bool {
+	let key_coefficient = match ctx.coefficient(pub_key) {
+		Ok(val) => val,
+		Err(_) => return false,
+	};
+
+	let session = Session::new(ctx, aggregate_nonce, message);
+	let (r_1, r_2) = decode_nonce_points(&public_nonce.0).unwrap();
+
+	// s * B = R_1 + b * R_2 + c * a * X
+	let left_side = basepoint_mult(&partial_signature.0);
+	let right_side = point_add(
+		point_add(r_1, scalar_mult(session.nonce_coefficient, r_2)),
+		scalar_mult(mul_num_mod_l(session.challenge, key_coefficient), pub_key.point),
+	);
+
+	points_equal(left_side, right_side).into()
+}
+
+/// Combines every signer's partial signature into an Ed25519 signature under
+/// the aggregate key. The result is only valid if every partial signature
+/// is, which can be checked with [`partial_verify`].
+pub fn aggregate_partial_signatures(
+	ctx: &KeyAggContext,
+	aggregate_nonce: &AggregateNonce,
+	message: &[u8],
+	partial_signatures: &[PartialSignature],
+) -> Signature {
+	let session = Session::new(ctx, aggregate_nonce, message);
+
+	let mut s = [0; 4];
+
+	for partial_signature in partial_signatures {
+		s = add_num_mod_l(s, num_mod_l_from_32_bytes(&partial_signature.0));
+	}
+
+	let mut out = [0; 64];
+	out[0 .. 32].copy_from_slice(&session.big_r);
+	out[32 .. 64].copy_from_slice(&num_mod_l_to_bytes(s));
+
+	// s is reduced mod l, so this can't fail
+	Signature::from_bytes(out).unwrap()
+}
+
+#[test]
+fn test_three_of_three_signing() {
+	let priv_keys = [
+		SigningKey::from_bytes([1; 32]),
+		SigningKey::from_bytes([2; 32]),
+		SigningKey::from_bytes([3; 32]),
+	];
+
+	let pub_keys = [
+		priv_keys[0].verifying_key(),
+		priv_keys[1].verifying_key(),
+		priv_keys[2].verifying_key(),
+	];
+
+	let ctx = KeyAggContext::new(&pub_keys).unwrap();
+	let message = b"a message signed by the whole group";
+
+	let mut secret_nonces = [None, None, None];
+	let mut public_nonces = [PublicNonce([0; 64]); 3];
+
+	for i in 0 .. 3 {
+		let (secret, public) = SecretNonce::generate(&priv_keys[i], &ctx, message, [i as u8 + 10; 32]);
+		secret_nonces[i] = Some(secret);
+		public_nonces[i] = PublicNonce::from_bytes(public.to_bytes()).unwrap();
+	}
+
+	let aggregate_nonce = aggregate_nonces(&public_nonces);
+	let mut partial_signatures = [PartialSignature([0; 32]); 3];
+
+	for i in 0 .. 3 {
+		let secret = secret_nonces[i].take().unwrap();
+		partial_signatures[i] = partial_sign(&priv_keys[i], secret, &ctx, &aggregate_nonce, message).unwrap();
+
+		assert!(partial_verify(
+			&pub_keys[i],
+			&public_nonces[i],
+			&partial_signatures[i],
+			&ctx,
+			&aggregate_nonce,
+			message,
+		));
+	}
+
+	// a partial signature doesn't verify for a different signer
+	assert!(!partial_verify(
+		&pub_keys[0],
+		&public_nonces[0],
+		&partial_signatures[1],
+		&ctx,
+		&aggregate_nonce,
+		message,
+	));
+
+	let signature = aggregate_partial_signatures(&ctx, &aggregate_nonce, message, &partial_signatures);
+	let aggregate_key = ctx.aggregate_key();
+
+	assert!(aggregate_key.verify(message, &signature));
+	assert!(!aggregate_key.verify(b"a different message", &signature));
+
+	// dropping a signer's share invalidates the signature
+	let incomplete = aggregate_partial_signatures(&ctx, &aggregate_nonce, message, &partial_signatures[.. 2]);
+	assert!(!aggregate_key.verify(message, &incomplete));
+}
+
+#[test]
+fn test_key_aggregation() {
+	let pub_keys = [
+		SigningKey::from_bytes([1; 32]).verifying_key(),
+		SigningKey::from_bytes([2; 32]).verifying_key(),
+	];
+
+	let swapped = [pub_keys[1], pub_keys[0]];
+
+	let aggregate_key = KeyAggContext::new(&pub_keys).unwrap().aggregate_key();
+	assert_ne!(aggregate_key, KeyAggContext::new(&swapped).unwrap().aggregate_key());
+	assert_ne!(aggregate_key, pub_keys[0]);
+
+	assert_eq!(KeyAggContext::new(&[]).unwrap_err(), Error::NoKeys);
+
+	// a point of order 2, which isn't in the prime-order subgroup
+	let mut small_order = [0xff; 32];
+	small_order[0] = 0xec;
+	small_order[31] = 0x7f;
+
+	let small_order_key = VerifyingKey::from_bytes(small_order).unwrap();
+	assert_eq!(KeyAggContext::new(&[pub_keys[0], small_order_key]).unwrap_err(), Error::InvalidPublicKey);
+
+	let ctx = KeyAggContext::new(&pub_keys[.. 1]).unwrap();
+	let outsider = SigningKey::from_bytes([9; 32]);
+	let (secret, public) = SecretNonce::generate(&outsider, &ctx, b"", [0; 32]);
+	let aggregate_nonce = aggregate_nonces(&[public]);
+
+	assert_eq!(partial_sign(&outsider, secret, &ctx, &aggregate_nonce, b"").unwrap_err(), Error::KeyMismatch);
+}
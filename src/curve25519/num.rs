@@ -1,8 +1,15 @@
 use core::ops::{Div, DivAssign};
 
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "ed25519")]
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
 use crate::segmented_int::{SegmentedInt, SegmentedIntDescriptor};
+#[cfg(feature = "rand_core")]
+use crate::zeroize::zeroize;
 
-pub type Num = SegmentedInt<Curve25519Descriptor>;
+pub type Num = SegmentedInt<Curve25519Descriptor, 5>;
 
 pub struct Curve25519Descriptor;
 
@@ -14,6 +21,21 @@ impl SegmentedIntDescriptor for Curve25519Descriptor {
 	const SEGMENT_MASK: u128 = LOW_51_BITS;
 	const ZERO: u128 = 0;
 	const ONE: u128 = 1;
+
+	#[cfg(feature = "fiat-crypto")]
+	fn add_segments<const N: usize>(num_a: &[u128; N], num_b: &[u128; N]) -> Option<[u128; N]> {
+		Some(super::fiat::add(num_a, num_b))
+	}
+
+	#[cfg(feature = "fiat-crypto")]
+	fn mul_segments<const N: usize>(num_a: &[u128; N], num_b: &[u128; N]) -> Option<[u128; N]> {
+		Some(super::fiat::mul(num_a, num_b))
+	}
+
+	#[cfg(feature = "fiat-crypto")]
+	fn neg_segments<const N: usize>(num: &[u128; N]) -> Option<[u128; N]> {
+		Some(super::fiat::neg(num))
+	}
 }
 
 const LOW_51_BITS: u128 = 0x0007_ffff_ffff_ffff;
@@ -22,24 +44,101 @@ impl Num {
 	pub const ZERO: Self = Self {segments: [0, 0, 0, 0, 0]};
 	pub const ONE: Self = Self {segments: [1, 0, 0, 0, 0]};
 
-	pub fn recip(self) -> Self {
-		let mut acc = Num::ONE;
+	#[cfg(feature = "ed25519")]
+	pub const SQRT_MINUS_1: Self = Self {
+		segments: [
+			0x61b274a0ea0b0,
+			0x0d5a5fc8f189d,
+			0x7ef5e9cbd0c60,
+			0x78595a6804c9e,
+			0x2b8324804fc1d,
+		],
+	};
+
+	pub fn square(self) -> Self {
+		self * self
+	}
 
-		for _ in 0 .. 250 {
-			acc = acc * acc;
-			acc *= self;
+	fn square_n(mut self, n: usize) -> Self {
+		for _ in 0 .. n {
+			self = self.square();
 		}
 
-		for _ in 0 .. 2 {
-			acc = acc * acc;
-			acc = acc * acc;
-			acc *= self;
-		}
+		self
+	}
 
-		acc = acc * acc;
-		acc *= self;
+	// returns this number raised to the power of 2 ** 250 - 1, along with its
+	// 11th power, which are the shared start of the chains for p - 2 and
+	// (p - 5) / 8
+	fn pow_2_250_minus_1(self) -> (Self, Self) {
+		// each pow_2_n is this number raised to the power of 2 ** n - 1
+		let pow_2 = self.square();
+		let pow_9 = pow_2.square_n(2) * self;
+		let pow_11 = pow_9 * pow_2;
+		let pow_2_5 = pow_11.square() * pow_9;
+		let pow_2_10 = pow_2_5.square_n(5) * pow_2_5;
+		let pow_2_20 = pow_2_10.square_n(10) * pow_2_10;
+		let pow_2_40 = pow_2_20.square_n(20) * pow_2_20;
+		let pow_2_50 = pow_2_40.square_n(10) * pow_2_10;
+		let pow_2_100 = pow_2_50.square_n(50) * pow_2_50;
+		let pow_2_200 = pow_2_100.square_n(100) * pow_2_100;
+		let pow_2_250 = pow_2_200.square_n(50) * pow_2_50;
+
+		(pow_2_250, pow_11)
+	}
 
-		acc
+	/// Raises this number to the power of p - 2, its reciprocal. Uses 254
+	/// squarings and 11 multiplications.
+	pub fn recip(self) -> Self {
+		// p - 2 = 2 ** 255 - 21 = (2 ** 250 - 1) * 2 ** 5 + 11
+		let (pow_2_250, pow_11) = self.pow_2_250_minus_1();
+		pow_2_250.square_n(5) * pow_11
+	}
+
+	/// Raises this number to the power of (p - 5) / 8, which is used for
+	/// computing square roots.
+	#[cfg(feature = "ed25519")]
+	pub fn pow_p58(self) -> Self {
+		// (p - 5) / 8 = 2 ** 252 - 3 = (2 ** 250 - 1) * 2 ** 2 + 1
+		let (pow_2_250, _) = self.pow_2_250_minus_1();
+		pow_2_250.square_n(2) * self
+	}
+
+	/// Returns whether the fully reduced number is odd, which is what counts
+	/// as negative for choosing between square roots. Works in constant time.
+	#[cfg(feature = "ed25519")]
+	pub fn is_negative(self) -> Choice {
+		let mut reduced = self;
+		reduced.full_modular_reduction();
+
+		Choice::from((reduced.segments[0] & 0x01) as u8)
+	}
+
+	/// Computes the nonnegative square root of `u / v` in constant time,
+	/// returning 1 along with it if `u / v` is a square. Otherwise the root
+	/// is of `sqrt(-1) * u / v`, or zero if `v` is zero and `u` isn't. If `u`
+	/// is zero, the result is zero and counts as a square.
+	#[cfg(feature = "ed25519")]
+	pub fn sqrt_ratio(u: &Self, v: &Self) -> (Choice, Self) {
+		let v_cubed = v.square() * *v;
+		let v_to_the_7 = v_cubed.square() * *v;
+
+		// r = u v^3 (u v^7)^((p - 5) / 8), whose square is plus or minus u / v,
+		// or plus or minus sqrt(-1) u / v
+		let mut root = *u * v_cubed * (*u * v_to_the_7).pow_p58();
+		let check = *v * root.square();
+
+		let correct_sign = check.ct_eq(u);
+		let flipped_sign = check.ct_eq(&-*u);
+		let flipped_sign_i = check.ct_eq(&(-*u * Self::SQRT_MINUS_1));
+
+		let rotated = root * Self::SQRT_MINUS_1;
+		root.conditional_assign(&rotated, flipped_sign | flipped_sign_i);
+
+		let negated = -root;
+		root.conditional_assign(&negated, root.is_negative());
+
+		(correct_sign | flipped_sign, root)
 	}
 
 	pub fn from_bytes(mut bytes: [u8; 32]) -> Self {
@@ -67,6 +166,30 @@ impl Num {
 		Self {segments: out}
 	}
 
+	/// Generates a uniformly random number modulo p, by reducing 64 random
+	/// bytes, so the bias is negligible.
+	#[cfg(feature = "rand_core")]
+	pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut low = [0; 32];
+		let mut high = [0; 32];
+		rng.fill_bytes(&mut low);
+		rng.fill_bytes(&mut high);
+
+		// from_bytes drops the top bit of each half, which is worth
+		// 2 ** 255 = 19 and 2 ** 511 = 19 * 38 mod p, while the upper half
+		// itself is worth 2 ** 256 = 38
+		let low_top_bit = (low[31] >> 7) as u128;
+		let high_top_bit = (high[31] >> 7) as u128;
+		let top_bits = Self {segments: [19 * low_top_bit + 722 * high_top_bit, 0, 0, 0, 0]};
+
+		let out = Self::from_bytes(low) + Self::from_bytes(high) * Self {segments: [38, 0, 0, 0, 0]} + top_bits;
+
+		zeroize(&mut low);
+		zeroize(&mut high);
+
+		out
+	}
+
 	pub fn to_bytes(self) -> [u8; 32] {
 		let mut len = 0;
 		let mut acc = 0;
@@ -75,7 +198,9 @@ impl Num {
 		let mut out = [0; 32];
 
 		for segment in self.segments {
-			acc |= segment << len;
+			// added rather than or'd in, since segments can be exactly 2 ** 51
+			// before a full reduction
+			acc += segment << len;
 			len += 51;
 
 			while len >= 8 {
@@ -93,7 +218,7 @@ impl Num {
 }
 
 #[test]
-#[cfg(feature = "std")]
+#[cfg(all(feature = "std", feature = "chacha20"))]
 fn test_from_and_to_bytes() {
 	use std::io::Read;
 	use crate::chacha20::ChaCha20;
@@ -136,13 +261,108 @@ fn test_recip() {
 impl Div for Num {
 	type Output = Self;
 
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn div(self, other: Self) -> Self {
 		self * other.recip()
 	}
 }
 
 impl DivAssign for Num {
+	#[allow(clippy::suspicious_op_assign_impl)]
 	fn div_assign(&mut self, other: Self) {
 		*self *= other.recip();
 	}
 }
+
+#[test]
+fn test_conditional_select_and_swap() {
+	use crate::choice::{Choice, ConditionallySelectable};
+
+	let num_a = Num {segments: [1, 2, 3, 4, 5]};
+	let num_b = Num {segments: [6, 7, 8, 9, 10]};
+
+	assert_eq!(Num::conditional_select(&num_a, &num_b, Choice::from(0)).segments, num_a.segments);
+	assert_eq!(Num::conditional_select(&num_a, &num_b, Choice::from(1)).segments, num_b.segments);
+
+	let mut assigned = num_a;
+	assigned.conditional_assign(&num_b, Choice::from(0));
+	assert_eq!(assigned.segments, num_a.segments);
+	assigned.conditional_assign(&num_b, Choice::from(1));
+	assert_eq!(assigned.segments, num_b.segments);
+
+	let (mut swap_a, mut swap_b) = (num_a, num_b);
+	Num::conditional_swap(&mut swap_a, &mut swap_b, Choice::from(0));
+	assert_eq!((swap_a.segments, swap_b.segments), (num_a.segments, num_b.segments));
+	Num::conditional_swap(&mut swap_a, &mut swap_b, Choice::from(1));
+	assert_eq!((swap_a.segments, swap_b.segments), (num_b.segments, num_a.segments));
+}
+
+#[test]
+fn test_ct_eq() {
+	use crate::choice::ConstantTimeEq;
+
+	let mask = (1 << 51) - 1;
+	let p = Num {segments: [mask - 18, mask, mask, mask, mask]};
+	let p_plus_one = Num {segments: [mask - 17, mask, mask, mask, mask]};
+
+	assert!(bool::from(p.ct_eq(&Num::ZERO)));
+	assert!(bool::from(p_plus_one.ct_eq(&Num::ONE)));
+	assert!(!bool::from(p_plus_one.ct_eq(&Num::ZERO)));
+	assert!(!bool::from(Num::ONE.ct_eq(&-Num::ONE)));
+}
+
+#[test]
+#[cfg(feature = "ed25519")]
+fn test_sqrt_ratio() {
+	for i in 0 .. 20 {
+		let u = Num {segments: [i, 0, 0, 0, 0]};
+		let v = Num {segments: [i * 7 + 3, 0, 0, 0, 0]};
+
+		let (is_square, root) = Num::sqrt_ratio(&u, &v);
+		let expected = if bool::from(is_square) {u} else {u * Num::SQRT_MINUS_1};
+
+		assert!(bool::from((v * root.square()).ct_eq(&expected)));
+		assert!(!bool::from(root.is_negative()));
+	}
+
+	// 4 / 1 has the root 2 and not -2
+	let four = Num {segments: [4, 0, 0, 0, 0]};
+	let (is_square, root) = Num::sqrt_ratio(&four, &Num::ONE);
+	assert!(bool::from(is_square));
+	assert!(bool::from(root.ct_eq(&Num {segments: [2, 0, 0, 0, 0]})));
+
+	// a nonzero numerator over zero has no root
+	let (is_square, root) = Num::sqrt_ratio(&Num::ONE, &Num::ZERO);
+	assert!(!bool::from(is_square));
+	assert!(bool::from(root.ct_eq(&Num::ZERO)));
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn test_random() {
+	use crate::test_util::FixedRng;
+
+	// 2 ** 512 - 1 and 0x3f3e ... 0100 mod p, checked against python's
+	// arbitrary-precision integers
+	let cases = [
+		([0xff; 64], [
+			0xa3, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		]),
+		(core::array::from_fn(|i| i as u8), [
+			0x29, 0xed, 0x12, 0x3a, 0x61, 0x88, 0xaf, 0xd6,
+			0xfd, 0x24, 0x4c, 0x73, 0x9a, 0xc1, 0xe8, 0x0f,
+			0x37, 0x5e, 0x85, 0xac, 0xd3, 0xfa, 0x21, 0x49,
+			0x70, 0x97, 0xbe, 0xe5, 0x0c, 0x34, 0x5b, 0x02,
+		]),
+	];
+
+	for (bytes, expected) in cases {
+		let mut num = Num::random(&mut FixedRng::new(&bytes));
+		num.full_modular_reduction();
+
+		assert_eq!(num.to_bytes(), expected);
+	}
+}
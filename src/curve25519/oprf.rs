@@ -0,0 +1,463 @@
+//! Implemented according to [IETF RFC 9497](https://datatracker.ietf.org/doc/html/rfc9497),
+//! using the ristretto255-SHA512 ciphersuite.
+//! An oblivious pseudorandom function lets a client compute a PRF of its
+//! input under a server's key without the server learning the input or the
+//! output, and without the client learning the key. This is the building
+//! block of OPAQUE and of Privacy Pass tokens.
+//!
+//! The client blinds its input with a random scalar and sends the blinded
+//! element to the server, which evaluates it with its secret key. The client
+//! then unblinds the result and hashes it into the output. In the verifiable
+//! mode (VOPRF), the server also proves that it used the key matching its
+//! public key, so it can't tag clients by using a different key for each.
+//!
+//! Only single evaluations are supported, not batches sharing a proof.
+//!
+//! ```
+//! use libkrypton::oprf::{self, Mode};
+//! use libkrypton::scalar::Scalar;
+//!
+//! let (secret_key, public_key) = oprf::derive_key_pair(Mode::Voprf, &[0xa3; 32], b"test key").unwrap();
+//!
+//! // the client, with a blind that should be a random scalar
+//! let blind = Scalar::from_bytes_mod_order(&[0x42; 32]);
+//! let blinded_element = oprf::blind(Mode::Voprf, b"input", &blind).unwrap();
+//!
+//! // the server, with proof randomness that should also be random
+//! let proof_scalar = Scalar::from_bytes_mod_order(&[0x24; 32]);
+//! let (evaluated_element, proof) = oprf::voprf_blind_evaluate(&secret_key, &blinded_element, &proof_scalar);
+//!
+//! // the client again
+//! let output = oprf::voprf_finalize(b"input", &blind, &blinded_element, &evaluated_element, &public_key, &proof).unwrap();
+//! assert_eq!(output, oprf::evaluate(Mode::Voprf, &secret_key, b"input").unwrap());
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha512;
+
+use super::ristretto::RistrettoPoint;
+use super::scalar::Scalar;
+
+const IDENTIFIER: &[u8] = b"ristretto255-SHA512";
+
+/// An error returned by the OPRF functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// An input hashes to the identity element, which happens with
+	/// negligible probability, or a received element is the identity.
+	InvalidInput,
+	/// The server's proof doesn't verify.
+	VerifyError,
+	/// No key pair could be derived from the seed, which happens with
+	/// negligible probability.
+	DeriveKeyPairError,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidInput => "invalid oprf input",
+			Error::VerifyError => "invalid oprf proof",
+			Error::DeriveKeyPairError => "oprf key pair derivation failed",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The protocol variant, which is bound into every hash so that the modes
+/// can't be mixed up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+	/// The base mode, without proofs.
+	Oprf,
+	/// The verifiable mode, where the server proves its evaluation.
+	Voprf,
+}
+
+impl Mode {
+	// "OPRFV1-" || mode || "-" || identifier, padded to a fixed size
+	fn context_string(self) -> [u8; 28] {
+		let mut out = [0; 28];
+		out[.. 7].copy_from_slice(b"OPRFV1-");
+		out[7] = self as u8;
+		out[8] = b'-';
+		out[9 ..].copy_from_slice(IDENTIFIER);
+		out
+	}
+}
+
+/// A proof that the server evaluated an element with the secret key
+/// matching its public key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+	c: Scalar,
+	s: Scalar,
+}
+
+impl Proof {
+	/// Parses a proof from its 64-byte encoding, returning [`None`] if either
+	/// scalar isn't canonical.
+	pub fn from_bytes(bytes: &[u8; 64]) -> Option<Self> {
+		let c = Scalar::from_canonical_bytes(bytes[.. 32].try_into().unwrap())?;
+		let s = Scalar::from_canonical_bytes(bytes[32 ..].try_into().unwrap())?;
+
+		Some(Self {c, s})
+	}
+
+	/// Encodes this proof as 64 bytes.
+	pub fn to_bytes(&self) -> [u8; 64] {
+		let mut out = [0; 64];
+		out[.. 32].copy_from_slice(&self.c.to_bytes());
+		out[32 ..].copy_from_slice(&self.s.to_bytes());
+		out
+	}
+}
+
+// expand_message_xmd from RFC 9380 with SHA-512, for 64 bytes of output
+fn expand_message(parts: &[&[u8]], dst: &[&[u8]]) -> [u8; 64] {
+	let dst_len = dst.iter().map(|x| x.len()).sum::<usize>() as u8;
+
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&[0; 128]);
+
+	for part in parts {
+		hasher.add_bytes(part);
+	}
+
+	hasher.add_bytes(&[0, 64, 0]);
+	dst.iter().for_each(|x| hasher.add_bytes(x));
+	hasher.add_bytes(&[dst_len]);
+	let b_0 = hasher.out();
+
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&b_0);
+	hasher.add_bytes(&[1]);
+	dst.iter().for_each(|x| hasher.add_bytes(x));
+	hasher.add_bytes(&[dst_len]);
+	hasher.out()
+}
+
+fn hash_to_group(mode: Mode, input: &[u8]) -> RistrettoPoint {
+	RistrettoPoint::from_uniform_bytes(&expand_message(&[input], &[b"HashToGroup-", &mode.context_string()]))
+}
+
+fn hash_to_scalar(mode: Mode, parts: &[&[u8]]) -> Scalar {
+	Scalar::from_bytes_mod_order_wide(&expand_message(parts, &[b"HashToScalar-", &mode.context_string()]))
+}
+
+fn length_prefix(bytes: &[u8]) -> [u8; 2] {
+	(bytes.len() as u16).to_be_bytes()
+}
+
+/// Decodes an element received from the other party, rejecting the
+/// identity as well as invalid encodings.
+pub fn decode_element(bytes: [u8; 32]) -> Result<RistrettoPoint, Error> {
+	match RistrettoPoint::decompress(bytes) {
+		Some(element) if !element.is_identity() => Ok(element),
+		_ => Err(Error::InvalidInput),
+	}
+}
+
+/// Deterministically derives a server key pair from a 32-byte `seed`, which
+/// must be secret and uniformly random, and public `info` separating keys
+/// derived from the same seed.
+///
+/// # Panics
+/// * Panics if `info` is longer than 65535 bytes.
+pub fn derive_key_pair(mode: Mode, seed: &[u8; 32], info: &[u8]) -> Result<(Scalar, RistrettoPoint), Error> {
+	assert!(info.len() <= u16::MAX as usize);
+
+	let context_string = mode.context_string();
+
+	for counter in 0 ..= 255 {
+		let uniform = expand_message(
+			&[seed, &length_prefix(info), info, &[counter]],
+			&[b"DeriveKeyPair", &context_string],
+		);
+
+		let secret_key = Scalar::from_bytes_mod_order_wide(&uniform);
+
+		if secret_key != Scalar::ZERO {
+			return Ok((secret_key, RistrettoPoint::mul_base(&secret_key)));
+		}
+	}
+
+	Err(Error::DeriveKeyPairError)
+}
+
+/// Blinds `input` with `blind`, which must be a uniformly random nonzero
+/// scalar that's kept until [`finalize`] or [`voprf_finalize`], returning
+/// the blinded element to send to the server.
+pub fn blind(mode: Mode, input: &[u8], blind: &Scalar) -> Result<RistrettoPoint, Error> {
+	let input_element = hash_to_group(mode, input);
+
+	if input_element.is_identity() {
+		return Err(Error::InvalidInput);
+	}
+
+	Ok(input_element.mul(blind))
+}
+
+/// Blinds `input` like [`blind`], with a blind generated by `rng`, which must
+/// be a cryptographically secure random number generator. Returns the blind
+/// along with the blinded element.
+#[cfg(feature = "rand_core")]
+pub fn blind_with_rng<R: RngCore + CryptoRng>(
+	mode: Mode,
+	input: &[u8],
+	rng: &mut R,
+) -> Result<(Scalar, RistrettoPoint), Error> {
+	let blind_scalar = loop {
+		let scalar = Scalar::random(rng);
+
+		if scalar != Scalar::ZERO {
+			break scalar;
+		}
+	};
+
+	Ok((blind_scalar, blind(mode, input, &blind_scalar)?))
+}
+
+/// Evaluates a blinded element with the server's secret key, in the base
+/// mode.
+pub fn blind_evaluate(secret_key: &Scalar, blinded_element: &RistrettoPoint) -> RistrettoPoint {
+	blinded_element.mul(secret_key)
+}
+
+// the composite elements M and Z of the RFC for a single evaluation, with
+// Z = secret_key * M if the secret key is known
+fn compute_composites(
+	secret_key: Option<&Scalar>,
+	public_key: &RistrettoPoint,
+	blinded_element: &RistrettoPoint,
+	evaluated_element: &RistrettoPoint,
+) -> (RistrettoPoint, RistrettoPoint) {
+	let context_string = Mode::Voprf.context_string();
+	let public_key = public_key.compress();
+
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&length_prefix(&public_key));
+	hasher.add_bytes(&public_key);
+	hasher.add_bytes(&[0, 5 + context_string.len() as u8]);
+	hasher.add_bytes(b"Seed-");
+	hasher.add_bytes(&context_string);
+	let seed = hasher.out();
+
+	let blinded = blinded_element.compress();
+	let evaluated = evaluated_element.compress();
+
+	let d = hash_to_scalar(Mode::Voprf, &[
+		&length_prefix(&seed),
+		&seed,
+		&[0, 0],
+		&length_prefix(&blinded),
+		&blinded,
+		&length_prefix(&evaluated),
+		&evaluated,
+		b"Composite",
+	]);
+
+	let m = blinded_element.mul(&d);
+
+	let z = match secret_key {
+		Some(secret_key) => m.mul(secret_key),
+		None => evaluated_element.mul(&d),
+	};
+
+	(m, z)
+}
+
+fn challenge(elements: [&RistrettoPoint; 5]) -> Scalar {
+	let encoded = elements.map(|x| x.compress());
+	let prefix = length_prefix(&encoded[0]);
+
+	hash_to_scalar(Mode::Voprf, &[
+		&prefix, &encoded[0],
+		&prefix, &encoded[1],
+		&prefix, &encoded[2],
+		&prefix, &encoded[3],
+		&prefix, &encoded[4],
+		b"Challenge",
+	])
+}
+
+/// Evaluates a blinded element with the server's secret key in the
+/// verifiable mode, returning the evaluated element along with a proof of
+/// it. `proof_scalar` must be a uniformly random scalar, used only once.
+pub fn voprf_blind_evaluate(
+	secret_key: &Scalar,
+	blinded_element: &RistrettoPoint,
+	proof_scalar: &Scalar,
+) -> (RistrettoPoint, Proof) {
+	let public_key = RistrettoPoint::mul_base(secret_key);
+	let evaluated_element = blinded_element.mul(secret_key);
+
+	let (m, z) = compute_composites(Some(secret_key), &public_key, blinded_element, &evaluated_element);
+	let t2 = RistrettoPoint::mul_base(proof_scalar);
+	let t3 = m.mul(proof_scalar);
+
+	let c = challenge([&public_key, &m, &z, &t2, &t3]);
+	let s = *proof_scalar - c * *secret_key;
+
+	(evaluated_element, Proof {c, s})
+}
+
+/// Evaluates a blinded element like [`voprf_blind_evaluate`], with proof
+/// randomness generated by `rng`, which must be a cryptographically secure
+/// random number generator.
+#[cfg(feature = "rand_core")]
+pub fn voprf_blind_evaluate_with_rng<R: RngCore + CryptoRng>(
+	secret_key: &Scalar,
+	blinded_element: &RistrettoPoint,
+	rng: &mut R,
+) -> (RistrettoPoint, Proof) {
+	voprf_blind_evaluate(secret_key, blinded_element, &Scalar::random(rng))
+}
+
+fn finalize_hash(input: &[u8], unblinded_element: &RistrettoPoint) -> [u8; 64] {
+	let unblinded = unblinded_element.compress();
+
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(&length_prefix(input));
+	hasher.add_bytes(input);
+	hasher.add_bytes(&length_prefix(&unblinded));
+	hasher.add_bytes(&unblinded);
+	hasher.add_bytes(b"Finalize");
+	hasher.out()
+}
+
+/// Unblinds the server's evaluation of `input` in the base mode, returning
+/// the 64-byte PRF output.
+///
+/// # Panics
+/// * Panics if `input` is longer than 65535 bytes.
+pub fn finalize(input: &[u8], blind: &Scalar, evaluated_element: &RistrettoPoint) -> [u8; 64] {
+	assert!(input.len() <= u16::MAX as usize);
+
+	finalize_hash(input, &evaluated_element.mul(&blind.invert()))
+}
+
+/// Checks the server's proof and unblinds its evaluation of `input` in the
+/// verifiable mode, returning the 64-byte PRF output.
+///
+/// # Panics
+/// * Panics if `input` is longer than 65535 bytes.
+pub fn voprf_finalize(
+	input: &[u8],
+	blind: &Scalar,
+	blinded_element: &RistrettoPoint,
+	evaluated_element: &RistrettoPoint,
+	public_key: &RistrettoPoint,
+	proof: &Proof,
+) -> Result<[u8; 64], Error> {
+	let (m, z) = compute_composites(None, public_key, blinded_element, evaluated_element);
+	let t2 = RistrettoPoint::mul_base(&proof.s) + public_key.mul(&proof.c);
+	let t3 = m.mul(&proof.s) + z.mul(&proof.c);
+
+	if challenge([public_key, &m, &z, &t2, &t3]) != proof.c {
+		return Err(Error::VerifyError);
+	}
+
+	Ok(finalize(input, blind, evaluated_element))
+}
+
+/// Computes the PRF output for `input` directly with the secret key, as the
+/// server, which gives the same result as the blinded protocol.
+///
+/// # Panics
+/// * Panics if `input` is longer than 65535 bytes.
+pub fn evaluate(mode: Mode, secret_key: &Scalar, input: &[u8]) -> Result<[u8; 64], Error> {
+	assert!(input.len() <= u16::MAX as usize);
+
+	let input_element = hash_to_group(mode, input);
+
+	if input_element.is_identity() {
+		return Err(Error::InvalidInput);
+	}
+
+	Ok(finalize_hash(input, &input_element.mul(secret_key)))
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix A.1.1 of the RFC
+#[test]
+fn test_oprf_vectors() {
+	let (secret_key, _) = derive_key_pair(Mode::Oprf, &[0xa3; 32], b"test key").unwrap();
+	assert_eq!(secret_key.to_bytes(), hex("5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e"));
+
+	let blind_scalar = Scalar::from_canonical_bytes(hex("64d37aed22a27f5191de1c1cfc1a3e6d8b9fc86ba5d5ca6e1dd6b4e13ac60a07")).unwrap();
+
+	let vectors = [
+		(
+			&[0x00][..],
+			"46a66736ac3d177b5687268ffb6913343bb0c9163e67f5cdebfb2af631d24c26",
+			"169e78d9f6ef5ccacadcd34e552a641180447c4eb43d636cf803f0a88e3d4151",
+			"527759c3d9366f277d8c6020418d96bb393ba2afb20ff90df23fb7708264e2f3ab9135e3bd69955851de4b1f9fe8a0973396719b7912ba9ee8aa7d0b5e24bcf6",
+		),
+		(
+			&[0x5a; 17][..],
+			"de72caab382d8dae475d6f50cbaecff37011f3b83adc539b60226272cd51ef7f",
+			"34aaea795af178c47bffde4768df7beaed3c1ef184b7ba612732a9eb31f0c566",
+			"f4a74c9c592497375e796aa837e907b1a045d34306a749db9f34221f7e750cb4f2a6413a6bf6fa5e19ba6348eb673934a722a7ede2e7621306d18951e7cf2c73",
+		),
+	];
+
+	for (input, blinded, evaluated, output) in vectors {
+		let blinded_element = blind(Mode::Oprf, input, &blind_scalar).unwrap();
+		assert_eq!(blinded_element.compress(), hex(blinded));
+
+		let evaluated_element = blind_evaluate(&secret_key, &decode_element(hex(blinded)).unwrap());
+		assert_eq!(evaluated_element.compress(), hex(evaluated));
+
+		assert_eq!(finalize(input, &blind_scalar, &evaluated_element), hex(output));
+		assert_eq!(evaluate(Mode::Oprf, &secret_key, input).unwrap(), hex(output));
+	}
+}
+
+// the first test vector from appendix A.1.2 of RFC 9497, for VOPRF mode with the
+// ristretto255-SHA512 ciphersuite
+#[test]
+fn test_voprf_vectors() {
+	let (secret_key, public_key) = derive_key_pair(Mode::Voprf, &[0xa3; 32], b"test key").unwrap();
+	assert_eq!(secret_key.to_bytes(), hex("e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909"));
+	assert_eq!(public_key.compress(), hex("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e"));
+
+	let blind_scalar = Scalar::from_canonical_bytes(hex("64d37aed22a27f5191de1c1cfc1a3e6d8b9fc86ba5d5ca6e1dd6b4e13ac60a07")).unwrap();
+	let proof_scalar = Scalar::from_canonical_bytes(hex("222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e")).unwrap();
+
+	let blinded_element = blind(Mode::Voprf, &[0x00], &blind_scalar).unwrap();
+	assert_eq!(blinded_element.compress(), hex("da085e051497a525a096bed56466c0c66bbe4839c4d6ab8fc66424cbb3c28f42"));
+
+	let (evaluated_element, proof) = voprf_blind_evaluate(&secret_key, &blinded_element, &proof_scalar);
+	assert_eq!(evaluated_element.compress(), hex("9cc4ecfd50a981798052306b82b246e760863b7d1dbbaac71a9d1bf3541b991a"));
+	assert_eq!(proof.to_bytes(), hex("31b1e2eb0c3ca3c32366b8dbda87815aaad81a2ed105ea47f40bb3181c6b0e0b70f3b1c0bdfc99523197a13ca283e6893bf1d17545c3ff5e945c2b857d973e01"));
+
+	let output = voprf_finalize(&[0x00], &blind_scalar, &blinded_element, &evaluated_element, &public_key, &proof).unwrap();
+	assert_eq!(output, hex("b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7da4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c"));
+
+	// a proof for another element, or from another key, is rejected
+	let other_element = blind(Mode::Voprf, &[0x01], &blind_scalar).unwrap();
+	assert_eq!(
+		voprf_finalize(&[0x00], &blind_scalar, &other_element, &evaluated_element, &public_key, &proof),
+		Err(Error::VerifyError),
+	);
+
+	let (_, other_key) = derive_key_pair(Mode::Voprf, &[0xa4; 32], b"test key").unwrap();
+	assert_eq!(
+		voprf_finalize(&[0x00], &blind_scalar, &blinded_element, &evaluated_element, &other_key, &proof),
+		Err(Error::VerifyError),
+	);
+
+	assert_eq!(Proof::from_bytes(&proof.to_bytes()), Some(proof));
+	assert_eq!(decode_element([0; 32]), Err(Error::InvalidInput));
+}
@@ -0,0 +1,313 @@
+//! Points on the Edwards25519 curve and its birationally equivalent
+//! Montgomery form Curve25519, for building protocols on top of the same
+//! curve arithmetic used by [`ed25519`](crate::ed25519) and
+//! [`x25519`](crate::x25519).
+//!
+//! Scalars are 32-byte little-endian integers. Scalar multiplication works
+//! in constant time, while decompression, comparisons and the subgroup
+//! checks don't, since they're meant to be used on public points.
+//!
+//! The Edwards curve has a cofactor of 8: besides the prime-order subgroup
+//! generated by the base point, it contains points of small order, which
+//! protocols usually need to reject or clear with
+//! [`EdwardsPoint::mul_by_cofactor`].
+
+use core::fmt;
+use core::ops::{Add, Neg, Sub};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::arith_mod_l::{num_mod_l_from_32_bytes, num_mod_l_to_bytes};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	ed25519_mult,
+	is_torsion_free,
+	mul_by_cofactor,
+	negate,
+	point_add,
+	point_double,
+	points_equal,
+	Point,
+	BASE_POINT,
+	NEUTRAL_POINT,
+};
+use super::num::Num;
+#[cfg(feature = "x25519")]
+use super::x25519::x25519_derive_secret;
+
+/// A point on the Edwards25519 curve.
+#[derive(Copy, Clone)]
+pub struct EdwardsPoint(Point);
+
+impl EdwardsPoint {
+	/// The identity element, the neutral point (0, 1).
+	pub const IDENTITY: Self = Self(NEUTRAL_POINT);
+
+	/// The base point used by Ed25519, which generates the prime-order
+	/// subgroup.
+	pub const BASEPOINT: Self = Self(BASE_POINT);
+
+	/// Decompresses a point from its 32-byte encoding, returning [`None`] if
+	/// the y-coordinate isn't canonical or there's no point with it. The
+	/// point may have a small-order component.
+	pub fn decompress(bytes: [u8; 32]) -> Option<Self> {
+		decompress(bytes).map(Self)
+	}
+
+	/// Compresses the point to its canonical 32-byte encoding.
+	pub fn compress(&self) -> [u8; 32] {
+		compress(self.0)
+	}
+
+	/// Returns twice this point.
+	pub fn double(&self) -> Self {
+		Self(point_double(self.0))
+	}
+
+	/// Multiplies this point by a 256-bit scalar. Works in constant time.
+	pub fn mul(&self, scalar: &[u8; 32]) -> Self {
+		Self(ed25519_mult(scalar, 256, self.0))
+	}
+
+	/// Multiplies the base point by a 256-bit scalar, which is faster than
+	/// [`EdwardsPoint::mul`] on [`EdwardsPoint::BASEPOINT`]. Works in
+	/// constant time.
+	pub fn mul_base(scalar: &[u8; 32]) -> Self {
+		// the base point has order l, so reducing the scalar doesn't change
+		// the result, and clears the top bit as basepoint_mult requires
+		let reduced = num_mod_l_to_bytes(num_mod_l_from_32_bytes(scalar));
+		Self(basepoint_mult(&reduced))
+	}
+
+	/// Multiplies this point by the cofactor 8, clearing any small-order
+	/// component.
+	pub fn mul_by_cofactor(&self) -> Self {
+		Self(mul_by_cofactor(self.0))
+	}
+
+	/// Returns whether this is the identity.
+	pub fn is_identity(&self) -> bool {
+		*self == Self::IDENTITY
+	}
+
+	/// Returns whether this point has a small order, dividing 8.
+	pub fn is_small_order(&self) -> bool {
+		self.mul_by_cofactor().is_identity()
+	}
+
+	/// Returns whether this point is in the prime-order subgroup generated by
+	/// the base point, with no small-order component.
+	pub fn is_torsion_free(&self) -> bool {
+		is_torsion_free(self.0)
+	}
+
+	/// Converts this point to the Montgomery curve with the birational map
+	/// u = (1 + y) / (1 - y). The identity maps to u = 0, like the point of
+	/// order 2.
+	pub fn to_montgomery(&self) -> MontgomeryPoint {
+		let mut u = (self.0.z + self.0.y) / (self.0.z - self.0.y);
+		u.full_modular_reduction();
+
+		MontgomeryPoint(u.to_bytes())
+	}
+}
+
+impl Add for EdwardsPoint {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self(point_add(self.0, other.0))
+	}
+}
+
+impl Sub for EdwardsPoint {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self(point_add(self.0, negate(other.0)))
+	}
+}
+
+impl Neg for EdwardsPoint {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Self(negate(self.0))
+	}
+}
+
+impl ConstantTimeEq for EdwardsPoint {
+	fn ct_eq(&self, other: &Self) -> Choice {
+		points_equal(self.0, other.0)
+	}
+}
+
+impl ConditionallySelectable for EdwardsPoint {
+	fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+		Self(Point::conditional_select(&a.0, &b.0, choice))
+	}
+}
+
+impl PartialEq for EdwardsPoint {
+	fn eq(&self, other: &Self) -> bool {
+		self.ct_eq(other).into()
+	}
+}
+
+impl Eq for EdwardsPoint {}
+
+impl fmt::Debug for EdwardsPoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("EdwardsPoint").field(&self.compress()).finish()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(EdwardsPoint, 32, Hex, |point| point.compress(), try |bytes| {
+	EdwardsPoint::decompress(bytes).ok_or("invalid point encoding")
+});
+
+/// A point on Curve25519, represented by its u-coordinate as in X25519. The
+/// u-coordinate is shared by a point and its negation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MontgomeryPoint([u8; 32]);
+
+impl MontgomeryPoint {
+	/// The base point used by X25519, u = 9.
+	pub const BASEPOINT: Self = Self([
+		0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	]);
+
+	/// Creates a point from the 32-byte encoding of its u-coordinate. Like
+	/// X25519, the top bit is ignored, and every value is accepted.
+	pub fn from_bytes(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	/// Returns the 32-byte encoding of the u-coordinate.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Returns a reference to the 32-byte encoding of the u-coordinate.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+
+	/// Multiplies this point by a scalar after clamping it, which is exactly
+	/// the X25519 function. Works in constant time.
+	#[cfg(feature = "x25519")]
+	pub fn mul_clamped(&self, scalar: [u8; 32]) -> Self {
+		Self(x25519_derive_secret(scalar, self.0))
+	}
+
+	/// Converts this point to the Edwards curve with the birational map
+	/// y = (u - 1) / (u + 1). The u-coordinate doesn't determine the sign of
+	/// the x-coordinate, so `x_sign` chooses it. Returns [`None`] if u = -1,
+	/// which has no Edwards equivalent, or if u isn't on Curve25519 itself.
+	pub fn to_edwards(&self, x_sign: bool) -> Option<EdwardsPoint> {
+		let u = Num::from_bytes(self.0);
+		let u_plus_one = u + Num::ONE;
+
+		if u_plus_one.ct_eq(&Num::ZERO).into() {
+			return None;
+		}
+
+		let mut y = (u - Num::ONE) / u_plus_one;
+		y.full_modular_reduction();
+
+		let mut compressed = y.to_bytes();
+
+		if x_sign {
+			compressed[31] |= 0x80;
+		}
+
+		EdwardsPoint::decompress(compressed)
+	}
+}
+
+impl From<EdwardsPoint> for MontgomeryPoint {
+	fn from(point: EdwardsPoint) -> Self {
+		point.to_montgomery()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(MontgomeryPoint, 32, Hex, |point| point.to_bytes(), |bytes| MontgomeryPoint::from_bytes(bytes));
+
+#[test]
+fn test_group_law() {
+	let base = EdwardsPoint::BASEPOINT;
+
+	assert_eq!(base + base, base.double());
+	assert_eq!(base + base + base - base, base.double());
+	assert_eq!(base + -base, EdwardsPoint::IDENTITY);
+	assert_eq!(base.mul(&[3; 32]) + base.mul(&[4; 32]), base.mul(&[7; 32]));
+
+	let scalar = [0xab; 32];
+	assert_eq!(EdwardsPoint::mul_base(&scalar), base.mul(&scalar));
+
+	// l, the order of the base point
+	let l = [
+		0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+		0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+	];
+	assert!(base.mul(&l).is_identity());
+	assert!(EdwardsPoint::mul_base(&l).is_identity());
+
+	let compressed = base.mul(&scalar).compress();
+	assert_eq!(EdwardsPoint::decompress(compressed).unwrap(), base.mul(&scalar));
+	assert!(EdwardsPoint::decompress([0xff; 32]).is_none());
+}
+
+#[test]
+fn test_small_order() {
+	// a point of order 8
+	let low_order = EdwardsPoint::decompress([
+		0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0,
+		0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98, 0xf0,
+		0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+		0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x05,
+	]).unwrap();
+
+	assert!(low_order.is_small_order());
+	assert!(!low_order.is_torsion_free());
+	assert!(!low_order.double().double().is_identity());
+
+	let mixed = EdwardsPoint::BASEPOINT + low_order;
+	assert!(!mixed.is_small_order());
+	assert!(!mixed.is_torsion_free());
+	assert!(mixed.mul_by_cofactor().is_torsion_free());
+	assert!(EdwardsPoint::BASEPOINT.is_torsion_free());
+}
+
+#[test]
+#[cfg(feature = "x25519")]
+fn test_montgomery_conversion() {
+	assert_eq!(EdwardsPoint::BASEPOINT.to_montgomery(), MontgomeryPoint::BASEPOINT);
+
+	let mut clamped = [0x5a; 32];
+	clamped[0] &= 0xf8;
+	clamped[31] &= 0x7f;
+	clamped[31] |= 0x40;
+
+	let edwards = EdwardsPoint::BASEPOINT.mul(&clamped);
+	let montgomery = MontgomeryPoint::BASEPOINT.mul_clamped([0x5a; 32]);
+	assert_eq!(MontgomeryPoint::from(edwards), montgomery);
+
+	let x_sign = edwards.compress()[31] & 0x80 != 0;
+	assert_eq!(montgomery.to_edwards(x_sign), Some(edwards));
+	assert_eq!(montgomery.to_edwards(!x_sign), Some(-edwards));
+
+	let mut minus_one = [0xff; 32];
+	minus_one[0] = 0xec;
+	minus_one[31] = 0x7f;
+	assert_eq!(MontgomeryPoint::from_bytes(minus_one).to_edwards(false), None);
+}
@@ -0,0 +1,250 @@
+//! Implemented according to ["Poseidon: A New Hash Function for Zero-Knowledge Proof Systems"](https://eprint.iacr.org/2019/458.pdf),
+//! over the field of [`Scalar`]s modulo the order of Edwards25519.
+//! Poseidon is a hash function built from field additions and
+//! multiplications only, which makes it cheap to prove statements about
+//! inside zero-knowledge proof systems over the same field, such as knowing
+//! the opening of a commitment or a path in a Merkle tree.
+//!
+//! The permutation has a width of 3 field elements, with the S-box `x^5`,
+//! 8 full rounds and 57 partial rounds, for 128-bit security. The round
+//! constants and MDS matrix are generated with the Grain LFSR, as in the
+//! reference script `generate_parameters_grain.sage`, though its extra checks
+//! of the matrix against infinitely long subspace trails aren't repeated here.
+//!
+//! Poseidon is much slower than SHA-2 or BLAKE3 in software, so it's only
+//! worth using when hashes have to be checked inside a proof.
+//!
+//! ```
+//! use libkrypton::poseidon::Poseidon;
+//! use libkrypton::scalar::Scalar;
+//!
+//! let poseidon = Poseidon::new();
+//!
+//! let left = Scalar::from_bytes_mod_order(&[1; 32]);
+//! let right = Scalar::from_bytes_mod_order(&[2; 32]);
+//! let parent = poseidon.hash2(left, right);
+//!
+//! assert_ne!(parent, poseidon.hash2(right, left));
+//! ```
+
+use super::scalar::Scalar;
+
+/// The number of field elements in the state.
+pub const WIDTH: usize = 3;
+
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 57;
+
+// the bit length of l
+const FIELD_BITS: u32 = 253;
+
+// the Grain LFSR used to generate the parameters, with the oldest bit in the
+// lowest bit of the state
+struct Grain(u128);
+
+impl Grain {
+	fn new() -> Self {
+		// the field type (prime), the S-box type (x^alpha), the field size,
+		// the width and the numbers of rounds, followed by 30 ones
+		let fields = [
+			(1, 2),
+			(0, 4),
+			(FIELD_BITS as u128, 12),
+			(WIDTH as u128, 12),
+			(FULL_ROUNDS as u128, 10),
+			(PARTIAL_ROUNDS as u128, 10),
+			((1 << 30) - 1, 30),
+		];
+
+		let mut state = 0;
+		let mut pos = 0;
+
+		for (value, len) in fields {
+			for i in (0 .. len).rev() {
+				state |= ((value >> i) & 1) << pos;
+				pos += 1;
+			}
+		}
+
+		let mut grain = Self(state);
+
+		for _ in 0 .. 160 {
+			grain.step();
+		}
+
+		grain
+	}
+
+	fn step(&mut self) -> u8 {
+		let s = self.0;
+		let bit = ((s >> 62) ^ (s >> 51) ^ (s >> 38) ^ (s >> 23) ^ (s >> 13) ^ s) & 1;
+		self.0 = (s >> 1) | (bit << 79);
+		bit as u8
+	}
+
+	// output bits are filtered in pairs: a 1 keeps the bit after it
+	fn next_bit(&mut self) -> u8 {
+		while self.step() == 0 {
+			self.step();
+		}
+
+		self.step()
+	}
+
+	// a FIELD_BITS-bit big-endian integer, as 32 little-endian bytes
+	fn next_bytes(&mut self) -> [u8; 32] {
+		let mut bytes = [0; 32];
+
+		for i in (0 .. FIELD_BITS as usize).rev() {
+			bytes[i / 8] |= self.next_bit() << (i % 8);
+		}
+
+		bytes
+	}
+}
+
+fn scalar_from_u128(x: u128) -> Scalar {
+	let mut bytes = [0; 32];
+	bytes[.. 16].copy_from_slice(&x.to_le_bytes());
+	Scalar::from_bytes_mod_order(&bytes)
+}
+
+fn sbox(x: Scalar) -> Scalar {
+	let x2 = x * x;
+	x2 * x2 * x
+}
+
+/// The Poseidon permutation and hash functions, holding the round constants
+/// and MDS matrix, which take a moment to generate and are best reused.
+#[derive(Clone)]
+pub struct Poseidon {
+	round_constants: [[Scalar; WIDTH]; FULL_ROUNDS + PARTIAL_ROUNDS],
+	mds: [[Scalar; WIDTH]; WIDTH],
+}
+
+impl Poseidon {
+	/// Generates the round constants and MDS matrix.
+	pub fn new() -> Self {
+		let mut grain = Grain::new();
+
+		// round constants are drawn by rejection sampling
+		let round_constants = [(); FULL_ROUNDS + PARTIAL_ROUNDS].map(|_| [(); WIDTH].map(|_| loop {
+			if let Some(constant) = Scalar::from_canonical_bytes(grain.next_bytes()) {
+				break constant;
+			}
+		}));
+
+		// the MDS matrix is a Cauchy matrix 1 / (x_i + y_j), with distinct
+		// x_i and y_j drawn without rejection
+		let mds = loop {
+			let values = [(); 2 * WIDTH].map(|_| Scalar::from_bytes_mod_order(&grain.next_bytes()));
+
+			let distinct = (0 .. 2 * WIDTH).all(|i| (0 .. i).all(|j| values[i] != values[j]));
+			let invertible = (0 .. WIDTH).all(|i| (WIDTH .. 2 * WIDTH).all(|j| values[i] + values[j] != Scalar::ZERO));
+
+			if distinct && invertible {
+				break [0, 1, 2].map(|i| [0, 1, 2].map(|j| (values[i] + values[WIDTH + j]).invert()));
+			}
+		};
+
+		Self {round_constants, mds}
+	}
+
+	/// Applies the Poseidon permutation to `state`.
+	pub fn permute(&self, state: &mut [Scalar; WIDTH]) {
+		for (round, constants) in self.round_constants.iter().enumerate() {
+			for i in 0 .. WIDTH {
+				state[i] = state[i] + constants[i];
+			}
+
+			// partial rounds only apply the S-box to the first element
+			let partial = (FULL_ROUNDS / 2 .. FULL_ROUNDS / 2 + PARTIAL_ROUNDS).contains(&round);
+
+			if partial {
+				state[0] = sbox(state[0]);
+			} else {
+				*state = state.map(sbox);
+			}
+
+			*state = self.mds.map(|row| row[0] * state[0] + row[1] * state[1] + row[2] * state[2]);
+		}
+	}
+
+	/// Hashes two field elements into one, for the nodes of a binary Merkle
+	/// tree. The capacity element is set to 3, as the paper suggests for
+	/// Merkle trees of arity 2.
+	pub fn hash2(&self, left: Scalar, right: Scalar) -> Scalar {
+		let mut state = [scalar_from_u128(3), left, right];
+		self.permute(&mut state);
+		state[1]
+	}
+
+	/// Hashes a fixed number of field elements into one, absorbing two at a
+	/// time. The capacity element is set to `inputs.len() * 2^64`, so inputs
+	/// of different lengths can't collide, but the length has to be fixed by
+	/// the application for the hash to be collision resistant, since zeros
+	/// are appended to fill the last block.
+	pub fn hash(&self, inputs: &[Scalar]) -> Scalar {
+		let mut state = [scalar_from_u128((inputs.len() as u128) << 64), Scalar::ZERO, Scalar::ZERO];
+
+		if inputs.is_empty() {
+			self.permute(&mut state);
+		}
+
+		for chunk in inputs.chunks(WIDTH - 1) {
+			for (i, &input) in chunk.iter().enumerate() {
+				state[i + 1] = state[i + 1] + input;
+			}
+
+			self.permute(&mut state);
+		}
+
+		state[1]
+	}
+}
+
+impl Default for Poseidon {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// parses a big-endian hex scalar, as the reference script prints them
+#[cfg(test)]
+fn scalar(text: &str) -> Scalar {
+	let mut bytes: [u8; 32] = hex(text);
+	bytes.reverse();
+
+	Scalar::from_canonical_bytes(bytes).unwrap()
+}
+
+// checked against a python port of the reference script, without its checks
+// for subspace trails
+#[test]
+fn test_parameters() {
+	let poseidon = Poseidon::new();
+
+	assert_eq!(poseidon.round_constants[0][0], scalar("02d3c9c8d37dfdbf16ea08e4a9b159c6df311947b1ae6ff864be4803d0f23e31"));
+	assert_eq!(poseidon.round_constants[64][2], scalar("09b5237749d9d799837093fe3eec8281876f23e3da4a57a42e2707dc87900c6a"));
+	assert_eq!(poseidon.mds[0][0], scalar("050dac3e8682789e14cedf4273812f7f3d26eba7f4f4d5df4934c62bd4693988"));
+	assert_eq!(poseidon.mds[2][1], scalar("0cc89aa10e49fe7b174dfafcca895898ddcb0495829f7d4228e6514c13da9f34"));
+}
+
+// checked against the permutation from the paper, in python with the parameters
+// from the port of the reference script
+#[test]
+fn test_permutation() {
+	let poseidon = Poseidon::new();
+
+	let mut state = [scalar_from_u128(0), scalar_from_u128(1), scalar_from_u128(2)];
+	poseidon.permute(&mut state);
+
+	assert_eq!(state, [scalar("0ed318a7cfb294b9c2417c29cd96795a6a31f601fd0679521e8a16d64a5632ee"), scalar("00bf6e779d63de1e3022deb18ab90ecb8f6a15cb1af48e5abe2c43725731f6ee"), scalar("01a17ef25aae0f28032cba69f9c01b32e4126ef53389fd44686bff2b1627c5a1")]);
+
+	assert_eq!(poseidon.hash2(scalar_from_u128(1), scalar_from_u128(2)), scalar("04fca3a5835523c36e6317c67a01d5cff3820697293317a1848438bfc85102ad"));
+	assert_eq!(poseidon.hash(&[scalar_from_u128(1), scalar_from_u128(2), scalar_from_u128(3)]), scalar("0fe1de15f30d6fd5fc2bf587990d9c65a6e0edf2193712b7d0f629f4ce6a6120"));
+	assert_ne!(poseidon.hash(&[]), poseidon.hash(&[Scalar::ZERO]));
+}
@@ -0,0 +1,390 @@
+//! Linkable ring signatures over Ed25519, using the LSAG construction from
+//! [Liu, Wei and Wong](https://eprint.iacr.org/2004/027).
+//! A ring signature shows that the message was signed by the private key of
+//! one of the public keys in a ring, without revealing which one.
+//!
+//! Every signature includes a key image, which depends only on the signer's
+//! private key. Two signatures with the same key image were made by the same
+//! signer, even if they use different rings, which makes it possible to
+//! detect a signer acting twice (for example, voting twice) without learning
+//! who they are.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha512;
+#[cfg(feature = "rand_core")]
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	mul_num_mod_l,
+	num_mod_l_from_32_bytes,
+	num_mod_l_from_64_bytes,
+	num_mod_l_to_bytes,
+};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	is_torsion_free,
+	mul_by_cofactor,
+	negate,
+	point_add,
+	points_equal,
+	scalar_mult,
+	verify_less_than_l,
+	Point,
+	SigningKey,
+	VerifyingKey,
+	NEUTRAL_POINT,
+};
+
+/// An error returned when creating or parsing ring signatures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The ring has no public keys in it.
+	EmptyRing,
+	/// The signing key's public key isn't part of the ring.
+	NotInRing,
+	/// A public key in the ring has a small-order component.
+	InvalidPublicKey,
+	/// The bytes passed in are not a valid encoding of a ring signature.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::EmptyRing => "empty ring",
+			Error::NotInRing => "signing key is not part of the ring",
+			Error::InvalidPublicKey => "invalid public key in ring",
+			Error::InvalidEncoding => "invalid ring signature encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// A linkable ring signature. Its size is proportional to the size of the
+/// ring it was created with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingSignature {
+	key_image: [u8; 32],
+	challenge: [u8; 32],
+	responses: Vec<[u8; 32]>,
+}
+
+impl RingSignature {
+	/// Parses a ring signature from the representation returned by
+	/// [`RingSignature::to_bytes`].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() < 96 || !bytes.len().is_multiple_of(32) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let chunks: Vec<[u8; 32]> = bytes.chunks(32)
+			.map(|chunk| chunk.try_into().unwrap())
+			.collect();
+
+		let key_image = chunks[0];
+
+		let image_point = decompress(key_image).ok_or(Error::InvalidEncoding)?;
+
+		// a key image outside of the prime-order subgroup could be used to
+		// produce several unlinkable signatures with the same key
+		if !is_torsion_free(image_point) || key_image == compress(NEUTRAL_POINT) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		if !chunks[1 ..].iter().all(verify_less_than_l) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(Self {
+			key_image,
+			challenge: chunks[1],
+			responses: chunks[2 ..].to_vec(),
+		})
+	}
+
+	/// Returns the representation of this signature: the key image, the
+	/// initial challenge, and then one response per member of the ring.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut out = Vec::with_capacity(64 + 32 * self.responses.len());
+		out.extend_from_slice(&self.key_image);
+		out.extend_from_slice(&self.challenge);
+
+		for response in &self.responses {
+			out.extend_from_slice(response);
+		}
+
+		out
+	}
+
+	/// Returns the key image, which is the same for every signature made with
+	/// the same private key.
+	pub fn key_image(&self) -> [u8; 32] {
+		self.key_image
+	}
+
+	/// Returns whether both signatures were made with the same private key.
+	pub fn is_linked(&self, other: &RingSignature) -> bool {
+		self.key_image == other.key_image
+	}
+}
+
+// hashes a public key to a point with no known discrete logarithm, using the
+// try-and-increment method
+fn hash_to_point(pub_key: &[u8; 32]) -> Point {
+	for counter in 0 ..= 255 {
+		let mut hasher = Sha512::new();
+		hasher.add_bytes(b"libkrypton/ring signature/hash to point\0");
+		hasher.add_bytes(pub_key);
+		hasher.add_bytes(&[counter]);
+
+		let digest = hasher.out();
+
+		if let Some(point) = decompress(digest[0 .. 32].try_into().unwrap()) {
+			let point = mul_by_cofactor(point);
+
+			if !bool::from(points_equal(point, NEUTRAL_POINT)) {
+				return point;
+			}
+		}
+	}
+
+	// each attempt succeeds with probability about 1/2
+	unreachable!("failed to hash to curve 256 times in a row")
+}
+
+// hashes everything that a signature commits to except for the per-member
+// values, which are added by `challenge`
+fn ring_hasher(ring: &[VerifyingKey], key_image: &[u8; 32], message: &[u8]) -> Sha512 {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(b"libkrypton/ring signature/challenge\0");
+	hasher.add_bytes(&(ring.len() as u64).to_le_bytes());
+
+	for key in ring {
+		hasher.add_bytes(key.as_bytes());
+	}
+
+	hasher.add_bytes(key_image);
+	hasher.add_bytes(message);
+	hasher
+}
+
+fn challenge(ring_hasher: &Sha512, l: Point, r: Point) -> [u64; 4] {
+	let mut hasher = ring_hasher.clone();
+	hasher.add_bytes(&compress(l));
+	hasher.add_bytes(&compress(r));
+	num_mod_l_from_64_bytes(&hasher.out())
+}
+
+// computes s * G - c * P and s * H(P) - c * I for one member of the ring
+fn member_commitments(
+	response: &[u8; 32],
+	challenge: [u64; 4],
+	pub_key: &VerifyingKey,
+	key_image: Point,
+) -> (Point, Point) {
+	let l = point_add(basepoint_mult(response), negate(scalar_mult(challenge, pub_key.point)));
+	let r = point_add(
+		scalar_mult(num_mod_l_from_32_bytes(response), hash_to_point(pub_key.as_bytes())),
+		negate(scalar_mult(challenge, key_image)),
+	);
+
+	(l, r)
+}
+
+fn check_ring(ring: &[VerifyingKey]) -> Result<(), Error> {
+	if ring.len() == 0 {
+		return Err(Error::EmptyRing);
+	}
+
+	if !ring.iter().all(|key| is_torsion_free(key.point)) {
+		return Err(Error::InvalidPublicKey);
+	}
+
+	Ok(())
+}
+
+/// Signs `message` on behalf of the `ring` of public keys, which must include
+/// the public key of `priv_key`. Verifiers need the same ring, in the same
+/// order. `randomness` should be freshly generated by a secure random source,
+/// but it's mixed with the private key as a safeguard against a weak one.
+pub fn ring_sign(
+	priv_key: &SigningKey,
+	ring: &[VerifyingKey],
+	message: &[u8],
+	randomness: [u8; 32],
+) -> Result<RingSignature, Error> {
+	check_ring(ring)?;
+
+	let pub_key = priv_key.verifying_key();
+	let signer_index = ring.iter().position(|key| *key == pub_key).ok_or(Error::NotInRing)?;
+
+	let x = num_mod_l_from_32_bytes(priv_key.secret_scalar());
+
+	let signer_hash_point = hash_to_point(pub_key.as_bytes());
+	let key_image_point = scalar_mult(x, signer_hash_point);
+	let key_image = compress(key_image_point);
+
+	let ring_hasher = ring_hasher(ring, &key_image, message);
+
+	// derives the nonce and the responses of the other members from the
+	// randomness, the private key, and everything being signed
+	let mut nonce_hasher = ring_hasher.clone();
+	nonce_hasher.add_bytes(&randomness);
+	nonce_hasher.add_bytes(priv_key.prefix());
+
+	let nonce_seed = nonce_hasher.out();
+
+	let derive_scalar = |index: usize| {
+		let mut hasher = Sha512::new();
+		hasher.add_bytes(&nonce_seed);
+		hasher.add_bytes(&(index as u64).to_le_bytes());
+		num_mod_l_from_64_bytes(&hasher.out())
+	};
+
+	let alpha = derive_scalar(signer_index);
+	let alpha_bytes = num_mod_l_to_bytes(alpha);
+
+	let mut challenges = vec![[0; 4]; ring.len()];
+	let mut responses = vec![[0; 32]; ring.len()];
+
+	let mut index = (signer_index + 1) % ring.len();
+	challenges[index] = challenge(
+		&ring_hasher,
+		basepoint_mult(&alpha_bytes),
+		scalar_mult(alpha, signer_hash_point),
+	);
+
+	while index != signer_index {
+		responses[index] = num_mod_l_to_bytes(derive_scalar(index));
+
+		let (l, r) = member_commitments(&responses[index], challenges[index], &ring[index], key_image_point);
+
+		let next = (index + 1) % ring.len();
+		challenges[next] = challenge(&ring_hasher, l, r);
+		index = next;
+	}
+
+	// closes the ring, so that s * G - c * P = alpha * G for the signer
+	let response = add_num_mod_l(alpha, mul_num_mod_l(challenges[signer_index], x));
+	responses[signer_index] = num_mod_l_to_bytes(response);
+
+	Ok(RingSignature {
+		key_image,
+		challenge: num_mod_l_to_bytes(challenges[0]),
+		responses,
+	})
+}
+
+/// Like [`ring_sign`], but generates the randomness with `rng`, which must be
+/// a cryptographically secure random number generator.
+#[cfg(feature = "rand_core")]
+pub fn ring_sign_with_rng<R: RngCore + CryptoRng>(
+	priv_key: &SigningKey,
+	ring: &[VerifyingKey],
+	message: &[u8],
+	rng: &mut R,
+) -> Result<RingSignature, Error> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = ring_sign(priv_key, ring, message, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Verifies that `signature` was made over `message` by the private key of
+/// one of the public keys in `ring`.
+/// Not constant-time, since it doesn't involve secret data.
+pub fn ring_verify(ring: &[VerifyingKey], message: &[u8], signature: &RingSignature) -> bool {
+	if check_ring(ring).is_err() || signature.responses.len() != ring.len() {
+		return false;
+	}
+
+	// the key image was checked when the signature was constructed
+	let key_image_point = decompress(signature.key_image).unwrap();
+	let ring_hasher = ring_hasher(ring, &signature.key_image, message);
+
+	let initial_challenge = num_mod_l_from_32_bytes(&signature.challenge);
+	let mut current_challenge = initial_challenge;
+
+	for i in 0 .. ring.len() {
+		let (l, r) = member_commitments(&signature.responses[i], current_challenge, &ring[i], key_image_point);
+		current_challenge = challenge(&ring_hasher, l, r);
+	}
+
+	current_challenge == initial_challenge
+}
+
+#[test]
+fn test_sign_and_verify() {
+	let priv_keys: Vec<SigningKey> = (1 ..= 4).map(|i| SigningKey::from_bytes([i; 32])).collect();
+	let ring: Vec<VerifyingKey> = priv_keys.iter().map(|key| key.verifying_key()).collect();
+
+	for (i, priv_key) in priv_keys.iter().enumerate() {
+		let signature = ring_sign(priv_key, &ring, b"message", [i as u8; 32]).unwrap();
+		let parsed = RingSignature::from_bytes(&signature.to_bytes()).unwrap();
+
+		assert_eq!(parsed, signature);
+		assert!(ring_verify(&ring, b"message", &signature));
+		assert!(!ring_verify(&ring, b"massage", &signature));
+		assert!(!ring_verify(&ring[.. 3], b"message", &signature));
+
+		let mut reordered = ring.clone();
+		reordered.swap(0, 1);
+		assert!(!ring_verify(&reordered, b"message", &signature));
+	}
+
+	// a ring of one is an ordinary signature, with a key image
+	let signature = ring_sign(&priv_keys[0], &ring[.. 1], b"message", [0; 32]).unwrap();
+	assert!(ring_verify(&ring[.. 1], b"message", &signature));
+}
+
+#[test]
+fn test_linkability() {
+	let priv_keys: Vec<SigningKey> = (1 ..= 3).map(|i| SigningKey::from_bytes([i; 32])).collect();
+	let ring: Vec<VerifyingKey> = priv_keys.iter().map(|key| key.verifying_key()).collect();
+
+	let first = ring_sign(&priv_keys[1], &ring, b"vote for a", [1; 32]).unwrap();
+	let second = ring_sign(&priv_keys[1], &ring[1 ..], b"vote for b", [2; 32]).unwrap();
+	let other = ring_sign(&priv_keys[2], &ring, b"vote for b", [3; 32]).unwrap();
+
+	assert!(first.is_linked(&second));
+	assert!(!first.is_linked(&other));
+
+	let outsider = SigningKey::from_bytes([9; 32]);
+	assert_eq!(ring_sign(&outsider, &ring, b"message", [0; 32]), Err(Error::NotInRing));
+	assert_eq!(ring_sign(&outsider, &[], b"message", [0; 32]), Err(Error::EmptyRing));
+}
+
+#[test]
+fn test_rejects_invalid_key_images() {
+	let priv_key = SigningKey::from_bytes([1; 32]);
+	let ring = [priv_key.verifying_key()];
+	let mut bytes = ring_sign(&priv_key, &ring, b"message", [0; 32]).unwrap().to_bytes();
+
+	// the identity point
+	bytes[0 .. 32].copy_from_slice(&compress(NEUTRAL_POINT));
+	assert_eq!(RingSignature::from_bytes(&bytes), Err(Error::InvalidEncoding));
+
+	// a point of order 2
+	bytes[0] = 0xec;
+	bytes[1 .. 31].fill(0xff);
+	bytes[31] = 0x7f;
+	assert_eq!(RingSignature::from_bytes(&bytes), Err(Error::InvalidEncoding));
+
+	assert_eq!(RingSignature::from_bytes(&bytes[.. 64]), Err(Error::InvalidEncoding));
+}
@@ -0,0 +1,329 @@
+//! Implemented according to [IETF RFC 9496](https://datatracker.ietf.org/doc/html/rfc9496).
+//! ristretto255 is a prime-order group built on top of Edwards25519: each
+//! group element is a class of curve points that differ by a small-order
+//! point, with a single canonical encoding. Protocols built on it don't have
+//! to deal with the cofactor of 8, so ristretto255 is often what protocol
+//! specifications such as OPRFs and OPAQUE ask for.
+//!
+//! Decompression and comparisons don't work in constant time, since they're
+//! meant to be used on public elements, while scalar multiplication and
+//! [`RistrettoPoint::from_uniform_bytes`] do.
+
+use core::fmt;
+use core::ops::{Add, Neg, Sub};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::ed25519::{
+	basepoint_mult,
+	ed25519_mult,
+	negate,
+	point_add,
+	Point,
+	BASE_POINT,
+	ED25519_D,
+	NEUTRAL_POINT,
+};
+use super::num::Num;
+use super::scalar::Scalar;
+
+const SQRT_AD_MINUS_ONE: Num = Num {
+	segments: [
+		0x7f6a0497b2e1b,
+		0x1836f0a97afd2,
+		0x7d747f6be7638,
+		0x456079e7e6498,
+		0x376931bf2b834,
+	],
+};
+
+const INVSQRT_A_MINUS_D: Num = Num {
+	segments: [
+		0x0fdaa805d40ea,
+		0x2eb482e57d339,
+		0x007610274bc58,
+		0x6510b613dc8ff,
+		0x786c8905cfaff,
+	],
+};
+
+const ONE_MINUS_D_SQ: Num = Num {
+	segments: [
+		0x409c1945fc176,
+		0x719abc6a1fc4f,
+		0x1c37f90b20684,
+		0x06bccca55eedf,
+		0x029072a8b2b3e,
+	],
+};
+
+const D_MINUS_ONE_SQ: Num = Num {
+	segments: [
+		0x55aaa44ed4d20,
+		0x59603c3332635,
+		0x26d3baf4a7928,
+		0x120a66e6997a9,
+		0x5968b37af66c2,
+	],
+};
+
+fn abs(num: Num) -> Num {
+	Num::conditional_select(&num, &-num, num.is_negative())
+}
+
+// the one-way map from a field element to a point, MAP in the RFC
+fn map(t: Num) -> Point {
+	let one = Num::ONE;
+
+	let r = Num::SQRT_MINUS_1 * t.square();
+	let u = (r + one) * ONE_MINUS_D_SQ;
+	let v = (-one - r * ED25519_D) * (r + ED25519_D);
+
+	let (was_square, s) = Num::sqrt_ratio(&u, &v);
+	let s_prime = -abs(s * t);
+	let s = Num::conditional_select(&s_prime, &s, was_square);
+	let c = Num::conditional_select(&r, &-one, was_square);
+
+	let n = c * (r - one) * D_MINUS_ONE_SQ - v;
+
+	let w0 = (s + s) * v;
+	let w1 = n * SQRT_AD_MINUS_ONE;
+	let w2 = one - s.square();
+	let w3 = one + s.square();
+
+	Point {
+		x: w0 * w3,
+		y: w2 * w1,
+		z: w1 * w3,
+		t: w0 * w2,
+	}
+}
+
+/// An element of the ristretto255 group.
+#[derive(Copy, Clone)]
+pub struct RistrettoPoint(Point);
+
+impl RistrettoPoint {
+	/// The identity element.
+	pub const IDENTITY: Self = Self(NEUTRAL_POINT);
+
+	/// The generator, which is the class of the Ed25519 base point.
+	pub const BASEPOINT: Self = Self(BASE_POINT);
+
+	/// Decodes an element from its 32-byte encoding, returning [`None`] if
+	/// it isn't the canonical encoding of an element.
+	pub fn decompress(bytes: [u8; 32]) -> Option<Self> {
+		let mut s = Num::from_bytes(bytes);
+		s.full_modular_reduction();
+
+		// the encoding must be a reduced, nonnegative field element
+		if s.to_bytes() != bytes || bool::from(s.is_negative()) {
+			return None;
+		}
+
+		let one = Num::ONE;
+		let ss = s.square();
+		let u1 = one - ss;
+		let u2 = one + ss;
+		let u2_sqr = u2.square();
+
+		let v = -(ED25519_D * u1.square()) - u2_sqr;
+		let (was_square, invsqrt) = Num::sqrt_ratio(&one, &(v * u2_sqr));
+
+		let den_x = invsqrt * u2;
+		let den_y = invsqrt * den_x * v;
+
+		let x = abs((s + s) * den_x);
+		let y = u1 * den_y;
+		let t = x * y;
+
+		if !bool::from(was_square) || bool::from(t.is_negative() | y.ct_eq(&Num::ZERO)) {
+			return None;
+		}
+
+		Some(Self(Point {x, y, z: one, t}))
+	}
+
+	/// Encodes this element as its canonical 32 bytes.
+	pub fn compress(&self) -> [u8; 32] {
+		let Point {x: x0, y: y0, z: z0, t: t0} = self.0;
+
+		let u1 = (z0 + y0) * (z0 - y0);
+		let u2 = x0 * y0;
+
+		let (_, invsqrt) = Num::sqrt_ratio(&Num::ONE, &(u1 * u2.square()));
+
+		let den1 = invsqrt * u1;
+		let den2 = invsqrt * u2;
+		let z_inv = den1 * den2 * t0;
+
+		let ix0 = x0 * Num::SQRT_MINUS_1;
+		let iy0 = y0 * Num::SQRT_MINUS_1;
+		let enchanted_denominator = den1 * INVSQRT_A_MINUS_D;
+
+		let rotate = (t0 * z_inv).is_negative();
+
+		let x = Num::conditional_select(&x0, &iy0, rotate);
+		let y = Num::conditional_select(&y0, &ix0, rotate);
+		let den_inv = Num::conditional_select(&den2, &enchanted_denominator, rotate);
+
+		let y = Num::conditional_select(&y, &-y, (x * z_inv).is_negative());
+
+		let mut s = abs(den_inv * (z0 - y));
+		s.full_modular_reduction();
+
+		s.to_bytes()
+	}
+
+	/// Maps 64 uniformly random bytes, such as a hash, to an element, with a
+	/// distribution indistinguishable from uniform. No discrete logarithm
+	/// relative to other elements is known for the result. This is the
+	/// element derivation function of the RFC, and works in constant time.
+	pub fn from_uniform_bytes(bytes: &[u8; 64]) -> Self {
+		let mut half = [0; 32];
+
+		half.copy_from_slice(&bytes[.. 32]);
+		let point_a = map(Num::from_bytes(half));
+
+		half.copy_from_slice(&bytes[32 ..]);
+		let point_b = map(Num::from_bytes(half));
+
+		Self(point_add(point_a, point_b))
+	}
+
+	/// Multiplies this element by a scalar. Works in constant time.
+	pub fn mul(&self, scalar: &Scalar) -> Self {
+		Self(ed25519_mult(&scalar.to_bytes(), 256, self.0))
+	}
+
+	/// Multiplies the generator by a scalar, which is faster than
+	/// [`RistrettoPoint::mul`] on [`RistrettoPoint::BASEPOINT`]. Works in
+	/// constant time.
+	pub fn mul_base(scalar: &Scalar) -> Self {
+		Self(basepoint_mult(&scalar.to_bytes()))
+	}
+
+	/// Returns whether this is the identity.
+	pub fn is_identity(&self) -> bool {
+		*self == Self::IDENTITY
+	}
+}
+
+impl Add for RistrettoPoint {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self(point_add(self.0, other.0))
+	}
+}
+
+impl Sub for RistrettoPoint {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self(point_add(self.0, negate(other.0)))
+	}
+}
+
+impl Neg for RistrettoPoint {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Self(negate(self.0))
+	}
+}
+
+impl ConstantTimeEq for RistrettoPoint {
+	// points in the same class are equal, which the RFC checks without
+	// encoding them
+	fn ct_eq(&self, other: &Self) -> Choice {
+		let (a, b) = (self.0, other.0);
+		(a.x * b.y).ct_eq(&(a.y * b.x)) | (a.y * b.y).ct_eq(&(a.x * b.x))
+	}
+}
+
+impl ConditionallySelectable for RistrettoPoint {
+	fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+		Self(Point::conditional_select(&a.0, &b.0, choice))
+	}
+}
+
+impl PartialEq for RistrettoPoint {
+	fn eq(&self, other: &Self) -> bool {
+		self.ct_eq(other).into()
+	}
+}
+
+impl Eq for RistrettoPoint {}
+
+impl fmt::Debug for RistrettoPoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "RistrettoPoint(")?;
+
+		for byte in self.compress() {
+			write!(f, "{:02x}", byte)?;
+		}
+
+		write!(f, ")")
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(RistrettoPoint, 32, Hex, |point| point.compress(), try |bytes| {
+	RistrettoPoint::decompress(bytes).ok_or("invalid point encoding")
+});
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix A.1 of the RFC
+#[test]
+fn test_multiples_of_generator() {
+	let expected = [
+		"0000000000000000000000000000000000000000000000000000000000000000",
+		"e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76",
+		"6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919",
+		"94741f5d5d52755ece4f23f044ee27d5d1ea1e2bd196b462166b16152a9d0259",
+		"da80862773358b466ffadfe0b3293ab3d9fd53c5ea6c955358f568322daf6a57",
+	];
+
+	let mut point = RistrettoPoint::IDENTITY;
+
+	for text in expected {
+		assert_eq!(point.compress(), hex(text));
+		assert_eq!(RistrettoPoint::decompress(hex(text)), Some(point));
+		point = point + RistrettoPoint::BASEPOINT;
+	}
+
+	let mut scalar = [0; 32];
+	scalar[0] = 4;
+	assert_eq!(RistrettoPoint::mul_base(&Scalar::from_bytes_mod_order(&scalar)).compress(), hex(expected[4]));
+}
+
+// checked against libsodium's crypto_core_ristretto255_is_valid_point
+#[test]
+fn test_invalid_encodings() {
+	let invalid = [
+		// non-canonical field encodings
+		"00ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+		"ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+		// negative field elements
+		"0100000000000000000000000000000000000000000000000000000000000000",
+		"01ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+		// non-square x^2
+		"26948d35ca62e643e26a83177332e6b6afeb9d08e4268b650f1f5bbd8d81d371",
+		"8debe1c8b6a3a5e1e3fbe84e2a17db2b9b5f2e1f88c1d4e4e7d20d6b81bff43d",
+		"0200000000000000000000000000000000000000000000000000000000000000",
+	];
+
+	for text in invalid {
+		assert_eq!(RistrettoPoint::decompress(hex(text)), None);
+	}
+}
+
+// checked against libsodium's crypto_core_ristretto255_from_hash
+#[test]
+fn test_from_uniform_bytes() {
+	let bytes: [u8; 64] = hex("226220f8e01208249cd1d23d21e8f14e185e8dfa0c92ec0177d9ea737ad38cc42a6590d9f82b27c1993eeac0100f24dc957018a8cb10605f7fcaa0f2ae0e3f5e");
+	assert_eq!(RistrettoPoint::from_uniform_bytes(&bytes).compress(), hex("ec10870780595c6ddc4481f34eb6f11da64ad3b6ac96abb2188a720dbb5fda6a"));
+}
@@ -0,0 +1,246 @@
+//! Scalars modulo l, the order of the prime-order subgroup of Edwards25519,
+//! for use with the point types in [`point`](crate::point).
+//!
+//! All arithmetic on scalars works in constant time.
+
+use core::fmt;
+use core::ops::{Add, Mul, Neg, Sub};
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+#[cfg(feature = "rand_core")]
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	invert_num_mod_l,
+	mul_num_mod_l,
+	neg_num_mod_l,
+	num_mod_l_from_32_bytes,
+	num_mod_l_from_64_bytes,
+	num_mod_l_to_bytes,
+};
+use super::ed25519::verify_less_than_l;
+
+/// An integer modulo l = 2^252 + 27742317777372353535851937790883648493.
+#[derive(Copy, Clone)]
+pub struct Scalar([u64; 4]);
+
+impl Scalar {
+	/// The scalar 0.
+	pub const ZERO: Self = Self([0, 0, 0, 0]);
+
+	/// The scalar 1.
+	pub const ONE: Self = Self([1, 0, 0, 0]);
+
+	/// Reduces a 32-byte little-endian integer modulo l.
+	pub fn from_bytes_mod_order(bytes: &[u8; 32]) -> Self {
+		Self(num_mod_l_from_32_bytes(bytes))
+	}
+
+	/// Reduces a 64-byte little-endian integer modulo l. Reducing 64 uniformly
+	/// random bytes, such as a SHA-512 hash, gives a scalar with negligible
+	/// bias.
+	pub fn from_bytes_mod_order_wide(bytes: &[u8; 64]) -> Self {
+		Self(num_mod_l_from_64_bytes(bytes))
+	}
+
+	/// Generates a uniformly random scalar, by reducing 64 random bytes, so
+	/// the bias is negligible.
+	#[cfg(feature = "rand_core")]
+	pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut bytes = [0; 64];
+		rng.fill_bytes(&mut bytes);
+
+		let scalar = Self::from_bytes_mod_order_wide(&bytes);
+		zeroize(&mut bytes);
+
+		scalar
+	}
+
+	/// Parses a 32-byte little-endian integer, returning [`None`] if it isn't
+	/// less than l. Unlike the arithmetic, this doesn't work in constant time.
+	pub fn from_canonical_bytes(bytes: [u8; 32]) -> Option<Self> {
+		if verify_less_than_l(&bytes) {
+			Some(Self::from_bytes_mod_order(&bytes))
+		} else {
+			None
+		}
+	}
+
+	/// Returns the canonical 32-byte little-endian encoding of this scalar.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		num_mod_l_to_bytes(self.0)
+	}
+
+	/// Returns the multiplicative inverse of this scalar, or 0 if this scalar
+	/// is 0.
+	pub fn invert(&self) -> Self {
+		Self(invert_num_mod_l(self.0))
+	}
+
+	/// Inverts every scalar in place, like [`Scalar::invert`], but with a
+	/// single inversion shared by every 32 scalars, which is much faster.
+	pub fn batch_invert(scalars: &mut [Scalar]) {
+		for chunk in scalars.chunks_mut(BATCH_SIZE) {
+			let mut prefix_products = [Self::ZERO; BATCH_SIZE];
+			let mut acc = Self::ONE;
+
+			// zeros are treated as ones so they don't wipe out the whole product
+			for i in 0 .. chunk.len() {
+				prefix_products[i] = acc;
+				acc = acc * chunk[i].one_if_zero();
+			}
+
+			let mut inverse = acc.invert();
+
+			for i in (0 .. chunk.len()).rev() {
+				let is_zero = chunk[i].ct_eq(&Self::ZERO);
+				let scalar = chunk[i].one_if_zero();
+				chunk[i] = Self::conditional_select(&(inverse * prefix_products[i]), &Self::ZERO, is_zero);
+				inverse = inverse * scalar;
+			}
+		}
+	}
+
+	fn one_if_zero(&self) -> Self {
+		Self::conditional_select(self, &Self::ONE, self.ct_eq(&Self::ZERO))
+	}
+}
+
+impl ConstantTimeEq for Scalar {
+	fn ct_eq(&self, other: &Self) -> Choice {
+		self.0.ct_eq(&other.0)
+	}
+}
+
+impl ConditionallySelectable for Scalar {
+	fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+		Self(<[u64; 4]>::conditional_select(&a.0, &b.0, choice))
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Scalar {
+	fn zeroize(&mut self) {
+		crate::zeroize::zeroize(&mut self.0);
+	}
+}
+
+const BATCH_SIZE: usize = 32;
+
+impl Add for Scalar {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self(add_num_mod_l(self.0, other.0))
+	}
+}
+
+impl Sub for Scalar {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self(add_num_mod_l(self.0, neg_num_mod_l(other.0)))
+	}
+}
+
+impl Mul for Scalar {
+	type Output = Self;
+
+	fn mul(self, other: Self) -> Self {
+		Self(mul_num_mod_l(self.0, other.0))
+	}
+}
+
+impl Neg for Scalar {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Self(neg_num_mod_l(self.0))
+	}
+}
+
+impl PartialEq for Scalar {
+	/// Compares two scalars in constant time.
+	fn eq(&self, other: &Self) -> bool {
+		self.ct_eq(other).into()
+	}
+}
+
+impl Eq for Scalar {}
+
+// deliberately doesn't print the scalar, which is often secret
+impl fmt::Debug for Scalar {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Scalar").finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Scalar, 32, Hex, |scalar| scalar.to_bytes(), try |bytes| {
+	Scalar::from_canonical_bytes(bytes).ok_or("non-canonical scalar")
+});
+
+#[test]
+fn test_arithmetic() {
+	let a = Scalar::from_bytes_mod_order_wide(&[0x5a; 64]);
+	let b = Scalar::from_bytes_mod_order(&[0xa7; 32]);
+
+	assert_eq!(a + b - b, a);
+	assert_eq!(a - a, Scalar::ZERO);
+	assert_eq!(-Scalar::ZERO, Scalar::ZERO);
+	assert_eq!(a + -a, Scalar::ZERO);
+	assert_eq!((a + b) * b, a * b + b * b);
+
+	assert_eq!(a * a.invert(), Scalar::ONE);
+	assert_eq!(a.invert().invert(), a);
+	assert_eq!(Scalar::ZERO.invert(), Scalar::ZERO);
+
+	// 1 / 2 mod l, checked against python's pow(2, -1, l)
+	let half = Scalar::from_canonical_bytes([
+		0xf7, 0xe9, 0x7a, 0x2e, 0x8d, 0x31, 0x09, 0x2c,
+		0x6b, 0xce, 0x7b, 0x51, 0xef, 0x7c, 0x6f, 0x0a,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+	]).unwrap();
+	assert_eq!((Scalar::ONE + Scalar::ONE).invert(), half);
+
+	assert!(Scalar::from_canonical_bytes([0xff; 32]).is_none());
+}
+
+#[test]
+fn test_batch_invert() {
+	let mut scalars = [Scalar::ZERO; 70];
+
+	for i in 0 .. 70 {
+		// every tenth scalar is left as zero
+		if i % 10 != 0 {
+			scalars[i] = Scalar::from_bytes_mod_order_wide(&[i as u8; 64]);
+		}
+	}
+
+	let mut inverted = scalars;
+	Scalar::batch_invert(&mut inverted);
+
+	for i in 0 .. 70 {
+		assert_eq!(inverted[i], scalars[i].invert());
+	}
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn test_random() {
+	use crate::test_util::CountingRng;
+
+	let mut rng = CountingRng(0);
+	let first = Scalar::random(&mut rng);
+	let second = Scalar::random(&mut rng);
+
+	let expected_bytes: [u8; 64] = core::array::from_fn(|i| i as u8);
+
+	assert_eq!(first, Scalar::from_bytes_mod_order_wide(&expected_bytes));
+	assert_ne!(first, second);
+}
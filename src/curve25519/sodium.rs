@@ -0,0 +1,109 @@
+//! Compatibility with the key layout and combined signing mode of libsodium's
+//! `crypto_sign` API, for interop with NaCl-style libraries.
+//!
+//! libsodium stores an Ed25519 secret key as the 32-byte seed followed by the
+//! 32-byte public key, and its combined mode prepends the 64-byte signature
+//! to the message it signs.
+
+use core::convert::TryInto;
+
+use super::ed25519::{Error, Signature, SigningKey, VerifyingKey};
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+impl SigningKey {
+	/// Creates a signing key from the 64-byte libsodium secret key layout,
+	/// the seed followed by the public key. Returns
+	/// [`Error::InvalidPublicKey`] if the public key doesn't match the seed.
+	pub fn from_keypair_bytes(bytes: &[u8; 64]) -> Result<Self, Error> {
+		let priv_key = Self::from_bytes(bytes[0 .. 32].try_into().unwrap());
+
+		if priv_key.verifying_key().as_bytes()[..] != bytes[32 .. 64] {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		Ok(priv_key)
+	}
+
+	/// Returns the 64-byte libsodium secret key layout of this signing key,
+	/// the seed followed by the public key. This must be kept secret.
+	pub fn to_keypair_bytes(&self) -> [u8; 64] {
+		let mut out = [0; 64];
+		out[0 .. 32].copy_from_slice(&self.to_bytes());
+		out[32 .. 64].copy_from_slice(self.verifying_key().as_bytes());
+		out
+	}
+
+	/// Signs a `message` using Ed25519, returning the signature followed by
+	/// the message, like libsodium's `crypto_sign`.
+	#[cfg(feature = "std")]
+	pub fn sign_combined(&self, message: &[u8]) -> Vec<u8> {
+		let mut out = Vec::with_capacity(64 + message.len());
+		out.extend_from_slice(self.sign(message).as_bytes());
+		out.extend_from_slice(message);
+		out
+	}
+}
+
+impl VerifyingKey {
+	/// Verifies a signature followed by a message, like libsodium's
+	/// `crypto_sign_open`, returning the message if the signature is valid.
+	pub fn open_combined<'a>(&self, signed_message: &'a [u8]) -> Result<&'a [u8], Error> {
+		if signed_message.len() < 64 {
+			return Err(Error::InvalidLength);
+		}
+
+		let (signature, message) = signed_message.split_at(64);
+		let signature = Signature::from_bytes(signature.try_into().unwrap())?;
+
+		if !self.verify(message, &signature) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(message)
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// test 2 from section 7.1 of RFC 8032
+#[cfg(test)]
+const TEST_KEYPAIR: &str = "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb\
+	3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+
+#[cfg(test)]
+const TEST_SIGNED_MESSAGE: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+	085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00\
+	72";
+
+#[test]
+fn test_keypair_bytes() {
+	let keypair: [u8; 64] = hex(TEST_KEYPAIR);
+
+	let priv_key = SigningKey::from_keypair_bytes(&keypair).unwrap();
+	assert_eq!(priv_key.to_bytes()[..], keypair[0 .. 32]);
+	assert_eq!(priv_key.to_keypair_bytes(), keypair);
+
+	let mut mismatched = keypair;
+	mismatched[63] ^= 1;
+	assert_eq!(SigningKey::from_keypair_bytes(&mismatched).unwrap_err(), Error::InvalidPublicKey);
+}
+
+#[test]
+fn test_open_combined() {
+	let priv_key = SigningKey::from_keypair_bytes(&hex(TEST_KEYPAIR)).unwrap();
+	let pub_key = priv_key.verifying_key();
+	let signed_message: [u8; 65] = hex(TEST_SIGNED_MESSAGE);
+
+	#[cfg(feature = "std")]
+	assert_eq!(priv_key.sign_combined(&[0x72]), signed_message);
+
+	assert_eq!(pub_key.open_combined(&signed_message), Ok(&[0x72][..]));
+	assert_eq!(pub_key.open_combined(&signed_message[0 .. 63]), Err(Error::InvalidLength));
+
+	let mut forged = signed_message;
+	forged[64] ^= 1;
+	assert_eq!(pub_key.open_combined(&forged), Err(Error::InvalidSignature));
+}
@@ -0,0 +1,873 @@
+//! SPAKE2 and SPAKE2+ password-authenticated key exchange over the
+//! Edwards25519 curve, implemented according to
+//! [IETF RFC 9382](https://datatracker.ietf.org/doc/html/rfc9382) and
+//! [IETF RFC 9383](https://datatracker.ietf.org/doc/html/rfc9383) with the
+//! SHA-256, HKDF-SHA256 and HMAC-SHA256 ciphersuite. Two parties who share a
+//! password establish a strong shared key, without anyone observing or
+//! tampering with the exchange learning enough to guess the password offline.
+//!
+//! SPAKE2 is symmetric: [`Spake2`] is used by both parties, who each know the
+//! password. In SPAKE2+, only the prover ([`Spake2PlusProver`]) knows the
+//! password, while the verifier ([`Spake2PlusVerifier`]) stores a
+//! [`Spake2PlusRecord`] derived from it, which can't be used to impersonate
+//! the prover if it's stolen.
+//!
+//! Passwords should be stretched with a memory-hard password hash before
+//! being turned into a [`Password`] or [`Spake2PlusPassword`], since anyone
+//! who learns the derived scalars can test password guesses offline.
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::ConstantTimeEq;
+use crate::hkdf::{hkdf_sha256, hkdf_sha256_extract};
+use crate::hmac::{hmac_sha256, HmacSha256};
+use crate::sha2::{Sha256, Sha512};
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{num_mod_l_from_32_bytes, num_mod_l_from_64_bytes, num_mod_l_to_bytes};
+use super::ed25519::{
+	basepoint_mult,
+	compress,
+	decompress,
+	is_torsion_free,
+	mul_by_cofactor,
+	negate,
+	point_add,
+	scalar_mult,
+	verify_less_than_l,
+	Point,
+};
+
+// the points M and N from section 6 of RFC 9382
+const M: [u8; 32] = [
+	0xd0, 0x48, 0x03, 0x2c, 0x6e, 0xa0, 0xb6, 0xd6,
+	0x97, 0xdd, 0xc2, 0xe8, 0x6b, 0xda, 0x85, 0xa3,
+	0x3a, 0xda, 0xc9, 0x20, 0xf1, 0xbf, 0x18, 0xe1,
+	0xb0, 0xc6, 0xd1, 0x66, 0xa5, 0xce, 0xcd, 0xaf,
+];
+
+const N: [u8; 32] = [
+	0xd3, 0xbf, 0xb5, 0x18, 0xf4, 0x4f, 0x34, 0x30,
+	0xf2, 0x9d, 0x0c, 0x92, 0xaf, 0x50, 0x38, 0x65,
+	0xa1, 0xed, 0x32, 0x81, 0xdc, 0x69, 0xb3, 0x5d,
+	0xd8, 0x68, 0xba, 0x85, 0xf8, 0x86, 0xc4, 0xab,
+];
+
+const IDENTITY: [u8; 32] = [
+	0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// An error returned by the SPAKE2 and SPAKE2+ protocol functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The other party's message is not a valid point, or cancels out to the
+	/// identity.
+	InvalidMessage,
+	/// The other party's confirmation is wrong, meaning it used a different
+	/// password or the exchange was tampered with.
+	InvalidConfirmation,
+	/// The bytes passed in are not a valid encoding of a record.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidMessage => "invalid spake2 message",
+			Error::InvalidConfirmation => "invalid spake2 key confirmation",
+			Error::InvalidEncoding => "invalid spake2+ record encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+fn random_scalar(randomness: &[u8; 32]) -> [u8; 32] {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(b"libkrypton/spake2/scalar\0");
+	hasher.add_bytes(randomness);
+
+	num_mod_l_to_bytes(num_mod_l_from_64_bytes(&hasher.out()))
+}
+
+// reduces up to 64 bytes of password hash output mod l
+fn reduce_wide(bytes: &[u8]) -> [u8; 32] {
+	let mut wide = [0; 64];
+	wide[0 .. bytes.len()].copy_from_slice(bytes);
+
+	let out = num_mod_l_to_bytes(num_mod_l_from_64_bytes(&wide));
+	zeroize(&mut wide);
+
+	out
+}
+
+fn scalar(bytes: &[u8; 32]) -> [u64; 4] {
+	num_mod_l_from_32_bytes(bytes)
+}
+
+// computes x * G + w * blinding
+fn blinded_share(x: &[u8; 32], w: &[u8; 32], blinding: [u8; 32]) -> [u8; 32] {
+	let blinding = decompress(blinding).unwrap();
+	compress(point_add(basepoint_mult(x), scalar_mult(scalar(w), blinding)))
+}
+
+// removes w * blinding from the other party's share
+fn unblind_share(share: [u8; 32], w: &[u8; 32], blinding: [u8; 32]) -> Result<Point, Error> {
+	let share = decompress(share).ok_or(Error::InvalidMessage)?;
+	let blinding = decompress(blinding).unwrap();
+
+	Ok(point_add(share, negate(scalar_mult(scalar(w), blinding))))
+}
+
+// computes h * scalar * point, rejecting the identity
+fn cofactor_mult(scalar_bytes: &[u8; 32], point: Point) -> Result<[u8; 32], Error> {
+	let out = compress(mul_by_cofactor(scalar_mult(scalar(scalar_bytes), point)));
+
+	if out == IDENTITY {
+		return Err(Error::InvalidMessage);
+	}
+
+	Ok(out)
+}
+
+fn add_prefixed(hasher: &mut Sha256, bytes: &[u8]) {
+	hasher.add_bytes(&(bytes.len() as u64).to_le_bytes());
+	hasher.add_bytes(bytes);
+}
+
+/// A password turned into a scalar for use with [`Spake2`]. Zeroized when
+/// dropped.
+#[derive(Clone)]
+pub struct Password {
+	w: [u8; 32],
+}
+
+impl Password {
+	/// Derives the password scalar from 64 bytes of output of a memory-hard
+	/// password hash, which both parties must compute in the same way.
+	pub fn from_hash(hash: &[u8; 64]) -> Self {
+		Self {w: reduce_wide(hash)}
+	}
+}
+
+impl Drop for Password {
+	fn drop(&mut self) {
+		zeroize(&mut self.w);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Password {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.w);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Password {}
+
+// deliberately doesn't print the password scalar
+impl fmt::Debug for Password {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Password").finish_non_exhaustive()
+	}
+}
+
+/// The state of one party in a SPAKE2 exchange, called A or B, after sending
+/// its message. Both parties must agree on who is A, and on the identities
+/// of both parties, which may be empty.
+pub struct Spake2<'a> {
+	w: [u8; 32],
+	x: [u8; 32],
+	is_a: bool,
+	message: [u8; 32],
+	id_a: &'a [u8],
+	id_b: &'a [u8],
+}
+
+impl<'a> Spake2<'a> {
+	fn start(is_a: bool, password: &Password, id_a: &'a [u8], id_b: &'a [u8], randomness: [u8; 32]) -> (Self, [u8; 32]) {
+		let x = random_scalar(&randomness);
+		let message = blinded_share(&x, &password.w, if is_a {M} else {N});
+
+		let state = Self {
+			w: password.w,
+			x,
+			is_a,
+			message,
+			id_a,
+			id_b,
+		};
+
+		(state, message)
+	}
+
+	/// Starts the exchange as party A, returning the message to send to B.
+	/// `randomness` must be freshly generated by a secure random source for
+	/// every exchange.
+	pub fn start_a(password: &Password, id_a: &'a [u8], id_b: &'a [u8], randomness: [u8; 32]) -> (Self, [u8; 32]) {
+		Self::start(true, password, id_a, id_b, randomness)
+	}
+
+	/// Starts the exchange as party B, returning the message to send to A.
+	/// `randomness` must be freshly generated by a secure random source for
+	/// every exchange.
+	pub fn start_b(password: &Password, id_a: &'a [u8], id_b: &'a [u8], randomness: [u8; 32]) -> (Self, [u8; 32]) {
+		Self::start(false, password, id_a, id_b, randomness)
+	}
+
+	/// Like [`Spake2::start_a`], but generates the randomness with `rng`,
+	/// which must be a cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn start_a_with_rng<R: RngCore + CryptoRng>(
+		password: &Password,
+		id_a: &'a [u8],
+		id_b: &'a [u8],
+		rng: &mut R,
+	) -> (Self, [u8; 32]) {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::start_a(password, id_a, id_b, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Like [`Spake2::start_b`], but generates the randomness with `rng`,
+	/// which must be a cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn start_b_with_rng<R: RngCore + CryptoRng>(
+		password: &Password,
+		id_a: &'a [u8],
+		id_b: &'a [u8],
+		rng: &mut R,
+	) -> (Self, [u8; 32]) {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::start_b(password, id_a, id_b, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Processes the other party's message, deriving the keys with the
+	/// additional authenticated data `aad`, which both parties must agree on.
+	pub fn finish(self, peer_message: [u8; 32], aad: &[u8]) -> Result<Spake2Confirmation, Error> {
+		let peer_blinding = if self.is_a {N} else {M};
+		let k = cofactor_mult(&self.x, unblind_share(peer_message, &self.w, peer_blinding)?)?;
+
+		let (message_a, message_b) = if self.is_a {
+			(self.message, peer_message)
+		} else {
+			(peer_message, self.message)
+		};
+
+		// the transcript TT, which is hashed into the keys and then macced for
+		// the confirmations
+		let transcript: [&[u8]; 6] = [self.id_a, self.id_b, &message_a, &message_b, &k, &self.w];
+
+		let mut hasher = Sha256::new();
+
+		for part in transcript {
+			add_prefixed(&mut hasher, part);
+		}
+
+		let mut transcript_hash = hasher.out();
+
+		let mut shared_key = [0; 16];
+		shared_key.copy_from_slice(&transcript_hash[0 .. 16]);
+
+		// a single block of HKDF-Expand, with "ConfirmationKeys" || aad as the info
+		let mut prk = hkdf_sha256_extract(&[], &transcript_hash[16 .. 32]);
+		let mut hmac = HmacSha256::new(&prk);
+		hmac.add_bytes(b"ConfirmationKeys");
+		hmac.add_bytes(aad);
+		hmac.add_bytes(&[1]);
+		let mut confirmation_keys = hmac.out();
+
+		// each party's confirmation is a mac of the whole transcript
+		let mac_transcript = |key: &[u8]| {
+			let mut hmac = HmacSha256::new(key);
+
+			for part in transcript {
+				hmac.add_bytes(&(part.len() as u64).to_le_bytes());
+				hmac.add_bytes(part);
+			}
+
+			hmac.out()
+		};
+
+		let confirmation_a = mac_transcript(&confirmation_keys[0 .. 16]);
+		let confirmation_b = mac_transcript(&confirmation_keys[16 .. 32]);
+
+		zeroize(&mut transcript_hash);
+		zeroize(&mut prk);
+		zeroize(&mut confirmation_keys);
+
+		let (confirmation, expected_confirmation) = if self.is_a {
+			(confirmation_a, confirmation_b)
+		} else {
+			(confirmation_b, confirmation_a)
+		};
+
+		Ok(Spake2Confirmation {
+			shared_key,
+			confirmation,
+			expected_confirmation,
+		})
+	}
+}
+
+impl Drop for Spake2<'_> {
+	fn drop(&mut self) {
+		zeroize(&mut self.w);
+		zeroize(&mut self.x);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2<'_> {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.w);
+		zeroize(&mut self.x);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2<'_> {}
+
+// deliberately doesn't print any scalars
+impl fmt::Debug for Spake2<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2").field("is_a", &self.is_a).finish_non_exhaustive()
+	}
+}
+
+/// The keys derived by a SPAKE2 exchange, which the shared key is only
+/// released from once the other party's confirmation has been checked.
+pub struct Spake2Confirmation {
+	shared_key: [u8; 16],
+	confirmation: [u8; 32],
+	expected_confirmation: [u8; 32],
+}
+
+impl Spake2Confirmation {
+	/// Returns this party's confirmation, to send to the other party.
+	pub fn confirmation(&self) -> [u8; 32] {
+		self.confirmation
+	}
+
+	/// Checks the other party's confirmation in constant time, returning the
+	/// 16-byte shared key if it's correct.
+	pub fn verify(self, peer_confirmation: &[u8; 32]) -> Result<[u8; 16], Error> {
+		if self.expected_confirmation.ct_ne(peer_confirmation).into() {
+			return Err(Error::InvalidConfirmation);
+		}
+
+		Ok(self.shared_key)
+	}
+}
+
+impl Drop for Spake2Confirmation {
+	fn drop(&mut self) {
+		zeroize(&mut self.shared_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2Confirmation {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.shared_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2Confirmation {}
+
+// deliberately doesn't print the shared key
+impl fmt::Debug for Spake2Confirmation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2Confirmation").finish_non_exhaustive()
+	}
+}
+
+/// A password turned into the two scalars `w0` and `w1` used by the
+/// SPAKE2+ prover. Zeroized when dropped.
+#[derive(Clone)]
+pub struct Spake2PlusPassword {
+	w0: [u8; 32],
+	w1: [u8; 32],
+}
+
+impl Spake2PlusPassword {
+	/// Derives the password scalars from 80 bytes of output of a memory-hard
+	/// password hash. RFC 9383 hashes the password along with the identities
+	/// of both parties, each prefixed with its length as an 8-byte
+	/// little-endian integer.
+	pub fn from_hash(hash: &[u8; 80]) -> Self {
+		Self {
+			w0: reduce_wide(&hash[0 .. 40]),
+			w1: reduce_wide(&hash[40 .. 80]),
+		}
+	}
+
+	/// Computes the record the verifier stores in place of the password.
+	pub fn record(&self) -> Spake2PlusRecord {
+		Spake2PlusRecord {
+			w0: self.w0,
+			l: compress(basepoint_mult(&self.w1)),
+		}
+	}
+}
+
+impl Drop for Spake2PlusPassword {
+	fn drop(&mut self) {
+		zeroize(&mut self.w0);
+		zeroize(&mut self.w1);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2PlusPassword {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.w0);
+		zeroize(&mut self.w1);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2PlusPassword {}
+
+// deliberately doesn't print the password scalars
+impl fmt::Debug for Spake2PlusPassword {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2PlusPassword").finish_non_exhaustive()
+	}
+}
+
+/// The verifier's record of a SPAKE2+ password: the scalar `w0` and the
+/// point `L`. It should still be kept secret, since it allows testing
+/// password guesses offline. Zeroized when dropped.
+#[derive(Clone)]
+pub struct Spake2PlusRecord {
+	w0: [u8; 32],
+	l: [u8; 32],
+}
+
+impl Spake2PlusRecord {
+	/// Parses a record from its 64-byte representation, returning an error
+	/// if `w0` isn't reduced or `L` isn't a point in the prime-order
+	/// subgroup.
+	pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self, Error> {
+		let mut w0 = [0; 32];
+		let mut l = [0; 32];
+		w0.copy_from_slice(&bytes[0 .. 32]);
+		l.copy_from_slice(&bytes[32 .. 64]);
+
+		if !verify_less_than_l(&w0) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		match decompress(l) {
+			Some(point) if is_torsion_free(point) => Ok(Self {w0, l}),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+
+	/// Returns the 64-byte representation of this record, `w0` followed by
+	/// `L`.
+	pub fn to_bytes(&self) -> [u8; 64] {
+		let mut out = [0; 64];
+		out[0 .. 32].copy_from_slice(&self.w0);
+		out[32 .. 64].copy_from_slice(&self.l);
+		out
+	}
+}
+
+impl Drop for Spake2PlusRecord {
+	fn drop(&mut self) {
+		zeroize(&mut self.w0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2PlusRecord {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.w0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2PlusRecord {}
+
+// deliberately doesn't print w0
+impl fmt::Debug for Spake2PlusRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2PlusRecord").finish_non_exhaustive()
+	}
+}
+
+struct Spake2PlusKeys {
+	confirm_p: [u8; 32],
+	confirm_v: [u8; 32],
+	shared_key: [u8; 32],
+}
+
+impl Drop for Spake2PlusKeys {
+	fn drop(&mut self) {
+		zeroize(&mut self.shared_key);
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spake2plus_keys(
+	context: &[u8],
+	id_prover: &[u8],
+	id_verifier: &[u8],
+	share_p: &[u8; 32],
+	share_v: &[u8; 32],
+	z: &[u8; 32],
+	v: &[u8; 32],
+	w0: &[u8; 32],
+) -> Spake2PlusKeys {
+	let mut hasher = Sha256::new();
+	add_prefixed(&mut hasher, context);
+	add_prefixed(&mut hasher, id_prover);
+	add_prefixed(&mut hasher, id_verifier);
+	add_prefixed(&mut hasher, &M);
+	add_prefixed(&mut hasher, &N);
+	add_prefixed(&mut hasher, share_p);
+	add_prefixed(&mut hasher, share_v);
+	add_prefixed(&mut hasher, z);
+	add_prefixed(&mut hasher, v);
+	add_prefixed(&mut hasher, w0);
+	let mut main_key = hasher.out();
+
+	let mut confirmation_keys = [0; 64];
+	hkdf_sha256(&[], &main_key, b"ConfirmationKeys", &mut confirmation_keys);
+
+	let mut shared_key = [0; 32];
+	hkdf_sha256(&[], &main_key, b"SharedKey", &mut shared_key);
+
+	let keys = Spake2PlusKeys {
+		confirm_p: hmac_sha256(&confirmation_keys[0 .. 32], share_v),
+		confirm_v: hmac_sha256(&confirmation_keys[32 .. 64], share_p),
+		shared_key,
+	};
+
+	zeroize(&mut main_key);
+	zeroize(&mut confirmation_keys);
+
+	keys
+}
+
+/// The state of the SPAKE2+ prover, who knows the password, after sending
+/// its share to the verifier.
+pub struct Spake2PlusProver<'a> {
+	w0: [u8; 32],
+	w1: [u8; 32],
+	x: [u8; 32],
+	share_p: [u8; 32],
+	context: &'a [u8],
+	id_prover: &'a [u8],
+	id_verifier: &'a [u8],
+}
+
+impl<'a> Spake2PlusProver<'a> {
+	/// Starts the exchange, returning the share to send to the verifier.
+	/// Both parties must agree on the `context` and on the identities, which
+	/// may be empty. `randomness` must be freshly generated by a secure
+	/// random source for every exchange.
+	pub fn start(
+		password: &Spake2PlusPassword,
+		context: &'a [u8],
+		id_prover: &'a [u8],
+		id_verifier: &'a [u8],
+		randomness: [u8; 32],
+	) -> (Self, [u8; 32]) {
+		let x = random_scalar(&randomness);
+		let share_p = blinded_share(&x, &password.w0, M);
+
+		let state = Self {
+			w0: password.w0,
+			w1: password.w1,
+			x,
+			share_p,
+			context,
+			id_prover,
+			id_verifier,
+		};
+
+		(state, share_p)
+	}
+
+	/// Like [`Spake2PlusProver::start`], but generates the randomness with
+	/// `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn start_with_rng<R: RngCore + CryptoRng>(
+		password: &Spake2PlusPassword,
+		context: &'a [u8],
+		id_prover: &'a [u8],
+		id_verifier: &'a [u8],
+		rng: &mut R,
+	) -> (Self, [u8; 32]) {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::start(password, context, id_prover, id_verifier, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Processes the verifier's share and confirmation. If the confirmation
+	/// is correct, returns the prover's confirmation to send back, along with
+	/// the 32-byte shared key.
+	pub fn finish(self, share_v: [u8; 32], confirm_v: &[u8; 32]) -> Result<([u8; 32], [u8; 32]), Error> {
+		let unblinded = unblind_share(share_v, &self.w0, N)?;
+		let mut z = cofactor_mult(&self.x, unblinded)?;
+		let mut v = cofactor_mult(&self.w1, unblinded)?;
+
+		let keys = spake2plus_keys(
+			self.context,
+			self.id_prover,
+			self.id_verifier,
+			&self.share_p,
+			&share_v,
+			&z,
+			&v,
+			&self.w0,
+		);
+
+		zeroize(&mut z);
+		zeroize(&mut v);
+
+		if keys.confirm_v.ct_ne(confirm_v).into() {
+			return Err(Error::InvalidConfirmation);
+		}
+
+		Ok((keys.confirm_p, keys.shared_key))
+	}
+}
+
+impl Drop for Spake2PlusProver<'_> {
+	fn drop(&mut self) {
+		zeroize(&mut self.w0);
+		zeroize(&mut self.w1);
+		zeroize(&mut self.x);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2PlusProver<'_> {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.w0);
+		zeroize(&mut self.w1);
+		zeroize(&mut self.x);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2PlusProver<'_> {}
+
+// deliberately doesn't print any scalars
+impl fmt::Debug for Spake2PlusProver<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2PlusProver").finish_non_exhaustive()
+	}
+}
+
+/// The state of the SPAKE2+ verifier after responding to the prover's
+/// share, waiting for the prover's confirmation.
+pub struct Spake2PlusVerifier {
+	expected_confirm_p: [u8; 32],
+	shared_key: [u8; 32],
+}
+
+impl Spake2PlusVerifier {
+	/// Responds to the prover's share, returning the verifier's state along
+	/// with its share and confirmation to send to the prover. Both parties
+	/// must agree on the `context` and on the identities, which may be empty.
+	/// `randomness` must be freshly generated by a secure random source for
+	/// every exchange.
+	pub fn respond(
+		record: &Spake2PlusRecord,
+		context: &[u8],
+		id_prover: &[u8],
+		id_verifier: &[u8],
+		share_p: [u8; 32],
+		randomness: [u8; 32],
+	) -> Result<(Self, [u8; 32], [u8; 32]), Error> {
+		let mut y = random_scalar(&randomness);
+		let share_v = blinded_share(&y, &record.w0, N);
+
+		let l = decompress(record.l).unwrap();
+		let mut z = cofactor_mult(&y, unblind_share(share_p, &record.w0, M)?)?;
+		let mut v = cofactor_mult(&y, l)?;
+
+		zeroize(&mut y);
+
+		let keys = spake2plus_keys(context, id_prover, id_verifier, &share_p, &share_v, &z, &v, &record.w0);
+
+		zeroize(&mut z);
+		zeroize(&mut v);
+
+		let state = Self {
+			expected_confirm_p: keys.confirm_p,
+			shared_key: keys.shared_key,
+		};
+
+		Ok((state, share_v, keys.confirm_v))
+	}
+
+	/// Like [`Spake2PlusVerifier::respond`], but generates the randomness
+	/// with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn respond_with_rng<R: RngCore + CryptoRng>(
+		record: &Spake2PlusRecord,
+		context: &[u8],
+		id_prover: &[u8],
+		id_verifier: &[u8],
+		share_p: [u8; 32],
+		rng: &mut R,
+	) -> Result<(Self, [u8; 32], [u8; 32]), Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::respond(record, context, id_prover, id_verifier, share_p, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Checks the prover's confirmation in constant time, returning the
+	/// 32-byte shared key if it's correct.
+	pub fn finish(self, confirm_p: &[u8; 32]) -> Result<[u8; 32], Error> {
+		if self.expected_confirm_p.ct_ne(confirm_p).into() {
+			return Err(Error::InvalidConfirmation);
+		}
+
+		Ok(self.shared_key)
+	}
+}
+
+impl Drop for Spake2PlusVerifier {
+	fn drop(&mut self) {
+		zeroize(&mut self.shared_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Spake2PlusVerifier {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.shared_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Spake2PlusVerifier {}
+
+// deliberately doesn't print the shared key
+impl fmt::Debug for Spake2PlusVerifier {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spake2PlusVerifier").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// checked against a python transcription of section 4 of RFC 9382, doing the
+// edwards25519 arithmetic with python's arbitrary-precision integers
+#[test]
+fn test_spake2() {
+	let password = Password::from_hash(&[0x77; 64]);
+
+	let (alice, message_a) = Spake2::start_a(&password, b"alice", b"bob", [0x11; 32]);
+	let (bob, message_b) = Spake2::start_b(&password, b"alice", b"bob", [0x22; 32]);
+	assert_eq!(message_a, hex("fb116a5aeea33334bb77cbdedb4575b1d2a55c95345173e47c23733ca2f089ca"));
+	assert_eq!(message_b, hex("c3adb1b04261255cb92bd558add7a3da10cb0edeef2d95189c46d30d6e97d08a"));
+
+	let alice = alice.finish(message_b, b"aad").unwrap();
+	let bob = bob.finish(message_a, b"aad").unwrap();
+	assert_eq!(alice.confirmation(), hex("a4d7d053610c4574facde4e9c539dd058296afa7780a6c4ef47fd813de4d5e2d"));
+	assert_eq!(bob.confirmation(), hex("25a3f2bbb96cb87a4d34801ec2ce39f69f8d5548c2a0041b6a4f3e6929439968"));
+
+	let alice_confirmation = alice.confirmation();
+	let key = hex("d5851f472def5fcffa1e06432e3785ca");
+	assert_eq!(alice.verify(&bob.confirmation()), Ok(key));
+	assert_eq!(bob.verify(&alice_confirmation), Ok(key));
+}
+
+#[test]
+fn test_spake2_wrong_password() {
+	let (alice, message_a) = Spake2::start_a(&Password::from_hash(&[0x77; 64]), b"", b"", [0x11; 32]);
+	let (bob, message_b) = Spake2::start_b(&Password::from_hash(&[0x78; 64]), b"", b"", [0x22; 32]);
+
+	let alice = alice.finish(message_b, b"").unwrap();
+	let bob = bob.finish(message_a, b"").unwrap();
+	assert_eq!(alice.verify(&bob.confirmation()), Err(Error::InvalidConfirmation));
+
+	// a message equal to w * N cancels out to the identity
+	let password = Password::from_hash(&[0x77; 64]);
+	let (alice, _) = Spake2::start_a(&password, b"", b"", [0x11; 32]);
+	let canceling = compress(scalar_mult(scalar(&password.w), decompress(N).unwrap()));
+	assert_eq!(alice.finish(canceling, b"").unwrap_err(), Error::InvalidMessage);
+}
+
+// checked against a python transcription of section 3 of RFC 9383, doing the
+// edwards25519 arithmetic with python's arbitrary-precision integers
+#[test]
+fn test_spake2plus() {
+	let mut hash = [0; 80];
+
+	for i in 0 .. 80 {
+		hash[i] = i as u8;
+	}
+
+	let password = Spake2PlusPassword::from_hash(&hash);
+	let record = Spake2PlusRecord::from_bytes(&password.record().to_bytes()).unwrap();
+	assert_eq!(record.l, hex("1a917c1dce277d30a90f96b01347ea35d7b12a11fae66ffd1ea9422b7618b33a"));
+
+	let (prover, share_p) = Spake2PlusProver::start(&password, b"ctx", b"client", b"server", [0x33; 32]);
+	assert_eq!(share_p, hex("1ae936a027731f6e943f3259e5900939de57a7170c395eb3100360e3abca56cc"));
+
+	let (verifier, share_v, confirm_v) =
+		Spake2PlusVerifier::respond(&record, b"ctx", b"client", b"server", share_p, [0x44; 32]).unwrap();
+	assert_eq!(share_v, hex("c2bee883e4d8a70dce879cd9a6cb68c3312b57bba07a68a38c280f5396b4cb1f"));
+	assert_eq!(confirm_v, hex("bf56ddb98586485d7509bf8b1d2fb365f52274495c44ff3dd45e17c84750b1bb"));
+
+	let (confirm_p, prover_key) = prover.finish(share_v, &confirm_v).unwrap();
+	assert_eq!(confirm_p, hex("21186cb7713e2d90fb60f3d2ecc374e48182bd56c69dac1e8abba5d8fbbed6d9"));
+
+	let key = hex("11cf57697603095f8af168cd785a6a6c5758e1acaa26022e8967d57037a7287d");
+	assert_eq!(prover_key, key);
+	assert_eq!(verifier.finish(&confirm_p), Ok(key));
+}
+
+#[test]
+fn test_spake2plus_wrong_password() {
+	let record = Spake2PlusPassword::from_hash(&[0x55; 80]).record();
+	let password = Spake2PlusPassword::from_hash(&[0x56; 80]);
+
+	let (prover, share_p) = Spake2PlusProver::start(&password, b"", b"", b"", [0x33; 32]);
+	let (_, share_v, confirm_v) = Spake2PlusVerifier::respond(&record, b"", b"", b"", share_p, [0x44; 32]).unwrap();
+	assert_eq!(prover.finish(share_v, &confirm_v).unwrap_err(), Error::InvalidConfirmation);
+
+	let mut bytes = record.to_bytes();
+	bytes[0 .. 32].copy_from_slice(&[0xff; 32]);
+	assert_eq!(Spake2PlusRecord::from_bytes(&bytes).unwrap_err(), Error::InvalidEncoding);
+}
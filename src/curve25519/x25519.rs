@@ -2,32 +2,144 @@
 //! X25519 is a variant of Diffie-Hellman key exchange, used to establish
 //! a shared secret between two parties without any middleman able to discern
 //! the secret.
+//!
+//! [`StaticSecret`], [`EphemeralSecret`] and [`PublicKey`] wrap the raw
+//! functions in this module, zeroizing secrets when they're dropped and
+//! ensuring ephemeral secrets are only used for a single key exchange.
 
-use super::conditional_swap;
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+use crate::zeroize::zeroize;
+
+#[cfg(feature = "rand_core")]
+use super::blinding::{blind_scalar, BLINDED_BITS};
+#[cfg(feature = "basepoint-table")]
+use super::ed25519::basepoint_mult;
 use super::num::Num;
 
+/// An error returned by the checked X25519 functions, or when decoding a key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The other party's public key is a point of small order, so the shared
+	/// secret is all zeros and doesn't depend on your private key.
+	SmallOrderPoint,
+	/// The public key is not the canonical encoding of its u-coordinate,
+	/// either because its top bit is set or because it's not reduced mod p.
+	NonCanonicalPoint,
+	/// The bytes or text passed in are not a valid encoding of a key.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::SmallOrderPoint => "x25519 public key is a small-order point",
+			Error::NonCanonicalPoint => "x25519 public key is not canonically encoded",
+			Error::InvalidEncoding => "invalid x25519 key encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// only used by the ladder when the basepoint table is disabled
+#[cfg(any(not(feature = "basepoint-table"), test))]
 const BASE: Num = Num {segments: [9, 0, 0, 0, 0]};
 const A24: Num = Num {segments: [121665, 0, 0, 0, 0]};
 
-fn x25519_mult(mut scalar: [u8; 32], point: Num) -> Num {
+// u-coordinates of the points of small order on the curve and its twist, all of
+// which give an all-zero shared secret
+const SMALL_ORDER_POINTS: [[u8; 32]; 5] = [
+	// 0, of order 2
+	[
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	],
+	// 1, of order 4
+	[
+		0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	],
+	// of order 8
+	[
+		0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae,
+		0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4, 0x6a,
+		0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd,
+		0x86, 0x62, 0x05, 0x16, 0x5f, 0x49, 0xb8, 0x00,
+	],
+	// of order 8
+	[
+		0x5f, 0x9c, 0x95, 0xbc, 0xa3, 0x50, 0x8c, 0x24,
+		0xb1, 0xd0, 0xb1, 0x55, 0x9c, 0x83, 0xef, 0x5b,
+		0x04, 0x44, 0x5c, 0xc4, 0x58, 0x1c, 0x8e, 0x86,
+		0xd8, 0x22, 0x4e, 0xdd, 0xd0, 0x9f, 0x11, 0x57,
+	],
+	// p - 1, of order 4 on the twist
+	[
+		0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+		0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+		0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+		0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+	],
+];
+
+fn x25519_mult(scalar: [u8; 32], point: Num) -> Num {
+	let (x, z) = ladder(scalar, point);
+
+	let mut out = x / z;
+	out.full_modular_reduction();
+	out
+}
+
+// returns the result of the montgomery ladder in projective coordinates,
+// leaving the inversion to the caller
+fn ladder(mut scalar: [u8; 32], point: Num) -> (Num, Num) {
 	// clamp the scalar as specified in the RFC
 	scalar[0] &= 0xf8;
 	scalar[31] &= 0x7f;
 	scalar[31] |= 0x40;
 
+	#[cfg(all(target_arch = "x86_64", not(feature = "fiat-crypto")))]
+	if super::avx2::is_available() {
+		// SAFETY: the CPU was just checked to support AVX2
+		return unsafe {super::avx2::ladder(&scalar, point, A24)};
+	}
+
+	ladder_portable(&scalar, point)
+}
+
+fn ladder_portable(scalar: &[u8; 32], point: Num) -> (Num, Num) {
+	ladder_bits(scalar, 255, point, Num::ONE, Num::ONE)
+}
+
+// the montgomery ladder over the lowest `num_bits` bits of `scalar`, starting
+// from the neutral point as (x2 : 0) and `point` as (point * z3 : z3), which
+// represent the same points for any nonzero x2 and z3
+fn ladder_bits(scalar: &[u8], num_bits: usize, point: Num, x2: Num, z3: Num) -> (Num, Num) {
 	let x1 = point;
-	let mut x2 = Num::ONE;
+	let mut x2 = x2;
 	let mut z2 = Num::ZERO;
-	let mut x3 = point;
-	let mut z3 = Num::ONE;
+	let mut x3 = point * z3;
+	let mut z3 = z3;
 
 	let mut swapped = 0;
 
-	for current_bit in (0 .. 255).rev() {
+	for current_bit in (0 .. num_bits).rev() {
 		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
-		let do_swap = swapped ^ current_bit;
-		conditional_swap(do_swap, &mut x2, &mut x3);
-		conditional_swap(do_swap, &mut z2, &mut z3);
+		let do_swap = Choice::from(swapped ^ current_bit);
+		Num::conditional_swap(&mut x2, &mut x3, do_swap);
+		Num::conditional_swap(&mut z2, &mut z3, do_swap);
 		swapped = current_bit;
 
 		// this variable naming is pretty much straight out of the RFC
@@ -49,12 +161,11 @@ fn x25519_mult(mut scalar: [u8; 32], point: Num) -> Num {
 		z2 = e * (aa + A24 * e);
 	}
 
-	conditional_swap(swapped, &mut x2, &mut x3);
-	conditional_swap(swapped, &mut z2, &mut z3);
+	let swapped = Choice::from(swapped);
+	Num::conditional_swap(&mut x2, &mut x3, swapped);
+	Num::conditional_swap(&mut z2, &mut z3, swapped);
 
-	let mut out = x2 / z2;
-	out.full_modular_reduction();
-	out
+	(x2, z2)
 }
 
 #[test]
@@ -155,15 +266,37 @@ fn x25519_iterated_test_vector() {
 /// Given your private key (`priv_key`), returns your public key. This public
 /// key may be used by any other party to compute a shared secret using
 /// [`x25519_derive_secret`] or another implementation of X25519.
+#[cfg(not(feature = "basepoint-table"))]
 pub fn x25519_derive_pub_key(priv_key: [u8; 32]) -> [u8; 32] {
 	x25519_mult(priv_key, BASE).to_bytes()
 }
 
+/// Given your private key (`priv_key`), returns your public key. This public
+/// key may be used by any other party to compute a shared secret using
+/// [`x25519_derive_secret`] or another implementation of X25519.
+#[cfg(feature = "basepoint-table")]
+pub fn x25519_derive_pub_key(priv_key: [u8; 32]) -> [u8; 32] {
+	let mut scalar = priv_key;
+	scalar[0] &= 0xf8;
+	scalar[31] &= 0x7f;
+	scalar[31] |= 0x40;
+
+	// multiplying on the edwards curve can use the precomputed table, and the
+	// birational map takes the result to the same point on the montgomery curve
+	let point = basepoint_mult(&scalar);
+	zeroize(&mut scalar);
+
+	let mut u = (point.z + point.y) / (point.z - point.y);
+	u.full_modular_reduction();
+	u.to_bytes()
+}
+
 /// Given your private key (`priv_key`) and another party's public key (`pub_key`),
 /// returns a shared secret that is computable by both you and the other party.
 /// This shared secret is suitable to be used with a KDF to derive keys for use
 /// with symmetric cryptography. Note that the other party may maliciously
-/// choose their public key, and the shared secret will be all zeros in this case.
+/// choose their public key, and the shared secret will be all zeros in this case;
+/// [`x25519_derive_secret_checked`] returns an error instead.
 /// Otherwise, this shared secret cannot be computed by anyone without knowledge of
 /// either your private key or the other party's private key.
 pub fn x25519_derive_secret(priv_key: [u8; 32], pub_key: [u8; 32]) -> [u8; 32] {
@@ -171,16 +304,578 @@ pub fn x25519_derive_secret(priv_key: [u8; 32], pub_key: [u8; 32]) -> [u8; 32] {
 	x25519_mult(priv_key, pub_key).to_bytes()
 }
 
+const BATCH_SIZE: usize = 32;
+
+/// Computes a shared secret for each pair of private key and public key,
+/// writing them to `out`, with the same results as calling
+/// [`x25519_derive_secret`] on each pair. The field inversion at the end of
+/// each key exchange is shared between batches of them with Montgomery's
+/// trick, which makes this faster when computing many shared secrets at once.
+///
+/// # Panics
+/// * Panics if `priv_keys`, `pub_keys` and `out` don't all have the same length.
+pub fn x25519_derive_secret_batch(priv_keys: &[[u8; 32]], pub_keys: &[[u8; 32]], out: &mut [[u8; 32]]) {
+	assert!(priv_keys.len() == pub_keys.len() && pub_keys.len() == out.len());
+
+	let mut xs = [Num::ZERO; BATCH_SIZE];
+	let mut zs = [Num::ZERO; BATCH_SIZE];
+	let mut products = [Num::ZERO; BATCH_SIZE];
+
+	for start in (0 .. out.len()).step_by(BATCH_SIZE) {
+		let len = usize::min(BATCH_SIZE, out.len() - start);
+		let mut product = Num::ONE;
+
+		for i in 0 .. len {
+			let (mut x, mut z) = ladder(priv_keys[start + i], Num::from_bytes(pub_keys[start + i]));
+
+			// small-order public keys give a z of zero, which would spoil the
+			// whole batch, so use 0 / 1 instead, which is the same result
+			let is_zero = z.ct_eq(&Num::ZERO);
+
+			x.conditional_assign(&Num::ZERO, is_zero);
+			z.conditional_assign(&Num::ONE, is_zero);
+
+			xs[i] = x;
+			zs[i] = z;
+			product = product * z;
+			products[i] = product;
+		}
+
+		// the inverse of the product of all zs, which is peeled apart into the
+		// inverse of each one
+		let mut inverse = product.recip();
+
+		for i in (0 .. len).rev() {
+			let z_inverse = if i == 0 {inverse} else {inverse * products[i - 1]};
+			inverse = inverse * zs[i];
+
+			let mut result = xs[i] * z_inverse;
+			result.full_modular_reduction();
+			out[start + i] = result.to_bytes();
+		}
+	}
+}
+
+/// Like [`x25519_derive_secret`], but returns [`Error::SmallOrderPoint`]
+/// instead of a shared secret that is all zeros. This is the recommended way
+/// to compute a shared secret, since an all-zero secret means the other party
+/// chose their public key maliciously.
+pub fn x25519_derive_secret_checked(priv_key: [u8; 32], pub_key: [u8; 32]) -> Result<[u8; 32], Error> {
+	let secret = x25519_derive_secret(priv_key, pub_key);
+
+	if is_shared_secret_all_zero(secret) {
+		return Err(Error::SmallOrderPoint);
+	}
+
+	Ok(secret)
+}
+
+/// Like [`x25519_derive_secret_checked`], with countermeasures against power
+/// and electromagnetic analysis, for long-term keys on devices that an
+/// attacker may be able to take measurements of, such as smart cards and
+/// other embedded targets. A random multiple of eight times the group order
+/// is added to the clamped scalar, and the ladder starts from points in
+/// randomized projective coordinates, so no two key exchanges compute with the
+/// same values. This takes about a quarter more time than
+/// [`x25519_derive_secret_checked`], and never uses the AVX2 ladder.
+///
+/// The shared secret is the same as that of [`x25519_derive_secret_checked`]
+/// for every public key on the curve. Public keys on its twist, which no
+/// honest party sends, give a random result instead, since the group order of
+/// the twist is different.
+#[cfg(feature = "rand_core")]
+pub fn x25519_derive_secret_hardened<R: RngCore + CryptoRng>(
+	priv_key: [u8; 32],
+	pub_key: [u8; 32],
+	rng: &mut R,
+) -> Result<[u8; 32], Error> {
+	let mut scalar = priv_key;
+	scalar[0] &= 0xf8;
+	scalar[31] &= 0x7f;
+	scalar[31] |= 0x40;
+
+	// a multiple of 8l also takes the small-order component of the point,
+	// which clamping clears in the unblinded scalar, to the neutral point
+	let mut blinded = blind_scalar(&scalar, 8, rng);
+	zeroize(&mut scalar);
+
+	let point = Num::from_bytes(pub_key);
+	let (x, z) = ladder_bits(&blinded, BLINDED_BITS, point, Num::random(rng), Num::random(rng));
+	zeroize(&mut blinded);
+
+	let mut secret = x / z;
+	secret.full_modular_reduction();
+	let secret = secret.to_bytes();
+
+	if is_shared_secret_all_zero(secret) {
+		return Err(Error::SmallOrderPoint);
+	}
+
+	Ok(secret)
+}
+
+/// Checks another party's public key before using it, returning
+/// [`Error::SmallOrderPoint`] if it's one of the points that give an all-zero
+/// shared secret (in any encoding), or [`Error::NonCanonicalPoint`] if it's not
+/// canonically encoded. Most protocols only need
+/// [`x25519_derive_secret_checked`], but some must reject these keys before
+/// performing the key exchange. Works in constant time.
+pub fn x25519_check_pub_key(pub_key: [u8; 32]) -> Result<(), Error> {
+	let mut reduced = Num::from_bytes(pub_key);
+	reduced.full_modular_reduction();
+	let reduced = reduced.to_bytes();
+
+	let mut small_order = Choice::from(0);
+
+	for point in SMALL_ORDER_POINTS {
+		small_order |= reduced.ct_eq(&point);
+	}
+
+	if small_order.into() {
+		return Err(Error::SmallOrderPoint);
+	}
+
+	if reduced.ct_ne(&pub_key).into() {
+		return Err(Error::NonCanonicalPoint);
+	}
+
+	Ok(())
+}
+
 /// Determines whether the shared secret is all zeros. It's strongly recommended
 /// to use this function instead of something like `==` to check if the secret is
 /// all zeros because this function works in constant time, and will not leak
 /// any information about the shared secret.
 pub fn is_shared_secret_all_zero(secret: [u8; 32]) -> bool {
-	let mut acc = 0;
+	secret.ct_eq(&[0; 32]).into()
+}
+
+/// An X25519 public key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+	/// Creates a public key from its 32-byte representation. Any 32 bytes
+	/// are accepted, since X25519 is defined for all inputs; see
+	/// [`x25519_check_pub_key`] for rejecting small-order points.
+	pub fn from_bytes(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	/// Returns the 32-byte representation of this public key.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Returns a reference to the 32-byte representation of this public key.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+}
 
-	for byte in secret {
-		acc |= byte;
+impl From<[u8; 32]> for PublicKey {
+	fn from(bytes: [u8; 32]) -> Self {
+		Self(bytes)
 	}
+}
+
+impl From<&StaticSecret> for PublicKey {
+	fn from(secret: &StaticSecret) -> Self {
+		Self(x25519_derive_pub_key(secret.0))
+	}
+}
+
+impl From<&EphemeralSecret> for PublicKey {
+	fn from(secret: &EphemeralSecret) -> Self {
+		Self(x25519_derive_pub_key(secret.0))
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(PublicKey, 32, Hex, |key| key.to_bytes(), |bytes| PublicKey::from_bytes(bytes));
+
+text_impl!(PublicKey, 32, Hex, |key| key.to_bytes(), |bytes| PublicKey::from_bytes(bytes));
+
+/// An X25519 private key that can be used for any number of key exchanges,
+/// for example a long-term identity key. Zeroized when dropped.
+#[derive(Clone)]
+pub struct StaticSecret([u8; 32]);
+
+impl StaticSecret {
+	/// Creates a private key from its 32-byte representation. Any 32 bytes
+	/// are a valid private key, so these should be generated by a secure
+	/// random source.
+	pub fn from_bytes(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	/// Generates a new private key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut bytes = [0; 32];
+		rng.fill_bytes(&mut bytes);
+
+		Self(bytes)
+	}
+
+	/// Returns the 32-byte representation of this private key. This must be
+	/// kept secret.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Computes the secret shared with the owner of `their_public`. See
+	/// [`x25519_derive_secret_checked`].
+	pub fn diffie_hellman(&self, their_public: &PublicKey) -> Result<SharedSecret, Error> {
+		x25519_derive_secret_checked(self.0, their_public.0).map(SharedSecret)
+	}
+
+	/// Computes the secret shared with the owner of `their_public`, with
+	/// countermeasures against power and electromagnetic analysis. See
+	/// [`x25519_derive_secret_hardened`].
+	#[cfg(feature = "rand_core")]
+	pub fn diffie_hellman_hardened<R: RngCore + CryptoRng>(
+		&self,
+		their_public: &PublicKey,
+		rng: &mut R,
+	) -> Result<SharedSecret, Error> {
+		x25519_derive_secret_hardened(self.0, their_public.0, rng).map(SharedSecret)
+	}
+}
+
+impl Drop for StaticSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for StaticSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for StaticSecret {}
 
-	acc == 0
+// deliberately doesn't print the private key
+impl fmt::Debug for StaticSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("StaticSecret")
+			.field("public_key", &PublicKey::from(self))
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(StaticSecret, 32, Hex, |key| key.to_bytes(), |bytes| StaticSecret::from_bytes(bytes));
+
+/// An X25519 private key that can only be used for a single key exchange,
+/// since [`EphemeralSecret::diffie_hellman`] consumes it. It can't be
+/// cloned or serialized, and is zeroized when dropped.
+pub struct EphemeralSecret([u8; 32]);
+
+impl EphemeralSecret {
+	/// Creates an ephemeral private key from 32 bytes, which must be freshly
+	/// generated by a secure random source.
+	pub fn from_bytes(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	/// Generates a new ephemeral private key using a cryptographically secure
+	/// random number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut bytes = [0; 32];
+		rng.fill_bytes(&mut bytes);
+
+		Self(bytes)
+	}
+
+	/// Computes the secret shared with the owner of `their_public`, consuming
+	/// this private key. See [`x25519_derive_secret_checked`].
+	pub fn diffie_hellman(self, their_public: &PublicKey) -> Result<SharedSecret, Error> {
+		x25519_derive_secret_checked(self.0, their_public.0).map(SharedSecret)
+	}
+}
+
+impl Drop for EphemeralSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for EphemeralSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for EphemeralSecret {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for EphemeralSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EphemeralSecret").finish_non_exhaustive()
+	}
+}
+
+/// The result of an X25519 key exchange, which is never all zeros.
+/// Zeroized when dropped.
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+	/// Returns the 32 bytes of this shared secret, which should be passed
+	/// through a KDF before being used as a key.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Returns a reference to the 32 bytes of this shared secret.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+}
+
+impl Drop for SharedSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SharedSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SharedSecret {}
+
+// deliberately doesn't print the shared secret
+impl fmt::Debug for SharedSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SharedSecret").finish_non_exhaustive()
+	}
+}
+
+#[test]
+fn x25519_check_pub_key_rejects_small_order_points() {
+	for point in SMALL_ORDER_POINTS {
+		assert_eq!(x25519_check_pub_key(point), Err(Error::SmallOrderPoint));
+		assert_eq!(x25519_derive_secret([0x42; 32], point), [0; 32]);
+
+		// with the ignored top bit set
+		let mut high_bit = point;
+		high_bit[31] |= 0x80;
+		assert_eq!(x25519_check_pub_key(high_bit), Err(Error::SmallOrderPoint));
+	}
+
+	// p and p + 1, which are non-canonical encodings of 0 and 1
+	let mut p = SMALL_ORDER_POINTS[4];
+	p[0] = 0xed;
+	assert_eq!(x25519_check_pub_key(p), Err(Error::SmallOrderPoint));
+	p[0] = 0xee;
+	assert_eq!(x25519_check_pub_key(p), Err(Error::SmallOrderPoint));
+
+	// p + 9, a non-canonical encoding of the base point
+	p[0] = 0xf6;
+	assert_eq!(x25519_check_pub_key(p), Err(Error::NonCanonicalPoint));
+
+	let mut pub_key = x25519_derive_pub_key([0x42; 32]);
+	assert_eq!(x25519_check_pub_key(pub_key), Ok(()));
+	pub_key[31] |= 0x80;
+	assert_eq!(x25519_check_pub_key(pub_key), Err(Error::NonCanonicalPoint));
+}
+
+#[test]
+fn x25519_typed_key_exchange() {
+	// from section 6.1 of the RFC
+	let alice_priv_key = [
+		0x77, 0x07, 0x6d, 0x0a, 0x73, 0x18, 0xa5, 0x7d,
+		0x3c, 0x16, 0xc1, 0x72, 0x51, 0xb2, 0x66, 0x45,
+		0xdf, 0x4c, 0x2f, 0x87, 0xeb, 0xc0, 0x99, 0x2a,
+		0xb1, 0x77, 0xfb, 0xa5, 0x1d, 0xb9, 0x2c, 0x2a,
+	];
+	let alice_pub_key = [
+		0x85, 0x20, 0xf0, 0x09, 0x89, 0x30, 0xa7, 0x54,
+		0x74, 0x8b, 0x7d, 0xdc, 0xb4, 0x3e, 0xf7, 0x5a,
+		0x0d, 0xbf, 0x3a, 0x0d, 0x26, 0x38, 0x1a, 0xf4,
+		0xeb, 0xa4, 0xa9, 0x8e, 0xaa, 0x9b, 0x4e, 0x6a,
+	];
+	let bob_priv_key = [
+		0x5d, 0xab, 0x08, 0x7e, 0x62, 0x4a, 0x8a, 0x4b,
+		0x79, 0xe1, 0x7f, 0x8b, 0x83, 0x80, 0x0e, 0xe6,
+		0x6f, 0x3b, 0xb1, 0x29, 0x26, 0x18, 0xb6, 0xfd,
+		0x1c, 0x2f, 0x8b, 0x27, 0xff, 0x88, 0xe0, 0xeb,
+	];
+	let bob_pub_key = [
+		0xde, 0x9e, 0xdb, 0x7d, 0x7b, 0x7d, 0xc1, 0xb4,
+		0xd3, 0x5b, 0x61, 0xc2, 0xec, 0xe4, 0x35, 0x37,
+		0x3f, 0x83, 0x43, 0xc8, 0x5b, 0x78, 0x67, 0x4d,
+		0xad, 0xfc, 0x7e, 0x14, 0x6f, 0x88, 0x2b, 0x4f,
+	];
+	let expected_secret = [
+		0x4a, 0x5d, 0x9d, 0x5b, 0xa4, 0xce, 0x2d, 0xe1,
+		0x72, 0x8e, 0x3b, 0xf4, 0x80, 0x35, 0x0f, 0x25,
+		0xe0, 0x7e, 0x21, 0xc9, 0x47, 0xd1, 0x9e, 0x33,
+		0x76, 0xf0, 0x9b, 0x3c, 0x1e, 0x16, 0x17, 0x42,
+	];
+
+	let alice = StaticSecret::from_bytes(alice_priv_key);
+	let bob = EphemeralSecret::from_bytes(bob_priv_key);
+
+	assert_eq!(PublicKey::from(&alice).to_bytes(), alice_pub_key);
+	assert_eq!(PublicKey::from(&bob).to_bytes(), bob_pub_key);
+
+	let alice_secret = alice.diffie_hellman(&PublicKey::from_bytes(bob_pub_key)).unwrap();
+	let bob_secret = bob.diffie_hellman(&PublicKey::from_bytes(alice_pub_key)).unwrap();
+
+	assert_eq!(alice_secret.to_bytes(), expected_secret);
+	assert_eq!(bob_secret.as_bytes(), &expected_secret);
+
+	let small_order = alice.diffie_hellman(&PublicKey::from_bytes([0; 32]));
+	assert_eq!(small_order.unwrap_err(), Error::SmallOrderPoint);
+}
+
+#[test]
+fn x25519_checked_rejects_small_order_points() {
+	let priv_key = [0x42; 32];
+
+	// points of order 2, 4 and 8
+	let mut order_4 = [0; 32];
+	order_4[0] = 1;
+	let order_8 = [
+		0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae,
+		0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4, 0x6a,
+		0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd,
+		0x86, 0x62, 0x05, 0x16, 0x5f, 0x49, 0xb8, 0x00,
+	];
+
+	for pub_key in [[0; 32], order_4, order_8] {
+		assert_eq!(x25519_derive_secret_checked(priv_key, pub_key), Err(Error::SmallOrderPoint));
+	}
+
+	let pub_key = x25519_derive_pub_key([0x43; 32]);
+	assert_eq!(
+		x25519_derive_secret_checked(priv_key, pub_key),
+		Ok(x25519_derive_secret(priv_key, pub_key)),
+	);
+}
+
+#[test]
+fn x25519_derive_pub_key_matches_ladder() {
+	for i in 0 .. 16u8 {
+		let mut priv_key = [i.wrapping_mul(0x1d); 32];
+		priv_key[0] = i;
+		priv_key[31] = 0xff - i;
+
+		assert_eq!(x25519_derive_pub_key(priv_key), x25519_mult(priv_key, BASE).to_bytes());
+	}
+}
+
+#[test]
+fn x25519_batch_matches_single() {
+	let mut priv_keys = [[0; 32]; 70];
+	let mut pub_keys = [[0; 32]; 70];
+
+	for i in 0 .. 70 {
+		priv_keys[i] = [i as u8; 32];
+		pub_keys[i] = x25519_derive_pub_key([0x80 ^ i as u8; 32]);
+	}
+
+	// small-order points give all zeros without affecting the rest of the batch
+	pub_keys[5] = SMALL_ORDER_POINTS[0];
+	pub_keys[40] = SMALL_ORDER_POINTS[2];
+
+	let mut out = [[0xff; 32]; 70];
+	x25519_derive_secret_batch(&priv_keys, &pub_keys, &mut out);
+
+	for i in 0 .. 70 {
+		assert_eq!(out[i], x25519_derive_secret(priv_keys[i], pub_keys[i]));
+	}
+
+	assert_eq!(out[5], [0; 32]);
+	assert_eq!(out[40], [0; 32]);
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "fiat-crypto")))]
+#[test]
+fn x25519_avx2_matches_portable() {
+	if !super::avx2::is_available() {
+		return;
+	}
+
+	let affine = |(x, z): (Num, Num)| {
+		let mut out = x / z;
+		out.full_modular_reduction();
+		out.to_bytes()
+	};
+
+	for i in 0 .. 16u8 {
+		let mut scalar = [i.wrapping_mul(0x3b); 32];
+		scalar[0] = i & 0xf8;
+		scalar[31] = (0xff - i) & 0x7f | 0x40;
+
+		let points = [x25519_derive_pub_key([0x55 ^ i; 32]), SMALL_ORDER_POINTS[i as usize % 5]];
+
+		for point in points {
+			let point = Num::from_bytes(point);
+			// SAFETY: the CPU was just checked to support AVX2
+			let avx2 = unsafe {super::avx2::ladder(&scalar, point, A24)};
+
+			assert_eq!(affine(avx2), affine(ladder_portable(&scalar, point)));
+		}
+	}
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn x25519_hardened_matches_unhardened() {
+	use crate::test_util::CountingRng;
+
+	let mut rng = CountingRng(0);
+
+	// the point of the first test vector from the RFC has a small-order
+	// component, which the blinding must not change the result for
+	let priv_key = [
+		0xa5, 0x46, 0xe3, 0x6b, 0xf0, 0x52, 0x7c, 0x9d,
+		0x3b, 0x16, 0x15, 0x4b, 0x82, 0x46, 0x5e, 0xdd,
+		0x62, 0x14, 0x4c, 0x0a, 0xc1, 0xfc, 0x5a, 0x18,
+		0x50, 0x6a, 0x22, 0x44, 0xba, 0x44, 0x9a, 0xc4,
+	];
+	let pub_key = [
+		0xe6, 0xdb, 0x68, 0x67, 0x58, 0x30, 0x30, 0xdb,
+		0x35, 0x94, 0xc1, 0xa4, 0x24, 0xb1, 0x5f, 0x7c,
+		0x72, 0x66, 0x24, 0xec, 0x26, 0xb3, 0x35, 0x3b,
+		0x10, 0xa9, 0x03, 0xa6, 0xd0, 0xab, 0x1c, 0x4c,
+	];
+	let expected_secret = [
+		0xc3, 0xda, 0x55, 0x37, 0x9d, 0xe9, 0xc6, 0x90,
+		0x8e, 0x94, 0xea, 0x4d, 0xf2, 0x8d, 0x08, 0x4f,
+		0x32, 0xec, 0xcf, 0x03, 0x49, 0x1c, 0x71, 0xf7,
+		0x54, 0xb4, 0x07, 0x55, 0x77, 0xa2, 0x85, 0x52,
+	];
+
+	for _ in 0 .. 4 {
+		assert_eq!(x25519_derive_secret_hardened(priv_key, pub_key, &mut rng), Ok(expected_secret));
+	}
+
+	for i in 0 .. 8u8 {
+		let alice = StaticSecret::from_bytes([i; 32]);
+		let bob_public = PublicKey::from_bytes(x25519_derive_pub_key([!i; 32]));
+
+		let hardened = alice.diffie_hellman_hardened(&bob_public, &mut rng).unwrap();
+		assert_eq!(hardened.to_bytes(), alice.diffie_hellman(&bob_public).unwrap().to_bytes());
+	}
+
+	for pub_key in SMALL_ORDER_POINTS {
+		let result = x25519_derive_secret_hardened(priv_key, pub_key, &mut rng);
+		assert_eq!(result, Err(Error::SmallOrderPoint));
+	}
 }
@@ -0,0 +1,89 @@
+//! performs constant-time modular reduction over the order of the ed448 curve group
+//!
+//! numbers are little-endian arrays of 64-bit limbs, reduced with the generic
+//! Barrett core in [`barrett`](crate::barrett), in the same way as for the
+//! ed25519 group order
+
+use core::convert::TryInto;
+
+use crate::barrett::{BarrettDescriptor, BarrettInt};
+
+struct OrderDescriptor;
+
+impl BarrettDescriptor<7> for OrderDescriptor {
+	// l = 2 ** 446 - 13818066809895115352007386748515426880336692474882178609894547503885
+	const MODULUS: [u64; 7] = [
+		0x2378c292ab5844f3,
+		0x216cc2728dc58f55,
+		0xc44edb49aed63690,
+		0xffffffff7cca23e9,
+		0xffffffffffffffff,
+		0xffffffffffffffff,
+		0x3fffffffffffffff,
+	];
+
+	// floor(2 ** 896 / l)
+	const MU: &'static [u64] = &[
+		0xc873d6d54a7bb0e0,
+		0xe933d8d723a70aad,
+		0xbb124b65129c96fd,
+		0x00000008335dc163,
+		0x0000000000000000,
+		0x0000000000000000,
+		0x0000000000000000,
+		0x0000000000000004,
+	];
+}
+
+type NumModL = BarrettInt<OrderDescriptor, 7>;
+
+// 2 ** 896 mod l, for folding in the last two bytes of 114-byte inputs
+const TWO_896_MOD_L: NumModL = NumModL::from_reduced_limbs([
+	0xe3539257049b9b60,
+	0x7af32c4bc1b195d9,
+	0x0d66de2388ea1859,
+	0xae17cf725ee4d838,
+	0x1a9cc14ba3c47c44,
+	0x2052bcb7e4d070af,
+	0x3402a939f823b729,
+]);
+
+pub fn num_mod_l_from_57_bytes(buf: &[u8; 57]) -> [u64; 7] {
+	NumModL::from_bytes_mod_order(buf).limbs
+}
+
+pub fn num_mod_l_from_114_bytes(buf: &[u8; 114]) -> [u64; 7] {
+	// the bottom 112 bytes fit the reduction directly, and the top two bytes
+	// are folded in afterwards as a multiple of 2 ** 896
+	let low = NumModL::from_bytes_mod_order(&buf[.. 112]);
+	let high = NumModL::from_bytes_mod_order(&buf[112 ..]);
+
+	(low + high * TWO_896_MOD_L).limbs
+}
+
+pub fn num_mod_l_to_bytes(num: [u64; 7]) -> [u8; 57] {
+	let mut out = [0; 57];
+	NumModL::from_reduced_limbs(num).write_bytes(&mut out);
+
+	out
+}
+
+pub fn add_num_mod_l(num_a: [u64; 7], num_b: [u64; 7]) -> [u64; 7] {
+	(NumModL::from_reduced_limbs(num_a) + NumModL::from_reduced_limbs(num_b)).limbs
+}
+
+pub fn mul_num_mod_l(num_a: [u64; 7], num_b: [u64; 7]) -> [u64; 7] {
+	(NumModL::from_reduced_limbs(num_a) * NumModL::from_reduced_limbs(num_b)).limbs
+}
+
+/// Returns whether the 57-byte little-endian number is less than l.
+pub fn verify_less_than_l(value: &[u8; 57]) -> bool {
+	let mut limbs = [0; 7];
+
+	for i in 0 .. 7 {
+		limbs[i] = u64::from_le_bytes(value[8 * i .. 8 * (i + 1)].try_into().unwrap());
+	}
+
+	// l is below 2 ** 448, so the top byte has to be zero
+	value[56] == 0 && bool::from(NumModL::is_canonical(&limbs))
+}
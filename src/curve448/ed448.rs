@@ -0,0 +1,561 @@
+//! Implemented according to [IETF RFC 8032](https://datatracker.ietf.org/doc/html/rfc8032).
+//! Ed448 is a digital signature scheme over the Edwards448 curve, like
+//! [`ed25519`](crate::ed25519) but with a higher security level of about 224
+//! bits, and using SHAKE256 instead of SHA-512.
+//!
+//! Signatures may optionally be bound to a context string of up to 255 bytes
+//! with [`SigningKey::sign_with_context`], which must then be passed to
+//! [`VerifyingKey::verify_with_context`] as well.
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, CtOption};
+use crate::sha3::Shake256;
+use crate::zeroize::zeroize;
+
+use super::arith_mod_l::{
+	add_num_mod_l,
+	mul_num_mod_l,
+	num_mod_l_from_114_bytes,
+	num_mod_l_from_57_bytes,
+	num_mod_l_to_bytes,
+	verify_less_than_l,
+};
+use super::num::Num;
+
+/// An error returned when bytes can't be interpreted as an Ed448 key or
+/// signature, or when signing fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The bytes passed in do not encode a point on the curve.
+	InvalidPublicKey,
+	/// The bytes passed in are not a canonically encoded signature.
+	InvalidSignature,
+	/// The context string is longer than 255 bytes.
+	ContextTooLong,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPublicKey => "invalid ed448 public key",
+			Error::InvalidSignature => "invalid ed448 signature",
+			Error::ContextTooLong => "ed448 context is longer than 255 bytes",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// a point in projective coordinates, (x / z, y / z)
+#[derive(Copy, Clone)]
+struct Point {
+	x: Num,
+	y: Num,
+	z: Num,
+}
+
+// -39081 mod p
+const ED448_D: Num = Num {
+	segments: [
+		0xffffffffff6756,
+		0xffffffffffffff,
+		0xffffffffffffff,
+		0xffffffffffffff,
+		0xfffffffffffffe,
+		0xffffffffffffff,
+		0xffffffffffffff,
+		0xffffffffffffff,
+	],
+};
+
+const BASE_POINT: Point = Point {
+	x: Num {
+		segments: [
+			0x26a82bc70cc05e,
+			0x80e18b00938e26,
+			0xf72ab66511433b,
+			0xa3d3a46412ae1a,
+			0x0f1767ea6de324,
+			0x36da9e14657047,
+			0xed221d15a622bf,
+			0x4f1970c66bed0d,
+		],
+	},
+	y: Num {
+		segments: [
+			0x08795bf230fa14,
+			0x132c4ed7c8ad98,
+			0x1ce67c39c4fdbd,
+			0x05a0c2d73ad3ff,
+			0xa3984087789c1e,
+			0xc7624bea73736c,
+			0x248876203756c9,
+			0x693f46716eb6bc,
+		],
+	},
+	z: Num::ONE,
+};
+
+const NEUTRAL_POINT: Point = Point {
+	x: Num::ZERO,
+	y: Num::ONE,
+	z: Num::ONE,
+};
+
+// the formulas are from section 5.2.4 of the RFC, and are complete on this curve
+fn point_add(point_a: Point, point_b: Point) -> Point {
+	let a = point_a.z * point_b.z;
+	let b = a.square();
+	let c = point_a.x * point_b.x;
+	let d = point_a.y * point_b.y;
+	let e = ED448_D * c * d;
+	let f = b - e;
+	let g = b + e;
+	let h = (point_a.x + point_a.y) * (point_b.x + point_b.y);
+
+	Point {
+		x: a * f * (h - c - d),
+		y: a * g * (d - c),
+		z: f * g,
+	}
+}
+
+fn point_double(point: Point) -> Point {
+	let b = (point.x + point.y).square();
+	let c = point.x.square();
+	let d = point.y.square();
+	let e = c + d;
+	let h = point.z.square();
+	let j = e - h - h;
+
+	Point {
+		x: (b - e) * j,
+		y: e * (c - d),
+		z: e * j,
+	}
+}
+
+impl ConditionallySelectable for Point {
+	fn conditional_select(point_a: &Self, point_b: &Self, choice: Choice) -> Self {
+		Self {
+			x: Num::conditional_select(&point_a.x, &point_b.x, choice),
+			y: Num::conditional_select(&point_a.y, &point_b.y, choice),
+			z: Num::conditional_select(&point_a.z, &point_b.z, choice),
+		}
+	}
+}
+
+// multiplies a point by a 448-bit little-endian scalar in constant time, by
+// always computing both the doubled point and the sum
+fn scalar_mult(scalar: &[u8; 57], point: Point) -> Point {
+	let mut acc = NEUTRAL_POINT;
+
+	for current_bit in (0 .. 448).rev() {
+		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
+		acc = point_double(acc);
+		let sum = point_add(acc, point);
+		acc.conditional_assign(&sum, Choice::from(current_bit));
+	}
+
+	acc
+}
+
+fn compress(point: Point) -> [u8; 57] {
+	let z_recip = point.z.recip();
+	let mut x = point.x * z_recip;
+	let mut y = point.y * z_recip;
+	x.full_modular_reduction();
+	y.full_modular_reduction();
+
+	let mut out = [0; 57];
+	out[.. 56].copy_from_slice(&y.to_bytes());
+	out[56] = ((x.segments[0] & 0x01) as u8) << 7;
+	out
+}
+
+// doesn't work in constant time, since it's only used on public points
+fn decompress(compressed: &[u8; 57]) -> Option<Point> {
+	if compressed[56] & 0x7f != 0 {
+		return None;
+	}
+
+	let x_sign = compressed[56] >> 7;
+	let y_bytes: [u8; 56] = compressed[.. 56].try_into().unwrap();
+
+	let mut y = Num::from_bytes(y_bytes);
+	y.full_modular_reduction();
+
+	// y must be canonically encoded
+	if y.to_bytes() != y_bytes {
+		return None;
+	}
+
+	// x^2 = (y^2 - 1) / (d y^2 - 1), and d isn't a square, so the denominator
+	// is never zero
+	let y_squared = y.square();
+	let u = y_squared - Num::ONE;
+	let v = ED448_D * y_squared - Num::ONE;
+
+	let mut x = (u / v).pow_p14();
+	let is_square = (v * x.square() - u).is_zero();
+
+	x.full_modular_reduction();
+
+	// x = 0 can't take on the sign bit
+	let x_sign = Choice::from(x_sign);
+	let is_valid = is_square & !(x.is_zero() & x_sign);
+
+	let wrong_sign = Choice::from((x.segments[0] & 0x01) as u8) ^ x_sign;
+	let x = Num::conditional_select(&x, &-x, wrong_sign);
+
+	CtOption::new(Point {x, y, z: Num::ONE}, is_valid).into()
+}
+
+fn points_equal(point_a: Point, point_b: Point) -> Choice {
+	(point_a.x * point_b.z - point_b.x * point_a.z).is_zero() &
+	(point_a.y * point_b.z - point_b.y * point_a.z).is_zero()
+}
+
+// returns the clamped secret scalar and the prefix used to generate nonces
+fn expand_priv_key(seed: &[u8; 57]) -> ([u8; 57], [u8; 57]) {
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(seed);
+	let mut digest: [u8; 114] = hasher.out();
+
+	let mut secret_scalar: [u8; 57] = digest[.. 57].try_into().unwrap();
+	secret_scalar[0] &= 0xfc;
+	secret_scalar[55] |= 0x80;
+	secret_scalar[56] = 0;
+
+	let prefix = digest[57 ..].try_into().unwrap();
+	zeroize(&mut digest);
+
+	(secret_scalar, prefix)
+}
+
+// starts a hash with dom4(0, context) from the RFC
+fn hasher_with_context(context: &[u8]) -> Shake256 {
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(b"SigEd448");
+	hasher.add_bytes(&[0, context.len() as u8]);
+	hasher.add_bytes(context);
+	hasher
+}
+
+/// An Ed448 private key, used to create signatures. Any 57 bytes are a valid
+/// private key, so these should be generated by a secure random source.
+#[derive(Clone)]
+pub struct SigningKey {
+	seed: [u8; 57],
+	secret_scalar: [u8; 57],
+	prefix: [u8; 57],
+	verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+	/// Creates a signing key from its 57-byte representation.
+	pub fn from_bytes(seed: [u8; 57]) -> Self {
+		let (secret_scalar, prefix) = expand_priv_key(&seed);
+		let point = scalar_mult(&secret_scalar, BASE_POINT);
+
+		let verifying_key = VerifyingKey {
+			compressed: compress(point),
+			point,
+		};
+
+		Self {seed, secret_scalar, prefix, verifying_key}
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 57];
+		rng.fill_bytes(&mut seed);
+
+		let signing_key = Self::from_bytes(seed);
+		zeroize(&mut seed);
+
+		signing_key
+	}
+
+	/// Returns the 57-byte representation of this signing key. This must be
+	/// kept secret.
+	pub fn to_bytes(&self) -> [u8; 57] {
+		self.seed
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+
+	/// Signs a `message` using Ed448 with an empty context.
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		self.sign_with_context(message, b"").unwrap()
+	}
+
+	/// Signs a `message` using Ed448, binding the signature to `context`,
+	/// which must be at most 255 bytes long.
+	pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Result<Signature, Error> {
+		if context.len() > 255 {
+			return Err(Error::ContextTooLong);
+		}
+
+		let mut hasher = hasher_with_context(context);
+		hasher.add_bytes(&self.prefix);
+		hasher.add_bytes(message);
+		let mut nonce_digest: [u8; 114] = hasher.out();
+		let nonce = num_mod_l_from_114_bytes(&nonce_digest);
+		zeroize(&mut nonce_digest);
+
+		let mut nonce_bytes = num_mod_l_to_bytes(nonce);
+		let nonce_point = compress(scalar_mult(&nonce_bytes, BASE_POINT));
+		zeroize(&mut nonce_bytes);
+
+		let mut hasher = hasher_with_context(context);
+		hasher.add_bytes(&nonce_point);
+		hasher.add_bytes(&self.verifying_key.compressed);
+		hasher.add_bytes(message);
+		let challenge = num_mod_l_from_114_bytes(&hasher.out());
+
+		let secret_scalar = num_mod_l_from_57_bytes(&self.secret_scalar);
+		let response = add_num_mod_l(nonce, mul_num_mod_l(challenge, secret_scalar));
+
+		let mut signature = [0; 114];
+		signature[.. 57].copy_from_slice(&nonce_point);
+		signature[57 ..].copy_from_slice(&num_mod_l_to_bytes(response));
+
+		Ok(Signature(signature))
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+		zeroize(&mut self.prefix);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.seed);
+		zeroize(&mut self.secret_scalar);
+		zeroize(&mut self.prefix);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey")
+			.field("verifying_key", &self.verifying_key)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(SigningKey, 57, Hex, |key| key.to_bytes(), |bytes| SigningKey::from_bytes(bytes));
+
+/// An Ed448 public key, used to verify signatures. Constructing one checks
+/// that the key is a valid point on the curve.
+#[derive(Copy, Clone)]
+pub struct VerifyingKey {
+	compressed: [u8; 57],
+	point: Point,
+}
+
+impl VerifyingKey {
+	/// Creates a verifying key from its 57-byte representation, returning an
+	/// error if the bytes do not encode a point on the curve.
+	pub fn from_bytes(bytes: [u8; 57]) -> Result<Self, Error> {
+		match decompress(&bytes) {
+			Some(point) => Ok(Self {compressed: bytes, point}),
+			None => Err(Error::InvalidPublicKey),
+		}
+	}
+
+	/// Returns the 57-byte representation of this verifying key.
+	pub fn to_bytes(&self) -> [u8; 57] {
+		self.compressed
+	}
+
+	/// Returns a reference to the 57-byte representation of this verifying key.
+	pub fn as_bytes(&self) -> &[u8; 57] {
+		&self.compressed
+	}
+
+	/// Verifies an Ed448 `signature` over a `message` with an empty context.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		self.verify_with_context(message, b"", signature)
+	}
+
+	/// Verifies an Ed448 `signature` over a `message` that was bound to
+	/// `context` when signing. Returns false if the context is longer than
+	/// 255 bytes.
+	pub fn verify_with_context(&self, message: &[u8], context: &[u8], signature: &Signature) -> bool {
+		if context.len() > 255 {
+			return false;
+		}
+
+		let nonce_point_bytes: &[u8; 57] = signature.0[.. 57].try_into().unwrap();
+		let response: &[u8; 57] = signature.0[57 ..].try_into().unwrap();
+
+		let nonce_point = match decompress(nonce_point_bytes) {
+			Some(point) => point,
+			None => return false,
+		};
+
+		let mut hasher = hasher_with_context(context);
+		hasher.add_bytes(nonce_point_bytes);
+		hasher.add_bytes(&self.compressed);
+		hasher.add_bytes(message);
+		let challenge = num_mod_l_to_bytes(num_mod_l_from_114_bytes(&hasher.out()));
+
+		// checks [4][S]B = [4]R + [4][k]A, as the RFC requires
+		let lhs = scalar_mult(response, BASE_POINT);
+		let rhs = point_add(nonce_point, scalar_mult(&challenge, self.point));
+
+		points_equal(
+			point_double(point_double(lhs)),
+			point_double(point_double(rhs)),
+		).into()
+	}
+}
+
+impl PartialEq for VerifyingKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.compressed == other.compressed
+	}
+}
+
+impl Eq for VerifyingKey {}
+
+impl fmt::Debug for VerifyingKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("VerifyingKey").field(&self.compressed).finish()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(VerifyingKey, 57, Hex, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(bytes));
+
+text_impl!(VerifyingKey, 57, Hex, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(bytes));
+
+/// An Ed448 signature. Constructing one checks that the scalar half of the
+/// signature is canonically encoded; the point half is checked on verification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature([u8; 114]);
+
+impl Signature {
+	/// Creates a signature from its 114-byte representation, returning an
+	/// error if it is not canonically encoded.
+	pub fn from_bytes(bytes: [u8; 114]) -> Result<Self, Error> {
+		if !verify_less_than_l(bytes[57 ..].try_into().unwrap()) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Returns the 114-byte representation of this signature.
+	pub fn to_bytes(&self) -> [u8; 114] {
+		self.0
+	}
+
+	/// Returns a reference to the 114-byte representation of this signature.
+	pub fn as_bytes(&self) -> &[u8; 114] {
+		&self.0
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Signature, 114, Hex, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(bytes));
+
+text_impl!(Signature, 114, Hex, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(bytes));
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 7.4 of RFC 8032
+#[test]
+fn test_rfc8032_blank() {
+	let signing_key = SigningKey::from_bytes(hex(concat!(
+		"6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3",
+		"528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b",
+	)));
+
+	assert_eq!(signing_key.verifying_key().to_bytes(), hex(concat!(
+		"5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778",
+		"edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180",
+	)));
+
+	let signature = signing_key.sign(b"");
+
+	assert_eq!(signature.to_bytes(), hex(concat!(
+		"533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f",
+		"2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a",
+		"9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4db",
+		"b61149f05a7363268c71d95808ff2e652600",
+	)));
+
+	assert!(signing_key.verifying_key().verify(b"", &signature));
+}
+
+// the "1 octet" and "1 octet (with context)" vectors from section 7.4 of RFC 8032
+#[test]
+fn test_sign_and_verify() {
+	let signing_key = SigningKey::from_bytes(hex(concat!(
+		"c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463a",
+		"fbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e",
+	)));
+	let verifying_key = VerifyingKey::from_bytes(signing_key.verifying_key().to_bytes()).unwrap();
+
+	assert_eq!(verifying_key.to_bytes(), hex(concat!(
+		"43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c086",
+		"6aea01eb00742802b8438ea4cb82169c235160627b4c3a9480",
+	)));
+
+	let signature = signing_key.sign(b"\x03");
+
+	assert_eq!(signature.to_bytes(), hex(concat!(
+		"26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f435",
+		"2541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cb",
+		"cee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0f",
+		"f3348ab21aa4adafd1d234441cf807c03a00",
+	)));
+
+	let with_context = signing_key.sign_with_context(b"\x03", b"foo").unwrap();
+
+	assert_eq!(with_context.to_bytes(), hex(concat!(
+		"d4f8f6131770dd46f40867d6fd5d5055de43541f8c5e35abbcd001b32a89f7d2",
+		"151f7647f11d8ca2ae279fb842d607217fce6e042f6815ea000c85741de5c8da",
+		"1144a6a1aba7f96de42505d7a7298524fda538fccbbb754f578c1cad10d54d0d",
+		"5428407e85dcbc98a49155c13764e66c3c00",
+	)));
+
+	assert!(verifying_key.verify(b"\x03", &signature));
+	assert!(!verifying_key.verify(b"\x04", &signature));
+	assert!(verifying_key.verify_with_context(b"\x03", b"foo", &with_context));
+	assert!(!verifying_key.verify(b"\x03", &with_context));
+
+	let mut tampered = signature.to_bytes();
+	tampered[3] ^= 0x01;
+	assert!(!verifying_key.verify(b"\x03", &Signature::from_bytes(tampered).unwrap()));
+
+	assert_eq!(signing_key.sign_with_context(b"", &[0; 256]).unwrap_err(), Error::ContextTooLong);
+	assert_eq!(Signature::from_bytes([0xff; 114]).unwrap_err(), Error::InvalidSignature);
+	assert_eq!(VerifyingKey::from_bytes([0xff; 57]).unwrap_err(), Error::InvalidPublicKey);
+}
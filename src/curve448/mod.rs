@@ -0,0 +1,10 @@
+#[cfg(feature = "ed448")]
+pub mod ed448;
+#[cfg(feature = "ed448")]
+pub mod vdf;
+#[cfg(feature = "x448")]
+pub mod x448;
+
+#[cfg(feature = "ed448")]
+mod arith_mod_l;
+mod num;
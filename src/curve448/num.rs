@@ -0,0 +1,317 @@
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+// numbers mod p = 2 ** 448 - 2 ** 224 - 1 are stored as 8 segments of 56 bits,
+// which may grow a little past 56 bits between full reductions
+const SEGMENT_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+
+// 4 * p, added before subtracting so that no segment goes negative
+const FOUR_P: [u64; 8] = [
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fff8,
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fffc,
+	0x03ff_ffff_ffff_fffc,
+];
+
+#[derive(Copy, Clone)]
+pub struct Num {
+	pub segments: [u64; 8],
+}
+
+// carries each segment into the next, wrapping the carry out of the top
+// segment around as 2 ** 448 = 2 ** 224 + 1
+fn carry_propagate(segments: &mut [u64; 8]) {
+	for i in 0 .. 7 {
+		segments[i + 1] += segments[i] >> 56;
+		segments[i] &= SEGMENT_MASK;
+	}
+
+	let carry = segments[7] >> 56;
+	segments[7] &= SEGMENT_MASK;
+	segments[0] += carry;
+	segments[4] += carry;
+}
+
+impl Num {
+	pub const ZERO: Self = Self {segments: [0; 8]};
+	pub const ONE: Self = Self {segments: [1, 0, 0, 0, 0, 0, 0, 0]};
+
+	#[cfg(any(feature = "x448", test))]
+	pub const fn from_u64(val: u64) -> Self {
+		Self {segments: [val, 0, 0, 0, 0, 0, 0, 0]}
+	}
+
+	pub fn square(self) -> Self {
+		self * self
+	}
+
+	// squares n times, multiplying by factor after each squaring, which
+	// appends n one bits to the exponent when factor is the base
+	fn square_and_multiply(mut self, n: usize, factor: Self) -> Self {
+		for _ in 0 .. n {
+			self = self.square() * factor;
+		}
+
+		self
+	}
+
+	#[cfg(feature = "ed448")]
+	fn square_n(mut self, n: usize) -> Self {
+		for _ in 0 .. n {
+			self = self.square();
+		}
+
+		self
+	}
+
+	/// Raises this number to the power of p - 2, its reciprocal.
+	pub fn recip(self) -> Self {
+		// p - 2 = 2 ** 448 - 2 ** 224 - 3, which in binary is 223 ones, a
+		// zero, 222 ones, a zero and a one
+		let acc = Num::ONE.square_and_multiply(223, self);
+		let acc = acc.square();
+		let acc = acc.square_and_multiply(222, self);
+		let acc = acc.square();
+		acc.square() * self
+	}
+
+	/// Raises this number to the power of (p + 1) / 4, which is a square root
+	/// of it if it's a square.
+	#[cfg(feature = "ed448")]
+	pub fn pow_p14(self) -> Self {
+		// (p + 1) / 4 = 2 ** 446 - 2 ** 222, which in binary is 224 ones
+		// followed by 222 zeros
+		Num::ONE.square_and_multiply(224, self).square_n(222)
+	}
+
+	/// Reduces the number so that it's guaranteed to be below p, with every
+	/// segment below 2 ** 56.
+	pub fn full_modular_reduction(&mut self) {
+		// three passes leave every segment below 2 ** 56 and the value below
+		// 2 ** 448, since each pass shrinks the carry out of the top
+		for _ in 0 .. 3 {
+			carry_propagate(&mut self.segments);
+		}
+
+		// adding 2 ** 448 - p = 2 ** 224 + 1 carries out of the top exactly
+		// when the value is at least p, in which case the sum is the result
+		let mut sum = self.segments;
+		sum[0] += 1;
+		sum[4] += 1;
+
+		for i in 0 .. 7 {
+			sum[i + 1] += sum[i] >> 56;
+			sum[i] &= SEGMENT_MASK;
+		}
+
+		let keep_sum = Choice::from((sum[7] >> 56) as u8);
+		sum[7] &= SEGMENT_MASK;
+
+		self.conditional_assign(&Self {segments: sum}, keep_sum);
+	}
+
+	pub fn from_bytes(bytes: [u8; 56]) -> Self {
+		let mut segments = [0; 8];
+
+		for i in 0 .. 8 {
+			let mut buf = [0; 8];
+			buf[.. 7].copy_from_slice(&bytes[7 * i .. 7 * (i + 1)]);
+			segments[i] = u64::from_le_bytes(buf);
+		}
+
+		Self {segments}
+	}
+
+	/// Returns the little-endian encoding of this number, which must already
+	/// be fully reduced.
+	pub fn to_bytes(self) -> [u8; 56] {
+		let mut out = [0; 56];
+
+		for i in 0 .. 8 {
+			out[7 * i .. 7 * (i + 1)].copy_from_slice(&self.segments[i].to_le_bytes()[.. 7]);
+		}
+
+		out
+	}
+
+	/// Returns whether the number is zero mod p. Works in constant time.
+	#[cfg(feature = "ed448")]
+	pub fn is_zero(self) -> Choice {
+		self.ct_eq(&Num::ZERO)
+	}
+}
+
+impl ConstantTimeEq for Num {
+	/// Returns whether the two numbers are equal mod p, fully reducing copies
+	/// of both first.
+	fn ct_eq(&self, other: &Self) -> Choice {
+		let mut num_a = *self;
+		let mut num_b = *other;
+		num_a.full_modular_reduction();
+		num_b.full_modular_reduction();
+
+		let mut diff = 0;
+
+		for i in 0 .. 8 {
+			diff |= num_a.segments[i] ^ num_b.segments[i];
+		}
+
+		// reduced segments fit in 56 bits, so this carries out unless diff is 0
+		Choice::from(1 - ((diff + SEGMENT_MASK) >> 56) as u8)
+	}
+}
+
+impl ConditionallySelectable for Num {
+	fn conditional_select(num_a: &Self, num_b: &Self, choice: Choice) -> Self {
+		let mut out = *num_a;
+		out.conditional_assign(num_b, choice);
+		out
+	}
+
+	fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+		let mask = 0u64.wrapping_sub(choice.unwrap_u8() as u64);
+
+		for i in 0 .. 8 {
+			self.segments[i] ^= mask & (self.segments[i] ^ other.segments[i]);
+		}
+	}
+
+	fn conditional_swap(num_a: &mut Self, num_b: &mut Self, choice: Choice) {
+		let mask = 0u64.wrapping_sub(choice.unwrap_u8() as u64);
+
+		for i in 0 .. 8 {
+			let temp = mask & (num_a.segments[i] ^ num_b.segments[i]);
+			num_a.segments[i] ^= temp;
+			num_b.segments[i] ^= temp;
+		}
+	}
+}
+
+impl Add for Num {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		let mut segments = [0; 8];
+
+		for i in 0 .. 8 {
+			segments[i] = self.segments[i] + other.segments[i];
+		}
+
+		carry_propagate(&mut segments);
+
+		Self {segments}
+	}
+}
+
+impl Sub for Num {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		let mut segments = [0; 8];
+
+		for i in 0 .. 8 {
+			segments[i] = self.segments[i] + FOUR_P[i] - other.segments[i];
+		}
+
+		carry_propagate(&mut segments);
+
+		Self {segments}
+	}
+}
+
+impl Neg for Num {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Num::ZERO - self
+	}
+}
+
+impl Mul for Num {
+	type Output = Self;
+
+	fn mul(self, other: Self) -> Self {
+		let a = self.segments;
+		let b = other.segments;
+
+		let mut product = [0u128; 15];
+
+		for i in 0 .. 8 {
+			for j in 0 .. 8 {
+				product[i + j] += a[i] as u128 * b[j] as u128;
+			}
+		}
+
+		// fold the top half back down using 2 ** 448 = 2 ** 224 + 1, from the
+		// top so that segments folded into the top half are folded again
+		for i in (8 .. 15).rev() {
+			product[i - 4] += product[i];
+			product[i - 8] += product[i];
+		}
+
+		let mut wide = [0u128; 8];
+		wide.copy_from_slice(&product[.. 8]);
+
+		for _ in 0 .. 2 {
+			for i in 0 .. 7 {
+				wide[i + 1] += wide[i] >> 56;
+				wide[i] &= SEGMENT_MASK as u128;
+			}
+
+			let carry = wide[7] >> 56;
+			wide[7] &= SEGMENT_MASK as u128;
+			wide[0] += carry;
+			wide[4] += carry;
+		}
+
+		let mut segments = [0; 8];
+
+		for i in 0 .. 8 {
+			segments[i] = wide[i] as u64;
+		}
+
+		Self {segments}
+	}
+}
+
+impl Div for Num {
+	type Output = Self;
+
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn div(self, other: Self) -> Self {
+		self * other.recip()
+	}
+}
+
+#[test]
+#[cfg(feature = "ed448")]
+fn test_recip_and_sqrt() {
+	for i in 1 .. 50 {
+		let num = Num::from_u64(i * 0x1234_5678_9abc);
+		let mut res = num.recip() * num;
+		res.full_modular_reduction();
+		assert!(res.segments == Num::ONE.segments);
+
+		let square = num.square();
+		let mut root = square.pow_p14();
+		root.full_modular_reduction();
+		let mut negated = -num;
+		negated.full_modular_reduction();
+		let mut num = num;
+		num.full_modular_reduction();
+		assert!(root.segments == num.segments || root.segments == negated.segments);
+	}
+
+	// p itself reduces to zero, and p - 1 doesn't
+	let mut p = Num::from_bytes([0xff; 56]);
+	p.segments[4] -= 1;
+	assert!(bool::from(p.is_zero()));
+
+	p.segments[0] -= 1;
+	assert!(!bool::from(p.is_zero()));
+}
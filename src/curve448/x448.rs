@@ -0,0 +1,387 @@
+//! Implemented according to [IETF RFC 7748](https://datatracker.ietf.org/doc/html/rfc7748).
+//! X448 is Diffie-Hellman key exchange over Curve448, like
+//! [`x25519`](crate::x25519) but with a higher security level of about 224
+//! bits, at the cost of larger keys and slower key exchanges.
+//!
+//! [`StaticSecret`], [`EphemeralSecret`] and [`PublicKey`] wrap the raw
+//! functions in this module, zeroizing secrets when they're dropped and
+//! ensuring ephemeral secrets are only used for a single key exchange.
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+use crate::zeroize::zeroize;
+
+use super::num::Num;
+
+/// An error returned by the checked X448 functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The other party's public key is a point of small order, so the shared
+	/// secret is all zeros and doesn't depend on your private key.
+	SmallOrderPoint,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::SmallOrderPoint => "x448 public key is a small-order point",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+const BASE: Num = Num::from_u64(5);
+const A24: Num = Num::from_u64(39081);
+
+fn x448_mult(mut scalar: [u8; 56], point: Num) -> Num {
+	// clamp the scalar as specified in the RFC
+	scalar[0] &= 0xfc;
+	scalar[55] |= 0x80;
+
+	let x1 = point;
+	let mut x2 = Num::ONE;
+	let mut z2 = Num::ZERO;
+	let mut x3 = point;
+	let mut z3 = Num::ONE;
+
+	let mut swapped = 0;
+
+	for current_bit in (0 .. 448).rev() {
+		let current_bit = (scalar[current_bit / 8] >> (current_bit % 8)) & 0x01;
+		let do_swap = Choice::from(swapped ^ current_bit);
+		Num::conditional_swap(&mut x2, &mut x3, do_swap);
+		Num::conditional_swap(&mut z2, &mut z3, do_swap);
+		swapped = current_bit;
+
+		let a = x2 + z2;
+		let aa = a * a;
+		let b = x2 - z2;
+		let bb = b * b;
+		let e = aa - bb;
+		let c = x3 + z3;
+		let d = x3 - z3;
+		let da = d * a;
+		let cb = c * b;
+		let dapcb = da + cb;
+		let damcb = da - cb;
+		x3 = dapcb * dapcb;
+		z3 = x1 * damcb * damcb;
+		x2 = aa * bb;
+		z2 = e * (aa + A24 * e);
+	}
+
+	let swapped = Choice::from(swapped);
+	Num::conditional_swap(&mut x2, &mut x3, swapped);
+	Num::conditional_swap(&mut z2, &mut z3, swapped);
+
+	zeroize(&mut scalar);
+
+	let mut out = x2 / z2;
+	out.full_modular_reduction();
+	out
+}
+
+/// Given your private key (`priv_key`), returns your public key. This public
+/// key may be used by any other party to compute a shared secret using
+/// [`x448_derive_secret`] or another implementation of X448.
+pub fn x448_derive_pub_key(priv_key: [u8; 56]) -> [u8; 56] {
+	x448_mult(priv_key, BASE).to_bytes()
+}
+
+/// Given your private key (`priv_key`) and another party's public key
+/// (`pub_key`), returns a shared secret that is computable by both you and
+/// the other party. This shared secret is suitable to be used with a KDF to
+/// derive keys. The other party may maliciously choose their public key so
+/// that the shared secret is all zeros; [`x448_derive_secret_checked`]
+/// returns an error instead.
+pub fn x448_derive_secret(priv_key: [u8; 56], pub_key: [u8; 56]) -> [u8; 56] {
+	x448_mult(priv_key, Num::from_bytes(pub_key)).to_bytes()
+}
+
+/// Like [`x448_derive_secret`], but returns [`Error::SmallOrderPoint`]
+/// instead of a shared secret that is all zeros. This is the recommended way
+/// to compute a shared secret.
+pub fn x448_derive_secret_checked(priv_key: [u8; 56], pub_key: [u8; 56]) -> Result<[u8; 56], Error> {
+	let secret = x448_derive_secret(priv_key, pub_key);
+
+	if is_shared_secret_all_zero(&secret) {
+		return Err(Error::SmallOrderPoint);
+	}
+
+	Ok(secret)
+}
+
+/// Determines whether the shared secret is all zeros, in constant time.
+pub fn is_shared_secret_all_zero(secret: &[u8; 56]) -> bool {
+	secret.ct_eq(&[0; 56]).into()
+}
+
+/// An X448 public key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey([u8; 56]);
+
+impl PublicKey {
+	/// Creates a public key from its 56-byte representation. Any 56 bytes
+	/// are accepted, since X448 is defined for all inputs.
+	pub fn from_bytes(bytes: [u8; 56]) -> Self {
+		Self(bytes)
+	}
+
+	/// Returns the 56-byte representation of this public key.
+	pub fn to_bytes(&self) -> [u8; 56] {
+		self.0
+	}
+
+	/// Returns a reference to the 56-byte representation of this public key.
+	pub fn as_bytes(&self) -> &[u8; 56] {
+		&self.0
+	}
+}
+
+impl From<[u8; 56]> for PublicKey {
+	fn from(bytes: [u8; 56]) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<&StaticSecret> for PublicKey {
+	fn from(secret: &StaticSecret) -> Self {
+		Self(x448_derive_pub_key(secret.0))
+	}
+}
+
+impl From<&EphemeralSecret> for PublicKey {
+	fn from(secret: &EphemeralSecret) -> Self {
+		Self(x448_derive_pub_key(secret.0))
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(PublicKey, 56, Hex, |key| key.to_bytes(), |bytes| PublicKey::from_bytes(bytes));
+
+text_impl!(PublicKey, 56, Hex, |key| key.to_bytes(), |bytes| PublicKey::from_bytes(bytes));
+
+/// An X448 private key that can be used for any number of key exchanges.
+/// Zeroized when dropped.
+#[derive(Clone)]
+pub struct StaticSecret([u8; 56]);
+
+impl StaticSecret {
+	/// Creates a private key from its 56-byte representation. Any 56 bytes
+	/// are a valid private key, so these should be generated by a secure
+	/// random source.
+	pub fn from_bytes(bytes: [u8; 56]) -> Self {
+		Self(bytes)
+	}
+
+	/// Generates a new private key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut bytes = [0; 56];
+		rng.fill_bytes(&mut bytes);
+
+		Self(bytes)
+	}
+
+	/// Returns the 56-byte representation of this private key. This must be
+	/// kept secret.
+	pub fn to_bytes(&self) -> [u8; 56] {
+		self.0
+	}
+
+	/// Computes the secret shared with the owner of `their_public`. See
+	/// [`x448_derive_secret_checked`].
+	pub fn diffie_hellman(&self, their_public: &PublicKey) -> Result<SharedSecret, Error> {
+		x448_derive_secret_checked(self.0, their_public.0).map(SharedSecret)
+	}
+}
+
+impl Drop for StaticSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for StaticSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for StaticSecret {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for StaticSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("StaticSecret")
+			.field("public_key", &PublicKey::from(self))
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(StaticSecret, 56, Hex, |key| key.to_bytes(), |bytes| StaticSecret::from_bytes(bytes));
+
+/// An X448 private key that can only be used for a single key exchange,
+/// since [`EphemeralSecret::diffie_hellman`] consumes it. Zeroized when
+/// dropped.
+pub struct EphemeralSecret([u8; 56]);
+
+impl EphemeralSecret {
+	/// Creates an ephemeral private key from 56 bytes, which must be freshly
+	/// generated by a secure random source.
+	pub fn from_bytes(bytes: [u8; 56]) -> Self {
+		Self(bytes)
+	}
+
+	/// Generates a new ephemeral private key using a cryptographically secure
+	/// random number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut bytes = [0; 56];
+		rng.fill_bytes(&mut bytes);
+
+		Self(bytes)
+	}
+
+	/// Computes the secret shared with the owner of `their_public`, consuming
+	/// this private key. See [`x448_derive_secret_checked`].
+	pub fn diffie_hellman(self, their_public: &PublicKey) -> Result<SharedSecret, Error> {
+		x448_derive_secret_checked(self.0, their_public.0).map(SharedSecret)
+	}
+}
+
+impl Drop for EphemeralSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for EphemeralSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for EphemeralSecret {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for EphemeralSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EphemeralSecret").finish_non_exhaustive()
+	}
+}
+
+/// The result of an X448 key exchange, which is never all zeros. Zeroized
+/// when dropped.
+pub struct SharedSecret([u8; 56]);
+
+impl SharedSecret {
+	/// Returns the 56 bytes of this shared secret, which should be passed
+	/// through a KDF before being used as a key.
+	pub fn to_bytes(&self) -> [u8; 56] {
+		self.0
+	}
+
+	/// Returns a reference to the 56 bytes of this shared secret.
+	pub fn as_bytes(&self) -> &[u8; 56] {
+		&self.0
+	}
+}
+
+impl Drop for SharedSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SharedSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SharedSecret {}
+
+// deliberately doesn't print the shared secret
+impl fmt::Debug for SharedSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SharedSecret").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 5.2 of RFC 7748
+#[test]
+fn test_vectors() {
+	let scalar = hex("3d262fddf9ec8e88495266fea19a34d28882acef045104d0d1aae121700a779c984c24f8cdd78fbff44943eba368f54b29259a4f1c600ad3");
+	let coordinate = hex("06fce640fa3487bfda5f6cf2d5263f8aad88334cbd07437f020f08f9814dc031ddbdc38c19c6da2583fa5429db94ada18aa7a7fb4ef8a086");
+	let expected = hex("ce3e4ff95a60dc6697da1db1d85e6afbdf79b50a2412d7546d5f239fe14fbaadeb445fc66a01b0779d98223961111e21766282f73dd96b6f");
+	assert_eq!(x448_derive_secret(scalar, coordinate), expected);
+
+	let scalar = hex("203d494428b8399352665ddca42f9de8fef600908e0d461cb021f8c538345dd77c3e4806e25f46d3315c44e0a5b4371282dd2c8d5be3095f");
+	let coordinate = hex("0fbcc2f993cd56d3305b0b7d9e55d4c1a8fb5dbb52f8e9a1e9b6201b165d015894e56c4d3570bee52fe205e28a78b91cdfbde71ce8d157db");
+	let expected = hex("884a02576239ff7a2f2f63b2db6a9ff37047ac13568e1e30fe63c4a7ad1b3ee3a5700df34321d62077e63633c575c1c954514e99da7c179d");
+	assert_eq!(x448_derive_secret(scalar, coordinate), expected);
+}
+
+// from section 6.2 of RFC 7748
+#[test]
+fn test_key_exchange() {
+	let priv_a = hex("9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28dd9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b");
+	let priv_b = hex("1c306a7ac2a0e2e0990b294470cba339e6453772b075811d8fad0d1d6927c120bb5ee8972b0d3e21374c9c921b09d1b0366f10b65173992d");
+
+	let pub_a = x448_derive_pub_key(priv_a);
+	let pub_b = x448_derive_pub_key(priv_b);
+	assert_eq!(pub_a, hex("9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0"));
+	assert_eq!(pub_b, hex("3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b43027d8b972fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609"));
+
+	let expected = hex("07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282bb60c0b56fd2464c335543936521c24403085d59a449a5037514a879d");
+	assert_eq!(x448_derive_secret(priv_a, pub_b), expected);
+
+	let secret = StaticSecret::from_bytes(priv_b).diffie_hellman(&PublicKey::from_bytes(pub_a)).unwrap();
+	assert_eq!(secret.to_bytes(), expected);
+
+	assert_eq!(x448_derive_secret_checked(priv_a, [0; 56]).unwrap_err(), Error::SmallOrderPoint);
+}
+
+// from section 5.2 of RFC 7748
+#[test]
+fn test_iterated() {
+	let mut scalar = [0; 56];
+	scalar[0] = 5;
+	let mut coordinate = scalar;
+
+	fn one_step(scalar: &mut [u8; 56], coordinate: &mut [u8; 56]) {
+		let out = x448_derive_secret(*scalar, *coordinate);
+
+		*coordinate = *scalar;
+		*scalar = out;
+	}
+
+	one_step(&mut scalar, &mut coordinate);
+	assert_eq!(scalar, hex("3f482c8a9f19b01e6c46ee9711d9dc14fd4bf67af30765c2ae2b846a4d23a8cd0db897086239492caf350b51f833868b9bc2b3bca9cf4113"));
+
+	for _ in 1 .. 1000 {
+		one_step(&mut scalar, &mut coordinate);
+	}
+
+	assert_eq!(scalar, hex("aa3b4749d55b9daf1e5b00288826c467274ce3ebbdd5c17b975e09d4af6c67cf10d087202db88286e2b79fceea3ec353ef54faa26e219f38"));
+}
@@ -0,0 +1,494 @@
+//! A minimal reader and writer for the Distinguished Encoding Rules of ASN.1,
+//! as specified in [ITU-T X.690](https://www.itu.int/rec/T-REC-X.690), which
+//! is enough for key formats, signatures and certificates. Only definite
+//! lengths of up to four bytes and single-byte tags are supported, and the
+//! reader rejects anything that isn't the unique DER encoding of its value.
+//!
+//! ```
+//! use libkrypton::der::{Reader, Writer};
+//!
+//! let mut writer = Writer::new();
+//! writer.write_sequence(|writer| {
+//!     writer.write_u64(1);
+//!     writer.write_octet_string(b"key");
+//! });
+//!
+//! let encoded = writer.into_bytes();
+//! let mut sequence = Reader::new(&encoded).read_sequence().unwrap();
+//!
+//! assert_eq!(sequence.read_u64(), Ok(1));
+//! assert_eq!(sequence.read_octet_string(), Ok(&b"key"[..]));
+//! assert!(sequence.finish().is_ok());
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+/// Returns the tag of the context-specific field `[number]`, which is
+/// constructed if it's explicitly tagged or wraps a constructed type, like in
+/// `[0] EXPLICIT`, and primitive otherwise.
+///
+/// # Panics
+/// * Panics if `number` is 31 or more, since those need multi-byte tags.
+pub const fn context_tag(number: u8, constructed: bool) -> u8 {
+	assert!(number < 31);
+
+	0x80 | (constructed as u8) << 5 | number
+}
+
+/// An error returned when reading DER.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The input isn't valid DER, or ends early.
+	InvalidEncoding,
+	/// A value has a different tag than the one expected.
+	UnexpectedTag,
+	/// There's data after the last expected value.
+	TrailingData,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid der encoding",
+			Error::UnexpectedTag => "unexpected der tag",
+			Error::TrailingData => "trailing data after der value",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Reads DER-encoded values from a byte slice, one at a time.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	/// Creates a reader over the concatenated encodings in `bytes`.
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self {bytes}
+	}
+
+	/// Returns whether every value has been read.
+	pub fn is_empty(&self) -> bool {
+		self.bytes.len() == 0
+	}
+
+	/// Returns [`Error::TrailingData`] unless every value has been read.
+	pub fn finish(&self) -> Result<(), Error> {
+		if !self.is_empty() {
+			return Err(Error::TrailingData);
+		}
+
+		Ok(())
+	}
+
+	/// Returns the tag of the next value without reading it.
+	pub fn peek_tag(&self) -> Option<u8> {
+		self.bytes.first().copied()
+	}
+
+	// splits off the next value, returning its tag, its contents and its
+	// whole encoding
+	fn next(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), Error> {
+		let all = self.bytes;
+
+		let (&tag, rest) = all.split_first().ok_or(Error::InvalidEncoding)?;
+		let (&first_len_byte, mut rest) = rest.split_first().ok_or(Error::InvalidEncoding)?;
+
+		// tags of 31 and up are followed by more tag bytes
+		if tag & 0x1f == 0x1f {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let len = match first_len_byte {
+			0x00 ..= 0x7f => first_len_byte as usize,
+
+			// DER requires the shortest length encoding, so long form
+			// lengths need to be at least 128 and can't have leading zeros
+			0x81 ..= 0x84 => {
+				let num_len_bytes = (first_len_byte & 0x7f) as usize;
+
+				if rest.len() < num_len_bytes || rest[0] == 0 {
+					return Err(Error::InvalidEncoding);
+				}
+
+				let mut len = 0usize;
+
+				for &byte in &rest[.. num_len_bytes] {
+					len = len.checked_mul(256).ok_or(Error::InvalidEncoding)? | byte as usize;
+				}
+
+				if len < 0x80 {
+					return Err(Error::InvalidEncoding);
+				}
+
+				rest = &rest[num_len_bytes ..];
+				len
+			},
+
+			// indefinite lengths aren't allowed in DER
+			_ => return Err(Error::InvalidEncoding),
+		};
+
+		if rest.len() < len {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let (contents, rest) = rest.split_at(len);
+		self.bytes = rest;
+
+		Ok((tag, contents, &all[.. all.len() - rest.len()]))
+	}
+
+	/// Reads the next value, whatever its tag, returning the tag and contents.
+	pub fn read_any(&mut self) -> Result<(u8, &'a [u8]), Error> {
+		self.next().map(|(tag, contents, _)| (tag, contents))
+	}
+
+	/// Reads a value with the given tag, returning its contents.
+	pub fn read(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+		self.read_tagged(tag).map(|(contents, _)| contents)
+	}
+
+	/// Reads a value with the given tag, returning its whole encoding, which
+	/// is needed to check signatures over encoded structures such as the
+	/// `tbsCertificate` of a certificate.
+	pub fn read_raw(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+		self.read_tagged(tag).map(|(_, raw)| raw)
+	}
+
+	fn read_tagged(&mut self, tag: u8) -> Result<(&'a [u8], &'a [u8]), Error> {
+		if self.peek_tag().ok_or(Error::InvalidEncoding)? != tag {
+			return Err(Error::UnexpectedTag);
+		}
+
+		self.next().map(|(_, contents, raw)| (contents, raw))
+	}
+
+	/// Reads a value with the given tag if it's next, for `OPTIONAL` and
+	/// `DEFAULT` fields.
+	pub fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, Error> {
+		if self.peek_tag() != Some(tag) {
+			return Ok(None);
+		}
+
+		self.read(tag).map(Some)
+	}
+
+	/// Reads a value with the given constructed tag, returning a reader over
+	/// the values inside it.
+	pub fn read_nested(&mut self, tag: u8) -> Result<Reader<'a>, Error> {
+		self.read(tag).map(Reader::new)
+	}
+
+	/// Reads a `SEQUENCE`, returning a reader over its fields.
+	pub fn read_sequence(&mut self) -> Result<Reader<'a>, Error> {
+		self.read_nested(TAG_SEQUENCE)
+	}
+
+	/// Reads an `[number] EXPLICIT` field, returning a reader over the value
+	/// inside it.
+	pub fn read_explicit(&mut self, number: u8) -> Result<Reader<'a>, Error> {
+		self.read_nested(context_tag(number, true))
+	}
+
+	/// Reads a non-negative `INTEGER`, returning its big-endian bytes without
+	/// the leading zero byte that's added when the top bit is set. Zero is
+	/// returned as a single zero byte. Negative integers are rejected, since
+	/// nothing here uses them.
+	pub fn read_integer(&mut self) -> Result<&'a [u8], Error> {
+		let contents = self.read(TAG_INTEGER)?;
+
+		match contents {
+			[] => Err(Error::InvalidEncoding),
+			[first, ..] if first & 0x80 != 0 => Err(Error::InvalidEncoding),
+
+			// a leading zero byte is only allowed when the next byte has its
+			// top bit set
+			[0, second, ..] if second & 0x80 == 0 => Err(Error::InvalidEncoding),
+			[0, rest @ ..] if !rest.is_empty() => Ok(rest),
+
+			_ => Ok(contents),
+		}
+	}
+
+	/// Reads a non-negative `INTEGER` that fits in a `u64`.
+	pub fn read_u64(&mut self) -> Result<u64, Error> {
+		let bytes = self.read_integer()?;
+
+		if bytes.len() > 8 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(bytes.iter().fold(0, |acc, &byte| (acc << 8) | byte as u64))
+	}
+
+	/// Reads a `BIT STRING` made of whole bytes, as used for keys and
+	/// signatures, returning the bytes.
+	pub fn read_bit_string(&mut self) -> Result<&'a [u8], Error> {
+		match self.read(TAG_BIT_STRING)?.split_first() {
+			Some((0, bytes)) => Ok(bytes),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+
+	/// Reads an `OCTET STRING`.
+	pub fn read_octet_string(&mut self) -> Result<&'a [u8], Error> {
+		self.read(TAG_OCTET_STRING)
+	}
+
+	/// Reads an `OBJECT IDENTIFIER`, returning its encoded contents, which can
+	/// be compared against known identifiers.
+	pub fn read_oid(&mut self) -> Result<&'a [u8], Error> {
+		let contents = self.read(TAG_OID)?;
+
+		// every component must end in a byte with the top bit clear, and can't
+		// start with a padding byte of 0x80
+		let valid = contents.last().is_some_and(|&x| x & 0x80 == 0)
+			&& contents.first() != Some(&0x80)
+			&& contents.windows(2).all(|pair| pair[0] & 0x80 != 0 || pair[1] != 0x80);
+
+		if !valid {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(contents)
+	}
+
+	/// Reads a `NULL`.
+	pub fn read_null(&mut self) -> Result<(), Error> {
+		if !self.read(TAG_NULL)?.is_empty() {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(())
+	}
+
+	/// Reads a `BOOLEAN`, which DER requires to be encoded as 0x00 or 0xff.
+	pub fn read_bool(&mut self) -> Result<bool, Error> {
+		match self.read(TAG_BOOLEAN)? {
+			[0x00] => Ok(false),
+			[0xff] => Ok(true),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+}
+
+/// Builds up DER-encoded values in a buffer.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct Writer {
+	out: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl Writer {
+	/// Creates an empty writer.
+	pub fn new() -> Self {
+		Self {out: Vec::new()}
+	}
+
+	/// Returns the encodings written so far.
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.out
+	}
+
+	fn write_header(&mut self, tag: u8, len: usize) {
+		self.out.push(tag);
+
+		if len < 0x80 {
+			self.out.push(len as u8);
+			return;
+		}
+
+		let len_bytes = (len as u64).to_be_bytes();
+		let skip = len_bytes.iter().take_while(|&&x| x == 0).count();
+
+		self.out.push(0x80 | (8 - skip) as u8);
+		self.out.extend_from_slice(&len_bytes[skip ..]);
+	}
+
+	/// Writes a value with the given tag and contents.
+	pub fn write(&mut self, tag: u8, contents: &[u8]) {
+		self.write_header(tag, contents.len());
+		self.out.extend_from_slice(contents);
+	}
+
+	/// Appends an existing encoding, such as one returned by
+	/// [`Reader::read_raw`].
+	pub fn write_raw(&mut self, encoding: &[u8]) {
+		self.out.extend_from_slice(encoding);
+	}
+
+	/// Writes a value with the given constructed tag, with contents written
+	/// by `f`.
+	pub fn write_nested(&mut self, tag: u8, f: impl FnOnce(&mut Writer)) {
+		let mut inner = Writer::new();
+		f(&mut inner);
+
+		self.write(tag, &inner.out);
+	}
+
+	/// Writes a `SEQUENCE`, with fields written by `f`.
+	pub fn write_sequence(&mut self, f: impl FnOnce(&mut Writer)) {
+		self.write_nested(TAG_SEQUENCE, f);
+	}
+
+	/// Writes an `[number] EXPLICIT` field, with the value written by `f`.
+	pub fn write_explicit(&mut self, number: u8, f: impl FnOnce(&mut Writer)) {
+		self.write_nested(context_tag(number, true), f);
+	}
+
+	/// Writes a non-negative `INTEGER` from its big-endian bytes, which may
+	/// have leading zeros.
+	pub fn write_integer(&mut self, bytes: &[u8]) {
+		let skip = bytes.iter().take_while(|&&x| x == 0).count();
+		let bytes = &bytes[skip ..];
+
+		match bytes.first() {
+			None => self.write(TAG_INTEGER, &[0]),
+			Some(first) if first & 0x80 != 0 => {
+				self.write_header(TAG_INTEGER, bytes.len() + 1);
+				self.out.push(0);
+				self.out.extend_from_slice(bytes);
+			},
+			Some(_) => self.write(TAG_INTEGER, bytes),
+		}
+	}
+
+	/// Writes a non-negative `INTEGER`.
+	pub fn write_u64(&mut self, value: u64) {
+		self.write_integer(&value.to_be_bytes());
+	}
+
+	/// Writes a `BIT STRING` made of whole bytes.
+	pub fn write_bit_string(&mut self, bytes: &[u8]) {
+		self.write_header(TAG_BIT_STRING, bytes.len() + 1);
+		self.out.push(0);
+		self.out.extend_from_slice(bytes);
+	}
+
+	/// Writes an `OCTET STRING`.
+	pub fn write_octet_string(&mut self, bytes: &[u8]) {
+		self.write(TAG_OCTET_STRING, bytes);
+	}
+
+	/// Writes an `OBJECT IDENTIFIER` from its encoded contents.
+	pub fn write_oid(&mut self, oid: &[u8]) {
+		self.write(TAG_OID, oid);
+	}
+
+	/// Writes a `NULL`.
+	pub fn write_null(&mut self) {
+		self.write(TAG_NULL, &[]);
+	}
+
+	/// Writes a `BOOLEAN`.
+	pub fn write_bool(&mut self, value: bool) {
+		self.write(TAG_BOOLEAN, &[if value {0xff} else {0x00}]);
+	}
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip() {
+	let oid = [0x2b, 0x65, 0x70];
+
+	let mut writer = Writer::new();
+
+	writer.write_sequence(|writer| {
+		writer.write_u64(0);
+		writer.write_u64(0x80);
+		writer.write_integer(&[0x00, 0x00, 0x7f, 0xff]);
+		writer.write_sequence(|writer| writer.write_oid(&oid));
+		writer.write_bit_string(&[0xaa; 200]);
+		writer.write_explicit(3, |writer| writer.write_bool(true));
+		writer.write_null();
+	});
+
+	let encoded = writer.into_bytes();
+
+	// a long form length, and integers that need and don't need a zero byte
+	assert_eq!(encoded[.. 16], [
+		0x30, 0x81, 0xe5,
+		0x02, 0x01, 0x00,
+		0x02, 0x02, 0x00, 0x80,
+		0x02, 0x02, 0x7f, 0xff,
+		0x30, 0x05,
+	]);
+
+	let mut outer = Reader::new(&encoded);
+	let mut reader = outer.read_sequence().unwrap();
+	outer.finish().unwrap();
+
+	assert_eq!(reader.read_u64(), Ok(0));
+	assert_eq!(reader.read_integer(), Ok(&[0x80][..]));
+	assert_eq!(reader.read_u64(), Ok(0x7fff));
+	assert_eq!(reader.read_sequence().unwrap().read_oid(), Ok(&oid[..]));
+	assert_eq!(reader.read_optional(context_tag(0, true)), Ok(None));
+	assert_eq!(reader.read_bit_string(), Ok(&[0xaa; 200][..]));
+	assert_eq!(reader.read_explicit(3).unwrap().read_bool(), Ok(true));
+	assert_eq!(reader.read_bool(), Err(Error::UnexpectedTag));
+	reader.read_null().unwrap();
+	reader.finish().unwrap();
+}
+
+#[test]
+fn test_strictness() {
+	let invalid: [&[u8]; 10] = [
+		// non-minimal lengths, and an indefinite length
+		&[0x04, 0x81, 0x01, 0x00],
+		&[0x04, 0x82, 0x00, 0x80],
+		&[0x30, 0x80, 0x00, 0x00],
+		// truncated contents
+		&[0x04, 0x02, 0x00],
+		// non-minimal and negative integers
+		&[0x02, 0x02, 0x00, 0x7f],
+		&[0x02, 0x01, 0x80],
+		&[0x02, 0x00],
+		// unused bits in a bit string
+		&[0x03, 0x02, 0x01, 0x00],
+		// a padded oid component, and a multi-byte tag
+		&[0x06, 0x02, 0x80, 0x01],
+		&[0x1f, 0x01, 0x00],
+	];
+
+	for encoding in invalid {
+		let mut reader = Reader::new(encoding);
+
+		let result = match encoding[0] {
+			TAG_INTEGER => reader.read_integer().map(|_| ()),
+			TAG_BIT_STRING => reader.read_bit_string().map(|_| ()),
+			TAG_OID => reader.read_oid().map(|_| ()),
+			_ => reader.read_any().map(|_| ()),
+		};
+
+		assert_eq!(result, Err(Error::InvalidEncoding), "{:02x?}", encoding);
+	}
+
+	let mut reader = Reader::new(&[0x05, 0x00, 0x05, 0x00]);
+	reader.read_null().unwrap();
+	assert_eq!(reader.finish(), Err(Error::TrailingData));
+	assert_eq!(reader.read_octet_string(), Err(Error::UnexpectedTag));
+}
@@ -0,0 +1,837 @@
+//! DKIM signatures of email messages with Ed25519, the `ed25519-sha256`
+//! algorithm from [RFC 8463](https://datatracker.ietf.org/doc/html/rfc8463),
+//! following [RFC 6376](https://datatracker.ietf.org/doc/html/rfc6376).
+//!
+//! A signature covers a hash of the body and a chosen list of header fields,
+//! after they've been put in canonical form with either the `simple`
+//! algorithm, which tolerates almost no changes in transit, or the `relaxed`
+//! algorithm, which tolerates changes to whitespace and the case of header
+//! names. The signer adds the resulting `DKIM-Signature` header field to the
+//! top of the message, and publishes its public key in a DNS TXT record at
+//! [`Signature::key_name`], in the form returned by [`key_record`].
+//!
+//! Messages must be given as they're sent, with CRLF line endings. Looking up
+//! the key record in DNS, and policies such as DMARC, are left to the caller.
+//! Only Ed25519 signatures are supported, so messages that are also signed
+//! with RSA will have their RSA signatures reported as unsupported by
+//! [`signatures`].
+
+use core::fmt;
+
+use std::string::String;
+use std::vec::Vec;
+
+use crate::base64;
+use crate::ed25519::{self, SigningKey, VerifyingKey};
+use crate::sha2::{sha256, Sha256};
+
+/// The name of the algorithm in the `a=` tag of a signature.
+pub const ALGORITHM: &str = "ed25519-sha256";
+
+/// An error returned when a message can't be signed or a signature can't be
+/// verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The message doesn't start with a valid header section.
+	InvalidMessage,
+	/// The domain, selector or a header name can't be put in a signature, or
+	/// the `From` field isn't among the signed header fields.
+	InvalidOptions,
+	/// A `DKIM-Signature` field is malformed or is missing a required tag.
+	InvalidSignatureField,
+	/// A signature or key record uses an algorithm other than Ed25519.
+	UnsupportedAlgorithm,
+	/// A key record is malformed, or the key has been revoked.
+	InvalidKeyRecord,
+	/// The signature's expiration time has passed.
+	Expired,
+	/// The body has been changed since it was signed.
+	BodyHashMismatch,
+	/// The signature is invalid for the key.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidMessage => "invalid email message",
+			Error::InvalidOptions => "invalid dkim signing options",
+			Error::InvalidSignatureField => "invalid dkim-signature field",
+			Error::UnsupportedAlgorithm => "unsupported dkim algorithm",
+			Error::InvalidKeyRecord => "invalid dkim key record",
+			Error::Expired => "dkim signature expired",
+			Error::BodyHashMismatch => "dkim body hash mismatch",
+			Error::InvalidSignature => "invalid dkim signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// A canonicalization algorithm, applied separately to the header fields and
+/// the body.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Canonicalization {
+	/// Leaves header fields as they are, and only removes empty lines from
+	/// the end of the body.
+	Simple,
+	/// Lowercases header names, unfolds header fields and collapses runs of
+	/// whitespace in both header fields and body lines.
+	Relaxed,
+}
+
+impl Canonicalization {
+	fn name(self) -> &'static str {
+		match self {
+			Canonicalization::Simple => "simple",
+			Canonicalization::Relaxed => "relaxed",
+		}
+	}
+
+	fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"simple" => Some(Canonicalization::Simple),
+			"relaxed" => Some(Canonicalization::Relaxed),
+			_ => None,
+		}
+	}
+}
+
+/// The parameters of a signature made by [`sign`].
+#[derive(Copy, Clone, Debug)]
+pub struct SignOptions<'a> {
+	/// The signing domain, in the `d=` tag.
+	pub domain: &'a str,
+	/// The selector, in the `s=` tag, which picks out one of the domain's
+	/// keys.
+	pub selector: &'a str,
+	/// The names of the header fields to sign, which must include `From`. A
+	/// name may be listed more times than the field appears, which stops
+	/// more fields of that name from being added without breaking the
+	/// signature.
+	pub headers: &'a [&'a str],
+	/// The canonicalization of the header fields, which is usually relaxed.
+	pub header_canonicalization: Canonicalization,
+	/// The canonicalization of the body.
+	pub body_canonicalization: Canonicalization,
+	/// The time of signing in seconds since the Unix epoch, in the `t=` tag.
+	pub timestamp: Option<u64>,
+	/// The time after which the signature should no longer be accepted, in
+	/// the `x=` tag.
+	pub expiration: Option<u64>,
+}
+
+/// Signs `message` with `key`, returning the `DKIM-Signature` header field,
+/// ending with CRLF, to add to the top of the message.
+pub fn sign(key: &SigningKey, options: &SignOptions, message: &[u8]) -> Result<String, Error> {
+	let valid_name = |name: &str| {
+		!name.is_empty() && name.bytes().all(|x| x.is_ascii_alphanumeric() || b"-._".contains(&x))
+	};
+
+	let valid_header = |name: &&str| {
+		!name.is_empty() && name.bytes().all(|x| x.is_ascii_graphic() && x != b':')
+	};
+
+	if !valid_name(options.domain) || !valid_name(options.selector)
+		|| !options.headers.iter().all(valid_header)
+		|| !options.headers.iter().any(|name| name.eq_ignore_ascii_case("from"))
+	{
+		return Err(Error::InvalidOptions);
+	}
+
+	if let (Some(timestamp), Some(expiration)) = (options.timestamp, options.expiration) {
+		if expiration <= timestamp {
+			return Err(Error::InvalidOptions);
+		}
+	}
+
+	let (fields, body) = split_message(message)?;
+	let body_hash = sha256(&canonical_body(options.body_canonicalization, body));
+
+	let mut field = format!(
+		"DKIM-Signature: v=1; a={}; c={}/{}; d={}; s={};\r\n\t",
+		ALGORITHM,
+		options.header_canonicalization.name(),
+		options.body_canonicalization.name(),
+		options.domain,
+		options.selector,
+	);
+
+	if let Some(timestamp) = options.timestamp {
+		field.push_str(&format!("t={}; ", timestamp));
+	}
+
+	if let Some(expiration) = options.expiration {
+		field.push_str(&format!("x={}; ", expiration));
+	}
+
+	field.push_str("h=");
+	field.push_str(&options.headers.join(":"));
+	field.push_str(";\r\n\tbh=");
+	base64::encode(&body_hash, &mut field);
+	field.push_str(";\r\n\tb=");
+
+	let unsigned = format!("{}\r\n", field);
+	let hash = header_hash(options.header_canonicalization, &fields, options.headers, unsigned.as_bytes());
+
+	let mut signature = String::new();
+	base64::encode(key.sign(&hash).as_bytes(), &mut signature);
+
+	// folded to keep the lines short
+	field.push_str(&signature[.. 64]);
+	field.push_str("\r\n\t");
+	field.push_str(&signature[64 ..]);
+	field.push_str("\r\n");
+
+	Ok(field)
+}
+
+/// A parsed `DKIM-Signature` header field with the `ed25519-sha256`
+/// algorithm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	domain: String,
+	selector: String,
+	identity: Option<String>,
+	headers: Vec<String>,
+	header_canonicalization: Canonicalization,
+	body_canonicalization: Canonicalization,
+	body_hash: [u8; 32],
+	signature: [u8; 64],
+	timestamp: Option<u64>,
+	expiration: Option<u64>,
+	body_length: Option<u64>,
+	field: Vec<u8>,
+}
+
+impl Signature {
+	/// Parses a whole `DKIM-Signature` header field, including its name and
+	/// the CRLF at its end.
+	pub fn from_field(field: &[u8]) -> Result<Self, Error> {
+		let text = core::str::from_utf8(field).map_err(|_| Error::InvalidSignatureField)?;
+		let (name, value) = text.split_once(':').ok_or(Error::InvalidSignatureField)?;
+
+		if !name.trim_end_matches([' ', '\t']).eq_ignore_ascii_case("dkim-signature") {
+			return Err(Error::InvalidSignatureField);
+		}
+
+		let tags = parse_tags(value).ok_or(Error::InvalidSignatureField)?;
+		let tag = |name: &str| tags.iter().find(|(tag, _)| *tag == name).map(|&(_, value)| value);
+		let required = |name: &str| tag(name).ok_or(Error::InvalidSignatureField);
+
+		if required("v")? != "1" {
+			return Err(Error::InvalidSignatureField);
+		}
+
+		if required("a")? != ALGORITHM {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		if tag("q").is_some_and(|methods| !methods.split(':').any(|x| trim_fws(x) == "dns/txt")) {
+			return Err(Error::InvalidSignatureField);
+		}
+
+		let domain = String::from(required("d")?);
+		let selector = String::from(required("s")?);
+
+		let headers: Vec<String> = required("h")?.split(':').map(|x| String::from(trim_fws(x))).collect();
+
+		if headers.iter().any(|x| x.is_empty()) || !headers.iter().any(|x| x.eq_ignore_ascii_case("from")) {
+			return Err(Error::InvalidSignatureField);
+		}
+
+		let (header_canonicalization, body_canonicalization) = match tag("c") {
+			Some(value) => {
+				let (header, body) = value.split_once('/').unwrap_or((value, "simple"));
+				let header = Canonicalization::from_name(header).ok_or(Error::InvalidSignatureField)?;
+				let body = Canonicalization::from_name(body).ok_or(Error::InvalidSignatureField)?;
+				(header, body)
+			},
+			None => (Canonicalization::Simple, Canonicalization::Simple),
+		};
+
+		// the identity must be in the signing domain or one of its subdomains
+		let identity = tag("i").map(String::from);
+
+		if let Some(identity) = &identity {
+			let (_, identity_domain) = identity.rsplit_once('@').ok_or(Error::InvalidSignatureField)?;
+
+			if !is_subdomain(identity_domain, &domain) {
+				return Err(Error::InvalidSignatureField);
+			}
+		}
+
+		let body_hash = decode_base64(required("bh")?).ok_or(Error::InvalidSignatureField)?;
+		let signature = decode_base64(required("b")?).ok_or(Error::InvalidSignatureField)?;
+
+		let number = |name: &str| match tag(name) {
+			Some(value) => parse_number(value).map(Some).ok_or(Error::InvalidSignatureField),
+			None => Ok(None),
+		};
+
+		let timestamp = number("t")?;
+		let expiration = number("x")?;
+		let body_length = number("l")?;
+
+		if let (Some(timestamp), Some(expiration)) = (timestamp, expiration) {
+			if expiration <= timestamp {
+				return Err(Error::InvalidSignatureField);
+			}
+		}
+
+		Ok(Self {
+			domain,
+			selector,
+			identity,
+			headers,
+			header_canonicalization,
+			body_canonicalization,
+			body_hash,
+			signature,
+			timestamp,
+			expiration,
+			body_length,
+			field: field.to_vec(),
+		})
+	}
+
+	/// Returns the signing domain.
+	pub fn domain(&self) -> &str {
+		&self.domain
+	}
+
+	/// Returns the selector.
+	pub fn selector(&self) -> &str {
+		&self.selector
+	}
+
+	/// Returns the agent or user the signature is on behalf of, if given.
+	pub fn identity(&self) -> Option<&str> {
+		self.identity.as_deref()
+	}
+
+	/// Returns the names of the signed header fields.
+	pub fn signed_headers(&self) -> &[String] {
+		&self.headers
+	}
+
+	/// Returns the time of signing, if given.
+	pub fn timestamp(&self) -> Option<u64> {
+		self.timestamp
+	}
+
+	/// Returns the expiration time, if given.
+	pub fn expiration(&self) -> Option<u64> {
+		self.expiration
+	}
+
+	/// Returns the number of bytes of the canonical body that are signed, if
+	/// not all of them are. Anything after them, which could have been added
+	/// by anyone, should be treated with suspicion.
+	pub fn body_length(&self) -> Option<u64> {
+		self.body_length
+	}
+
+	/// Returns the DNS name of the TXT record holding the public key, which is
+	/// `<selector>._domainkey.<domain>`.
+	pub fn key_name(&self) -> String {
+		format!("{}._domainkey.{}", self.selector, self.domain)
+	}
+
+	/// Verifies the signature of `message` with `key`, which should have been
+	/// parsed from the record at [`key_name`](Self::key_name) with
+	/// [`parse_key_record`]. The signature is rejected if its expiration time
+	/// is before `now`, in seconds since the Unix epoch.
+	pub fn verify(&self, key: &VerifyingKey, message: &[u8], now: u64) -> Result<(), Error> {
+		if self.expiration.is_some_and(|expiration| expiration < now) {
+			return Err(Error::Expired);
+		}
+
+		let (fields, body) = split_message(message)?;
+
+		let body = canonical_body(self.body_canonicalization, body);
+
+		let body = match self.body_length {
+			Some(len) => body.get(.. usize::try_from(len).unwrap_or(usize::MAX)).ok_or(Error::BodyHashMismatch)?,
+			None => &body[..],
+		};
+
+		if sha256(body) != self.body_hash {
+			return Err(Error::BodyHashMismatch);
+		}
+
+		let names: Vec<&str> = self.headers.iter().map(String::as_str).collect();
+		let hash = header_hash(self.header_canonicalization, &fields, &names, &without_signature(&self.field));
+
+		let signature = ed25519::Signature::from_bytes(self.signature).map_err(|_| Error::InvalidSignature)?;
+
+		if !key.verify(&hash, &signature) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(())
+	}
+}
+
+/// Parses every `DKIM-Signature` header field of `message`, from the top
+/// down. Signatures with other algorithms give
+/// [`Error::UnsupportedAlgorithm`].
+pub fn signatures(message: &[u8]) -> Result<Vec<Result<Signature, Error>>, Error> {
+	let (fields, _) = split_message(message)?;
+
+	let signatures = fields.iter()
+		.filter(|field| field_name(field).eq_ignore_ascii_case(b"dkim-signature"))
+		.map(|field| Signature::from_field(field))
+		.collect();
+
+	Ok(signatures)
+}
+
+/// Returns the contents of the DNS TXT record publishing `key`.
+pub fn key_record(key: &VerifyingKey) -> String {
+	let mut out = String::from("v=DKIM1; k=ed25519; p=");
+	base64::encode(key.as_bytes(), &mut out);
+	out
+}
+
+/// Parses the contents of a DNS TXT record holding an Ed25519 DKIM key. A
+/// record split into several strings should have them concatenated first.
+pub fn parse_key_record(record: &str) -> Result<VerifyingKey, Error> {
+	let tags = parse_tags(record).ok_or(Error::InvalidKeyRecord)?;
+	let tag = |name: &str| tags.iter().find(|(tag, _)| *tag == name).map(|&(_, value)| value);
+
+	// the version is optional, but must come first if present
+	if tag("v").is_some() && tags[0] != ("v", "DKIM1") {
+		return Err(Error::InvalidKeyRecord);
+	}
+
+	if tag("k") != Some("ed25519") {
+		return Err(Error::UnsupportedAlgorithm);
+	}
+
+	if tag("h").is_some_and(|hashes| !hashes.split(':').any(|x| trim_fws(x) == "sha256")) {
+		return Err(Error::UnsupportedAlgorithm);
+	}
+
+	if tag("s").is_some_and(|services| !services.split(':').any(|x| matches!(trim_fws(x), "*" | "email"))) {
+		return Err(Error::InvalidKeyRecord);
+	}
+
+	// an empty key means that it has been revoked
+	let public_key = tag("p").and_then(decode_base64).ok_or(Error::InvalidKeyRecord)?;
+	VerifyingKey::from_bytes(public_key).map_err(|_| Error::InvalidKeyRecord)
+}
+
+fn is_wsp(byte: u8) -> bool {
+	byte == b' ' || byte == b'\t'
+}
+
+fn trim_fws(text: &str) -> &str {
+	text.trim_matches([' ', '\t', '\r', '\n'])
+}
+
+// splits a tag list into its tags and values, with whitespace around them
+// trimmed, failing on duplicate or malformed tags
+fn parse_tags(text: &str) -> Option<Vec<(&str, &str)>> {
+	let mut tags: Vec<(&str, &str)> = Vec::new();
+
+	for spec in text.split(';') {
+		if trim_fws(spec).is_empty() {
+			continue;
+		}
+
+		let (name, value) = spec.split_once('=')?;
+		let name = trim_fws(name);
+
+		let valid_name = name.bytes().next().is_some_and(|x| x.is_ascii_alphabetic())
+			&& name.bytes().all(|x| x.is_ascii_alphanumeric() || x == b'_');
+
+		if !valid_name || tags.iter().any(|&(tag, _)| tag == name) {
+			return None;
+		}
+
+		tags.push((name, trim_fws(value)));
+	}
+
+	Some(tags)
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+	if text.is_empty() || !text.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+
+	text.parse().ok()
+}
+
+// base64 with folding whitespace allowed anywhere in it
+fn decode_base64<const N: usize>(text: &str) -> Option<[u8; N]> {
+	let text: String = text.chars().filter(|x| !matches!(x, ' ' | '\t' | '\r' | '\n')).collect();
+	base64::decode(&text)?.try_into().ok()
+}
+
+fn is_subdomain(name: &str, domain: &str) -> bool {
+	let name = name.to_ascii_lowercase();
+	let domain = domain.to_ascii_lowercase();
+
+	name == domain || name.strip_suffix(domain.as_str()).is_some_and(|x| x.ends_with('.'))
+}
+
+// splits a message into its header fields, each with its trailing CRLF, and
+// its body
+fn split_message(message: &[u8]) -> Result<(Vec<&[u8]>, &[u8]), Error> {
+	let mut fields = Vec::new();
+	let mut start = 0;
+	let mut i = 0;
+
+	loop {
+		if message[i ..].starts_with(b"\r\n") {
+			if start != i {
+				fields.push(&message[start .. i]);
+			}
+
+			return Ok((fields, &message[i + 2 ..]));
+		}
+
+		// a message without a body may end right after its header fields
+		if i == message.len() {
+			if start != i {
+				fields.push(&message[start .. i]);
+			}
+
+			return Ok((fields, &[]));
+		}
+
+		let line_end = message[i ..].windows(2).position(|x| x == b"\r\n").ok_or(Error::InvalidMessage)? + i + 2;
+
+		// lines starting with whitespace continue the field before them
+		if is_wsp(message[i]) {
+			if start == i {
+				return Err(Error::InvalidMessage);
+			}
+		} else {
+			if start != i {
+				fields.push(&message[start .. i]);
+			}
+
+			if !message[i .. line_end].contains(&b':') {
+				return Err(Error::InvalidMessage);
+			}
+
+			start = i;
+		}
+
+		i = line_end;
+	}
+}
+
+fn field_name(field: &[u8]) -> &[u8] {
+	let colon = field.iter().position(|&x| x == b':').unwrap_or(field.len());
+	let mut name = &field[.. colon];
+
+	while let [rest @ .., last] = name {
+		if !is_wsp(*last) {
+			break;
+		}
+
+		name = rest;
+	}
+
+	name
+}
+
+// returns a DKIM-Signature field with the value of its b= tag removed
+fn without_signature(field: &[u8]) -> Vec<u8> {
+	let colon = field.iter().position(|&x| x == b':').unwrap_or(0);
+	let mut start = colon + 1;
+
+	while start < field.len() {
+		let end = field[start ..].iter().position(|&x| x == b';').map_or(field.len(), |x| start + x);
+		let spec = &field[start .. end];
+
+		if let Some(equals) = spec.iter().position(|&x| x == b'=') {
+			if spec[.. equals].iter().filter(|&&x| !matches!(x, b' ' | b'\t' | b'\r' | b'\n')).eq(b"b") {
+				// the CRLF ending the field isn't part of the value
+				let value_end = match end == field.len() && field.ends_with(b"\r\n") {
+					true => end - 2,
+					false => end,
+				};
+
+				let mut out = field[.. start + equals + 1].to_vec();
+				out.extend_from_slice(&field[value_end ..]);
+				return out;
+			}
+		}
+
+		start = end + 1;
+	}
+
+	field.to_vec()
+}
+
+fn canonical_field(canonicalization: Canonicalization, field: &[u8], out: &mut Vec<u8>) {
+	if canonicalization == Canonicalization::Simple {
+		out.extend_from_slice(field);
+		return;
+	}
+
+	let name = field_name(field);
+	out.extend(name.iter().map(u8::to_ascii_lowercase));
+	out.push(b':');
+
+	let value = &field[field.iter().position(|&x| x == b':').map_or(field.len(), |x| x + 1) ..];
+	let mut started = false;
+	let mut pending_space = false;
+
+	for &byte in value {
+		match byte {
+			b'\r' | b'\n' => {},
+			b' ' | b'\t' => pending_space = true,
+			_ => {
+				// whitespace at the start of the value is dropped
+				if pending_space && started {
+					out.push(b' ');
+				}
+
+				started = true;
+				pending_space = false;
+				out.push(byte);
+			},
+		}
+	}
+
+	out.extend_from_slice(b"\r\n");
+}
+
+fn canonical_body(canonicalization: Canonicalization, body: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(body.len() + 2);
+
+	match canonicalization {
+		Canonicalization::Simple => out.extend_from_slice(body),
+		Canonicalization::Relaxed => {
+			let mut lines = body.split(|&x| x == b'\n').peekable();
+
+			while let Some(line) = lines.next() {
+				let is_last = lines.peek().is_none();
+				let line = line.strip_suffix(b"\r").filter(|_| !is_last).unwrap_or(line);
+
+				// a final piece without a line ending is still a line, but the
+				// empty piece after the last CRLF isn't
+				if is_last && line.is_empty() {
+					break;
+				}
+
+				let mut pending_space = false;
+
+				for &byte in line {
+					if is_wsp(byte) {
+						pending_space = true;
+					} else {
+						if pending_space {
+							out.push(b' ');
+						}
+
+						pending_space = false;
+						out.push(byte);
+					}
+				}
+
+				out.extend_from_slice(b"\r\n");
+			}
+		},
+	}
+
+	while out.ends_with(b"\r\n") {
+		out.truncate(out.len() - 2);
+	}
+
+	// the simple algorithm turns an empty body into a single CRLF, while the
+	// relaxed algorithm leaves it empty
+	if !out.is_empty() || canonicalization == Canonicalization::Simple {
+		out.extend_from_slice(b"\r\n");
+	}
+
+	out
+}
+
+// hashes the signed header fields, each taken from the bottom up, followed by
+// the signature's own field without its final CRLF
+fn header_hash(canonicalization: Canonicalization, fields: &[&[u8]], names: &[&str], signature_field: &[u8]) -> [u8; 32] {
+	let mut used = std::vec![false; fields.len()];
+	let mut hasher = Sha256::new();
+	let mut out = Vec::new();
+
+	for name in names {
+		let found = (0 .. fields.len()).rev()
+			.find(|&i| !used[i] && field_name(fields[i]).eq_ignore_ascii_case(name.as_bytes()));
+
+		// fields that aren't present are signed as if they were empty
+		if let Some(i) = found {
+			used[i] = true;
+			out.clear();
+			canonical_field(canonicalization, fields[i], &mut out);
+			hasher.add_bytes(&out);
+		}
+	}
+
+	out.clear();
+	canonical_field(canonicalization, signature_field, &mut out);
+	hasher.add_bytes(&out[.. out.len() - 2]);
+
+	hasher.out()
+}
+
+#[cfg(test)]
+const RFC8463_MESSAGE: &[u8] = b"\
+DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed;\r
+ d=football.example.com; i=@football.example.com;\r
+ q=dns/txt; s=brisbane; t=1528637909; h=from : to :\r
+ subject : date : message-id : from : subject : date;\r
+ bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;\r
+ b=/gCrinpcQOoIfuHNQIbq4pgh9kyIK3AQUdt9OdqQehSwhEIug4D11Bus\r
+ Fa3bT3FY5OsU7ZbnKELq+eXdp1Q1Dw==\r
+From: Joe SixPack <joe@football.example.com>\r
+To: Suzie Q <suzie@shopping.example.net>\r
+Subject: Is dinner ready?\r
+Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)\r
+Message-ID: <20030712040037.46341.5F8J@football.example.com>\r
+\r
+Hi.\r
+\r
+We lost the game.  Are you hungry yet?\r
+\r
+Joe.\r
+";
+
+#[test]
+fn test_rfc8463_example() {
+	let key = parse_key_record("v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=").unwrap();
+	assert_eq!(key_record(&key), "v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=");
+
+	let signatures = signatures(RFC8463_MESSAGE).unwrap();
+	assert_eq!(signatures.len(), 1);
+
+	let signature = signatures[0].as_ref().unwrap();
+	assert_eq!(signature.key_name(), "brisbane._domainkey.football.example.com");
+	assert_eq!(signature.identity(), Some("@football.example.com"));
+	assert_eq!(signature.timestamp(), Some(1528637909));
+	assert_eq!(signature.signed_headers().len(), 8);
+	assert_eq!(signature.verify(&key, RFC8463_MESSAGE, 1528637909), Ok(()));
+
+	// relaxed canonicalization tolerates changes to whitespace
+	let text = core::str::from_utf8(RFC8463_MESSAGE).unwrap();
+	let reformatted = text
+		.replace("Subject: Is dinner ready?", "subject:   Is dinner ready? ")
+		.replace("We lost the game.  Are", "We  lost the game. \tAre")
+		+ "\r\n\r\n";
+
+	assert_eq!(signature.verify(&key, reformatted.as_bytes(), 1528637909), Ok(()));
+
+	// but not to anything else
+	let changed = text.replace("Joe.", "Joe!");
+	assert_eq!(signature.verify(&key, changed.as_bytes(), 1528637909), Err(Error::BodyHashMismatch));
+
+	// or to adding another field that was signed as missing
+	let added = text.replace("\r\n\r\nHi.", "\r\nSubject: Free money\r\n\r\nHi.");
+	assert_eq!(signature.verify(&key, added.as_bytes(), 1528637909), Err(Error::InvalidSignature));
+}
+
+#[test]
+fn test_sign_and_verify() {
+	let key = SigningKey::from_bytes([7; 32]);
+	let message = b"From: a@example.com\r\nTo: b@example.org\r\nSubject: Hi\r\n\r\nHello  there\r\n\r\n";
+
+	for header_canonicalization in [Canonicalization::Simple, Canonicalization::Relaxed] {
+		for body_canonicalization in [Canonicalization::Simple, Canonicalization::Relaxed] {
+			let options = SignOptions {
+				domain: "example.com",
+				selector: "mail",
+				headers: &["From", "To", "Subject", "Subject"],
+				header_canonicalization,
+				body_canonicalization,
+				timestamp: Some(1700000000),
+				expiration: Some(1700086400),
+			};
+
+			let field = sign(&key, &options, message).unwrap();
+			let signed = [field.as_bytes(), message].concat();
+
+			let signature = Signature::from_field(field.as_bytes()).unwrap();
+			let verifying_key = key.verifying_key();
+			assert_eq!(signature.verify(&verifying_key, &signed, 1700000000), Ok(()));
+			assert_eq!(signature.verify(&verifying_key, &signed, 1700086401), Err(Error::Expired));
+
+			let other_key = SigningKey::from_bytes([8; 32]).verifying_key();
+			assert_eq!(signature.verify(&other_key, &signed, 1700000000), Err(Error::InvalidSignature));
+		}
+	}
+
+	let options = SignOptions {
+		domain: "example.com",
+		selector: "mail",
+		headers: &["To", "Subject"],
+		header_canonicalization: Canonicalization::Relaxed,
+		body_canonicalization: Canonicalization::Relaxed,
+		timestamp: None,
+		expiration: None,
+	};
+
+	assert_eq!(sign(&key, &options, message), Err(Error::InvalidOptions));
+	assert_eq!(sign(&key, &SignOptions {headers: &["From"], selector: "a;b", ..options}, message), Err(Error::InvalidOptions));
+	assert_eq!(sign(&key, &SignOptions {headers: &["From"], ..options}, b"From a\r\n\r\n"), Err(Error::InvalidMessage));
+}
+
+#[test]
+fn test_simple_signature() {
+	// checked against a python transcription of sections 3.4 and 3.7 of RFC
+	// 6376, signing with the Ed25519 class of python's cryptography package
+	let key = SigningKey::from_bytes([7; 32]);
+	let message = b"From: a@example.com\r\nSubject:  Hi \r\n\r\nHello  there\r\n\r\n";
+
+	let options = SignOptions {
+		domain: "example.com",
+		selector: "mail",
+		headers: &["From", "Subject"],
+		header_canonicalization: Canonicalization::Simple,
+		body_canonicalization: Canonicalization::Simple,
+		timestamp: Some(1700000000),
+		expiration: None,
+	};
+
+	assert_eq!(sign(&key, &options, message).unwrap(), concat!(
+		"DKIM-Signature: v=1; a=ed25519-sha256; c=simple/simple; d=example.com; s=mail;\r\n",
+		"\tt=1700000000; h=From:Subject;\r\n",
+		"\tbh=of1yODq1UauYvVevrGdNf/vVhOVdsERxLzUn/zwF+AY=;\r\n",
+		"\tb=ObVfc1BE42A04A4fVqjjAsh0bthpmcOKM9D0qemJj5d9zx5fx4evKLwT8NQ2Aj5q\r\n",
+		"\tXeZf2yZoiMOGKgcQdxfJAg==\r\n",
+	));
+}
+
+#[test]
+fn test_canonical_body() {
+	let cases: [(&[u8], &[u8], &[u8]); 5] = [
+		(b"", b"\r\n", b""),
+		(b"\r\n\r\n", b"\r\n", b""),
+		(b"a \t b  \r\n \r\n", b"a \t b  \r\n \r\n", b"a b\r\n"),
+		(b"a\r\n\r\nb", b"a\r\n\r\nb\r\n", b"a\r\n\r\nb\r\n"),
+		(b" a\r\n\t\r\n\r\n", b" a\r\n\t\r\n", b" a\r\n"),
+	];
+
+	for (body, simple, relaxed) in cases {
+		assert_eq!(canonical_body(Canonicalization::Simple, body), simple);
+		assert_eq!(canonical_body(Canonicalization::Relaxed, body), relaxed);
+	}
+}
+
+#[test]
+fn test_parse_key_record() {
+	let key = "p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=";
+	assert!(parse_key_record(&format!("k=ed25519; {}", key)).is_ok());
+	assert!(parse_key_record(&format!("k=ed25519; h=sha1:sha256; s=email; {}", key)).is_ok());
+	assert_eq!(parse_key_record(key), Err(Error::UnsupportedAlgorithm));
+	assert_eq!(parse_key_record(&format!("k=rsa; {}", key)), Err(Error::UnsupportedAlgorithm));
+	assert_eq!(parse_key_record(&format!("k=ed25519; {}; v=DKIM1", key)), Err(Error::InvalidKeyRecord));
+	assert_eq!(parse_key_record("v=DKIM1; k=ed25519; p="), Err(Error::InvalidKeyRecord));
+}
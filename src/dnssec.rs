@@ -0,0 +1,650 @@
+//! Verification of DNSSEC signatures made with Ed25519, algorithm 15 from
+//! [RFC 8080](https://datatracker.ietf.org/doc/html/rfc8080), for resolvers
+//! and monitoring tools.
+//!
+//! An `RRSIG` record signs a whole RRset, the records of one owner name, type
+//! and class, in the canonical form of
+//! [RFC 4034, section 6](https://datatracker.ietf.org/doc/html/rfc4034#section-6):
+//! names are lowercased, the records are sorted by their data and duplicates
+//! are removed, and the TTLs are replaced by the original TTL from the
+//! `RRSIG`. [`Rrsig::verify`] builds this from records in whatever order and
+//! case they arrived in, and checks the signature against a `DNSKEY`.
+//!
+//! Names are taken in the uncompressed wire format, a sequence of labels each
+//! prefixed with its length and ending with the empty root label. Record data
+//! containing names must also be uncompressed. Following the chain of trust
+//! from the root through `DS` records, and proving nonexistence with `NSEC`
+//! records, is left to the caller.
+
+use core::fmt;
+
+use std::vec::Vec;
+
+use crate::ed25519::{Signature, VerifyingKey};
+
+/// The DNSSEC algorithm number of Ed25519.
+pub const ALGORITHM_ED25519: u8 = 15;
+
+// the only protocol value allowed in a DNSKEY
+const PROTOCOL: u8 = 3;
+
+const ZONE_KEY: u16 = 1 << 8;
+const REVOKE: u16 = 1 << 7;
+const SECURE_ENTRY_POINT: u16 = 1 << 0;
+
+const DNSKEY_LEN: usize = 4 + 32;
+
+/// An error returned when records can't be parsed or a signature can't be
+/// verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A name isn't a valid uncompressed name in the wire format.
+	InvalidName,
+	/// The data of a `DNSKEY` record is malformed.
+	InvalidDnskey,
+	/// The data of an `RRSIG` record is malformed.
+	InvalidRrsig,
+	/// The data of a signed record is malformed for its type.
+	InvalidRecord,
+	/// The `DNSKEY` or `RRSIG` uses an algorithm other than Ed25519.
+	UnsupportedAlgorithm,
+	/// The `DNSKEY` isn't the key the `RRSIG` names, or isn't a zone key.
+	KeyMismatch,
+	/// The records aren't a single RRset covered by the `RRSIG`, or aren't
+	/// inside the signer's zone.
+	RecordMismatch,
+	/// The signature's inception time hasn't been reached yet.
+	NotYetValid,
+	/// The signature's expiration time has passed.
+	Expired,
+	/// The signature is invalid.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidName => "invalid dns name",
+			Error::InvalidDnskey => "invalid dnskey record",
+			Error::InvalidRrsig => "invalid rrsig record",
+			Error::InvalidRecord => "invalid dns record",
+			Error::UnsupportedAlgorithm => "unsupported dnssec algorithm",
+			Error::KeyMismatch => "dnskey doesn't match rrsig",
+			Error::RecordMismatch => "records don't match rrsig",
+			Error::NotYetValid => "dnssec signature not yet valid",
+			Error::Expired => "dnssec signature expired",
+			Error::InvalidSignature => "invalid dnssec signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The data of an Ed25519 `DNSKEY` record.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dnskey {
+	flags: u16,
+	verifying_key: VerifyingKey,
+}
+
+impl Dnskey {
+	/// Creates a key with the given flags, which are usually 256 for a
+	/// zone-signing key or 257 for a key-signing key.
+	pub fn new(flags: u16, verifying_key: VerifyingKey) -> Self {
+		Self {flags, verifying_key}
+	}
+
+	/// Parses the data of a `DNSKEY` record.
+	pub fn from_rdata(rdata: &[u8]) -> Result<Self, Error> {
+		if rdata.len() < 4 || rdata[2] != PROTOCOL {
+			return Err(Error::InvalidDnskey);
+		}
+
+		if rdata[3] != ALGORITHM_ED25519 {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		let flags = u16::from_be_bytes([rdata[0], rdata[1]]);
+		let public_key = rdata[4 ..].try_into().map_err(|_| Error::InvalidDnskey)?;
+		let verifying_key = VerifyingKey::from_bytes(public_key).map_err(|_| Error::InvalidDnskey)?;
+
+		Ok(Self {flags, verifying_key})
+	}
+
+	/// Returns the data of the `DNSKEY` record.
+	pub fn to_rdata(&self) -> [u8; DNSKEY_LEN] {
+		let mut out = [0; DNSKEY_LEN];
+		out[0 .. 2].copy_from_slice(&self.flags.to_be_bytes());
+		out[2] = PROTOCOL;
+		out[3] = ALGORITHM_ED25519;
+		out[4 ..].copy_from_slice(self.verifying_key.as_bytes());
+		out
+	}
+
+	/// Returns the flags of the key.
+	pub fn flags(&self) -> u16 {
+		self.flags
+	}
+
+	/// Returns whether the zone key flag is set, without which the key can't
+	/// be used to verify records.
+	pub fn is_zone_key(&self) -> bool {
+		self.flags & ZONE_KEY != 0
+	}
+
+	/// Returns whether the secure entry point flag is set, which marks a
+	/// key-signing key.
+	pub fn is_secure_entry_point(&self) -> bool {
+		self.flags & SECURE_ENTRY_POINT != 0
+	}
+
+	/// Returns whether the revoke flag of
+	/// [RFC 5011](https://datatracker.ietf.org/doc/html/rfc5011) is set.
+	pub fn is_revoked(&self) -> bool {
+		self.flags & REVOKE != 0
+	}
+
+	/// Returns the Ed25519 public key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+
+	/// Returns the key tag, which `RRSIG` and `DS` records use to pick out
+	/// the key, computed as in appendix B of RFC 4034. Different keys can
+	/// have the same tag.
+	pub fn key_tag(&self) -> u16 {
+		let mut sum = 0u32;
+
+		for (i, &byte) in self.to_rdata().iter().enumerate() {
+			sum += match i % 2 {
+				0 => (byte as u32) << 8,
+				_ => byte as u32,
+			};
+		}
+
+		(sum + (sum >> 16)) as u16
+	}
+}
+
+/// One record of an RRset, as it appears in an answer.
+#[derive(Copy, Clone, Debug)]
+pub struct Record<'a> {
+	/// The owner name, in the wire format.
+	pub owner: &'a [u8],
+	/// The type, such as 1 for `A` or 15 for `MX`.
+	pub rr_type: u16,
+	/// The class, which is 1 for `IN`.
+	pub class: u16,
+	/// The record data, with any names in it uncompressed.
+	pub rdata: &'a [u8],
+}
+
+/// The data of an Ed25519 `RRSIG` record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rrsig {
+	type_covered: u16,
+	labels: u8,
+	original_ttl: u32,
+	expiration: u32,
+	inception: u32,
+	key_tag: u16,
+	signer_name: Vec<u8>,
+	signature: [u8; 64],
+}
+
+impl Rrsig {
+	/// Parses the data of an `RRSIG` record. The signer's name is
+	/// lowercased.
+	pub fn from_rdata(rdata: &[u8]) -> Result<Self, Error> {
+		if rdata.len() < 18 {
+			return Err(Error::InvalidRrsig);
+		}
+
+		if rdata[2] != ALGORITHM_ED25519 {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		let u16_at = |i: usize| u16::from_be_bytes([rdata[i], rdata[i + 1]]);
+		let u32_at = |i: usize| u32::from_be_bytes(rdata[i .. i + 4].try_into().unwrap());
+
+		let mut signer_name = Vec::new();
+		let name_len = read_name(&rdata[18 ..], &mut signer_name).ok_or(Error::InvalidRrsig)?;
+		let signature = rdata[18 + name_len ..].try_into().map_err(|_| Error::InvalidRrsig)?;
+
+		Ok(Self {
+			type_covered: u16_at(0),
+			labels: rdata[3],
+			original_ttl: u32_at(4),
+			expiration: u32_at(8),
+			inception: u32_at(12),
+			key_tag: u16_at(16),
+			signer_name,
+			signature,
+		})
+	}
+
+	/// Returns the type of the RRset that's signed.
+	pub fn type_covered(&self) -> u16 {
+		self.type_covered
+	}
+
+	/// Returns the number of labels in the owner name that was signed, which
+	/// is less than that of the records' owner name if they were synthesized
+	/// from a wildcard.
+	pub fn labels(&self) -> u8 {
+		self.labels
+	}
+
+	/// Returns the TTL of the records when they were signed.
+	pub fn original_ttl(&self) -> u32 {
+		self.original_ttl
+	}
+
+	/// Returns the time after which the signature is no longer valid, in
+	/// seconds since the Unix epoch modulo 2 ** 32.
+	pub fn expiration(&self) -> u32 {
+		self.expiration
+	}
+
+	/// Returns the time from which the signature is valid, in seconds since
+	/// the Unix epoch modulo 2 ** 32.
+	pub fn inception(&self) -> u32 {
+		self.inception
+	}
+
+	/// Returns the key tag of the `DNSKEY` that made the signature.
+	pub fn key_tag(&self) -> u16 {
+		self.key_tag
+	}
+
+	/// Returns the name of the zone that signed the records, in the wire
+	/// format.
+	pub fn signer_name(&self) -> &[u8] {
+		&self.signer_name
+	}
+
+	/// Returns the signature.
+	pub fn signature(&self) -> [u8; 64] {
+		self.signature
+	}
+
+	/// Returns the data that's signed for the RRset `records`, which is the
+	/// `RRSIG` data without the signature followed by the records in
+	/// canonical form and order.
+	///
+	/// The records must all have the same owner name, class and the covered
+	/// type, and the owner name must be in the signer's zone. Duplicate
+	/// records are only signed once.
+	pub fn signed_data(&self, records: &[Record]) -> Result<Vec<u8>, Error> {
+		let first = records.first().ok_or(Error::RecordMismatch)?;
+
+		let mut owner = Vec::new();
+		read_whole_name(first.owner, &mut owner)?;
+
+		if !is_in_zone(&owner, &self.signer_name) {
+			return Err(Error::RecordMismatch);
+		}
+
+		let mut rdatas = Vec::with_capacity(records.len());
+
+		for record in records {
+			let mut record_owner = Vec::new();
+			read_whole_name(record.owner, &mut record_owner)?;
+
+			if record_owner != owner || record.rr_type != self.type_covered || record.class != first.class {
+				return Err(Error::RecordMismatch);
+			}
+
+			let rdata = canonical_rdata(record.rr_type, record.rdata).ok_or(Error::InvalidRecord)?;
+
+			if rdata.len() > u16::MAX as usize {
+				return Err(Error::InvalidRecord);
+			}
+
+			rdatas.push(rdata);
+		}
+
+		rdatas.sort();
+		rdatas.dedup();
+
+		// a wildcard expansion is signed as the wildcard name it came from
+		let owner_labels = label_count(&owner);
+
+		if owner_labels < self.labels as usize {
+			return Err(Error::RecordMismatch);
+		}
+
+		if owner_labels > self.labels as usize {
+			let mut wildcard = std::vec![1, b'*'];
+			wildcard.extend_from_slice(skip_labels(&owner, owner_labels - self.labels as usize));
+			owner = wildcard;
+		}
+
+		let mut out = Vec::new();
+		out.extend_from_slice(&self.type_covered.to_be_bytes());
+		out.push(ALGORITHM_ED25519);
+		out.push(self.labels);
+		out.extend_from_slice(&self.original_ttl.to_be_bytes());
+		out.extend_from_slice(&self.expiration.to_be_bytes());
+		out.extend_from_slice(&self.inception.to_be_bytes());
+		out.extend_from_slice(&self.key_tag.to_be_bytes());
+		out.extend_from_slice(&self.signer_name);
+
+		for rdata in &rdatas {
+			out.extend_from_slice(&owner);
+			out.extend_from_slice(&self.type_covered.to_be_bytes());
+			out.extend_from_slice(&first.class.to_be_bytes());
+			out.extend_from_slice(&self.original_ttl.to_be_bytes());
+			out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+			out.extend_from_slice(rdata);
+		}
+
+		Ok(out)
+	}
+
+	/// Verifies the signature over the RRset `records` with `dnskey`, whose
+	/// owner name is `key_owner`, at the time `now` in seconds since the Unix
+	/// epoch. Times compare with serial number arithmetic, so the inception
+	/// and expiration must be within 68 years of `now`.
+	pub fn verify(&self, key_owner: &[u8], dnskey: &Dnskey, records: &[Record], now: u32) -> Result<(), Error> {
+		let mut key_owner_name = Vec::new();
+		read_whole_name(key_owner, &mut key_owner_name)?;
+
+		if key_owner_name != self.signer_name || dnskey.key_tag() != self.key_tag || !dnskey.is_zone_key() {
+			return Err(Error::KeyMismatch);
+		}
+
+		let signed_data = self.signed_data(records)?;
+
+		if (now.wrapping_sub(self.inception) as i32) < 0 {
+			return Err(Error::NotYetValid);
+		}
+
+		if (self.expiration.wrapping_sub(now) as i32) < 0 {
+			return Err(Error::Expired);
+		}
+
+		let signature = Signature::from_bytes(self.signature).map_err(|_| Error::InvalidSignature)?;
+
+		if !dnskey.verifying_key.verify(&signed_data, &signature) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(())
+	}
+}
+
+// reads an uncompressed name from the start of `bytes`, appending it to `out`
+// in lowercase and returning its length
+fn read_name(bytes: &[u8], out: &mut Vec<u8>) -> Option<usize> {
+	let mut i = 0;
+
+	loop {
+		let len = *bytes.get(i)? as usize;
+
+		// longer labels are compression pointers or reserved
+		if len > 63 || i + 1 + len > 255 {
+			return None;
+		}
+
+		let label = bytes.get(i + 1 .. i + 1 + len)?;
+		out.push(len as u8);
+		out.extend(label.iter().map(u8::to_ascii_lowercase));
+		i += 1 + len;
+
+		if len == 0 {
+			return Some(i);
+		}
+	}
+}
+
+fn read_whole_name(bytes: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+	match read_name(bytes, out) {
+		Some(len) if len == bytes.len() => Ok(()),
+		_ => Err(Error::InvalidName),
+	}
+}
+
+// the number of labels in a valid name, not counting the root or a leading
+// wildcard label
+fn label_count(name: &[u8]) -> usize {
+	let mut count = 0;
+	let mut i = 0;
+
+	while name[i] != 0 {
+		count += 1;
+		i += 1 + name[i] as usize;
+	}
+
+	match name.starts_with(&[1, b'*']) {
+		true => count - 1,
+		false => count,
+	}
+}
+
+fn skip_labels(name: &[u8], count: usize) -> &[u8] {
+	let mut i = 0;
+
+	for _ in 0 .. count {
+		i += 1 + name[i] as usize;
+	}
+
+	&name[i ..]
+}
+
+// whether `name` is `zone` or below it, both in lowercase
+fn is_in_zone(name: &[u8], zone: &[u8]) -> bool {
+	let mut i = 0;
+
+	loop {
+		if name[i ..] == *zone {
+			return true;
+		}
+
+		if name[i] == 0 {
+			return false;
+		}
+
+		i += 1 + name[i] as usize;
+	}
+}
+
+// the parts of record data that canonicalization needs to tell apart
+#[derive(Copy, Clone)]
+enum Field {
+	Fixed(usize),
+	Name,
+	CharacterString,
+	Rest,
+}
+
+// the layout of record data, for the types that RFC 4034 section 6.2, as
+// amended by RFC 6840 section 5.1, lists as having names to lowercase
+fn rdata_fields(rr_type: u16) -> &'static [Field] {
+	use Field::*;
+
+	match rr_type {
+		// NS, MD, MF, CNAME, MB, MG, MR, PTR and DNAME
+		2 ..= 5 | 7 ..= 9 | 12 | 39 => &[Name],
+		// SOA
+		6 => &[Name, Name, Fixed(20)],
+		// MINFO and RP
+		14 | 17 => &[Name, Name],
+		// MX, AFSDB, RT and KX
+		15 | 18 | 21 | 36 => &[Fixed(2), Name],
+		// SIG and RRSIG
+		24 | 46 => &[Fixed(18), Name, Rest],
+		// PX
+		26 => &[Fixed(2), Name, Name],
+		// NXT
+		30 => &[Name, Rest],
+		// SRV
+		33 => &[Fixed(6), Name],
+		// NAPTR
+		35 => &[Fixed(4), CharacterString, CharacterString, CharacterString, Name],
+		_ => &[Rest],
+	}
+}
+
+fn canonical_rdata(rr_type: u16, rdata: &[u8]) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(rdata.len());
+	let mut i = 0;
+
+	for field in rdata_fields(rr_type) {
+		let len = match *field {
+			Field::Fixed(len) => len,
+			Field::CharacterString => 1 + *rdata.get(i)? as usize,
+			Field::Rest => rdata.len() - i,
+			Field::Name => {
+				i += read_name(&rdata[i ..], &mut out)?;
+				continue;
+			},
+		};
+
+		out.extend_from_slice(rdata.get(i .. i + len)?);
+		i += len;
+	}
+
+	match i == rdata.len() {
+		true => Some(out),
+		false => None,
+	}
+}
+
+#[cfg(test)]
+fn name(text: &str) -> Vec<u8> {
+	let mut out = Vec::new();
+
+	for label in text.split('.').filter(|label| !label.is_empty()) {
+		out.push(label.len() as u8);
+		out.extend_from_slice(label.as_bytes());
+	}
+
+	out.push(0);
+	out
+}
+
+#[cfg(test)]
+fn base64<const N: usize>(text: &str) -> [u8; N] {
+	let mut out = [0; N];
+	crate::encoding::decode_base64(text, crate::encoding::Base64Variant::Standard, &mut out).unwrap();
+	out
+}
+
+#[cfg(test)]
+fn rrsig_rdata(key_tag: u16, signer_name: &[u8], signature: &str) -> Vec<u8> {
+	let mut rdata = Vec::new();
+	rdata.extend_from_slice(&[0, 15, ALGORITHM_ED25519, 2]);
+	rdata.extend_from_slice(&3600u32.to_be_bytes());
+	rdata.extend_from_slice(&1440021600u32.to_be_bytes());
+	rdata.extend_from_slice(&1438207200u32.to_be_bytes());
+	rdata.extend_from_slice(&key_tag.to_be_bytes());
+	rdata.extend_from_slice(signer_name);
+	rdata.extend_from_slice(&base64::<64>(signature));
+	rdata
+}
+
+#[test]
+fn test_rfc8080_examples() {
+	let examples = [
+		(
+			"l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+			3613,
+			"oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg==",
+		),
+		(
+			"zPnZ/QwEe7S8C5SPz2OfS5RR40ATk2/rYnE9xHIEijs=",
+			35217,
+			"zXQ0bkYgQTEFyfLyi9QoiY6D8ZdYo4wyUhVioYZXFdT410QPRITQSqJSnzQoSm5poJ7gD7AQR0O7KuI5k2pcBg==",
+		),
+	];
+
+	let owner = name("example.com.");
+	let mut mx = std::vec![0, 10];
+	mx.extend_from_slice(&name("mail.example.com."));
+	let records = [Record {owner: &owner, rr_type: 15, class: 1, rdata: &mx}];
+
+	for (public_key, key_tag, signature) in examples {
+		let mut dnskey_rdata = std::vec![1, 1, 3, 15];
+		dnskey_rdata.extend_from_slice(&base64::<32>(public_key));
+
+		let dnskey = Dnskey::from_rdata(&dnskey_rdata).unwrap();
+		assert_eq!(dnskey.key_tag(), key_tag);
+		assert_eq!(dnskey.to_rdata()[..], dnskey_rdata[..]);
+		assert!(dnskey.is_zone_key() && dnskey.is_secure_entry_point() && !dnskey.is_revoked());
+
+		let rrsig = Rrsig::from_rdata(&rrsig_rdata(key_tag, &owner, signature)).unwrap();
+		assert_eq!(rrsig.verify(&owner, &dnskey, &records, 1440000000), Ok(()));
+
+		assert_eq!(rrsig.verify(&owner, &dnskey, &records, 1438207199), Err(Error::NotYetValid));
+		assert_eq!(rrsig.verify(&owner, &dnskey, &records, 1440021601), Err(Error::Expired));
+		assert_eq!(rrsig.verify(&name("example.org."), &dnskey, &records, 1440000000), Err(Error::KeyMismatch));
+
+		let mut other_mx = mx.clone();
+		other_mx[1] = 20;
+		let other_records = [Record {rdata: &other_mx, ..records[0]}];
+		assert_eq!(rrsig.verify(&owner, &dnskey, &other_records, 1440000000), Err(Error::InvalidSignature));
+	}
+}
+
+#[test]
+fn test_canonical_form() {
+	// signed with the private key from the first example of RFC 8080 by a
+	// python transcription of sections 3.1.8.1 and 6 of RFC 4034, over the
+	// wildcard name *.example.com with the records lowercased, sorted and
+	// deduplicated
+	let mut dnskey_rdata = std::vec![1, 1, 3, 15];
+	dnskey_rdata.extend_from_slice(&base64::<32>("l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4="));
+	let dnskey = Dnskey::from_rdata(&dnskey_rdata).unwrap();
+
+	let signature = "/4SVELQgN/EuPB4aK3s2BHPyGnAqNAzCzAg3RyVkn2j02augQTwEkUTpLsUehXEmkqxYccwMh1GPHGgFslXQDQ==";
+	let rrsig = Rrsig::from_rdata(&rrsig_rdata(3613, &name("Example.COM."), signature)).unwrap();
+	assert_eq!(rrsig.signer_name(), name("example.com."));
+
+	let owner = name("Foo.Example.com.");
+	let mut mx1 = std::vec![0, 20];
+	mx1.extend_from_slice(&name("MAIL2.Example.com."));
+	let mut mx2 = std::vec![0, 10];
+	mx2.extend_from_slice(&name("mail.example.com."));
+
+	let records = [
+		Record {owner: &owner, rr_type: 15, class: 1, rdata: &mx1},
+		Record {owner: &owner, rr_type: 15, class: 1, rdata: &mx2},
+		Record {owner: &owner, rr_type: 15, class: 1, rdata: &mx1},
+	];
+
+	assert_eq!(rrsig.verify(&name("example.com."), &dnskey, &records, 1440000000), Ok(()));
+
+	// records of another type or owner aren't part of the RRset
+	let other_owner = name("bar.example.com.");
+	let mixed = [records[0], Record {owner: &other_owner, ..records[1]}];
+	assert_eq!(rrsig.signed_data(&mixed), Err(Error::RecordMismatch));
+	assert_eq!(rrsig.signed_data(&[Record {rr_type: 16, ..records[0]}]), Err(Error::RecordMismatch));
+
+	// and neither are records outside the signer's zone
+	let outside = name("example.net.");
+	assert_eq!(rrsig.signed_data(&[Record {owner: &outside, ..records[0]}]), Err(Error::RecordMismatch));
+}
+
+#[test]
+fn test_invalid_records() {
+	assert_eq!(Dnskey::from_rdata(&[1, 1, 3, 13]), Err(Error::UnsupportedAlgorithm));
+	assert_eq!(Dnskey::from_rdata(&[1, 1, 3, 15, 0]), Err(Error::InvalidDnskey));
+
+	let rrsig = Rrsig::from_rdata(&rrsig_rdata(1, &name("com."), &std::format!("{}==", "A".repeat(86)))).unwrap();
+
+	// compressed and overlong names are rejected
+	let compressed = [0xc0, 12];
+	let record = Record {owner: &compressed, rr_type: 15, class: 1, rdata: &[0, 10, 0]};
+	assert_eq!(rrsig.signed_data(&[record]), Err(Error::InvalidName));
+
+	let long = [[63].as_slice(), &[b'a'; 63]].concat().repeat(4);
+	assert_eq!(read_name(&[long.as_slice(), &[0]].concat(), &mut Vec::new()), None);
+
+	// and so is data that doesn't fit its type
+	let owner = name("example.com.");
+	let record = Record {owner: &owner, rr_type: 15, class: 1, rdata: &[0, 10, 4, b'm', b'a', b'i']};
+	assert_eq!(rrsig.signed_data(&[record]), Err(Error::InvalidRecord));
+}
@@ -0,0 +1,191 @@
+//! A simple integrated encryption scheme for encrypting messages to an X25519
+//! public key, combining an ephemeral X25519 key exchange, HKDF-SHA256 and
+//! XChaCha20-Poly1305. Only the holder of the matching private key can
+//! decrypt the messages, and any modification of them is detected.
+//!
+//! The wire format is stable: the 32-byte ephemeral public key, followed by
+//! the ciphertext, followed by the 16-byte tag, for a total of
+//! [`OVERHEAD`] bytes more than the plaintext. The key and nonce are the 56
+//! bytes of HKDF-SHA256 output with the X25519 shared secret as the input
+//! keying material, the ephemeral public key followed by the recipient's
+//! public key as the salt, and `libkrypton/ecies/v1` as the info.
+//!
+//! Messages aren't authenticated as coming from any particular sender.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20poly1305::{xchacha20poly1305_decrypt, xchacha20poly1305_encrypt};
+use crate::hkdf::hkdf_sha256;
+use crate::x25519::{EphemeralSecret, PublicKey, SharedSecret, StaticSecret};
+use crate::zeroize::zeroize;
+
+/// The number of bytes an encrypted message is longer than its plaintext.
+pub const OVERHEAD: usize = 32 + 16;
+
+const INFO: &[u8] = b"libkrypton/ecies/v1";
+
+/// An error returned by [`encrypt_to`] or [`decrypt`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The recipient's public key is a small-order point.
+	InvalidPublicKey,
+	/// The encrypted message is too short, was modified, or was encrypted to
+	/// a different public key.
+	DecryptionFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPublicKey => "invalid ecies public key",
+			Error::DecryptionFailed => "failed to decrypt ecies message",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+fn derive_key(
+	shared_secret: &SharedSecret,
+	ephemeral_pub_key: &PublicKey,
+	recipient: &PublicKey,
+) -> ([u8; 32], [u8; 24]) {
+	let mut salt = [0; 64];
+	salt[0 .. 32].copy_from_slice(ephemeral_pub_key.as_bytes());
+	salt[32 .. 64].copy_from_slice(recipient.as_bytes());
+
+	let mut okm = [0; 56];
+	hkdf_sha256(&salt, shared_secret.as_bytes(), INFO, &mut okm);
+
+	let key = okm[0 .. 32].try_into().unwrap();
+	let nonce = okm[32 .. 56].try_into().unwrap();
+	zeroize(&mut okm);
+
+	(key, nonce)
+}
+
+/// Encrypts `plaintext` so that only the holder of the private key for
+/// `recipient` can decrypt it. `randomness` is used as the ephemeral private
+/// key, so it must be freshly generated by a secure random source for every
+/// message.
+pub fn encrypt_to(recipient: &PublicKey, plaintext: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+	let ephemeral_secret = EphemeralSecret::from_bytes(randomness);
+	let ephemeral_pub_key = PublicKey::from(&ephemeral_secret);
+
+	let shared_secret = ephemeral_secret
+		.diffie_hellman(recipient)
+		.map_err(|_| Error::InvalidPublicKey)?;
+
+	let (mut key, nonce) = derive_key(&shared_secret, &ephemeral_pub_key, recipient);
+
+	let mut out = Vec::with_capacity(plaintext.len() + OVERHEAD);
+	out.extend_from_slice(ephemeral_pub_key.as_bytes());
+	out.extend_from_slice(plaintext);
+
+	let tag = xchacha20poly1305_encrypt(key, nonce, &[], &mut out[32 ..]);
+	out.extend_from_slice(&tag);
+
+	zeroize(&mut key);
+
+	Ok(out)
+}
+
+/// Encrypts `plaintext` like [`encrypt_to`], with an ephemeral private key
+/// generated by `rng`, which must be a cryptographically secure random number
+/// generator.
+#[cfg(feature = "rand_core")]
+pub fn encrypt_to_with_rng<R: RngCore + CryptoRng>(
+	recipient: &PublicKey,
+	plaintext: &[u8],
+	rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = encrypt_to(recipient, plaintext, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Decrypts a message that was encrypted with [`encrypt_to`] for the public
+/// key of `secret`.
+pub fn decrypt(secret: &StaticSecret, message: &[u8]) -> Result<Vec<u8>, Error> {
+	if message.len() < OVERHEAD {
+		return Err(Error::DecryptionFailed);
+	}
+
+	let ephemeral_pub_key = PublicKey::from_bytes(message[0 .. 32].try_into().unwrap());
+	let tag = message[message.len() - 16 ..].try_into().unwrap();
+
+	let shared_secret = secret
+		.diffie_hellman(&ephemeral_pub_key)
+		.map_err(|_| Error::DecryptionFailed)?;
+
+	let (mut key, nonce) = derive_key(&shared_secret, &ephemeral_pub_key, &PublicKey::from(secret));
+
+	let mut plaintext = message[32 .. message.len() - 16].to_vec();
+	let result = xchacha20poly1305_decrypt(key, nonce, &[], &mut plaintext, tag);
+
+	zeroize(&mut key);
+
+	match result {
+		Ok(()) => Ok(plaintext),
+		Err(_) => Err(Error::DecryptionFailed),
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex_vec;
+
+#[test]
+fn test_round_trip() {
+	let secret = StaticSecret::from_bytes([0x42; 32]);
+	let recipient = PublicKey::from(&secret);
+
+	let message = encrypt_to(&recipient, b"attack at dawn", [0x11; 32]).unwrap();
+	assert_eq!(message.len(), 14 + OVERHEAD);
+	assert_eq!(decrypt(&secret, &message).unwrap(), b"attack at dawn");
+
+	let empty = encrypt_to(&recipient, b"", [0x12; 32]).unwrap();
+	assert_eq!(decrypt(&secret, &empty).unwrap(), b"");
+
+	let other_secret = StaticSecret::from_bytes([0x43; 32]);
+	assert_eq!(decrypt(&other_secret, &message), Err(Error::DecryptionFailed));
+
+	for i in [0, 32, message.len() - 1] {
+		let mut modified = message.clone();
+		modified[i] ^= 1;
+		assert_eq!(decrypt(&secret, &modified), Err(Error::DecryptionFailed));
+	}
+
+	assert_eq!(decrypt(&secret, &message[0 .. OVERHEAD - 1]), Err(Error::DecryptionFailed));
+}
+
+#[test]
+fn test_rejects_small_order_keys() {
+	let small_order = PublicKey::from_bytes([0; 32]);
+	assert_eq!(encrypt_to(&small_order, b"message", [0x11; 32]), Err(Error::InvalidPublicKey));
+}
+
+// pins the wire format, checked against python's cryptography package, using
+// its X25519, HKDF and ChaCha20Poly1305 classes with HChaCha20 from section 2.2
+// of draft-irtf-cfrg-xchacha
+#[test]
+fn test_known_answer() {
+	let secret = StaticSecret::from_bytes([0x42; 32]);
+	let message = encrypt_to(&PublicKey::from(&secret), b"libkrypton", [0x11; 32]).unwrap();
+
+	assert_eq!(message, hex_vec(
+		"7b4e909bbe7ffe44c465a220037d608ee35897d31ef972f07f74892cb0f73f13\
+		346b1a19c704b48481b5bbc7e214e5b4458c8ee22dc533a65767"
+	));
+}
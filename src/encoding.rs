@@ -0,0 +1,456 @@
+//! Hex, base32 and base64 encoding and decoding in constant time, like
+//! libsodium's `sodium_bin2hex` and `sodium_base642bin`. Characters are
+//! converted with arithmetic instead of table lookups or branches, so that
+//! encoding or decoding a secret, such as a private key read from a config
+//! file, doesn't leak it through cache or branch timing. Only the length of
+//! the input, and whether it's valid, can affect the time taken.
+//!
+//! Decoding writes into a caller-provided buffer and returns the number of
+//! bytes written, which is never more than the length of the text, so it
+//! works without allocating:
+//!
+//! ```
+//! use libkrypton::encoding::{self, Base64Variant};
+//!
+//! let mut key = [0; 32];
+//! let text = "QkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkI=";
+//!
+//! assert_eq!(encoding::decode_base64(text, Base64Variant::Standard, &mut key), Ok(32));
+//! assert_eq!(key, [0x42; 32]);
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::string::String;
+
+/// An error returned when decoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The text has an invalid character, an invalid length, misplaced
+	/// padding, or nonzero unused bits at the end.
+	InvalidEncoding,
+	/// The output buffer is too short for the decoded bytes.
+	BufferTooSmall,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid encoding",
+			Error::BufferTooSmall => "output buffer too small",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The base64 alphabets and padding rules from
+/// [IETF RFC 4648](https://datatracker.ietf.org/doc/html/rfc4648).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Base64Variant {
+	/// The standard alphabet, with `+` and `/`, padded with `=`.
+	Standard,
+	/// The standard alphabet, without padding.
+	StandardUnpadded,
+	/// The URL-safe alphabet, with `-` and `_`, padded with `=`.
+	UrlSafe,
+	/// The URL-safe alphabet, without padding, as used by JOSE.
+	UrlSafeUnpadded,
+}
+
+impl Base64Variant {
+	// the characters for 62 and 63, and whether there's padding
+	fn params(self) -> (u8, u8, bool) {
+		match self {
+			Base64Variant::Standard => (b'+', b'/', true),
+			Base64Variant::StandardUnpadded => (b'+', b'/', false),
+			Base64Variant::UrlSafe => (b'-', b'_', true),
+			Base64Variant::UrlSafeUnpadded => (b'-', b'_', false),
+		}
+	}
+}
+
+// the comparisons below return 0xff for true and 0 for false, and the
+// differences of bytes they're computed from always fit in an i16
+
+fn lt(x: u8, y: u8) -> u8 {
+	((x as i16 - y as i16) >> 8) as u8
+}
+
+fn eq(x: u8, y: u8) -> u8 {
+	(((x ^ y) as i16 - 1) >> 8) as u8
+}
+
+fn in_range(x: u8, low: u8, high: u8) -> u8 {
+	!lt(x, low) & !lt(high, x)
+}
+
+fn encode_hex_char(value: u8) -> u8 {
+	let digit = lt(value, 10);
+
+	(digit & value.wrapping_add(b'0')) | (!digit & value.wrapping_add(b'a' - 10))
+}
+
+// returns the value of the character, and 0xff if it's valid
+fn decode_hex_char(char: u8) -> (u8, u8) {
+	let digit = in_range(char, b'0', b'9');
+	let lower = in_range(char, b'a', b'f');
+	let upper = in_range(char, b'A', b'F');
+
+	let value = (digit & char.wrapping_sub(b'0'))
+		| (lower & char.wrapping_sub(b'a' - 10))
+		| (upper & char.wrapping_sub(b'A' - 10));
+
+	(value, digit | lower | upper)
+}
+
+#[cfg(any(feature = "std", test))]
+fn encode_base32_char(value: u8) -> u8 {
+	let letter = lt(value, 26);
+
+	(letter & value.wrapping_add(b'A')) | (!letter & value.wrapping_add(b'2' - 26))
+}
+
+fn decode_base32_char(char: u8) -> (u8, u8) {
+	let upper = in_range(char, b'A', b'Z');
+	let lower = in_range(char, b'a', b'z');
+	let digit = in_range(char, b'2', b'7');
+
+	let value = (upper & char.wrapping_sub(b'A'))
+		| (lower & char.wrapping_sub(b'a'))
+		| (digit & char.wrapping_sub(b'2' - 26));
+
+	(value, upper | lower | digit)
+}
+
+fn encode_base64_char(value: u8, char_62: u8, char_63: u8) -> u8 {
+	let upper = lt(value, 26);
+	let lower = !upper & lt(value, 52);
+	let digit = !lt(value, 52) & lt(value, 62);
+
+	(upper & value.wrapping_add(b'A'))
+		| (lower & value.wrapping_add(b'a' - 26))
+		| (digit & value.wrapping_sub(52 - b'0'))
+		| (eq(value, 62) & char_62)
+		| (eq(value, 63) & char_63)
+}
+
+fn decode_base64_char(char: u8, char_62: u8, char_63: u8) -> (u8, u8) {
+	let upper = in_range(char, b'A', b'Z');
+	let lower = in_range(char, b'a', b'z');
+	let digit = in_range(char, b'0', b'9');
+	let is_62 = eq(char, char_62);
+	let is_63 = eq(char, char_63);
+
+	let value = (upper & char.wrapping_sub(b'A'))
+		| (lower & char.wrapping_sub(b'a' - 26))
+		| (digit & char.wrapping_add(52 - b'0'))
+		| (is_62 & 62)
+		| (is_63 & 63);
+
+	(value, upper | lower | digit | is_62 | is_63)
+}
+
+// packs `bits_per_char` bits from each character into bytes, returning the
+// number of bytes written, and checking that any unused bits at the end are
+// zero if `canonical` is set
+fn unpack(
+	text: &[u8],
+	bits_per_char: u32,
+	decode_char: impl Fn(u8) -> (u8, u8),
+	canonical: bool,
+	out: &mut [u8],
+) -> Result<usize, Error> {
+	let len = text.len() * bits_per_char as usize / 8;
+
+	if out.len() < len {
+		return Err(Error::BufferTooSmall);
+	}
+
+	let mut valid = 0xff;
+	let mut acc = 0u32;
+	let mut bits = 0;
+	let mut pos = 0;
+
+	for &char in text {
+		let (value, char_valid) = decode_char(char);
+		valid &= char_valid;
+
+		acc = (acc << bits_per_char) | value as u32;
+		bits += bits_per_char;
+
+		if bits >= 8 {
+			bits -= 8;
+			out[pos] = (acc >> bits) as u8;
+			pos += 1;
+		}
+
+		acc &= (1 << bits) - 1;
+	}
+
+	if canonical {
+		valid &= eq(acc as u8, 0);
+	}
+
+	if valid != 0xff {
+		out[.. len].fill(0);
+		return Err(Error::InvalidEncoding);
+	}
+
+	Ok(len)
+}
+
+/// Appends the lowercase hex encoding of `bytes` to `out`.
+#[cfg(feature = "std")]
+pub fn encode_hex(bytes: &[u8], out: &mut String) {
+	out.reserve(2 * bytes.len());
+
+	// writing to a string never fails
+	let _ = write_hex(bytes, out);
+}
+
+/// Writes the lowercase hex encoding of `bytes` to `out`, such as a
+/// formatter, which works without allocating.
+pub fn write_hex<W: fmt::Write>(bytes: &[u8], out: &mut W) -> fmt::Result {
+	for &byte in bytes {
+		out.write_char(encode_hex_char(byte >> 4) as char)?;
+		out.write_char(encode_hex_char(byte & 0xf) as char)?;
+	}
+
+	Ok(())
+}
+
+/// Decodes hex, which may be uppercase or lowercase, into `out`, returning
+/// the number of bytes written.
+pub fn decode_hex(text: &str, out: &mut [u8]) -> Result<usize, Error> {
+	if !text.len().is_multiple_of(2) {
+		return Err(Error::InvalidEncoding);
+	}
+
+	unpack(text.as_bytes(), 4, decode_hex_char, true, out)
+}
+
+/// Appends the unpadded base32 encoding of `bytes` to `out`, with the
+/// uppercase alphabet from RFC 4648. This is the format of the secrets of
+/// one-time passwords.
+#[cfg(feature = "std")]
+pub fn encode_base32(bytes: &[u8], out: &mut String) {
+	out.reserve((8 * bytes.len()).div_ceil(5));
+
+	let mut acc = 0u32;
+	let mut bits = 0;
+
+	for &byte in bytes {
+		acc = (acc << 8) | byte as u32;
+		bits += 8;
+
+		while bits >= 5 {
+			bits -= 5;
+			out.push(encode_base32_char(((acc >> bits) & 0x1f) as u8) as char);
+		}
+
+		acc &= (1 << bits) - 1;
+	}
+
+	if bits != 0 {
+		out.push(encode_base32_char(((acc << (5 - bits)) & 0x1f) as u8) as char);
+	}
+}
+
+/// Decodes base32 into `out`, returning the number of bytes written. The text
+/// may be lowercase and may be padded. Unlike the other decoders, any unused
+/// bits at the end don't have to be zero, since some generators of one-time
+/// password secrets don't clear them.
+pub fn decode_base32(text: &str, out: &mut [u8]) -> Result<usize, Error> {
+	let text = text.as_bytes();
+	let unpadded = text.iter().rposition(|&x| x != b'=').map_or(0, |x| x + 1);
+
+	// padding fills the last group of 8 characters, and no length of
+	// unpadded text leaves more than 6 characters of it
+	if unpadded != text.len() && (!text.len().is_multiple_of(8) || text.len() - unpadded > 6) {
+		return Err(Error::InvalidEncoding);
+	}
+
+	let text = &text[.. unpadded];
+
+	// these lengths would leave a character that's only partly used
+	if [1, 3, 6].contains(&(text.len() % 8)) {
+		return Err(Error::InvalidEncoding);
+	}
+
+	unpack(text, 5, decode_base32_char, false, out)
+}
+
+/// Appends the base64 encoding of `bytes` to `out`, in the given `variant`.
+#[cfg(feature = "std")]
+pub fn encode_base64(bytes: &[u8], variant: Base64Variant, out: &mut String) {
+	out.reserve(4 * bytes.len().div_ceil(3));
+
+	// writing to a string never fails
+	let _ = write_base64(bytes, variant, out);
+}
+
+/// Writes the base64 encoding of `bytes` to `out`, in the given `variant`,
+/// which works without allocating.
+pub fn write_base64<W: fmt::Write>(bytes: &[u8], variant: Base64Variant, out: &mut W) -> fmt::Result {
+	let (char_62, char_63, pad) = variant.params();
+
+	for chunk in bytes.chunks(3) {
+		let mut group = [0; 3];
+		group[.. chunk.len()].copy_from_slice(chunk);
+		let group = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+		for i in 0 .. 4 {
+			if i <= chunk.len() {
+				let value = (group >> (18 - 6 * i)) & 0x3f;
+				out.write_char(encode_base64_char(value as u8, char_62, char_63) as char)?;
+			} else if pad {
+				out.write_char('=')?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Decodes base64 in the given `variant` into `out`, returning the number of
+/// bytes written. The padding must be present exactly when the variant has
+/// it, and any unused bits at the end must be zero, so that each input has
+/// exactly one valid encoding.
+pub fn decode_base64(text: &str, variant: Base64Variant, out: &mut [u8]) -> Result<usize, Error> {
+	let (char_62, char_63, pad) = variant.params();
+	let mut text = text.as_bytes();
+
+	if pad {
+		if !text.len().is_multiple_of(4) {
+			return Err(Error::InvalidEncoding);
+		}
+
+		for _ in 0 .. 2 {
+			if let Some(rest) = text.strip_suffix(b"=") {
+				text = rest;
+			}
+		}
+	}
+
+	if text.len() % 4 == 1 {
+		return Err(Error::InvalidEncoding);
+	}
+
+	unpack(text, 6, |char| decode_base64_char(char, char_62, char_63), true, out)
+}
+
+#[cfg(all(test, feature = "std"))]
+fn decode_to_vec(decode: impl Fn(&mut [u8]) -> Result<usize, Error>, max_len: usize) -> Result<std::vec::Vec<u8>, Error> {
+	let mut out = std::vec![0; max_len];
+	let len = decode(&mut out)?;
+	out.truncate(len);
+
+	Ok(out)
+}
+
+#[test]
+fn test_chars() {
+	// compared against table lookups for every byte
+	let hex_alphabet = b"0123456789abcdef";
+	let base32_alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+	let base64_alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	for value in 0 .. 16 {
+		assert_eq!(encode_hex_char(value), hex_alphabet[value as usize]);
+	}
+
+	for value in 0 .. 32 {
+		assert_eq!(encode_base32_char(value), base32_alphabet[value as usize]);
+	}
+
+	for value in 0 .. 64 {
+		assert_eq!(encode_base64_char(value, b'+', b'/'), base64_alphabet[value as usize]);
+	}
+
+	for char in 0 ..= 255u8 {
+		let hex = hex_alphabet.iter().position(|&x| x == char.to_ascii_lowercase());
+		let (value, valid) = decode_hex_char(char);
+		assert_eq!(hex, if valid == 0xff {Some(value as usize)} else {None});
+
+		let base32 = base32_alphabet.iter().position(|&x| x == char.to_ascii_uppercase());
+		let (value, valid) = decode_base32_char(char);
+		assert_eq!(base32, if valid == 0xff {Some(value as usize)} else {None});
+
+		let base64 = base64_alphabet.iter().position(|&x| x == char);
+		let (value, valid) = decode_base64_char(char, b'+', b'/');
+		assert_eq!(base64, if valid == 0xff {Some(value as usize)} else {None});
+	}
+}
+
+// from section 10 of RFC 4648
+#[test]
+#[cfg(feature = "std")]
+fn test_rfc4648_vectors() {
+	let vectors: [(&[u8], &str, &str, &str); 7] = [
+		(b"", "", "", ""),
+		(b"f", "66", "MY", "Zg=="),
+		(b"fo", "666f", "MZXQ", "Zm8="),
+		(b"foo", "666f6f", "MZXW6", "Zm9v"),
+		(b"foob", "666f6f62", "MZXW6YQ", "Zm9vYg=="),
+		(b"fooba", "666f6f6261", "MZXW6YTB", "Zm9vYmE="),
+		(b"foobar", "666f6f626172", "MZXW6YTBOI", "Zm9vYmFy"),
+	];
+
+	for (bytes, hex, base32, base64) in vectors {
+		let mut encoded = String::new();
+		encode_hex(bytes, &mut encoded);
+		assert_eq!(encoded, hex);
+		assert_eq!(decode_to_vec(|out| decode_hex(hex, out), 12).unwrap(), bytes);
+		assert_eq!(decode_to_vec(|out| decode_hex(&hex.to_ascii_uppercase(), out), 12).unwrap(), bytes);
+
+		let mut encoded = String::new();
+		encode_base32(bytes, &mut encoded);
+		assert_eq!(encoded, base32);
+		assert_eq!(decode_to_vec(|out| decode_base32(base32, out), 12).unwrap(), bytes);
+		assert_eq!(decode_to_vec(|out| decode_base32(&base32.to_ascii_lowercase(), out), 12).unwrap(), bytes);
+
+		let mut encoded = String::new();
+		encode_base64(bytes, Base64Variant::Standard, &mut encoded);
+		assert_eq!(encoded, base64);
+		assert_eq!(decode_to_vec(|out| decode_base64(base64, Base64Variant::Standard, out), 12).unwrap(), bytes);
+
+		let unpadded = base64.trim_end_matches('=');
+		let mut encoded = String::new();
+		encode_base64(bytes, Base64Variant::UrlSafeUnpadded, &mut encoded);
+		assert_eq!(encoded, unpadded);
+		assert_eq!(decode_to_vec(|out| decode_base64(unpadded, Base64Variant::UrlSafeUnpadded, out), 12).unwrap(), bytes);
+	}
+
+	assert_eq!(decode_to_vec(|out| decode_base32("MZXW6YQ=", out), 12).unwrap(), b"foob");
+}
+
+#[test]
+fn test_invalid() {
+	let mut out = [0; 8];
+
+	assert_eq!(decode_hex("6", &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_hex("6g", &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_hex("666f6f6261726261", &mut out[.. 7]), Err(Error::BufferTooSmall));
+
+	assert_eq!(decode_base32("MZXW6Y1", &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base32("MZX", &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base32("MZXW6YQ", &mut out), Ok(4));
+	assert_eq!(decode_base32("MZXW6YQ==", &mut out), Err(Error::InvalidEncoding));
+
+	// nonzero unused bits are only allowed in base32
+	assert_eq!(decode_base32("MZXW6YR", &mut out), Ok(4));
+	assert_eq!(decode_base64("Zm9vYh==", Base64Variant::Standard, &mut out), Err(Error::InvalidEncoding));
+
+	assert_eq!(decode_base64("Zm9vYg", Base64Variant::Standard, &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base64("Zm9vYg==", Base64Variant::StandardUnpadded, &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base64("Zg=v", Base64Variant::Standard, &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base64("Z===", Base64Variant::Standard, &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base64("+/8", Base64Variant::UrlSafeUnpadded, &mut out), Err(Error::InvalidEncoding));
+	assert_eq!(decode_base64("-_8", Base64Variant::UrlSafeUnpadded, &mut out), Ok(2));
+	assert_eq!(out[.. 2], [0xfb, 0xff]);
+}
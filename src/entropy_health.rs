@@ -0,0 +1,285 @@
+//! The continuous health tests from section 4.4 of
+//! [NIST SP 800-90B](https://csrc.nist.gov/pubs/sp/800/90/b/final), for
+//! detecting a noise source that has stopped working, such as a hardware
+//! random number generator stuck at one value, before its output is used for
+//! keys.
+//!
+//! The repetition count test catches a source that repeats the same sample
+//! many times in a row, and the adaptive proportion test catches one that
+//! produces some value far more often than expected in a window of 512
+//! samples. Both tests need to know how much min-entropy each sample is
+//! supposed to have, which is given in eighths of a bit, so a source credited
+//! with a quarter of a bit per sample passes `2`. The cutoffs are chosen for a
+//! false positive rate of `2 ** -30`, as the standard recommends.
+//!
+//! ```
+//! use libkrypton::entropy_health::{EntropyHealth, EntropySource, Error};
+//!
+//! // a hardware random number generator that has failed
+//! struct StuckTrng;
+//!
+//! impl EntropySource for StuckTrng {
+//!     fn sample(&mut self) -> u64 {
+//!         0x5a
+//!     }
+//! }
+//!
+//! // each sample is an 8-bit register, assessed at 4 bits of min-entropy
+//! let mut trng = EntropyHealth::new(StuckTrng, 32);
+//!
+//! assert_eq!(trng.sample(), Err(Error::RepetitionCount));
+//! ```
+
+use core::fmt;
+
+/// The number of samples in each window of the adaptive proportion test.
+pub const ADAPTIVE_PROPORTION_WINDOW: u32 = 512;
+
+/// The number of samples tested by [`EntropyHealth`] before the first one is
+/// returned, from section 4.3.
+pub const STARTUP_SAMPLES: u32 = 1024;
+
+// the adaptive proportion cutoffs for each min-entropy from 1 / 8 to 8 bits
+// per sample in steps of 1 / 8, which are one more than the smallest count
+// whose upper tail in a binomial distribution of 512 trials with probability
+// 2 ** -H is at most 2 ** -30
+const ADAPTIVE_PROPORTION_CUTOFFS: [u32; 64] = [
+	502, 477, 449, 422, 396, 371, 347, 325, 304, 284, 266, 248, 232, 217, 203, 190,
+	178, 167, 157, 147, 138, 129, 121, 114, 107, 101, 95, 89, 84, 79, 75, 71,
+	67, 63, 60, 56, 53, 51, 48, 46, 43, 41, 39, 37, 35, 34, 32, 31,
+	29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 19, 18, 17, 17, 16,
+];
+
+/// An error returned when a noise source fails a health test.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The same sample came up too many times in a row, so the source is
+	/// probably stuck.
+	RepetitionCount,
+	/// One sample came up too often in a window, so there's much less entropy
+	/// than expected.
+	AdaptiveProportion,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::RepetitionCount => "entropy source failed the repetition count test",
+			Error::AdaptiveProportion => "entropy source failed the adaptive proportion test",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The state of the repetition count and adaptive proportion tests, for
+/// sources that produce samples in their own way.
+#[derive(Clone, Debug)]
+pub struct HealthTests {
+	repetition_count_cutoff: u32,
+	adaptive_proportion_cutoff: u32,
+	last_sample: Option<u64>,
+	repetition_count: u32,
+	window_value: u64,
+	window_size: u32,
+	window_count: u32,
+}
+
+impl HealthTests {
+	/// Creates the tests for a source with `min_entropy_eighths` eighths of a
+	/// bit of min-entropy per sample.
+	///
+	/// # Panics
+	/// * Panics if `min_entropy_eighths` is 0 or more than 64. Sources with
+	///   more than 8 bits per sample can be tested as if they had 8.
+	pub fn new(min_entropy_eighths: u32) -> Self {
+		assert!((1 ..= 64).contains(&min_entropy_eighths));
+
+		Self {
+			// 1 + ceil(30 / H), from section 4.4.1
+			repetition_count_cutoff: 1 + (30 * 8u32).div_ceil(min_entropy_eighths),
+			adaptive_proportion_cutoff: ADAPTIVE_PROPORTION_CUTOFFS[min_entropy_eighths as usize - 1],
+			last_sample: None,
+			repetition_count: 0,
+			window_value: 0,
+			window_size: 0,
+			window_count: 0,
+		}
+	}
+
+	/// Runs both tests on the next sample from the source.
+	pub fn check(&mut self, sample: u64) -> Result<(), Error> {
+		if self.last_sample == Some(sample) {
+			self.repetition_count += 1;
+
+			if self.repetition_count >= self.repetition_count_cutoff {
+				return Err(Error::RepetitionCount);
+			}
+		} else {
+			self.last_sample = Some(sample);
+			self.repetition_count = 1;
+		}
+
+		if self.window_size == 0 {
+			self.window_value = sample;
+			self.window_count = 1;
+		} else if sample == self.window_value {
+			self.window_count += 1;
+
+			if self.window_count >= self.adaptive_proportion_cutoff {
+				return Err(Error::AdaptiveProportion);
+			}
+		}
+
+		self.window_size = (self.window_size + 1) % ADAPTIVE_PROPORTION_WINDOW;
+
+		Ok(())
+	}
+}
+
+/// A noise source, such as a ring oscillator or a hardware random number
+/// generator peripheral, which produces raw samples of up to 64 bits.
+pub trait EntropySource {
+	/// Returns the next raw sample.
+	fn sample(&mut self) -> u64;
+}
+
+/// Wraps an [`EntropySource`], running the health tests on every sample it
+/// produces. Once a test has failed, every later call returns the same error,
+/// since the source can't be trusted anymore.
+pub struct EntropyHealth<S: EntropySource> {
+	source: S,
+	tests: HealthTests,
+	started: bool,
+	failed: Option<Error>,
+}
+
+impl<S: EntropySource> EntropyHealth<S> {
+	/// Wraps `source`, which is assessed to have `min_entropy_eighths` eighths
+	/// of a bit of min-entropy per sample.
+	///
+	/// # Panics
+	/// * Panics if `min_entropy_eighths` is 0 or more than 64.
+	pub fn new(source: S, min_entropy_eighths: u32) -> Self {
+		Self {source, tests: HealthTests::new(min_entropy_eighths), started: false, failed: None}
+	}
+
+	fn next_tested(&mut self) -> Result<u64, Error> {
+		if let Some(error) = self.failed {
+			return Err(error);
+		}
+
+		let sample = self.source.sample();
+
+		if let Err(error) = self.tests.check(sample) {
+			self.failed = Some(error);
+			return Err(error);
+		}
+
+		Ok(sample)
+	}
+
+	/// Returns the next sample once it has passed the health tests. The first
+	/// call first runs the tests on [`STARTUP_SAMPLES`] samples, which are
+	/// thrown away.
+	pub fn sample(&mut self) -> Result<u64, Error> {
+		if !self.started {
+			for _ in 0 .. STARTUP_SAMPLES {
+				self.next_tested()?;
+			}
+
+			self.started = true;
+		}
+
+		self.next_tested()
+	}
+
+	/// Returns the error from a failed health test, if any.
+	pub fn failure(&self) -> Option<Error> {
+		self.failed
+	}
+
+	/// Returns the wrapped source.
+	pub fn into_inner(self) -> S {
+		self.source
+	}
+}
+
+#[test]
+fn test_cutoffs() {
+	// section 4.4.1 gives 1 + ceil(30 / H) for the repetition count test
+	assert_eq!(HealthTests::new(2).repetition_count_cutoff, 121);
+	assert_eq!(HealthTests::new(8).repetition_count_cutoff, 31);
+	assert_eq!(HealthTests::new(64).repetition_count_cutoff, 5);
+
+	// section 4.4.2 gives 1 + CRITBINOM(512, 2 ** -H, 1 - 2 ** -30) for the
+	// adaptive proportion test, computed exactly with python's fractions
+	assert_eq!(HealthTests::new(8).adaptive_proportion_cutoff, 325);
+	assert_eq!(HealthTests::new(16).adaptive_proportion_cutoff, 190);
+	assert_eq!(HealthTests::new(32).adaptive_proportion_cutoff, 71);
+}
+
+#[test]
+fn test_health() {
+	// a linear congruential generator, which of course only passes because the
+	// tests can't tell it apart from a real noise source
+	struct Lcg(u64);
+
+	impl EntropySource for Lcg {
+		fn sample(&mut self) -> u64 {
+			self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			self.0 >> 56
+		}
+	}
+
+	// the same, but stuck for a while after a number of samples
+	struct Stuck(Lcg, u32);
+
+	impl EntropySource for Stuck {
+		fn sample(&mut self) -> u64 {
+			self.1 += 1;
+
+			match self.1 {
+				2000 ..= 2100 => 7,
+				_ => self.0.sample(),
+			}
+		}
+	}
+
+	// the same, but with one value far too common
+	struct Biased(Lcg, u32);
+
+	impl EntropySource for Biased {
+		fn sample(&mut self) -> u64 {
+			self.1 += 1;
+
+			match self.1 % 4 {
+				0 => self.0.sample(),
+				_ => 7,
+			}
+		}
+	}
+
+	let mut source = EntropyHealth::new(Lcg(1), 32);
+
+	for _ in 0 .. 10000 {
+		source.sample().unwrap();
+	}
+
+	let mut source = EntropyHealth::new(Stuck(Lcg(1), 0), 32);
+
+	for _ in 0 .. 100 {
+		source.sample().unwrap();
+	}
+
+	assert_eq!((0 .. 1000).find_map(|_| source.sample().err()), Some(Error::RepetitionCount));
+	assert_eq!(source.sample(), Err(Error::RepetitionCount));
+	assert_eq!(source.failure(), Some(Error::RepetitionCount));
+
+	// caught during the startup tests, so no sample is ever returned
+	let mut source = EntropyHealth::new(Biased(Lcg(1), 0), 32);
+	assert_eq!(source.sample(), Err(Error::AdaptiveProportion));
+}
@@ -0,0 +1,34 @@
+//! The error type of the `try_*` functions, which return an error in the
+//! cases where their counterparts panic, for code that must never abort.
+
+use core::fmt;
+
+/// An error returned by one of the panic-free `try_*` functions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A hash function was given more input than its padding can encode the
+	/// length of.
+	MessageTooLong,
+	/// A stream cipher has no keystream left to encrypt or decrypt with.
+	KeystreamExhausted,
+	/// An input wasn't a length the function accepts.
+	InvalidLength,
+	/// An input wasn't validly encoded.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::MessageTooLong => "message too long",
+			Error::KeystreamExhausted => "keystream exhausted",
+			Error::InvalidLength => "invalid length",
+			Error::InvalidEncoding => "invalid encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,451 @@
+//! Finite-field Diffie-Hellman key exchange over the ffdhe2048, ffdhe3072
+//! and ffdhe4096 groups from [IETF RFC 7919](https://datatracker.ietf.org/doc/html/rfc7919),
+//! for talking to peers that can't do [X25519](crate::x25519), such as older
+//! TLS and IKE implementations. Elliptic curves are faster and have much
+//! shorter keys, so X25519 should be used whenever both sides support it.
+//!
+//! The groups' primes are safe primes, so the only small subgroup is the one
+//! of order 2, which [`PublicKey::from_bytes`] rules out by requiring that
+//! 1 < y < p - 1. Private keys are short exponents of twice the groups'
+//! security level, which section 5.2 of the RFC allows, and exponentiation
+//! takes the same time for every private key.
+//!
+//! ```
+//! use libkrypton::ffdhe::{Group, PrivateKey};
+//!
+//! let alice = PrivateKey::from_bytes(Group::Ffdhe2048, &[0x11; 32]).unwrap();
+//! let bob = PrivateKey::from_bytes(Group::Ffdhe2048, &[0x22; 32]).unwrap();
+//!
+//! let alice_secret = alice.diffie_hellman(&bob.public_key()).unwrap();
+//! let bob_secret = bob.diffie_hellman(&alice.public_key()).unwrap();
+//! assert_eq!(alice_secret.as_bytes(), bob_secret.as_bytes());
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::bignum::{Limbs, Modulus, MAX_LIMBS};
+use crate::zeroize::zeroize;
+
+// the largest prime's length in bytes
+const MAX_LEN: usize = 512;
+
+// the longest private key, in bytes
+const MAX_EXPONENT_LEN: usize = 48;
+
+// the primes from appendix A of the RFC, in big-endian hex
+const FFDHE2048_HEX: &str = "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+	a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+	d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+	984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+	bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+	ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+	9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+	c58ef1837d1683b2c6f34a26c1b2effa886b423861285c97ffffffffffffffff";
+
+const FFDHE3072_HEX: &str = "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+	a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+	d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+	984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+	bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+	ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+	9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+	c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b\
+	bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c\
+	aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff\
+	5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e\
+	0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b66c62e37ffffffffffffffff";
+
+const FFDHE4096_HEX: &str = "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+	a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+	d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+	984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+	bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+	ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+	9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+	c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b\
+	bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c\
+	aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff\
+	5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e\
+	0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b669e1ef16e6f52c3164df4fb\
+	7930e9e4e58857b6ac7d5f42d69f6d187763cf1d5503400487f55ba57e31cc7a\
+	7135c886efb4318aed6a1e012d9e6832a907600a918130c46dc778f971ad0038\
+	092999a333cb8b7a1a1db93d7140003c2a4ecea9f98d0acc0a8291cdcec97dcf\
+	8ec9b55a7f88a46b4db5a851f44182e1c68a007e5e655f6affffffffffffffff";
+
+/// An error returned when decoding a key or computing a shared secret fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The key is not the right length for its group.
+	InvalidLength,
+	/// The public key is 0, 1, p - 1, or not less than p.
+	InvalidPublicKey,
+	/// The private and public keys are in different groups.
+	GroupMismatch,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidLength => "invalid ffdhe key length",
+			Error::InvalidPublicKey => "invalid ffdhe public key",
+			Error::GroupMismatch => "ffdhe keys are in different groups",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// One of the groups from RFC 7919, which all have the generator 2.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Group {
+	Ffdhe2048,
+	Ffdhe3072,
+	Ffdhe4096,
+}
+
+impl Group {
+	/// All the groups, from smallest to largest.
+	pub const ALL: [Group; 3] = [Group::Ffdhe2048, Group::Ffdhe3072, Group::Ffdhe4096];
+
+	/// Returns the group's name in the TLS supported groups registry, such
+	/// as `ffdhe2048`.
+	pub fn name(self) -> &'static str {
+		match self {
+			Group::Ffdhe2048 => "ffdhe2048",
+			Group::Ffdhe3072 => "ffdhe3072",
+			Group::Ffdhe4096 => "ffdhe4096",
+		}
+	}
+
+	/// Returns the length of the prime in bytes, which public keys and
+	/// shared secrets are always encoded as.
+	pub fn prime_len(self) -> usize {
+		match self {
+			Group::Ffdhe2048 => 256,
+			Group::Ffdhe3072 => 384,
+			Group::Ffdhe4096 => 512,
+		}
+	}
+
+	/// Returns the length of private keys in bytes, which is twice the
+	/// group's estimated security level of 103, 125 or 150 bits, rounded up.
+	pub fn private_key_len(self) -> usize {
+		match self {
+			Group::Ffdhe2048 => 32,
+			Group::Ffdhe3072 => 40,
+			Group::Ffdhe4096 => 48,
+		}
+	}
+
+	// returns the prime in its first prime_len() bytes
+	fn prime(self) -> [u8; MAX_LEN] {
+		let hex = match self {
+			Group::Ffdhe2048 => FFDHE2048_HEX,
+			Group::Ffdhe3072 => FFDHE3072_HEX,
+			Group::Ffdhe4096 => FFDHE4096_HEX,
+		};
+
+		let mut prime = [0; MAX_LEN];
+		crate::encoding::decode_hex(hex, &mut prime).unwrap();
+		prime
+	}
+
+	fn modulus(self) -> Modulus {
+		// the primes are odd and fit in MAX_LIMBS
+		Modulus::from_be_bytes(&self.prime()[.. self.prime_len()]).unwrap()
+	}
+}
+
+impl fmt::Display for Group {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// A public key, y = 2<sup>x</sup> mod p for the private key x.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+	group: Group,
+	bytes: [u8; MAX_LEN],
+}
+
+impl PublicKey {
+	/// Decodes a public key from its big-endian encoding, which must be
+	/// exactly [`Group::prime_len`] bytes, with leading zeros. Fails unless
+	/// 1 < y < p - 1, as section 5.1 of the RFC requires.
+	pub fn from_bytes(group: Group, bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() != group.prime_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		// both are big-endian and the same length, so they compare the same
+		// way as numbers; the primes end in 0xff, so p - 1 ends in 0xfe
+		let mut minus_one = group.prime();
+		let minus_one = &mut minus_one[.. bytes.len()];
+		minus_one[bytes.len() - 1] = 0xfe;
+
+		let (rest, last) = bytes.split_at(bytes.len() - 1);
+		let at_most_one = rest.iter().all(|&byte| byte == 0) && last[0] <= 1;
+
+		if at_most_one || bytes >= &*minus_one {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		let mut key = Self {group, bytes: [0; MAX_LEN]};
+		key.bytes[.. bytes.len()].copy_from_slice(bytes);
+		Ok(key)
+	}
+
+	/// Returns the group the key is in.
+	pub fn group(&self) -> Group {
+		self.group
+	}
+
+	/// Returns the big-endian encoding of the key, which is
+	/// [`Group::prime_len`] bytes long.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[.. self.group.prime_len()]
+	}
+}
+
+impl fmt::Debug for PublicKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "PublicKey({}, ", self.group)?;
+		crate::encoding::write_hex(self.as_bytes(), f)?;
+		f.write_str(")")
+	}
+}
+
+/// A private key, which is a short exponent and can be used for any number
+/// of key exchanges. Zeroized when dropped.
+#[derive(Clone)]
+pub struct PrivateKey {
+	group: Group,
+	exponent: [u8; MAX_EXPONENT_LEN],
+}
+
+impl PrivateKey {
+	/// Creates a private key from [`Group::private_key_len`] big-endian
+	/// bytes, which should be generated by a secure random source.
+	pub fn from_bytes(group: Group, bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() != group.private_key_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut key = Self {group, exponent: [0; MAX_EXPONENT_LEN]};
+		key.exponent[.. bytes.len()].copy_from_slice(bytes);
+		Ok(key)
+	}
+
+	/// Generates a new private key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(group: Group, rng: &mut R) -> Self {
+		let mut key = Self {group, exponent: [0; MAX_EXPONENT_LEN]};
+		rng.fill_bytes(&mut key.exponent[.. group.private_key_len()]);
+		key
+	}
+
+	/// Returns the group the key is in.
+	pub fn group(&self) -> Group {
+		self.group
+	}
+
+	/// Returns the big-endian bytes of the private key. These must be kept
+	/// secret.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.exponent[.. self.group.private_key_len()]
+	}
+
+	/// Computes the public key, which takes about as long as a key exchange.
+	pub fn public_key(&self) -> PublicKey {
+		let modulus = self.group.modulus();
+
+		let mut two = [0; MAX_LIMBS];
+		two[0] = 2;
+
+		let mut key = PublicKey {group: self.group, bytes: [0; MAX_LEN]};
+		modulus.encode(&modulus.pow(&two, self.as_bytes()), &mut key.bytes[.. self.group.prime_len()]);
+		key
+	}
+
+	/// Computes the secret shared with the owner of `their_public`, which
+	/// must be in the same group.
+	pub fn diffie_hellman(&self, their_public: &PublicKey) -> Result<SharedSecret, Error> {
+		if their_public.group != self.group {
+			return Err(Error::GroupMismatch);
+		}
+
+		let modulus = self.group.modulus();
+
+		// checked to be in range when the public key was decoded
+		let y: Limbs = modulus.parse(their_public.as_bytes()).unwrap();
+
+		let mut secret = SharedSecret {len: self.group.prime_len(), bytes: [0; MAX_LEN]};
+		let mut z = modulus.pow(&y, self.as_bytes());
+		modulus.encode(&z, &mut secret.bytes[.. secret.len]);
+		zeroize(&mut z);
+
+		Ok(secret)
+	}
+}
+
+impl Drop for PrivateKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.exponent);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for PrivateKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.exponent);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for PrivateKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for PrivateKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("PrivateKey")
+			.field("group", &self.group)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The result of a key exchange. Zeroized when dropped.
+pub struct SharedSecret {
+	len: usize,
+	bytes: [u8; MAX_LEN],
+}
+
+impl SharedSecret {
+	/// Returns the big-endian bytes of the shared secret, which are
+	/// [`Group::prime_len`] bytes long, keeping any leading zeros as TLS 1.3
+	/// does. TLS 1.2 strips them instead. They should be passed through a
+	/// KDF before being used as a key.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[.. self.len]
+	}
+}
+
+impl Drop for SharedSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SharedSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SharedSecret {}
+
+// deliberately doesn't print the secret
+impl fmt::Debug for SharedSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SharedSecret").finish_non_exhaustive()
+	}
+}
+
+// checked against python's pow, with the ffdhe2048 prime from OpenSSL's built-in group
+#[test]
+fn test_known_answer() {
+	let mut alice_private = [0; 32];
+	let mut bob_private = [0; 32];
+
+	for i in 0 .. 32 {
+		alice_private[i] = i as u8 + 1;
+		bob_private[i] = i as u8 + 101;
+	}
+
+	let alice = PrivateKey::from_bytes(Group::Ffdhe2048, &alice_private).unwrap();
+	let bob = PrivateKey::from_bytes(Group::Ffdhe2048, &bob_private).unwrap();
+
+	let mut expected = [0; 256];
+	crate::encoding::decode_hex(
+		"0eef0c0eae9c65a3332cdc742b58561d362c0af526f8ad528b19ff39c9143431\
+		2f83302ed9dc6f2b84b250482dbd80962154f8a6d683741bf8bf4f3fdee22f80\
+		541a77553d1ae7a096c521f869987ae5eca1090d93e7bdea0b4349f17fee34f4\
+		775bfb380f426dec937b25825680c6e2b8c4c9eede64b91f5cdc02ba78e75908\
+		d47cd27fda4b5188c22baf2acd1e861ed0864e9eb9346640d354eada9f7156a6\
+		01387ba5f1b8609733707803a5ac866fc1bbe54b15a99934727bc5c157f893d8\
+		67a89abf0ce3dd0cf3d7ce00073db8b3bbae1486faedc462f7edeb3dc44ba303\
+		36de3d4dbfb4f14f143c46b0ba93f316f50c3377b9a38a27ae048f5605a4dcbf",
+		&mut expected,
+	).unwrap();
+	assert_eq!(alice.public_key().as_bytes(), expected);
+
+	crate::encoding::decode_hex(
+		"a0d416535e485f2f8a95ecbf89d4a6df9552dede279a78c136ad2914805153d5\
+		6aab53ca8e87d865201891e1c9c14c2a12f34f6d3caea3b685838a11d90eed08\
+		9812a9feecf1a02f633ea3d36ce86b1c1319aa78a25f5d748b1df5324048423a\
+		7b7016744592806ebb6c8571a6e633612cb67134576c726ba142a6111c92ce5f\
+		87e38e4693d445d08bee7148dcdfce857d4436fdcdcf17c69093708b7e8557ba\
+		6cdbaa500ff99a12cdccbd15e29bbf19407dce39a06754a358cf217127af6e92\
+		4e43e1db0193af5759e4a57f372b1d42524bf22eecb67b33b68c22fd402e5243\
+		46045c89c574495545f87469eb5cdd06bacfc4e3100d20aaa5fbbb684c25acb5",
+		&mut expected,
+	).unwrap();
+	assert_eq!(alice.diffie_hellman(&bob.public_key()).unwrap().as_bytes(), expected);
+	assert_eq!(bob.diffie_hellman(&alice.public_key()).unwrap().as_bytes(), expected);
+}
+
+#[test]
+fn test_groups() {
+	for group in Group::ALL {
+		let alice = PrivateKey::from_bytes(group, &[0x11; MAX_EXPONENT_LEN][.. group.private_key_len()]).unwrap();
+		let bob = PrivateKey::from_bytes(group, &[0x22; MAX_EXPONENT_LEN][.. group.private_key_len()]).unwrap();
+
+		let public_key = PublicKey::from_bytes(group, alice.public_key().as_bytes()).unwrap();
+		assert_eq!(public_key, alice.public_key());
+		assert_eq!(public_key.as_bytes().len(), group.prime_len());
+
+		let secret = bob.diffie_hellman(&public_key).unwrap();
+		assert_eq!(secret.as_bytes(), alice.diffie_hellman(&bob.public_key()).unwrap().as_bytes());
+		assert_eq!(secret.as_bytes().len(), group.prime_len());
+	}
+
+	let alice = PrivateKey::from_bytes(Group::Ffdhe2048, &[0x11; 32]).unwrap();
+	let bob = PrivateKey::from_bytes(Group::Ffdhe3072, &[0x22; 40]).unwrap();
+	assert_eq!(alice.diffie_hellman(&bob.public_key()).unwrap_err(), Error::GroupMismatch);
+	assert_eq!(PrivateKey::from_bytes(Group::Ffdhe3072, &[0x22; 32]).unwrap_err(), Error::InvalidLength);
+}
+
+#[test]
+fn test_invalid_public_keys() {
+	let prime = Group::Ffdhe2048.prime();
+	let mut key = [0; 256];
+
+	// 0 and 1
+	assert_eq!(PublicKey::from_bytes(Group::Ffdhe2048, &key).unwrap_err(), Error::InvalidPublicKey);
+	key[255] = 1;
+	assert_eq!(PublicKey::from_bytes(Group::Ffdhe2048, &key).unwrap_err(), Error::InvalidPublicKey);
+	key[255] = 2;
+	assert!(PublicKey::from_bytes(Group::Ffdhe2048, &key).is_ok());
+	assert_eq!(PublicKey::from_bytes(Group::Ffdhe2048, &key[1 ..]).unwrap_err(), Error::InvalidLength);
+
+	// p - 2, p - 1, p and 2 ** 2048 - 1
+	key.copy_from_slice(&prime[.. 256]);
+	key[255] = 0xfd;
+	assert!(PublicKey::from_bytes(Group::Ffdhe2048, &key).is_ok());
+
+	for last in [0xfe, 0xff] {
+		key[255] = last;
+		assert_eq!(PublicKey::from_bytes(Group::Ffdhe2048, &key).unwrap_err(), Error::InvalidPublicKey);
+	}
+
+	assert_eq!(PublicKey::from_bytes(Group::Ffdhe2048, &[0xff; 256]).unwrap_err(), Error::InvalidPublicKey);
+}
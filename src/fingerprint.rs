@@ -0,0 +1,194 @@
+//! SHA-256 fingerprints of public keys, for showing users a short string they
+//! can compare to check that they have the right key. Fingerprints can be
+//! displayed in hex, in the `SHA256:` base64 form printed by `ssh-keygen -l`,
+//! or as OpenSSH's randomart, which is easier to recognize at a glance.
+//!
+//! ```
+//! use libkrypton::ed25519::VerifyingKey;
+//!
+//! let key = VerifyingKey::from_openssh("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG8BW2di64VB1hFlhf/OG3bP57CImMNCsTR2A/rQlkCR").unwrap();
+//! let fingerprint = key.fingerprint();
+//!
+//! assert_eq!(fingerprint.to_string(), "SHA256:BpIZKIkFlC2k5jLM5HwCn7AvFS3W1OhuVp7ENwVKE6A");
+//! ```
+
+use core::fmt;
+
+use std::string::String;
+
+use crate::base64;
+use crate::sha2::sha256;
+
+// the randomart board is 17 columns by 9 rows, like OpenSSH's
+const WIDTH: usize = 17;
+const HEIGHT: usize = 9;
+
+// the characters for squares visited 0 to 14 or more times, and the start
+// and end squares
+const SYMBOLS: &[u8; 17] = b" .o+=*BOX@%&#/^SE";
+
+/// The SHA-256 fingerprint of a public key, along with the key type and size
+/// shown in its randomart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+	digest: [u8; 32],
+	key_type: &'static str,
+	bits: u32,
+}
+
+impl Fingerprint {
+	/// Computes the fingerprint of `encoded_key`, the canonical encoding of a
+	/// public key, such as the raw bytes of an X25519 key. `key_type` and
+	/// `bits` are only used to title the randomart, for example `"X25519"`
+	/// and `256`.
+	///
+	/// Ed25519 keys should use
+	/// [`VerifyingKey::fingerprint`](crate::ed25519::VerifyingKey::fingerprint)
+	/// instead, which matches OpenSSH.
+	pub fn new(key_type: &'static str, bits: u32, encoded_key: &[u8]) -> Self {
+		Self {digest: sha256(encoded_key), key_type, bits}
+	}
+
+	/// Returns the SHA-256 digest making up this fingerprint.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.digest
+	}
+
+	/// Returns this fingerprint as colon-separated lowercase hex, such as
+	/// `06:92:19:...`.
+	pub fn to_hex(&self) -> String {
+		let mut out = String::with_capacity(3 * self.digest.len());
+
+		for (i, byte) in self.digest.iter().enumerate() {
+			if i != 0 {
+				out.push(':');
+			}
+
+			out.push_str(&format!("{:02x}", byte));
+		}
+
+		out
+	}
+
+	/// Returns this fingerprint as unpadded base64 after a `SHA256:` prefix,
+	/// the form printed by `ssh-keygen -l`. This is also how it's displayed.
+	pub fn to_base64(&self) -> String {
+		let mut out = String::from("SHA256:");
+		base64::encode_unpadded(&self.digest, &mut out);
+		out
+	}
+
+	/// Renders this fingerprint as randomart, exactly like `ssh-keygen -lv`:
+	/// a "drunken bishop" walks a 17 by 9 board two bits of the digest at a
+	/// time, and each square shows how often it was visited. The result is
+	/// 11 lines, without a trailing newline.
+	pub fn randomart(&self) -> String {
+		let mut board = [[0u8; WIDTH]; HEIGHT];
+		let (mut x, mut y) = (WIDTH / 2, HEIGHT / 2);
+
+		for &byte in &self.digest {
+			for step in 0 .. 4 {
+				let bits = byte >> (2 * step);
+
+				x = if bits & 1 != 0 { (x + 1).min(WIDTH - 1) } else { x.saturating_sub(1) };
+				y = if bits & 2 != 0 { (y + 1).min(HEIGHT - 1) } else { y.saturating_sub(1) };
+
+				if board[y][x] < 14 {
+					board[y][x] += 1;
+				}
+			}
+		}
+
+		board[HEIGHT / 2][WIDTH / 2] = 15;
+		board[y][x] = 16;
+
+		let mut title = format!("[{} {}]", self.key_type, self.bits);
+
+		if title.len() > WIDTH - 2 {
+			title = format!("[{}]", self.key_type);
+		}
+
+		let mut out = String::new();
+		push_border(&mut out, &title);
+		out.push('\n');
+
+		for row in &board {
+			out.push('|');
+			out.extend(row.iter().map(|&x| SYMBOLS[x as usize] as char));
+			out.push_str("|\n");
+		}
+
+		push_border(&mut out, "[SHA256]");
+		out
+	}
+}
+
+// pushes a horizontal border with `label` centered in it
+fn push_border(out: &mut String, label: &str) {
+	let left = WIDTH.saturating_sub(label.len()) / 2;
+	let right = WIDTH.saturating_sub(left + label.len());
+
+	out.push('+');
+	out.extend(std::iter::repeat_n('-', left));
+	out.push_str(label);
+	out.extend(std::iter::repeat_n('-', right));
+	out.push('+');
+}
+
+impl fmt::Display for Fingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.to_base64())
+	}
+}
+
+// checked against ssh-keygen -lv
+#[test]
+fn test_randomart() {
+	let key = crate::ed25519::VerifyingKey::from_openssh("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIP9x3HHNklu4r4uKfgjNNhaOuzd1/9v1UTogytdb/cy9").unwrap();
+	let fingerprint = key.fingerprint();
+
+	assert_eq!(fingerprint.to_base64(), "SHA256:cQ1PjLsa+orFQ8uyKl19qXdDlIvZzjUuc5p8f4NXXNs");
+	assert_eq!(fingerprint.randomart(), "\
++--[ED25519 256]--+
+|          .o.    |
+|          .=.    |
+|        . .oo    |
+|         o+     .|
+|     .. S* o   .+|
+|    .+..* = o  .E|
+| . .. *+ * o .. .|
+|. .  =o.o.B.+. o.|
+| ...o .+..+B .o..|
++----[SHA256]-----+");
+
+	let key = crate::ed25519::VerifyingKey::from_openssh("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG8BW2di64VB1hFlhf/OG3bP57CImMNCsTR2A/rQlkCR").unwrap();
+
+	assert_eq!(key.fingerprint().randomart(), "\
++--[ED25519 256]--+
+|=*+.+++...       |
+|=o.*.=.o  .      |
+|++E.*.o  .       |
+|X= +..+.o        |
+|+B+o + oS.       |
+|.+o + o.         |
+|. .o             |
+| .               |
+|                 |
++----[SHA256]-----+");
+}
+
+#[test]
+fn test_forms() {
+	let fingerprint = Fingerprint::new("X25519", 256, b"abc");
+
+	assert_eq!(
+		fingerprint.to_hex(),
+		"ba:78:16:bf:8f:01:cf:ea:41:41:40:de:5d:ae:22:23:b0:03:61:a3:96:17:7a:9c:b4:10:ff:61:f2:00:15:ad",
+	);
+	assert_eq!(fingerprint.to_string(), "SHA256:ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0");
+	assert!(fingerprint.randomart().starts_with("+--[X25519 256]---+\n"));
+
+	// titles too long for the border drop the size
+	let fingerprint = Fingerprint::new("ML-DSA-65", 15616, b"abc");
+	assert!(fingerprint.randomart().starts_with("+---[ML-DSA-65]---+\n"));
+}
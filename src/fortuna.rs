@@ -0,0 +1,385 @@
+//! Implemented according to chapter 9 of
+//! [Cryptography Engineering](https://www.schneier.com/books/cryptography-engineering/)
+//! by Ferguson, Schneier and Kohno. Fortuna is a pseudorandom generator for
+//! long-running programs that collect entropy from many sources over time,
+//! such as interrupt timings, network packets and disk activity.
+//!
+//! Entropy is added as small events spread over 32 pools. Whenever the first
+//! pool has collected enough, the generator is reseeded from a subset of the
+//! pools, with pool `i` taking part in every `2 ** i`th reseed, so even if an
+//! attacker can predict most of the events, some pool eventually gathers
+//! enough unpredictable entropy to recover from a compromised state. The
+//! generator itself is AES-256 in counter mode, and replaces its key after
+//! every request so that earlier output can't be recovered from a later
+//! state.
+
+use core::fmt;
+
+use crate::aes::Aes;
+use crate::sha2::Sha256;
+use crate::zeroize::zeroize;
+
+/// The number of entropy pools.
+pub const NUM_POOLS: usize = 32;
+
+// the first pool must have received this many bytes of events before a reseed
+const MIN_POOL_SIZE: u64 = 64;
+
+// reseeds are at least this many milliseconds apart, so that an attacker who
+// can trigger requests can't drain the pools faster than they fill
+const MIN_RESEED_INTERVAL_MS: u64 = 100;
+
+// the generator is rekeyed at least this often, in bytes
+const MAX_REQUEST_SIZE: usize = 1 << 20;
+
+/// An error returned when generating random bytes fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The generator hasn't been seeded yet, either from the pools or with
+	/// [`Fortuna::reseed_with`].
+	NotSeeded,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::NotSeeded => "fortuna has not been seeded",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// SHA-256 applied twice, which the book uses to avoid length extension
+fn sha256d(hasher: Sha256) -> [u8; 32] {
+	let mut inner = hasher.out();
+	let mut outer = Sha256::new();
+	outer.add_bytes(&inner);
+	zeroize(&mut inner);
+
+	outer.out()
+}
+
+#[derive(Clone)]
+struct Generator {
+	key: [u8; 32],
+	aes: Aes,
+	// a counter of 0 means the generator hasn't been seeded
+	counter: u128,
+}
+
+impl Generator {
+	fn new() -> Self {
+		Self {key: [0; 32], aes: Aes::new_256([0; 32]), counter: 0}
+	}
+
+	fn set_key(&mut self, key: [u8; 32]) {
+		self.key = key;
+		self.aes = Aes::new_256(key);
+	}
+
+	// starts the reseed hash with the current key, leaving the seed to be
+	// added by the caller
+	fn reseed_hasher(&self) -> Sha256 {
+		let mut hasher = Sha256::new();
+		hasher.add_bytes(&self.key);
+		hasher
+	}
+
+	fn finish_reseed(&mut self, hasher: Sha256) {
+		self.set_key(sha256d(hasher));
+		self.counter = self.counter.wrapping_add(1);
+	}
+
+	fn generate_blocks(&mut self, out: &mut [u8]) {
+		for chunk in out.chunks_mut(16) {
+			let mut block = self.counter.to_le_bytes();
+			self.aes.encrypt_block(&mut block);
+			chunk.copy_from_slice(&block[.. chunk.len()]);
+			self.counter = self.counter.wrapping_add(1);
+
+			zeroize(&mut block);
+		}
+	}
+
+	fn pseudo_random_data(&mut self, out: &mut [u8]) {
+		debug_assert!(self.counter != 0);
+		debug_assert!(out.len() <= MAX_REQUEST_SIZE);
+
+		self.generate_blocks(out);
+
+		// switch to a new key, so the output can't be recomputed later
+		let mut key = [0; 32];
+		self.generate_blocks(&mut key);
+		self.set_key(key);
+
+		zeroize(&mut key);
+	}
+}
+
+impl Drop for Generator {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Generator {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.key);
+		::zeroize::Zeroize::zeroize(&mut self.aes);
+	}
+}
+
+/// A Fortuna generator along with its entropy pools.
+#[derive(Clone)]
+pub struct Fortuna {
+	generator: Generator,
+	pools: [Sha256; NUM_POOLS],
+	pool_0_size: u64,
+	reseed_count: u64,
+	last_reseed_ms: Option<u64>,
+}
+
+impl Default for Fortuna {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Fortuna {
+	/// Creates an unseeded generator. It can't produce output until the first
+	/// pool has collected 64 bytes of events, or it's seeded directly with
+	/// [`reseed_with`](Self::reseed_with).
+	pub fn new() -> Self {
+		Self {
+			generator: Generator::new(),
+			pools: core::array::from_fn(|_| Sha256::new()),
+			pool_0_size: 0,
+			reseed_count: 0,
+			last_reseed_ms: None,
+		}
+	}
+
+	/// Adds an event of 1 to 32 bytes from the entropy source numbered
+	/// `source` to the pool numbered `pool`. Each source should spread its
+	/// events evenly over the pools, by cycling through them in order.
+	///
+	/// # Panics
+	/// * Panics if `pool` is not less than [`NUM_POOLS`].
+	/// * Panics if `data` is empty or longer than 32 bytes.
+	pub fn add_random_event(&mut self, source: u8, pool: usize, data: &[u8]) {
+		assert!(pool < NUM_POOLS);
+		assert!(data.len() >= 1 && data.len() <= 32);
+
+		self.pools[pool].add_bytes(&[source, data.len() as u8]);
+		self.pools[pool].add_bytes(data);
+
+		if pool == 0 {
+			self.pool_0_size += 2 + data.len() as u64;
+		}
+	}
+
+	/// Reseeds the generator directly with `seed`, such as the contents of a
+	/// seed file saved by a previous run, or bytes from the operating system.
+	/// This doesn't touch the pools.
+	pub fn reseed_with(&mut self, seed: &[u8]) {
+		let mut hasher = self.generator.reseed_hasher();
+		hasher.add_bytes(seed);
+		self.generator.finish_reseed(hasher);
+	}
+
+	fn reseed_from_pools(&mut self) {
+		self.reseed_count += 1;
+
+		let mut hasher = self.generator.reseed_hasher();
+
+		for i in 0 .. NUM_POOLS {
+			// pool i is used in every 2 ** i-th reseed
+			if !self.reseed_count.is_multiple_of(1 << i) {
+				break;
+			}
+
+			let pool = core::mem::take(&mut self.pools[i]);
+			let mut pool_hash = sha256d(pool);
+			hasher.add_bytes(&pool_hash);
+			zeroize(&mut pool_hash);
+		}
+
+		self.generator.finish_reseed(hasher);
+		self.pool_0_size = 0;
+	}
+
+	/// Fills `out` with random bytes, first reseeding from the pools if the
+	/// first one has collected enough entropy and the last reseed was at
+	/// least 100 milliseconds ago. `now_ms` is the current time in
+	/// milliseconds, from any clock that never goes backwards.
+	pub fn random_data(&mut self, out: &mut [u8], now_ms: u64) -> Result<(), Error> {
+		let interval_passed = match self.last_reseed_ms {
+			Some(last_reseed_ms) => now_ms.saturating_sub(last_reseed_ms) >= MIN_RESEED_INTERVAL_MS,
+			None => true,
+		};
+
+		if self.pool_0_size >= MIN_POOL_SIZE && interval_passed {
+			self.reseed_from_pools();
+			self.last_reseed_ms = Some(now_ms);
+		}
+
+		if self.generator.counter == 0 {
+			return Err(Error::NotSeeded);
+		}
+
+		for chunk in out.chunks_mut(MAX_REQUEST_SIZE) {
+			self.generator.pseudo_random_data(chunk);
+		}
+
+		Ok(())
+	}
+
+	/// Produces 64 bytes to save as a seed file, to be passed to
+	/// [`reseed_with`](Self::reseed_with) by the next run. The seed file
+	/// should be overwritten with fresh output right after it's used, so the
+	/// same seed is never used twice.
+	pub fn seed_file_contents(&mut self, now_ms: u64) -> Result<[u8; 64], Error> {
+		let mut out = [0; 64];
+		self.random_data(&mut out, now_ms)?;
+
+		Ok(out)
+	}
+
+	/// Derives a child generator for a separate use, such as nonces or key
+	/// generation, so that the uses can't end up sharing output. The child
+	/// is seeded with 32 bytes of this generator's output followed by
+	/// `label`, so forking twice gives different children even with the same
+	/// label. Its pools start out empty, and don't share this generator's
+	/// events.
+	pub fn fork(&mut self, label: &[u8]) -> Result<Self, Error> {
+		if self.generator.counter == 0 {
+			return Err(Error::NotSeeded);
+		}
+
+		let mut seed = [0; 32];
+		self.generator.pseudo_random_data(&mut seed);
+
+		let mut child = Self::new();
+		let mut hasher = child.generator.reseed_hasher();
+		hasher.add_bytes(&seed);
+		hasher.add_bytes(label);
+		child.generator.finish_reseed(hasher);
+
+		zeroize(&mut seed);
+
+		Ok(child)
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Fortuna {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.generator);
+		self.pools.iter_mut().for_each(::zeroize::Zeroize::zeroize);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Fortuna {}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// checked against a python transcription of chapter 9 of the book, using
+// hashlib's SHA-256 and AES from python's cryptography package
+#[test]
+fn test_reseeding() {
+	let mut fortuna = Fortuna::new();
+	let mut out = [0; 40];
+
+	assert_eq!(fortuna.random_data(&mut out, 0), Err(Error::NotSeeded));
+
+	for i in 0 .. 64 {
+		fortuna.add_random_event(i % 3, i as usize % NUM_POOLS, &[i; 32]);
+	}
+
+	// the first reseed only uses the first pool
+	fortuna.random_data(&mut out, 1000).unwrap();
+	assert_eq!(out, hex("6a7ba1d02bdf6ccd63508a27c764cf2a9fef8d88b412dffa521de741807def54b25c957129891ca3"));
+
+	for i in 0 .. 64 {
+		fortuna.add_random_event(i % 3, i as usize % NUM_POOLS, &[i + 64; 32]);
+	}
+
+	// too soon after the last reseed, so there's no reseed yet
+	fortuna.random_data(&mut out, 1050).unwrap();
+	assert_eq!(out, hex("b024c56d12ba4235b79a40981b67778419dc136c84113b60fd9437e0ea87cd3ce87942e23889d5a3"));
+
+	// the second reseed uses the first two pools
+	fortuna.random_data(&mut out, 1100).unwrap();
+	assert_eq!(out, hex("bcdd4caa8368b925ad22ee16c8a4d23dcd7e099edf7beb1f4b13c9b7c4a248024d106ff870d02ed2"));
+
+	fortuna.reseed_with(b"seed file");
+
+	let mut out = [0; 16];
+	fortuna.random_data(&mut out, 1100).unwrap();
+	assert_eq!(out, hex("73d62b1625b50d04b811fdb1da7c2d1c"));
+}
+
+#[test]
+fn test_seed_file() {
+	let mut fortuna = Fortuna::new();
+	fortuna.reseed_with(&[0x42; 64]);
+
+	let seed = fortuna.seed_file_contents(0).unwrap();
+
+	// a new instance seeded from the file gets going without any events
+	let mut next_run = Fortuna::new();
+	next_run.reseed_with(&seed);
+
+	let mut out = [0; 32];
+	assert_eq!(next_run.random_data(&mut out, 0), Ok(()));
+	assert_ne!(out, [0; 32]);
+}
+
+#[test]
+fn test_fork() {
+	let mut parent = Fortuna::new();
+	assert!(parent.fork(b"nonces").is_err());
+
+	parent.reseed_with(&[0x42; 64]);
+	let mut copy = parent.clone();
+
+	let mut nonces = parent.fork(b"nonces").unwrap();
+	let mut keys = parent.fork(b"keys").unwrap();
+	let mut keys_again = parent.fork(b"keys").unwrap();
+
+	// a child is seeded with the parent's output followed by the label
+	let mut seed = [0; 32 + 6];
+	copy.random_data(&mut seed[.. 32], 0).unwrap();
+	seed[32 ..].copy_from_slice(b"nonces");
+	let mut expected = Fortuna::new();
+	expected.reseed_with(&seed);
+
+	let next = |fortuna: &mut Fortuna| {
+		let mut out = [0; 32];
+		fortuna.random_data(&mut out, 0).unwrap();
+		out
+	};
+
+	let outputs = [
+		next(&mut nonces),
+		next(&mut keys),
+		next(&mut keys_again),
+		next(&mut parent),
+		next(&mut expected),
+	];
+
+	assert_eq!(outputs[0], outputs[4]);
+
+	for i in 0 .. 4 {
+		for j in 0 .. i {
+			assert_ne!(outputs[i], outputs[j]);
+		}
+	}
+}
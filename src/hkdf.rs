@@ -0,0 +1,103 @@
+//! Implemented according to [IETF RFC 5869](https://datatracker.ietf.org/doc/html/rfc5869).
+//! HKDF derives one or more keys from input keying material, such as a
+//! Diffie-Hellman shared secret, which may not be uniformly random. It first
+//! extracts a pseudorandom key from the input, then expands it into as much
+//! output as is needed, with `info` separating keys for different purposes.
+
+use crate::hmac::{HmacSha256, HmacSha512};
+use crate::zeroize::zeroize;
+
+macro_rules! hkdf_impl {
+	(
+		$hkdf:ident,
+		$extract:ident,
+		$expand:ident,
+		$hmac:ident,
+		$hash_name:literal,
+		$output_size:literal,
+	) => {
+		#[doc = concat!("Extracts a pseudorandom key from the input keying material `ikm` with HKDF-", $hash_name, ".")]
+		/// The `salt` is optional, and may be left empty.
+		pub fn $extract(salt: &[u8], ikm: &[u8]) -> [u8; $output_size] {
+			let mut hmac = $hmac::new(salt);
+			hmac.add_bytes(ikm);
+			hmac.out()
+		}
+
+		#[doc = concat!("Expands a pseudorandom key `prk` into `okm` with HKDF-", $hash_name, ".")]
+		///
+		/// # Panics
+		#[doc = concat!("* Panics if `okm` is longer than ", stringify!($output_size), " * 255 bytes.")]
+		pub fn $expand(prk: &[u8; $output_size], info: &[u8], okm: &mut [u8]) {
+			assert!(okm.len() <= 255 * $output_size);
+
+			let mut block = [0; $output_size];
+
+			for (i, chunk) in okm.chunks_mut($output_size).enumerate() {
+				let mut hmac = $hmac::new(prk);
+
+				if i != 0 {
+					hmac.add_bytes(&block);
+				}
+
+				hmac.add_bytes(info);
+				hmac.add_bytes(&[i as u8 + 1]);
+
+				block = hmac.out();
+				chunk.copy_from_slice(&block[0 .. chunk.len()]);
+			}
+
+			zeroize(&mut block);
+		}
+
+		#[doc = concat!("Derives `okm` from the input keying material `ikm` with HKDF-", $hash_name, ",")]
+		/// extracting and then expanding. The `salt` is optional, and may be
+		/// left empty.
+		///
+		/// # Panics
+		#[doc = concat!("* Panics if `okm` is longer than ", stringify!($output_size), " * 255 bytes.")]
+		pub fn $hkdf(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+			let mut prk = $extract(salt, ikm);
+			$expand(&prk, info, okm);
+			zeroize(&mut prk);
+		}
+	};
+}
+
+hkdf_impl!(hkdf_sha256, hkdf_sha256_extract, hkdf_sha256_expand, HmacSha256, "SHA-256", 32,);
+hkdf_impl!(hkdf_sha512, hkdf_sha512_extract, hkdf_sha512_expand, HmacSha512, "SHA-512", 64,);
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// test cases 1 and 3 from RFC 5869
+#[test]
+fn test_rfc5869_vectors() {
+	let ikm = [0x0b; 22];
+	let salt: [u8; 13] = hex("000102030405060708090a0b0c");
+	let info: [u8; 10] = hex("f0f1f2f3f4f5f6f7f8f9");
+
+	let prk = hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+	let okm: [u8; 42] = hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+
+	assert_eq!(hkdf_sha256_extract(&salt, &ikm), prk);
+
+	let mut computed = [0; 42];
+	hkdf_sha256_expand(&prk, &info, &mut computed);
+	assert_eq!(computed, okm);
+
+	let mut computed = [0; 42];
+	hkdf_sha256(&[], &ikm, &[], &mut computed);
+	assert_eq!(computed[..], hex::<42>("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8")[..]);
+}
+
+#[test]
+fn test_hkdf_sha512() {
+	let ikm = [0x0b; 22];
+	let salt: [u8; 13] = hex("000102030405060708090a0b0c");
+	let info: [u8; 10] = hex("f0f1f2f3f4f5f6f7f8f9");
+
+	let mut computed = [0; 42];
+	hkdf_sha512(&salt, &ikm, &info, &mut computed);
+	assert_eq!(computed[..], hex::<42>("832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579338da362cb8d9f925d7cb")[..]);
+}
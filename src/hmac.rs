@@ -0,0 +1,182 @@
+//! Implemented according to [IETF RFC 2104](https://datatracker.ietf.org/doc/html/rfc2104).
+//! HMAC is a message authentication code built from a hash function, here
+//! SHA-256, SHA-512 or BLAKE2b, or SHA-1 for older protocols that require it.
+//! Unlike Poly1305, the same key may be used to authenticate any number of
+//! messages.
+
+#[cfg(feature = "blake2")]
+use crate::blake2::Blake2b;
+use crate::choice::ConstantTimeEq;
+use crate::sha1::Sha1;
+use crate::sha2::{Sha256, Sha512};
+use crate::zeroize::zeroize;
+
+macro_rules! hmac_impl {
+	(
+		$name:ident,
+		$function:ident,
+		$verify_function:ident,
+		$hasher:ty,
+		$hash_name:literal,
+		$block_size:literal,
+		$output_size:literal,
+	) => {
+		#[doc = concat!("Struct used for computing HMAC-", $hash_name, " on data not stored")]
+		/// contiguously in memory.
+		#[derive(Clone)]
+		pub struct $name {
+			inner: $hasher,
+			outer: $hasher,
+		}
+
+		impl $name {
+			/// Creates a new HMAC instance with the given `key`, which may be
+			/// of any length.
+			pub fn new(key: &[u8]) -> Self {
+				let mut key_block = [0; $block_size];
+
+				if key.len() > $block_size {
+					let mut hasher = <$hasher>::new();
+					hasher.add_bytes(key);
+					key_block[0 .. $output_size].copy_from_slice(&hasher.out());
+				} else {
+					key_block[0 .. key.len()].copy_from_slice(key);
+				}
+
+				let mut inner = <$hasher>::new();
+				let mut outer = <$hasher>::new();
+
+				let mut pad = [0; $block_size];
+
+				for i in 0 .. $block_size {
+					pad[i] = key_block[i] ^ 0x36;
+				}
+
+				inner.add_bytes(&pad);
+
+				for i in 0 .. $block_size {
+					pad[i] = key_block[i] ^ 0x5c;
+				}
+
+				outer.add_bytes(&pad);
+
+				zeroize(&mut key_block);
+				zeroize(&mut pad);
+
+				Self {inner, outer}
+			}
+
+			/// Adds bytes of the message.
+			pub fn add_bytes(&mut self, bytes: &[u8]) {
+				self.inner.add_bytes(bytes);
+			}
+
+			/// Consumes the HMAC instance and outputs the tag of the
+			/// concatenation of all bytes added to it.
+			pub fn out(self) -> [u8; $output_size] {
+				let mut outer = self.outer;
+				outer.add_bytes(&self.inner.out());
+				outer.out()
+			}
+
+			/// Consumes the HMAC instance and checks, in constant time, that
+			/// the tag of all bytes added to it is `tag`.
+			pub fn verify(self, tag: &[u8; $output_size]) -> bool {
+				self.out().ct_eq(tag).into()
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl ::zeroize::Zeroize for $name {
+			fn zeroize(&mut self) {
+				self.inner.zeroize();
+				self.outer.zeroize();
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl ::zeroize::ZeroizeOnDrop for $name {}
+
+		#[doc = concat!("Computes the HMAC-", $hash_name, " tag of a `message` with the given `key`.")]
+		pub fn $function(key: &[u8], message: &[u8]) -> [u8; $output_size] {
+			let mut hmac = $name::new(key);
+			hmac.add_bytes(message);
+			hmac.out()
+		}
+
+		#[doc = concat!("Verifies an HMAC-", $hash_name, " `tag` of a `message` in constant time.")]
+		pub fn $verify_function(key: &[u8], message: &[u8], tag: &[u8; $output_size]) -> bool {
+			let mut hmac = $name::new(key);
+			hmac.add_bytes(message);
+			hmac.verify(tag)
+		}
+	};
+}
+
+hmac_impl!(HmacSha1, hmac_sha1, hmac_sha1_verify, Sha1, "SHA-1", 64, 20,);
+hmac_impl!(HmacSha256, hmac_sha256, hmac_sha256_verify, Sha256, "SHA-256", 64, 32,);
+hmac_impl!(HmacSha512, hmac_sha512, hmac_sha512_verify, Sha512, "SHA-512", 128, 64,);
+#[cfg(feature = "blake2")]
+hmac_impl!(HmacBlake2b, hmac_blake2b, hmac_blake2b_verify, Blake2b<64>, "BLAKE2b", 128, 64,);
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// test cases 2 and 6 from RFC 4231
+#[test]
+fn test_rfc4231_vectors() {
+	let key = b"Jefe";
+	let message = b"what do ya want for nothing?";
+
+	let tag = hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+	assert_eq!(hmac_sha256(key, message), tag);
+	assert!(hmac_sha256_verify(key, message, &tag));
+
+	let tag = hex(
+		"164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+		9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+	);
+	assert_eq!(hmac_sha512(key, message), tag);
+	assert!(!hmac_sha512_verify(key, b"what do ya want for something?", &tag));
+
+	let key = [0xaa; 131];
+	let message = b"Test Using Larger Than Block-Size Key - Hash Key First";
+
+	let tag = hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+	assert_eq!(hmac_sha256(&key, message), tag);
+
+	let tag = hex(
+		"80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+		6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+	);
+	assert_eq!(hmac_sha512(&key, message), tag);
+}
+
+// test cases 1 and 2 from RFC 2202
+#[test]
+fn test_rfc2202_vectors() {
+	let tag = hex("b617318655057264e28bc0b6fb378c8ef146be00");
+	assert_eq!(hmac_sha1(&[0x0b; 20], b"Hi There"), tag);
+
+	let tag = hex("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+	assert!(hmac_sha1_verify(b"Jefe", b"what do ya want for nothing?", &tag));
+}
+
+// the inputs of test cases 2 and 6 from RFC 4231, with the tags computed by
+// Python's hmac module using hashlib.blake2b
+#[test]
+#[cfg(feature = "blake2")]
+fn test_blake2b() {
+	let tag = hex(
+		"6ff884f8ddc2a6586b3c98a4cd6ebdf14ec10204b6710073eb5865ade37a2643\
+		b8807c1335d107ecdb9ffeaeb6828c4625ba172c66379efcd222c2de11727ab4",
+	);
+	assert_eq!(hmac_blake2b(b"Jefe", b"what do ya want for nothing?"), tag);
+	assert!(hmac_blake2b_verify(b"Jefe", b"what do ya want for nothing?", &tag));
+
+	let tag = hex(
+		"a54b2943b2a20227d41ca46c0945af09bc1faefb2f49894c23aebc557fb79c48\
+		89dca74408dc865086667aedee4a3185c53a49c80b814c4c5813ea0c8b38a8f8",
+	);
+	assert_eq!(hmac_blake2b(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First"), tag);
+}
@@ -0,0 +1,397 @@
+//! Hybrid signatures, which sign with both Ed25519 and
+//! [ML-DSA-65](crate::ml_dsa) and only verify if both signatures do. They stay
+//! secure as long as either scheme is, so deployments can start relying on
+//! post-quantum signatures without giving up the well-studied security of
+//! Ed25519 in case ML-DSA turns out to be weaker than believed.
+//!
+//! Both algorithms sign the same representative of the message, which starts
+//! with a label naming the combination and includes a context string and the
+//! SHA-512 hash of the message, and ML-DSA also uses the label as its own
+//! context. Neither half of a hybrid signature is therefore a valid signature
+//! on its own, so an attacker who breaks one of the schemes can't strip the
+//! other half off and pass the rest off as an ordinary Ed25519 or ML-DSA
+//! signature, or the other way around. This follows the construction of the
+//! IETF's composite ML-DSA drafts, but with its own label, so signatures
+//! aren't compatible with theirs.
+//!
+//! ```
+//! use libkrypton::hybrid_signature::SigningKey;
+//!
+//! // the seeds should be generated by a secure random source
+//! let signing_key = SigningKey::from_seeds([0x42; 32], [0x43; 32]);
+//! let signature = signing_key.sign(b"message");
+//!
+//! assert!(signing_key.verifying_key().verify(b"message", &signature));
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::ed25519;
+use crate::ml_dsa;
+use crate::sha2::Sha512;
+#[cfg(feature = "rand_core")]
+use crate::zeroize::zeroize;
+
+/// The length of an encoded verifying key, the ML-DSA-65 key followed by the
+/// Ed25519 key.
+pub const VERIFYING_KEY_LEN: usize = ml_dsa::VERIFYING_KEY_LEN + 32;
+/// The length of an encoded signature, the ML-DSA-65 signature followed by
+/// the Ed25519 signature.
+pub const SIGNATURE_LEN: usize = ml_dsa::SIGNATURE_LEN + 64;
+
+// names the algorithms, so that signatures made by one combination can't be
+// used with another, and is ML-DSA's context string
+const LABEL: &[u8] = b"libkrypton 2024 ed25519+ml-dsa-65";
+
+/// An error returned when signing fails, or when bytes can't be interpreted
+/// as a key or signature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The Ed25519 half of the key does not encode a point on the curve.
+	InvalidPublicKey,
+	/// The Ed25519 half of the signature is not canonically encoded.
+	InvalidSignature,
+	/// The context string is longer than 255 bytes.
+	ContextTooLong,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPublicKey => "invalid hybrid public key",
+			Error::InvalidSignature => "invalid hybrid signature",
+			Error::ContextTooLong => "hybrid signature context string too long",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// the longest message representative, with a 255-byte context
+const MAX_REPRESENTATIVE_LEN: usize = LABEL.len() + 1 + 255 + 64;
+
+// the message both algorithms sign: the label, the length of the context and
+// the context, and the hash of the message, returned with its length
+fn message_representative(context: &[u8], message: &[u8]) -> ([u8; MAX_REPRESENTATIVE_LEN], usize) {
+	let mut hasher = Sha512::new();
+	hasher.add_bytes(message);
+	let digest = hasher.out();
+
+	let mut out = [0; MAX_REPRESENTATIVE_LEN];
+	let mut len = 0;
+
+	for part in [LABEL, &[context.len() as u8], context, &digest] {
+		out[len .. len + part.len()].copy_from_slice(part);
+		len += part.len();
+	}
+
+	(out, len)
+}
+
+/// A hybrid signing key, made of an Ed25519 and an ML-DSA-65 signing key.
+/// Zeroized when dropped.
+#[derive(Clone)]
+pub struct SigningKey {
+	ed25519: ed25519::SigningKey,
+	ml_dsa: ml_dsa::SigningKey,
+}
+
+impl SigningKey {
+	/// Combines an existing Ed25519 and ML-DSA-65 signing key. These should
+	/// not also be used on their own.
+	pub fn from_parts(ed25519: ed25519::SigningKey, ml_dsa: ml_dsa::SigningKey) -> Self {
+		Self {ed25519, ml_dsa}
+	}
+
+	/// Creates a signing key from an Ed25519 seed and an ML-DSA-65 seed,
+	/// which are the compact encodings of each key.
+	pub fn from_seeds(ed25519_seed: [u8; 32], ml_dsa_seed: [u8; 32]) -> Self {
+		Self::from_parts(ed25519::SigningKey::from_bytes(ed25519_seed), ml_dsa::SigningKey::from_seed(ml_dsa_seed))
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		let key = Self::from_parts(ed25519::SigningKey::from_bytes(seed), ml_dsa::SigningKey::generate(rng));
+		zeroize(&mut seed);
+
+		key
+	}
+
+	/// Returns the Ed25519 half of the key.
+	pub fn ed25519(&self) -> &ed25519::SigningKey {
+		&self.ed25519
+	}
+
+	/// Returns the ML-DSA-65 half of the key.
+	pub fn ml_dsa(&self) -> &ml_dsa::SigningKey {
+		&self.ml_dsa
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		VerifyingKey {
+			ed25519: self.ed25519.verifying_key(),
+			ml_dsa: self.ml_dsa.verifying_key(),
+		}
+	}
+
+	/// Deterministically signs a `message` with an empty context.
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		self.sign_with_context(message, b"").unwrap()
+	}
+
+	/// Deterministically signs a `message`, binding the signature to
+	/// `context`, which must be at most 255 bytes long.
+	pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Result<Signature, Error> {
+		self.sign_randomized(message, context, [0; 32])
+	}
+
+	/// Signs a `message` bound to `context`, like
+	/// [`SigningKey::sign_with_context`], but with hedged ML-DSA signing, which
+	/// mixes `randomness` into the signature. `randomness` should be freshly
+	/// generated by a secure random source for every call.
+	pub fn sign_randomized(&self, message: &[u8], context: &[u8], randomness: [u8; 32]) -> Result<Signature, Error> {
+		if context.len() > 255 {
+			return Err(Error::ContextTooLong);
+		}
+
+		let (representative, len) = message_representative(context, message);
+		let representative = &representative[.. len];
+
+		// the label is shorter than 255 bytes
+		let ml_dsa = self.ml_dsa.sign_randomized(representative, LABEL, randomness).unwrap();
+		let ed25519 = self.ed25519.sign(representative);
+
+		Ok(Signature {ml_dsa, ed25519})
+	}
+
+	/// Like [`SigningKey::sign_randomized`], but generates the randomness
+	/// with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn sign_with_rng<R: RngCore + CryptoRng>(
+		&self,
+		message: &[u8],
+		context: &[u8],
+		rng: &mut R,
+	) -> Result<Signature, Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = self.sign_randomized(message, context, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.ed25519);
+		::zeroize::Zeroize::zeroize(&mut self.ml_dsa);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey").field("verifying_key", &self.verifying_key()).finish_non_exhaustive()
+	}
+}
+
+/// A hybrid verifying key, made of an Ed25519 and an ML-DSA-65 verifying key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+	ed25519: ed25519::VerifyingKey,
+	ml_dsa: ml_dsa::VerifyingKey,
+}
+
+impl VerifyingKey {
+	/// Combines an existing Ed25519 and ML-DSA-65 verifying key.
+	pub fn from_parts(ed25519: ed25519::VerifyingKey, ml_dsa: ml_dsa::VerifyingKey) -> Self {
+		Self {ed25519, ml_dsa}
+	}
+
+	/// Creates a verifying key from its encoding, returning an error if the
+	/// Ed25519 half doesn't encode a point on the curve.
+	pub fn from_bytes(bytes: &[u8; VERIFYING_KEY_LEN]) -> Result<Self, Error> {
+		let (ml_dsa, ed25519) = bytes.split_at(ml_dsa::VERIFYING_KEY_LEN);
+
+		let ed25519 = ed25519::VerifyingKey::from_bytes(ed25519.try_into().unwrap())
+			.map_err(|_| Error::InvalidPublicKey)?;
+		let ml_dsa = ml_dsa::VerifyingKey::from_bytes(ml_dsa.try_into().unwrap());
+
+		Ok(Self {ed25519, ml_dsa})
+	}
+
+	/// Returns the encoding of this key.
+	pub fn to_bytes(&self) -> [u8; VERIFYING_KEY_LEN] {
+		let mut out = [0; VERIFYING_KEY_LEN];
+		out[.. ml_dsa::VERIFYING_KEY_LEN].copy_from_slice(self.ml_dsa.as_bytes());
+		out[ml_dsa::VERIFYING_KEY_LEN ..].copy_from_slice(self.ed25519.as_bytes());
+		out
+	}
+
+	/// Returns the Ed25519 half of the key.
+	pub fn ed25519(&self) -> &ed25519::VerifyingKey {
+		&self.ed25519
+	}
+
+	/// Returns the ML-DSA-65 half of the key.
+	pub fn ml_dsa(&self) -> &ml_dsa::VerifyingKey {
+		&self.ml_dsa
+	}
+
+	/// Verifies a `signature` over a `message` with an empty context.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		self.verify_with_context(message, b"", signature)
+	}
+
+	/// Verifies a `signature` over a `message` that was bound to `context`
+	/// when signing, which only succeeds if both halves of the signature are
+	/// valid. Returns false if the context is longer than 255 bytes.
+	pub fn verify_with_context(&self, message: &[u8], context: &[u8], signature: &Signature) -> bool {
+		if context.len() > 255 {
+			return false;
+		}
+
+		let (representative, len) = message_representative(context, message);
+		let representative = &representative[.. len];
+
+		self.ed25519.verify(representative, &signature.ed25519)
+			&& self.ml_dsa.verify_with_context(representative, LABEL, &signature.ml_dsa)
+	}
+}
+
+impl fmt::Debug for VerifyingKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("VerifyingKey")
+			.field("ed25519", &self.ed25519)
+			.field("ml_dsa", &self.ml_dsa)
+			.finish()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(VerifyingKey, VERIFYING_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(&bytes));
+
+text_impl!(VerifyingKey, VERIFYING_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| VerifyingKey::from_bytes(&bytes));
+
+/// A hybrid signature, made of an Ed25519 and an ML-DSA-65 signature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	ml_dsa: ml_dsa::Signature,
+	ed25519: ed25519::Signature,
+}
+
+impl Signature {
+	/// Creates a signature from its encoding, returning an error if the
+	/// Ed25519 half isn't canonically encoded.
+	pub fn from_bytes(bytes: &[u8; SIGNATURE_LEN]) -> Result<Self, Error> {
+		let (ml_dsa, ed25519) = bytes.split_at(ml_dsa::SIGNATURE_LEN);
+
+		let ed25519 = ed25519::Signature::from_bytes(ed25519.try_into().unwrap())
+			.map_err(|_| Error::InvalidSignature)?;
+		let ml_dsa = ml_dsa::Signature::from_bytes(ml_dsa.try_into().unwrap());
+
+		Ok(Self {ml_dsa, ed25519})
+	}
+
+	/// Returns the encoding of this signature.
+	pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+		let mut out = [0; SIGNATURE_LEN];
+		out[.. ml_dsa::SIGNATURE_LEN].copy_from_slice(self.ml_dsa.as_bytes());
+		out[ml_dsa::SIGNATURE_LEN ..].copy_from_slice(self.ed25519.as_bytes());
+		out
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Signature, SIGNATURE_LEN, Base64, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(&bytes));
+
+text_impl!(Signature, SIGNATURE_LEN, Base64, |signature| signature.to_bytes(), try |bytes| Signature::from_bytes(&bytes));
+
+#[test]
+fn test_sign_and_verify() {
+	let signing_key = SigningKey::from_seeds([1; 32], [2; 32]);
+	let verifying_key = signing_key.verifying_key();
+
+	let signature = signing_key.sign(b"message");
+	assert!(verifying_key.verify(b"message", &signature));
+	assert!(!verifying_key.verify(b"messagf", &signature));
+	assert!(!verifying_key.verify_with_context(b"message", b"context", &signature));
+
+	let signature = signing_key.sign_with_context(b"message", b"context").unwrap();
+	assert!(verifying_key.verify_with_context(b"message", b"context", &signature));
+	assert!(!verifying_key.verify(b"message", &signature));
+
+	let randomized = signing_key.sign_randomized(b"message", b"context", [3; 32]).unwrap();
+	assert_ne!(randomized, signature);
+	assert!(verifying_key.verify_with_context(b"message", b"context", &randomized));
+
+	assert_eq!(signing_key.sign_with_context(b"message", &[0; 256]).unwrap_err(), Error::ContextTooLong);
+	assert!(!verifying_key.verify_with_context(b"message", &[0; 256], &signature));
+}
+
+// both halves have to be valid, and neither is valid on its own
+#[test]
+fn test_halves() {
+	let signing_key = SigningKey::from_seeds([1; 32], [2; 32]);
+	let verifying_key = signing_key.verifying_key();
+	let signature = signing_key.sign(b"message");
+
+	let other_key = SigningKey::from_seeds([1; 32], [4; 32]);
+	let other_signature = other_key.sign(b"message");
+
+	let mixed = Signature {ml_dsa: other_signature.ml_dsa, ed25519: signature.ed25519};
+	assert!(!verifying_key.verify(b"message", &mixed));
+
+	let other_key = SigningKey::from_seeds([5; 32], [2; 32]);
+	let other_signature = other_key.sign(b"message");
+
+	let mixed = Signature {ml_dsa: signature.ml_dsa, ed25519: other_signature.ed25519};
+	assert!(!verifying_key.verify(b"message", &mixed));
+
+	assert!(!verifying_key.ed25519().verify(b"message", &signature.ed25519));
+	assert!(!verifying_key.ml_dsa().verify(b"message", &signature.ml_dsa));
+}
+
+#[test]
+fn test_encoding() {
+	let signing_key = SigningKey::from_seeds([1; 32], [2; 32]);
+	let verifying_key = signing_key.verifying_key();
+	let signature = signing_key.sign(b"message");
+
+	let bytes = verifying_key.to_bytes();
+	assert_eq!(&bytes[.. ml_dsa::VERIFYING_KEY_LEN], signing_key.ml_dsa().verifying_key().as_bytes());
+	assert_eq!(&bytes[ml_dsa::VERIFYING_KEY_LEN ..], signing_key.ed25519().verifying_key().as_bytes());
+	assert_eq!(VerifyingKey::from_bytes(&bytes).unwrap(), verifying_key);
+
+	let bytes = signature.to_bytes();
+	assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+
+	let mut invalid = bytes;
+	invalid[SIGNATURE_LEN - 1] = 0xff;
+	assert_eq!(Signature::from_bytes(&invalid).unwrap_err(), Error::InvalidSignature);
+
+	// y = 2 isn't on the curve
+	let mut invalid = verifying_key.to_bytes();
+	invalid[ml_dsa::VERIFYING_KEY_LEN ..].copy_from_slice(&[2; 32]);
+	assert_eq!(VerifyingKey::from_bytes(&invalid).unwrap_err(), Error::InvalidPublicKey);
+}
@@ -0,0 +1,236 @@
+//! An entropy source for targets without an operating system random number
+//! generator, based on the timing jitter of the CPU, like
+//! [jitterentropy](https://www.chronox.de/jent/). Memory accesses and
+//! computations take a slightly unpredictable number of cycles, due to caches,
+//! pipelines, interrupts and clock domain crossings, so timing a short piece of
+//! work with a cycle counter gives a little entropy each time.
+//!
+//! Each sample is credited with a quarter of a bit of entropy, and 1024 of them
+//! are hashed with SHA-256 into every 32 bytes of output. Every sample goes
+//! through the health tests from [`entropy_health`](crate::entropy_health), so
+//! a broken or too coarse cycle counter is reported as an error instead of
+//! silently producing predictable output.
+//!
+//! The output is meant for seeding a deterministic generator, such as
+//! [`CtrDrbg`](crate::ctr_drbg::CtrDrbg), rather than being used directly.
+//! On x86-64, the time stamp counter can be used, and other targets need to
+//! implement [`CycleCounter`] themselves:
+//!
+//! ```
+//! use libkrypton::ctr_drbg::CtrDrbg;
+//! use libkrypton::jitter::{JitterEntropy, Rdtsc};
+//!
+//! let mut jitter = JitterEntropy::new(Rdtsc);
+//!
+//! let mut entropy = [0; 32];
+//! let mut nonce = [0; 16];
+//! jitter.fill(&mut entropy).unwrap();
+//! jitter.fill(&mut nonce).unwrap();
+//!
+//! let drbg = CtrDrbg::new(&entropy, &nonce, b"first boot key generation");
+//! ```
+
+use crate::entropy_health::HealthTests;
+use crate::sha2::Sha256;
+use crate::zeroize::zeroize;
+
+// samples hashed into every 32 bytes of output, at a quarter bit each
+const SAMPLES_PER_BLOCK: usize = 1024;
+
+// the min-entropy of each sample for the health tests, in eighths of a bit
+const MIN_ENTROPY_EIGHTHS: u32 = 2;
+
+// the size of the buffer walked through between timestamps, which is big
+// enough to miss the fastest caches
+const MEMORY_SIZE: usize = 2048;
+
+/// A source of timestamps with a resolution of about one CPU cycle, such as
+/// the TSC on x86-64, the PMCCNTR register on ARM or the DWT cycle counter on
+/// Cortex-M.
+pub trait CycleCounter {
+	/// Returns the current value of the counter, which may wrap around.
+	fn read(&mut self) -> u64;
+}
+
+/// The time stamp counter of x86-64 CPUs.
+#[cfg(target_arch = "x86_64")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Rdtsc;
+
+#[cfg(target_arch = "x86_64")]
+impl CycleCounter for Rdtsc {
+	fn read(&mut self) -> u64 {
+		// SAFETY: rdtsc is available on every x86-64 CPU
+		unsafe {core::arch::x86_64::_rdtsc()}
+	}
+}
+
+pub use crate::entropy_health::Error;
+
+/// Collects entropy from the timing jitter measured by a [`CycleCounter`].
+pub struct JitterEntropy<C: CycleCounter> {
+	counter: C,
+	memory: [u8; MEMORY_SIZE],
+	memory_pos: usize,
+	last_time: u64,
+	last_delta: u64,
+	last_delta2: u64,
+	health: HealthTests,
+	// carried over between outputs, so every output depends on every sample
+	// so far
+	pool: [u8; 32],
+	failed: Option<Error>,
+}
+
+impl<C: CycleCounter> JitterEntropy<C> {
+	pub fn new(mut counter: C) -> Self {
+		let last_time = counter.read();
+
+		Self {
+			counter,
+			memory: [0; MEMORY_SIZE],
+			memory_pos: 0,
+			last_time,
+			last_delta: 0,
+			last_delta2: 0,
+			health: HealthTests::new(MIN_ENTROPY_EIGHTHS),
+			pool: [0; 32],
+			failed: None,
+		}
+	}
+
+	// the work being timed, which walks through memory in a pattern that
+	// depends on the earlier timings
+	fn memory_access(&mut self, delta: u64) {
+		let steps = 64 + (delta & 0x3f) as usize;
+
+		for _ in 0 .. steps {
+			self.memory_pos = (self.memory_pos + 67 + (self.memory[self.memory_pos] as usize)) % MEMORY_SIZE;
+			self.memory[self.memory_pos] = self.memory[self.memory_pos].wrapping_add(1);
+		}
+	}
+
+	// takes a timing sample, returning it along with whether it's stuck,
+	// meaning that its delta or the first or second differences between
+	// deltas are zero, so it isn't credited with any entropy
+	fn sample(&mut self) -> Result<(u64, bool), Error> {
+		self.memory_access(self.last_delta);
+
+		let time = self.counter.read();
+		let delta = time.wrapping_sub(self.last_time);
+		let delta2 = delta.wrapping_sub(self.last_delta);
+		let delta3 = delta2.wrapping_sub(self.last_delta2);
+
+		self.health.check(delta)?;
+
+		self.last_time = time;
+		self.last_delta = delta;
+		self.last_delta2 = delta2;
+
+		Ok((delta, delta == 0 || delta2 == 0 || delta3 == 0))
+	}
+
+	/// Fills `out` with entropy. Once a health test has failed, this always
+	/// returns the same error, since the source can't be trusted anymore.
+	pub fn fill(&mut self, out: &mut [u8]) -> Result<(), Error> {
+		if let Some(error) = self.failed {
+			return Err(error);
+		}
+
+		for chunk in out.chunks_mut(32) {
+			let mut hasher = Sha256::new();
+			hasher.add_bytes(&self.pool);
+
+			let mut credited = 0;
+
+			while credited < SAMPLES_PER_BLOCK {
+				let (delta, stuck) = match self.sample() {
+					Ok(sample) => sample,
+					Err(error) => {
+						self.failed = Some(error);
+						return Err(error);
+					},
+				};
+
+				hasher.add_bytes(&delta.to_le_bytes());
+
+				if !stuck {
+					credited += 1;
+				}
+			}
+
+			self.pool = hasher.out();
+
+			// the output is hashed separately from the pool that's kept
+			let mut hasher = Sha256::new();
+			hasher.add_bytes(b"libkrypton/jitter/output\0");
+			hasher.add_bytes(&self.pool);
+			let mut block = hasher.out();
+			chunk.copy_from_slice(&block[.. chunk.len()]);
+
+			zeroize(&mut block);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_rdtsc() {
+	let mut jitter = JitterEntropy::new(Rdtsc);
+
+	let mut first = [0; 48];
+	let mut second = [0; 48];
+	jitter.fill(&mut first).unwrap();
+	jitter.fill(&mut second).unwrap();
+
+	assert_ne!(first, second);
+	assert_ne!(first, [0; 48]);
+}
+
+#[test]
+fn test_broken_counters() {
+	// a counter that never changes
+	struct Stopped;
+
+	impl CycleCounter for Stopped {
+		fn read(&mut self) -> u64 {
+			0
+		}
+	}
+
+	// a counter that moves by the same amount at every read
+	struct Steady(u64);
+
+	impl CycleCounter for Steady {
+		fn read(&mut self) -> u64 {
+			self.0 += 1000;
+			self.0
+		}
+	}
+
+	// a counter whose delta is the same fifteen times out of sixteen, which
+	// passes the repetition count test but not the adaptive proportion test
+	struct Mostly(u64, u64);
+
+	impl CycleCounter for Mostly {
+		fn read(&mut self) -> u64 {
+			self.1 += 1;
+			self.0 += if self.1.is_multiple_of(16) {1001} else {1000};
+			self.0
+		}
+	}
+
+	let mut out = [0; 32];
+
+	let mut jitter = JitterEntropy::new(Stopped);
+	assert_eq!(jitter.fill(&mut out), Err(Error::RepetitionCount));
+	assert_eq!(jitter.fill(&mut out), Err(Error::RepetitionCount));
+
+	let mut jitter = JitterEntropy::new(Steady(0));
+	assert_eq!(jitter.fill(&mut out), Err(Error::RepetitionCount));
+
+	let mut jitter = JitterEntropy::new(Mostly(0, 0));
+	assert_eq!(jitter.fill(&mut out), Err(Error::AdaptiveProportion));
+}
@@ -0,0 +1,194 @@
+//! A minimal JSON parser shared by the formats that embed JSON, which only
+//! need to read a few string parameters out of an object.
+
+use std::string::String;
+use std::vec::Vec;
+
+/// Just enough of a JSON parser to read string and boolean values out of an
+/// object and skip over everything else. Every failure returns the error
+/// that the parser was created with.
+pub struct JsonParser<'a, E> {
+	bytes: &'a [u8],
+	pos: usize,
+	error: E,
+}
+
+impl<'a, E: Copy> JsonParser<'a, E> {
+	/// Creates a parser over `bytes`, which fails with `error`.
+	pub fn new(bytes: &'a [u8], error: E) -> Self {
+		Self {bytes, pos: 0, error}
+	}
+
+	/// Returns whether only whitespace is left.
+	pub fn is_done(&mut self) -> bool {
+		self.skip_whitespace();
+		self.pos == self.bytes.len()
+	}
+
+	fn skip_whitespace(&mut self) {
+		while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+			self.pos += 1;
+		}
+	}
+
+	pub fn peek(&mut self) -> Option<u8> {
+		self.skip_whitespace();
+		self.bytes.get(self.pos).copied()
+	}
+
+	pub fn try_consume(&mut self, byte: u8) -> bool {
+		if self.peek() == Some(byte) {
+			self.pos += 1;
+			true
+		} else {
+			false
+		}
+	}
+
+	pub fn expect(&mut self, byte: u8) -> Result<(), E> {
+		if self.try_consume(byte) {
+			Ok(())
+		} else {
+			Err(self.error)
+		}
+	}
+
+	fn next_byte(&mut self) -> Result<u8, E> {
+		let byte = *self.bytes.get(self.pos).ok_or(self.error)?;
+		self.pos += 1;
+		Ok(byte)
+	}
+
+	fn parse_hex4(&mut self) -> Result<u32, E> {
+		let mut val = 0;
+
+		for _ in 0 .. 4 {
+			let digit = (self.next_byte()? as char).to_digit(16).ok_or(self.error)?;
+			val = (val << 4) | digit;
+		}
+
+		Ok(val)
+	}
+
+	pub fn parse_string(&mut self) -> Result<String, E> {
+		self.expect(b'"')?;
+
+		let mut out = Vec::new();
+
+		loop {
+			match self.next_byte()? {
+				b'"' => break,
+
+				b'\\' => {
+					let char = match self.next_byte()? {
+						b'"' => '"',
+						b'\\' => '\\',
+						b'/' => '/',
+						b'b' => '\u{8}',
+						b'f' => '\u{c}',
+						b'n' => '\n',
+						b'r' => '\r',
+						b't' => '\t',
+
+						b'u' => {
+							let mut code_point = self.parse_hex4()?;
+
+							// characters outside the basic multilingual plane are
+							// escaped as a utf-16 surrogate pair
+							if (0xd800 .. 0xdc00).contains(&code_point) {
+								if self.next_byte()? != b'\\' || self.next_byte()? != b'u' {
+									return Err(self.error);
+								}
+
+								let low = self.parse_hex4()?;
+
+								if !(0xdc00 .. 0xe000).contains(&low) {
+									return Err(self.error);
+								}
+
+								code_point = 0x10000 + ((code_point - 0xd800) << 10) + (low - 0xdc00);
+							}
+
+							char::from_u32(code_point).ok_or(self.error)?
+						}
+
+						_ => return Err(self.error),
+					};
+
+					let mut buf = [0; 4];
+					out.extend_from_slice(char.encode_utf8(&mut buf).as_bytes());
+				}
+
+				byte if byte < 0x20 => return Err(self.error),
+				byte => out.push(byte),
+			}
+		}
+
+		String::from_utf8(out).map_err(|_| self.error)
+	}
+
+	pub fn parse_bool(&mut self) -> Result<bool, E> {
+		self.skip_whitespace();
+
+		for (literal, value) in [(&b"true"[..], true), (&b"false"[..], false)] {
+			if self.bytes[self.pos ..].starts_with(literal) {
+				self.pos += literal.len();
+				return Ok(value);
+			}
+		}
+
+		Err(self.error)
+	}
+
+	pub fn skip_value(&mut self, depth: usize) -> Result<(), E> {
+		// bounds the recursion on maliciously nested input
+		if depth > 32 {
+			return Err(self.error);
+		}
+
+		match self.peek().ok_or(self.error)? {
+			b'"' => {
+				self.parse_string()?;
+			}
+
+			open @ (b'{' | b'[') => {
+				let close = if open == b'{' {b'}'} else {b']'};
+				self.pos += 1;
+
+				if self.try_consume(close) {
+					return Ok(());
+				}
+
+				loop {
+					if open == b'{' {
+						self.parse_string()?;
+						self.expect(b':')?;
+					}
+
+					self.skip_value(depth + 1)?;
+
+					if self.try_consume(close) {
+						break;
+					}
+
+					self.expect(b',')?;
+				}
+			}
+
+			_ => {
+				// numbers and the literals true, false and null
+				let start = self.pos;
+
+				while let Some(b'0' ..= b'9' | b'a' ..= b'z' | b'+' | b'-' | b'.' | b'E') = self.bytes.get(self.pos) {
+					self.pos += 1;
+				}
+
+				if self.pos == start {
+					return Err(self.error);
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
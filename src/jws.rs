@@ -0,0 +1,261 @@
+//! Compact JSON Web Signatures using Ed25519, with the `EdDSA` algorithm from
+//! [RFC 8037](https://www.rfc-editor.org/rfc/rfc8037). JWTs are JWSs whose
+//! payload is a JSON claims set, so they can be issued and verified with
+//! [`sign`] and [`verify`] by setting `typ` to `JWT`. Checking the claims
+//! themselves, such as `exp`, is left to the caller.
+//!
+//! Only `EdDSA` is accepted when verifying, so tokens that claim any other
+//! algorithm (including `none`) are always rejected.
+
+use core::fmt;
+
+use std::string::String;
+use std::vec::Vec;
+
+use crate::base64;
+use crate::ed25519::{Signature, SigningKey, VerifyingKey};
+use crate::json::JsonParser;
+
+/// An error returned when a token can't be parsed or verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The token is not a well-formed compact JWS.
+	InvalidFormat,
+	/// The header specifies an algorithm other than `EdDSA`.
+	UnsupportedAlgorithm,
+	/// The header has a `crit` parameter, meaning it uses extensions that
+	/// must be understood to process the token.
+	UnsupportedCriticalHeader,
+	/// The signature is invalid for the given key.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidFormat => "malformed jws",
+			Error::UnsupportedAlgorithm => "unsupported jws algorithm",
+			Error::UnsupportedCriticalHeader => "unsupported critical jws header",
+			Error::InvalidSignature => "invalid jws signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The optional parameters of a protected header. The `alg` parameter is
+/// always `EdDSA`, so it isn't included here.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+	/// The media type of the token, which is `JWT` for JSON Web Tokens.
+	pub typ: Option<String>,
+	/// An identifier for the key used to sign the token.
+	pub kid: Option<String>,
+}
+
+impl Header {
+	fn to_json(&self) -> String {
+		let mut out = String::from("{\"alg\":\"EdDSA\"");
+
+		if let Some(typ) = &self.typ {
+			out.push_str(",\"typ\":");
+			write_json_string(typ, &mut out);
+		}
+
+		if let Some(kid) = &self.kid {
+			out.push_str(",\"kid\":");
+			write_json_string(kid, &mut out);
+		}
+
+		out.push('}');
+		out
+	}
+
+	fn from_json(json: &[u8]) -> Result<Self, Error> {
+		let mut parser = JsonParser::new(json, Error::InvalidFormat);
+		let mut header = Self::default();
+		let mut alg = None;
+
+		parser.expect(b'{')?;
+
+		if !parser.try_consume(b'}') {
+			loop {
+				let key = parser.parse_string()?;
+				parser.expect(b':')?;
+
+				match &*key {
+					"alg" => set_once(&mut alg, parser.parse_string()?)?,
+					"typ" => set_once(&mut header.typ, parser.parse_string()?)?,
+					"kid" => set_once(&mut header.kid, parser.parse_string()?)?,
+					"crit" => return Err(Error::UnsupportedCriticalHeader),
+					_ => parser.skip_value(0)?,
+				}
+
+				if parser.try_consume(b'}') {
+					break;
+				}
+
+				parser.expect(b',')?;
+			}
+		}
+
+		if !parser.is_done() {
+			return Err(Error::InvalidFormat);
+		}
+
+		if alg.as_deref() != Some("EdDSA") {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		Ok(header)
+	}
+}
+
+/// Signs `payload` with `key`, returning a token in the compact serialization.
+pub fn sign(key: &SigningKey, header: &Header, payload: &[u8]) -> String {
+	let mut out = String::new();
+	base64::encode_url(header.to_json().as_bytes(), &mut out);
+	out.push('.');
+	base64::encode_url(payload, &mut out);
+
+	let signature = key.sign(out.as_bytes());
+
+	out.push('.');
+	base64::encode_url(signature.as_bytes(), &mut out);
+	out
+}
+
+/// Verifies a token in the compact serialization against `key`, returning its
+/// header and payload if it's valid.
+pub fn verify(key: &VerifyingKey, token: &str) -> Result<(Header, Vec<u8>), Error> {
+	let (signing_input, encoded_signature) = token.rsplit_once('.')
+		.ok_or(Error::InvalidFormat)?;
+
+	let (encoded_header, encoded_payload) = signing_input.split_once('.')
+		.ok_or(Error::InvalidFormat)?;
+
+	let header = decode_header_part(encoded_header)?;
+
+	let signature = base64::decode_url(encoded_signature)
+		.and_then(|bytes| Signature::try_from(&bytes[..]).ok())
+		.ok_or(Error::InvalidFormat)?;
+
+	if !key.verify(signing_input.as_bytes(), &signature) {
+		return Err(Error::InvalidSignature);
+	}
+
+	let payload = base64::decode_url(encoded_payload).ok_or(Error::InvalidFormat)?;
+
+	Ok((header, payload))
+}
+
+/// Parses the header of a token without verifying it. This is useful to find
+/// the verification key from the `kid` parameter, but nothing in the header
+/// should be trusted until the token has been verified.
+pub fn decode_header(token: &str) -> Result<Header, Error> {
+	let (encoded_header, _) = token.split_once('.').ok_or(Error::InvalidFormat)?;
+	decode_header_part(encoded_header)
+}
+
+fn decode_header_part(encoded_header: &str) -> Result<Header, Error> {
+	let json = base64::decode_url(encoded_header).ok_or(Error::InvalidFormat)?;
+	Header::from_json(&json)
+}
+
+// duplicate parameters are rejected rather than picking one of them
+fn set_once(slot: &mut Option<String>, val: String) -> Result<(), Error> {
+	if slot.is_some() {
+		return Err(Error::InvalidFormat);
+	}
+
+	*slot = Some(val);
+	Ok(())
+}
+
+fn write_json_string(string: &str, out: &mut String) {
+	out.push('"');
+
+	for char in string.chars() {
+		match char {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			_ if (char as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", char as u32)),
+			_ => out.push(char),
+		}
+	}
+
+	out.push('"');
+}
+
+#[test]
+fn test_rfc8037_example() {
+	let key = SigningKey::from_bytes([
+		0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60,
+		0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+		0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+		0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+	]);
+
+	let token = sign(&key, &Header::default(), b"Example of Ed25519 signing");
+
+	assert_eq!(
+		token,
+		"eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg",
+	);
+
+	let (header, payload) = verify(&key.verifying_key(), &token).unwrap();
+	assert_eq!(header, Header::default());
+	assert_eq!(payload, b"Example of Ed25519 signing");
+}
+
+#[test]
+fn test_header_parameters() {
+	let key = SigningKey::from_bytes([7; 32]);
+
+	let header = Header {
+		typ: Some(String::from("JWT")),
+		kid: Some(String::from("key \"one\"\n\u{1f511}")),
+	};
+
+	let token = sign(&key, &header, b"{\"sub\":\"1234567890\"}");
+	assert_eq!(decode_header(&token).unwrap(), header);
+	assert_eq!(verify(&key.verifying_key(), &token).unwrap().0, header);
+
+	let parse = |json: &str| Header::from_json(json.as_bytes());
+
+	assert_eq!(
+		parse(r#" { "x5u" : [1, {"a": null}, -2.5e3], "kid": "🔑", "alg": "EdDSA" } "#),
+		Ok(Header {typ: None, kid: Some(String::from("\u{1f511}"))}),
+	);
+
+	assert_eq!(parse(r#"{"alg":"none"}"#), Err(Error::UnsupportedAlgorithm));
+	assert_eq!(parse(r#"{"typ":"JWT"}"#), Err(Error::UnsupportedAlgorithm));
+	assert_eq!(parse(r#"{"alg":"EdDSA","crit":["b64"],"b64":false}"#), Err(Error::UnsupportedCriticalHeader));
+	assert_eq!(parse(r#"{"alg":"EdDSA","alg":"EdDSA"}"#), Err(Error::InvalidFormat));
+	assert_eq!(parse(r#"{"alg":"EdDSA"}x"#), Err(Error::InvalidFormat));
+}
+
+#[test]
+fn test_rejects_tampered_tokens() {
+	let key = SigningKey::from_bytes([7; 32]);
+	let token = sign(&key, &Header::default(), b"payload");
+
+	let (rest, signature) = token.rsplit_once('.').unwrap();
+	let (header, _) = rest.split_once('.').unwrap();
+
+	let mut other_payload = String::new();
+	base64::encode_url(b"payloae", &mut other_payload);
+
+	let tampered = format!("{}.{}.{}", header, other_payload, signature);
+	assert_eq!(verify(&key.verifying_key(), &tampered), Err(Error::InvalidSignature));
+
+	let wrong_key = SigningKey::from_bytes([8; 32]).verifying_key();
+	assert_eq!(verify(&wrong_key, &token), Err(Error::InvalidSignature));
+
+	assert_eq!(verify(&key.verifying_key(), "a.b"), Err(Error::InvalidFormat));
+}
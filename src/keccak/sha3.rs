@@ -1,67 +1,298 @@
-//! An implementation of [SHA-3](https://en.wikipedia.org/wiki/SHA-3).
+//! An implementation of [SHA-3](https://en.wikipedia.org/wiki/SHA-3) and the
+//! SHAKE128 and SHAKE256 extendable-output functions from FIPS 202.
 
 use super::keccak;
 
-struct Padding<'a> {
-	bytes: &'a [u8],
-	done: bool,
+// the keccak sponge, absorbing and squeezing RATE bytes at a time
+#[derive(Clone)]
+struct Sponge<const RATE: usize> {
+	state: [[u64; 5]; 5],
+	buffer: [u8; RATE],
+	buffer_len: usize,
 }
 
-impl<'a> Padding<'a> {
-	fn new(bytes: &'a [u8]) -> Self {
-		Self {bytes, done: false}
+impl<const RATE: usize> Sponge<RATE> {
+	fn new() -> Self {
+		Self {
+			state: [[0; 5]; 5],
+			buffer: [0; RATE],
+			buffer_len: 0,
+		}
 	}
-}
 
-impl<'a> Iterator for Padding<'a> {
-	type Item = [u64; 9];
+	fn absorb(&mut self, mut bytes: &[u8]) {
+		while bytes.len() > 0 {
+			let len = usize::min(bytes.len(), RATE - self.buffer_len);
+			self.buffer[self.buffer_len ..][.. len].copy_from_slice(&bytes[.. len]);
+			self.buffer_len += len;
+			bytes = &bytes[len ..];
 
-	fn next(&mut self) -> Option<Self::Item> {
-		if self.done {
-			return None;
+			if self.buffer_len == RATE {
+				self.absorb_buffer();
+			}
 		}
+	}
 
-		let mut buf = [0; 72];
-		let buf_len = buf.len();
-
-		if self.bytes.len() >= buf_len {
-			buf.copy_from_slice(&self.bytes[.. buf_len]);
-			self.bytes = &self.bytes[buf_len ..];
-		} else {
-			buf[.. self.bytes.len()].copy_from_slice(self.bytes);
-			buf[self.bytes.len()] |= 0x06;
-			*buf.last_mut().unwrap() |= 0x80;
-			self.done = true;
+	fn absorb_buffer(&mut self) {
+		for i in 0 .. RATE / 8 {
+			let val = u64::from_le_bytes(self.buffer[i * 8 ..][.. 8].try_into().unwrap());
+			self.state[i % 5][i / 5] ^= val;
 		}
 
-		let mut out = [0; 9];
+		keccak(&mut self.state);
+		self.buffer = [0; RATE];
+		self.buffer_len = 0;
+	}
+
+	// pads the input with the domain separation bits, after which the buffer
+	// holds output, and is empty
+	fn pad(&mut self, domain: u8) {
+		self.buffer[self.buffer_len] |= domain;
+		self.buffer[RATE - 1] |= 0x80;
+		self.absorb_buffer();
+		self.buffer_len = RATE;
+	}
+
+	fn squeeze(&mut self, mut out: &mut [u8]) {
+		while out.len() > 0 {
+			if self.buffer_len == RATE {
+				for i in 0 .. RATE / 8 {
+					self.buffer[i * 8 ..][.. 8].copy_from_slice(&self.state[i % 5][i / 5].to_le_bytes());
+				}
 
-		for i in 0 .. 9 {
-			out[i] = u64::from_le_bytes(buf[i * 8 ..][.. 8].try_into().unwrap());
-		}
+				keccak(&mut self.state);
+				self.buffer_len = 0;
+			}
 
-		Some(out)
+			let len = usize::min(out.len(), RATE - self.buffer_len);
+			out[.. len].copy_from_slice(&self.buffer[self.buffer_len ..][.. len]);
+			self.buffer_len += len;
+			out = &mut out[len ..];
+		}
 	}
 }
 
+// the domain separation bits of SHA-3 and SHAKE, together with the first bit
+// of the padding
+const SHA3_DOMAIN: u8 = 0x06;
+const SHAKE_DOMAIN: u8 = 0x1f;
+
+fn sha3<const RATE: usize, const N: usize>(bytes: &[u8]) -> [u8; N] {
+	let mut sponge = Sponge::<RATE>::new();
+	sponge.absorb(bytes);
+	sponge.pad(SHA3_DOMAIN);
+
+	let mut out = [0; N];
+	sponge.squeeze(&mut out);
+	out
+}
+
+/// Returns the SHA3-256 digest of the byte slice passed to it.
+pub fn sha3_256(bytes: &[u8]) -> [u8; 32] {
+	sha3::<136, 32>(bytes)
+}
+
 /// Returns the SHA3-512 digest of the byte slice passed to it.
 pub fn sha3_512(bytes: &[u8]) -> [u8; 64] {
-	let mut state = [[0; 5]; 5];
+	sha3::<72, 64>(bytes)
+}
+
+/// A SHA3-256 hasher. Input is passed in piece by piece with
+/// [`Sha3_256::add_bytes`].
+#[derive(Clone)]
+pub struct Sha3_256(Sponge<136>);
+
+impl Sha3_256 {
+	/// Creates a new SHA3-256 hasher.
+	pub fn new() -> Self {
+		Self(Sponge::new())
+	}
+
+	/// Adds bytes to the input being hashed.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.0.absorb(bytes);
+	}
+
+	/// Finishes hashing, returning the digest.
+	pub fn out(mut self) -> [u8; 32] {
+		self.0.pad(SHA3_DOMAIN);
+
+		let mut out = [0; 32];
+		self.0.squeeze(&mut out);
+		out
+	}
+}
+
+impl Default for Sha3_256 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+macro_rules! shake_impl {
+	(
+		$name:ident,
+		$reader:ident,
+		$function:ident,
+		$bits:literal,
+		$rate:literal,
+	) => {
+		#[doc = concat!("A SHAKE", $bits, " hasher, which produces output of any length. Input is passed")]
+		#[doc = concat!("in piece by piece with [`", stringify!($name), "::add_bytes`].")]
+		#[derive(Clone)]
+		pub struct $name(Sponge<$rate>);
+
+		impl $name {
+			#[doc = concat!("Creates a new SHAKE", $bits, " hasher.")]
+			pub fn new() -> Self {
+				Self(Sponge::new())
+			}
 
-	for block in Padding::new(bytes) {
-		for (i, val) in block.into_iter().enumerate() {
-			state[i % 5][i / 5] ^= val;
+			/// Adds bytes to the input being hashed.
+			pub fn add_bytes(&mut self, bytes: &[u8]) {
+				self.0.absorb(bytes);
+			}
+
+			/// Finishes hashing, returning a reader that the output can be
+			/// read from incrementally.
+			pub fn into_reader(mut self) -> $reader {
+				self.0.pad(SHAKE_DOMAIN);
+				$reader(self.0)
+			}
+
+			/// Finishes hashing, writing as many bytes of output as fit in `out`.
+			pub fn out_into(self, out: &mut [u8]) {
+				self.into_reader().read(out);
+			}
+
+			/// Finishes hashing, returning `N` bytes of output.
+			pub fn out<const N: usize>(self) -> [u8; N] {
+				let mut out = [0; N];
+				self.out_into(&mut out);
+				out
+			}
+		}
+
+		impl Default for $name {
+			fn default() -> Self {
+				Self::new()
+			}
+		}
+
+		#[doc = concat!("The output of a [`", stringify!($name), "`] hasher, which can be read in pieces.")]
+		#[derive(Clone)]
+		pub struct $reader(Sponge<$rate>);
+
+		impl $reader {
+			/// Fills `out` with the next bytes of output.
+			pub fn read(&mut self, out: &mut [u8]) {
+				self.0.squeeze(out);
+			}
 		}
 
-		keccak(&mut state);
+		#[doc = concat!("Returns `N` bytes of SHAKE", $bits, " output for the byte slice passed to it.")]
+		pub fn $function<const N: usize>(bytes: &[u8]) -> [u8; N] {
+			let mut hasher = $name::new();
+			hasher.add_bytes(bytes);
+			hasher.out()
+		}
+	};
+}
+
+// the rates are (1600 - 2 * 128) / 8 and (1600 - 2 * 256) / 8
+shake_impl!(Shake128, Shake128Reader, shake128, "128", 168,);
+shake_impl!(Shake256, Shake256Reader, shake256, "256", 136,);
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// checked against python's hashlib.shake_256
+#[test]
+fn test_shake256() {
+	assert_eq!(shake256::<32>(b""), hex("46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f"));
+
+	let mut input = [0; 200];
+
+	for i in 0 .. 200 {
+		input[i] = i as u8;
 	}
 
-	let mut out = [0; 64];
+	let expected: [u8; 300] = hex(concat!(
+		"4ee1ca03272b05d3bfb1e1c79a967f823b9fc5e4bb3987b1ba9e9cb5afb07a5ee3a07fbd457a94364964a841e7f466e5",
+		"a022e21ab7f673c18ba98cdb1d5aecfae62268b068f1e4bf9ee9853bcce08dcd491c629aa218b60d3d453e83a554eb17",
+		"6cfef9729e99ff3a8127c49e3c3cf19ad26018ed796fedce98c5f867ec2bacbdb8012cc52b76e6d24a80fa3692d02a03",
+		"634b34b2fb336232e4c027dca0cc4bd03a01f1cec8c35ad0e51687fad4e18ebc23a75851d466979d59db7391b61702a7",
+		"fc85a1162bdbaaeab699499162f551da8b0c839f88ff96b8dd79015606526ab78fd1c101660de85653340f3d1dac2a22",
+		"bcf1a2bef88d742de9006c2d5b6d8acd586b6bee76f85cccbf94e387c53c23e716c670c4db23c67901358ae64f3f0cce",
+		"dfa05b29e84e1a11a635bfe7",
+	));
 
-	for i in 0 .. 8 {
-		let val = state[i % 5][i / 5];
-		out[8 * i ..][.. 8].copy_from_slice(&val.to_le_bytes());
+	assert_eq!(shake256::<300>(&input), expected);
+
+	// the same input, split across calls at awkward boundaries
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(&input[.. 7]);
+	hasher.add_bytes(&input[7 .. 150]);
+	hasher.add_bytes(&input[150 ..]);
+	assert_eq!(hasher.out::<300>(), expected);
+}
+
+// checked against python's hashlib.sha3_256 and hashlib.sha3_512
+#[test]
+fn test_sha3() {
+	let mut input = [0; 200];
+
+	for i in 0 .. 200 {
+		input[i] = i as u8;
 	}
 
-	out
+	assert_eq!(sha3_256(b""), hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"));
+	assert_eq!(sha3_256(&input), hex("5f728f63bf5ee48c77f453c0490398fa645b8d4c4e56be9a41cfec344d6ca899"));
+
+	// split across the rate, so that some pieces straddle a block boundary
+	let mut hasher = Sha3_256::new();
+	hasher.add_bytes(&input[.. 100]);
+	hasher.add_bytes(&input[100 .. 137]);
+	hasher.add_bytes(&input[137 ..]);
+	assert_eq!(hasher.out(), sha3_256(&input));
+
+	assert_eq!(sha3_512(&input), hex(concat!(
+		"ea5d05f19348dd589793354793a15f37a73b4c0bb4e750b9a00757dfce2f8b65",
+		"a64191bb9b137de00feef6474cfd47abf7880efbc51614a5715df12cfe0caee3",
+	)));
+}
+
+// checked against python's hashlib.shake_128
+#[test]
+fn test_shake128_reader() {
+	let mut input = [0; 200];
+
+	for i in 0 .. 200 {
+		input[i] = i as u8;
+	}
+
+	let expected: [u8; 400] = hex(concat!(
+		"0c4234ca1e31801ae606f8b8d8e0665c66f42a21d601c2681858a92c79ad5d69e143c3b1393dd894e7abd5621b0d877f",
+		"3573a34245e6b911f671081664a5fa53f778886cb56bdba60b2e8d21bd5b68b2f03f7db45fab8bec05d5869227359673",
+		"93f6c99991150acb1dcbfe12e54793975742408b347feedeabfeb77f9bbc70f3b14024309f530cc8919ed69e58b9b8ec",
+		"e0cf40db1b7a33d1329885e9ca4004b1fba4bad349b3f98d635b9775fc9cb1027c1e431756302e109614ff269d8415f4",
+		"3b504fbdff98605f9bf8a5ac0120f6e2403cc38fc07c6dfe2575f52f208cdf030b9fbdc20ecf6cbff7ff8e22744c70b2",
+		"5e3fa55eca18d67f3767f095f03856264588cf1fd09f29da759c2e849b1f345feebde0f271a418c12e126fbe086095b9",
+		"433e06a84f609a0c91793cc7379342c5822870da2c37ea464a0ad2d778678a33d40bc054dfe5f39fcf3dae74a1e11e5c",
+		"62dfab35b73cd2ecf088cc55d9724862c7641051d76a524264402261d3d8f601fee6ee2f71a7379d317fde494491ec87",
+		"3fa6be71b2cf3888ff169e5a98c7fa85",
+	));
+
+	assert_eq!(shake128::<400>(&input), expected);
+
+	// read in pieces that straddle the 168-byte blocks
+	let mut hasher = Shake128::new();
+	hasher.add_bytes(&input);
+	let mut reader = hasher.into_reader();
+
+	let mut out = [0; 400];
+	reader.read(&mut out[.. 1]);
+	reader.read(&mut out[1 .. 170]);
+	reader.read(&mut out[170 ..]);
+	assert_eq!(out, expected);
 }
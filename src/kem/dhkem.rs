@@ -0,0 +1,114 @@
+//! DHKEM(X25519, HKDF-SHA256), implemented according to section 4.1 of
+//! [IETF RFC 9180](https://datatracker.ietf.org/doc/html/rfc9180).
+
+use crate::hmac::HmacSha256;
+use crate::x25519::{self, PublicKey, StaticSecret};
+use crate::zeroize::zeroize;
+
+use super::{Kem, SharedSecret};
+
+/// DHKEM(X25519, HKDF-SHA256), the X25519 KEM from RFC 9180. Its public keys
+/// and encapsulated keys are X25519 public keys, and it rejects small-order
+/// public keys with [`x25519::Error`].
+#[derive(Copy, Clone, Debug)]
+pub struct X25519HkdfSha256;
+
+impl X25519HkdfSha256 {
+	/// The identifier of this KEM in the HPKE registry.
+	pub const ID: u16 = 0x0020;
+}
+
+const SUITE_ID: [u8; 5] = [b'K', b'E', b'M', 0x00, 0x20];
+
+fn labeled_extract(salt: &[u8], label: &[u8], ikm: &[u8]) -> [u8; 32] {
+	let mut hmac = HmacSha256::new(salt);
+	hmac.add_bytes(b"HPKE-v1");
+	hmac.add_bytes(&SUITE_ID);
+	hmac.add_bytes(label);
+	hmac.add_bytes(ikm);
+	hmac.out()
+}
+
+// only ever expands to 32 bytes, which is a single block of HKDF output
+fn labeled_expand(prk: &[u8; 32], label: &[u8], info: &[&[u8]]) -> [u8; 32] {
+	let mut hmac = HmacSha256::new(prk);
+	hmac.add_bytes(&32u16.to_be_bytes());
+	hmac.add_bytes(b"HPKE-v1");
+	hmac.add_bytes(&SUITE_ID);
+	hmac.add_bytes(label);
+
+	for part in info {
+		hmac.add_bytes(part);
+	}
+
+	hmac.add_bytes(&[1]);
+	hmac.out()
+}
+
+fn extract_and_expand(dh: &x25519::SharedSecret, encapsulated_key: &PublicKey, pub_key: &PublicKey) -> SharedSecret {
+	let mut prk = labeled_extract(b"", b"eae_prk", dh.as_bytes());
+	let secret = labeled_expand(&prk, b"shared_secret", &[encapsulated_key.as_bytes(), pub_key.as_bytes()]);
+	zeroize(&mut prk);
+
+	SharedSecret(secret)
+}
+
+impl Kem for X25519HkdfSha256 {
+	type PublicKey = PublicKey;
+	type SecretKey = StaticSecret;
+	type EncapsulatedKey = PublicKey;
+	type Error = x25519::Error;
+
+	fn derive_key_pair(ikm: &[u8]) -> (StaticSecret, PublicKey) {
+		let mut prk = labeled_extract(b"", b"dkp_prk", ikm);
+		let secret_key = StaticSecret::from_bytes(labeled_expand(&prk, b"sk", &[]));
+		zeroize(&mut prk);
+
+		let pub_key = PublicKey::from(&secret_key);
+
+		(secret_key, pub_key)
+	}
+
+	fn encapsulate(pub_key: &PublicKey, randomness: [u8; 32]) -> Result<(SharedSecret, PublicKey), x25519::Error> {
+		let (ephemeral_secret, encapsulated_key) = Self::derive_key_pair(&randomness);
+		let dh = ephemeral_secret.diffie_hellman(pub_key)?;
+
+		Ok((extract_and_expand(&dh, &encapsulated_key, pub_key), encapsulated_key))
+	}
+
+	fn decapsulate(secret_key: &StaticSecret, encapsulated_key: &PublicKey) -> Result<SharedSecret, x25519::Error> {
+		let dh = secret_key.diffie_hellman(encapsulated_key)?;
+
+		Ok(extract_and_expand(&dh, encapsulated_key, &PublicKey::from(secret_key)))
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix A.1 of RFC 9180
+#[test]
+fn test_rfc9180_vector() {
+	let ikm_e = hex::<32>("7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234");
+	let ikm_r = hex::<32>("6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037");
+
+	let (secret_key, pub_key) = X25519HkdfSha256::derive_key_pair(&ikm_r);
+	assert_eq!(secret_key.to_bytes(), hex("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8"));
+	assert_eq!(pub_key.to_bytes(), hex("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d"));
+
+	let (shared_secret, encapsulated_key) = X25519HkdfSha256::encapsulate(&pub_key, ikm_e).unwrap();
+	assert_eq!(encapsulated_key.to_bytes(), hex("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431"));
+	assert_eq!(shared_secret.to_bytes(), hex("fe0e18c9f024ce43799ae393c7e8fe8fce9d218875e8227b0187c04e7d2ea1fc"));
+
+	let decapsulated = X25519HkdfSha256::decapsulate(&secret_key, &encapsulated_key).unwrap();
+	assert_eq!(decapsulated.to_bytes(), shared_secret.to_bytes());
+}
+
+#[test]
+fn test_rejects_small_order_keys() {
+	let small_order = PublicKey::from_bytes([0; 32]);
+	let (secret_key, _) = X25519HkdfSha256::derive_key_pair(&[0x42; 32]);
+
+	assert_eq!(X25519HkdfSha256::encapsulate(&small_order, [0x11; 32]).unwrap_err(), x25519::Error::SmallOrderPoint);
+	assert_eq!(X25519HkdfSha256::decapsulate(&secret_key, &small_order).unwrap_err(), x25519::Error::SmallOrderPoint);
+}
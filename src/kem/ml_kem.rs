@@ -0,0 +1,818 @@
+//! ML-KEM-768, the module-lattice-based KEM standardized in
+//! [FIPS 203](https://doi.org/10.6028/NIST.FIPS.203), which is believed to be
+//! secure against attackers with quantum computers. Keys and ciphertexts use
+//! the standard encodings, so they interoperate with other implementations.
+//!
+//! ML-KEM is usually combined with X25519 in new protocols, so that the
+//! shared secret stays safe as long as either of them is unbroken.
+//!
+//! ```
+//! use libkrypton::kem::Kem;
+//! use libkrypton::kem::ml_kem::MlKem768;
+//!
+//! let (decapsulation_key, encapsulation_key) = MlKem768::derive_key_pair(&[0x42; 32]);
+//!
+//! // randomness should be generated by a secure random source
+//! let (shared_secret, ciphertext) = MlKem768::encapsulate(&encapsulation_key, [0x11; 32]).unwrap();
+//! let decapsulated = MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap();
+//!
+//! assert_eq!(shared_secret.to_bytes(), decapsulated.to_bytes());
+//! ```
+
+use core::convert::{Infallible, TryInto};
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use super::{Kem, SharedSecret};
+use crate::choice::{ConditionallySelectable, ConstantTimeEq};
+use crate::sha3::{sha3_256, sha3_512, Shake128, Shake256};
+use crate::zeroize::zeroize;
+
+const Q: u32 = 3329;
+const N: usize = 256;
+
+// the parameters of ML-KEM-768
+const K: usize = 3;
+const ETA1: usize = 2;
+const ETA2: usize = 2;
+const DU: usize = 10;
+const DV: usize = 4;
+
+// a polynomial encoded with 12 bits per coefficient
+const POLY_BYTES: usize = 384;
+const U_BYTES: usize = 32 * DU * K;
+
+/// The length of an encoded encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = POLY_BYTES * K + 32;
+/// The length of an encoded decapsulation key.
+pub const DECAPSULATION_KEY_LEN: usize = 2 * POLY_BYTES * K + 96;
+/// The length of an encoded ciphertext.
+pub const CIPHERTEXT_LEN: usize = U_BYTES + 32 * DV;
+
+// the coefficients of a polynomial, always fully reduced modulo q
+type Poly = [u16; N];
+
+/// An error returned when parsing keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The encapsulation key has a coefficient that isn't reduced, or the
+	/// decapsulation key contains an inconsistent encapsulation key or hash.
+	InvalidKey,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidKey => "invalid ml-kem key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// reduces x < 2q modulo q, without branching
+fn reduce_once(x: u32) -> u16 {
+	let y = x.wrapping_sub(Q);
+	y.wrapping_add(Q & 0u32.wrapping_sub(y >> 31)) as u16
+}
+
+// reduces x < 2^26 modulo q using Barrett's method, where 1290167 is
+// floor(2^32 / q)
+fn reduce(x: u32) -> u16 {
+	let quotient = ((x as u64 * 1290167) >> 32) as u32;
+	reduce_once(x - quotient * Q)
+}
+
+fn add(a: u16, b: u16) -> u16 {
+	reduce_once(a as u32 + b as u32)
+}
+
+fn sub(a: u16, b: u16) -> u16 {
+	reduce_once(a as u32 + Q - b as u32)
+}
+
+fn mul(a: u16, b: u16) -> u16 {
+	reduce(a as u32 * b as u32)
+}
+
+const fn pow17(exponent: usize) -> u16 {
+	let mut out = 1;
+	let mut i = 0;
+
+	while i < exponent {
+		out = out * 17 % Q;
+		i += 1;
+	}
+
+	out as u16
+}
+
+const fn bit_rev7(x: usize) -> usize {
+	((x as u8).reverse_bits() >> 1) as usize
+}
+
+// 17 is a primitive 256th root of unity modulo q, and these are its powers in
+// the order the NTT uses them
+const ZETAS: [u16; 128] = {
+	let mut out = [0; 128];
+	let mut i = 0;
+
+	while i < 128 {
+		out[i] = pow17(bit_rev7(i));
+		i += 1;
+	}
+
+	out
+};
+
+const GAMMAS: [u16; 128] = {
+	let mut out = [0; 128];
+	let mut i = 0;
+
+	while i < 128 {
+		out[i] = pow17(2 * bit_rev7(i) + 1);
+		i += 1;
+	}
+
+	out
+};
+
+// algorithm 9 of FIPS 203
+fn ntt(f: &mut Poly) {
+	let mut k = 1;
+	let mut len = 128;
+
+	while len >= 2 {
+		for start in (0 .. N).step_by(2 * len) {
+			let zeta = ZETAS[k];
+			k += 1;
+
+			for j in start .. start + len {
+				let t = mul(zeta, f[j + len]);
+				f[j + len] = sub(f[j], t);
+				f[j] = add(f[j], t);
+			}
+		}
+
+		len /= 2;
+	}
+}
+
+// algorithm 10 of FIPS 203
+fn inverse_ntt(f: &mut Poly) {
+	let mut k = 127;
+	let mut len = 2;
+
+	while len <= 128 {
+		for start in (0 .. N).step_by(2 * len) {
+			let zeta = ZETAS[k];
+			k -= 1;
+
+			for j in start .. start + len {
+				let t = f[j];
+				f[j] = add(t, f[j + len]);
+				f[j + len] = mul(zeta, sub(f[j + len], t));
+			}
+		}
+
+		len *= 2;
+	}
+
+	// 3303 is 128^-1 modulo q
+	for x in f.iter_mut() {
+		*x = mul(*x, 3303);
+	}
+}
+
+// adds the product of f and g, both in the NTT domain, to acc, as in
+// algorithms 11 and 12 of FIPS 203
+fn multiply_add(acc: &mut Poly, f: &Poly, g: &Poly) {
+	for i in 0 .. N / 2 {
+		let (a0, a1) = (f[2 * i], f[2 * i + 1]);
+		let (b0, b1) = (g[2 * i], g[2 * i + 1]);
+
+		let c0 = add(mul(a0, b0), mul(mul(a1, b1), GAMMAS[i]));
+		let c1 = add(mul(a0, b1), mul(a1, b0));
+
+		acc[2 * i] = add(acc[2 * i], c0);
+		acc[2 * i + 1] = add(acc[2 * i + 1], c1);
+	}
+}
+
+fn add_assign(acc: &mut Poly, f: &Poly) {
+	for i in 0 .. N {
+		acc[i] = add(acc[i], f[i]);
+	}
+}
+
+// algorithm 7 of FIPS 203, sampling a uniformly random polynomial in the NTT
+// domain by rejection sampling, which only ever handles public values
+fn sample_ntt(rho: &[u8; 32], j: u8, i: u8) -> Poly {
+	let mut hasher = Shake128::new();
+	hasher.add_bytes(rho);
+	hasher.add_bytes(&[j, i]);
+	let mut reader = hasher.into_reader();
+
+	let mut out = [0; N];
+	let mut len = 0;
+	let mut block = [0; 168];
+
+	while len < N {
+		reader.read(&mut block);
+
+		for chunk in block.chunks(3) {
+			let d1 = chunk[0] as u16 | (chunk[1] as u16 & 0x0f) << 8;
+			let d2 = (chunk[1] >> 4) as u16 | (chunk[2] as u16) << 4;
+
+			for d in [d1, d2] {
+				if (d as u32) < Q && len < N {
+					out[len] = d;
+					len += 1;
+				}
+			}
+		}
+	}
+
+	out
+}
+
+// algorithm 8 of FIPS 203, sampling from the centered binomial distribution
+// with eta = bytes.len() / 64
+fn sample_cbd(bytes: &[u8]) -> Poly {
+	let eta = bytes.len() / 64;
+	let bit = |k: usize| ((bytes[k / 8] >> (k % 8)) & 1) as u32;
+
+	let mut out = [0; N];
+
+	for i in 0 .. N {
+		let x: u32 = (0 .. eta).map(|j| bit(2 * i * eta + j)).sum();
+		let y: u32 = (0 .. eta).map(|j| bit(2 * i * eta + eta + j)).sum();
+		out[i] = reduce_once(x + Q - y);
+	}
+
+	out
+}
+
+// samples a polynomial from the output of PRF_eta(seed, nonce)
+fn sample_prf(seed: &[u8; 32], eta: usize, nonce: u8) -> Poly {
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(seed);
+	hasher.add_bytes(&[nonce]);
+
+	let mut bytes = [0; 64 * ETA1];
+	let bytes = &mut bytes[.. 64 * eta];
+	hasher.out_into(bytes);
+
+	let out = sample_cbd(bytes);
+	zeroize(bytes);
+
+	out
+}
+
+// algorithm 5 of FIPS 203, packing d bits of each coefficient in
+// little-endian order
+fn byte_encode(f: &Poly, d: usize, out: &mut [u8]) {
+	let mut acc = 0u32;
+	let mut bits = 0;
+	let mut pos = 0;
+
+	for &x in f {
+		acc |= (x as u32) << bits;
+		bits += d;
+
+		while bits >= 8 {
+			out[pos] = acc as u8;
+			pos += 1;
+			acc >>= 8;
+			bits -= 8;
+		}
+	}
+}
+
+// algorithm 6 of FIPS 203, which reduces 12-bit coefficients modulo q
+fn byte_decode(bytes: &[u8], d: usize) -> Poly {
+	let mut out = [0; N];
+	let mut acc = 0u32;
+	let mut bits = 0;
+	let mut pos = 0;
+
+	for x in out.iter_mut() {
+		while bits < d {
+			acc |= (bytes[pos] as u32) << bits;
+			pos += 1;
+			bits += 8;
+		}
+
+		*x = (acc & ((1 << d) - 1)) as u16;
+		acc >>= d;
+		bits -= d;
+
+		if d == 12 {
+			*x = reduce_once(*x as u32);
+		}
+	}
+
+	out
+}
+
+// rounds 2^d * x / q, with 2580335 being round(2^33 / q), which is exact for
+// every x < q and d <= 12
+fn compress(f: &mut Poly, d: usize) {
+	for x in f.iter_mut() {
+		let scaled = ((((*x as u64) << d) + (Q / 2) as u64) * 2580335) >> 33;
+		*x = (scaled as u16) & ((1 << d) - 1);
+	}
+}
+
+// rounds q * x / 2^d
+fn decompress(f: &mut Poly, d: usize) {
+	for x in f.iter_mut() {
+		*x = ((*x as u32 * Q + (1 << (d - 1))) >> d) as u16;
+	}
+}
+
+// the matrix A in the NTT domain, or its transpose
+fn sample_matrix(rho: &[u8; 32], transpose: bool) -> [[Poly; K]; K] {
+	let mut out = [[[0; N]; K]; K];
+
+	for i in 0 .. K {
+		for j in 0 .. K {
+			out[i][j] = match transpose {
+				true => sample_ntt(rho, i as u8, j as u8),
+				false => sample_ntt(rho, j as u8, i as u8),
+			};
+		}
+	}
+
+	out
+}
+
+// K-PKE.KeyGen, algorithm 13 of FIPS 203, writing the encryption key to ek
+// and the decryption key to dk
+fn pke_key_gen(d: &[u8; 32], ek: &mut [u8], dk: &mut [u8]) {
+	let mut input = [0; 33];
+	input[.. 32].copy_from_slice(d);
+	input[32] = K as u8;
+
+	let mut g = sha3_512(&input);
+	let rho: [u8; 32] = g[.. 32].try_into().unwrap();
+	let sigma: [u8; 32] = g[32 ..].try_into().unwrap();
+
+	let a = sample_matrix(&rho, false);
+
+	let mut s = [[0; N]; K];
+	let mut e = [[0; N]; K];
+
+	for i in 0 .. K {
+		s[i] = sample_prf(&sigma, ETA1, i as u8);
+		e[i] = sample_prf(&sigma, ETA1, (K + i) as u8);
+		ntt(&mut s[i]);
+		ntt(&mut e[i]);
+	}
+
+	for i in 0 .. K {
+		let mut t = e[i];
+
+		for j in 0 .. K {
+			multiply_add(&mut t, &a[i][j], &s[j]);
+		}
+
+		byte_encode(&t, 12, &mut ek[POLY_BYTES * i ..]);
+		byte_encode(&s[i], 12, &mut dk[POLY_BYTES * i ..]);
+	}
+
+	ek[POLY_BYTES * K ..].copy_from_slice(&rho);
+
+	zeroize(&mut g);
+	s.iter_mut().for_each(|f| zeroize(f));
+	e.iter_mut().for_each(|f| zeroize(f));
+}
+
+// K-PKE.Encrypt, algorithm 14 of FIPS 203
+fn pke_encrypt(ek: &[u8; ENCAPSULATION_KEY_LEN], m: &[u8; 32], r: &[u8; 32]) -> [u8; CIPHERTEXT_LEN] {
+	let rho: [u8; 32] = ek[POLY_BYTES * K ..].try_into().unwrap();
+	let a_transpose = sample_matrix(&rho, true);
+
+	let mut y = [[0; N]; K];
+
+	for i in 0 .. K {
+		y[i] = sample_prf(r, ETA1, i as u8);
+		ntt(&mut y[i]);
+	}
+
+	let mut out = [0; CIPHERTEXT_LEN];
+
+	for i in 0 .. K {
+		let mut u = [0; N];
+
+		for j in 0 .. K {
+			multiply_add(&mut u, &a_transpose[i][j], &y[j]);
+		}
+
+		inverse_ntt(&mut u);
+		add_assign(&mut u, &sample_prf(r, ETA2, (K + i) as u8));
+		compress(&mut u, DU);
+		byte_encode(&u, DU, &mut out[32 * DU * i ..]);
+	}
+
+	let mut v = [0; N];
+
+	for i in 0 .. K {
+		multiply_add(&mut v, &byte_decode(&ek[POLY_BYTES * i ..], 12), &y[i]);
+	}
+
+	inverse_ntt(&mut v);
+	add_assign(&mut v, &sample_prf(r, ETA2, (2 * K) as u8));
+
+	let mut mu = byte_decode(m, 1);
+	decompress(&mut mu, 1);
+	add_assign(&mut v, &mu);
+
+	compress(&mut v, DV);
+	byte_encode(&v, DV, &mut out[U_BYTES ..]);
+
+	y.iter_mut().for_each(|f| zeroize(f));
+	zeroize(&mut v);
+	zeroize(&mut mu);
+
+	out
+}
+
+// K-PKE.Decrypt, algorithm 15 of FIPS 203
+fn pke_decrypt(dk: &[u8], c: &[u8; CIPHERTEXT_LEN]) -> [u8; 32] {
+	let mut w = [0; N];
+
+	for i in 0 .. K {
+		let mut u = byte_decode(&c[32 * DU * i ..], DU);
+		decompress(&mut u, DU);
+		ntt(&mut u);
+
+		multiply_add(&mut w, &byte_decode(&dk[POLY_BYTES * i ..], 12), &u);
+	}
+
+	inverse_ntt(&mut w);
+
+	let mut v = byte_decode(&c[U_BYTES ..], DV);
+	decompress(&mut v, DV);
+
+	for i in 0 .. N {
+		w[i] = sub(v[i], w[i]);
+	}
+
+	compress(&mut w, 1);
+
+	let mut m = [0; 32];
+	byte_encode(&w, 1, &mut m);
+	zeroize(&mut w);
+
+	m
+}
+
+/// An ML-KEM-768 encapsulation key, which is the public key that secrets are
+/// encapsulated to.
+#[derive(Clone)]
+pub struct EncapsulationKey {
+	bytes: [u8; ENCAPSULATION_KEY_LEN],
+	hash: [u8; 32],
+}
+
+impl EncapsulationKey {
+	/// Parses an encoded encapsulation key, checking that every coefficient
+	/// is reduced as FIPS 203 requires.
+	pub fn from_bytes(bytes: &[u8; ENCAPSULATION_KEY_LEN]) -> Result<Self, Error> {
+		let mut encoded = [0; POLY_BYTES];
+
+		for i in 0 .. K {
+			let chunk = &bytes[POLY_BYTES * i ..][.. POLY_BYTES];
+			byte_encode(&byte_decode(chunk, 12), 12, &mut encoded);
+
+			if encoded != chunk {
+				return Err(Error::InvalidKey);
+			}
+		}
+
+		Ok(Self {bytes: *bytes, hash: sha3_256(bytes)})
+	}
+
+	/// Returns the encoding of this key.
+	pub fn to_bytes(&self) -> [u8; ENCAPSULATION_KEY_LEN] {
+		self.bytes
+	}
+
+	/// Returns a reference to the encoding of this key.
+	pub fn as_bytes(&self) -> &[u8; ENCAPSULATION_KEY_LEN] {
+		&self.bytes
+	}
+}
+
+impl PartialEq for EncapsulationKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.bytes == other.bytes
+	}
+}
+
+impl Eq for EncapsulationKey {}
+
+impl fmt::Debug for EncapsulationKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EncapsulationKey").field("hash", &self.hash).finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(EncapsulationKey, ENCAPSULATION_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| {
+	EncapsulationKey::from_bytes(&bytes)
+});
+
+text_impl!(EncapsulationKey, ENCAPSULATION_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| {
+	EncapsulationKey::from_bytes(&bytes)
+});
+
+/// An ML-KEM-768 decapsulation key, which is the secret key used to recover
+/// shared secrets. Zeroized when dropped.
+#[derive(Clone)]
+pub struct DecapsulationKey {
+	bytes: [u8; DECAPSULATION_KEY_LEN],
+}
+
+impl DecapsulationKey {
+	/// Derives a decapsulation key from a 64-byte seed, which is the `d`
+	/// and `z` values of FIPS 203 concatenated. This is the usual compact
+	/// encoding of a private key, and a random seed generates a new key.
+	pub fn from_seed(seed: [u8; 64]) -> Self {
+		let mut bytes = [0; DECAPSULATION_KEY_LEN];
+		let (dk, rest) = bytes.split_at_mut(POLY_BYTES * K);
+		let (ek, rest) = rest.split_at_mut(ENCAPSULATION_KEY_LEN);
+
+		pke_key_gen(seed[.. 32].try_into().unwrap(), ek, dk);
+		rest[.. 32].copy_from_slice(&sha3_256(ek));
+		rest[32 ..].copy_from_slice(&seed[32 ..]);
+
+		let mut seed = seed;
+		zeroize(&mut seed);
+
+		Self {bytes}
+	}
+
+	/// Generates a new decapsulation key using a cryptographically secure
+	/// random number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 64];
+		rng.fill_bytes(&mut seed);
+
+		Self::from_seed(seed)
+	}
+
+	/// Parses an expanded decapsulation key, checking that the encapsulation
+	/// key and its hash inside it are consistent.
+	pub fn from_bytes(bytes: &[u8; DECAPSULATION_KEY_LEN]) -> Result<Self, Error> {
+		let ek = &bytes[POLY_BYTES * K ..][.. ENCAPSULATION_KEY_LEN];
+		let encapsulation_key = EncapsulationKey::from_bytes(ek.try_into().unwrap())?;
+
+		if encapsulation_key.hash != bytes[DECAPSULATION_KEY_LEN - 64 ..][.. 32] {
+			return Err(Error::InvalidKey);
+		}
+
+		Ok(Self {bytes: *bytes})
+	}
+
+	/// Returns the expanded encoding of this key.
+	pub fn to_bytes(&self) -> [u8; DECAPSULATION_KEY_LEN] {
+		self.bytes
+	}
+
+	/// Returns the encapsulation key for this decapsulation key.
+	pub fn encapsulation_key(&self) -> EncapsulationKey {
+		EncapsulationKey {
+			bytes: self.bytes[POLY_BYTES * K ..][.. ENCAPSULATION_KEY_LEN].try_into().unwrap(),
+			hash: self.bytes[DECAPSULATION_KEY_LEN - 64 ..][.. 32].try_into().unwrap(),
+		}
+	}
+}
+
+impl Drop for DecapsulationKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for DecapsulationKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for DecapsulationKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for DecapsulationKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DecapsulationKey").finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(DecapsulationKey, DECAPSULATION_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| {
+	DecapsulationKey::from_bytes(&bytes)
+});
+
+/// An ML-KEM-768 ciphertext, which is the encapsulated key sent to the holder
+/// of the decapsulation key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ciphertext([u8; CIPHERTEXT_LEN]);
+
+impl Ciphertext {
+	/// Creates a ciphertext from its encoding. Any bytes are accepted, since
+	/// invalid ciphertexts are implicitly rejected when decapsulating.
+	pub fn from_bytes(bytes: [u8; CIPHERTEXT_LEN]) -> Self {
+		Self(bytes)
+	}
+
+	/// Returns the encoding of this ciphertext.
+	pub fn to_bytes(&self) -> [u8; CIPHERTEXT_LEN] {
+		self.0
+	}
+
+	/// Returns a reference to the encoding of this ciphertext.
+	pub fn as_bytes(&self) -> &[u8; CIPHERTEXT_LEN] {
+		&self.0
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Ciphertext, CIPHERTEXT_LEN, Base64, |ciphertext| ciphertext.to_bytes(), |bytes| Ciphertext::from_bytes(bytes));
+
+/// ML-KEM-768 from FIPS 203. Neither encapsulation nor decapsulation can
+/// fail: decapsulating an invalid ciphertext returns a pseudorandom shared
+/// secret that the sender won't know.
+#[derive(Copy, Clone, Debug)]
+pub struct MlKem768;
+
+impl Kem for MlKem768 {
+	type PublicKey = EncapsulationKey;
+	type SecretKey = DecapsulationKey;
+	type EncapsulatedKey = Ciphertext;
+	type Error = Infallible;
+
+	/// Derives the 64-byte seed as SHAKE256 of `ikm`, as HPKE does for
+	/// ML-KEM.
+	fn derive_key_pair(ikm: &[u8]) -> (DecapsulationKey, EncapsulationKey) {
+		let decapsulation_key = DecapsulationKey::from_seed(crate::sha3::shake256(ikm));
+		let encapsulation_key = decapsulation_key.encapsulation_key();
+
+		(decapsulation_key, encapsulation_key)
+	}
+
+	// ML-KEM.Encaps_internal, algorithm 17 of FIPS 203
+	fn encapsulate(pub_key: &EncapsulationKey, randomness: [u8; 32]) -> Result<(SharedSecret, Ciphertext), Infallible> {
+		let mut input = [0; 64];
+		input[.. 32].copy_from_slice(&randomness);
+		input[32 ..].copy_from_slice(&pub_key.hash);
+
+		let mut g = sha3_512(&input);
+		let shared_secret = SharedSecret(g[.. 32].try_into().unwrap());
+		let ciphertext = pke_encrypt(&pub_key.bytes, &randomness, g[32 ..].try_into().unwrap());
+
+		zeroize(&mut input);
+		zeroize(&mut g);
+
+		Ok((shared_secret, Ciphertext(ciphertext)))
+	}
+
+	// ML-KEM.Decaps_internal, algorithm 18 of FIPS 203
+	fn decapsulate(secret_key: &DecapsulationKey, encapsulated_key: &Ciphertext) -> Result<SharedSecret, Infallible> {
+		let bytes = &secret_key.bytes;
+		let dk = &bytes[.. POLY_BYTES * K];
+		let ek = bytes[POLY_BYTES * K ..][.. ENCAPSULATION_KEY_LEN].try_into().unwrap();
+		let h = &bytes[DECAPSULATION_KEY_LEN - 64 ..][.. 32];
+		let z = &bytes[DECAPSULATION_KEY_LEN - 32 ..];
+
+		let mut input = [0; 64];
+		input[.. 32].copy_from_slice(&pke_decrypt(dk, &encapsulated_key.0));
+		input[32 ..].copy_from_slice(h);
+
+		let mut g = sha3_512(&input);
+		let ciphertext = pke_encrypt(ek, input[.. 32].try_into().unwrap(), g[32 ..].try_into().unwrap());
+
+		let mut hasher = Shake256::new();
+		hasher.add_bytes(z);
+		hasher.add_bytes(&encapsulated_key.0);
+		let rejection: [u8; 32] = hasher.out();
+
+		// the secret is replaced by the rejection value without branching if
+		// the ciphertext doesn't re-encrypt to the same bytes
+		let matches = ciphertext[..].ct_eq(&encapsulated_key.0[..]);
+		let secret = <[u8; 32]>::conditional_select(&rejection, g[.. 32].try_into().unwrap(), matches);
+
+		zeroize(&mut input);
+		zeroize(&mut g);
+
+		Ok(SharedSecret(secret))
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// checked against OpenSSL 3.5, with `openssl genpkey -algorithm ML-KEM-768
+// -pkeyopt hexseed:0001..3f`, then `openssl pkeyutl -encap -pkeyopt
+// hexikme:4242..42` for the encapsulation and `openssl pkeyutl -decap` for
+// the implicit rejection
+#[test]
+fn test_known_answer() {
+	let mut seed = [0; 64];
+
+	for i in 0 .. 64 {
+		seed[i] = i as u8;
+	}
+
+	let decapsulation_key = DecapsulationKey::from_seed(seed);
+	let encapsulation_key = decapsulation_key.encapsulation_key();
+
+	assert_eq!(
+		sha3_256(encapsulation_key.as_bytes()),
+		hex("a24e16d8f8f9383a95b77050f4d9fd2f5733eec1d63ef3c23ebf9918173669a7"),
+	);
+
+	assert_eq!(
+		sha3_256(&decapsulation_key.to_bytes()),
+		hex("1149f17c3c4ac6ab1e3e2d9d8bd0171355ac0fa31bb8855c48ceade874c0864b"),
+	);
+
+	let (shared_secret, ciphertext) = MlKem768::encapsulate(&encapsulation_key, [0x42; 32]).unwrap();
+
+	assert_eq!(
+		sha3_256(ciphertext.as_bytes()),
+		hex("e9a0824664dba3f8f3c86ecb43a0c889030947ff01d276d04d46c204b62fc221"),
+	);
+
+	let expected = hex("b83e7f23b33f909715c7a50b0d4b1f6684d53e1f4b9056f803b29f058ccb5566");
+	assert_eq!(shared_secret.to_bytes(), expected);
+	assert_eq!(MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap().to_bytes(), expected);
+
+	// a modified ciphertext is implicitly rejected
+	let mut modified = ciphertext.to_bytes();
+	modified[0] ^= 1;
+
+	assert_eq!(
+		MlKem768::decapsulate(&decapsulation_key, &Ciphertext::from_bytes(modified)).unwrap().to_bytes(),
+		hex("3816af13752429d4e8b800fd2c691b3254d09ed953cf287c99453d3d8057b41e"),
+	);
+}
+
+// checked against `openssl genpkey -algorithm ML-KEM-768 -pkeyopt hexseed:`
+// with 64 bytes of SHAKE256 of the ikm as the seed
+#[test]
+fn test_derive_key_pair() {
+	let (decapsulation_key, encapsulation_key) = MlKem768::derive_key_pair(b"test ikm");
+
+	assert_eq!(
+		sha3_256(encapsulation_key.as_bytes()),
+		hex("12d44fc1d534197327e04b631e5517920fcaac6a157e7e2ee7525c1e3c0c1d62"),
+	);
+
+	let (shared_secret, ciphertext) = MlKem768::encapsulate(&encapsulation_key, [7; 32]).unwrap();
+	let decapsulated = MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap();
+	assert_eq!(shared_secret.to_bytes(), decapsulated.to_bytes());
+}
+
+#[test]
+fn test_key_encodings() {
+	let (decapsulation_key, encapsulation_key) = MlKem768::derive_key_pair(&[1; 32]);
+
+	let parsed = DecapsulationKey::from_bytes(&decapsulation_key.to_bytes()).unwrap();
+	assert_eq!(parsed.encapsulation_key(), encapsulation_key);
+	assert_eq!(EncapsulationKey::from_bytes(encapsulation_key.as_bytes()), Ok(encapsulation_key.clone()));
+
+	// a coefficient of q isn't reduced
+	let mut unreduced = encapsulation_key.to_bytes();
+	unreduced[0] = 0x01;
+	unreduced[1] = (unreduced[1] & 0xf0) | 0x0d;
+	assert_eq!(EncapsulationKey::from_bytes(&unreduced).unwrap_err(), Error::InvalidKey);
+
+	// the hash of the encapsulation key is checked
+	let mut inconsistent = decapsulation_key.to_bytes();
+	inconsistent[DECAPSULATION_KEY_LEN - 64] ^= 1;
+	assert_eq!(DecapsulationKey::from_bytes(&inconsistent).unwrap_err(), Error::InvalidKey);
+}
+
+#[test]
+fn test_compress_rounding() {
+	// compared against exact rounding of 2^d * x / q, with ties rounded up
+	for d in [1, 4, 10] {
+		for x in 0 .. Q {
+			let mut f = [x as u16; N];
+			compress(&mut f, d);
+
+			assert_eq!(f[0] as u32, (((2 * x) << d) + Q) / (2 * Q) % (1 << d));
+		}
+	}
+}
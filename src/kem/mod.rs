@@ -0,0 +1,109 @@
+//! Key encapsulation mechanisms (KEMs), which let a sender establish a shared
+//! secret with the holder of a public key by sending them a single
+//! encapsulated key. The [`Kem`] trait lets protocols such as HPKE be
+//! generic over the KEM they use.
+//!
+//! [`X25519HkdfSha256`] is DHKEM(X25519, HKDF-SHA256), implemented according
+//! to section 4.1 of [IETF RFC 9180](https://datatracker.ietf.org/doc/html/rfc9180),
+//! and [`ml_kem::MlKem768`] is the post-quantum ML-KEM-768 from FIPS 203.
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::zeroize::zeroize;
+
+#[cfg(all(feature = "hmac", feature = "x25519"))]
+pub use dhkem::X25519HkdfSha256;
+
+#[cfg(feature = "ml-kem")]
+pub mod ml_kem;
+
+#[cfg(all(feature = "hmac", feature = "x25519"))]
+mod dhkem;
+
+/// A key encapsulation mechanism.
+pub trait Kem {
+	/// The public key that secrets are encapsulated to.
+	type PublicKey;
+	/// The secret key used to decapsulate secrets.
+	type SecretKey;
+	/// The value sent to the holder of the secret key, from which they
+	/// recover the shared secret.
+	type EncapsulatedKey;
+	/// The error returned when encapsulation or decapsulation fails.
+	type Error;
+
+	/// Deterministically derives a key pair from the input keying material
+	/// `ikm`, which must have at least as much entropy as the secret key.
+	fn derive_key_pair(ikm: &[u8]) -> (Self::SecretKey, Self::PublicKey);
+
+	/// Generates a shared secret and encapsulates it to `pub_key`.
+	/// `randomness` must be freshly generated by a secure random source for
+	/// every call.
+	fn encapsulate(
+		pub_key: &Self::PublicKey,
+		randomness: [u8; 32],
+	) -> Result<(SharedSecret, Self::EncapsulatedKey), Self::Error>;
+
+	/// Like [`Kem::encapsulate`], but generates the randomness with `rng`,
+	/// which must be a cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	fn encapsulate_with_rng<R: RngCore + CryptoRng>(
+		pub_key: &Self::PublicKey,
+		rng: &mut R,
+	) -> Result<(SharedSecret, Self::EncapsulatedKey), Self::Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = Self::encapsulate(pub_key, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Recovers the shared secret from an encapsulated key.
+	fn decapsulate(
+		secret_key: &Self::SecretKey,
+		encapsulated_key: &Self::EncapsulatedKey,
+	) -> Result<SharedSecret, Self::Error>;
+}
+
+/// A 32-byte secret shared through a [`Kem`]. Zeroized when dropped.
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+	/// Returns the 32 bytes of this shared secret.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Returns a reference to the 32 bytes of this shared secret.
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+}
+
+impl Drop for SharedSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SharedSecret {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SharedSecret {}
+
+// deliberately doesn't print the shared secret
+impl fmt::Debug for SharedSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SharedSecret").finish_non_exhaustive()
+	}
+}
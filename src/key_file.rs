@@ -0,0 +1,436 @@
+//! A small, versioned format for storing Ed25519 and X25519 private keys at
+//! rest, encrypted with a passphrase. The key is derived from the passphrase
+//! with Argon2id or scrypt, and the private key is encrypted with
+//! XChaCha20-Poly1305. The KDF and its parameters are stored in the header,
+//! which is authenticated along with the key, so the cost can be raised for
+//! new files without breaking old ones.
+//!
+//! ```
+//! use libkrypton::ed25519::SigningKey;
+//! use libkrypton::key_file::Kdf;
+//!
+//! # let seed = [0x42; 32];
+//! # let randomness = [0x24; 32];
+//! # let kdf = Kdf::Argon2id {memory_kib: 64, passes: 1, lanes: 1};
+//! let key = SigningKey::from_bytes(seed);
+//! let file = key.to_key_file(b"hunter2", kdf, randomness);
+//!
+//! let loaded = SigningKey::from_key_file(&file, b"hunter2").unwrap();
+//! assert_eq!(loaded.to_bytes(), key.to_bytes());
+//! ```
+//!
+//! The file is laid out as follows, with integers in big-endian order:
+//!
+//! | Field | Size |
+//! | --- | --- |
+//! | The magic bytes `KRYPTKEY` | 8 bytes |
+//! | The version, currently 1 | 1 byte |
+//! | The key type, 1 for Ed25519 or 2 for X25519 | 1 byte |
+//! | The KDF, 1 for Argon2id or 2 for scrypt | 1 byte |
+//! | For Argon2id, the memory in KiB, the passes and the lanes | 12 bytes |
+//! | For scrypt, the base 2 log of N, then r and p | 9 bytes |
+//! | The KDF salt | 16 bytes |
+//! | The XChaCha20-Poly1305 nonce | 24 bytes |
+//! | The encrypted private key | 32 bytes |
+//! | The Poly1305 tag, with everything before the key as associated data | 16 bytes |
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::argon2::argon2id;
+use crate::chacha20::ChaCha20;
+use crate::chacha20poly1305::{xchacha20poly1305_decrypt, xchacha20poly1305_encrypt};
+use crate::ed25519::SigningKey;
+use crate::scrypt::scrypt;
+use crate::x25519::StaticSecret;
+use crate::zeroize::zeroize;
+
+const MAGIC: [u8; 8] = *b"KRYPTKEY";
+const VERSION: u8 = 1;
+
+const KDF_ARGON2ID: u8 = 1;
+const KDF_SCRYPT: u8 = 2;
+
+/// The most memory any KDF may use when loading a key, 1 GiB, so that a
+/// malicious file can't exhaust the memory of whoever opens it.
+pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+
+/// An error returned when loading an encrypted key file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The file is not a valid encrypted key file.
+	InvalidEncoding,
+	/// The file was written by a newer version of the format.
+	UnsupportedVersion,
+	/// The file holds a different type of key than the one being loaded.
+	WrongKeyType,
+	/// The KDF parameters in the file would take more than [`MAX_MEMORY_KIB`]
+	/// of memory, or an unreasonable amount of time.
+	WorkFactorTooHigh,
+	/// The passphrase is wrong, or the file has been tampered with.
+	DecryptionFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid key file encoding",
+			Error::UnsupportedVersion => "unsupported key file version",
+			Error::WrongKeyType => "key file holds a different type of key",
+			Error::WorkFactorTooHigh => "key file work factor too high",
+			Error::DecryptionFailed => "key file decryption failed",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The type of private key held by a key file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyType {
+	/// An Ed25519 [`SigningKey`].
+	Ed25519,
+	/// An X25519 [`StaticSecret`].
+	X25519,
+}
+
+impl KeyType {
+	fn to_byte(self) -> u8 {
+		match self {
+			KeyType::Ed25519 => 1,
+			KeyType::X25519 => 2,
+		}
+	}
+
+	fn from_byte(byte: u8) -> Result<Self, Error> {
+		match byte {
+			1 => Ok(KeyType::Ed25519),
+			2 => Ok(KeyType::X25519),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+}
+
+/// The function used to derive the encryption key from the passphrase, along
+/// with its parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kdf {
+	/// Argon2id, using `memory_kib` KiB of memory, split into `lanes` lanes,
+	/// with `passes` passes over it.
+	Argon2id {memory_kib: u32, passes: u32, lanes: u32},
+	/// scrypt, with a cost parameter of `2 ** log_n`.
+	Scrypt {log_n: u8, r: u32, p: u32},
+}
+
+impl Default for Kdf {
+	/// Argon2id with 64 MiB of memory, 3 passes and 1 lane, the second
+	/// recommended option in RFC 9106.
+	fn default() -> Self {
+		Kdf::Argon2id {memory_kib: 1 << 16, passes: 3, lanes: 1}
+	}
+}
+
+impl Kdf {
+	// rejects parameters that the kdf doesn't accept, or that would take too
+	// many resources to be reasonable for decrypting a key
+	fn check(self) -> Result<(), Error> {
+		match self {
+			Kdf::Argon2id {memory_kib, passes, lanes} => {
+				if passes == 0 || lanes == 0 || memory_kib / 8 < lanes {
+					return Err(Error::InvalidEncoding);
+				}
+
+				if memory_kib > MAX_MEMORY_KIB || passes > 64 || lanes > 64 {
+					return Err(Error::WorkFactorTooHigh);
+				}
+			},
+			Kdf::Scrypt {log_n, r, p} => {
+				if log_n == 0 || log_n >= 32 || r == 0 || p == 0 {
+					return Err(Error::InvalidEncoding);
+				}
+
+				// scrypt needs 128 * r * N bytes, and takes time proportional to
+				// that times p
+				let memory_kib = (r as u64) << log_n >> 3;
+
+				if memory_kib > MAX_MEMORY_KIB as u64 || p > 64 {
+					return Err(Error::WorkFactorTooHigh);
+				}
+			},
+		}
+
+		Ok(())
+	}
+
+	fn derive_key(self, passphrase: &[u8], salt: &[u8; 16]) -> [u8; 32] {
+		let mut key = [0; 32];
+
+		match self {
+			Kdf::Argon2id {memory_kib, passes, lanes} => {
+				argon2id(passphrase, salt, &[], &[], passes, memory_kib, lanes, &mut key);
+			},
+			Kdf::Scrypt {log_n, r, p} => {
+				scrypt(passphrase, salt, log_n, r, p, &mut key);
+			},
+		}
+
+		key
+	}
+}
+
+fn write_header(out: &mut Vec<u8>, key_type: KeyType, kdf: Kdf) {
+	out.extend_from_slice(&MAGIC);
+	out.push(VERSION);
+	out.push(key_type.to_byte());
+
+	match kdf {
+		Kdf::Argon2id {memory_kib, passes, lanes} => {
+			out.push(KDF_ARGON2ID);
+			out.extend_from_slice(&memory_kib.to_be_bytes());
+			out.extend_from_slice(&passes.to_be_bytes());
+			out.extend_from_slice(&lanes.to_be_bytes());
+		},
+		Kdf::Scrypt {log_n, r, p} => {
+			out.push(KDF_SCRYPT);
+			out.push(log_n);
+			out.extend_from_slice(&r.to_be_bytes());
+			out.extend_from_slice(&p.to_be_bytes());
+		},
+	}
+}
+
+// reads the fixed part of the header, returning the key type, the kdf and
+// the length of the header up to the salt
+fn read_header(file: &[u8]) -> Result<(KeyType, Kdf, usize), Error> {
+	if file.len() < 11 || file[.. 8] != MAGIC {
+		return Err(Error::InvalidEncoding);
+	}
+
+	if file[8] != VERSION {
+		return Err(Error::UnsupportedVersion);
+	}
+
+	let key_type = KeyType::from_byte(file[9])?;
+	let params = &file[11 ..];
+
+	let read_u32 = |pos: usize| u32::from_be_bytes(params[pos .. pos + 4].try_into().unwrap());
+
+	let (kdf, params_len) = match file[10] {
+		KDF_ARGON2ID if params.len() >= 12 => {
+			(Kdf::Argon2id {memory_kib: read_u32(0), passes: read_u32(4), lanes: read_u32(8)}, 12)
+		},
+		KDF_SCRYPT if params.len() >= 9 => {
+			(Kdf::Scrypt {log_n: params[0], r: read_u32(1), p: read_u32(5)}, 9)
+		},
+		_ => return Err(Error::InvalidEncoding),
+	};
+
+	Ok((key_type, kdf, 11 + params_len))
+}
+
+/// Returns the type of private key held by an encrypted key file, without
+/// decrypting it.
+pub fn key_type(file: &[u8]) -> Result<KeyType, Error> {
+	read_header(file).map(|(key_type, _, _)| key_type)
+}
+
+fn seal(key_type: KeyType, secret: &[u8; 32], passphrase: &[u8], kdf: Kdf, randomness: [u8; 32]) -> Vec<u8> {
+	kdf.check().expect("invalid key file kdf parameters");
+
+	let mut random_stream = ChaCha20::new(randomness, [0; 12]);
+
+	let mut salt = [0; 16];
+	let mut nonce = [0; 24];
+	random_stream.read_infallible(&mut salt);
+	random_stream.read_infallible(&mut nonce);
+
+	let mut out = Vec::with_capacity(123);
+	write_header(&mut out, key_type, kdf);
+	out.extend_from_slice(&salt);
+	out.extend_from_slice(&nonce);
+
+	let header_len = out.len();
+	out.extend_from_slice(secret);
+
+	let mut key = kdf.derive_key(passphrase, &salt);
+	let (header, body) = out.split_at_mut(header_len);
+	let tag = xchacha20poly1305_encrypt(key, nonce, header, body);
+	zeroize(&mut key);
+
+	out.extend_from_slice(&tag);
+	out
+}
+
+fn open(expected: KeyType, file: &[u8], passphrase: &[u8]) -> Result<[u8; 32], Error> {
+	let (key_type, kdf, params_end) = read_header(file)?;
+
+	if file.len() != params_end + 16 + 24 + 32 + 16 {
+		return Err(Error::InvalidEncoding);
+	}
+
+	if key_type != expected {
+		return Err(Error::WrongKeyType);
+	}
+
+	kdf.check()?;
+
+	let salt: [u8; 16] = file[params_end .. params_end + 16].try_into().unwrap();
+	let nonce: [u8; 24] = file[params_end + 16 .. params_end + 40].try_into().unwrap();
+	let header = &file[.. params_end + 40];
+	let mut secret: [u8; 32] = file[params_end + 40 .. params_end + 72].try_into().unwrap();
+	let tag: [u8; 16] = file[params_end + 72 ..].try_into().unwrap();
+
+	let mut key = kdf.derive_key(passphrase, &salt);
+	let result = xchacha20poly1305_decrypt(key, nonce, header, &mut secret, tag);
+	zeroize(&mut key);
+
+	result.map_err(|_| Error::DecryptionFailed)?;
+	Ok(secret)
+}
+
+impl SigningKey {
+	/// Encrypts this key with `passphrase`, returning an encrypted key file.
+	/// `randomness` is expanded into the KDF salt and the nonce, so it must be
+	/// freshly generated by a secure random source for every file.
+	///
+	/// # Panics
+	/// * Panics if the `kdf` parameters are invalid, or higher than
+	///   [`SigningKey::from_key_file`] accepts.
+	pub fn to_key_file(&self, passphrase: &[u8], kdf: Kdf, randomness: [u8; 32]) -> Vec<u8> {
+		let mut seed = self.to_bytes();
+		let out = seal(KeyType::Ed25519, &seed, passphrase, kdf, randomness);
+		zeroize(&mut seed);
+
+		out
+	}
+
+	/// Encrypts this key like [`SigningKey::to_key_file`], with randomness
+	/// generated by `rng`, which must be a cryptographically secure random
+	/// number generator.
+	///
+	/// # Panics
+	/// * Panics if the `kdf` parameters are invalid, or higher than
+	///   [`SigningKey::from_key_file`] accepts.
+	#[cfg(feature = "rand_core")]
+	pub fn to_key_file_with_rng<R: RngCore + CryptoRng>(&self, passphrase: &[u8], kdf: Kdf, rng: &mut R) -> Vec<u8> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let out = self.to_key_file(passphrase, kdf, randomness);
+		zeroize(&mut randomness);
+
+		out
+	}
+
+	/// Decrypts an encrypted key file holding an Ed25519 key.
+	pub fn from_key_file(file: &[u8], passphrase: &[u8]) -> Result<Self, Error> {
+		let mut seed = open(KeyType::Ed25519, file, passphrase)?;
+		let key = SigningKey::from_bytes(seed);
+		zeroize(&mut seed);
+
+		Ok(key)
+	}
+}
+
+impl StaticSecret {
+	/// Encrypts this key with `passphrase`, returning an encrypted key file.
+	/// `randomness` is expanded into the KDF salt and the nonce, so it must be
+	/// freshly generated by a secure random source for every file.
+	///
+	/// # Panics
+	/// * Panics if the `kdf` parameters are invalid, or higher than
+	///   [`StaticSecret::from_key_file`] accepts.
+	pub fn to_key_file(&self, passphrase: &[u8], kdf: Kdf, randomness: [u8; 32]) -> Vec<u8> {
+		let mut bytes = self.to_bytes();
+		let out = seal(KeyType::X25519, &bytes, passphrase, kdf, randomness);
+		zeroize(&mut bytes);
+
+		out
+	}
+
+	/// Encrypts this key like [`StaticSecret::to_key_file`], with randomness
+	/// generated by `rng`, which must be a cryptographically secure random
+	/// number generator.
+	///
+	/// # Panics
+	/// * Panics if the `kdf` parameters are invalid, or higher than
+	///   [`StaticSecret::from_key_file`] accepts.
+	#[cfg(feature = "rand_core")]
+	pub fn to_key_file_with_rng<R: RngCore + CryptoRng>(&self, passphrase: &[u8], kdf: Kdf, rng: &mut R) -> Vec<u8> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let out = self.to_key_file(passphrase, kdf, randomness);
+		zeroize(&mut randomness);
+
+		out
+	}
+
+	/// Decrypts an encrypted key file holding an X25519 key.
+	pub fn from_key_file(file: &[u8], passphrase: &[u8]) -> Result<Self, Error> {
+		let mut bytes = open(KeyType::X25519, file, passphrase)?;
+		let key = StaticSecret::from_bytes(bytes);
+		zeroize(&mut bytes);
+
+		Ok(key)
+	}
+}
+
+#[cfg(test)]
+const TEST_ARGON2ID: Kdf = Kdf::Argon2id {memory_kib: 64, passes: 1, lanes: 2};
+
+#[cfg(test)]
+const TEST_SCRYPT: Kdf = Kdf::Scrypt {log_n: 4, r: 8, p: 1};
+
+#[test]
+fn test_round_trip() {
+	let signing_key = SigningKey::from_bytes([0x42; 32]);
+	let static_secret = StaticSecret::from_bytes([0x43; 32]);
+
+	for kdf in [TEST_ARGON2ID, TEST_SCRYPT] {
+		let file = signing_key.to_key_file(b"hunter2", kdf, [0x24; 32]);
+		assert_eq!(key_type(&file), Ok(KeyType::Ed25519));
+
+		let loaded = SigningKey::from_key_file(&file, b"hunter2").unwrap();
+		assert_eq!(loaded.to_bytes(), signing_key.to_bytes());
+
+		let file = static_secret.to_key_file(b"hunter2", kdf, [0x24; 32]);
+		assert_eq!(key_type(&file), Ok(KeyType::X25519));
+
+		let loaded = StaticSecret::from_key_file(&file, b"hunter2").unwrap();
+		assert_eq!(loaded.to_bytes(), static_secret.to_bytes());
+	}
+}
+
+#[test]
+fn test_errors() {
+	let signing_key = SigningKey::from_bytes([0x42; 32]);
+	let file = signing_key.to_key_file(b"hunter2", TEST_ARGON2ID, [0x24; 32]);
+
+	assert_eq!(SigningKey::from_key_file(&file, b"hunter3").unwrap_err(), Error::DecryptionFailed);
+	assert_eq!(StaticSecret::from_key_file(&file, b"hunter2").unwrap_err(), Error::WrongKeyType);
+	assert_eq!(SigningKey::from_key_file(&file[.. file.len() - 1], b"hunter2").unwrap_err(), Error::InvalidEncoding);
+
+	// every byte is either checked or authenticated
+	for i in 0 .. file.len() {
+		let mut tampered = file.clone();
+		tampered[i] ^= 1;
+		assert!(SigningKey::from_key_file(&tampered, b"hunter2").is_err());
+	}
+
+	let mut newer = file.clone();
+	newer[8] = 2;
+	assert_eq!(SigningKey::from_key_file(&newer, b"hunter2").unwrap_err(), Error::UnsupportedVersion);
+
+	// a file asking for 4 GiB of memory is rejected before running the kdf
+	let mut expensive = file.clone();
+	expensive[11 .. 15].copy_from_slice(&(1u32 << 22).to_be_bytes());
+	assert_eq!(SigningKey::from_key_file(&expensive, b"hunter2").unwrap_err(), Error::WorkFactorTooHigh);
+}
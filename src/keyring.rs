@@ -0,0 +1,435 @@
+//! Keyrings of several keys, each with a short ID, for rotating keys without
+//! downtime. Messages are encrypted with the active key and start with its
+//! ID, so they can still be decrypted with whichever key made them after a
+//! newer key becomes active.
+//!
+//! A rotation goes in three steps. First the new key is added to every
+//! keyring that decrypts, then it's made active where messages are
+//! encrypted, and once nothing encrypted under the old key is left, such as
+//! after re-encrypting stored data, the old key is removed.
+//!
+//! [`Keyring`] holds symmetric keys for XChaCha20-Poly1305, and
+//! [`RecipientKeyring`] holds X25519 private keys for messages encrypted with
+//! [`ecies`]. Key IDs are chosen by the caller, and are unique within a
+//! keyring.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20poly1305::{xchacha20poly1305_decrypt, xchacha20poly1305_encrypt};
+use crate::zeroize::zeroize;
+
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+use crate::ecies;
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+use crate::x25519::{PublicKey, StaticSecret};
+
+/// The number of bytes a message encrypted with a [`Keyring`] is longer than
+/// its plaintext: the key ID, the nonce and the tag.
+pub const OVERHEAD: usize = 4 + 24 + 16;
+
+/// The number of bytes a message encrypted with a [`RecipientKeyring`] is
+/// longer than its plaintext: the key ID and the overhead of ECIES.
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+pub const RECIPIENT_OVERHEAD: usize = 4 + ecies::OVERHEAD;
+
+/// An error returned when managing the keys of a keyring, or encrypting or
+/// decrypting with them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A key with the same ID is already in the keyring.
+	DuplicateKeyId,
+	/// No key in the keyring has the ID.
+	UnknownKeyId,
+	/// No key has been made active, so nothing can be encrypted.
+	NoActiveKey,
+	/// The active recipient's public key is a small-order point.
+	InvalidPublicKey,
+	/// The message is too short, was modified, or was encrypted with another
+	/// key of the same ID.
+	DecryptionFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::DuplicateKeyId => "duplicate key id in keyring",
+			Error::UnknownKeyId => "unknown key id",
+			Error::NoActiveKey => "no active key in keyring",
+			Error::InvalidPublicKey => "invalid keyring public key",
+			Error::DecryptionFailed => "failed to decrypt keyring message",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// Returns the ID of the key that a message from either kind of keyring was
+/// encrypted with, without decrypting it.
+pub fn key_id(message: &[u8]) -> Option<u32> {
+	Some(u32::from_be_bytes(message.get(0 .. 4)?.try_into().unwrap()))
+}
+
+// the keys and the choice of active key, shared by both kinds of keyring
+struct Slots<K> {
+	keys: Vec<(u32, K)>,
+	active: Option<u32>,
+}
+
+impl<K> Slots<K> {
+	fn new() -> Self {
+		Self {keys: Vec::new(), active: None}
+	}
+
+	fn add(&mut self, id: u32, key: K) -> Result<(), Error> {
+		if self.get(id).is_some() {
+			return Err(Error::DuplicateKeyId);
+		}
+
+		self.keys.push((id, key));
+		Ok(())
+	}
+
+	fn remove(&mut self, id: u32) -> Option<K> {
+		let index = self.keys.iter().position(|(key_id, _)| *key_id == id)?;
+
+		if self.active == Some(id) {
+			self.active = None;
+		}
+
+		Some(self.keys.remove(index).1)
+	}
+
+	fn set_active(&mut self, id: u32) -> Result<(), Error> {
+		self.get(id).ok_or(Error::UnknownKeyId)?;
+		self.active = Some(id);
+		Ok(())
+	}
+
+	fn get(&self, id: u32) -> Option<&K> {
+		self.keys.iter().find(|(key_id, _)| *key_id == id).map(|(_, key)| key)
+	}
+
+	fn active(&self) -> Result<(u32, &K), Error> {
+		let id = self.active.ok_or(Error::NoActiveKey)?;
+		Ok((id, self.get(id).unwrap()))
+	}
+
+	fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.keys.iter().map(|(id, _)| *id)
+	}
+}
+
+/// A keyring of 256-bit keys for XChaCha20-Poly1305.
+pub struct Keyring {
+	slots: Slots<[u8; 32]>,
+}
+
+impl Keyring {
+	/// Creates an empty keyring.
+	pub fn new() -> Self {
+		Self {slots: Slots::new()}
+	}
+
+	/// Adds `key` with the ID `id`, which must not already be used.
+	pub fn add(&mut self, id: u32, key: [u8; 32]) -> Result<(), Error> {
+		self.slots.add(id, key)
+	}
+
+	/// Removes the key with the ID `id`, returning whether there was one.
+	/// Removing the active key leaves no key active.
+	pub fn remove(&mut self, id: u32) -> bool {
+		match self.slots.remove(id) {
+			Some(mut key) => {
+				zeroize(&mut key);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Makes the key with the ID `id` the one that new messages are encrypted
+	/// with.
+	pub fn set_active(&mut self, id: u32) -> Result<(), Error> {
+		self.slots.set_active(id)
+	}
+
+	/// Returns the ID of the active key, if there is one.
+	pub fn active(&self) -> Option<u32> {
+		self.slots.active
+	}
+
+	/// Returns the IDs of all the keys, in the order they were added.
+	pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.slots.ids()
+	}
+
+	/// Encrypts `plaintext` with the active key, authenticating it along with
+	/// `associated_data`. The `randomness` is used as the nonce, so it must be
+	/// freshly generated by a secure random source for every message.
+	pub fn encrypt(&self, plaintext: &[u8], associated_data: &[u8], randomness: [u8; 24]) -> Result<Vec<u8>, Error> {
+		let (id, key) = self.slots.active()?;
+
+		let mut out = Vec::with_capacity(plaintext.len() + OVERHEAD);
+		out.extend_from_slice(&id.to_be_bytes());
+		out.extend_from_slice(&randomness);
+		out.extend_from_slice(plaintext);
+
+		let tag = xchacha20poly1305_encrypt(*key, randomness, &bound_data(id, associated_data), &mut out[28 ..]);
+		out.extend_from_slice(&tag);
+
+		Ok(out)
+	}
+
+	/// Encrypts `plaintext` like [`encrypt`](Self::encrypt), with a nonce
+	/// generated by `rng`, which must be a cryptographically secure random
+	/// number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+		&self,
+		plaintext: &[u8],
+		associated_data: &[u8],
+		rng: &mut R,
+	) -> Result<Vec<u8>, Error> {
+		let mut randomness = [0; 24];
+		rng.fill_bytes(&mut randomness);
+
+		self.encrypt(plaintext, associated_data, randomness)
+	}
+
+	/// Decrypts a message encrypted with any key in the keyring, picked by the
+	/// ID at its start.
+	pub fn decrypt(&self, message: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, Error> {
+		if message.len() < OVERHEAD {
+			return Err(Error::DecryptionFailed);
+		}
+
+		let id = key_id(message).unwrap();
+		let key = self.slots.get(id).ok_or(Error::UnknownKeyId)?;
+
+		let nonce = message[4 .. 28].try_into().unwrap();
+		let tag = message[message.len() - 16 ..].try_into().unwrap();
+		let mut out = message[28 .. message.len() - 16].to_vec();
+
+		xchacha20poly1305_decrypt(*key, nonce, &bound_data(id, associated_data), &mut out, tag)
+			.map_err(|_| Error::DecryptionFailed)?;
+
+		Ok(out)
+	}
+}
+
+// the key ID is authenticated along with the associated data, so that it
+// can't be changed to that of another key
+fn bound_data(id: u32, associated_data: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(4 + associated_data.len());
+	out.extend_from_slice(&id.to_be_bytes());
+	out.extend_from_slice(associated_data);
+	out
+}
+
+impl Default for Keyring {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for Keyring {
+	fn drop(&mut self) {
+		for (_, key) in self.slots.keys.iter_mut() {
+			zeroize(key);
+		}
+	}
+}
+
+impl fmt::Debug for Keyring {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Keyring")
+			.field("ids", &self.slots.ids().collect::<Vec<_>>())
+			.field("active", &self.slots.active)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A keyring of X25519 private keys, for messages encrypted to the public key
+/// of the active one with [`ecies`].
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+pub struct RecipientKeyring {
+	slots: Slots<StaticSecret>,
+}
+
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+impl RecipientKeyring {
+	/// Creates an empty keyring.
+	pub fn new() -> Self {
+		Self {slots: Slots::new()}
+	}
+
+	/// Adds `secret` with the ID `id`, which must not already be used.
+	pub fn add(&mut self, id: u32, secret: StaticSecret) -> Result<(), Error> {
+		self.slots.add(id, secret)
+	}
+
+	/// Removes the key with the ID `id`, returning whether there was one.
+	/// Removing the active key leaves no key active.
+	pub fn remove(&mut self, id: u32) -> bool {
+		self.slots.remove(id).is_some()
+	}
+
+	/// Makes the key with the ID `id` the one that new messages are encrypted
+	/// to.
+	pub fn set_active(&mut self, id: u32) -> Result<(), Error> {
+		self.slots.set_active(id)
+	}
+
+	/// Returns the ID of the active key, if there is one.
+	pub fn active(&self) -> Option<u32> {
+		self.slots.active
+	}
+
+	/// Returns the IDs of all the keys, in the order they were added.
+	pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.slots.ids()
+	}
+
+	/// Returns the public key of the key with the ID `id`, for senders that
+	/// encrypt without a keyring of their own.
+	pub fn public_key(&self, id: u32) -> Option<PublicKey> {
+		self.slots.get(id).map(PublicKey::from)
+	}
+
+	/// Encrypts `plaintext` to the public key of the active key. The
+	/// `randomness` is used as the ephemeral private key, so it must be
+	/// freshly generated by a secure random source for every message.
+	pub fn encrypt(&self, plaintext: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+		let (id, secret) = self.slots.active()?;
+
+		let message = ecies::encrypt_to(&PublicKey::from(secret), plaintext, randomness)
+			.map_err(|_| Error::InvalidPublicKey)?;
+
+		let mut out = Vec::with_capacity(4 + message.len());
+		out.extend_from_slice(&id.to_be_bytes());
+		out.extend_from_slice(&message);
+
+		Ok(out)
+	}
+
+	/// Encrypts `plaintext` like [`encrypt`](Self::encrypt), with an
+	/// ephemeral private key generated by `rng`, which must be a
+	/// cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn encrypt_with_rng<R: RngCore + CryptoRng>(&self, plaintext: &[u8], rng: &mut R) -> Result<Vec<u8>, Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = self.encrypt(plaintext, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Decrypts a message encrypted to any key in the keyring, picked by the
+	/// ID at its start.
+	pub fn decrypt(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+		let id = key_id(message).ok_or(Error::DecryptionFailed)?;
+		let secret = self.slots.get(id).ok_or(Error::UnknownKeyId)?;
+
+		ecies::decrypt(secret, &message[4 ..]).map_err(|_| Error::DecryptionFailed)
+	}
+}
+
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+impl Default for RecipientKeyring {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+impl fmt::Debug for RecipientKeyring {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("RecipientKeyring")
+			.field("ids", &self.slots.ids().collect::<Vec<_>>())
+			.field("active", &self.slots.active)
+			.finish_non_exhaustive()
+	}
+}
+
+#[test]
+fn test_keyring_rotation() {
+	let mut keyring = Keyring::new();
+	assert_eq!(keyring.encrypt(b"hello", b"", [0; 24]), Err(Error::NoActiveKey));
+
+	keyring.add(1, [1; 32]).unwrap();
+	assert_eq!(keyring.add(1, [2; 32]), Err(Error::DuplicateKeyId));
+	assert_eq!(keyring.set_active(2), Err(Error::UnknownKeyId));
+	keyring.set_active(1).unwrap();
+
+	let old = keyring.encrypt(b"hello", b"context", [3; 24]).unwrap();
+	assert_eq!(old.len(), 5 + OVERHEAD);
+	assert_eq!(key_id(&old), Some(1));
+
+	// the new key takes over encryption, while the old one still decrypts
+	keyring.add(2, [2; 32]).unwrap();
+	keyring.set_active(2).unwrap();
+
+	let new = keyring.encrypt(b"world", b"context", [4; 24]).unwrap();
+	assert_eq!(key_id(&new), Some(2));
+	assert_eq!(keyring.decrypt(&old, b"context").unwrap(), b"hello");
+	assert_eq!(keyring.decrypt(&new, b"context").unwrap(), b"world");
+	assert_eq!(keyring.decrypt(&new, b"other").unwrap_err(), Error::DecryptionFailed);
+
+	// changing the key ID is detected even when the other key is present
+	let mut relabeled = old.clone();
+	relabeled[3] = 2;
+	assert_eq!(keyring.decrypt(&relabeled, b"context").unwrap_err(), Error::DecryptionFailed);
+
+	assert!(keyring.remove(1));
+	assert!(!keyring.remove(1));
+	assert_eq!(keyring.ids().collect::<Vec<_>>(), [2]);
+	assert_eq!(keyring.decrypt(&old, b"context").unwrap_err(), Error::UnknownKeyId);
+	assert_eq!(keyring.decrypt(&old[.. OVERHEAD - 1], b"context").unwrap_err(), Error::DecryptionFailed);
+
+	// removing the active key stops encryption
+	assert!(keyring.remove(2));
+	assert_eq!(keyring.active(), None);
+	assert_eq!(keyring.encrypt(b"hello", b"", [0; 24]), Err(Error::NoActiveKey));
+}
+
+#[cfg(all(feature = "hkdf", feature = "x25519"))]
+#[test]
+fn test_recipient_keyring() {
+	let mut keyring = RecipientKeyring::new();
+	keyring.add(7, StaticSecret::from_bytes([7; 32])).unwrap();
+	keyring.set_active(7).unwrap();
+
+	let old = keyring.encrypt(b"hello", [1; 32]).unwrap();
+	assert_eq!(old.len(), 5 + RECIPIENT_OVERHEAD);
+
+	keyring.add(8, StaticSecret::from_bytes([8; 32])).unwrap();
+	keyring.set_active(8).unwrap();
+
+	let new = keyring.encrypt(b"world", [2; 32]).unwrap();
+	assert_eq!(key_id(&new), Some(8));
+	assert_eq!(keyring.decrypt(&old).unwrap(), b"hello");
+	assert_eq!(keyring.decrypt(&new).unwrap(), b"world");
+
+	// senders without the keyring can encrypt to the public key directly
+	let public_key = keyring.public_key(8).unwrap();
+	let mut direct = 8u32.to_be_bytes().to_vec();
+	direct.extend_from_slice(&ecies::encrypt_to(&public_key, b"direct", [3; 32]).unwrap());
+	assert_eq!(keyring.decrypt(&direct).unwrap(), b"direct");
+
+	let mut relabeled = old.clone();
+	relabeled[3] = 8;
+	assert_eq!(keyring.decrypt(&relabeled).unwrap_err(), Error::DecryptionFailed);
+
+	keyring.remove(7);
+	assert_eq!(keyring.decrypt(&old).unwrap_err(), Error::UnknownKeyId);
+}
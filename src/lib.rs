@@ -1,24 +1,312 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+// index-based loops and explicit length checks are used deliberately throughout
+// the crate, since they map more directly onto the specifications being implemented
+#![allow(
+	clippy::assign_op_pattern,
+	clippy::len_zero,
+	clippy::needless_range_loop,
+	clippy::needless_return,
+)]
+
 #![doc(html_logo_url = "https://raw.githubusercontent.com/ast-ral/libkrypton/master/logo.svg")]
 
 // to prevent broken links when building documentation in #![no_std] mode
 #[cfg(all(not(feature = "std"), doc))]
 extern crate std;
 
+// declared first so that their macros are visible in every module after them
+#[cfg(any(
+	feature = "ed25519",
+	feature = "ed448",
+	feature = "ml-dsa",
+	feature = "ml-kem",
+	feature = "x25519",
+	feature = "x448",
+))]
+#[macro_use]
+mod text;
+#[cfg(all(
+	feature = "serde",
+	any(
+		feature = "ed25519",
+		feature = "ed448",
+		feature = "ml-dsa",
+		feature = "ml-kem",
+		feature = "x25519",
+		feature = "x448",
+	),
+))]
+#[macro_use]
+mod serde_support;
+
+#[cfg(all(feature = "aes", feature = "chacha20", feature = "poly1305"))]
+pub mod adiantum;
+#[cfg(feature = "aes")]
+pub mod aes_xts;
+#[cfg(all(feature = "std", feature = "chacha20poly1305", feature = "hkdf", feature = "x25519"))]
+pub mod age;
+#[cfg(all(
+	feature = "std",
+	feature = "blake3",
+	feature = "chacha20poly1305",
+	feature = "ed25519",
+	feature = "ed448",
+	feature = "hmac",
+	feature = "ml-dsa",
+	feature = "ml-kem",
+	feature = "sha2",
+	feature = "sha3",
+	feature = "x25519",
+))]
+pub mod agility;
+#[cfg(any(feature = "tokio", feature = "futures"))]
+pub mod async_io;
+#[cfg(feature = "blake2")]
+pub mod blake2;
+#[cfg(feature = "blake3")]
+pub mod blake3;
+#[cfg(feature = "chacha20")]
 pub mod chacha20;
+#[cfg(feature = "chacha20poly1305")]
+pub mod chacha20poly1305;
+#[cfg(feature = "hmac")]
+pub mod chain_key;
+pub mod choice;
+#[cfg(all(feature = "std", feature = "chacha20poly1305"))]
+pub mod container;
+#[cfg(feature = "aes")]
+pub mod ctr_drbg;
+pub mod der;
+#[cfg(all(feature = "std", feature = "ed25519", feature = "sha2"))]
+pub mod dkim;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod dnssec;
+#[cfg(all(feature = "std", feature = "chacha20poly1305", feature = "hkdf", feature = "x25519"))]
+pub mod ecies;
+pub mod encoding;
+pub mod entropy_health;
+#[cfg(feature = "ffdhe")]
+pub mod ffdhe;
+#[cfg(all(feature = "std", feature = "aes", feature = "chacha20", feature = "ed25519", feature = "poly1305"))]
+pub mod fingerprint;
+#[cfg(all(feature = "aes", feature = "sha2"))]
+pub mod fortuna;
+#[cfg(feature = "hkdf")]
+pub mod hkdf;
+#[cfg(feature = "hmac")]
+pub mod hmac;
+#[cfg(all(feature = "ed25519", feature = "ml-dsa"))]
+pub mod hybrid_signature;
+#[cfg(all(feature = "aes", feature = "sha2"))]
+pub mod jitter;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod jws;
+#[cfg(all(
+	feature = "std",
+	feature = "blake2",
+	feature = "chacha20poly1305",
+	feature = "ed25519",
+	feature = "hmac",
+	feature = "x25519",
+))]
+pub mod key_file;
+#[cfg(all(feature = "std", feature = "chacha20poly1305"))]
+pub mod keyring;
+#[cfg(any(all(feature = "hmac", feature = "x25519"), feature = "ml-kem"))]
+pub mod kem;
+#[cfg(all(feature = "std", feature = "blake2", feature = "chacha20poly1305", feature = "hmac", feature = "x25519"))]
+pub mod noise;
+pub mod nonce;
+#[cfg(all(feature = "std", feature = "blake2", feature = "ed25519"))]
+pub mod minisign;
+#[cfg(feature = "ml-dsa")]
+pub mod ml_dsa;
+#[cfg(feature = "std")]
+pub mod multibase;
+#[cfg(all(
+	feature = "std",
+	feature = "blake2",
+	feature = "blake3",
+	feature = "sha2",
+	feature = "sha3",
+	feature = "sm3",
+))]
+pub mod multihash;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod openpgp;
+#[cfg(feature = "hmac")]
+pub mod otp;
+#[cfg(all(feature = "sha2", feature = "sha3"))]
+pub mod ots;
+#[cfg(feature = "chacha20poly1305")]
+pub mod padme;
+#[cfg(any(feature = "blake3", feature = "sha2", feature = "sha3"))]
+pub mod par_hash;
+#[cfg(feature = "std")]
+pub mod pem;
+#[cfg(feature = "poly1305")]
 pub mod poly1305;
+#[cfg(all(feature = "blake3", feature = "sha2"))]
+pub mod pow;
+#[cfg(feature = "getrandom")]
+pub mod rand;
+#[cfg(all(feature = "std", feature = "chacha20poly1305", feature = "hkdf", feature = "x25519"))]
+pub mod ratchet;
+#[cfg(feature = "rsa")]
+pub mod rsa;
+#[cfg(feature = "hkdf")]
+pub mod sas;
+#[cfg(feature = "hmac")]
+pub mod sealed_log;
+#[cfg(feature = "std")]
+pub mod secret_box;
+pub mod self_test;
+#[cfg(feature = "sha2")]
 pub mod sha2;
+#[cfg(all(feature = "std", feature = "chacha20poly1305", feature = "ed25519", feature = "hkdf", feature = "x25519"))]
+pub mod signcryption;
+#[cfg(all(feature = "std", feature = "slh-dsa"))]
+pub mod slh_dsa;
+#[cfg(feature = "sm3")]
+pub mod sm3;
+#[cfg(feature = "sm4")]
+pub mod sm4;
+#[cfg(all(feature = "std", feature = "sha2", feature = "x25519"))]
+pub mod ssh_kex;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+pub mod webauthn;
+#[cfg(feature = "ed25519")]
+pub mod x509;
+
+pub use error::Error;
 
+pub use self_test::self_test;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::blind_signature;
+
+#[cfg(all(feature = "std", feature = "ed25519"))]
+#[doc(inline)]
+pub use curve25519::dkg;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::ecvrf;
+
+#[cfg(feature = "ed25519")]
 #[doc(inline)]
 pub use curve25519::ed25519;
 
+#[cfg(feature = "ed448")]
+#[doc(inline)]
+pub use curve448::ed448;
+
+#[cfg(all(feature = "ed25519", feature = "x25519"))]
+#[doc(inline)]
+pub use curve25519::elligator2;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::musig2;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::oprf;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::point;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::poseidon;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::ristretto;
+
+#[cfg(feature = "ed25519")]
+#[doc(inline)]
+pub use curve25519::scalar;
+
+#[cfg(all(feature = "std", feature = "ed25519"))]
+#[doc(inline)]
+pub use curve25519::ring_signature;
+
+#[cfg(all(feature = "ed25519", feature = "hkdf"))]
+#[doc(inline)]
+pub use curve25519::spake2;
+
+#[cfg(feature = "ed448")]
+#[doc(inline)]
+pub use curve448::vdf;
+
+#[cfg(feature = "x25519")]
 #[doc(inline)]
 pub use curve25519::x25519;
 
+#[cfg(feature = "x448")]
+#[doc(inline)]
+pub use curve448::x448;
+
+#[cfg(feature = "sha3")]
 #[doc(inline)]
 pub use keccak::sha3;
 
+#[cfg(feature = "aes")]
+mod aes;
+#[cfg(all(
+	feature = "std",
+	feature = "blake2",
+	feature = "chacha20poly1305",
+	feature = "ed25519",
+	feature = "hmac",
+	feature = "x25519",
+))]
+mod argon2;
+// shared by the text formats, which each use only some of it
+#[cfg(all(
+	feature = "std",
+	any(feature = "ed25519", all(feature = "chacha20poly1305", feature = "hkdf", feature = "x25519")),
+))]
+#[cfg_attr(not(feature = "full"), allow(dead_code))]
+mod base64;
+#[cfg(all(feature = "std", feature = "aes", feature = "chacha20", feature = "ed25519", feature = "poly1305"))]
+mod bcrypt_pbkdf;
+#[cfg(all(feature = "std", feature = "chacha20poly1305", feature = "hkdf", feature = "x25519"))]
+mod bech32;
+// shared by both curves, which each use only some of it
+#[cfg(any(feature = "ed25519", feature = "ed448"))]
+#[cfg_attr(not(all(feature = "ed25519", feature = "ed448")), allow(dead_code))]
+mod barrett;
+#[cfg(any(feature = "ffdhe", feature = "rsa"))]
+mod bignum;
+#[cfg(any(feature = "ed25519", feature = "x25519"))]
 mod curve25519;
+#[cfg(any(feature = "ed448", feature = "x448"))]
+mod curve448;
+mod error;
+#[cfg(all(feature = "std", feature = "ed25519"))]
+mod json;
+#[cfg(feature = "sha3")]
 mod keccak;
+#[cfg(all(
+	feature = "std",
+	feature = "chacha20poly1305",
+	feature = "x25519",
+	any(feature = "hkdf", all(feature = "blake2", feature = "ed25519", feature = "hmac")),
+))]
+mod scrypt;
+#[cfg(any(feature = "ed25519", feature = "poly1305", feature = "x25519"))]
 mod segmented_int;
+#[cfg(any(feature = "hmac", all(feature = "std", feature = "ed25519")))]
+mod sha1;
+// each helper is used only by the tests of some primitives
+#[cfg(test)]
+#[allow(dead_code)]
+mod test_util;
+// unused when only primitives without secrets, such as hash functions, are built
+#[cfg_attr(not(feature = "full"), allow(dead_code))]
+mod zeroize;
@@ -0,0 +1,489 @@
+//! Keys and signatures compatible with [minisign](https://jedisct1.github.io/minisign/)
+//! and rsign2.
+//!
+//! Signatures created here always use the prehashed `ED` algorithm, where the
+//! BLAKE2b-512 digest of the file is signed rather than the file itself.
+//! Verification also accepts the legacy `Ed` algorithm.
+//!
+//! Secret keys can only be read if they were stored unencrypted (as with
+//! `minisign -G -W`), since encrypted secret keys use scrypt.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::string::String;
+use std::vec::Vec;
+
+use crate::base64;
+use crate::blake2::blake2b;
+use crate::ed25519::{self, SigningKey, VerifyingKey};
+
+const ALG_ED25519: [u8; 2] = *b"Ed";
+const ALG_ED25519_PREHASHED: [u8; 2] = *b"ED";
+const KDF_NONE: [u8; 2] = [0, 0];
+const CHECKSUM_BLAKE2B: [u8; 2] = *b"B2";
+
+const UNTRUSTED_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_PREFIX: &str = "trusted comment: ";
+
+/// An error returned when parsing or verifying minisign keys and signatures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The text passed in is not a valid minisign key or signature.
+	InvalidEncoding,
+	/// The key or signature uses an algorithm that isn't supported.
+	UnsupportedAlgorithm,
+	/// The secret key is encrypted, which isn't supported.
+	EncryptedKey,
+	/// The checksum of a secret key didn't match its contents.
+	ChecksumMismatch,
+	/// The signature was created by a different key than the one given.
+	KeyIdMismatch,
+	/// The signature or the trusted comment failed verification.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid minisign encoding",
+			Error::UnsupportedAlgorithm => "unsupported minisign algorithm",
+			Error::EncryptedKey => "encrypted minisign secret keys are not supported",
+			Error::ChecksumMismatch => "minisign secret key checksum mismatch",
+			Error::KeyIdMismatch => "signature was created by a different key",
+			Error::InvalidSignature => "invalid minisign signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<ed25519::Error> for Error {
+	fn from(_: ed25519::Error) -> Self {
+		Error::InvalidEncoding
+	}
+}
+
+/// A minisign public key: an Ed25519 public key along with an 8-byte key id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+	key_id: [u8; 8],
+	key: VerifyingKey,
+}
+
+impl PublicKey {
+	/// Creates a public key from an Ed25519 public key and a key id.
+	pub fn new(key: VerifyingKey, key_id: [u8; 8]) -> Self {
+		Self {key_id, key}
+	}
+
+	/// Parses a public key from its base64 encoding, as passed to
+	/// `minisign -P`.
+	pub fn from_base64(text: &str) -> Result<Self, Error> {
+		let bytes = base64::decode(text).ok_or(Error::InvalidEncoding)?;
+
+		if bytes.len() != 42 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		if bytes[0 .. 2] != ALG_ED25519 {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		let key_id = bytes[2 .. 10].try_into().unwrap();
+		let key = VerifyingKey::from_bytes(bytes[10 .. 42].try_into().unwrap())?;
+
+		Ok(Self {key_id, key})
+	}
+
+	/// Parses the contents of a public key file, such as `minisign.pub`.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		let mut lines = text.lines();
+		let (_, encoded) = read_commented_line(&mut lines, UNTRUSTED_PREFIX)?;
+		expect_end(lines)?;
+
+		Self::from_base64(encoded)
+	}
+
+	/// Encodes this public key as base64, without a comment.
+	pub fn to_base64(&self) -> String {
+		let mut bytes = Vec::with_capacity(42);
+		bytes.extend_from_slice(&ALG_ED25519);
+		bytes.extend_from_slice(&self.key_id);
+		bytes.extend_from_slice(self.key.as_bytes());
+
+		let mut out = String::new();
+		base64::encode(&bytes, &mut out);
+		out
+	}
+
+	/// Encodes this public key as the contents of a public key file, in the
+	/// same way `minisign -G` does.
+	pub fn to_text(&self) -> String {
+		format!(
+			"{}minisign public key {}\n{}\n",
+			UNTRUSTED_PREFIX,
+			format_key_id(&self.key_id),
+			self.to_base64(),
+		)
+	}
+
+	/// Returns the key id of this key.
+	pub fn key_id(&self) -> [u8; 8] {
+		self.key_id
+	}
+
+	/// Returns the underlying Ed25519 public key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.key
+	}
+
+	/// Verifies that `signature` is a valid signature of `message` by this key,
+	/// including the signature over its trusted comment. The trusted comment
+	/// should only be relied on if this returns `Ok`.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), Error> {
+		if signature.key_id != self.key_id {
+			return Err(Error::KeyIdMismatch);
+		}
+
+		let valid = if signature.prehashed {
+			self.key.verify(&blake2b::<64>(message), &signature.signature)
+		} else {
+			self.key.verify(message, &signature.signature)
+		};
+
+		if !valid {
+			return Err(Error::InvalidSignature);
+		}
+
+		let global_message = global_message(&signature.signature, &signature.trusted_comment);
+
+		if !self.key.verify(&global_message, &signature.global_signature) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(())
+	}
+}
+
+/// A minisign secret key: an Ed25519 private key along with an 8-byte key id.
+#[derive(Clone, Debug)]
+pub struct SecretKey {
+	key_id: [u8; 8],
+	key: SigningKey,
+}
+
+impl SecretKey {
+	/// Creates a secret key from an Ed25519 private key and a key id. The key
+	/// id should be generated randomly, just like the private key.
+	pub fn new(key: SigningKey, key_id: [u8; 8]) -> Self {
+		Self {key_id, key}
+	}
+
+	/// Parses the contents of an unencrypted secret key file. Returns
+	/// [`Error::EncryptedKey`] if the key is encrypted with a password.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		let mut lines = text.lines();
+		let (_, encoded) = read_commented_line(&mut lines, UNTRUSTED_PREFIX)?;
+		expect_end(lines)?;
+
+		let bytes = base64::decode(encoded).ok_or(Error::InvalidEncoding)?;
+
+		if bytes.len() != 158 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		if bytes[0 .. 2] != ALG_ED25519 || bytes[4 .. 6] != CHECKSUM_BLAKE2B {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		if bytes[2 .. 4] != KDF_NONE {
+			return Err(Error::EncryptedKey);
+		}
+
+		// skip the kdf salt and limits, which are unused for unencrypted keys
+		let key_id: [u8; 8] = bytes[54 .. 62].try_into().unwrap();
+		let key_bytes = &bytes[62 .. 126];
+		let checksum = &bytes[126 .. 158];
+
+		if checksum != secret_key_checksum(&key_id, key_bytes) {
+			return Err(Error::ChecksumMismatch);
+		}
+
+		let key = SigningKey::from_bytes(key_bytes[0 .. 32].try_into().unwrap());
+
+		if key.verifying_key().as_bytes()[..] != key_bytes[32 .. 64] {
+			return Err(Error::InvalidEncoding);
+		}
+
+		Ok(Self {key_id, key})
+	}
+
+	/// Encodes this secret key as the contents of an unencrypted secret key
+	/// file, in the same way `minisign -G -W` does.
+	pub fn to_text(&self) -> String {
+		let mut key_bytes = [0; 64];
+		key_bytes[0 .. 32].copy_from_slice(&self.key.to_bytes());
+		key_bytes[32 .. 64].copy_from_slice(self.key.verifying_key().as_bytes());
+
+		let mut bytes = Vec::with_capacity(158);
+		bytes.extend_from_slice(&ALG_ED25519);
+		bytes.extend_from_slice(&KDF_NONE);
+		bytes.extend_from_slice(&CHECKSUM_BLAKE2B);
+		bytes.extend_from_slice(&[0; 48]);
+		bytes.extend_from_slice(&self.key_id);
+		bytes.extend_from_slice(&key_bytes);
+		bytes.extend_from_slice(&secret_key_checksum(&self.key_id, &key_bytes));
+
+		let mut out = String::new();
+		out.push_str(UNTRUSTED_PREFIX);
+		out.push_str("minisign encrypted secret key\n");
+		base64::encode(&bytes, &mut out);
+		out.push('\n');
+		out
+	}
+
+	/// Returns the key id of this key.
+	pub fn key_id(&self) -> [u8; 8] {
+		self.key_id
+	}
+
+	/// Returns the public key corresponding to this secret key.
+	pub fn public_key(&self) -> PublicKey {
+		PublicKey {key_id: self.key_id, key: self.key.verifying_key()}
+	}
+
+	/// Signs `message` using the prehashed algorithm, attaching the given
+	/// trusted comment. minisign puts a timestamp and the file name in the
+	/// trusted comment, such as `timestamp:1700000000\tfile:release.tar.gz`.
+	/// Panics if the trusted comment contains a newline.
+	pub fn sign(&self, message: &[u8], trusted_comment: &str) -> Signature {
+		assert!(!trusted_comment.contains(['\r', '\n']));
+
+		let signature = self.key.sign(&blake2b::<64>(message));
+		let global_signature = self.key.sign(&global_message(&signature, trusted_comment));
+
+		Signature {
+			prehashed: true,
+			key_id: self.key_id,
+			signature,
+			untrusted_comment: String::from("signature from minisign secret key"),
+			trusted_comment: String::from(trusted_comment),
+			global_signature,
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SecretKey {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.key_id);
+		::zeroize::Zeroize::zeroize(&mut self.key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SecretKey {}
+
+/// A minisign signature, as stored in a `.minisig` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	prehashed: bool,
+	key_id: [u8; 8],
+	signature: ed25519::Signature,
+	untrusted_comment: String,
+	trusted_comment: String,
+	global_signature: ed25519::Signature,
+}
+
+impl Signature {
+	/// Parses the contents of a signature file. This doesn't verify the
+	/// signature; use [`PublicKey::verify`] for that.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		let mut lines = text.lines();
+		let (untrusted_comment, encoded) = read_commented_line(&mut lines, UNTRUSTED_PREFIX)?;
+		let (trusted_comment, encoded_global) = read_commented_line(&mut lines, TRUSTED_PREFIX)?;
+		expect_end(lines)?;
+
+		let bytes = base64::decode(encoded).ok_or(Error::InvalidEncoding)?;
+
+		if bytes.len() != 74 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let prehashed = match bytes[0 .. 2].try_into().unwrap() {
+			ALG_ED25519 => false,
+			ALG_ED25519_PREHASHED => true,
+			_ => return Err(Error::UnsupportedAlgorithm),
+		};
+
+		let global_bytes = base64::decode(encoded_global).ok_or(Error::InvalidEncoding)?;
+
+		Ok(Self {
+			prehashed,
+			key_id: bytes[2 .. 10].try_into().unwrap(),
+			signature: ed25519::Signature::try_from(&bytes[10 .. 74])?,
+			untrusted_comment: String::from(untrusted_comment),
+			trusted_comment: String::from(trusted_comment),
+			global_signature: ed25519::Signature::try_from(&global_bytes[..])?,
+		})
+	}
+
+	/// Encodes this signature as the contents of a signature file.
+	pub fn to_text(&self) -> String {
+		let algorithm = if self.prehashed {ALG_ED25519_PREHASHED} else {ALG_ED25519};
+
+		let mut bytes = Vec::with_capacity(74);
+		bytes.extend_from_slice(&algorithm);
+		bytes.extend_from_slice(&self.key_id);
+		bytes.extend_from_slice(self.signature.as_bytes());
+
+		let mut out = String::new();
+		out.push_str(UNTRUSTED_PREFIX);
+		out.push_str(&self.untrusted_comment);
+		out.push('\n');
+		base64::encode(&bytes, &mut out);
+		out.push('\n');
+		out.push_str(TRUSTED_PREFIX);
+		out.push_str(&self.trusted_comment);
+		out.push('\n');
+		base64::encode(self.global_signature.as_bytes(), &mut out);
+		out.push('\n');
+		out
+	}
+
+	/// Returns the id of the key that created this signature.
+	pub fn key_id(&self) -> [u8; 8] {
+		self.key_id
+	}
+
+	/// Returns whether this signature uses the prehashed algorithm.
+	pub fn is_prehashed(&self) -> bool {
+		self.prehashed
+	}
+
+	/// Returns the trusted comment. This is only authenticated once the
+	/// signature has been verified.
+	pub fn trusted_comment(&self) -> &str {
+		&self.trusted_comment
+	}
+
+	/// Returns the untrusted comment, which isn't covered by the signature.
+	pub fn untrusted_comment(&self) -> &str {
+		&self.untrusted_comment
+	}
+
+	/// Replaces the untrusted comment. Panics if the comment contains a
+	/// newline.
+	pub fn set_untrusted_comment(&mut self, comment: &str) {
+		assert!(!comment.contains(['\r', '\n']));
+		self.untrusted_comment = String::from(comment);
+	}
+}
+
+// minisign displays key ids as a little-endian 64-bit number in hex
+fn format_key_id(key_id: &[u8; 8]) -> String {
+	format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+fn secret_key_checksum(key_id: &[u8; 8], key_bytes: &[u8]) -> [u8; 32] {
+	let mut checksum_input = Vec::with_capacity(74);
+	checksum_input.extend_from_slice(&ALG_ED25519);
+	checksum_input.extend_from_slice(key_id);
+	checksum_input.extend_from_slice(key_bytes);
+
+	blake2b::<32>(&checksum_input)
+}
+
+fn global_message(signature: &ed25519::Signature, trusted_comment: &str) -> Vec<u8> {
+	let mut out = Vec::with_capacity(64 + trusted_comment.len());
+	out.extend_from_slice(signature.as_bytes());
+	out.extend_from_slice(trusted_comment.as_bytes());
+	out
+}
+
+// reads a comment line starting with `prefix`, followed by a line of base64
+fn read_commented_line<'a>(
+	lines: &mut impl Iterator<Item = &'a str>,
+	prefix: &str,
+) -> Result<(&'a str, &'a str), Error> {
+	let comment = lines.next()
+		.and_then(|line| line.strip_prefix(prefix))
+		.ok_or(Error::InvalidEncoding)?;
+
+	let encoded = lines.next().ok_or(Error::InvalidEncoding)?;
+
+	Ok((comment, encoded.trim()))
+}
+
+fn expect_end<'a>(mut lines: impl Iterator<Item = &'a str>) -> Result<(), Error> {
+	if lines.all(|line| line.trim().is_empty()) {
+		Ok(())
+	} else {
+		Err(Error::InvalidEncoding)
+	}
+}
+
+#[cfg(test)]
+const TEST_PUBLIC_KEY: &str = "\
+untrusted comment: minisign public key 4D9B07E2115C8A31
+RWQxilwR4gebTQOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4
+";
+
+#[cfg(test)]
+const TEST_MESSAGE: &[u8] = b"libkrypton release artifact\n";
+
+#[test]
+fn test_key_round_trip() {
+	let secret_key = SecretKey::from_text("\
+untrusted comment: minisign encrypted secret key
+RWQAAEIyAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAMYpcEeIHm00AAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHwOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4k0y4IOR/ztyzk3Pbn91YHPcpYO2OHl2yHUMOW9ExRfQ=
+").unwrap();
+
+	let public_key = PublicKey::from_text(TEST_PUBLIC_KEY).unwrap();
+
+	assert_eq!(secret_key.public_key(), public_key);
+	assert_eq!(public_key.to_text(), TEST_PUBLIC_KEY);
+	assert_eq!(SecretKey::from_text(&secret_key.to_text()).unwrap().public_key(), public_key);
+
+	let signature = secret_key.sign(TEST_MESSAGE, "timestamp:1700000000\tfile:artifact.txt");
+	let parsed = Signature::from_text(&signature.to_text()).unwrap();
+
+	assert_eq!(parsed, signature);
+	assert_eq!(public_key.verify(TEST_MESSAGE, &parsed), Ok(()));
+}
+
+#[test]
+fn test_verify_signatures() {
+	let public_key = PublicKey::from_text(TEST_PUBLIC_KEY).unwrap();
+
+	let prehashed = Signature::from_text("\
+untrusted comment: signature from minisign secret key
+RUQxilwR4gebTYj1T7Rmb6pp8npUvZyjMLHS0aub3HeBNDaQf2e8w19UTm1hzcr3DUrPH+NIPTX4ocK+oyR/WaA5ImQ5wdFIOw4=
+trusted comment: timestamp:1700000000\tfile:artifact.txt\thashed
+3iheuNgcx2L4g9/7RrkTYC/h0JcfKZfewU/LbOob1GAgEQsws2YOVhTVVXKUL53FH5OVppndmea3jbTTFc00AQ==
+").unwrap();
+
+	let legacy = Signature::from_text("\
+untrusted comment: signature from minisign secret key
+RWQxilwR4gebTeuVDA7DSIcXSFzoh2tyWfhoQWye1qOu3p2eyAo0d8h5vB92T4OXLMZMHyI4BZiV3NGW2eIJMqLvuhGOD1a7AgM=
+trusted comment: timestamp:1700000000\tfile:artifact.txt
+4NLNs2sMoAUuAPlXADBK7jBGfAdC0QHHmR96oXpaHcRj1WEHusVJZ66lXvrscm0E9w3gx1Iecr5CfxiAO8T8Aw==
+").unwrap();
+
+	assert!(prehashed.is_prehashed());
+	assert!(!legacy.is_prehashed());
+
+	assert_eq!(public_key.verify(TEST_MESSAGE, &prehashed), Ok(()));
+	assert_eq!(public_key.verify(TEST_MESSAGE, &legacy), Ok(()));
+	assert_eq!(public_key.verify(b"tampered", &prehashed), Err(Error::InvalidSignature));
+
+	let mut tampered_comment = prehashed.clone();
+	tampered_comment.trusted_comment.push_str("\tfile:other.txt");
+	assert_eq!(public_key.verify(TEST_MESSAGE, &tampered_comment), Err(Error::InvalidSignature));
+
+	let other_key = PublicKey::new(public_key.verifying_key(), [0; 8]);
+	assert_eq!(other_key.verify(TEST_MESSAGE, &prehashed), Err(Error::KeyIdMismatch));
+}
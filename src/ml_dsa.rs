@@ -0,0 +1,1134 @@
+//! ML-DSA-65, the module-lattice-based signature scheme standardized in
+//! [FIPS 204](https://doi.org/10.6028/NIST.FIPS.204), which is believed to be
+//! secure against attackers with quantum computers. Keys and signatures use
+//! the standard encodings, so they interoperate with other implementations.
+//!
+//! Signing is deterministic by default. [`SigningKey::sign_randomized`] mixes
+//! fresh randomness into the signature as well, which FIPS 204 calls hedged
+//! signing, and which protects against fault and side-channel attacks.
+//! Signatures may be bound to a context string of up to 255 bytes, like with
+//! [`ed448`](crate::ed448).
+//!
+//! ```
+//! use libkrypton::ml_dsa::SigningKey;
+//!
+//! // the seed should be generated by a secure random source
+//! let signing_key = SigningKey::from_seed([0x42; 32]);
+//! let signature = signing_key.sign(b"message");
+//!
+//! assert!(signing_key.verifying_key().verify(b"message", &signature));
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha3::{Shake128, Shake256};
+use crate::zeroize::zeroize;
+
+const Q: u32 = 8380417;
+const N: usize = 256;
+const D: usize = 13;
+
+// the parameters of ML-DSA-65
+const K: usize = 6;
+const L: usize = 5;
+const ETA: u32 = 4;
+const TAU: usize = 49;
+const BETA: u32 = TAU as u32 * ETA;
+const GAMMA1: u32 = 1 << 19;
+const GAMMA2: u32 = (Q - 1) / 32;
+const OMEGA: usize = 55;
+const C_TILDE_BYTES: usize = 48;
+
+// the bit lengths of the packed coefficients
+const T1_BITS: usize = 10;
+const T0_BITS: usize = D;
+const ETA_BITS: usize = 4;
+const Z_BITS: usize = 20;
+const W1_BITS: usize = 4;
+
+/// The length of an encoded verifying key.
+pub const VERIFYING_KEY_LEN: usize = 32 + 32 * K * T1_BITS;
+/// The length of an encoded signing key.
+pub const SIGNING_KEY_LEN: usize = 128 + 32 * ((K + L) * ETA_BITS + K * T0_BITS);
+/// The length of an encoded signature.
+pub const SIGNATURE_LEN: usize = C_TILDE_BYTES + 32 * L * Z_BITS + OMEGA + K;
+
+// the offsets of the vectors in an encoded signing key
+const S1_OFFSET: usize = 128;
+const S2_OFFSET: usize = S1_OFFSET + 32 * L * ETA_BITS;
+const T0_OFFSET: usize = S2_OFFSET + 32 * K * ETA_BITS;
+
+// the coefficients of a polynomial, always fully reduced modulo q
+type Poly = [u32; N];
+
+/// An error returned when signing fails or a signing key can't be parsed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The bytes passed in are not a consistent ML-DSA-65 signing key.
+	InvalidPrivateKey,
+	/// The context string is longer than 255 bytes.
+	ContextTooLong,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPrivateKey => "invalid ml-dsa private key",
+			Error::ContextTooLong => "ml-dsa context string too long",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// reduces x < 2q modulo q, without branching
+fn reduce_once(x: u32) -> u32 {
+	let y = x.wrapping_sub(Q);
+	y.wrapping_add(Q & 0u32.wrapping_sub(y >> 31))
+}
+
+fn add(a: u32, b: u32) -> u32 {
+	reduce_once(a + b)
+}
+
+fn sub(a: u32, b: u32) -> u32 {
+	reduce_once(a + Q - b)
+}
+
+// multiplies modulo q using Barrett's method, with floor(2^64 / q)
+fn mul(a: u32, b: u32) -> u32 {
+	let x = a as u64 * b as u64;
+	let quotient = ((x as u128 * (u64::MAX / Q as u64) as u128) >> 64) as u64;
+	reduce_once((x - quotient * Q as u64) as u32)
+}
+
+// maps x to the representative in (-(q - 1) / 2, (q - 1) / 2]
+fn centered(x: u32) -> i32 {
+	let x = x as i32;
+	x - (Q as i32 & (((Q as i32 - 1) / 2 - x) >> 31))
+}
+
+// whether any coefficient has an absolute value of at least bound
+fn exceeds_norm(polys: &[Poly], bound: u32) -> bool {
+	let mut exceeds = 0;
+
+	for poly in polys {
+		for &x in poly {
+			exceeds |= (bound as i32 - 1 - centered(x).abs()) >> 31;
+		}
+	}
+
+	exceeds != 0
+}
+
+const fn pow1753(exponent: usize) -> u32 {
+	let mut out = 1;
+	let mut i = 0;
+
+	while i < exponent {
+		out = out * 1753 % Q as u64;
+		i += 1;
+	}
+
+	out as u32
+}
+
+// 1753 is a primitive 512th root of unity modulo q, and these are its powers
+// in the order the NTT uses them
+const ZETAS: [u32; N] = {
+	let mut out = [0; N];
+	let mut i = 0;
+
+	while i < N {
+		out[i] = pow1753((i as u8).reverse_bits() as usize);
+		i += 1;
+	}
+
+	out
+};
+
+// algorithm 41 of FIPS 204
+fn ntt(w: &mut Poly) {
+	let mut m = 0;
+	let mut len = 128;
+
+	while len >= 1 {
+		for start in (0 .. N).step_by(2 * len) {
+			m += 1;
+			let zeta = ZETAS[m];
+
+			for j in start .. start + len {
+				let t = mul(zeta, w[j + len]);
+				w[j + len] = sub(w[j], t);
+				w[j] = add(w[j], t);
+			}
+		}
+
+		len /= 2;
+	}
+}
+
+// algorithm 42 of FIPS 204
+fn inverse_ntt(w: &mut Poly) {
+	let mut m = N;
+	let mut len = 1;
+
+	while len < N {
+		for start in (0 .. N).step_by(2 * len) {
+			m -= 1;
+			let zeta = Q - ZETAS[m];
+
+			for j in start .. start + len {
+				let t = w[j];
+				w[j] = add(t, w[j + len]);
+				w[j + len] = mul(zeta, sub(t, w[j + len]));
+			}
+		}
+
+		len *= 2;
+	}
+
+	// 8347681 is 256^-1 modulo q
+	for x in w.iter_mut() {
+		*x = mul(*x, 8347681);
+	}
+}
+
+fn ntt_vector<const M: usize>(v: &[Poly; M]) -> [Poly; M] {
+	let mut out = *v;
+	out.iter_mut().for_each(ntt);
+	out
+}
+
+// multiplies a matrix by a vector, both in the NTT domain, returning the
+// result outside of it
+fn matrix_mul(a: &[[Poly; L]; K], v: &[Poly; L]) -> [Poly; K] {
+	let mut out = [[0; N]; K];
+
+	for i in 0 .. K {
+		for j in 0 .. L {
+			for n in 0 .. N {
+				out[i][n] = add(out[i][n], mul(a[i][j][n], v[j][n]));
+			}
+		}
+
+		inverse_ntt(&mut out[i]);
+	}
+
+	out
+}
+
+// multiplies each polynomial of v by c, all in the NTT domain, returning the
+// results outside of it
+fn scale<const M: usize>(c: &Poly, v: &[Poly; M]) -> [Poly; M] {
+	let mut out = [[0; N]; M];
+
+	for i in 0 .. M {
+		for n in 0 .. N {
+			out[i][n] = mul(c[n], v[i][n]);
+		}
+
+		inverse_ntt(&mut out[i]);
+	}
+
+	out
+}
+
+fn zeroize_polys(polys: &mut [Poly]) {
+	polys.iter_mut().for_each(|poly| zeroize(poly));
+}
+
+fn shake256<const M: usize>(parts: &[&[u8]]) -> [u8; M] {
+	let mut hasher = Shake256::new();
+
+	for part in parts {
+		hasher.add_bytes(part);
+	}
+
+	hasher.out()
+}
+
+// algorithm 30 of FIPS 204, sampling a uniformly random polynomial in the NTT
+// domain by rejection sampling
+fn rej_ntt_poly(rho: &[u8; 32], s: u8, r: u8) -> Poly {
+	let mut hasher = Shake128::new();
+	hasher.add_bytes(rho);
+	hasher.add_bytes(&[s, r]);
+	let mut reader = hasher.into_reader();
+
+	let mut out = [0; N];
+	let mut len = 0;
+	let mut block = [0; 168];
+
+	while len < N {
+		reader.read(&mut block);
+
+		for chunk in block.chunks(3) {
+			let z = u32::from_le_bytes([chunk[0], chunk[1], chunk[2] & 0x7f, 0]);
+
+			if z < Q && len < N {
+				out[len] = z;
+				len += 1;
+			}
+		}
+	}
+
+	out
+}
+
+// algorithm 32 of FIPS 204
+fn expand_a(rho: &[u8; 32]) -> [[Poly; L]; K] {
+	let mut out = [[[0; N]; L]; K];
+
+	for r in 0 .. K {
+		for s in 0 .. L {
+			out[r][s] = rej_ntt_poly(rho, s as u8, r as u8);
+		}
+	}
+
+	out
+}
+
+// algorithm 31 of FIPS 204, sampling coefficients in [-eta, eta]
+fn rej_bounded_poly(rho: &[u8; 64], index: u16) -> Poly {
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(rho);
+	hasher.add_bytes(&index.to_le_bytes());
+	let mut reader = hasher.into_reader();
+
+	let mut out = [0; N];
+	let mut len = 0;
+	let mut block = [0; 136];
+
+	while len < N {
+		reader.read(&mut block);
+
+		for &byte in &block {
+			for half in [byte & 0x0f, byte >> 4] {
+				if (half as u32) < 2 * ETA + 1 && len < N {
+					out[len] = sub(ETA, half as u32);
+					len += 1;
+				}
+			}
+		}
+	}
+
+	zeroize(&mut block);
+
+	out
+}
+
+// algorithm 33 of FIPS 204
+fn expand_s(rho: &[u8; 64]) -> ([Poly; L], [Poly; K]) {
+	let mut s1 = [[0; N]; L];
+	let mut s2 = [[0; N]; K];
+
+	for r in 0 .. L {
+		s1[r] = rej_bounded_poly(rho, r as u16);
+	}
+
+	for r in 0 .. K {
+		s2[r] = rej_bounded_poly(rho, (L + r) as u16);
+	}
+
+	(s1, s2)
+}
+
+// algorithm 34 of FIPS 204, sampling coefficients in [-gamma1 + 1, gamma1]
+fn expand_mask(rho: &[u8; 64], kappa: u16) -> [Poly; L] {
+	let mut out = [[0; N]; L];
+
+	for r in 0 .. L {
+		let mut bytes: [u8; 32 * Z_BITS] = shake256(&[rho, &(kappa + r as u16).to_le_bytes()]);
+		out[r] = bit_unpack(&bytes, GAMMA1, Z_BITS);
+		zeroize(&mut bytes);
+	}
+
+	out
+}
+
+// algorithm 29 of FIPS 204, sampling a polynomial with tau coefficients of
+// plus or minus one, and the rest zero
+fn sample_in_ball(seed: &[u8; C_TILDE_BYTES]) -> Poly {
+	let mut hasher = Shake256::new();
+	hasher.add_bytes(seed);
+	let mut reader = hasher.into_reader();
+
+	let mut signs = [0; 8];
+	reader.read(&mut signs);
+	let signs = u64::from_le_bytes(signs);
+
+	let mut out = [0; N];
+
+	for i in N - TAU .. N {
+		let mut j = [0];
+
+		loop {
+			reader.read(&mut j);
+
+			if j[0] as usize <= i {
+				break;
+			}
+		}
+
+		let j = j[0] as usize;
+		out[i] = out[j];
+		out[j] = match (signs >> (i + TAU - N)) & 1 {
+			0 => 1,
+			_ => Q - 1,
+		};
+	}
+
+	out
+}
+
+// algorithm 16 of FIPS 204, packing bits of each coefficient in
+// little-endian order
+fn simple_bit_pack(f: &Poly, bits: usize, out: &mut [u8]) {
+	let mut acc = 0u64;
+	let mut acc_bits = 0;
+	let mut pos = 0;
+
+	for &x in f {
+		acc |= (x as u64) << acc_bits;
+		acc_bits += bits;
+
+		while acc_bits >= 8 {
+			out[pos] = acc as u8;
+			pos += 1;
+			acc >>= 8;
+			acc_bits -= 8;
+		}
+	}
+}
+
+fn simple_bit_unpack(bytes: &[u8], bits: usize) -> Poly {
+	let mut out = [0; N];
+	let mut acc = 0u64;
+	let mut acc_bits = 0;
+	let mut pos = 0;
+
+	for x in out.iter_mut() {
+		while acc_bits < bits {
+			acc |= (bytes[pos] as u64) << acc_bits;
+			pos += 1;
+			acc_bits += 8;
+		}
+
+		*x = (acc & ((1 << bits) - 1)) as u32;
+		acc >>= bits;
+		acc_bits -= bits;
+	}
+
+	out
+}
+
+// algorithm 17 of FIPS 204, packing coefficients in [b - 2^bits + 1, b] as
+// b minus the coefficient
+fn bit_pack(f: &Poly, b: u32, bits: usize, out: &mut [u8]) {
+	let mut shifted = [0; N];
+
+	for i in 0 .. N {
+		shifted[i] = sub(b, f[i]);
+	}
+
+	simple_bit_pack(&shifted, bits, out);
+	zeroize_polys(core::slice::from_mut(&mut shifted));
+}
+
+fn bit_unpack(bytes: &[u8], b: u32, bits: usize) -> Poly {
+	let mut out = simple_bit_unpack(bytes, bits);
+
+	for x in out.iter_mut() {
+		*x = sub(b, *x);
+	}
+
+	out
+}
+
+// algorithm 35 of FIPS 204, splitting t into t1 * 2^d + t0
+fn power2round(t: &Poly) -> (Poly, Poly) {
+	let mut t1 = [0; N];
+	let mut t0 = [0; N];
+
+	for i in 0 .. N {
+		t1[i] = (t[i] + (1 << (D - 1)) - 1) >> D;
+		t0[i] = sub(t[i], t1[i] << D);
+	}
+
+	(t1, t0)
+}
+
+// algorithm 36 of FIPS 204, splitting r into r1 * 2 * gamma2 + r0, the
+// arithmetic being that of the reference implementation for this gamma2
+fn decompose(r: u32) -> (u32, i32) {
+	let r1 = (r + 127) >> 7;
+	let r1 = ((r1 * 1025 + (1 << 21)) >> 22) & 15;
+
+	let r0 = r as i32 - (r1 * 2 * GAMMA2) as i32;
+	let r0 = r0 - ((((Q as i32 - 1) / 2 - r0) >> 31) & Q as i32);
+
+	(r1, r0)
+}
+
+fn high_bits(r: u32) -> u32 {
+	decompose(r).0
+}
+
+// algorithm 40 of FIPS 204
+fn use_hint(hint: bool, r: u32) -> u32 {
+	let (r1, r0) = decompose(r);
+
+	match (hint, r0 > 0) {
+		(false, _) => r1,
+		(true, true) => (r1 + 1) & 15,
+		(true, false) => r1.wrapping_sub(1) & 15,
+	}
+}
+
+// algorithm 28 of FIPS 204
+fn w1_encode(w1: &[Poly; K]) -> [u8; 32 * K * W1_BITS] {
+	let mut out = [0; 32 * K * W1_BITS];
+
+	for i in 0 .. K {
+		simple_bit_pack(&w1[i], W1_BITS, &mut out[32 * W1_BITS * i ..]);
+	}
+
+	out
+}
+
+// algorithm 21 of FIPS 204, which also checks that the hint is canonical
+fn hint_bit_unpack(bytes: &[u8]) -> Option<[[bool; N]; K]> {
+	let mut out = [[false; N]; K];
+	let mut index = 0;
+
+	for i in 0 .. K {
+		let end = bytes[OMEGA + i] as usize;
+
+		if end < index || end > OMEGA {
+			return None;
+		}
+
+		let first = index;
+
+		while index < end {
+			if index > first && bytes[index - 1] >= bytes[index] {
+				return None;
+			}
+
+			out[i][bytes[index] as usize] = true;
+			index += 1;
+		}
+	}
+
+	if bytes[index .. OMEGA].iter().any(|&x| x != 0) {
+		return None;
+	}
+
+	Some(out)
+}
+
+// ML-DSA.KeyGen_internal, algorithm 6 of FIPS 204, returning the encoded
+// signing key and verifying key
+fn key_gen(seed: &[u8; 32]) -> ([u8; SIGNING_KEY_LEN], [u8; VERIFYING_KEY_LEN]) {
+	let mut expanded: [u8; 128] = shake256(&[seed, &[K as u8, L as u8]]);
+	let rho: [u8; 32] = expanded[.. 32].try_into().unwrap();
+	let rho_prime: [u8; 64] = expanded[32 .. 96].try_into().unwrap();
+
+	let (mut s1, mut s2) = expand_s(&rho_prime);
+
+	let mut out = [0; SIGNING_KEY_LEN];
+	out[.. 32].copy_from_slice(&rho);
+	out[32 .. 64].copy_from_slice(&expanded[96 ..]);
+
+	for i in 0 .. L {
+		bit_pack(&s1[i], ETA, ETA_BITS, &mut out[S1_OFFSET + 32 * ETA_BITS * i ..]);
+	}
+
+	for i in 0 .. K {
+		bit_pack(&s2[i], ETA, ETA_BITS, &mut out[S2_OFFSET + 32 * ETA_BITS * i ..]);
+	}
+
+	zeroize(&mut expanded);
+	zeroize_polys(&mut s1);
+	zeroize_polys(&mut s2);
+
+	// tr and t0 are computed the same way as when parsing a signing key
+	let (pub_key, mut t0) = public_parts(&out);
+	out[64 .. 128].copy_from_slice(&shake256::<64>(&[&pub_key]));
+
+	for i in 0 .. K {
+		bit_pack(&t0[i], 1 << (D - 1), T0_BITS, &mut out[T0_OFFSET + 32 * T0_BITS * i ..]);
+	}
+
+	zeroize_polys(&mut t0);
+
+	(out, pub_key)
+}
+
+// decodes s1 and s2 from a signing key, checking that their coefficients are
+// in range
+fn decode_secrets(bytes: &[u8; SIGNING_KEY_LEN]) -> Option<([Poly; L], [Poly; K])> {
+	let mut invalid = 0;
+
+	for &byte in &bytes[S1_OFFSET .. T0_OFFSET] {
+		for half in [byte & 0x0f, byte >> 4] {
+			invalid |= (2 * ETA as i32 - half as i32) >> 31;
+		}
+	}
+
+	if invalid != 0 {
+		return None;
+	}
+
+	let mut s1 = [[0; N]; L];
+	let mut s2 = [[0; N]; K];
+
+	for i in 0 .. L {
+		s1[i] = bit_unpack(&bytes[S1_OFFSET + 32 * ETA_BITS * i ..], ETA, ETA_BITS);
+	}
+
+	for i in 0 .. K {
+		s2[i] = bit_unpack(&bytes[S2_OFFSET + 32 * ETA_BITS * i ..], ETA, ETA_BITS);
+	}
+
+	Some((s1, s2))
+}
+
+// computes the encoded verifying key and t0 from rho, s1 and s2 in a signing
+// key whose secrets have been checked
+fn public_parts(bytes: &[u8; SIGNING_KEY_LEN]) -> ([u8; VERIFYING_KEY_LEN], [Poly; K]) {
+	let rho: [u8; 32] = bytes[.. 32].try_into().unwrap();
+	let (mut s1, mut s2) = decode_secrets(bytes).unwrap();
+
+	let mut s1_hat = ntt_vector(&s1);
+	let mut t = matrix_mul(&expand_a(&rho), &s1_hat);
+
+	let mut pub_key = [0; VERIFYING_KEY_LEN];
+	pub_key[.. 32].copy_from_slice(&rho);
+
+	let mut t0 = [[0; N]; K];
+
+	for i in 0 .. K {
+		for n in 0 .. N {
+			t[i][n] = add(t[i][n], s2[i][n]);
+		}
+
+		let (t1, low) = power2round(&t[i]);
+		simple_bit_pack(&t1, T1_BITS, &mut pub_key[32 + 32 * T1_BITS * i ..]);
+		t0[i] = low;
+	}
+
+	zeroize_polys(&mut s1);
+	zeroize_polys(&mut s1_hat);
+	zeroize_polys(&mut s2);
+	zeroize_polys(&mut t);
+
+	(pub_key, t0)
+}
+
+// the message representative mu, for pure ML-DSA with a context string
+fn message_representative(tr: &[u8; 64], context: &[u8], message: &[u8]) -> [u8; 64] {
+	shake256(&[tr, &[0, context.len() as u8], context, message])
+}
+
+/// An ML-DSA-65 signing key. Zeroized when dropped.
+#[derive(Clone)]
+pub struct SigningKey {
+	bytes: [u8; SIGNING_KEY_LEN],
+	verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+	/// Derives a signing key from a 32-byte seed, the `xi` value of FIPS
+	/// 204. This is the usual compact encoding of a private key, and a random
+	/// seed generates a new key.
+	pub fn from_seed(seed: [u8; 32]) -> Self {
+		let (bytes, pub_key) = key_gen(&seed);
+
+		let mut seed = seed;
+		zeroize(&mut seed);
+
+		Self {bytes, verifying_key: VerifyingKey::from_bytes(&pub_key)}
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		Self::from_seed(seed)
+	}
+
+	/// Parses an expanded signing key, checking that it's consistent, which
+	/// takes about as long as generating it.
+	pub fn from_bytes(bytes: &[u8; SIGNING_KEY_LEN]) -> Result<Self, Error> {
+		if decode_secrets(bytes).is_none() {
+			return Err(Error::InvalidPrivateKey);
+		}
+
+		let (pub_key, mut t0) = public_parts(bytes);
+		let verifying_key = VerifyingKey::from_bytes(&pub_key);
+
+		let mut expected = [0; 32 * K * T0_BITS];
+
+		for i in 0 .. K {
+			bit_pack(&t0[i], 1 << (D - 1), T0_BITS, &mut expected[32 * T0_BITS * i ..]);
+		}
+
+		let consistent = expected[..] == bytes[T0_OFFSET ..] && verifying_key.tr[..] == bytes[64 .. 128];
+
+		zeroize(&mut expected);
+		zeroize_polys(&mut t0);
+
+		if !consistent {
+			return Err(Error::InvalidPrivateKey);
+		}
+
+		Ok(Self {bytes: *bytes, verifying_key})
+	}
+
+	/// Returns the expanded encoding of this signing key. This must be kept
+	/// secret.
+	pub fn to_bytes(&self) -> [u8; SIGNING_KEY_LEN] {
+		self.bytes
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key.clone()
+	}
+
+	/// Deterministically signs a `message` with an empty context.
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		self.sign_with_context(message, b"").unwrap()
+	}
+
+	/// Deterministically signs a `message`, binding the signature to
+	/// `context`, which must be at most 255 bytes long.
+	pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Result<Signature, Error> {
+		self.sign_randomized(message, context, [0; 32])
+	}
+
+	/// Signs a `message` bound to `context`, like
+	/// [`SigningKey::sign_with_context`], but mixes `randomness` into the
+	/// signature. `randomness` should be freshly generated by a secure random
+	/// source for every call.
+	pub fn sign_randomized(&self, message: &[u8], context: &[u8], randomness: [u8; 32]) -> Result<Signature, Error> {
+		if context.len() > 255 {
+			return Err(Error::ContextTooLong);
+		}
+
+		let tr: &[u8; 64] = self.bytes[64 .. 128].try_into().unwrap();
+		let mu = message_representative(tr, context, message);
+
+		Ok(self.sign_internal(&mu, &randomness))
+	}
+
+	/// Like [`SigningKey::sign_randomized`], but generates the randomness
+	/// with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn sign_with_rng<R: RngCore + CryptoRng>(
+		&self,
+		message: &[u8],
+		context: &[u8],
+		rng: &mut R,
+	) -> Result<Signature, Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = self.sign_randomized(message, context, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	// ML-DSA.Sign_internal, algorithm 7 of FIPS 204
+	fn sign_internal(&self, mu: &[u8; 64], randomness: &[u8; 32]) -> Signature {
+		let rho: [u8; 32] = self.bytes[.. 32].try_into().unwrap();
+		let (mut s1, mut s2) = decode_secrets(&self.bytes).unwrap();
+		let mut t0 = [[0; N]; K];
+
+		for i in 0 .. K {
+			t0[i] = bit_unpack(&self.bytes[T0_OFFSET + 32 * T0_BITS * i ..], 1 << (D - 1), T0_BITS);
+		}
+
+		let mut s1_hat = ntt_vector(&s1);
+		let mut s2_hat = ntt_vector(&s2);
+		let mut t0_hat = ntt_vector(&t0);
+		let a = expand_a(&rho);
+
+		let mut rho_prime: [u8; 64] = shake256(&[&self.bytes[32 .. 64], randomness, mu]);
+		let mut kappa = 0;
+
+		let mut out = [0; SIGNATURE_LEN];
+
+		loop {
+			let mut y = expand_mask(&rho_prime, kappa);
+			kappa += L as u16;
+
+			let mut w = matrix_mul(&a, &ntt_vector(&y));
+			let mut w1 = [[0; N]; K];
+
+			for i in 0 .. K {
+				for n in 0 .. N {
+					w1[i][n] = high_bits(w[i][n]);
+				}
+			}
+
+			let c_tilde: [u8; C_TILDE_BYTES] = shake256(&[mu, &w1_encode(&w1)]);
+			let mut c_hat = sample_in_ball(&c_tilde);
+			ntt(&mut c_hat);
+
+			let mut z = scale(&c_hat, &s1_hat);
+			let mut cs2 = scale(&c_hat, &s2_hat);
+			let mut ct0 = scale(&c_hat, &t0_hat);
+
+			for i in 0 .. L {
+				for n in 0 .. N {
+					z[i][n] = add(z[i][n], y[i][n]);
+				}
+			}
+
+			// w - cs2, whose low bits must be small, and whose high bits are
+			// recovered from w - cs2 + ct0 with the hint
+			let mut r0_exceeds = 0;
+			let mut hint_count = 0;
+			let mut hints = [[false; N]; K];
+
+			for i in 0 .. K {
+				for n in 0 .. N {
+					let r = sub(w[i][n], cs2[i][n]);
+					let (r1, r0) = decompose(r);
+					r0_exceeds |= ((GAMMA2 - BETA) as i32 - 1 - r0.abs()) >> 31;
+
+					let hint = high_bits(add(r, ct0[i][n])) != r1;
+					hints[i][n] = hint;
+					hint_count += hint as usize;
+				}
+			}
+
+			let rejected = exceeds_norm(&z, GAMMA1 - BETA)
+				|| r0_exceeds != 0
+				|| exceeds_norm(&ct0, GAMMA2)
+				|| hint_count > OMEGA;
+
+			if !rejected {
+				out[.. C_TILDE_BYTES].copy_from_slice(&c_tilde);
+
+				for i in 0 .. L {
+					bit_pack(&z[i], GAMMA1, Z_BITS, &mut out[C_TILDE_BYTES + 32 * Z_BITS * i ..]);
+				}
+
+				// algorithm 20 of FIPS 204
+				let hint_bytes = &mut out[C_TILDE_BYTES + 32 * Z_BITS * L ..];
+				let mut index = 0;
+
+				for i in 0 .. K {
+					for n in 0 .. N {
+						if hints[i][n] {
+							hint_bytes[index] = n as u8;
+							index += 1;
+						}
+					}
+
+					hint_bytes[OMEGA + i] = index as u8;
+				}
+			}
+
+			zeroize_polys(&mut y);
+			zeroize_polys(&mut w);
+			zeroize_polys(&mut w1);
+			zeroize_polys(core::slice::from_mut(&mut c_hat));
+			zeroize_polys(&mut z);
+			zeroize_polys(&mut cs2);
+			zeroize_polys(&mut ct0);
+
+			if !rejected {
+				break;
+			}
+		}
+
+		zeroize(&mut rho_prime);
+		zeroize_polys(&mut s1);
+		zeroize_polys(&mut s2);
+		zeroize_polys(&mut t0);
+		zeroize_polys(&mut s1_hat);
+		zeroize_polys(&mut s2_hat);
+		zeroize_polys(&mut t0_hat);
+
+		Signature(out)
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey").field("verifying_key", &self.verifying_key).finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(SigningKey, SIGNING_KEY_LEN, Base64, |key| key.to_bytes(), try |bytes| SigningKey::from_bytes(&bytes));
+
+/// An ML-DSA-65 verifying key.
+#[derive(Clone)]
+pub struct VerifyingKey {
+	bytes: [u8; VERIFYING_KEY_LEN],
+	tr: [u8; 64],
+}
+
+impl VerifyingKey {
+	/// Creates a verifying key from its encoding. Any bytes encode a valid
+	/// key.
+	pub fn from_bytes(bytes: &[u8; VERIFYING_KEY_LEN]) -> Self {
+		Self {bytes: *bytes, tr: shake256(&[bytes])}
+	}
+
+	/// Returns the encoding of this key.
+	pub fn to_bytes(&self) -> [u8; VERIFYING_KEY_LEN] {
+		self.bytes
+	}
+
+	/// Returns a reference to the encoding of this key.
+	pub fn as_bytes(&self) -> &[u8; VERIFYING_KEY_LEN] {
+		&self.bytes
+	}
+
+	/// Verifies a `signature` over a `message` with an empty context.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		self.verify_with_context(message, b"", signature)
+	}
+
+	/// Verifies a `signature` over a `message` that was bound to `context`
+	/// when signing. Returns false if the context is longer than 255 bytes.
+	pub fn verify_with_context(&self, message: &[u8], context: &[u8], signature: &Signature) -> bool {
+		if context.len() > 255 {
+			return false;
+		}
+
+		self.verify_internal(&message_representative(&self.tr, context, message), &signature.0)
+	}
+
+	// ML-DSA.Verify_internal, algorithm 8 of FIPS 204
+	fn verify_internal(&self, mu: &[u8; 64], signature: &[u8; SIGNATURE_LEN]) -> bool {
+		let c_tilde: &[u8; C_TILDE_BYTES] = signature[.. C_TILDE_BYTES].try_into().unwrap();
+
+		let mut z = [[0; N]; L];
+
+		for i in 0 .. L {
+			z[i] = bit_unpack(&signature[C_TILDE_BYTES + 32 * Z_BITS * i ..], GAMMA1, Z_BITS);
+		}
+
+		let hints = match hint_bit_unpack(&signature[C_TILDE_BYTES + 32 * Z_BITS * L ..]) {
+			Some(hints) => hints,
+			None => return false,
+		};
+
+		if exceeds_norm(&z, GAMMA1 - BETA) {
+			return false;
+		}
+
+		let rho: [u8; 32] = self.bytes[.. 32].try_into().unwrap();
+		let mut t1 = [[0; N]; K];
+
+		for i in 0 .. K {
+			t1[i] = simple_bit_unpack(&self.bytes[32 + 32 * T1_BITS * i ..], T1_BITS);
+
+			for x in t1[i].iter_mut() {
+				*x <<= D;
+			}
+		}
+
+		let mut c_hat = sample_in_ball(c_tilde);
+		ntt(&mut c_hat);
+
+		let az = matrix_mul(&expand_a(&rho), &ntt_vector(&z));
+		let ct1 = scale(&c_hat, &ntt_vector(&t1));
+
+		let mut w1 = [[0; N]; K];
+
+		for i in 0 .. K {
+			for n in 0 .. N {
+				w1[i][n] = use_hint(hints[i][n], sub(az[i][n], ct1[i][n]));
+			}
+		}
+
+		let expected: [u8; C_TILDE_BYTES] = shake256(&[mu, &w1_encode(&w1)]);
+
+		expected == *c_tilde
+	}
+}
+
+impl PartialEq for VerifyingKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.bytes == other.bytes
+	}
+}
+
+impl Eq for VerifyingKey {}
+
+impl fmt::Debug for VerifyingKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("VerifyingKey").field("tr", &self.tr).finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(VerifyingKey, VERIFYING_KEY_LEN, Base64, |key| key.to_bytes(), |bytes| VerifyingKey::from_bytes(&bytes));
+
+text_impl!(VerifyingKey, VERIFYING_KEY_LEN, Base64, |key| key.to_bytes(), |bytes| VerifyingKey::from_bytes(&bytes));
+
+/// An ML-DSA-65 signature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature([u8; SIGNATURE_LEN]);
+
+impl Signature {
+	/// Creates a signature from its encoding. Any bytes are accepted, since
+	/// malformed signatures fail to verify.
+	pub fn from_bytes(bytes: [u8; SIGNATURE_LEN]) -> Self {
+		Self(bytes)
+	}
+
+	/// Returns the encoding of this signature.
+	pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+		self.0
+	}
+
+	/// Returns a reference to the encoding of this signature.
+	pub fn as_bytes(&self) -> &[u8; SIGNATURE_LEN] {
+		&self.0
+	}
+}
+
+#[cfg(feature = "serde")]
+serde_bytes_impl!(Signature, SIGNATURE_LEN, Base64, |signature| signature.to_bytes(), |bytes| Signature::from_bytes(bytes));
+
+text_impl!(Signature, SIGNATURE_LEN, Base64, |signature| signature.to_bytes(), |bytes| Signature::from_bytes(bytes));
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[cfg(test)]
+fn test_key() -> SigningKey {
+	let mut seed = [0; 32];
+
+	for i in 0 .. 32 {
+		seed[i] = i as u8;
+	}
+
+	SigningKey::from_seed(seed)
+}
+
+// checked against OpenSSL 3.5, with `openssl genpkey -algorithm ML-DSA-65
+// -pkeyopt hexseed:0001..1f`, then `openssl pkeyutl -sign -rawin` with
+// `-pkeyopt deterministic:1`, `-pkeyopt context-string:ctx` and `-pkeyopt
+// hextest-entropy:5a5a..5a` for the three signatures
+#[test]
+fn test_known_answer() {
+	use crate::sha3::sha3_256;
+
+	let signing_key = test_key();
+	let verifying_key = signing_key.verifying_key();
+
+	assert_eq!(
+		sha3_256(verifying_key.as_bytes()),
+		hex("1800725067e388d837d911fe4f66101cc1961b1bb755030dc574272cfb00013f"),
+	);
+
+	assert_eq!(
+		sha3_256(&signing_key.to_bytes()),
+		hex("4ecfbd119980b1090a7feda39c225539ffa3ef3ff673b239b60189bfd4541170"),
+	);
+
+	let signature = signing_key.sign(b"hello ml-dsa");
+	assert_eq!(
+		sha3_256(signature.as_bytes()),
+		hex("059a81e5ebd7ad70f61e6faaa436f5f183ff253820ef1cbf9b251b7db1ac2b57"),
+	);
+
+	let signature = signing_key.sign_with_context(b"hello ml-dsa", b"ctx").unwrap();
+	assert_eq!(
+		sha3_256(signature.as_bytes()),
+		hex("2066e031fed1949aae5526dbb56b16ddf4c1ddcc3af1086622f0f8ad3f5e11d1"),
+	);
+
+	let signature = signing_key.sign_randomized(b"hello ml-dsa", b"", [0x5a; 32]).unwrap();
+	assert_eq!(
+		sha3_256(signature.as_bytes()),
+		hex("4fd62b1c974a7f43f73c4dd835d79175715cb52d9e467154a8ca067d6e5fdb1c"),
+	);
+
+	assert!(verifying_key.verify(b"hello ml-dsa", &signature));
+}
+
+#[test]
+fn test_verify() {
+	let signing_key = test_key();
+	let verifying_key = signing_key.verifying_key();
+
+	let signature = signing_key.sign_with_context(b"message", b"context").unwrap();
+	assert!(verifying_key.verify_with_context(b"message", b"context", &signature));
+	assert!(!verifying_key.verify_with_context(b"message", b"other", &signature));
+	assert!(!verifying_key.verify(b"message", &signature));
+	assert!(!verifying_key.verify_with_context(b"massage", b"context", &signature));
+
+	let mut modified = signature.to_bytes();
+	modified[100] ^= 1;
+	assert!(!verifying_key.verify_with_context(b"message", b"context", &Signature::from_bytes(modified)));
+
+	// a hint index out of order
+	let mut modified = signature.to_bytes();
+	modified[SIGNATURE_LEN - 1] = OMEGA as u8 + 1;
+	assert!(!verifying_key.verify_with_context(b"message", b"context", &Signature::from_bytes(modified)));
+
+	assert_eq!(signing_key.sign_with_context(b"", &[0; 256]).unwrap_err(), Error::ContextTooLong);
+}
+
+#[test]
+fn test_key_encoding() {
+	let signing_key = test_key();
+
+	let parsed = SigningKey::from_bytes(&signing_key.to_bytes()).unwrap();
+	assert_eq!(parsed.verifying_key(), signing_key.verifying_key());
+
+	// a coefficient of s1 out of range
+	let mut modified = signing_key.to_bytes();
+	modified[S1_OFFSET] |= 0x0f;
+	assert_eq!(SigningKey::from_bytes(&modified).unwrap_err(), Error::InvalidPrivateKey);
+
+	// an inconsistent tr, and an inconsistent t0
+	let mut modified = signing_key.to_bytes();
+	modified[64] ^= 1;
+	assert_eq!(SigningKey::from_bytes(&modified).unwrap_err(), Error::InvalidPrivateKey);
+
+	let mut modified = signing_key.to_bytes();
+	modified[SIGNING_KEY_LEN - 1] ^= 1;
+	assert_eq!(SigningKey::from_bytes(&modified).unwrap_err(), Error::InvalidPrivateKey);
+}
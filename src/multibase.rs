@@ -0,0 +1,247 @@
+//! Implemented according to the
+//! [multibase specification](https://github.com/multiformats/multibase).
+//! Multibase strings start with a character that names their encoding, so
+//! that binary data such as [`multihash`](crate::multihash) digests and IPFS
+//! content identifiers can be decoded without knowing the encoding in
+//! advance.
+//!
+//! ```
+//! use libkrypton::multibase::{self, Base};
+//!
+//! let text = multibase::encode(Base::Base58Btc, b"yes mani !");
+//! assert_eq!(text, "z7paNL19xttacUY");
+//!
+//! assert_eq!(multibase::decode(&text).unwrap(), (Base::Base58Btc, b"yes mani !".to_vec()));
+//! ```
+
+use core::fmt;
+
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+use crate::encoding::{self, Base64Variant};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// An error returned when decoding a multibase string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The string is empty, or starts with a character that isn't the prefix
+	/// of a supported encoding.
+	UnsupportedBase,
+	/// The rest of the string isn't valid in its encoding.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::UnsupportedBase => "unsupported multibase encoding",
+			Error::InvalidEncoding => "invalid multibase string",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The supported multibase encodings. None of them are padded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Base {
+	/// Lowercase hex, with the prefix `f`.
+	Base16,
+	/// Uppercase hex, with the prefix `F`.
+	Base16Upper,
+	/// Lowercase RFC 4648 base32, with the prefix `b`, as used by CIDv1.
+	Base32,
+	/// Uppercase RFC 4648 base32, with the prefix `B`.
+	Base32Upper,
+	/// Bitcoin's base58 alphabet, with the prefix `z`, as used by CIDv0.
+	Base58Btc,
+	/// Standard base64, with the prefix `m`.
+	Base64,
+	/// URL-safe base64, with the prefix `u`.
+	Base64Url,
+}
+
+impl Base {
+	/// Returns the character that strings in this encoding start with.
+	pub fn prefix(self) -> char {
+		match self {
+			Base::Base16 => 'f',
+			Base::Base16Upper => 'F',
+			Base::Base32 => 'b',
+			Base::Base32Upper => 'B',
+			Base::Base58Btc => 'z',
+			Base::Base64 => 'm',
+			Base::Base64Url => 'u',
+		}
+	}
+
+	/// Returns the encoding with the given prefix, if it's supported.
+	pub fn from_prefix(prefix: char) -> Option<Self> {
+		let base = match prefix {
+			'f' => Base::Base16,
+			'F' => Base::Base16Upper,
+			'b' => Base::Base32,
+			'B' => Base::Base32Upper,
+			'z' => Base::Base58Btc,
+			'm' => Base::Base64,
+			'u' => Base::Base64Url,
+			_ => return None,
+		};
+
+		Some(base)
+	}
+}
+
+// base58 treats the bytes as one big-endian number, with each leading zero
+// byte encoded as a leading '1'
+fn encode_base58(bytes: &[u8], out: &mut String) {
+	let zeros = bytes.iter().take_while(|&&x| x == 0).count();
+
+	// little-endian base58 digits of the number
+	let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+
+	for &byte in &bytes[zeros ..] {
+		let mut carry = byte as u32;
+
+		for digit in digits.iter_mut() {
+			carry += (*digit as u32) << 8;
+			*digit = (carry % 58) as u8;
+			carry /= 58;
+		}
+
+		while carry > 0 {
+			digits.push((carry % 58) as u8);
+			carry /= 58;
+		}
+	}
+
+	out.extend(std::iter::repeat_n('1', zeros));
+	out.extend(digits.iter().rev().map(|&x| BASE58_ALPHABET[x as usize] as char));
+}
+
+fn decode_base58(text: &str) -> Result<Vec<u8>, Error> {
+	let zeros = text.bytes().take_while(|&x| x == b'1').count();
+
+	// little-endian bytes of the number
+	let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+
+	for char in text[zeros ..].bytes() {
+		let mut carry = BASE58_ALPHABET.iter().position(|&x| x == char).ok_or(Error::InvalidEncoding)? as u32;
+
+		for byte in bytes.iter_mut() {
+			carry += *byte as u32 * 58;
+			*byte = carry as u8;
+			carry >>= 8;
+		}
+
+		while carry > 0 {
+			bytes.push(carry as u8);
+			carry >>= 8;
+		}
+	}
+
+	let mut out = vec![0; zeros];
+	out.extend(bytes.iter().rev());
+
+	Ok(out)
+}
+
+/// Encodes `bytes` as a multibase string in the given `base`.
+pub fn encode(base: Base, bytes: &[u8]) -> String {
+	let mut out = String::new();
+	out.push(base.prefix());
+
+	match base {
+		Base::Base16 => encoding::encode_hex(bytes, &mut out),
+		Base::Base16Upper => {
+			let mut hex = String::new();
+			encoding::encode_hex(bytes, &mut hex);
+			out.push_str(&hex.to_ascii_uppercase());
+		},
+		Base::Base32 => {
+			let mut base32 = String::new();
+			encoding::encode_base32(bytes, &mut base32);
+			out.push_str(&base32.to_ascii_lowercase());
+		},
+		Base::Base32Upper => encoding::encode_base32(bytes, &mut out),
+		Base::Base58Btc => encode_base58(bytes, &mut out),
+		Base::Base64 => encoding::encode_base64(bytes, Base64Variant::StandardUnpadded, &mut out),
+		Base::Base64Url => encoding::encode_base64(bytes, Base64Variant::UrlSafeUnpadded, &mut out),
+	}
+
+	out
+}
+
+/// Decodes a multibase string, returning its encoding and the decoded bytes.
+/// Hex and base32 are accepted in either case, whichever the prefix.
+pub fn decode(text: &str) -> Result<(Base, Vec<u8>), Error> {
+	let prefix = text.chars().next().ok_or(Error::UnsupportedBase)?;
+	let base = Base::from_prefix(prefix).ok_or(Error::UnsupportedBase)?;
+	let text = &text[1 ..];
+
+	if base == Base::Base58Btc {
+		return Ok((base, decode_base58(text)?));
+	}
+
+	let mut out = vec![0; text.len()];
+
+	let len = match base {
+		Base::Base16 | Base::Base16Upper => encoding::decode_hex(text, &mut out),
+		// padding isn't allowed, though the base32 decoder would accept it
+		Base::Base32 | Base::Base32Upper if text.contains('=') => Err(encoding::Error::InvalidEncoding),
+		Base::Base32 | Base::Base32Upper => encoding::decode_base32(text, &mut out),
+		Base::Base64 => encoding::decode_base64(text, Base64Variant::StandardUnpadded, &mut out),
+		Base::Base64Url => encoding::decode_base64(text, Base64Variant::UrlSafeUnpadded, &mut out),
+		Base::Base58Btc => unreachable!(),
+	};
+
+	out.truncate(len.map_err(|_| Error::InvalidEncoding)?);
+
+	Ok((base, out))
+}
+
+// from the multibase specification's test vectors
+#[test]
+fn test_vectors() {
+	let expected = [
+		(Base::Base16, "f796573206d616e692021"),
+		(Base::Base16Upper, "F796573206D616E692021"),
+		(Base::Base32, "bpfsxgidnmfxgsibb"),
+		(Base::Base32Upper, "BPFSXGIDNMFXGSIBB"),
+		(Base::Base58Btc, "z7paNL19xttacUY"),
+		(Base::Base64, "meWVzIG1hbmkgIQ"),
+		(Base::Base64Url, "ueWVzIG1hbmkgIQ"),
+	];
+
+	for (base, text) in expected {
+		assert_eq!(encode(base, b"yes mani !"), text);
+		assert_eq!(decode(text).unwrap(), (base, b"yes mani !".to_vec()));
+	}
+}
+
+#[test]
+fn test_base58() {
+	assert_eq!(encode(Base::Base58Btc, b""), "z");
+	assert_eq!(encode(Base::Base58Btc, &[0]), "z1");
+	assert_eq!(encode(Base::Base58Btc, b"\0\0yes mani !"), "z117paNL19xttacUY");
+
+	for bytes in [&b""[..], &[0], &[0, 0, 1], &[0xff; 40]] {
+		assert_eq!(decode(&encode(Base::Base58Btc, bytes)).unwrap().1, bytes);
+	}
+
+	assert_eq!(decode("z0OIl"), Err(Error::InvalidEncoding));
+}
+
+#[test]
+fn test_errors() {
+	assert_eq!(decode(""), Err(Error::UnsupportedBase));
+	assert_eq!(decode("x1234"), Err(Error::UnsupportedBase));
+	assert_eq!(decode("f123"), Err(Error::InvalidEncoding));
+	assert_eq!(decode("bpfsxgidnmfxgsibb======"), Err(Error::InvalidEncoding));
+	assert_eq!(decode("meWVzIG1hbmkgIQ=="), Err(Error::InvalidEncoding));
+}
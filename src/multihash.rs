@@ -0,0 +1,394 @@
+//! Implemented according to the
+//! [multihash specification](https://github.com/multiformats/multihash).
+//! A multihash is a digest prefixed with varints for the code of the hash
+//! function and the length of the digest, so that content-addressed systems
+//! such as IPFS can store digests from different hash functions side by side.
+//! Together with [`multibase`], it gives text such as IPFS's version 0
+//! content identifiers.
+//!
+//! ```
+//! use libkrypton::multibase::Base;
+//! use libkrypton::multihash::{Code, Multihash};
+//!
+//! let multihash = Multihash::hash(Code::Sha2_256, b"multihash");
+//! assert_eq!(multihash.to_multibase(Base::Base58Btc), "zQmYtUc4iTCbbfVSDNKvtQqrfyezPPnFvE33wFmutw9PBBk");
+//!
+//! assert!(multihash.verify(b"multihash"));
+//! ```
+
+use core::fmt;
+
+use std::string::String;
+use std::vec::Vec;
+
+use crate::blake2::{blake2b, blake2s};
+use crate::multibase::{self, Base};
+use crate::sha2::{sha256, sha384, sha512};
+use crate::sha3::{sha3_256, sha3_512};
+
+/// An error returned when parsing a multihash.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The multihash is truncated, has trailing data, or has a varint that is
+	/// too long or not minimally encoded.
+	InvalidEncoding,
+	/// The code isn't one of the supported hash functions.
+	UnsupportedCode,
+	/// The digest is empty, or longer than the output of the hash function.
+	InvalidLength,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid multihash encoding",
+			Error::UnsupportedCode => "unsupported multihash code",
+			Error::InvalidLength => "invalid multihash digest length",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The hash functions in the multicodec table that the crate implements.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Code {
+	/// SHA-256, with the code `0x12`.
+	Sha2_256,
+	/// SHA-384, with the code `0x20`.
+	Sha2_384,
+	/// SHA-512, with the code `0x13`.
+	Sha2_512,
+	/// SHA3-256, with the code `0x16`.
+	Sha3_256,
+	/// SHA3-512, with the code `0x14`.
+	Sha3_512,
+	/// BLAKE2b with a 32-byte output, with the code `0xb220`.
+	Blake2b256,
+	/// BLAKE2b with a 64-byte output, with the code `0xb240`.
+	Blake2b512,
+	/// BLAKE2s with a 32-byte output, with the code `0xb260`.
+	Blake2s256,
+	/// BLAKE3 with its default 32-byte output, with the code `0x1e`.
+	Blake3,
+	/// SM3, with the code `0x534`.
+	Sm3_256,
+}
+
+impl Code {
+	/// Returns the multicodec code of this hash function.
+	pub fn to_u64(self) -> u64 {
+		match self {
+			Code::Sha2_256 => 0x12,
+			Code::Sha2_384 => 0x20,
+			Code::Sha2_512 => 0x13,
+			Code::Sha3_256 => 0x16,
+			Code::Sha3_512 => 0x14,
+			Code::Blake2b256 => 0xb220,
+			Code::Blake2b512 => 0xb240,
+			Code::Blake2s256 => 0xb260,
+			Code::Blake3 => 0x1e,
+			Code::Sm3_256 => 0x534,
+		}
+	}
+
+	/// Returns the hash function with the multicodec code `code`, if it's
+	/// supported.
+	pub fn from_u64(code: u64) -> Option<Self> {
+		let code = match code {
+			0x12 => Code::Sha2_256,
+			0x20 => Code::Sha2_384,
+			0x13 => Code::Sha2_512,
+			0x16 => Code::Sha3_256,
+			0x14 => Code::Sha3_512,
+			0xb220 => Code::Blake2b256,
+			0xb240 => Code::Blake2b512,
+			0xb260 => Code::Blake2s256,
+			0x1e => Code::Blake3,
+			0x534 => Code::Sm3_256,
+			_ => return None,
+		};
+
+		Some(code)
+	}
+
+	/// Returns the length of the full digest of this hash function.
+	pub fn digest_len(self) -> usize {
+		match self {
+			Code::Sha2_384 => 48,
+			Code::Sha2_512 | Code::Sha3_512 | Code::Blake2b512 => 64,
+			_ => 32,
+		}
+	}
+
+	fn hash(self, data: &[u8], out: &mut [u8; 64]) {
+		match self {
+			Code::Sha2_256 => out[.. 32].copy_from_slice(&sha256(data)),
+			Code::Sha2_384 => out[.. 48].copy_from_slice(&sha384(data)),
+			Code::Sha2_512 => out.copy_from_slice(&sha512(data)),
+			Code::Sha3_256 => out[.. 32].copy_from_slice(&sha3_256(data)),
+			Code::Sha3_512 => out.copy_from_slice(&sha3_512(data)),
+			Code::Blake2b256 => out[.. 32].copy_from_slice(&blake2b::<32>(data)),
+			Code::Blake2b512 => out.copy_from_slice(&blake2b::<64>(data)),
+			Code::Blake2s256 => out[.. 32].copy_from_slice(&blake2s::<32>(data)),
+			Code::Blake3 => out[.. 32].copy_from_slice(&crate::blake3::hash(data)),
+			Code::Sm3_256 => out[.. 32].copy_from_slice(&crate::sm3::sm3(data)),
+		}
+	}
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+	while value >= 0x80 {
+		out.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+
+	out.push(value as u8);
+}
+
+// reads an unsigned LEB128 varint, which the multiformats specification
+// limits to 9 bytes and requires to be minimally encoded
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, Error> {
+	let mut value = 0;
+
+	for i in 0 .. 9 {
+		let (&byte, rest) = bytes.split_first().ok_or(Error::InvalidEncoding)?;
+		*bytes = rest;
+		value |= ((byte & 0x7f) as u64) << (7 * i);
+
+		if byte & 0x80 == 0 {
+			if byte == 0 && i > 0 {
+				return Err(Error::InvalidEncoding);
+			}
+
+			return Ok(value);
+		}
+	}
+
+	Err(Error::InvalidEncoding)
+}
+
+/// A digest tagged with the hash function that produced it. The digest may
+/// be truncated, as the specification allows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Multihash {
+	code: Code,
+	digest: [u8; 64],
+	len: usize,
+}
+
+impl Multihash {
+	/// Hashes `data` with the hash function `code`, with its full digest.
+	pub fn hash(code: Code, data: &[u8]) -> Self {
+		let mut digest = [0; 64];
+		code.hash(data, &mut digest);
+
+		Self {code, digest, len: code.digest_len()}
+	}
+
+	/// Wraps an existing `digest`, which may be truncated, but must not be
+	/// empty.
+	pub fn new(code: Code, digest: &[u8]) -> Result<Self, Error> {
+		if digest.len() == 0 || digest.len() > code.digest_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut padded = [0; 64];
+		padded[.. digest.len()].copy_from_slice(digest);
+
+		Ok(Self {code, digest: padded, len: digest.len()})
+	}
+
+	/// Returns the hash function of this multihash.
+	pub fn code(&self) -> Code {
+		self.code
+	}
+
+	/// Returns the digest, without the code and length.
+	pub fn digest(&self) -> &[u8] {
+		&self.digest[.. self.len]
+	}
+
+	/// Checks whether this is a multihash of `data`, comparing only as many
+	/// bytes as the digest has.
+	pub fn verify(&self, data: &[u8]) -> bool {
+		let mut digest = [0; 64];
+		self.code.hash(data, &mut digest);
+
+		digest[.. self.len] == self.digest[.. self.len]
+	}
+
+	/// Parses the binary encoding of a multihash.
+	pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, Error> {
+		let code = read_varint(&mut bytes)?;
+		let len = read_varint(&mut bytes)?;
+
+		if len != bytes.len() as u64 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let code = Code::from_u64(code).ok_or(Error::UnsupportedCode)?;
+
+		Self::new(code, bytes)
+	}
+
+	/// Returns the binary encoding of this multihash.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut out = Vec::with_capacity(4 + self.len);
+		write_varint(self.code.to_u64(), &mut out);
+		write_varint(self.len as u64, &mut out);
+		out.extend_from_slice(self.digest());
+		out
+	}
+
+	/// Parses a multihash from a multibase string in any supported encoding.
+	pub fn from_multibase(text: &str) -> Result<Self, Error> {
+		let (_, bytes) = multibase::decode(text).map_err(|_| Error::InvalidEncoding)?;
+		Self::from_bytes(&bytes)
+	}
+
+	/// Returns the binary encoding of this multihash as a multibase string.
+	/// With [`Base::Base58Btc`] and SHA-256, this is an IPFS version 0
+	/// content identifier after the `z` prefix.
+	pub fn to_multibase(&self, base: Base) -> String {
+		multibase::encode(base, &self.to_bytes())
+	}
+}
+
+// human-readable formats get a base58btc multibase string, though any
+// multibase is accepted back, and other formats get the binary encoding
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Multihash {
+	fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		if serializer.is_human_readable() {
+			serializer.serialize_str(&self.to_multibase(Base::Base58Btc))
+		} else {
+			serializer.serialize_bytes(&self.to_bytes())
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Multihash {
+	fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct MultihashVisitor;
+
+		impl<'de> ::serde::de::Visitor<'de> for MultihashVisitor {
+			type Value = Multihash;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a multihash, as bytes or a multibase string")
+			}
+
+			fn visit_str<E: ::serde::de::Error>(self, text: &str) -> Result<Multihash, E> {
+				Multihash::from_multibase(text).map_err(E::custom)
+			}
+
+			fn visit_bytes<E: ::serde::de::Error>(self, bytes: &[u8]) -> Result<Multihash, E> {
+				Multihash::from_bytes(bytes).map_err(E::custom)
+			}
+
+			fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Multihash, A::Error> {
+				let mut bytes = Vec::new();
+
+				while let Some(byte) = seq.next_element()? {
+					bytes.push(byte);
+				}
+
+				Multihash::from_bytes(&bytes).map_err(::serde::de::Error::custom)
+			}
+		}
+
+		if deserializer.is_human_readable() {
+			deserializer.deserialize_str(MultihashVisitor)
+		} else {
+			deserializer.deserialize_bytes(MultihashVisitor)
+		}
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex_vec;
+
+// checked against python's hashlib, prefixed with the codes from the multihash
+// table, and a python base58btc encoder
+#[test]
+fn test_vectors() {
+	let multihash = Multihash::hash(Code::Sha2_256, b"multihash");
+	assert_eq!(multihash.to_bytes(), hex_vec("12209cbc07c3f991725836a3aa2a581ca2029198aa420b9d99bc0e131d9f3e2cbe47"));
+	assert_eq!(Multihash::from_bytes(&multihash.to_bytes()), Ok(multihash));
+	assert_eq!(Multihash::from_multibase("zQmYtUc4iTCbbfVSDNKvtQqrfyezPPnFvE33wFmutw9PBBk"), Ok(multihash));
+
+	let multihash = Multihash::hash(Code::Sha3_256, b"abc");
+	assert_eq!(multihash.to_bytes(), hex_vec("16203a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"));
+
+	// codes above 0x7f take more than one byte
+	let multihash = Multihash::hash(Code::Blake2b256, b"abc");
+	let bytes = multihash.to_bytes();
+	assert_eq!(bytes[.. 4], [0xa0, 0xe4, 0x02, 0x20]);
+	assert_eq!(bytes[4 ..], crate::blake2::blake2b::<32>(b"abc"));
+	assert_eq!(Multihash::from_bytes(&bytes), Ok(multihash));
+}
+
+#[test]
+fn test_codes() {
+	let codes = [
+		Code::Sha2_256,
+		Code::Sha2_384,
+		Code::Sha2_512,
+		Code::Sha3_256,
+		Code::Sha3_512,
+		Code::Blake2b256,
+		Code::Blake2b512,
+		Code::Blake2s256,
+		Code::Blake3,
+		Code::Sm3_256,
+	];
+
+	for code in codes {
+		assert_eq!(Code::from_u64(code.to_u64()), Some(code));
+
+		let multihash = Multihash::hash(code, b"data");
+		assert_eq!(multihash.digest().len(), code.digest_len());
+		assert!(multihash.verify(b"data"));
+		assert!(!multihash.verify(b"date"));
+		assert_eq!(Multihash::from_multibase(&multihash.to_multibase(Base::Base32)), Ok(multihash));
+	}
+}
+
+#[test]
+fn test_truncated_and_invalid() {
+	let full = Multihash::hash(Code::Sha2_512, b"data");
+	let truncated = Multihash::new(Code::Sha2_512, &full.digest()[.. 20]).unwrap();
+	assert!(truncated.verify(b"data"));
+	assert_eq!(Multihash::from_bytes(&truncated.to_bytes()), Ok(truncated));
+
+	assert_eq!(Multihash::new(Code::Sha2_256, &[0; 33]), Err(Error::InvalidLength));
+	assert_eq!(Multihash::new(Code::Sha2_256, &[]), Err(Error::InvalidLength));
+
+	let bytes = full.to_bytes();
+	assert_eq!(Multihash::from_bytes(&bytes[.. 10]), Err(Error::InvalidEncoding));
+	assert_eq!(Multihash::from_bytes(&[bytes.clone(), std::vec![0]].concat()), Err(Error::InvalidEncoding));
+	assert_eq!(Multihash::from_bytes(&[0x11, 0x01, 0x00]), Err(Error::UnsupportedCode));
+
+	// 0x12 encoded in two bytes isn't minimal
+	assert_eq!(Multihash::from_bytes(&[0x92, 0x00, 0x01, 0x00]), Err(Error::InvalidEncoding));
+	assert_eq!(Multihash::from_bytes(&[0xff; 10]), Err(Error::InvalidEncoding));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+	use serde_test::{assert_de_tokens, assert_tokens, Configure, Token};
+
+	let multihash = Multihash::hash(Code::Sha3_256, b"abc");
+
+	assert_tokens(&multihash.readable(), &[Token::Str("zW1dPidZ6r5gZPoADdz6TDXv967KaD93Y9LEtYS9QLo8m7F")]);
+	assert_tokens(&multihash.compact(), &[Token::Bytes(&[
+		0x16, 0x20, 0x3a, 0x98, 0x5d, 0xa7, 0x4f, 0xe2, 0x25, 0xb2, 0x04, 0x5c, 0x17, 0x2d, 0x6b, 0xd3, 0x90,
+		0xbd, 0x85, 0x5f, 0x08, 0x6e, 0x3e, 0x9d, 0x52, 0x5b, 0x46, 0xbf, 0xe2, 0x45, 0x11, 0x43, 0x15, 0x32,
+	])]);
+	assert_de_tokens(&multihash.readable(), &[Token::Str("f16203a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532")]);
+}
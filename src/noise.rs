@@ -0,0 +1,1024 @@
+//! Implemented according to [revision 34 of the Noise Protocol Framework](https://noiseprotocol.org/noise.html).
+//! Noise handshakes authenticate the parties with X25519 keys and establish
+//! a pair of ChaCha20-Poly1305 keys for encrypting the rest of the session,
+//! using the `25519` and `ChaChaPoly` functions with either `SHA256` or
+//! `BLAKE2b`.
+//!
+//! A [`Builder`] creates a [`HandshakeState`] for either side of the
+//! handshake. Once every handshake message has been written and read, it
+//! turns into a [`TransportState`] for exchanging encrypted messages.
+//!
+//! After any error the handshake must be abandoned, since its state is no
+//! longer consistent with the other party's.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blake2::Blake2b;
+use crate::chacha20poly1305::{chacha20poly1305_decrypt, chacha20poly1305_encrypt};
+use crate::hmac::{HmacBlake2b, HmacSha256};
+use crate::sha2::Sha256;
+use crate::x25519::{PublicKey, StaticSecret};
+use crate::zeroize::zeroize;
+
+/// The maximum length of a Noise message, including its tag and any public
+/// keys.
+pub const MAX_MESSAGE_LEN: usize = 65535;
+
+const TAG_LEN: usize = 16;
+
+/// An error returned during a Noise handshake or session.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A static key or pre-shared key required by the handshake pattern was
+	/// not given to the [`Builder`].
+	MissingKey,
+	/// A message was written or read out of turn.
+	WrongTurn,
+	/// A handshake message was written or read after the handshake finished.
+	HandshakeFinished,
+	/// The handshake was turned into a transport state before it finished.
+	HandshakeNotFinished,
+	/// The message would be longer than [`MAX_MESSAGE_LEN`].
+	MessageTooLong,
+	/// The message is too short to contain the public keys and tags the
+	/// handshake pattern calls for.
+	InvalidMessage,
+	/// The message was modified, or the other party used different keys.
+	DecryptionFailed,
+	/// A public key from the other party is a small-order point.
+	InvalidPublicKey,
+	/// The maximum number of messages has been sent or received with a key.
+	NonceExhausted,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::MissingKey => "key required by noise handshake pattern is missing",
+			Error::WrongTurn => "noise message written or read out of turn",
+			Error::HandshakeFinished => "noise handshake already finished",
+			Error::HandshakeNotFinished => "noise handshake not yet finished",
+			Error::MessageTooLong => "noise message too long",
+			Error::InvalidMessage => "invalid noise message",
+			Error::DecryptionFailed => "failed to decrypt noise message",
+			Error::InvalidPublicKey => "invalid noise public key",
+			Error::NonceExhausted => "noise nonce exhausted",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The hash function used by a Noise handshake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hash {
+	/// `SHA256`, with 32-byte hashes.
+	Sha256,
+	/// `BLAKE2b`, with 64-byte hashes.
+	Blake2b,
+}
+
+impl Hash {
+	fn name(self) -> &'static str {
+		match self {
+			Hash::Sha256 => "SHA256",
+			Hash::Blake2b => "BLAKE2b",
+		}
+	}
+
+	fn len(self) -> usize {
+		match self {
+			Hash::Sha256 => 32,
+			Hash::Blake2b => 64,
+		}
+	}
+
+	// outputs are always 64 bytes, with only the first len() of them used
+	fn hash(self, parts: &[&[u8]]) -> [u8; 64] {
+		let mut out = [0; 64];
+
+		match self {
+			Hash::Sha256 => {
+				let mut hasher = Sha256::new();
+
+				for part in parts {
+					hasher.add_bytes(part);
+				}
+
+				out[0 .. 32].copy_from_slice(&hasher.out());
+			},
+			Hash::Blake2b => {
+				let mut hasher = Blake2b::<64>::new();
+
+				for part in parts {
+					hasher.add_bytes(part);
+				}
+
+				out = hasher.out();
+			},
+		}
+
+		out
+	}
+
+	fn hmac(self, key: &[u8], parts: &[&[u8]]) -> [u8; 64] {
+		let mut out = [0; 64];
+
+		match self {
+			Hash::Sha256 => {
+				let mut hmac = HmacSha256::new(key);
+
+				for part in parts {
+					hmac.add_bytes(part);
+				}
+
+				out[0 .. 32].copy_from_slice(&hmac.out());
+			},
+			Hash::Blake2b => {
+				let mut hmac = HmacBlake2b::new(key);
+
+				for part in parts {
+					hmac.add_bytes(part);
+				}
+
+				out = hmac.out();
+			},
+		}
+
+		out
+	}
+
+	// the three outputs of the noise HKDF function
+	fn hkdf(self, chaining_key: &[u8], ikm: &[u8]) -> ([u8; 64], [u8; 64], [u8; 64]) {
+		let len = self.len();
+
+		let mut temp_key = self.hmac(chaining_key, &[ikm]);
+		let out_1 = self.hmac(&temp_key[.. len], &[&[0x01]]);
+		let out_2 = self.hmac(&temp_key[.. len], &[&out_1[.. len], &[0x02]]);
+		let out_3 = self.hmac(&temp_key[.. len], &[&out_2[.. len], &[0x03]]);
+
+		zeroize(&mut temp_key);
+
+		(out_1, out_2, out_3)
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Token {
+	E,
+	S,
+	Ee,
+	Es,
+	Se,
+	Ss,
+	Psk,
+}
+
+/// A Noise handshake pattern, determining which keys are exchanged and how
+/// each party is authenticated.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HandshakePattern {
+	/// Neither party is authenticated.
+	NN,
+	/// The responder's static key is known to the initiator in advance, and
+	/// the initiator is not authenticated.
+	NK,
+	/// Both parties transmit their static keys during the handshake.
+	XX,
+	/// The responder's static key is known to the initiator in advance, and
+	/// the initiator sends its static key in the first message.
+	IK,
+	/// Neither party has a static key, and both are authenticated by a
+	/// pre-shared key.
+	NNpsk0,
+}
+
+impl HandshakePattern {
+	fn name(self) -> &'static str {
+		match self {
+			HandshakePattern::NN => "NN",
+			HandshakePattern::NK => "NK",
+			HandshakePattern::XX => "XX",
+			HandshakePattern::IK => "IK",
+			HandshakePattern::NNpsk0 => "NNpsk0",
+		}
+	}
+
+	// whether the initiator knows the responder's static key in advance
+	fn responder_pre_message(self) -> bool {
+		matches!(self, HandshakePattern::NK | HandshakePattern::IK)
+	}
+
+	fn messages(self) -> &'static [&'static [Token]] {
+		use Token::*;
+
+		match self {
+			HandshakePattern::NN => &[&[E], &[E, Ee]],
+			HandshakePattern::NK => &[&[E, Es], &[E, Ee]],
+			HandshakePattern::XX => &[&[E], &[E, Ee, S, Es], &[S, Se]],
+			HandshakePattern::IK => &[&[E, Es, S, Ss], &[E, Ee, Se]],
+			HandshakePattern::NNpsk0 => &[&[Psk, E], &[E, Ee]],
+		}
+	}
+
+	fn has_psk(self) -> bool {
+		self.messages().iter().any(|message| message.contains(&Token::Psk))
+	}
+
+	fn sends_static(self, initiator: bool) -> bool {
+		let start = if initiator {0} else {1};
+
+		self.messages()
+			.iter()
+			.skip(start)
+			.step_by(2)
+			.any(|message| message.contains(&Token::S))
+	}
+}
+
+struct CipherState {
+	key: Option<[u8; 32]>,
+	nonce: u64,
+}
+
+impl CipherState {
+	fn empty() -> Self {
+		Self {key: None, nonce: 0}
+	}
+
+	fn with_key(key: &[u8]) -> Self {
+		Self {key: Some(key[0 .. 32].try_into().unwrap()), nonce: 0}
+	}
+
+	fn nonce_bytes(nonce: u64) -> [u8; 12] {
+		let mut out = [0; 12];
+		out[4 .. 12].copy_from_slice(&nonce.to_le_bytes());
+		out
+	}
+
+	// appends the encryption of plaintext to out
+	fn encrypt_with_ad(&mut self, associated_data: &[u8], plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+		let start = out.len();
+		out.extend_from_slice(plaintext);
+
+		if let Some(key) = self.key {
+			// the maximum nonce is reserved for rekeying
+			if self.nonce == u64::MAX {
+				out.truncate(start);
+				return Err(Error::NonceExhausted);
+			}
+
+			let tag = chacha20poly1305_encrypt(key, Self::nonce_bytes(self.nonce), associated_data, &mut out[start ..]);
+			out.extend_from_slice(&tag);
+			self.nonce += 1;
+		}
+
+		Ok(())
+	}
+
+	fn decrypt_with_ad(&mut self, associated_data: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+		let key = match self.key {
+			Some(key) => key,
+			None => return Ok(ciphertext.to_vec()),
+		};
+
+		if self.nonce == u64::MAX {
+			return Err(Error::NonceExhausted);
+		}
+
+		if ciphertext.len() < TAG_LEN {
+			return Err(Error::InvalidMessage);
+		}
+
+		let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+		let mut plaintext = ciphertext.to_vec();
+
+		chacha20poly1305_decrypt(key, Self::nonce_bytes(self.nonce), associated_data, &mut plaintext, tag.try_into().unwrap())
+			.map_err(|_| Error::DecryptionFailed)?;
+
+		self.nonce += 1;
+
+		Ok(plaintext)
+	}
+
+	fn rekey(&mut self) {
+		if let Some(key) = self.key.as_mut() {
+			let mut new_key = [0; 32];
+			chacha20poly1305_encrypt(*key, Self::nonce_bytes(u64::MAX), &[], &mut new_key);
+
+			*key = new_key;
+			zeroize(&mut new_key);
+		}
+	}
+}
+
+impl Drop for CipherState {
+	fn drop(&mut self) {
+		if let Some(key) = self.key.as_mut() {
+			zeroize(key);
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for CipherState {
+	fn zeroize(&mut self) {
+		if let Some(key) = self.key.as_mut() {
+			zeroize(key);
+		}
+	}
+}
+
+struct SymmetricState {
+	hash: Hash,
+	cipher: CipherState,
+	chaining_key: [u8; 64],
+	handshake_hash: [u8; 64],
+}
+
+impl SymmetricState {
+	fn new(hash: Hash, protocol_name: &[u8]) -> Self {
+		let handshake_hash = if protocol_name.len() <= hash.len() {
+			let mut padded = [0; 64];
+			padded[0 .. protocol_name.len()].copy_from_slice(protocol_name);
+			padded
+		} else {
+			hash.hash(&[protocol_name])
+		};
+
+		Self {
+			hash,
+			cipher: CipherState::empty(),
+			chaining_key: handshake_hash,
+			handshake_hash,
+		}
+	}
+
+	fn handshake_hash(&self) -> &[u8] {
+		&self.handshake_hash[.. self.hash.len()]
+	}
+
+	fn mix_key(&mut self, ikm: &[u8]) {
+		let (chaining_key, mut temp_key, _) = self.hash.hkdf(&self.chaining_key[.. self.hash.len()], ikm);
+
+		self.chaining_key = chaining_key;
+		self.cipher = CipherState::with_key(&temp_key);
+
+		zeroize(&mut temp_key);
+	}
+
+	fn mix_hash(&mut self, data: &[u8]) {
+		self.handshake_hash = self.hash.hash(&[self.handshake_hash(), data]);
+	}
+
+	fn mix_key_and_hash(&mut self, ikm: &[u8]) {
+		let (chaining_key, temp_hash, mut temp_key) = self.hash.hkdf(&self.chaining_key[.. self.hash.len()], ikm);
+
+		self.chaining_key = chaining_key;
+		self.mix_hash(&temp_hash[.. self.hash.len()]);
+		self.cipher = CipherState::with_key(&temp_key);
+
+		zeroize(&mut temp_key);
+	}
+
+	fn encrypt_and_hash(&mut self, plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+		let start = out.len();
+		let handshake_hash = self.handshake_hash;
+
+		self.cipher.encrypt_with_ad(&handshake_hash[.. self.hash.len()], plaintext, out)?;
+		self.mix_hash(&out[start ..]);
+
+		Ok(())
+	}
+
+	fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+		let handshake_hash = self.handshake_hash;
+
+		let plaintext = self.cipher.decrypt_with_ad(&handshake_hash[.. self.hash.len()], ciphertext)?;
+		self.mix_hash(ciphertext);
+
+		Ok(plaintext)
+	}
+
+	fn split(&self) -> (CipherState, CipherState) {
+		let (mut key_1, mut key_2, _) = self.hash.hkdf(&self.chaining_key[.. self.hash.len()], &[]);
+		let ciphers = (CipherState::with_key(&key_1), CipherState::with_key(&key_2));
+
+		zeroize(&mut key_1);
+		zeroize(&mut key_2);
+
+		ciphers
+	}
+}
+
+impl Drop for SymmetricState {
+	fn drop(&mut self) {
+		zeroize(&mut self.chaining_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SymmetricState {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.cipher);
+		zeroize(&mut self.chaining_key);
+		zeroize(&mut self.handshake_hash);
+	}
+}
+
+/// Builder for the [`HandshakeState`] of either party.
+pub struct Builder {
+	pattern: HandshakePattern,
+	hash: Hash,
+	prologue: Vec<u8>,
+	local_static: Option<StaticSecret>,
+	remote_static: Option<PublicKey>,
+	psk: Option<[u8; 32]>,
+}
+
+impl Builder {
+	/// Creates a builder for a handshake with the given pattern and hash
+	/// function.
+	pub fn new(pattern: HandshakePattern, hash: Hash) -> Self {
+		Self {
+			pattern,
+			hash,
+			prologue: Vec::new(),
+			local_static: None,
+			remote_static: None,
+			psk: None,
+		}
+	}
+
+	/// Sets the prologue, data that both parties must agree on for the
+	/// handshake to succeed, such as earlier negotiation messages.
+	pub fn prologue(mut self, prologue: &[u8]) -> Self {
+		self.prologue = prologue.to_vec();
+		self
+	}
+
+	/// Sets this party's static key, required by patterns where this party
+	/// sends a static key.
+	pub fn local_static(mut self, secret: StaticSecret) -> Self {
+		self.local_static = Some(secret);
+		self
+	}
+
+	/// Sets the other party's static key, required by the initiator for
+	/// patterns where it's known in advance.
+	pub fn remote_static(mut self, pub_key: PublicKey) -> Self {
+		self.remote_static = Some(pub_key);
+		self
+	}
+
+	/// Sets the pre-shared key, required by patterns with a `psk` modifier.
+	pub fn psk(mut self, psk: [u8; 32]) -> Self {
+		self.psk = Some(psk);
+		self
+	}
+
+	/// Builds the handshake state of the initiator, which writes the first
+	/// message. `ephemeral` is used as the ephemeral private key, so it must
+	/// be freshly generated by a secure random source for every handshake.
+	pub fn build_initiator(self, ephemeral: [u8; 32]) -> Result<HandshakeState, Error> {
+		self.build(true, ephemeral)
+	}
+
+	/// Builds the handshake state of the responder, which reads the first
+	/// message. `ephemeral` is used as the ephemeral private key, so it must
+	/// be freshly generated by a secure random source for every handshake.
+	pub fn build_responder(self, ephemeral: [u8; 32]) -> Result<HandshakeState, Error> {
+		self.build(false, ephemeral)
+	}
+
+	/// Like [`Builder::build_initiator`], but generates the ephemeral private
+	/// key with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn build_initiator_with_rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> Result<HandshakeState, Error> {
+		let mut ephemeral = [0; 32];
+		rng.fill_bytes(&mut ephemeral);
+
+		let result = self.build_initiator(ephemeral);
+		zeroize(&mut ephemeral);
+
+		result
+	}
+
+	/// Like [`Builder::build_responder`], but generates the ephemeral private
+	/// key with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn build_responder_with_rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> Result<HandshakeState, Error> {
+		let mut ephemeral = [0; 32];
+		rng.fill_bytes(&mut ephemeral);
+
+		let result = self.build_responder(ephemeral);
+		zeroize(&mut ephemeral);
+
+		result
+	}
+
+	fn build(mut self, initiator: bool, ephemeral: [u8; 32]) -> Result<HandshakeState, Error> {
+		let pattern = self.pattern;
+
+		let needs_local_static = pattern.sends_static(initiator) || (!initiator && pattern.responder_pre_message());
+		let needs_remote_static = initiator && pattern.responder_pre_message();
+
+		if (needs_local_static && self.local_static.is_none())
+			|| (needs_remote_static && self.remote_static.is_none())
+			|| (pattern.has_psk() && self.psk.is_none())
+		{
+			return Err(Error::MissingKey);
+		}
+
+		let protocol_name = format!("Noise_{}_25519_ChaChaPoly_{}", pattern.name(), self.hash.name());
+		let mut symmetric = SymmetricState::new(self.hash, protocol_name.as_bytes());
+		symmetric.mix_hash(&self.prologue);
+
+		if pattern.responder_pre_message() {
+			let responder_static = if initiator {
+				self.remote_static.unwrap()
+			} else {
+				PublicKey::from(self.local_static.as_ref().unwrap())
+			};
+
+			symmetric.mix_hash(responder_static.as_bytes());
+		}
+
+		Ok(HandshakeState {
+			symmetric,
+			pattern,
+			initiator,
+			local_static: self.local_static.take(),
+			local_ephemeral: StaticSecret::from_bytes(ephemeral),
+			remote_static: self.remote_static.take(),
+			remote_ephemeral: None,
+			psk: self.psk.take(),
+			message_index: 0,
+		})
+	}
+}
+
+impl Drop for Builder {
+	fn drop(&mut self) {
+		if let Some(psk) = self.psk.as_mut() {
+			zeroize(psk);
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Builder {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.local_static);
+
+		if let Some(psk) = self.psk.as_mut() {
+			zeroize(psk);
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Builder {}
+
+/// The state of one party during a Noise handshake.
+pub struct HandshakeState {
+	symmetric: SymmetricState,
+	pattern: HandshakePattern,
+	initiator: bool,
+	local_static: Option<StaticSecret>,
+	local_ephemeral: StaticSecret,
+	remote_static: Option<PublicKey>,
+	remote_ephemeral: Option<PublicKey>,
+	psk: Option<[u8; 32]>,
+	message_index: usize,
+}
+
+impl HandshakeState {
+	/// Returns whether all handshake messages have been written and read.
+	pub fn is_finished(&self) -> bool {
+		self.message_index == self.pattern.messages().len()
+	}
+
+	/// Returns whether the next handshake message is written by this party.
+	pub fn is_my_turn(&self) -> bool {
+		self.message_index.is_multiple_of(2) == self.initiator
+	}
+
+	/// Returns the other party's static key, once it's been received or if
+	/// it was known in advance.
+	pub fn remote_static(&self) -> Option<PublicKey> {
+		self.remote_static
+	}
+
+	/// Returns the handshake hash, which uniquely identifies the handshake
+	/// and can be used for channel binding once it has finished.
+	pub fn handshake_hash(&self) -> &[u8] {
+		self.symmetric.handshake_hash()
+	}
+
+	fn next_message(&self, writing: bool) -> Result<&'static [Token], Error> {
+		if self.is_finished() {
+			return Err(Error::HandshakeFinished);
+		}
+
+		if self.is_my_turn() != writing {
+			return Err(Error::WrongTurn);
+		}
+
+		Ok(self.pattern.messages()[self.message_index])
+	}
+
+	fn mix_dh(&mut self, token: Token) -> Result<(), Error> {
+		let remote_ephemeral = self.remote_ephemeral.as_ref();
+		let remote_static = self.remote_static.as_ref();
+		let local_static = self.local_static.as_ref();
+		let local_ephemeral = Some(&self.local_ephemeral);
+
+		// the first letter of the token names the initiator's key
+		let (local, remote) = match (token, self.initiator) {
+			(Token::Ee, _) => (local_ephemeral, remote_ephemeral),
+			(Token::Es, true) => (local_ephemeral, remote_static),
+			(Token::Es, false) => (local_static, remote_ephemeral),
+			(Token::Se, true) => (local_static, remote_ephemeral),
+			(Token::Se, false) => (local_ephemeral, remote_static),
+			(Token::Ss, _) => (local_static, remote_static),
+			_ => unreachable!(),
+		};
+
+		let shared_secret = local
+			.unwrap()
+			.diffie_hellman(remote.unwrap())
+			.map_err(|_| Error::InvalidPublicKey)?;
+
+		self.symmetric.mix_key(shared_secret.as_bytes());
+
+		Ok(())
+	}
+
+	/// Writes the next handshake message, carrying `payload`, which is
+	/// encrypted unless no keys have been exchanged yet.
+	pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+		let message = self.next_message(true)?;
+		let mut out = Vec::new();
+
+		for &token in message {
+			match token {
+				Token::E => {
+					let pub_key = PublicKey::from(&self.local_ephemeral);
+					out.extend_from_slice(pub_key.as_bytes());
+					self.symmetric.mix_hash(pub_key.as_bytes());
+
+					if self.pattern.has_psk() {
+						self.symmetric.mix_key(pub_key.as_bytes());
+					}
+				},
+				Token::S => {
+					let pub_key = PublicKey::from(self.local_static.as_ref().unwrap());
+					self.symmetric.encrypt_and_hash(pub_key.as_bytes(), &mut out)?;
+				},
+				Token::Psk => {
+					let psk = self.psk.unwrap();
+					self.symmetric.mix_key_and_hash(&psk);
+				},
+				_ => self.mix_dh(token)?,
+			}
+		}
+
+		self.symmetric.encrypt_and_hash(payload, &mut out)?;
+
+		if out.len() > MAX_MESSAGE_LEN {
+			return Err(Error::MessageTooLong);
+		}
+
+		self.message_index += 1;
+
+		Ok(out)
+	}
+
+	/// Reads the next handshake message, returning its payload.
+	pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+		let tokens = self.next_message(false)?;
+
+		if message.len() > MAX_MESSAGE_LEN {
+			return Err(Error::MessageTooLong);
+		}
+
+		let mut rest = message;
+
+		for &token in tokens {
+			match token {
+				Token::E => {
+					if rest.len() < 32 {
+						return Err(Error::InvalidMessage);
+					}
+
+					let pub_key = PublicKey::from_bytes(rest[0 .. 32].try_into().unwrap());
+					rest = &rest[32 ..];
+
+					self.remote_ephemeral = Some(pub_key);
+					self.symmetric.mix_hash(pub_key.as_bytes());
+
+					if self.pattern.has_psk() {
+						self.symmetric.mix_key(pub_key.as_bytes());
+					}
+				},
+				Token::S => {
+					let len = if self.symmetric.cipher.key.is_some() {32 + TAG_LEN} else {32};
+
+					if rest.len() < len {
+						return Err(Error::InvalidMessage);
+					}
+
+					let pub_key = self.symmetric.decrypt_and_hash(&rest[0 .. len])?;
+					rest = &rest[len ..];
+
+					self.remote_static = Some(PublicKey::from_bytes(pub_key[..].try_into().unwrap()));
+				},
+				Token::Psk => {
+					let psk = self.psk.unwrap();
+					self.symmetric.mix_key_and_hash(&psk);
+				},
+				_ => self.mix_dh(token)?,
+			}
+		}
+
+		let payload = self.symmetric.decrypt_and_hash(rest)?;
+		self.message_index += 1;
+
+		Ok(payload)
+	}
+
+	/// Turns a finished handshake into the transport state used to exchange
+	/// the rest of the session's messages.
+	pub fn into_transport(self) -> Result<TransportState, Error> {
+		if !self.is_finished() {
+			return Err(Error::HandshakeNotFinished);
+		}
+
+		let (initiator_cipher, responder_cipher) = self.symmetric.split();
+
+		let (send, receive) = if self.initiator {
+			(initiator_cipher, responder_cipher)
+		} else {
+			(responder_cipher, initiator_cipher)
+		};
+
+		Ok(TransportState {
+			send,
+			receive,
+			hash: self.symmetric.hash,
+			handshake_hash: self.symmetric.handshake_hash,
+			remote_static: self.remote_static,
+		})
+	}
+}
+
+impl Drop for HandshakeState {
+	fn drop(&mut self) {
+		if let Some(psk) = self.psk.as_mut() {
+			zeroize(psk);
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for HandshakeState {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.symmetric);
+		::zeroize::Zeroize::zeroize(&mut self.local_static);
+		::zeroize::Zeroize::zeroize(&mut self.local_ephemeral);
+
+		if let Some(psk) = self.psk.as_mut() {
+			zeroize(psk);
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for HandshakeState {}
+
+// deliberately doesn't print any keys
+impl fmt::Debug for HandshakeState {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("HandshakeState")
+			.field("pattern", &self.pattern)
+			.field("initiator", &self.initiator)
+			.field("message_index", &self.message_index)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The state of one party after a Noise handshake, holding a key for each
+/// direction. Messages must be read in the order they were written.
+pub struct TransportState {
+	send: CipherState,
+	receive: CipherState,
+	hash: Hash,
+	handshake_hash: [u8; 64],
+	remote_static: Option<PublicKey>,
+}
+
+impl TransportState {
+	/// Encrypts a message to the other party.
+	pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+		if payload.len() > MAX_MESSAGE_LEN - TAG_LEN {
+			return Err(Error::MessageTooLong);
+		}
+
+		let mut out = Vec::with_capacity(payload.len() + TAG_LEN);
+		self.send.encrypt_with_ad(&[], payload, &mut out)?;
+
+		Ok(out)
+	}
+
+	/// Decrypts a message from the other party.
+	pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+		if message.len() > MAX_MESSAGE_LEN {
+			return Err(Error::MessageTooLong);
+		}
+
+		self.receive.decrypt_with_ad(&[], message)
+	}
+
+	/// Replaces the key for sent messages with one derived from it. The
+	/// other party must call [`TransportState::rekey_incoming`] at the same
+	/// point in the session.
+	pub fn rekey_outgoing(&mut self) {
+		self.send.rekey();
+	}
+
+	/// Replaces the key for received messages with one derived from it, to
+	/// match a call to [`TransportState::rekey_outgoing`] by the other party.
+	pub fn rekey_incoming(&mut self) {
+		self.receive.rekey();
+	}
+
+	/// Returns the other party's static key, if the handshake pattern
+	/// included one.
+	pub fn remote_static(&self) -> Option<PublicKey> {
+		self.remote_static
+	}
+
+	/// Returns the hash of the completed handshake.
+	pub fn handshake_hash(&self) -> &[u8] {
+		&self.handshake_hash[.. self.hash.len()]
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for TransportState {
+	fn zeroize(&mut self) {
+		::zeroize::Zeroize::zeroize(&mut self.send);
+		::zeroize::Zeroize::zeroize(&mut self.receive);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for TransportState {}
+
+// deliberately doesn't print any keys
+impl fmt::Debug for TransportState {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TransportState").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex_vec;
+
+#[cfg(test)]
+fn handshake(initiator: Builder, responder: Builder) -> Result<(TransportState, TransportState), Error> {
+	let mut initiator = initiator.prologue(b"libkrypton").build_initiator([0x11; 32])?;
+	let mut responder = responder.prologue(b"libkrypton").build_responder([0x22; 32])?;
+
+	let mut turn = 0;
+
+	while !initiator.is_finished() {
+		let (writer, reader) = if initiator.is_my_turn() {
+			(&mut initiator, &mut responder)
+		} else {
+			(&mut responder, &mut initiator)
+		};
+
+		let payload = format!("payload {}", turn);
+		let message = writer.write_message(payload.as_bytes())?;
+		assert_eq!(reader.read_message(&message)?, payload.as_bytes());
+		turn += 1;
+	}
+
+	assert!(responder.is_finished());
+	assert_eq!(initiator.handshake_hash(), responder.handshake_hash());
+
+	Ok((initiator.into_transport()?, responder.into_transport()?))
+}
+
+// computed with a direct Python transcription of sections 5 and 7 of revision 34
+// of the Noise specification, using X25519 and ChaCha20-Poly1305 from the
+// cryptography package and SHA-256, BLAKE2b and HMAC from the standard library
+#[test]
+fn test_known_answers() {
+	let initiator_static = || StaticSecret::from_bytes([0x33; 32]);
+	let responder_static = || StaticSecret::from_bytes([0x44; 32]);
+	let responder_pub_key = PublicKey::from(&responder_static());
+
+	let vectors = [
+		(
+			Builder::new(HandshakePattern::XX, Hash::Sha256).local_static(initiator_static()),
+			Builder::new(HandshakePattern::XX, Hash::Sha256).local_static(responder_static()),
+			"a989c94ef98d9e47ddfc216b2602ead6e5949b51da0c0c5b542bf209ad74669c",
+			"489ad719ed1d7ccb062f047769d2b86214daaca08e",
+			"36cbb5dede9c08425cac8337c7fc3e302dbca4206f",
+		),
+		(
+			Builder::new(HandshakePattern::IK, Hash::Blake2b)
+				.local_static(initiator_static())
+				.remote_static(responder_pub_key),
+			Builder::new(HandshakePattern::IK, Hash::Blake2b).local_static(responder_static()),
+			"ecc555d8a68320025055f6cd7add089acc2eaac3ab0d5fb8a00673d710e08bd9\
+			06269de27fe054a7b94425389614cfc29e038e72f75c263ba11fc2fedd8beb10",
+			"9d68af8ea5ce6ce921924ce10e9258326cbc7d559c",
+			"3a9d269b3a7cbe39994637fb2e6e9cdb2124709540",
+		),
+		(
+			Builder::new(HandshakePattern::NK, Hash::Sha256).remote_static(responder_pub_key),
+			Builder::new(HandshakePattern::NK, Hash::Sha256).local_static(responder_static()),
+			"4d3a8bb0534744f09b33ce8b0227a39dad34a22995bd3a9cf2479f37a4037629",
+			"c326600c94433ff909bb402602740d19050bf3908a",
+			"a90f0c3352ef6650234bc43fd147700022fbfc6c51",
+		),
+		(
+			Builder::new(HandshakePattern::NNpsk0, Hash::Sha256).psk([0x55; 32]),
+			Builder::new(HandshakePattern::NNpsk0, Hash::Sha256).psk([0x55; 32]),
+			"c5c09f7b3bc50ac5882a85fe72489dd8af1e51763c7771050a97ef5919a833c9",
+			"9f3d6a096aea9fc635e421bcfc7a73d9ae909d1139",
+			"0299318d0f61590e21a7234098f81159d1154f129c",
+		),
+	];
+
+	for (initiator, responder, handshake_hash, expected_message, rekeyed_message) in vectors {
+		let (mut initiator, mut responder) = handshake(initiator, responder).unwrap();
+		assert_eq!(initiator.handshake_hash(), hex_vec(handshake_hash));
+
+		let message = initiator.write_message(b"hello").unwrap();
+		assert_eq!(message, hex_vec(expected_message));
+		assert_eq!(responder.read_message(&message).unwrap(), b"hello");
+
+		initiator.rekey_outgoing();
+		responder.rekey_incoming();
+
+		let message = initiator.write_message(b"hello").unwrap();
+		assert_eq!(message, hex_vec(rekeyed_message));
+		assert_eq!(responder.read_message(&message).unwrap(), b"hello");
+	}
+}
+
+#[test]
+fn test_transport() {
+	let initiator = Builder::new(HandshakePattern::XX, Hash::Blake2b).local_static(StaticSecret::from_bytes([0x33; 32]));
+	let responder = Builder::new(HandshakePattern::XX, Hash::Blake2b).local_static(StaticSecret::from_bytes([0x44; 32]));
+	let (mut initiator, mut responder) = handshake(initiator, responder).unwrap();
+
+	assert_eq!(initiator.remote_static(), Some(PublicKey::from(&StaticSecret::from_bytes([0x44; 32]))));
+	assert_eq!(responder.remote_static(), Some(PublicKey::from(&StaticSecret::from_bytes([0x33; 32]))));
+
+	for i in 0 .. 3u8 {
+		let message = responder.write_message(&[i; 10]).unwrap();
+		assert_eq!(initiator.read_message(&message).unwrap(), [i; 10]);
+	}
+
+	let mut message = initiator.write_message(b"hello").unwrap();
+	message[0] ^= 1;
+	assert_eq!(responder.read_message(&message), Err(Error::DecryptionFailed));
+
+	assert_eq!(initiator.write_message(&[0; MAX_MESSAGE_LEN]), Err(Error::MessageTooLong));
+}
+
+#[test]
+fn test_handshake_failures() {
+	let initiator = Builder::new(HandshakePattern::NNpsk0, Hash::Sha256).psk([0x55; 32]);
+	let responder = Builder::new(HandshakePattern::NNpsk0, Hash::Sha256).psk([0x56; 32]);
+	assert_eq!(handshake(initiator, responder).unwrap_err(), Error::DecryptionFailed);
+
+	let builder = Builder::new(HandshakePattern::IK, Hash::Sha256).local_static(StaticSecret::from_bytes([0x33; 32]));
+	assert_eq!(builder.build_initiator([0x11; 32]).unwrap_err(), Error::MissingKey);
+
+	let mut initiator = Builder::new(HandshakePattern::NN, Hash::Sha256).build_initiator([0x11; 32]).unwrap();
+	let mut responder = Builder::new(HandshakePattern::NN, Hash::Sha256).build_responder([0x22; 32]).unwrap();
+
+	assert_eq!(responder.write_message(b"").unwrap_err(), Error::WrongTurn);
+
+	let message = initiator.write_message(b"").unwrap();
+	assert_eq!(responder.read_message(&message[0 .. 31]).unwrap_err(), Error::InvalidMessage);
+	assert_eq!(initiator.into_transport().unwrap_err(), Error::HandshakeNotFinished);
+}
@@ -0,0 +1,355 @@
+//! Generators of nonces for AEADs, which must never encrypt two messages
+//! with the same key and nonce. Reusing a nonce with ChaCha20-Poly1305
+//! reveals the XOR of the two plaintexts and lets anyone forge messages, so
+//! nonces are better generated by one of these than by hand:
+//!
+//! * [`CounterNonces`] gives 96-bit nonces for ChaCha20-Poly1305 from a
+//!   counter, which is reserved in batches through a [`CounterStore`] so that
+//!   a restarted process never goes back to a counter it may have used.
+//! * [`RandomNonces`] gives 192-bit nonces for XChaCha20-Poly1305 from the
+//!   operating system's random number generator, with the `getrandom`
+//!   feature. These are long enough that a repeat is never expected, and need
+//!   no state to be kept.
+//! * [`RepeatDetector`] wraps either of them, and panics if a nonce is ever
+//!   given out twice. It remembers every nonce, so it's meant for tests and
+//!   debug builds, to catch a generator that's been cloned or restored from a
+//!   stale store.
+//!
+//! ```
+//! use libkrypton::chacha20poly1305::chacha20poly1305_encrypt;
+//! use libkrypton::nonce::{CounterNonces, InMemory, NonceSource};
+//!
+//! let key = [0x42; 32];
+//! let mut nonces = CounterNonces::new([0; 4], InMemory).unwrap();
+//!
+//! for message in [&b"first"[..], b"second"] {
+//!     let mut buf = message.to_vec();
+//!     let nonce = nonces.next_nonce().unwrap();
+//!     let tag = chacha20poly1305_encrypt(key, nonce, b"", &mut buf);
+//! }
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "getrandom")]
+use crate::rand::SystemRandom;
+
+/// The number of counters reserved from a [`CounterStore`] at a time, unless
+/// [`CounterNonces::with_batch`] is used.
+pub const DEFAULT_BATCH: u64 = 1 << 16;
+
+/// An error returned when a nonce can't be generated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// Every nonce has been used, so the key must be replaced.
+	Exhausted,
+	/// A [`CounterStore`] failed to load or save the counter.
+	StoreFailed,
+	/// The operating system's random number generator failed.
+	RandomUnavailable,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::Exhausted => "nonces exhausted",
+			Error::StoreFailed => "failed to load or save nonce counter",
+			Error::RandomUnavailable => "operating system random number generator is unavailable",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Something that gives out nonces which are never repeated.
+pub trait NonceSource {
+	/// The nonce, such as `[u8; 12]` for ChaCha20-Poly1305.
+	type Nonce;
+
+	/// Returns the next nonce.
+	fn next_nonce(&mut self) -> Result<Self::Nonce, Error>;
+}
+
+/// Durable storage of how far a [`CounterNonces`] has counted, such as a
+/// file next to the key, or a column in the database row holding it.
+pub trait CounterStore {
+	/// Returns the limit last passed to [`reserve`](Self::reserve), or 0 if
+	/// it never has been for this key.
+	fn load(&mut self) -> Result<u64, Error>;
+
+	/// Records that counters below `limit` may have been used. This must be
+	/// durable, such as by syncing a file, before it returns, since the
+	/// counters are used as soon as it does. Failures should be returned as
+	/// [`Error::StoreFailed`].
+	fn reserve(&mut self, limit: u64) -> Result<(), Error>;
+}
+
+/// A [`CounterStore`] that stores nothing, for keys that are only ever held
+/// in memory and are never used again once the process exits.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InMemory;
+
+impl CounterStore for InMemory {
+	fn load(&mut self) -> Result<u64, Error> {
+		Ok(0)
+	}
+
+	fn reserve(&mut self, _limit: u64) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+/// Gives 96-bit nonces made of a fixed 4-byte prefix followed by a 64-bit
+/// big-endian counter. The prefix can tell apart senders sharing a key,
+/// such as the two directions of a connection.
+///
+/// Counters are reserved from the store a batch at a time, so it's written
+/// once per batch rather than once per nonce. If the process stops partway
+/// through a batch, the rest of it is skipped after a restart.
+pub struct CounterNonces<S: CounterStore> {
+	prefix: [u8; 4],
+	next: u64,
+	reserved: u64,
+	batch: u64,
+	store: S,
+}
+
+impl<S: CounterStore> CounterNonces<S> {
+	/// Loads the counter from `store`, reserving [`DEFAULT_BATCH`] counters
+	/// at a time.
+	pub fn new(prefix: [u8; 4], store: S) -> Result<Self, Error> {
+		Self::with_batch(prefix, store, DEFAULT_BATCH)
+	}
+
+	/// Loads the counter from `store`, reserving `batch` counters at a time.
+	///
+	/// # Panics
+	/// * Panics if `batch` is 0.
+	pub fn with_batch(prefix: [u8; 4], mut store: S, batch: u64) -> Result<Self, Error> {
+		assert!(batch != 0, "batch must not be zero");
+
+		let next = store.load()?;
+		Ok(Self {prefix, next, reserved: next, batch, store})
+	}
+
+	/// Returns the store, such as to close it.
+	pub fn into_store(self) -> S {
+		self.store
+	}
+}
+
+impl<S: CounterStore> NonceSource for CounterNonces<S> {
+	type Nonce = [u8; 12];
+
+	fn next_nonce(&mut self) -> Result<[u8; 12], Error> {
+		// the last counter is never used, so the limit can always be stored
+		if self.next == u64::MAX {
+			return Err(Error::Exhausted);
+		}
+
+		if self.next == self.reserved {
+			let limit = self.reserved.saturating_add(self.batch);
+			self.store.reserve(limit)?;
+			self.reserved = limit;
+		}
+
+		let mut nonce = [0; 12];
+		nonce[.. 4].copy_from_slice(&self.prefix);
+		nonce[4 ..].copy_from_slice(&self.next.to_be_bytes());
+
+		self.next += 1;
+		Ok(nonce)
+	}
+}
+
+impl<S: CounterStore> fmt::Debug for CounterNonces<S> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CounterNonces")
+			.field("prefix", &self.prefix)
+			.field("next", &self.next)
+			.field("reserved", &self.reserved)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Gives random 192-bit nonces for XChaCha20-Poly1305, from the operating
+/// system's random number generator.
+#[cfg(feature = "getrandom")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RandomNonces {
+	rng: SystemRandom,
+}
+
+#[cfg(feature = "getrandom")]
+impl RandomNonces {
+	pub fn new() -> Self {
+		Self {rng: SystemRandom::new()}
+	}
+}
+
+#[cfg(feature = "getrandom")]
+impl NonceSource for RandomNonces {
+	type Nonce = [u8; 24];
+
+	fn next_nonce(&mut self) -> Result<[u8; 24], Error> {
+		let mut nonce = [0; 24];
+		self.rng.fill(&mut nonce).map_err(|_| Error::RandomUnavailable)?;
+		Ok(nonce)
+	}
+}
+
+/// Wraps a [`NonceSource`], remembering every nonce it gives out and
+/// panicking if one comes up again. Nonces from elsewhere, such as ones
+/// received from a peer, can be added with [`check`](Self::check).
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct RepeatDetector<G: NonceSource> {
+	source: G,
+	seen: std::collections::HashSet<G::Nonce>,
+}
+
+#[cfg(feature = "std")]
+impl<G: NonceSource> RepeatDetector<G>
+where
+	G::Nonce: Copy + Eq + core::hash::Hash,
+{
+	pub fn new(source: G) -> Self {
+		Self {source, seen: std::collections::HashSet::new()}
+	}
+
+	/// Remembers `nonce`, like one given out by the wrapped source.
+	///
+	/// # Panics
+	/// * Panics if `nonce` has been seen before.
+	pub fn check(&mut self, nonce: G::Nonce) {
+		assert!(self.seen.insert(nonce), "nonce reused");
+	}
+
+	/// Returns the wrapped source.
+	pub fn into_inner(self) -> G {
+		self.source
+	}
+}
+
+/// # Panics
+/// * Panics if the wrapped source gives out a nonce that has been seen
+///   before.
+#[cfg(feature = "std")]
+impl<G: NonceSource> NonceSource for RepeatDetector<G>
+where
+	G::Nonce: Copy + Eq + core::hash::Hash,
+{
+	type Nonce = G::Nonce;
+
+	fn next_nonce(&mut self) -> Result<G::Nonce, Error> {
+		let nonce = self.source.next_nonce()?;
+		self.check(nonce);
+		Ok(nonce)
+	}
+}
+
+// stores the limit in memory, like a file that survives restarts
+#[cfg(all(test, feature = "std"))]
+#[derive(Default)]
+struct TestStore {
+	limit: std::rc::Rc<core::cell::Cell<u64>>,
+	writes: std::rc::Rc<core::cell::Cell<u32>>,
+}
+
+#[cfg(all(test, feature = "std"))]
+impl CounterStore for TestStore {
+	fn load(&mut self) -> Result<u64, Error> {
+		Ok(self.limit.get())
+	}
+
+	fn reserve(&mut self, limit: u64) -> Result<(), Error> {
+		self.limit.set(limit);
+		self.writes.set(self.writes.get() + 1);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_counter_nonces() {
+	let store = TestStore::default();
+	let (limit, writes) = (store.limit.clone(), store.writes.clone());
+	let mut nonces = CounterNonces::with_batch([1, 2, 3, 4], store, 10).unwrap();
+
+	assert_eq!(nonces.next_nonce(), Ok([1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0]));
+	assert_eq!(nonces.next_nonce(), Ok([1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]));
+	assert_eq!((limit.get(), writes.get()), (10, 1));
+
+	for _ in 2 .. 11 {
+		nonces.next_nonce().unwrap();
+	}
+
+	assert_eq!((limit.get(), writes.get()), (20, 2));
+
+	// after a restart, the rest of the batch is skipped
+	let mut nonces = CounterNonces::with_batch([1, 2, 3, 4], nonces.into_store(), 10).unwrap();
+	assert_eq!(nonces.next_nonce(), Ok([1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 20]));
+	assert_eq!((limit.get(), writes.get()), (30, 3));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_counter_nonces_exhausted() {
+	let store = TestStore::default();
+	store.limit.set(u64::MAX - 2);
+	let mut nonces = CounterNonces::new([0; 4], store).unwrap();
+
+	assert_eq!(nonces.next_nonce(), Ok([0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfd]));
+	assert_eq!(nonces.next_nonce(), Ok([0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]));
+	assert_eq!(nonces.next_nonce(), Err(Error::Exhausted));
+	assert_eq!(nonces.into_store().limit.get(), u64::MAX);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_counter_nonces_store_failure() {
+	struct FailingStore;
+
+	impl CounterStore for FailingStore {
+		fn load(&mut self) -> Result<u64, Error> {
+			Ok(0)
+		}
+
+		fn reserve(&mut self, _limit: u64) -> Result<(), Error> {
+			Err(Error::StoreFailed)
+		}
+	}
+
+	let mut nonces = CounterNonces::new([0; 4], FailingStore).unwrap();
+	assert_eq!(nonces.next_nonce(), Err(Error::StoreFailed));
+	assert_eq!(nonces.next_nonce(), Err(Error::StoreFailed));
+}
+
+#[cfg(feature = "getrandom")]
+#[test]
+fn test_random_nonces() {
+	let mut nonces = RandomNonces::new();
+	assert_ne!(nonces.next_nonce().unwrap(), nonces.next_nonce().unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_repeat_detector() {
+	let mut nonces = RepeatDetector::new(CounterNonces::new([0; 4], InMemory).unwrap());
+
+	for _ in 0 .. 100 {
+		nonces.next_nonce().unwrap();
+	}
+
+	nonces.check([9; 12]);
+
+	// a second generator over the same key starts from the same counter
+	let mut stale = CounterNonces::new([0; 4], InMemory).unwrap();
+	let nonce = stale.next_nonce().unwrap();
+
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| nonces.check(nonce)));
+	assert!(result.is_err());
+}
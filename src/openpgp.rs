@@ -0,0 +1,1009 @@
+//! Just enough of OpenPGP, implemented according to
+//! [IETF RFC 9580](https://datatracker.ietf.org/doc/html/rfc9580), to create
+//! and verify detached Ed25519 signatures, such as the `.sig` and `.asc` files
+//! made by `gpg --detach-sign`, and to compute key fingerprints.
+//!
+//! Both v4 keys, which GnuPG makes, and v6 keys from RFC 9580 are supported.
+//! v4 keys may use either the legacy EdDSA algorithm that GnuPG uses, or the
+//! newer Ed25519 one, and are written with the legacy algorithm so that older
+//! implementations can read them. Only the primary key of a key block is
+//! read, and user IDs, subkeys and certifications are ignored, so it's up to
+//! the caller to decide whether a key is trusted, for example by checking its
+//! fingerprint.
+//!
+//! ```
+//! use libkrypton::ed25519::SigningKey;
+//! use libkrypton::openpgp::{PublicKey, Signature, Version};
+//!
+//! // the key should be generated by a secure random source
+//! let signing_key = SigningKey::from_bytes([0x42; 32]);
+//! let public_key = PublicKey::new(Version::V6, signing_key.verifying_key(), 1700000000);
+//!
+//! // the salt should be freshly generated by a secure random source
+//! let signature = Signature::sign_detached(&signing_key, &public_key, b"data", 1700000001, [0x11; 16]);
+//! let armored = signature.to_armored();
+//!
+//! let parsed = Signature::from_armored(&armored).unwrap();
+//! assert!(parsed.verify_detached(&public_key, b"data"));
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::base64;
+use crate::ed25519::{self, SigningKey, VerifyingKey};
+use crate::sha1::Sha1;
+use crate::sha2::{Sha224, Sha256, Sha384, Sha512};
+
+const SIGNATURE_TAG: u8 = 2;
+const PUBLIC_KEY_TAG: u8 = 6;
+
+const EDDSA_LEGACY: u8 = 22;
+const ED25519: u8 = 27;
+
+// the curve OID of Ed25519 for the legacy EdDSA algorithm, 1.3.6.1.4.1.11591.15.1
+const ED25519_OID: [u8; 9] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+const BINARY_DOCUMENT: u8 = 0x00;
+const TEXT_DOCUMENT: u8 = 0x01;
+
+// the subpacket types of section 5.2.3.7 of RFC 9580
+const CREATION_TIME: u8 = 2;
+const EXPIRATION_TIME: u8 = 3;
+const ISSUER_KEY_ID: u8 = 16;
+const ISSUER_FINGERPRINT: u8 = 33;
+
+const SIGNATURE_LABEL: &str = "PGP SIGNATURE";
+const PUBLIC_KEY_LABEL: &str = "PGP PUBLIC KEY BLOCK";
+
+/// An error returned when parsing keys or signatures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The packets or armor are malformed.
+	InvalidEncoding,
+	/// The key or signature uses an algorithm other than Ed25519, or a hash
+	/// function other than one from the SHA-2 family.
+	UnsupportedAlgorithm,
+	/// The key or signature has a version other than 4 or 6.
+	UnsupportedVersion,
+	/// The signature has a critical subpacket that isn't understood.
+	UnsupportedSubpacket,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid openpgp encoding",
+			Error::UnsupportedAlgorithm => "unsupported openpgp algorithm",
+			Error::UnsupportedVersion => "unsupported openpgp packet version",
+			Error::UnsupportedSubpacket => "unsupported critical openpgp subpacket",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The version of a key or signature packet.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Version {
+	/// Version 4, from RFC 4880, which GnuPG uses.
+	V4,
+	/// Version 6, from RFC 9580.
+	V6,
+}
+
+impl Version {
+	fn from_byte(byte: u8) -> Result<Self, Error> {
+		match byte {
+			4 => Ok(Version::V4),
+			6 => Ok(Version::V6),
+			_ => Err(Error::UnsupportedVersion),
+		}
+	}
+
+	fn to_byte(self) -> u8 {
+		match self {
+			Version::V4 => 4,
+			Version::V6 => 6,
+		}
+	}
+}
+
+// splits the first packet off `bytes`, returning its tag and body, with
+// either the legacy or the current header format
+fn read_packet(bytes: &[u8]) -> Result<(u8, &[u8], &[u8]), Error> {
+	let (&header, rest) = bytes.split_first().ok_or(Error::InvalidEncoding)?;
+
+	if header & 0x80 == 0 {
+		return Err(Error::InvalidEncoding);
+	}
+
+	let (tag, len, rest) = if header & 0x40 == 0 {
+		let tag = (header >> 2) & 0x0f;
+
+		let len_size = match header & 3 {
+			0 => 1,
+			1 => 2,
+			2 => 4,
+			// indeterminate lengths are only used for data packets
+			_ => return Err(Error::InvalidEncoding),
+		};
+
+		if rest.len() < len_size {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let len = rest[.. len_size].iter().fold(0, |acc, &x| (acc << 8) | x as usize);
+		(tag, len, &rest[len_size ..])
+	} else {
+		let (len, rest) = read_length(rest)?;
+		(header & 0x3f, len, rest)
+	};
+
+	if rest.len() < len {
+		return Err(Error::InvalidEncoding);
+	}
+
+	Ok((tag, &rest[.. len], &rest[len ..]))
+}
+
+// reads a packet or subpacket length, as in section 4.2.1 of RFC 9580,
+// rejecting the partial lengths that are only used for data packets
+fn read_length(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+	match bytes {
+		[first @ 0 ..= 191, rest @ ..] => Ok((*first as usize, rest)),
+		[first @ 192 ..= 223, second, rest @ ..] => {
+			Ok(((((*first as usize) - 192) << 8) + *second as usize + 192, rest))
+		},
+		[255, rest @ ..] if rest.len() >= 4 => {
+			Ok((u32::from_be_bytes(rest[.. 4].try_into().unwrap()) as usize, &rest[4 ..]))
+		},
+		_ => Err(Error::InvalidEncoding),
+	}
+}
+
+fn write_length(len: usize, out: &mut Vec<u8>) {
+	if len < 192 {
+		out.push(len as u8);
+	} else if len < 8384 {
+		out.push((((len - 192) >> 8) + 192) as u8);
+		out.push((len - 192) as u8);
+	} else {
+		out.push(255);
+		out.extend_from_slice(&(len as u32).to_be_bytes());
+	}
+}
+
+fn write_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(body.len() + 6);
+	out.push(0xc0 | tag);
+	write_length(body.len(), &mut out);
+	out.extend_from_slice(body);
+	out
+}
+
+// splits `len` bytes off the front of `bytes`
+fn take(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+	if bytes.len() < len {
+		return Err(Error::InvalidEncoding);
+	}
+
+	Ok(bytes.split_at(len))
+}
+
+// reads a multiprecision integer of at most 32 bytes, left-padding it with zeros
+fn read_mpi(bytes: &[u8]) -> Result<([u8; 32], &[u8]), Error> {
+	let (bits, rest) = take(bytes, 2)?;
+	let bits = u16::from_be_bytes(bits.try_into().unwrap()) as usize;
+
+	if bits > 256 {
+		return Err(Error::InvalidEncoding);
+	}
+
+	let (value, rest) = take(rest, bits.div_ceil(8))?;
+
+	let mut out = [0; 32];
+	out[32 - value.len() ..].copy_from_slice(value);
+
+	Ok((out, rest))
+}
+
+fn write_mpi(value: &[u8], out: &mut Vec<u8>) {
+	let start = value.iter().position(|&x| x != 0).unwrap_or(value.len());
+	let value = &value[start ..];
+
+	let bits = match value.first() {
+		Some(first) => 8 * value.len() - first.leading_zeros() as usize,
+		None => 0,
+	};
+
+	out.extend_from_slice(&(bits as u16).to_be_bytes());
+	out.extend_from_slice(value);
+}
+
+// decodes ASCII armor, as in section 6 of RFC 9580, which says that the
+// optional CRC24 checksum line must be ignored
+fn dearmor(text: &str, label: &str) -> Result<Vec<u8>, Error> {
+	let mut lines = text.lines().map(|line| line.trim_end());
+
+	let begin = ["-----BEGIN ", label, "-----"].concat();
+	let end = ["-----END ", label, "-----"].concat();
+
+	lines.find(|&line| line == begin).ok_or(Error::InvalidEncoding)?;
+
+	// armor headers, followed by an empty line
+	loop {
+		match lines.next() {
+			Some("") => break,
+			Some(line) if line.contains(": ") => continue,
+			_ => return Err(Error::InvalidEncoding),
+		}
+	}
+
+	let mut body = String::new();
+
+	loop {
+		match lines.next() {
+			Some(line) if line == end => break,
+			Some(line) if line.starts_with('=') => {
+				if lines.next() != Some(&end) {
+					return Err(Error::InvalidEncoding);
+				}
+
+				break;
+			},
+			Some(line) => body.push_str(line),
+			None => return Err(Error::InvalidEncoding),
+		}
+	}
+
+	base64::decode(&body).ok_or(Error::InvalidEncoding)
+}
+
+fn armor(label: &str, bytes: &[u8]) -> String {
+	let mut body = String::new();
+	base64::encode(bytes, &mut body);
+
+	let mut out = String::new();
+	out.push_str("-----BEGIN ");
+	out.push_str(label);
+	out.push_str("-----\n\n");
+
+	for line in body.as_bytes().chunks(64) {
+		// base64 is ascii, so this can't split a character
+		out.push_str(core::str::from_utf8(line).unwrap());
+		out.push('\n');
+	}
+
+	out.push_str("-----END ");
+	out.push_str(label);
+	out.push_str("-----\n");
+	out
+}
+
+/// The fingerprint of a key, which identifies it. It's displayed as
+/// uppercase hex, like GnuPG does.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fingerprint {
+	/// The SHA-1 fingerprint of a v4 key.
+	V4([u8; 20]),
+	/// The SHA-256 fingerprint of a v6 key.
+	V6([u8; 32]),
+}
+
+impl Fingerprint {
+	/// Returns the bytes of this fingerprint.
+	pub fn as_bytes(&self) -> &[u8] {
+		match self {
+			Fingerprint::V4(bytes) => bytes,
+			Fingerprint::V6(bytes) => bytes,
+		}
+	}
+
+	/// Returns the 8-byte key ID derived from this fingerprint, which is its
+	/// last 8 bytes for v4 keys and its first 8 bytes for v6 keys.
+	pub fn key_id(&self) -> [u8; 8] {
+		match self {
+			Fingerprint::V4(bytes) => bytes[12 ..].try_into().unwrap(),
+			Fingerprint::V6(bytes) => bytes[.. 8].try_into().unwrap(),
+		}
+	}
+
+	fn from_subpacket(bytes: &[u8]) -> Result<Self, Error> {
+		match bytes {
+			[4, fingerprint @ ..] if fingerprint.len() == 20 => Ok(Fingerprint::V4(fingerprint.try_into().unwrap())),
+			[6, fingerprint @ ..] if fingerprint.len() == 32 => Ok(Fingerprint::V6(fingerprint.try_into().unwrap())),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+}
+
+impl fmt::Display for Fingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for byte in self.as_bytes() {
+			write!(f, "{:02X}", byte)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// An OpenPGP Ed25519 public key.
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+	version: Version,
+	creation_time: u32,
+	verifying_key: VerifyingKey,
+	// the packet body, which the fingerprint is computed over
+	body: Vec<u8>,
+}
+
+impl PublicKey {
+	/// Creates a public key for `verifying_key`, created at `creation_time`
+	/// in seconds since the Unix epoch. The creation time is part of the
+	/// fingerprint, so it must be kept along with the key.
+	pub fn new(version: Version, verifying_key: VerifyingKey, creation_time: u32) -> Self {
+		let mut body = Vec::new();
+		body.push(version.to_byte());
+		body.extend_from_slice(&creation_time.to_be_bytes());
+
+		match version {
+			Version::V4 => {
+				body.push(EDDSA_LEGACY);
+				body.push(ED25519_OID.len() as u8);
+				body.extend_from_slice(&ED25519_OID);
+
+				// the point has a 0x40 prefix, which makes it 263 bits
+				body.extend_from_slice(&263u16.to_be_bytes());
+				body.push(0x40);
+				body.extend_from_slice(verifying_key.as_bytes());
+			},
+			Version::V6 => {
+				body.push(ED25519);
+				body.extend_from_slice(&32u32.to_be_bytes());
+				body.extend_from_slice(verifying_key.as_bytes());
+			},
+		}
+
+		Self {version, creation_time, verifying_key, body}
+	}
+
+	/// Parses the primary key of a binary key block, such as the output of
+	/// `gpg --export`, ignoring any packets after it.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		let (tag, body, _) = read_packet(bytes)?;
+
+		if tag != PUBLIC_KEY_TAG {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let (header, rest) = take(body, 6)?;
+		let version = Version::from_byte(header[0])?;
+		let creation_time = u32::from_be_bytes(header[1 .. 5].try_into().unwrap());
+		let algorithm = header[5];
+
+		let rest = match version {
+			Version::V4 => rest,
+			Version::V6 => {
+				let (len, rest) = take(rest, 4)?;
+
+				if u32::from_be_bytes(len.try_into().unwrap()) as usize != rest.len() {
+					return Err(Error::InvalidEncoding);
+				}
+
+				rest
+			},
+		};
+
+		let key = match (version, algorithm, rest) {
+			(Version::V4, EDDSA_LEGACY, [9, rest @ ..]) if rest.starts_with(&ED25519_OID) => {
+				match &rest[9 ..] {
+					[0x01, 0x07, 0x40, key @ ..] if key.len() == 32 => key,
+					_ => return Err(Error::InvalidEncoding),
+				}
+			},
+			(Version::V4, EDDSA_LEGACY, _) => return Err(Error::UnsupportedAlgorithm),
+			(_, ED25519, key) if key.len() == 32 => key,
+			(_, ED25519, _) => return Err(Error::InvalidEncoding),
+			_ => return Err(Error::UnsupportedAlgorithm),
+		};
+
+		let verifying_key = VerifyingKey::from_bytes(key.try_into().unwrap())
+			.map_err(|_| Error::InvalidEncoding)?;
+
+		Ok(Self {version, creation_time, verifying_key, body: body.to_vec()})
+	}
+
+	/// Parses the primary key of an armored key block, such as the output of
+	/// `gpg --armor --export`.
+	pub fn from_armored(text: &str) -> Result<Self, Error> {
+		Self::from_bytes(&dearmor(text, PUBLIC_KEY_LABEL)?)
+	}
+
+	/// Returns the encoding of this key as a public key packet. This isn't a
+	/// complete key block, since it has no user ID or self-signature.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		write_packet(PUBLIC_KEY_TAG, &self.body)
+	}
+
+	/// Returns the version of this key.
+	pub fn version(&self) -> Version {
+		self.version
+	}
+
+	/// Returns the time this key was created, in seconds since the Unix
+	/// epoch.
+	pub fn creation_time(&self) -> u32 {
+		self.creation_time
+	}
+
+	/// Returns the Ed25519 key itself.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		self.verifying_key
+	}
+
+	/// Returns the fingerprint of this key.
+	pub fn fingerprint(&self) -> Fingerprint {
+		match self.version {
+			Version::V4 => {
+				let mut hasher = Sha1::new();
+				hasher.add_bytes(&[0x99]);
+				hasher.add_bytes(&(self.body.len() as u16).to_be_bytes());
+				hasher.add_bytes(&self.body);
+				Fingerprint::V4(hasher.out())
+			},
+			Version::V6 => {
+				let mut hasher = Sha256::new();
+				hasher.add_bytes(&[0x9b]);
+				hasher.add_bytes(&(self.body.len() as u32).to_be_bytes());
+				hasher.add_bytes(&self.body);
+				Fingerprint::V6(hasher.out())
+			},
+		}
+	}
+
+	fn algorithm(&self) -> u8 {
+		self.body[5]
+	}
+}
+
+// the hash functions that can be used with Ed25519, which needs at least 256
+// bits of output
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum HashAlgorithm {
+	Sha256,
+	Sha384,
+	Sha512,
+	Sha224,
+}
+
+impl HashAlgorithm {
+	fn from_byte(byte: u8) -> Result<Self, Error> {
+		match byte {
+			8 => Ok(HashAlgorithm::Sha256),
+			9 => Ok(HashAlgorithm::Sha384),
+			10 => Ok(HashAlgorithm::Sha512),
+			11 => Ok(HashAlgorithm::Sha224),
+			_ => Err(Error::UnsupportedAlgorithm),
+		}
+	}
+
+	// the salt sizes of v6 signatures, from section 9.5 of RFC 9580
+	fn salt_len(self) -> usize {
+		match self {
+			HashAlgorithm::Sha256 | HashAlgorithm::Sha224 => 16,
+			HashAlgorithm::Sha384 => 24,
+			HashAlgorithm::Sha512 => 32,
+		}
+	}
+
+	fn hash(self, parts: &[&[u8]]) -> Vec<u8> {
+		macro_rules! hash_with {
+			($hasher:ident) => {{
+				let mut hasher = $hasher::new();
+
+				for part in parts {
+					hasher.add_bytes(part);
+				}
+
+				hasher.out().to_vec()
+			}};
+		}
+
+		match self {
+			HashAlgorithm::Sha256 => hash_with!(Sha256),
+			HashAlgorithm::Sha384 => hash_with!(Sha384),
+			HashAlgorithm::Sha512 => hash_with!(Sha512),
+			HashAlgorithm::Sha224 => hash_with!(Sha224),
+		}
+	}
+}
+
+// converts every line ending to CRLF, for text signatures
+fn canonicalize_text(data: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(data.len());
+
+	for (i, &byte) in data.iter().enumerate() {
+		if byte == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+			out.push(b'\r');
+		}
+
+		out.push(byte);
+	}
+
+	out
+}
+
+/// An OpenPGP Ed25519 signature packet.
+#[derive(Clone, Debug)]
+pub struct Signature {
+	version: Version,
+	signature_type: u8,
+	algorithm: u8,
+	hash: HashAlgorithm,
+	// the part of the packet covered by the hash, from the version to the
+	// end of the hashed subpackets
+	hashed: Vec<u8>,
+	hash_prefix: [u8; 2],
+	salt: Vec<u8>,
+	signature: ed25519::Signature,
+	creation_time: u32,
+	expiration: Option<u32>,
+	issuer_fingerprint: Option<Fingerprint>,
+	issuer_key_id: Option<[u8; 8]>,
+	// the whole packet body, for re-encoding
+	body: Vec<u8>,
+}
+
+// the values read from a signature's subpackets
+#[derive(Default)]
+struct Subpackets {
+	creation_time: Option<u32>,
+	expiration: Option<u32>,
+	issuer_fingerprint: Option<Fingerprint>,
+	issuer_key_id: Option<[u8; 8]>,
+}
+
+impl Subpackets {
+	fn read(&mut self, mut bytes: &[u8], hashed: bool) -> Result<(), Error> {
+		while !bytes.is_empty() {
+			let (len, rest) = read_length(bytes)?;
+			let (subpacket, rest) = take(rest, len)?;
+			bytes = rest;
+
+			let (&kind, contents) = subpacket.split_first().ok_or(Error::InvalidEncoding)?;
+			let critical = kind & 0x80 != 0;
+
+			// the unhashed subpackets aren't authenticated, so only the issuer,
+			// which is just a hint for finding the key, is read from them
+			match (kind & 0x7f, hashed) {
+				(CREATION_TIME, true) => {
+					self.creation_time = Some(u32::from_be_bytes(contents.try_into().map_err(|_| Error::InvalidEncoding)?));
+				},
+				(EXPIRATION_TIME, true) => {
+					self.expiration = Some(u32::from_be_bytes(contents.try_into().map_err(|_| Error::InvalidEncoding)?));
+				},
+				(ISSUER_FINGERPRINT, _) => {
+					let fingerprint = Fingerprint::from_subpacket(contents)?;
+
+					if hashed || self.issuer_fingerprint.is_none() {
+						self.issuer_fingerprint = Some(fingerprint);
+					}
+				},
+				(ISSUER_KEY_ID, _) => {
+					self.issuer_key_id = Some(contents.try_into().map_err(|_| Error::InvalidEncoding)?);
+				},
+				_ if critical && hashed => return Err(Error::UnsupportedSubpacket),
+				_ => {},
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Signature {
+	/// Parses a binary signature packet, such as a `.sig` file made by
+	/// `gpg --detach-sign`.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		let (tag, body, _) = read_packet(bytes)?;
+
+		if tag != SIGNATURE_TAG {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let (header, rest) = take(body, 4)?;
+		let version = Version::from_byte(header[0])?;
+		let signature_type = header[1];
+		let algorithm = header[2];
+		let hash = HashAlgorithm::from_byte(header[3])?;
+
+		if algorithm != EDDSA_LEGACY && algorithm != ED25519 {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		// the subpacket areas have 2-byte lengths in v4 and 4-byte ones in v6
+		let len_size = match version {
+			Version::V4 => 2,
+			Version::V6 => 4,
+		};
+
+		let read_area = |bytes| -> Result<(&[u8], &[u8]), Error> {
+			let (len, rest) = take(bytes, len_size)?;
+			let len = len.iter().fold(0, |acc, &x| (acc << 8) | x as usize);
+			take(rest, len)
+		};
+
+		let mut subpackets = Subpackets::default();
+
+		let (hashed_area, rest) = read_area(rest)?;
+		subpackets.read(hashed_area, true)?;
+		let hashed = body[.. body.len() - rest.len()].to_vec();
+
+		let (unhashed_area, rest) = read_area(rest)?;
+		subpackets.read(unhashed_area, false)?;
+
+		let (hash_prefix, rest) = take(rest, 2)?;
+
+		let (salt, rest) = match version {
+			Version::V4 => (&[][..], rest),
+			Version::V6 => {
+				let (&len, rest) = rest.split_first().ok_or(Error::InvalidEncoding)?;
+
+				if len as usize != hash.salt_len() {
+					return Err(Error::InvalidEncoding);
+				}
+
+				take(rest, len as usize)?
+			},
+		};
+
+		let mut signature = [0; 64];
+
+		match (version, algorithm) {
+			(Version::V4, EDDSA_LEGACY) => {
+				let (r, rest) = read_mpi(rest)?;
+				let (s, rest) = read_mpi(rest)?;
+
+				if !rest.is_empty() {
+					return Err(Error::InvalidEncoding);
+				}
+
+				signature[.. 32].copy_from_slice(&r);
+				signature[32 ..].copy_from_slice(&s);
+			},
+			(_, ED25519) if rest.len() == 64 => signature.copy_from_slice(rest),
+			_ => return Err(Error::InvalidEncoding),
+		}
+
+		let signature = ed25519::Signature::from_bytes(signature).map_err(|_| Error::InvalidEncoding)?;
+		let creation_time = subpackets.creation_time.ok_or(Error::InvalidEncoding)?;
+
+		Ok(Self {
+			version,
+			signature_type,
+			algorithm,
+			hash,
+			hashed,
+			hash_prefix: hash_prefix.try_into().unwrap(),
+			salt: salt.to_vec(),
+			signature,
+			creation_time,
+			expiration: subpackets.expiration,
+			issuer_fingerprint: subpackets.issuer_fingerprint,
+			issuer_key_id: subpackets.issuer_key_id,
+			body: body.to_vec(),
+		})
+	}
+
+	/// Parses an armored signature, such as an `.asc` file made by
+	/// `gpg --armor --detach-sign`.
+	pub fn from_armored(text: &str) -> Result<Self, Error> {
+		Self::from_bytes(&dearmor(text, SIGNATURE_LABEL)?)
+	}
+
+	/// Creates a detached signature over the binary `data`, made at
+	/// `creation_time` in seconds since the Unix epoch. The signature has the
+	/// same version as `public_key`, and uses SHA-256. v6 signatures are
+	/// salted, and `salt` must be freshly generated by a secure random source
+	/// for each one, while v4 signatures ignore it.
+	///
+	/// # Panics
+	/// * Panics if `public_key` isn't the public key of `signing_key`.
+	pub fn sign_detached(
+		signing_key: &SigningKey,
+		public_key: &PublicKey,
+		data: &[u8],
+		creation_time: u32,
+		salt: [u8; 16],
+	) -> Self {
+		assert!(
+			signing_key.verifying_key().as_bytes() == public_key.verifying_key().as_bytes(),
+			"signing key doesn't match the public key",
+		);
+
+		let version = public_key.version;
+		let fingerprint = public_key.fingerprint();
+
+		let mut hashed_subpackets = Vec::new();
+		write_length(fingerprint.as_bytes().len() + 2, &mut hashed_subpackets);
+		hashed_subpackets.push(ISSUER_FINGERPRINT);
+		hashed_subpackets.push(version.to_byte());
+		hashed_subpackets.extend_from_slice(fingerprint.as_bytes());
+		hashed_subpackets.extend_from_slice(&[5, CREATION_TIME]);
+		hashed_subpackets.extend_from_slice(&creation_time.to_be_bytes());
+
+		// v4 signatures also carry the key ID in the unhashed area, which
+		// older implementations use to find the key
+		let mut unhashed_subpackets = Vec::new();
+
+		let mut body = vec![version.to_byte(), BINARY_DOCUMENT, public_key.algorithm(), 8];
+
+		let salt = match version {
+			Version::V4 => {
+				unhashed_subpackets.extend_from_slice(&[9, ISSUER_KEY_ID]);
+				unhashed_subpackets.extend_from_slice(&fingerprint.key_id());
+
+				body.extend_from_slice(&(hashed_subpackets.len() as u16).to_be_bytes());
+				body.extend_from_slice(&hashed_subpackets);
+				&[][..]
+			},
+			Version::V6 => {
+				body.extend_from_slice(&(hashed_subpackets.len() as u32).to_be_bytes());
+				body.extend_from_slice(&hashed_subpackets);
+				&salt[..]
+			},
+		};
+
+		let hashed = body.clone();
+		let digest = digest(version, HashAlgorithm::Sha256, salt, data, &hashed);
+		let signature = signing_key.sign(&digest);
+
+		match version {
+			Version::V4 => body.extend_from_slice(&(unhashed_subpackets.len() as u16).to_be_bytes()),
+			Version::V6 => body.extend_from_slice(&(unhashed_subpackets.len() as u32).to_be_bytes()),
+		}
+
+		body.extend_from_slice(&unhashed_subpackets);
+		body.extend_from_slice(&digest[.. 2]);
+
+		match public_key.algorithm() {
+			EDDSA_LEGACY => {
+				write_mpi(&signature.as_bytes()[.. 32], &mut body);
+				write_mpi(&signature.as_bytes()[32 ..], &mut body);
+			},
+			_ => {
+				if version == Version::V6 {
+					body.push(salt.len() as u8);
+					body.extend_from_slice(salt);
+				}
+
+				body.extend_from_slice(signature.as_bytes());
+			},
+		}
+
+		Self {
+			version,
+			signature_type: BINARY_DOCUMENT,
+			algorithm: public_key.algorithm(),
+			hash: HashAlgorithm::Sha256,
+			hashed,
+			hash_prefix: [digest[0], digest[1]],
+			salt: salt.to_vec(),
+			signature,
+			creation_time,
+			expiration: None,
+			issuer_fingerprint: Some(fingerprint),
+			issuer_key_id: Some(fingerprint.key_id()),
+			body,
+		}
+	}
+
+	/// Like [`Signature::sign_detached`], but generates the salt with `rng`,
+	/// which must be a cryptographically secure random number generator.
+	#[cfg(feature = "rand_core")]
+	pub fn sign_detached_with_rng<R: RngCore + CryptoRng>(
+		signing_key: &SigningKey,
+		public_key: &PublicKey,
+		data: &[u8],
+		creation_time: u32,
+		rng: &mut R,
+	) -> Self {
+		let mut salt = [0; 16];
+		rng.fill_bytes(&mut salt);
+
+		Self::sign_detached(signing_key, public_key, data, creation_time, salt)
+	}
+
+	/// Returns the encoding of this signature as a binary packet.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		write_packet(SIGNATURE_TAG, &self.body)
+	}
+
+	/// Returns the encoding of this signature as ASCII armor.
+	pub fn to_armored(&self) -> String {
+		armor(SIGNATURE_LABEL, &self.to_bytes())
+	}
+
+	/// Returns the version of this signature.
+	pub fn version(&self) -> Version {
+		self.version
+	}
+
+	/// Returns the time this signature was made, in seconds since the Unix
+	/// epoch.
+	pub fn creation_time(&self) -> u32 {
+		self.creation_time
+	}
+
+	/// Returns the time this signature expires, in seconds since the Unix
+	/// epoch, if it does.
+	pub fn expiration_time(&self) -> Option<u32> {
+		match self.expiration {
+			None | Some(0) => None,
+			Some(period) => Some(self.creation_time.saturating_add(period)),
+		}
+	}
+
+	/// Returns the fingerprint of the key that made this signature, if it's
+	/// included. This is only a hint for finding the key.
+	pub fn issuer_fingerprint(&self) -> Option<Fingerprint> {
+		self.issuer_fingerprint
+	}
+
+	/// Returns the key ID of the key that made this signature, if it's
+	/// included. This is only a hint for finding the key.
+	pub fn issuer_key_id(&self) -> Option<[u8; 8]> {
+		self.issuer_key_id.or(self.issuer_fingerprint.map(|x| x.key_id()))
+	}
+
+	/// Verifies that this is a detached signature of `data` by `public_key`.
+	/// Both binary and text signatures are accepted, with line endings
+	/// converted to CRLF for text signatures. Expiration isn't checked, so the
+	/// caller should check [`Signature::expiration_time`] if it matters.
+	pub fn verify_detached(&self, public_key: &PublicKey, data: &[u8]) -> bool {
+		if self.version != public_key.version || self.algorithm != public_key.algorithm() {
+			return false;
+		}
+
+		if self.issuer_fingerprint.is_some_and(|x| x != public_key.fingerprint()) {
+			return false;
+		}
+
+		let digest = match self.signature_type {
+			BINARY_DOCUMENT => digest(self.version, self.hash, &self.salt, data, &self.hashed),
+			TEXT_DOCUMENT => digest(self.version, self.hash, &self.salt, &canonicalize_text(data), &self.hashed),
+			_ => return false,
+		};
+
+		digest[.. 2] == self.hash_prefix && public_key.verifying_key.verify(&digest, &self.signature)
+	}
+}
+
+// the hash that's signed, from section 5.2.4 of RFC 9580
+fn digest(version: Version, hash: HashAlgorithm, salt: &[u8], data: &[u8], hashed: &[u8]) -> Vec<u8> {
+	let trailer_version = [version.to_byte(), 0xff];
+	let hashed_len = (hashed.len() as u32).to_be_bytes();
+
+	hash.hash(&[salt, data, hashed, &trailer_version, &hashed_len])
+}
+
+#[cfg(test)]
+use crate::test_util::hex_vec;
+
+// made by GnuPG 2.2
+#[cfg(test)]
+const GNUPG_KEY: &str = "\
+-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEZZIAgBYJKwYBBAHaRw8BAQdAR3r4gT4KdQrci0XvnXpQkGEt7XijJRsJX+Rf
+HXOQUrq0F1Rlc3QgPHRlc3RAZXhhbXBsZS5jb20+iJAEExYIADgWIQRoCkPNbKgz
+ilUgRbARFD9e5SSfcwUCZZIAgAIbAwULCQgHAgYVCgkICwIEFgIDAQIeAQIXgAAK
+CRARFD9e5SSfc6UGAP9Ltj35jy5+iqHzFBDMa7KLsqLrTdQDfoC2M9sauTGS9QD+
+JDoYuju7YiYj4lqSyqeGvqdlmBKK2TBcbnBKKAAsOw0=
+=yava
+-----END PGP PUBLIC KEY BLOCK-----
+";
+
+#[cfg(test)]
+const GNUPG_SIGNATURE: &str = "\
+-----BEGIN PGP SIGNATURE-----
+
+iHUEABYIAB0WIQRoCkPNbKgzilUgRbARFD9e5SSfcwUCZZNSAAAKCRARFD9e5SSf
+cyDKAP9Kh06UOMcSvK4EbF4qYB8IdmbUo7HzcPTKNvE3bGoywAD8DwEzFHsIzwvn
+gKG/04zSUNwWrD5TpyZr85xk/7nmCgk=
+=AVYQ
+-----END PGP SIGNATURE-----
+";
+
+#[test]
+fn test_gnupg() {
+	let public_key = PublicKey::from_armored(GNUPG_KEY).unwrap();
+	assert_eq!(public_key.version(), Version::V4);
+	assert_eq!(public_key.creation_time(), 1704067200);
+	assert_eq!(public_key.fingerprint().to_string(), "680A43CD6CA8338A552045B011143F5EE5249F73");
+
+	let signature = Signature::from_armored(GNUPG_SIGNATURE).unwrap();
+	assert_eq!(signature.creation_time(), 1704153600);
+	assert_eq!(signature.issuer_key_id(), Some(public_key.fingerprint().key_id()));
+	assert!(signature.verify_detached(&public_key, b"hello openpgp\n"));
+	assert!(!signature.verify_detached(&public_key, b"hello openpgp\r\n"));
+	assert!(!signature.verify_detached(&public_key, b"hello openpgp"));
+
+	// a text signature of the same file
+	let text_signature = Signature::from_armored("\
+-----BEGIN PGP SIGNATURE-----
+
+iHUEARYIAB0WIQRoCkPNbKgzilUgRbARFD9e5SSfcwUCZZNSAAAKCRARFD9e5SSf
+c+1XAQDFE3rWN6EF5RgKawNsgmN6Verr9clSOO1LD0fl8AUdGwEAu9mGsuOrs3H1
+imCP5NXNMNgteZ8R+AkN3jttssBXoAM=
+=zNaM
+-----END PGP SIGNATURE-----
+").unwrap();
+
+	assert!(text_signature.verify_detached(&public_key, b"hello openpgp\n"));
+	assert!(text_signature.verify_detached(&public_key, b"hello openpgp\r\n"));
+	assert!(!text_signature.verify_detached(&public_key, b"hello openpgp"));
+
+	// re-creating the signature with the key GnuPG generated gives the same
+	// packet, apart from the header format
+	let signing_key = SigningKey::from_bytes(hex_vec("cb64d5dedc62b0ea89b9cd7678b31b3818f542849ec1f072657b8e5d0701c1b5").try_into().unwrap());
+	let created = Signature::sign_detached(&signing_key, &public_key, b"hello openpgp\n", 1704153600, [0; 16]);
+	assert_eq!(created.to_bytes()[2 ..], signature.to_bytes()[2 ..]);
+	assert_eq!(created.to_bytes()[.. 2], [0xc2, 0x75]);
+}
+
+// GnuPG 2.2 doesn't support v6 keys, so this was checked against a python
+// transcription of sections 5.2.4 and 5.5.4 of the RFC instead, signing with
+// the Ed25519 class of python's cryptography package
+#[test]
+fn test_v6() {
+	let signing_key = SigningKey::from_bytes([0x42; 32]);
+	let public_key = PublicKey::new(Version::V6, signing_key.verifying_key(), 1700000000);
+	assert_eq!(public_key.fingerprint().to_string(), "84C130617C17E35E8BB4523FF0B386800DFC2D0B30A219EC4015F5043D177693");
+	assert_eq!(PublicKey::from_bytes(&public_key.to_bytes()).unwrap().fingerprint(), public_key.fingerprint());
+
+	let signature = Signature::sign_detached(&signing_key, &public_key, b"hello openpgp\n", 1700000001, [0x11; 16]);
+	assert_eq!(signature.to_bytes(), hex_vec("c28806001b080000002922210684c130617c17e35e8bb4523ff0b386800dfc2d0b30a219ec4015f5043d17769305026553f1010000000019e3101111111111111111111111111111111181e5b10ee1cbd8c2e77f64caa6844ba7805721343a2ad34f607ac86a17d5e327242ec414764671209e98bc7c3a6f50769c43d6746c604b0ee2af067f49203006"));
+
+	let parsed = Signature::from_armored(&signature.to_armored()).unwrap();
+	assert_eq!(parsed.version(), Version::V6);
+	assert_eq!(parsed.issuer_fingerprint(), Some(public_key.fingerprint()));
+	assert!(parsed.verify_detached(&public_key, b"hello openpgp\n"));
+	assert!(!parsed.verify_detached(&public_key, b"hello openpgp"));
+
+	// a v4 key with the same Ed25519 key can't verify it
+	let v4_public_key = PublicKey::new(Version::V4, signing_key.verifying_key(), 1700000000);
+	assert!(!parsed.verify_detached(&v4_public_key, b"hello openpgp\n"));
+
+	let mut modified = signature.to_bytes();
+	let len = modified.len();
+	modified[len - 1] ^= 1;
+	assert!(!Signature::from_bytes(&modified).is_ok_and(|x| x.verify_detached(&public_key, b"hello openpgp\n")));
+}
+
+#[test]
+fn test_malformed() {
+	let signature = Signature::from_armored(GNUPG_SIGNATURE).unwrap().to_bytes();
+
+	for len in 0 .. signature.len() {
+		assert!(Signature::from_bytes(&signature[.. len]).is_err());
+	}
+
+	// an unknown critical subpacket
+	let mut critical = signature.clone();
+	critical[32] = 0x80 | 100;
+	assert_eq!(Signature::from_bytes(&critical).unwrap_err(), Error::UnsupportedSubpacket);
+
+	assert_eq!(Signature::from_armored(&GNUPG_SIGNATURE.replace("SIGNATURE", "MESSAGE")).unwrap_err(), Error::InvalidEncoding);
+	assert_eq!(PublicKey::from_bytes(&signature).unwrap_err(), Error::InvalidEncoding);
+}
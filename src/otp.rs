@@ -0,0 +1,585 @@
+//! One-time passwords for two-factor authentication, implemented according to
+//! [IETF RFC 4226](https://datatracker.ietf.org/doc/html/rfc4226) for HOTP,
+//! where each code is derived from a counter, and
+//! [IETF RFC 6238](https://datatracker.ietf.org/doc/html/rfc6238) for TOTP,
+//! where each code is derived from the current time. These are the codes shown
+//! by authenticator apps, which are set up by scanning an `otpauth://` URI,
+//! as described by [Google Authenticator](https://github.com/google/google-authenticator/wiki/Key-Uri-Format).
+//!
+//! ```
+//! use libkrypton::otp::Totp;
+//!
+//! # let secret = b"12345678901234567890";
+//! # let unix_time = 59;
+//! let totp = Totp::default();
+//!
+//! let code = totp.generate(secret, unix_time);
+//! let text = format!("{:01$}", code, totp.digits as usize);
+//!
+//! assert!(totp.verify(secret, &text, unix_time));
+//! ```
+
+#[cfg(feature = "std")]
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::string::String;
+#[cfg(feature = "std")]
+use std::vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::encoding;
+use crate::hmac::{HmacSha1, HmacSha256, HmacSha512};
+#[cfg(feature = "std")]
+use crate::zeroize::zeroize;
+
+/// The HMAC hash function used to derive codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+	/// HMAC-SHA-1, which is the default, and the only one supported by some
+	/// authenticator apps.
+	Sha1,
+	/// HMAC-SHA-256.
+	Sha256,
+	/// HMAC-SHA-512.
+	Sha512,
+}
+
+impl Algorithm {
+	#[cfg(feature = "std")]
+	fn name(self) -> &'static str {
+		match self {
+			Algorithm::Sha1 => "SHA1",
+			Algorithm::Sha256 => "SHA256",
+			Algorithm::Sha512 => "SHA512",
+		}
+	}
+
+	// the dynamic truncation from section 5.3 of RFC 4226, applied to the
+	// hmac of the counter
+	fn truncated_hmac(self, secret: &[u8], counter: u64) -> u32 {
+		let mut out = [0; 64];
+
+		let len = match self {
+			Algorithm::Sha1 => {
+				let mut hmac = HmacSha1::new(secret);
+				hmac.add_bytes(&counter.to_be_bytes());
+				out[.. 20].copy_from_slice(&hmac.out());
+				20
+			},
+			Algorithm::Sha256 => {
+				let mut hmac = HmacSha256::new(secret);
+				hmac.add_bytes(&counter.to_be_bytes());
+				out[.. 32].copy_from_slice(&hmac.out());
+				32
+			},
+			Algorithm::Sha512 => {
+				let mut hmac = HmacSha512::new(secret);
+				hmac.add_bytes(&counter.to_be_bytes());
+				out = hmac.out();
+				64
+			},
+		};
+
+		let offset = (out[len - 1] & 0xf) as usize;
+		let bytes = [out[offset], out[offset + 1], out[offset + 2], out[offset + 3]];
+
+		u32::from_be_bytes(bytes) & 0x7fffffff
+	}
+}
+
+fn hotp_code(algorithm: Algorithm, digits: u32, secret: &[u8], counter: u64) -> u32 {
+	assert!((1 ..= 9).contains(&digits));
+
+	algorithm.truncated_hmac(secret, counter) % 10u32.pow(digits)
+}
+
+// parses a code that has exactly `digits` decimal digits
+fn parse_code(code: &str, digits: u32) -> Option<u32> {
+	if code.len() != digits as usize || !code.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+
+	code.parse().ok()
+}
+
+/// The parameters of counter-based one-time passwords.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Hotp {
+	/// The hash function, which is SHA-1 by default.
+	pub algorithm: Algorithm,
+	/// The number of digits in each code, from 1 to 9, which is 6 by default.
+	pub digits: u32,
+}
+
+impl Default for Hotp {
+	fn default() -> Self {
+		Self {algorithm: Algorithm::Sha1, digits: 6}
+	}
+}
+
+impl Hotp {
+	/// Returns the code for `counter`. It should be shown with leading zeros
+	/// up to [`Hotp::digits`] digits.
+	///
+	/// # Panics
+	/// * Panics if [`Hotp::digits`] isn't between 1 and 9.
+	pub fn generate(&self, secret: &[u8], counter: u64) -> u32 {
+		hotp_code(self.algorithm, self.digits, secret, counter)
+	}
+
+	/// Checks `code` against the codes for `counter` and the `look_ahead`
+	/// counters after it, in case the user generated codes that were never
+	/// used. Returns the counter that matched, which the next code should be
+	/// checked against plus one, so that no code is accepted twice.
+	///
+	/// # Panics
+	/// * Panics if [`Hotp::digits`] isn't between 1 and 9.
+	pub fn verify(&self, secret: &[u8], code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+		let code = parse_code(code, self.digits)?;
+		let mut matched = None;
+
+		// every candidate is checked, so the time taken doesn't depend on
+		// which one matched
+		for candidate in counter ..= counter.saturating_add(look_ahead) {
+			if self.generate(secret, candidate) == code && matched.is_none() {
+				matched = Some(candidate);
+			}
+		}
+
+		matched
+	}
+}
+
+/// The parameters of time-based one-time passwords.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Totp {
+	/// The hash function, which is SHA-1 by default.
+	pub algorithm: Algorithm,
+	/// The number of digits in each code, from 1 to 9, which is 6 by default.
+	pub digits: u32,
+	/// The number of seconds each code is valid for, which is 30 by default.
+	pub period: u64,
+	/// The number of periods before and after the current one whose codes are
+	/// also accepted, to allow for clock drift and slow typing, which is 1 by
+	/// default.
+	pub skew: u64,
+}
+
+impl Default for Totp {
+	fn default() -> Self {
+		Self {algorithm: Algorithm::Sha1, digits: 6, period: 30, skew: 1}
+	}
+}
+
+impl Totp {
+	/// Returns the code at `unix_time`, in seconds since the Unix epoch. It
+	/// should be shown with leading zeros up to [`Totp::digits`] digits.
+	///
+	/// # Panics
+	/// * Panics if [`Totp::digits`] isn't between 1 and 9.
+	/// * Panics if [`Totp::period`] is 0.
+	pub fn generate(&self, secret: &[u8], unix_time: u64) -> u32 {
+		hotp_code(self.algorithm, self.digits, secret, unix_time / self.period)
+	}
+
+	/// Checks `code` against the codes of the period containing `unix_time`,
+	/// and [`Totp::skew`] periods on either side of it. A code that's been
+	/// accepted once should be remembered and rejected afterwards, since it
+	/// stays valid for a while.
+	///
+	/// # Panics
+	/// * Panics if [`Totp::digits`] isn't between 1 and 9.
+	/// * Panics if [`Totp::period`] is 0.
+	pub fn verify(&self, secret: &[u8], code: &str, unix_time: u64) -> bool {
+		let Some(code) = parse_code(code, self.digits) else {
+			return false;
+		};
+
+		let step = unix_time / self.period;
+		let mut matched = false;
+
+		for candidate in step.saturating_sub(self.skew) ..= step.saturating_add(self.skew) {
+			matched |= hotp_code(self.algorithm, self.digits, secret, candidate) == code;
+		}
+
+		matched
+	}
+}
+
+/// An error returned when parsing an `otpauth://` URI.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The text is not a valid `otpauth://` URI.
+	InvalidUri,
+	/// The URI has a parameter with an unsupported value, such as an unknown
+	/// algorithm or too many digits.
+	UnsupportedParameter,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidUri => "invalid otpauth uri",
+			Error::UnsupportedParameter => "unsupported otpauth uri parameter",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Whether a [`KeyUri`] is for counter-based or time-based codes.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+	/// HOTP, starting at `counter`.
+	Hotp {counter: u64},
+	/// TOTP, with codes valid for `period` seconds.
+	Totp {period: u64},
+}
+
+/// The contents of an `otpauth://` URI, which authenticator apps read from a
+/// QR code to set up an account. The secret is zeroized when dropped.
+#[cfg(feature = "std")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyUri {
+	pub kind: Kind,
+	/// The account name shown by the app, which is often of the form
+	/// `issuer:account`.
+	pub label: String,
+	/// The service the account belongs to.
+	pub issuer: Option<String>,
+	/// The shared secret, which should be at least 20 random bytes.
+	pub secret: Vec<u8>,
+	pub algorithm: Algorithm,
+	pub digits: u32,
+}
+
+#[cfg(feature = "std")]
+impl KeyUri {
+	/// Parses an `otpauth://` URI. Parameters that aren't used for generating
+	/// codes, such as `image`, are ignored.
+	pub fn from_text(text: &str) -> Result<Self, Error> {
+		let rest = strip_prefix_ignore_case(text, "otpauth://").ok_or(Error::InvalidUri)?;
+		let (kind, rest) = rest.split_once('/').ok_or(Error::InvalidUri)?;
+		let (label, query) = rest.split_once('?').ok_or(Error::InvalidUri)?;
+
+		let label = percent_decode(label)?;
+
+		let mut secret = None;
+		let mut issuer = None;
+		let mut algorithm = None;
+		let mut digits = None;
+		let mut period = None;
+		let mut counter = None;
+
+		for param in query.split('&') {
+			let (key, value) = param.split_once('=').ok_or(Error::InvalidUri)?;
+			let value = percent_decode(value)?;
+
+			match key {
+				"secret" => set_once(&mut secret, decode_secret(&value)?)?,
+				"issuer" => set_once(&mut issuer, value)?,
+				"algorithm" => set_once(&mut algorithm, parse_algorithm(&value)?)?,
+				"digits" => set_once(&mut digits, parse_number(&value)?)?,
+				"period" => set_once(&mut period, parse_number(&value)?)?,
+				"counter" => set_once(&mut counter, parse_number(&value)?)?,
+				_ => {},
+			}
+		}
+
+		let kind = if kind.eq_ignore_ascii_case("hotp") {
+			Kind::Hotp {counter: counter.ok_or(Error::InvalidUri)?}
+		} else if kind.eq_ignore_ascii_case("totp") {
+			Kind::Totp {period: period.unwrap_or(30)}
+		} else {
+			return Err(Error::UnsupportedParameter);
+		};
+
+		let digits = digits.unwrap_or(6);
+
+		if !(1 ..= 9).contains(&digits) || kind == (Kind::Totp {period: 0}) {
+			return Err(Error::UnsupportedParameter);
+		}
+
+		let secret = match secret {
+			Some(secret) if !secret.is_empty() => secret,
+			_ => return Err(Error::InvalidUri),
+		};
+
+		Ok(Self {
+			kind,
+			label,
+			issuer,
+			secret,
+			algorithm: algorithm.unwrap_or(Algorithm::Sha1),
+			digits: digits as u32,
+		})
+	}
+
+	/// Encodes this as an `otpauth://` URI, with every parameter written out.
+	pub fn to_text(&self) -> String {
+		let kind = match self.kind {
+			Kind::Hotp {..} => "hotp",
+			Kind::Totp {..} => "totp",
+		};
+
+		let mut out = format!("otpauth://{}/", kind);
+		percent_encode(&self.label, ":@", &mut out);
+
+		out.push_str("?secret=");
+		encoding::encode_base32(&self.secret, &mut out);
+
+		if let Some(issuer) = &self.issuer {
+			out.push_str("&issuer=");
+			percent_encode(issuer, "", &mut out);
+		}
+
+		out.push_str(&format!("&algorithm={}&digits={}", self.algorithm.name(), self.digits));
+
+		match self.kind {
+			Kind::Hotp {counter} => out.push_str(&format!("&counter={}", counter)),
+			Kind::Totp {period} => out.push_str(&format!("&period={}", period)),
+		}
+
+		out
+	}
+
+	/// Returns the HOTP parameters, if this is for counter-based codes.
+	pub fn hotp(&self) -> Option<Hotp> {
+		match self.kind {
+			Kind::Hotp {..} => Some(Hotp {algorithm: self.algorithm, digits: self.digits}),
+			Kind::Totp {..} => None,
+		}
+	}
+
+	/// Returns the TOTP parameters with the default skew, if this is for
+	/// time-based codes.
+	pub fn totp(&self) -> Option<Totp> {
+		match self.kind {
+			Kind::Hotp {..} => None,
+			Kind::Totp {period} => Some(Totp {algorithm: self.algorithm, digits: self.digits, period, skew: 1}),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl Drop for KeyUri {
+	fn drop(&mut self) {
+		zeroize(&mut self.secret);
+	}
+}
+
+#[cfg(all(feature = "std", feature = "zeroize"))]
+impl ::zeroize::Zeroize for KeyUri {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.secret);
+	}
+}
+
+#[cfg(all(feature = "std", feature = "zeroize"))]
+impl ::zeroize::ZeroizeOnDrop for KeyUri {}
+
+// deliberately doesn't print the secret
+#[cfg(feature = "std")]
+impl fmt::Debug for KeyUri {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("KeyUri")
+			.field("kind", &self.kind)
+			.field("label", &self.label)
+			.field("issuer", &self.issuer)
+			.field("algorithm", &self.algorithm)
+			.field("digits", &self.digits)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "std")]
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+	match text.get(.. prefix.len()) {
+		Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&text[prefix.len() ..]),
+		_ => None,
+	}
+}
+
+#[cfg(feature = "std")]
+fn set_once<T>(slot: &mut Option<T>, value: T) -> Result<(), Error> {
+	if slot.is_some() {
+		return Err(Error::InvalidUri);
+	}
+
+	*slot = Some(value);
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+fn decode_secret(text: &str) -> Result<Vec<u8>, Error> {
+	let mut secret = vec![0; text.len()];
+	let len = encoding::decode_base32(text, &mut secret).map_err(|_| Error::InvalidUri)?;
+	secret.truncate(len);
+
+	Ok(secret)
+}
+
+#[cfg(feature = "std")]
+fn parse_algorithm(text: &str) -> Result<Algorithm, Error> {
+	for algorithm in [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512] {
+		if text.eq_ignore_ascii_case(algorithm.name()) {
+			return Ok(algorithm);
+		}
+	}
+
+	Err(Error::UnsupportedParameter)
+}
+
+#[cfg(feature = "std")]
+fn parse_number(text: &str) -> Result<u64, Error> {
+	if text.is_empty() || !text.bytes().all(|x| x.is_ascii_digit()) {
+		return Err(Error::InvalidUri);
+	}
+
+	text.parse().map_err(|_| Error::UnsupportedParameter)
+}
+
+#[cfg(feature = "std")]
+fn percent_decode(text: &str) -> Result<String, Error> {
+	let mut bytes = Vec::with_capacity(text.len());
+	let mut rest = text.as_bytes();
+
+	while let Some((&byte, next)) = rest.split_first() {
+		if byte == b'%' {
+			let hex = next.get(.. 2).ok_or(Error::InvalidUri)?;
+			let hex = core::str::from_utf8(hex).map_err(|_| Error::InvalidUri)?;
+
+			if !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+				return Err(Error::InvalidUri);
+			}
+
+			bytes.push(u8::from_str_radix(hex, 16).unwrap());
+			rest = &next[2 ..];
+		} else {
+			bytes.push(byte);
+			rest = next;
+		}
+	}
+
+	String::from_utf8(bytes).map_err(|_| Error::InvalidUri)
+}
+
+// percent-encodes everything except unreserved characters and `allowed`
+#[cfg(feature = "std")]
+fn percent_encode(text: &str, allowed: &str, out: &mut String) {
+	for byte in text.bytes() {
+		if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) || allowed.as_bytes().contains(&byte) {
+			out.push(byte as char);
+		} else {
+			out.push_str(&format!("%{:02X}", byte));
+		}
+	}
+}
+
+// from appendix D of RFC 4226
+#[test]
+fn test_rfc4226_vectors() {
+	let secret = b"12345678901234567890";
+	let codes = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+
+	let hotp = Hotp::default();
+
+	for (counter, code) in codes.into_iter().enumerate() {
+		assert_eq!(hotp.generate(secret, counter as u64), code);
+	}
+
+	assert_eq!(hotp.verify(secret, "162583", 3, 5), Some(7));
+	assert_eq!(hotp.verify(secret, "162583", 3, 3), None);
+	assert_eq!(hotp.verify(secret, "755224", 1, 5), None);
+	assert_eq!(hotp.verify(secret, "55224", 0, 5), None);
+}
+
+// from appendix B of RFC 6238
+#[test]
+fn test_rfc6238_vectors() {
+	let vectors: [(u64, [u32; 3]); 6] = [
+		(59, [94287082, 46119246, 90693936]),
+		(1111111109, [7081804, 68084774, 25091201]),
+		(1111111111, [14050471, 67062674, 99943326]),
+		(1234567890, [89005924, 91819424, 93441116]),
+		(2000000000, [69279037, 90698825, 38618901]),
+		(20000000000, [65353130, 77737706, 47863826]),
+	];
+
+	let secrets: [&[u8]; 3] = [
+		b"12345678901234567890",
+		b"12345678901234567890123456789012",
+		b"1234567890123456789012345678901234567890123456789012345678901234",
+	];
+
+	let algorithms = [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512];
+
+	for (unix_time, codes) in vectors {
+		for i in 0 .. 3 {
+			let totp = Totp {algorithm: algorithms[i], digits: 8, ..Totp::default()};
+			assert_eq!(totp.generate(secrets[i], unix_time), codes[i]);
+		}
+	}
+
+	let totp = Totp {digits: 8, ..Totp::default()};
+
+	// the codes of the neighbouring periods are accepted, but no further
+	assert!(totp.verify(secrets[0], "07081804", 1111111109));
+	assert!(totp.verify(secrets[0], "07081804", 1111111109 + 30));
+	assert!(!totp.verify(secrets[0], "07081804", 1111111109 + 60));
+	assert!(!totp.verify(secrets[0], "7081804", 1111111109));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_key_uri() {
+	let text = "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+		&issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30";
+
+	let uri = KeyUri::from_text(text).unwrap();
+	assert_eq!(uri.kind, Kind::Totp {period: 30});
+	assert_eq!(uri.label, "ACME Co:john.doe@email.com");
+	assert_eq!(uri.issuer.as_deref(), Some("ACME Co"));
+	assert_eq!(uri.secret.len(), 20);
+	assert_eq!(uri.totp(), Some(Totp::default()));
+	assert_eq!(uri.to_text(), text);
+
+	// the defaults, in a different case
+	let uri = KeyUri::from_text("OTPAUTH://hotp/alice?secret=gezdgnbvgy3tqojq&counter=4").unwrap();
+	assert_eq!(uri.hotp(), Some(Hotp::default()));
+	assert_eq!(uri.kind, Kind::Hotp {counter: 4});
+	assert_eq!(uri.secret, b"1234567890");
+
+	let invalid = [
+		"https://totp/alice?secret=GEZDGNBV",
+		"otpauth://totp/alice",
+		"otpauth://totp/alice?issuer=ACME",
+		"otpauth://hotp/alice?secret=GEZDGNBV",
+		"otpauth://totp/alice?secret=GEZDGNBV&secret=GEZDGNBV",
+		"otpauth://totp/al%2ice?secret=GEZDGNBV",
+	];
+
+	for text in invalid {
+		assert_eq!(KeyUri::from_text(text).unwrap_err(), Error::InvalidUri);
+	}
+
+	let unsupported = [
+		"otpauth://motp/alice?secret=GEZDGNBV",
+		"otpauth://totp/alice?secret=GEZDGNBV&algorithm=MD5",
+		"otpauth://totp/alice?secret=GEZDGNBV&digits=10",
+		"otpauth://totp/alice?secret=GEZDGNBV&period=0",
+	];
+
+	for text in unsupported {
+		assert_eq!(KeyUri::from_text(text).unwrap_err(), Error::UnsupportedParameter);
+	}
+}
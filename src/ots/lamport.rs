@@ -0,0 +1,271 @@
+//! Lamport one-time signatures. The secret key is a pair of random values for
+//! each of the 256 bits of the message hash, and the public key is their
+//! hashes. A signature reveals the secret matching each bit, so it's 8 KiB,
+//! and the public key is 16 KiB.
+//!
+//! ```
+//! use libkrypton::ots::HashFunction;
+//! use libkrypton::ots::lamport::SigningKey;
+//!
+//! // the seed should be generated by a secure random source
+//! let mut signing_key = SigningKey::from_seed(HashFunction::Sha256, [0x42; 32]);
+//! let verifying_key = signing_key.verifying_key();
+//!
+//! let signature = signing_key.sign(b"message").unwrap();
+//! assert!(verifying_key.verify(b"message", &signature));
+//!
+//! // the key refuses to sign again
+//! assert!(signing_key.sign(b"another message").is_err());
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use super::{decode_signing_key, encode_signing_key, Error, HashFunction};
+use crate::zeroize::zeroize;
+
+/// The length of an encoded signing key.
+pub const SIGNING_KEY_LEN: usize = 34;
+
+/// The length of an encoded verifying key.
+pub const VERIFYING_KEY_LEN: usize = 512 * 32;
+
+/// The length of a signature.
+pub const SIGNATURE_LEN: usize = 256 * 32;
+
+/// A Lamport signing key, which can sign a single message. It deliberately
+/// doesn't implement [`Clone`], to make accidental reuse harder. Zeroized
+/// when dropped.
+pub struct SigningKey {
+	hash: HashFunction,
+	used: bool,
+	seed: [u8; 32],
+}
+
+impl SigningKey {
+	/// Derives a fresh signing key from a 32-byte seed. A random seed
+	/// generates a new key, and a seed must never be used for more than one
+	/// key.
+	pub fn from_seed(hash: HashFunction, seed: [u8; 32]) -> Self {
+		Self {hash, used: false, seed}
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(hash: HashFunction, rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		let signing_key = Self::from_seed(hash, seed);
+		zeroize(&mut seed);
+
+		signing_key
+	}
+
+	/// Parses an encoded signing key, including whether it has been used.
+	pub fn from_bytes(bytes: &[u8; SIGNING_KEY_LEN]) -> Result<Self, Error> {
+		let (hash, used, seed) = decode_signing_key(bytes)?;
+		Ok(Self {hash, used, seed})
+	}
+
+	/// Returns the encoding of this signing key, including whether it has
+	/// been used. This must be kept secret.
+	pub fn to_bytes(&self) -> [u8; SIGNING_KEY_LEN] {
+		encode_signing_key(self.hash, self.used, &self.seed)
+	}
+
+	/// Returns whether this key has already signed a message.
+	pub fn is_used(&self) -> bool {
+		self.used
+	}
+
+	fn secret(&self, bit: usize, value: usize) -> [u8; 32] {
+		self.hash.hash(&[&self.seed, &(bit as u16).to_be_bytes(), &[value as u8]])
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		let mut hashes = [[[0; 32]; 2]; 256];
+
+		for bit in 0 .. 256 {
+			for value in 0 .. 2 {
+				let mut secret = self.secret(bit, value);
+				hashes[bit][value] = self.hash.hash(&[&secret]);
+				zeroize(&mut secret);
+			}
+		}
+
+		VerifyingKey {hash: self.hash, hashes}
+	}
+
+	/// Signs a `message`, marking the key as used. Returns
+	/// [`Error::KeyUsed`] if the key has already signed a message.
+	pub fn sign(&mut self, message: &[u8]) -> Result<Signature, Error> {
+		if self.used {
+			return Err(Error::KeyUsed);
+		}
+
+		self.used = true;
+
+		let digest = self.hash.hash(&[message]);
+		let mut secrets = [[0; 32]; 256];
+
+		for bit in 0 .. 256 {
+			secrets[bit] = self.secret(bit, message_bit(&digest, bit));
+		}
+
+		Ok(Signature(secrets))
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.seed);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.seed);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the seed
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey")
+			.field("hash", &self.hash)
+			.field("used", &self.used)
+			.finish_non_exhaustive()
+	}
+}
+
+fn message_bit(digest: &[u8; 32], bit: usize) -> usize {
+	(digest[bit / 8] >> (7 - bit % 8)) as usize & 1
+}
+
+/// A Lamport verifying key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+	hash: HashFunction,
+	hashes: [[[u8; 32]; 2]; 256],
+}
+
+impl VerifyingKey {
+	/// Parses an encoded verifying key for the hash function `hash`.
+	pub fn from_bytes(hash: HashFunction, bytes: &[u8; VERIFYING_KEY_LEN]) -> Self {
+		let mut hashes = [[[0; 32]; 2]; 256];
+
+		for (i, chunk) in bytes.chunks(32).enumerate() {
+			hashes[i / 2][i % 2].copy_from_slice(chunk);
+		}
+
+		Self {hash, hashes}
+	}
+
+	/// Returns the encoding of this key, which is the hashes of the secrets
+	/// for a zero and a one bit, for each bit in turn.
+	pub fn to_bytes(&self) -> [u8; VERIFYING_KEY_LEN] {
+		let mut out = [0; VERIFYING_KEY_LEN];
+
+		for (i, chunk) in out.chunks_mut(32).enumerate() {
+			chunk.copy_from_slice(&self.hashes[i / 2][i % 2]);
+		}
+
+		out
+	}
+
+	/// Returns the hash function of this key.
+	pub fn hash_function(&self) -> HashFunction {
+		self.hash
+	}
+
+	/// Verifies a `signature` over a `message`.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		let digest = self.hash.hash(&[message]);
+		let mut valid = true;
+
+		for bit in 0 .. 256 {
+			let expected = &self.hashes[bit][message_bit(&digest, bit)];
+			valid &= self.hash.hash(&[&signature.0[bit]]) == *expected;
+		}
+
+		valid
+	}
+}
+
+/// A Lamport signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature([[u8; 32]; 256]);
+
+impl Signature {
+	/// Parses an encoded signature.
+	pub fn from_bytes(bytes: &[u8; SIGNATURE_LEN]) -> Self {
+		let mut secrets = [[0; 32]; 256];
+
+		for (secret, chunk) in secrets.iter_mut().zip(bytes.chunks(32)) {
+			secret.copy_from_slice(chunk);
+		}
+
+		Self(secrets)
+	}
+
+	/// Returns the encoding of this signature.
+	pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+		let mut out = [0; SIGNATURE_LEN];
+
+		for (chunk, secret) in out.chunks_mut(32).zip(&self.0) {
+			chunk.copy_from_slice(secret);
+		}
+
+		out
+	}
+}
+
+#[test]
+fn test_sign() {
+	for hash in [HashFunction::Sha256, HashFunction::Shake256] {
+		let mut signing_key = SigningKey::from_seed(hash, [7; 32]);
+		let verifying_key = signing_key.verifying_key();
+
+		assert!(!signing_key.is_used());
+		let signature = signing_key.sign(b"message").unwrap();
+		assert!(signing_key.is_used());
+		assert_eq!(signing_key.sign(b"message").unwrap_err(), Error::KeyUsed);
+
+		assert!(verifying_key.verify(b"message", &signature));
+		assert!(!verifying_key.verify(b"massage", &signature));
+
+		let mut modified = signature.to_bytes();
+		modified[1000] ^= 1;
+		assert!(!verifying_key.verify(b"message", &Signature::from_bytes(&modified)));
+
+		let parsed = VerifyingKey::from_bytes(hash, &verifying_key.to_bytes());
+		assert!(parsed.verify(b"message", &Signature::from_bytes(&signature.to_bytes())));
+	}
+}
+
+#[test]
+fn test_state() {
+	let mut signing_key = SigningKey::from_seed(HashFunction::Shake256, [7; 32]);
+	let fresh = signing_key.to_bytes();
+	signing_key.sign(b"message").unwrap();
+
+	let mut restored = SigningKey::from_bytes(&signing_key.to_bytes()).unwrap();
+	assert_eq!(restored.sign(b"message").unwrap_err(), Error::KeyUsed);
+	assert_eq!(restored.verifying_key(), SigningKey::from_bytes(&fresh).unwrap().verifying_key());
+
+	let mut invalid = fresh;
+	invalid[1] = 2;
+	assert_eq!(SigningKey::from_bytes(&invalid).unwrap_err(), Error::InvalidEncoding);
+	invalid[1] = 0;
+	invalid[0] = 2;
+	assert_eq!(SigningKey::from_bytes(&invalid).unwrap_err(), Error::InvalidEncoding);
+}
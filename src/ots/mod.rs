@@ -0,0 +1,122 @@
+//! Hash-based one-time signatures, which are secure as long as the hash
+//! function is, but break if a key signs more than one message. They're
+//! building blocks for schemes such as [`slh_dsa`](crate::slh_dsa), and are
+//! meant for custom constructions and for learning how those work.
+//!
+//! [`lamport`] signatures reveal one of two secrets for every bit of the
+//! message hash, and [`wots`] is WOTS+, which signs base-16 digits by
+//! revealing positions along hash chains, giving much smaller keys and
+//! signatures. Both sign a 32-byte hash of the message, using either SHA-256
+//! or SHAKE256.
+//!
+//! Signing keys track whether they have been used, and refuse to sign a
+//! second time. Their encoding includes that state, so it must be saved
+//! after signing, and an older copy of a key must never be reused.
+
+use core::fmt;
+
+use crate::sha2::Sha256;
+use crate::sha3::Shake256;
+
+pub mod lamport;
+pub mod wots;
+
+/// An error returned when signing or parsing keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The signing key has already signed a message.
+	KeyUsed,
+	/// An encoded key is malformed.
+	InvalidEncoding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::KeyUsed => "one-time signing key already used",
+			Error::InvalidEncoding => "invalid one-time signature key encoding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The hash function a one-time signature scheme is built on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HashFunction {
+	/// SHA-256.
+	Sha256,
+	/// SHAKE256 with a 32-byte output.
+	Shake256,
+}
+
+impl HashFunction {
+	fn hash(self, parts: &[&[u8]]) -> [u8; 32] {
+		match self {
+			HashFunction::Sha256 => {
+				let mut hasher = Sha256::new();
+
+				for part in parts {
+					hasher.add_bytes(part);
+				}
+
+				hasher.out()
+			},
+			HashFunction::Shake256 => {
+				let mut hasher = Shake256::new();
+
+				for part in parts {
+					hasher.add_bytes(part);
+				}
+
+				hasher.out()
+			},
+		}
+	}
+
+	fn to_byte(self) -> u8 {
+		match self {
+			HashFunction::Sha256 => 0,
+			HashFunction::Shake256 => 1,
+		}
+	}
+
+	fn from_byte(byte: u8) -> Result<Self, Error> {
+		match byte {
+			0 => Ok(HashFunction::Sha256),
+			1 => Ok(HashFunction::Shake256),
+			_ => Err(Error::InvalidEncoding),
+		}
+	}
+}
+
+// the encoding shared by the signing keys: the hash function, whether the key
+// has been used, and its seed
+fn encode_signing_key(hash: HashFunction, used: bool, seed: &[u8; 32]) -> [u8; 34] {
+	let mut out = [0; 34];
+	out[0] = hash.to_byte();
+	out[1] = used as u8;
+	out[2 ..].copy_from_slice(seed);
+	out
+}
+
+fn decode_signing_key(bytes: &[u8; 34]) -> Result<(HashFunction, bool, [u8; 32]), Error> {
+	let hash = HashFunction::from_byte(bytes[0])?;
+
+	let used = match bytes[1] {
+		0 => false,
+		1 => true,
+		_ => return Err(Error::InvalidEncoding),
+	};
+
+	let mut seed = [0; 32];
+	seed.copy_from_slice(&bytes[2 ..]);
+
+	Ok((hash, used, seed))
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
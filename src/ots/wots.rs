@@ -0,0 +1,327 @@
+//! WOTS+ one-time signatures with Winternitz parameter 16. The message hash
+//! and a checksum are split into 67 base-16 digits, and each digit is signed
+//! by revealing the value that many steps along a hash chain starting from a
+//! secret. The checksum stops a forger from advancing any chain further.
+//!
+//! The public key is a hash of the ends of all chains, together with a public
+//! seed that keys every chain step, as in XMSS and SLH-DSA. Public keys are
+//! 64 bytes and signatures are 2144 bytes.
+//!
+//! ```
+//! use libkrypton::ots::HashFunction;
+//! use libkrypton::ots::wots::SigningKey;
+//!
+//! // the seed should be generated by a secure random source
+//! let mut signing_key = SigningKey::from_seed(HashFunction::Shake256, [0x42; 32]);
+//! let verifying_key = signing_key.verifying_key();
+//!
+//! let signature = signing_key.sign(b"message").unwrap();
+//! assert!(verifying_key.verify(b"message", &signature));
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use super::{decode_signing_key, encode_signing_key, Error, HashFunction};
+use crate::zeroize::zeroize;
+
+/// The length of an encoded signing key.
+pub const SIGNING_KEY_LEN: usize = 34;
+
+/// The length of an encoded verifying key.
+pub const VERIFYING_KEY_LEN: usize = 64;
+
+/// The length of a signature.
+pub const SIGNATURE_LEN: usize = CHAINS * 32;
+
+// 64 digits of the message hash and 3 of the checksum
+const CHAINS: usize = 67;
+const W: u8 = 16;
+
+/// A WOTS+ signing key, which can sign a single message. It deliberately
+/// doesn't implement [`Clone`], to make accidental reuse harder. Zeroized
+/// when dropped.
+pub struct SigningKey {
+	hash: HashFunction,
+	used: bool,
+	seed: [u8; 32],
+}
+
+impl SigningKey {
+	/// Derives a fresh signing key from a 32-byte seed. A random seed
+	/// generates a new key, and a seed must never be used for more than one
+	/// key.
+	pub fn from_seed(hash: HashFunction, seed: [u8; 32]) -> Self {
+		Self {hash, used: false, seed}
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(hash: HashFunction, rng: &mut R) -> Self {
+		let mut seed = [0; 32];
+		rng.fill_bytes(&mut seed);
+
+		let signing_key = Self::from_seed(hash, seed);
+		zeroize(&mut seed);
+
+		signing_key
+	}
+
+	/// Parses an encoded signing key, including whether it has been used.
+	pub fn from_bytes(bytes: &[u8; SIGNING_KEY_LEN]) -> Result<Self, Error> {
+		let (hash, used, seed) = decode_signing_key(bytes)?;
+		Ok(Self {hash, used, seed})
+	}
+
+	/// Returns the encoding of this signing key, including whether it has
+	/// been used. This must be kept secret.
+	pub fn to_bytes(&self) -> [u8; SIGNING_KEY_LEN] {
+		encode_signing_key(self.hash, self.used, &self.seed)
+	}
+
+	/// Returns whether this key has already signed a message.
+	pub fn is_used(&self) -> bool {
+		self.used
+	}
+
+	fn public_seed(&self) -> [u8; 32] {
+		self.hash.hash(&[&self.seed, &[1]])
+	}
+
+	fn secret(&self, chain: usize) -> [u8; 32] {
+		self.hash.hash(&[&self.seed, &[0], &(chain as u32).to_be_bytes()])
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		let public_seed = self.public_seed();
+		let mut ends = [[0; 32]; CHAINS];
+
+		for i in 0 .. CHAINS {
+			let mut secret = self.secret(i);
+			ends[i] = chain(self.hash, &public_seed, i, &secret, 0, W - 1);
+			zeroize(&mut secret);
+		}
+
+		let root = compress(self.hash, &public_seed, &ends);
+
+		VerifyingKey {hash: self.hash, public_seed, root}
+	}
+
+	/// Signs a `message`, marking the key as used. Returns
+	/// [`Error::KeyUsed`] if the key has already signed a message.
+	pub fn sign(&mut self, message: &[u8]) -> Result<Signature, Error> {
+		if self.used {
+			return Err(Error::KeyUsed);
+		}
+
+		self.used = true;
+
+		let public_seed = self.public_seed();
+		let digits = digits(&self.hash.hash(&[message]));
+		let mut values = [[0; 32]; CHAINS];
+
+		for i in 0 .. CHAINS {
+			let mut secret = self.secret(i);
+			values[i] = chain(self.hash, &public_seed, i, &secret, 0, digits[i]);
+			zeroize(&mut secret);
+		}
+
+		Ok(Signature(values))
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.seed);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.seed);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the seed
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey")
+			.field("hash", &self.hash)
+			.field("used", &self.used)
+			.finish_non_exhaustive()
+	}
+}
+
+// takes `steps` steps along chain `index`, from the value at position `start`
+fn chain(hash: HashFunction, public_seed: &[u8; 32], index: usize, value: &[u8; 32], start: u8, steps: u8) -> [u8; 32] {
+	let mut out = *value;
+
+	for step in start .. start + steps {
+		out = hash.hash(&[public_seed, &(index as u32).to_be_bytes(), &(step as u32).to_be_bytes(), &out]);
+	}
+
+	out
+}
+
+fn compress(hash: HashFunction, public_seed: &[u8; 32], ends: &[[u8; 32]; CHAINS]) -> [u8; 32] {
+	let mut parts: [&[u8]; CHAINS + 1] = [&[]; CHAINS + 1];
+	parts[0] = public_seed;
+
+	for i in 0 .. CHAINS {
+		parts[i + 1] = &ends[i];
+	}
+
+	hash.hash(&parts)
+}
+
+// the base-16 digits of the message hash, followed by those of the checksum
+fn digits(digest: &[u8; 32]) -> [u8; CHAINS] {
+	let mut out = [0; CHAINS];
+
+	for i in 0 .. 32 {
+		out[2 * i] = digest[i] >> 4;
+		out[2 * i + 1] = digest[i] & 15;
+	}
+
+	let checksum: u16 = out[.. 64].iter().map(|&x| (W - 1 - x) as u16).sum();
+
+	// the checksum is at most 960, so it fits in three digits
+	out[64] = (checksum >> 8) as u8;
+	out[65] = (checksum >> 4) as u8 & 15;
+	out[66] = checksum as u8 & 15;
+
+	out
+}
+
+/// A WOTS+ verifying key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+	hash: HashFunction,
+	public_seed: [u8; 32],
+	root: [u8; 32],
+}
+
+impl VerifyingKey {
+	/// Parses an encoded verifying key for the hash function `hash`.
+	pub fn from_bytes(hash: HashFunction, bytes: &[u8; VERIFYING_KEY_LEN]) -> Self {
+		let mut public_seed = [0; 32];
+		let mut root = [0; 32];
+
+		public_seed.copy_from_slice(&bytes[.. 32]);
+		root.copy_from_slice(&bytes[32 ..]);
+
+		Self {hash, public_seed, root}
+	}
+
+	/// Returns the encoding of this key, which is the public seed followed by
+	/// the hash of the chain ends.
+	pub fn to_bytes(&self) -> [u8; VERIFYING_KEY_LEN] {
+		let mut out = [0; VERIFYING_KEY_LEN];
+		out[.. 32].copy_from_slice(&self.public_seed);
+		out[32 ..].copy_from_slice(&self.root);
+		out
+	}
+
+	/// Returns the hash function of this key.
+	pub fn hash_function(&self) -> HashFunction {
+		self.hash
+	}
+
+	/// Verifies a `signature` over a `message`.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		let digits = digits(&self.hash.hash(&[message]));
+		let mut ends = [[0; 32]; CHAINS];
+
+		for i in 0 .. CHAINS {
+			ends[i] = chain(self.hash, &self.public_seed, i, &signature.0[i], digits[i], W - 1 - digits[i]);
+		}
+
+		compress(self.hash, &self.public_seed, &ends) == self.root
+	}
+}
+
+/// A WOTS+ signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature([[u8; 32]; CHAINS]);
+
+impl Signature {
+	/// Parses an encoded signature.
+	pub fn from_bytes(bytes: &[u8; SIGNATURE_LEN]) -> Self {
+		let mut values = [[0; 32]; CHAINS];
+
+		for (value, chunk) in values.iter_mut().zip(bytes.chunks(32)) {
+			value.copy_from_slice(chunk);
+		}
+
+		Self(values)
+	}
+
+	/// Returns the encoding of this signature.
+	pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+		let mut out = [0; SIGNATURE_LEN];
+
+		for (chunk, value) in out.chunks_mut(32).zip(&self.0) {
+			chunk.copy_from_slice(value);
+		}
+
+		out
+	}
+}
+
+// checked against a python transcription of this module, using hashlib's
+// sha256 and shake_256
+#[test]
+fn test_vectors() {
+	use super::hex;
+
+	let mut seed = [0; 32];
+
+	for i in 0 .. 32 {
+		seed[i] = i as u8;
+	}
+
+	let expected = [
+		(HashFunction::Sha256, "8b44d96f214304bc15fe5ccb132bd5d50b3dfd89afc19878ab5cd0141b76a6e7a839595fa068cd2bd850f00744153f2726786ad8320e1f86b236f7c7a5f5010c"),
+		(HashFunction::Shake256, "3f28733170f6a07e7123a5da3e7cc2b1345228eaae3809b4af6c95c30c7f04049b5d5e8caa94273c396827908185e37f63259cb41dd3200686c3bf1e859bb5f0"),
+	];
+
+	for (hash, verifying_key) in expected {
+		let mut signing_key = SigningKey::from_seed(hash, seed);
+		assert_eq!(signing_key.verifying_key().to_bytes(), hex(verifying_key));
+
+		let signature = signing_key.sign(b"hello wots").unwrap();
+		assert!(signing_key.verifying_key().verify(b"hello wots", &signature));
+	}
+
+	let mut signing_key = SigningKey::from_seed(HashFunction::Sha256, seed);
+	let signature = signing_key.sign(b"hello wots").unwrap();
+	assert_eq!(crate::sha2::sha256(&signature.to_bytes()), hex("57673c26c90c63fae12cbfdc2b7696a7eaa484f9c9a4fa6bfebdb4327efe9a8e"));
+}
+
+#[test]
+fn test_sign() {
+	let mut signing_key = SigningKey::from_seed(HashFunction::Sha256, [7; 32]);
+	let verifying_key = VerifyingKey::from_bytes(HashFunction::Sha256, &signing_key.verifying_key().to_bytes());
+
+	let signature = signing_key.sign(b"message").unwrap();
+	assert_eq!(signing_key.sign(b"message").unwrap_err(), Error::KeyUsed);
+	assert!(SigningKey::from_bytes(&signing_key.to_bytes()).unwrap().is_used());
+
+	assert!(verifying_key.verify(b"message", &signature));
+	assert!(!verifying_key.verify(b"massage", &signature));
+
+	// advancing a chain is caught by the checksum
+	for i in [0, 40, 66] {
+		let mut modified = signature.to_bytes();
+		modified[32 * i] ^= 1;
+		assert!(!verifying_key.verify(b"message", &Signature::from_bytes(&modified)));
+	}
+}
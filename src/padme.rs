@@ -0,0 +1,263 @@
+//! Implemented according to section 4 of
+//! ["Reducing Metadata Leakage from Encrypted Files and Communication with PURBs"](https://petsymposium.org/popets/2019/popets-2019-0056.pdf).
+//! Encryption hides what a message says but not how long it is, which can be
+//! enough to tell which of a few known messages was sent. PADMÉ pads lengths
+//! up to one of a small set of sizes, so that a length of `L` reveals only
+//! about `log2(log2(L))` bits, while adding at most 12% of overhead.
+//!
+//! Messages are padded with a 0x80 byte followed by zeros, as in ISO/IEC
+//! 7816-4, so the padding can be removed unambiguously.
+//!
+//! ```
+//! use libkrypton::padme;
+//!
+//! let key = [0x42; 32];
+//! let nonce = [0x24; 24];
+//!
+//! let ciphertext = padme::xchacha20poly1305_encrypt_padded(key, nonce, b"", b"attack at dawn");
+//! // the 14 bytes and the 0x80 byte are padded to 16, then the tag is added
+//! assert_eq!(ciphertext.len(), 16 + 16);
+//!
+//! let plaintext = padme::xchacha20poly1305_decrypt_padded(key, nonce, b"", &ciphertext).unwrap();
+//! assert_eq!(plaintext, b"attack at dawn");
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::chacha20poly1305::{
+	chacha20poly1305_decrypt,
+	chacha20poly1305_encrypt,
+	xchacha20poly1305_decrypt,
+	xchacha20poly1305_encrypt,
+};
+
+/// An error returned when removing padding or decrypting a padded message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The tag is not valid for the ciphertext and associated data, meaning
+	/// the message was corrupted, forged, or encrypted with a different key.
+	InvalidTag,
+	/// The message doesn't end in a 0x80 byte followed by zeros.
+	InvalidPadding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidTag => "invalid authentication tag",
+			Error::InvalidPadding => "invalid padme padding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Returns the length that a length of `len` is padded up to by PADMÉ. Only
+/// the top `log2(log2(len)) + 1` bits of the result can be nonzero.
+pub fn padded_len(len: usize) -> usize {
+	if len < 2 {
+		return len;
+	}
+
+	// the exponent of len, and the number of bits needed to write it
+	let exponent = usize::BITS - 1 - len.leading_zeros();
+	let exponent_bits = u32::BITS - exponent.leading_zeros();
+
+	let mask = (1 << (exponent - exponent_bits)) - 1;
+	(len + mask) & !mask
+}
+
+/// Pads `message` to [`padded_len`] of its length plus one, with a 0x80 byte
+/// followed by zeros.
+#[cfg(feature = "std")]
+pub fn pad(message: &[u8]) -> Vec<u8> {
+	let len = padded_len(message.len() + 1);
+
+	let mut out = Vec::with_capacity(len);
+	out.extend_from_slice(message);
+	out.push(0x80);
+	out.resize(len, 0);
+	out
+}
+
+/// Removes the padding added by [`pad`], returning the message. The whole
+/// input is scanned, so the time taken doesn't depend on where the message
+/// ends. The length of the input isn't checked to be a padded length.
+pub fn unpad(padded: &[u8]) -> Result<&[u8], Error> {
+	// all ones once the 0x80 byte or any other nonzero byte has been seen
+	let mut done = 0usize;
+	let mut found = 0usize;
+	let mut len = 0usize;
+
+	for (i, &byte) in padded.iter().enumerate().rev() {
+		let is_marker = ((byte ^ 0x80) as usize).wrapping_sub(1) >> (usize::BITS - 1);
+		let is_nonzero = (byte as usize).wrapping_neg() >> (usize::BITS - 1);
+
+		let first_marker = is_marker.wrapping_neg() & !done;
+		found |= first_marker;
+		len |= first_marker & i;
+		done |= is_nonzero.wrapping_neg();
+	}
+
+	if found == 0 {
+		return Err(Error::InvalidPadding);
+	}
+
+	Ok(&padded[.. len])
+}
+
+/// Pads `plaintext` with [`pad`] and encrypts it with ChaCha20-Poly1305,
+/// returning the ciphertext followed by the 16-byte tag. The `nonce` *must
+/// not* be reused to encrypt different messages with the same key.
+///
+/// # Panics
+/// * Panics if the padded plaintext is longer than the ChaCha20 stream, about
+///   256 GiB.
+#[cfg(feature = "std")]
+pub fn chacha20poly1305_encrypt_padded(
+	key: [u8; 32],
+	nonce: [u8; 12],
+	associated_data: &[u8],
+	plaintext: &[u8],
+) -> Vec<u8> {
+	let mut out = pad(plaintext);
+	let tag = chacha20poly1305_encrypt(key, nonce, associated_data, &mut out);
+	out.extend_from_slice(&tag);
+	out
+}
+
+/// Decrypts a message encrypted with [`chacha20poly1305_encrypt_padded`], and
+/// removes its padding.
+#[cfg(feature = "std")]
+pub fn chacha20poly1305_decrypt_padded(
+	key: [u8; 32],
+	nonce: [u8; 12],
+	associated_data: &[u8],
+	ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+	decrypt_padded(ciphertext, |buffer, tag| chacha20poly1305_decrypt(key, nonce, associated_data, buffer, tag).is_ok())
+}
+
+/// Pads `plaintext` with [`pad`] and encrypts it with XChaCha20-Poly1305,
+/// returning the ciphertext followed by the 16-byte tag. The `nonce` *must
+/// not* be reused to encrypt different messages with the same key, but it's
+/// long enough to be generated at random.
+///
+/// # Panics
+/// * Panics if the padded plaintext is longer than the ChaCha20 stream, about
+///   256 GiB.
+#[cfg(feature = "std")]
+pub fn xchacha20poly1305_encrypt_padded(
+	key: [u8; 32],
+	nonce: [u8; 24],
+	associated_data: &[u8],
+	plaintext: &[u8],
+) -> Vec<u8> {
+	let mut out = pad(plaintext);
+	let tag = xchacha20poly1305_encrypt(key, nonce, associated_data, &mut out);
+	out.extend_from_slice(&tag);
+	out
+}
+
+/// Decrypts a message encrypted with [`xchacha20poly1305_encrypt_padded`], and
+/// removes its padding.
+#[cfg(feature = "std")]
+pub fn xchacha20poly1305_decrypt_padded(
+	key: [u8; 32],
+	nonce: [u8; 24],
+	associated_data: &[u8],
+	ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+	decrypt_padded(ciphertext, |buffer, tag| xchacha20poly1305_decrypt(key, nonce, associated_data, buffer, tag).is_ok())
+}
+
+#[cfg(feature = "std")]
+fn decrypt_padded(ciphertext: &[u8], decrypt: impl FnOnce(&mut [u8], [u8; 16]) -> bool) -> Result<Vec<u8>, Error> {
+	if ciphertext.len() < 16 {
+		return Err(Error::InvalidTag);
+	}
+
+	let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+	let mut buffer = ciphertext.to_vec();
+
+	if !decrypt(&mut buffer, tag.try_into().unwrap()) {
+		return Err(Error::InvalidTag);
+	}
+
+	let len = unpad(&buffer)?.len();
+	buffer.truncate(len);
+
+	Ok(buffer)
+}
+
+#[test]
+fn test_padded_len() {
+	let expected = [
+		(0, 0),
+		(1, 1),
+		(2, 2),
+		(9, 10),
+		(17, 18),
+		(100, 104),
+		(1000, 1024),
+		(1025, 1088),
+		(100000, 100352),
+		(1 << 20, 1 << 20),
+		((1 << 20) + 1, (1 << 20) + (1 << 15)),
+	];
+
+	for (len, padded) in expected {
+		assert_eq!(padded_len(len), padded);
+	}
+
+	// the overhead is at most 12%
+	for len in 1 .. 100000 {
+		let padded = padded_len(len);
+		assert!(padded >= len && (padded - len) * 100 <= len * 12);
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pad() {
+	assert_eq!(pad(b""), [0x80]);
+	assert_eq!(pad(b"12345678"), b"12345678\x80\0");
+
+	for len in 0 .. 300 {
+		let message = vec![0x80; len];
+		let padded = pad(&message);
+
+		assert_eq!(padded.len(), padded_len(len + 1));
+		assert_eq!(unpad(&padded), Ok(&message[..]));
+	}
+
+	assert_eq!(unpad(b""), Err(Error::InvalidPadding));
+	assert_eq!(unpad(b"\0\0"), Err(Error::InvalidPadding));
+	assert_eq!(unpad(b"abc\x80\x01\0"), Err(Error::InvalidPadding));
+	assert_eq!(unpad(b"abc\x80\0\0"), Ok(&b"abc"[..]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_aead() {
+	let key = [0x42; 32];
+
+	let ciphertext = chacha20poly1305_encrypt_padded(key, [1; 12], b"header", b"hello");
+	assert_eq!(ciphertext.len(), padded_len(6) + 16);
+	assert_eq!(chacha20poly1305_decrypt_padded(key, [1; 12], b"header", &ciphertext).unwrap(), b"hello");
+	assert_eq!(chacha20poly1305_decrypt_padded(key, [1; 12], b"footer", &ciphertext), Err(Error::InvalidTag));
+	assert_eq!(chacha20poly1305_decrypt_padded(key, [1; 12], b"header", &ciphertext[.. 15]), Err(Error::InvalidTag));
+
+	// a validly encrypted message without padding
+	let mut buffer = *b"hello";
+	let tag = xchacha20poly1305_encrypt(key, [2; 24], b"", &mut buffer);
+	let ciphertext = [&buffer[..], &tag].concat();
+	assert_eq!(xchacha20poly1305_decrypt_padded(key, [2; 24], b"", &ciphertext), Err(Error::InvalidPadding));
+}
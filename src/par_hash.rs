@@ -0,0 +1,203 @@
+//! Hashing of large inputs on multiple threads, such as multi-gigabyte
+//! artifacts, with the `rayon` feature. Without it, the same hashes are
+//! computed on one thread.
+//!
+//! SHA-256 and SHA3-256 process their input one block after another, so they
+//! can't be split up as they are. Instead, [`sha256_tree`] and
+//! [`sha3_256_tree`] cut the input into leaves of a fixed size and hash them
+//! into a Merkle tree, as specified for Certificate Transparency in
+//! [IETF RFC 6962](https://datatracker.ietf.org/doc/html/rfc6962): a leaf is
+//! hashed with a `0x00` byte before it, two subtrees with a `0x01` byte
+//! before their hashes, and each left subtree holds the largest power of two
+//! leaves that's less than the total. Different leaf sizes give different
+//! hashes, so the leaf size must be fixed by whatever protocol uses them.
+//! These aren't the same as the plain SHA-256 or SHA3-256 of the input.
+//!
+//! BLAKE3 is a tree hash already, so [`blake3`] gives the plain BLAKE3 hash.
+//!
+//! ```
+//! use libkrypton::par_hash::{sha256_tree, DEFAULT_LEAF_SIZE};
+//!
+//! let artifact = vec![0x42; 3 * DEFAULT_LEAF_SIZE + 1];
+//! let root = sha256_tree(&artifact, DEFAULT_LEAF_SIZE);
+//! ```
+
+#[cfg(feature = "sha2")]
+use crate::sha2::{sha256, Sha256};
+#[cfg(feature = "sha3")]
+use crate::sha3::{sha3_256, Sha3_256};
+
+/// A leaf size of 1 MiB, big enough that hashing the tree takes little more
+/// time than hashing the leaves, and small enough to split inputs of a few
+/// megabytes across threads.
+pub const DEFAULT_LEAF_SIZE: usize = 1 << 20;
+
+// subtrees smaller than this are hashed on the current thread, since handing
+// them to another costs more than it saves
+#[cfg(any(feature = "sha2", feature = "sha3"))]
+const PARALLEL_MIN_LEN: usize = 128 * 1024;
+
+#[cfg(any(feature = "sha2", feature = "sha3"))]
+const LEAF_PREFIX: u8 = 0x00;
+#[cfg(any(feature = "sha2", feature = "sha3"))]
+const NODE_PREFIX: u8 = 0x01;
+
+/// Returns the RFC 6962 Merkle tree hash over SHA-256 of `input`, cut into
+/// leaves of `leaf_size` bytes, the last of which may be shorter.
+///
+/// # Panics
+///
+/// Panics if `leaf_size` is zero.
+#[cfg(feature = "sha2")]
+pub fn sha256_tree(input: &[u8], leaf_size: usize) -> [u8; 32] {
+	fn hash(prefix: u8, parts: &[&[u8]]) -> [u8; 32] {
+		let mut hasher = Sha256::new();
+		hasher.add_bytes(&[prefix]);
+
+		for part in parts {
+			hasher.add_bytes(part);
+		}
+
+		hasher.out()
+	}
+
+	tree_hash(input, leaf_size, sha256(&[]), hash)
+}
+
+/// Returns the RFC 6962 Merkle tree hash over SHA3-256 of `input`, cut into
+/// leaves of `leaf_size` bytes, the last of which may be shorter.
+///
+/// # Panics
+///
+/// Panics if `leaf_size` is zero.
+#[cfg(feature = "sha3")]
+pub fn sha3_256_tree(input: &[u8], leaf_size: usize) -> [u8; 32] {
+	fn hash(prefix: u8, parts: &[&[u8]]) -> [u8; 32] {
+		let mut hasher = Sha3_256::new();
+		hasher.add_bytes(&[prefix]);
+
+		for part in parts {
+			hasher.add_bytes(part);
+		}
+
+		hasher.out()
+	}
+
+	tree_hash(input, leaf_size, sha3_256(&[]), hash)
+}
+
+/// Returns the BLAKE3 hash of `input`, the same as
+/// [`blake3::hash`](crate::blake3::hash).
+#[cfg(feature = "blake3")]
+pub fn blake3(input: &[u8]) -> [u8; 32] {
+	let mut hasher = crate::blake3::Hasher::new();
+
+	#[cfg(feature = "rayon")]
+	hasher.add_bytes_rayon(input);
+	#[cfg(not(feature = "rayon"))]
+	hasher.add_bytes(input);
+
+	hasher.out()
+}
+
+// the tree hash of an empty input is the hash of nothing, without a prefix
+#[cfg(any(feature = "sha2", feature = "sha3"))]
+fn tree_hash(input: &[u8], leaf_size: usize, empty: [u8; 32], hash: fn(u8, &[&[u8]]) -> [u8; 32]) -> [u8; 32] {
+	assert!(leaf_size != 0, "leaf size must not be zero");
+
+	if input.is_empty() {
+		return empty;
+	}
+
+	subtree_hash(input, leaf_size, hash)
+}
+
+#[cfg(any(feature = "sha2", feature = "sha3"))]
+fn subtree_hash(input: &[u8], leaf_size: usize, hash: fn(u8, &[&[u8]]) -> [u8; 32]) -> [u8; 32] {
+	let leaves = input.len().div_ceil(leaf_size);
+
+	if leaves <= 1 {
+		return hash(LEAF_PREFIX, &[input]);
+	}
+
+	// the largest power of two less than the number of leaves
+	let left_leaves = 1 << (usize::BITS - 1 - (leaves - 1).leading_zeros());
+	let (left, right) = input.split_at(left_leaves * leaf_size);
+
+	let (left_hash, right_hash) = join(
+		|| subtree_hash(left, leaf_size, hash),
+		|| subtree_hash(right, leaf_size, hash),
+		input.len() >= PARALLEL_MIN_LEN,
+	);
+
+	hash(NODE_PREFIX, &[&left_hash, &right_hash])
+}
+
+#[cfg(all(feature = "rayon", any(feature = "sha2", feature = "sha3")))]
+fn join<A, B>(a: A, b: B, parallel: bool) -> ([u8; 32], [u8; 32])
+where
+	A: FnOnce() -> [u8; 32] + Send,
+	B: FnOnce() -> [u8; 32] + Send,
+{
+	match parallel {
+		true => rayon::join(a, b),
+		false => (a(), b()),
+	}
+}
+
+#[cfg(all(not(feature = "rayon"), any(feature = "sha2", feature = "sha3")))]
+fn join<A, B>(a: A, b: B, _parallel: bool) -> ([u8; 32], [u8; 32])
+where
+	A: FnOnce() -> [u8; 32],
+	B: FnOnce() -> [u8; 32],
+{
+	(a(), b())
+}
+
+#[cfg(all(test, feature = "std", any(feature = "sha2", feature = "sha3")))]
+use crate::test_util::hex;
+
+#[cfg(all(test, feature = "std"))]
+fn test_input(len: usize, multiplier: usize, modulus: usize) -> std::vec::Vec<u8> {
+	(0 .. len).map(|i| (i * multiplier % modulus) as u8).collect()
+}
+
+// checked against a python transcription of section 2.1 of RFC 6962, using
+// hashlib.sha256
+#[cfg(all(feature = "std", feature = "sha2"))]
+#[test]
+fn test_sha256_tree() {
+	let input = test_input(1000, 1, 251);
+
+	assert_eq!(sha256_tree(b"", 64), hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+	assert_eq!(sha256_tree(b"abc", 64), hex("609f6e36d2405585188d5cfd761f407c7cc46a7d3f314c88270469dde315fcd1"));
+	assert_eq!(sha256_tree(&input, 64), hex("97936bb0af1ebe1f8bdf0438946f7b3feb5acba0db69b044bcb91234952a75b2"));
+	assert_eq!(sha256_tree(&input, 300), hex("86922dd62088810161e79f17538a89ac1305d31df09db5ffbb8fdfe02a0c4a8e"));
+
+	// large enough to be split across threads
+	let input = test_input(1 << 20, 7, 256);
+	assert_eq!(sha256_tree(&input, 1024), hex("d2fb24e38ad9775f6f337f2f8bdc1aab820c557c19aa4a5c04fd39786bdec7a5"));
+}
+
+// checked against a python transcription of section 2.1 of RFC 6962, using
+// hashlib.sha3_256
+#[cfg(all(feature = "std", feature = "sha3"))]
+#[test]
+fn test_sha3_256_tree() {
+	let input = test_input(1000, 1, 251);
+
+	assert_eq!(sha3_256_tree(b"", 64), hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"));
+	assert_eq!(sha3_256_tree(b"abc", 64), hex("6088d83b4b6a2506789885b50023b979f9368d5d50f6f5e78c53f6e07bddb119"));
+	assert_eq!(sha3_256_tree(&input, 64), hex("59fce8ed1cd00b1c33a2de5960e3d8f918db4d6e40ecaf4b42785c53722912a5"));
+	assert_eq!(sha3_256_tree(&input, 300), hex("9c37583b7cfab20da2355fe22fc3f23fbda5dc146d5adbb546ece84fa8d747e4"));
+
+	let input = test_input(1 << 20, 7, 256);
+	assert_eq!(sha3_256_tree(&input, 1024), hex("128301fe76863d5b3675ce76faa7eda8effda9ab651429508cbbf318c147e1ed"));
+}
+
+#[cfg(all(feature = "std", feature = "blake3"))]
+#[test]
+fn test_blake3() {
+	let input = test_input(1 << 20, 7, 256);
+	assert_eq!(blake3(&input), crate::blake3::hash(&input));
+}
@@ -2,10 +2,12 @@
 
 use core::convert::TryInto;
 
+use crate::choice::ConstantTimeEq;
 use crate::segmented_int::{SegmentedInt, SegmentedIntDescriptor};
+use crate::zeroize::zeroize;
 
 /// 130-bit integer type that subtracts out 2 ** 130 - 5 until results fit within the bit length.
-type Num = SegmentedInt<Poly1305Descriptor>;
+type Num = SegmentedInt<Poly1305Descriptor, 5>;
 
 struct Poly1305Descriptor;
 
@@ -82,29 +84,10 @@ fn clamp_radix(radix: &mut [u8; 16]) {
 
 /// Generates a Poly1305 tag for a `message`. While `radix` may be reused, `nonce`
 /// *must* only be used once. Both `radix` and `nonce` *must* be kept secret.
-pub fn poly1305(mut message: &[u8], mut radix: [u8; 16], nonce: [u8; 16]) -> [u8; 16] {
-	clamp_radix(&mut radix);
-
-	let radix = Num::from_16_le_bytes(radix);
-
-	let mut accum = Num::zero();
-
-	while message.len() >= 16 {
-		let val = Num::from_complete_chunk(message[0 .. 16].try_into().unwrap());
-		accum += val;
-		accum *= radix;
-		message = &message[16 ..];
-	}
-
-	if message.len() >= 1 {
-		let val = Num::from_incomplete_chunk(message);
-		accum += val;
-		accum *= radix;
-	}
-
-	accum = accum + Num::from_16_le_bytes(nonce);
-
-	accum.to_16_le_bytes()
+pub fn poly1305(message: &[u8], radix: [u8; 16], nonce: [u8; 16]) -> [u8; 16] {
+	let mut poly1305 = Poly1305::new(radix, nonce);
+	poly1305.add_bytes(message);
+	poly1305.out()
 }
 
 /// Verifies a Poly1305 `tag` given the original `message`, `radix`, and `nonce`
@@ -113,19 +96,117 @@ pub fn poly1305(mut message: &[u8], mut radix: [u8; 16], nonce: [u8; 16]) -> [u8
 /// Poly1305 tags instead of using `==` on tags.
 pub fn poly1305_verify(message: &[u8], radix: [u8; 16], nonce: [u8; 16], tag: [u8; 16]) -> bool {
 	let correct_tag = poly1305(message, radix, nonce);
-	constant_time_compare(tag, correct_tag)
+	tag.ct_eq(&correct_tag).into()
 }
 
-fn constant_time_compare(tag_a: [u8; 16], tag_b: [u8; 16]) -> bool {
-	let mut equal = true;
+/// Struct used for computing Poly1305 tags on data not stored contiguously in
+/// memory. The same rules apply to `radix` and `nonce` as for [`poly1305`].
+#[derive(Clone)]
+pub struct Poly1305 {
+	radix: Num,
+	nonce: [u8; 16],
+	accum: Num,
+	buffer: [u8; 16],
+	buffer_len: usize,
+}
 
-	for i in 0 .. 16 {
-		equal &= tag_a[i] == tag_b[i];
+impl Poly1305 {
+	/// Creates a new Poly1305 instance.
+	pub fn new(mut radix: [u8; 16], nonce: [u8; 16]) -> Self {
+		clamp_radix(&mut radix);
+
+		Self {
+			radix: Num::from_16_le_bytes(radix),
+			nonce,
+			accum: Num::zero(),
+			buffer: [0; 16],
+			buffer_len: 0,
+		}
+	}
+
+	/// Adds bytes of the message.
+	pub fn add_bytes(&mut self, mut bytes: &[u8]) {
+		if self.buffer_len != 0 {
+			let taking = (16 - self.buffer_len).min(bytes.len());
+			self.buffer[self.buffer_len .. self.buffer_len + taking].copy_from_slice(&bytes[.. taking]);
+			self.buffer_len += taking;
+			bytes = &bytes[taking ..];
+
+			if self.buffer_len != 16 {
+				return;
+			}
+
+			let chunk = self.buffer;
+			self.add_chunk(Num::from_complete_chunk(&chunk));
+			self.buffer_len = 0;
+		}
+
+		while bytes.len() >= 16 {
+			self.add_chunk(Num::from_complete_chunk(bytes[0 .. 16].try_into().unwrap()));
+			bytes = &bytes[16 ..];
+		}
+
+		self.buffer[.. bytes.len()].copy_from_slice(bytes);
+		self.buffer_len = bytes.len();
+	}
+
+	/// Pads the message with zeros up to a multiple of 16 bytes, as done by
+	/// the ChaCha20-Poly1305 AEAD construction.
+	pub fn pad_to_block(&mut self) {
+		if self.buffer_len != 0 {
+			self.add_bytes(&[0; 16][self.buffer_len ..]);
+		}
+	}
+
+	fn add_chunk(&mut self, chunk: Num) {
+		self.accum += chunk;
+		self.accum *= self.radix;
 	}
 
-	equal
+	/// Consumes the Poly1305 instance and outputs the tag of the concatenation
+	/// of all bytes added to it.
+	pub fn out(mut self) -> [u8; 16] {
+		if self.buffer_len != 0 {
+			let chunk = Num::from_incomplete_chunk(&self.buffer[.. self.buffer_len]);
+			self.add_chunk(chunk);
+		}
+
+		// the nonce is added as a 128-bit integer after the final reduction,
+		// not modulo 2^130 - 5
+		let accum = u128::from_le_bytes(self.accum.to_16_le_bytes());
+		accum.wrapping_add(u128::from_le_bytes(self.nonce)).to_le_bytes()
+	}
+
+	/// Consumes the Poly1305 instance and checks, in constant time, that the
+	/// tag of all bytes added to it is `tag`.
+	pub fn verify(self, tag: [u8; 16]) -> bool {
+		tag.ct_eq(&self.out()).into()
+	}
+}
+
+impl Drop for Poly1305 {
+	fn drop(&mut self) {
+		zeroize(&mut self.radix.segments);
+		zeroize(&mut self.nonce);
+		zeroize(&mut self.accum.segments);
+		zeroize(&mut self.buffer);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Poly1305 {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.radix.segments);
+		zeroize(&mut self.nonce);
+		zeroize(&mut self.accum.segments);
+		zeroize(&mut self.buffer);
+		self.buffer_len = 0;
+	}
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Poly1305 {}
+
 #[test]
 #[cfg(feature = "std")]
 fn rfc8439_main_test_vector() {
@@ -145,10 +226,40 @@ fn rfc8439_main_test_vector() {
 	for i in 0 .. 16 {
 		print!("{:>02x}", tag[i]);
 	}
-	println!("");
+	println!();
 
 	assert!(tag == [
 		0xa8, 0x06, 0x1d, 0xc1, 0x30, 0x51, 0x36, 0xc6,
 		0xc2, 0x2b, 0x8b, 0xaf, 0x0c, 0x01, 0x27, 0xa9,
 	]);
 }
+
+#[test]
+fn streaming_matches_one_shot() {
+	let message = [0x5a; 100];
+	let radix = [0x11; 16];
+	let nonce = [0x22; 16];
+
+	for split in [0, 1, 15, 16, 17, 50, 100] {
+		let mut poly1305_instance = Poly1305::new(radix, nonce);
+		poly1305_instance.add_bytes(&message[.. split]);
+		poly1305_instance.add_bytes(&message[split ..]);
+
+		assert_eq!(poly1305_instance.out(), poly1305(&message, radix, nonce));
+	}
+}
+
+// the accumulator plus the nonce exceeds 2^130 - 5 here, checked against the
+// Poly1305 class of python's cryptography package, with the radix and nonce
+// concatenated as its key
+#[test]
+fn nonce_added_after_reduction() {
+	let message = [0x01; 32];
+	let radix = [0x11; 16];
+	let nonce = [0xff; 16];
+
+	assert_eq!(poly1305(&message, radix, nonce), [
+		0xce, 0x33, 0xbb, 0x19, 0x46, 0xed, 0x78, 0x23,
+		0xee, 0xd9, 0x69, 0x60, 0xc9, 0xf9, 0x8d, 0xd0,
+	]);
+}
@@ -0,0 +1,436 @@
+//! Proofs of work, for throttling abuse such as account sign-ups or
+//! unauthenticated API calls: a service hands out a random challenge, and
+//! only accepts a request along with a solution to it, which takes the client
+//! a tunable amount of work to find and the service almost none to check.
+//!
+//! [`solve`] and [`verify`] are Hashcash-style: a solution is a nonce for
+//! which the hash of the challenge and nonce starts with a given number of
+//! zero bits, so each extra bit doubles the expected work. With the `rayon`
+//! feature, [`solve_parallel`] searches for one on every thread.
+//!
+//! ```
+//! use libkrypton::pow::{solve, verify, HashAlgorithm};
+//!
+//! let challenge = b"random challenge from the server";
+//! let nonce = solve(HashAlgorithm::Blake3, challenge, 12);
+//!
+//! assert!(verify(HashAlgorithm::Blake3, challenge, 12, nonce));
+//! ```
+//!
+//! Hash functions run just as fast on GPUs and ASICs, which lets attackers
+//! with them do the same work far more cheaply. [`Equihash`] is memory-bound
+//! instead: a solution is a set of indices of hashes which XOR to zero, and
+//! finding one takes a table of about 2<sup>n / (k + 1)</sup> hashes, though
+//! checking one takes just 2<sup>k</sup>. It's a smaller version of the
+//! Equihash from ["Equihash: Asymmetric Proof-of-Work Based on the Generalized
+//! Birthday Problem"](https://eprint.iacr.org/2015/946.pdf), with hashes of at
+//! most 128 bits taken from BLAKE3, so its solutions aren't compatible with
+//! Zcash's.
+
+#[cfg(feature = "std")]
+use core::convert::TryInto;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::blake3;
+use crate::sha2::Sha256;
+
+/// The hash function a Hashcash-style proof of work is computed with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+	Sha256,
+	Blake3,
+}
+
+// the hasher with the challenge already absorbed, cloned for every nonce;
+// only one is created for each search, so its size doesn't matter
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
+enum Prefix {
+	Sha256(Sha256),
+	Blake3(blake3::Hasher),
+}
+
+impl Prefix {
+	fn new(algorithm: HashAlgorithm, challenge: &[u8]) -> Self {
+		// the challenge's length is included so that its end is unambiguous
+		let len = (challenge.len() as u64).to_le_bytes();
+
+		match algorithm {
+			HashAlgorithm::Sha256 => {
+				let mut hasher = Sha256::new();
+				hasher.add_bytes(&len);
+				hasher.add_bytes(challenge);
+				Prefix::Sha256(hasher)
+			},
+			HashAlgorithm::Blake3 => {
+				let mut hasher = blake3::Hasher::new_derive_key("libkrypton 2024 hashcash");
+				hasher.add_bytes(&len);
+				hasher.add_bytes(challenge);
+				Prefix::Blake3(hasher)
+			},
+		}
+	}
+
+	fn hash(&self, nonce: u64) -> [u8; 32] {
+		match self.clone() {
+			Prefix::Sha256(mut hasher) => {
+				hasher.add_bytes(&nonce.to_le_bytes());
+				hasher.out()
+			},
+			Prefix::Blake3(mut hasher) => {
+				hasher.add_bytes(&nonce.to_le_bytes());
+				hasher.out()
+			},
+		}
+	}
+}
+
+/// Returns the number of zero bits at the start of `hash`.
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+	let mut bits = 0;
+
+	for &byte in hash {
+		bits += byte.leading_zeros();
+
+		if byte != 0 {
+			break;
+		}
+	}
+
+	bits
+}
+
+/// Returns the hash of `challenge` and `nonce`, which must start with at
+/// least the required number of zero bits for `nonce` to be a solution.
+pub fn hash(algorithm: HashAlgorithm, challenge: &[u8], nonce: u64) -> [u8; 32] {
+	Prefix::new(algorithm, challenge).hash(nonce)
+}
+
+/// Checks that `nonce` solves `challenge` with at least `difficulty` leading
+/// zero bits.
+pub fn verify(algorithm: HashAlgorithm, challenge: &[u8], difficulty: u32, nonce: u64) -> bool {
+	leading_zero_bits(&hash(algorithm, challenge, nonce)) >= difficulty
+}
+
+/// Searches the nonces in `range` in order for one that solves `challenge`
+/// with at least `difficulty` leading zero bits. Disjoint ranges can be
+/// searched on different threads or machines.
+pub fn solve_range(
+	algorithm: HashAlgorithm,
+	challenge: &[u8],
+	difficulty: u32,
+	range: core::ops::Range<u64>,
+) -> Option<u64> {
+	let prefix = Prefix::new(algorithm, challenge);
+	range.into_iter().find(|&nonce| leading_zero_bits(&prefix.hash(nonce)) >= difficulty)
+}
+
+/// Finds the smallest nonce that solves `challenge` with at least
+/// `difficulty` leading zero bits, which takes 2<sup>`difficulty`</sup>
+/// hashes on average.
+///
+/// # Panics
+/// * Panics if `difficulty` is more than 64, since there may be no solution.
+pub fn solve(algorithm: HashAlgorithm, challenge: &[u8], difficulty: u32) -> u64 {
+	assert!(difficulty <= 64, "difficulty must be at most 64");
+
+	solve_range(algorithm, challenge, difficulty, 0 .. u64::MAX).expect("no nonce solves the challenge")
+}
+
+/// Like [`solve`], but searches on every thread of rayon's thread pool. The
+/// nonce found is the same.
+///
+/// # Panics
+/// * Panics if `difficulty` is more than 64, since there may be no solution.
+#[cfg(feature = "rayon")]
+pub fn solve_parallel(algorithm: HashAlgorithm, challenge: &[u8], difficulty: u32) -> u64 {
+	use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+	// each thread takes blocks of nonces at a time, so that it isn't
+	// synchronizing with the others after every hash
+	const BLOCK: u64 = 1 << 12;
+
+	assert!(difficulty <= 64, "difficulty must be at most 64");
+
+	let prefix = Prefix::new(algorithm, challenge);
+
+	(0 .. u64::MAX / BLOCK)
+		.into_par_iter()
+		.find_map_first(|block| {
+			(block * BLOCK .. (block + 1) * BLOCK).find(|&nonce| leading_zero_bits(&prefix.hash(nonce)) >= difficulty)
+		})
+		.expect("no nonce solves the challenge")
+}
+
+/// A memory-bound proof of work with parameters `n` and `k`. A solution is
+/// 2<sup>k</sup> distinct indices of `n`-bit hashes which XOR to zero, found
+/// by colliding `n / (k + 1)` bits of them at a time with Wagner's algorithm.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Equihash {
+	n: u32,
+	k: u32,
+}
+
+/// A solution to an [`Equihash`] challenge, which is the nonce it was found
+/// for and the indices of its hashes.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+	pub nonce: u64,
+	pub indices: Vec<u32>,
+}
+
+// a partial solution, with the XOR of the hashes at its indices
+#[cfg(feature = "std")]
+struct Row {
+	hash: u128,
+	indices: Vec<u32>,
+}
+
+#[cfg(feature = "std")]
+impl Equihash {
+	/// Parameters which take about 20 MiB of memory and a fraction of a
+	/// second to solve, and 32 hashes to check.
+	pub const DEFAULT: Self = Self {n: 96, k: 5};
+
+	/// Creates parameters for solutions of 2<sup>`k`</sup> `n`-bit hashes.
+	///
+	/// # Panics
+	/// * Panics if `n` isn't a nonzero multiple of `k + 1`, `k` is 0 or more
+	///   than 10, `n` is more than 128, or `n / (k + 1)` is more than 25,
+	///   which would take tens of gigabytes of memory to solve.
+	pub fn new(n: u32, k: u32) -> Self {
+		assert!(k != 0 && k <= 10, "k must be between 1 and 10");
+		assert!(n != 0 && n <= 128 && n.is_multiple_of(k + 1), "n must be a nonzero multiple of k + 1, up to 128");
+		assert!(n / (k + 1) <= 25, "n / (k + 1) must be at most 25");
+
+		Self {n, k}
+	}
+
+	// the number of bits collided at each step
+	fn collision_bits(self) -> u32 {
+		self.n / (self.k + 1)
+	}
+
+	// the number of hashes in the initial table
+	fn table_len(self) -> u32 {
+		1 << (self.collision_bits() + 1)
+	}
+
+	fn prefix(self, challenge: &[u8], nonce: u64) -> blake3::Hasher {
+		let mut hasher = blake3::Hasher::new_derive_key("libkrypton 2024 equihash");
+		hasher.add_bytes(&self.n.to_le_bytes());
+		hasher.add_bytes(&self.k.to_le_bytes());
+		hasher.add_bytes(&(challenge.len() as u64).to_le_bytes());
+		hasher.add_bytes(challenge);
+		hasher.add_bytes(&nonce.to_le_bytes());
+		hasher
+	}
+
+	// the first n bits of the hash at an index, as the low n bits of the result
+	fn hash(self, prefix: &blake3::Hasher, index: u32) -> u128 {
+		let mut hasher = prefix.clone();
+		hasher.add_bytes(&index.to_le_bytes());
+
+		let hash = u128::from_be_bytes(hasher.out()[.. 16].try_into().unwrap());
+		hash >> (128 - self.n)
+	}
+
+	/// Finds a solution to `challenge`, trying one nonce after another until
+	/// there is one, which takes about two tries on average.
+	pub fn solve(self, challenge: &[u8]) -> Solution {
+		(0 ..)
+			.find_map(|nonce| {
+				let indices = self.solve_nonce(challenge, nonce)?;
+				Some(Solution {nonce, indices})
+			})
+			.unwrap()
+	}
+
+	fn solve_nonce(self, challenge: &[u8], nonce: u64) -> Option<Vec<u32>> {
+		let prefix = self.prefix(challenge, nonce);
+		let bits = self.collision_bits();
+
+		let mut rows: Vec<_> = (0 .. self.table_len())
+			.map(|index| Row {hash: self.hash(&prefix, index), indices: std::vec![index]})
+			.collect();
+
+		// each step collides the next bits, with the last one colliding the
+		// remaining 2 * bits so that the hashes XOR to zero
+		for step in 1 .. self.k + 1 {
+			let shift = match step == self.k {
+				true => 0,
+				false => self.n - step * bits,
+			};
+
+			rows.sort_unstable_by_key(|row| row.hash >> shift);
+			rows = collide(&rows, shift);
+		}
+
+		rows.into_iter().map(|row| row.indices).find(|indices| all_distinct(indices))
+	}
+
+	/// Checks that `solution` solves `challenge`.
+	pub fn verify(self, challenge: &[u8], solution: &Solution) -> bool {
+		let indices = &solution.indices;
+
+		if indices.len() != 1 << self.k || indices.iter().any(|&index| index >= self.table_len()) {
+			return false;
+		}
+
+		if !all_distinct(indices) {
+			return false;
+		}
+
+		let prefix = self.prefix(challenge, solution.nonce);
+
+		match self.verify_subtree(&prefix, indices) {
+			Some(hash) => hash == 0,
+			None => false,
+		}
+	}
+
+	// returns the XOR of the subtree's hashes, if its halves are in order and
+	// collide on the bits for its height
+	fn verify_subtree(self, prefix: &blake3::Hasher, indices: &[u32]) -> Option<u128> {
+		if indices.len() == 1 {
+			return Some(self.hash(prefix, indices[0]));
+		}
+
+		let (left, right) = indices.split_at(indices.len() / 2);
+
+		if left[0] >= right[0] {
+			return None;
+		}
+
+		let hash = self.verify_subtree(prefix, left)? ^ self.verify_subtree(prefix, right)?;
+		let height = indices.len().trailing_zeros();
+
+		match hash >> (self.n - height * self.collision_bits()) == 0 {
+			true => Some(hash),
+			false => None,
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl Default for Equihash {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+// combines every pair of rows, sorted by the bits above `shift`, which agree
+// on those bits and have no indices in common
+#[cfg(feature = "std")]
+fn collide(rows: &[Row], shift: u32) -> Vec<Row> {
+	let mut out = Vec::new();
+	let mut start = 0;
+
+	while start < rows.len() {
+		let key = rows[start].hash >> shift;
+		let end = start + rows[start ..].iter().take_while(|row| row.hash >> shift == key).count();
+
+		for i in start .. end {
+			for j in i + 1 .. end {
+				let (a, b) = (&rows[i], &rows[j]);
+
+				if a.indices.iter().any(|index| b.indices.contains(index)) {
+					continue;
+				}
+
+				// the half with the smaller first index goes first, so that
+				// each solution has only one order
+				let (first, second) = if a.indices[0] < b.indices[0] {(a, b)} else {(b, a)};
+
+				let mut indices = Vec::with_capacity(2 * a.indices.len());
+				indices.extend_from_slice(&first.indices);
+				indices.extend_from_slice(&second.indices);
+
+				out.push(Row {hash: a.hash ^ b.hash, indices});
+			}
+		}
+
+		start = end;
+	}
+
+	out
+}
+
+#[cfg(feature = "std")]
+fn all_distinct(indices: &[u32]) -> bool {
+	let mut sorted = indices.to_vec();
+	sorted.sort_unstable();
+	sorted.windows(2).all(|pair| pair[0] != pair[1])
+}
+
+#[test]
+fn test_leading_zero_bits() {
+	assert_eq!(leading_zero_bits(&[0xff]), 0);
+	assert_eq!(leading_zero_bits(&[0x00, 0x00, 0x1f, 0x00]), 19);
+	assert_eq!(leading_zero_bits(&[0x00; 32]), 256);
+}
+
+#[test]
+fn test_hashcash() {
+	for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Blake3] {
+		let nonce = solve(algorithm, b"challenge", 10);
+		assert!(verify(algorithm, b"challenge", 10, nonce));
+		assert!(!verify(algorithm, b"challenge", 64, nonce));
+
+		// every smaller nonce fails
+		assert_eq!(solve_range(algorithm, b"challenge", 10, 0 .. nonce), None);
+		assert_eq!(solve_range(algorithm, b"challenge", 10, nonce .. nonce + 1), Some(nonce));
+	}
+
+	// checked against python's hashlib.sha256
+	let mut expected = [0; 32];
+	crate::encoding::decode_hex("000ae09067d473d3b9672f4421e8977b60c9e74459124f96b081a6a618bec731", &mut expected).unwrap();
+	assert_eq!(solve(HashAlgorithm::Sha256, b"challenge", 10), 47);
+	assert_eq!(hash(HashAlgorithm::Sha256, b"challenge", 47), expected);
+
+	// the length prefix keeps the challenge and nonce apart
+	assert_ne!(hash(HashAlgorithm::Sha256, b"a", 0), hash(HashAlgorithm::Sha256, b"a\0", 0));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_solve_parallel() {
+	let nonce = solve_parallel(HashAlgorithm::Blake3, b"challenge", 14);
+	assert!(verify(HashAlgorithm::Blake3, b"challenge", 14, nonce));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_equihash() {
+	let equihash = Equihash::new(48, 3);
+	let solution = equihash.solve(b"challenge");
+
+	assert_eq!(solution.indices.len(), 8);
+	assert!(equihash.verify(b"challenge", &solution));
+	assert!(!equihash.verify(b"other challenge", &solution));
+	assert!(!Equihash::new(48, 2).verify(b"challenge", &solution));
+
+	// the halves of every subtree must be in order
+	let mut swapped = solution.clone();
+	swapped.indices.swap(0, 1);
+	assert!(!equihash.verify(b"challenge", &swapped));
+
+	let mut swapped = solution.clone();
+	swapped.indices.rotate_left(4);
+	assert!(!equihash.verify(b"challenge", &swapped));
+
+	let mut repeated = solution.clone();
+	repeated.indices[1] = repeated.indices[0];
+	assert!(!equihash.verify(b"challenge", &repeated));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_equihash_default() {
+	let solution = Equihash::DEFAULT.solve(b"challenge");
+	assert!(Equihash::DEFAULT.verify(b"challenge", &solution));
+}
@@ -0,0 +1,105 @@
+//! Random bytes from the operating system's cryptographically secure random
+//! number generator, such as `getrandom(2)` on Linux or `BCryptGenRandom` on
+//! Windows, by way of the [getrandom](https://crates.io/crates/getrandom)
+//! crate. These are suitable for private keys, nonces and the extra
+//! randomness of hedged signatures.
+//!
+//! ```
+//! use libkrypton::ed25519::Keypair;
+//! use libkrypton::rand::SystemRandom;
+//!
+//! let mut seed = [0; 32];
+//! SystemRandom::new().fill(&mut seed).unwrap();
+//!
+//! let keypair = Keypair::from_seed(seed);
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+/// An error returned when the operating system can't provide random bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The operating system's random number generator failed, or this
+	/// platform doesn't have one.
+	Unavailable,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::Unavailable => "operating system random number generator is unavailable",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The operating system's random number generator. It holds no state, so
+/// it's cheap to create wherever random bytes are needed.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemRandom {
+	_private: (),
+}
+
+impl SystemRandom {
+	pub fn new() -> Self {
+		Self {_private: ()}
+	}
+
+	/// Fills `dest` with random bytes. On an error, `dest` must not be used,
+	/// since it may have been partially filled or left as it was.
+	pub fn fill(&self, dest: &mut [u8]) -> Result<(), Error> {
+		getrandom::getrandom(dest).map_err(|_| Error::Unavailable)
+	}
+}
+
+/// Lets [`SystemRandom`] be passed to functions that take a random number
+/// generator, such as [`Keypair::generate`](crate::ed25519::Keypair::generate).
+/// [`fill_bytes`](RngCore::fill_bytes) panics if the operating system can't
+/// provide random bytes.
+#[cfg(feature = "rand_core")]
+impl RngCore for SystemRandom {
+	fn next_u32(&mut self) -> u32 {
+		rand_core::impls::next_u32_via_fill(self)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		rand_core::impls::next_u64_via_fill(self)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		self.fill(dest).expect("operating system random number generator is unavailable");
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill(dest).map_err(|_| {
+			let code = core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START).unwrap();
+			rand_core::Error::from(code)
+		})
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl CryptoRng for SystemRandom {}
+
+#[test]
+fn test_fill() {
+	let mut buf_a = [0; 64];
+	let mut buf_b = [0; 64];
+
+	SystemRandom::new().fill(&mut buf_a).unwrap();
+	SystemRandom::new().fill(&mut buf_b).unwrap();
+
+	// either of these failing by chance has probability 2 ** -512
+	assert_ne!(buf_a, [0; 64]);
+	assert_ne!(buf_a, buf_b);
+
+	// empty buffers are fine too
+	SystemRandom::new().fill(&mut []).unwrap();
+}
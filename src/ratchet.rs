@@ -0,0 +1,526 @@
+//! Implemented according to [the Double Ratchet specification](https://signal.org/docs/specifications/doubleratchet/).
+//! The Double Ratchet keeps a conversation encrypted with keys that change
+//! with every message, so that compromising the current state reveals
+//! neither earlier messages nor, once both parties have sent a new message,
+//! later ones.
+//!
+//! Both parties start from a 32-byte secret agreed on beforehand, for
+//! example with X3DH or a [Noise](crate::noise) handshake, along with the
+//! responder's initial ratchet key. The root chain uses HKDF-SHA256, the
+//! sending and receiving chains are each a [`ChainKey`], and messages are
+//! encrypted with ChaCha20-Poly1305.
+//!
+//! Each message is a 40-byte header holding the sender's current ratchet
+//! public key and two big-endian 32-bit counters, followed by the ciphertext
+//! and the 16-byte tag, for a total of [`OVERHEAD`] bytes more than the
+//! plaintext. Messages may arrive out of order, up to [`MAX_SKIP`] messages
+//! ahead of the last one received.
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20poly1305::{chacha20poly1305_decrypt, chacha20poly1305_encrypt};
+use crate::chain_key::{ChainKey, MessageKey};
+use crate::hkdf::hkdf_sha256;
+use crate::x25519::{PublicKey, StaticSecret};
+use crate::zeroize::zeroize;
+
+/// The number of bytes an encrypted message is longer than its plaintext.
+pub const OVERHEAD: usize = HEADER_LEN + 16;
+
+/// The maximum number of message keys skipped over in a single chain, and
+/// the maximum number kept around for messages that haven't arrived yet.
+pub const MAX_SKIP: u32 = 1000;
+
+const HEADER_LEN: usize = 40;
+
+/// An error returned by [`Ratchet::encrypt`] or [`Ratchet::decrypt`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The message is too short to hold a header and tag.
+	InvalidMessage,
+	/// The message was modified, replayed, or isn't part of this
+	/// conversation.
+	DecryptionFailed,
+	/// The message would require skipping more than [`MAX_SKIP`] message
+	/// keys.
+	TooManySkippedMessages,
+	/// The other party's ratchet public key is a small-order point.
+	InvalidPublicKey,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidMessage => "invalid ratchet message",
+			Error::DecryptionFailed => "failed to decrypt ratchet message",
+			Error::TooManySkippedMessages => "too many skipped ratchet messages",
+			Error::InvalidPublicKey => "invalid ratchet public key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+// KDF_RK, returning the new root key and chain key
+fn kdf_root(root_key: &[u8; 32], dh_out: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+	let mut okm = [0; 64];
+	hkdf_sha256(root_key, dh_out, b"libkrypton/ratchet/root", &mut okm);
+
+	let keys = (okm[0 .. 32].try_into().unwrap(), okm[32 .. 64].try_into().unwrap());
+	zeroize(&mut okm);
+
+	keys
+}
+
+fn message_cipher_key(message_key: &[u8; 32]) -> ([u8; 32], [u8; 12]) {
+	let mut okm = [0; 44];
+	hkdf_sha256(&[], message_key, b"libkrypton/ratchet/message", &mut okm);
+
+	let keys = (okm[0 .. 32].try_into().unwrap(), okm[32 .. 44].try_into().unwrap());
+	zeroize(&mut okm);
+
+	keys
+}
+
+fn associated_data_with_header(associated_data: &[u8], header: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(associated_data.len() + header.len());
+	out.extend_from_slice(associated_data);
+	out.extend_from_slice(header);
+	out
+}
+
+struct SkippedKey {
+	pub_key: PublicKey,
+	message_key: MessageKey,
+}
+
+// the parts of the receiving state that are only committed once a message
+// has been authenticated
+struct ReceivingChain {
+	root_key: [u8; 32],
+	remote_pub_key: PublicKey,
+	chain_key: ChainKey,
+	skipped: Vec<SkippedKey>,
+	new_chain: bool,
+}
+
+impl ReceivingChain {
+	fn skip_until(&mut self, until: u32) -> Result<(), Error> {
+		if until > self.chain_key.index().saturating_add(MAX_SKIP) {
+			return Err(Error::TooManySkippedMessages);
+		}
+
+		while self.chain_key.index() < until {
+			self.skipped.push(SkippedKey {
+				pub_key: self.remote_pub_key,
+				message_key: self.chain_key.next_message_key(),
+			});
+		}
+
+		Ok(())
+	}
+}
+
+impl Drop for ReceivingChain {
+	fn drop(&mut self) {
+		zeroize(&mut self.root_key);
+	}
+}
+
+/// The state of one party in a Double Ratchet conversation. Zeroized when
+/// dropped.
+pub struct Ratchet {
+	root_key: [u8; 32],
+	ratchet_secret: StaticSecret,
+	remote_pub_key: Option<PublicKey>,
+	// none until this party next sends, after receiving a new ratchet key
+	sending_chain: Option<ChainKey>,
+	previous_sending_len: u32,
+	receiving_chain: Option<ChainKey>,
+	skipped: VecDeque<SkippedKey>,
+}
+
+impl Ratchet {
+	/// Creates the state of the initiator, who sends the first message, from
+	/// the `shared_secret` and the responder's initial ratchet public key.
+	pub fn new_initiator(shared_secret: [u8; 32], responder_pub_key: PublicKey) -> Self {
+		Self {
+			root_key: shared_secret,
+			// replaced before the first message is sent
+			ratchet_secret: StaticSecret::from_bytes([0; 32]),
+			remote_pub_key: Some(responder_pub_key),
+			sending_chain: None,
+			previous_sending_len: 0,
+			receiving_chain: None,
+			skipped: VecDeque::new(),
+		}
+	}
+
+	/// Creates the state of the responder from the `shared_secret` and the
+	/// secret for its initial ratchet public key. The responder can only send
+	/// once it has received a message.
+	pub fn new_responder(shared_secret: [u8; 32], ratchet_secret: StaticSecret) -> Self {
+		Self {
+			root_key: shared_secret,
+			ratchet_secret,
+			remote_pub_key: None,
+			sending_chain: None,
+			previous_sending_len: 0,
+			receiving_chain: None,
+			skipped: VecDeque::new(),
+		}
+	}
+
+	/// Returns whether this party can send messages, which the responder can
+	/// only do after receiving one.
+	pub fn can_send(&self) -> bool {
+		self.remote_pub_key.is_some()
+	}
+
+	/// Encrypts `plaintext`, authenticating it along with `associated_data`.
+	/// `randomness` becomes a new ratchet private key if this is the first
+	/// message since receiving one, so it must be freshly generated by a
+	/// secure random source for every call.
+	///
+	/// # Panics
+	/// * Panics if [`Ratchet::can_send`] is false.
+	pub fn encrypt(&mut self, plaintext: &[u8], associated_data: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+		let remote_pub_key = self.remote_pub_key.expect("the responder can't send before receiving a message");
+
+		// the sending half of a DH ratchet step
+		let mut chain_key = match self.sending_chain.take() {
+			Some(chain_key) => chain_key,
+			None => {
+				let ratchet_secret = StaticSecret::from_bytes(randomness);
+				let dh_out = ratchet_secret
+					.diffie_hellman(&remote_pub_key)
+					.map_err(|_| Error::InvalidPublicKey)?;
+
+				let (root_key, chain_key) = kdf_root(&self.root_key, dh_out.as_bytes());
+
+				self.root_key = root_key;
+				self.ratchet_secret = ratchet_secret;
+
+				ChainKey::new(chain_key)
+			},
+		};
+
+		let message_key = chain_key.next_message_key();
+
+		let mut out = Vec::with_capacity(plaintext.len() + OVERHEAD);
+		out.extend_from_slice(PublicKey::from(&self.ratchet_secret).as_bytes());
+		out.extend_from_slice(&self.previous_sending_len.to_be_bytes());
+		out.extend_from_slice(&message_key.index().to_be_bytes());
+		out.extend_from_slice(plaintext);
+
+		let (mut key, nonce) = message_cipher_key(message_key.as_bytes());
+		let associated_data = associated_data_with_header(associated_data, &out[0 .. HEADER_LEN]);
+		let tag = chacha20poly1305_encrypt(key, nonce, &associated_data, &mut out[HEADER_LEN ..]);
+		out.extend_from_slice(&tag);
+
+		zeroize(&mut key);
+
+		self.sending_chain = Some(chain_key);
+
+		Ok(out)
+	}
+
+	/// Like [`Ratchet::encrypt`], but generates the randomness with `rng`,
+	/// which must be a cryptographically secure random number generator.
+	///
+	/// # Panics
+	/// * Panics if [`Ratchet::can_send`] is false.
+	#[cfg(feature = "rand_core")]
+	pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+		&mut self,
+		plaintext: &[u8],
+		associated_data: &[u8],
+		rng: &mut R,
+	) -> Result<Vec<u8>, Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = self.encrypt(plaintext, associated_data, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	/// Decrypts a message from the other party, checking that it was
+	/// encrypted with the same `associated_data`. If decryption fails, the
+	/// state is left unchanged.
+	pub fn decrypt(&mut self, message: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, Error> {
+		if message.len() < OVERHEAD {
+			return Err(Error::InvalidMessage);
+		}
+
+		let header = &message[0 .. HEADER_LEN];
+		let pub_key = PublicKey::from_bytes(header[0 .. 32].try_into().unwrap());
+		let previous_chain_len = u32::from_be_bytes(header[32 .. 36].try_into().unwrap());
+		let index = u32::from_be_bytes(header[36 .. 40].try_into().unwrap());
+
+		let associated_data = associated_data_with_header(associated_data, header);
+		let ciphertext = &message[HEADER_LEN .. message.len() - 16];
+		let tag = message[message.len() - 16 ..].try_into().unwrap();
+
+		let position = self.skipped
+			.iter()
+			.position(|skipped| skipped.pub_key == pub_key && skipped.message_key.index() == index);
+
+		if let Some(position) = position {
+			let message_key = self.skipped[position].message_key.as_bytes();
+			let plaintext = decrypt_with(message_key, &associated_data, ciphertext, tag)?;
+			self.skipped.remove(position);
+
+			return Ok(plaintext);
+		}
+
+		let mut chain = self.receiving_chain(pub_key, previous_chain_len)?;
+		chain.skip_until(index)?;
+
+		let message_key = chain.chain_key.next_message_key();
+		let plaintext = decrypt_with(message_key.as_bytes(), &associated_data, ciphertext, tag)?;
+
+		self.commit(chain);
+
+		Ok(plaintext)
+	}
+
+	// the receiving chain the message with this header belongs to, performing
+	// the receiving half of a DH ratchet step if it has a new ratchet key
+	fn receiving_chain(&self, pub_key: PublicKey, previous_chain_len: u32) -> Result<ReceivingChain, Error> {
+		let current = match (self.remote_pub_key, &self.receiving_chain) {
+			(Some(remote_pub_key), Some(chain_key)) => Some((remote_pub_key, chain_key.clone())),
+			_ => None,
+		};
+
+		let mut skipped = Vec::new();
+
+		if let Some((remote_pub_key, chain_key)) = current {
+			let mut current = ReceivingChain {
+				root_key: self.root_key,
+				remote_pub_key,
+				chain_key,
+				skipped: Vec::new(),
+				new_chain: false,
+			};
+
+			if remote_pub_key == pub_key {
+				return Ok(current);
+			}
+
+			// keep the keys for messages still to arrive on the previous chain
+			current.skip_until(previous_chain_len)?;
+			skipped = core::mem::take(&mut current.skipped);
+		}
+
+		// a message on a new chain can't be the initiator's first received
+		// message before it has sent anything
+		if self.sending_chain.is_none() && self.remote_pub_key.is_some() {
+			return Err(Error::DecryptionFailed);
+		}
+
+		let dh_out = self.ratchet_secret
+			.diffie_hellman(&pub_key)
+			.map_err(|_| Error::InvalidPublicKey)?;
+
+		let (root_key, chain_key) = kdf_root(&self.root_key, dh_out.as_bytes());
+
+		Ok(ReceivingChain {
+			root_key,
+			remote_pub_key: pub_key,
+			chain_key: ChainKey::new(chain_key),
+			skipped,
+			new_chain: true,
+		})
+	}
+
+	fn commit(&mut self, mut chain: ReceivingChain) {
+		for skipped in chain.skipped.drain(..) {
+			if self.skipped.len() == MAX_SKIP as usize {
+				self.skipped.pop_front();
+			}
+
+			self.skipped.push_back(skipped);
+		}
+
+		if chain.new_chain {
+			// the next message sent starts a new sending chain, and its
+			// header gives the length of this one
+			if let Some(chain_key) = self.sending_chain.take() {
+				self.previous_sending_len = chain_key.index();
+			}
+		}
+
+		self.root_key = chain.root_key;
+		self.remote_pub_key = Some(chain.remote_pub_key);
+		self.receiving_chain = Some(chain.chain_key.clone());
+	}
+}
+
+fn decrypt_with(message_key: &[u8; 32], associated_data: &[u8], ciphertext: &[u8], tag: [u8; 16]) -> Result<Vec<u8>, Error> {
+	let (mut key, nonce) = message_cipher_key(message_key);
+
+	let mut plaintext = ciphertext.to_vec();
+	let result = chacha20poly1305_decrypt(key, nonce, associated_data, &mut plaintext, tag);
+	zeroize(&mut key);
+
+	result.map_err(|_| Error::DecryptionFailed)?;
+
+	Ok(plaintext)
+}
+
+impl Drop for Ratchet {
+	fn drop(&mut self) {
+		zeroize(&mut self.root_key);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Ratchet {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.root_key);
+		::zeroize::Zeroize::zeroize(&mut self.ratchet_secret);
+
+		if let Some(chain_key) = self.sending_chain.as_mut() {
+			::zeroize::Zeroize::zeroize(chain_key);
+		}
+
+		if let Some(chain_key) = self.receiving_chain.as_mut() {
+			::zeroize::Zeroize::zeroize(chain_key);
+		}
+
+		// skipped keys are zeroized when they're dropped
+		self.skipped.clear();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Ratchet {}
+
+// deliberately doesn't print any keys
+impl fmt::Debug for Ratchet {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Ratchet")
+			.field("sending_chain", &self.sending_chain)
+			.field("receiving_chain", &self.receiving_chain)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex_vec;
+
+#[cfg(test)]
+fn new_pair() -> (Ratchet, Ratchet) {
+	let responder_secret = StaticSecret::from_bytes([0x44; 32]);
+	let responder_pub_key = PublicKey::from(&responder_secret);
+
+	(
+		Ratchet::new_initiator([0x55; 32], responder_pub_key),
+		Ratchet::new_responder([0x55; 32], responder_secret),
+	)
+}
+
+// checked against a python script following the Double Ratchet specification,
+// using the X25519, HKDF and ChaCha20Poly1305 classes of python's cryptography
+// package
+#[test]
+fn test_known_answer() {
+	let (mut alice, mut bob) = new_pair();
+	assert!(!bob.can_send());
+
+	let message = alice.encrypt(b"hello", b"ad", [0x11; 32]).unwrap();
+	assert_eq!(
+		message,
+		hex_vec("7b4e909bbe7ffe44c465a220037d608ee35897d31ef972f07f74892cb0f73f13\
+			000000000000000007d093f23d9dc778d29507d91747a7102023f82018"),
+	);
+	assert_eq!(bob.decrypt(&message, b"ad").unwrap(), b"hello");
+
+	let message = bob.encrypt(b"hi", b"ad", [0x22; 32]).unwrap();
+	assert_eq!(
+		message,
+		hex_vec("0faa684ed28867b97f4a6a2dee5df8ce974e76b7018e3f22a1c4cf2678570f20\
+			00000000000000001aa7f18f19044506a160912c00e10c0c0ec2"),
+	);
+	assert_eq!(alice.decrypt(&message, b"ad").unwrap(), b"hi");
+}
+
+#[test]
+fn test_conversation() {
+	let (mut alice, mut bob) = new_pair();
+
+	for round in 0 .. 4u8 {
+		for i in 0 .. round + 1 {
+			let message = alice.encrypt(&[i; 20], b"", [round * 2 + 1; 32]).unwrap();
+			assert_eq!(message.len(), 20 + OVERHEAD);
+			assert_eq!(bob.decrypt(&message, b"").unwrap(), [i; 20]);
+		}
+
+		for i in 0 .. 2 {
+			let message = bob.encrypt(&[i; 7], b"", [round * 2 + 2; 32]).unwrap();
+			assert_eq!(alice.decrypt(&message, b"").unwrap(), [i; 7]);
+		}
+	}
+}
+
+#[test]
+fn test_out_of_order() {
+	let (mut alice, mut bob) = new_pair();
+
+	let first: Vec<_> = (0 .. 3u8).map(|i| alice.encrypt(&[i], b"", [0x11; 32]).unwrap()).collect();
+	assert_eq!(bob.decrypt(&first[2], b"").unwrap(), [2]);
+
+	let reply = bob.encrypt(b"reply", b"", [0x22; 32]).unwrap();
+	assert_eq!(alice.decrypt(&reply, b"").unwrap(), b"reply");
+
+	// starts a new chain, so the rest of the first chain's keys are skipped
+	let second = alice.encrypt(b"second", b"", [0x33; 32]).unwrap();
+	let third = alice.encrypt(b"third", b"", [0x33; 32]).unwrap();
+	assert_eq!(bob.decrypt(&third, b"").unwrap(), b"third");
+	assert_eq!(bob.decrypt(&first[0], b"").unwrap(), [0]);
+	assert_eq!(bob.decrypt(&second, b"").unwrap(), b"second");
+	assert_eq!(bob.decrypt(&first[1], b"").unwrap(), [1]);
+
+	// message keys are deleted once used
+	assert_eq!(bob.decrypt(&first[1], b""), Err(Error::DecryptionFailed));
+	assert_eq!(bob.decrypt(&third, b""), Err(Error::DecryptionFailed));
+}
+
+#[test]
+fn test_failures_leave_state_unchanged() {
+	let (mut alice, mut bob) = new_pair();
+
+	let message = alice.encrypt(b"hello", b"ad", [0x11; 32]).unwrap();
+	assert_eq!(bob.decrypt(&message, b"other ad"), Err(Error::DecryptionFailed));
+	assert_eq!(bob.decrypt(&message[0 .. OVERHEAD - 1], b"ad"), Err(Error::InvalidMessage));
+
+	let mut modified = message.clone();
+	modified[39] ^= 1;
+	assert_eq!(bob.decrypt(&modified, b"ad"), Err(Error::DecryptionFailed));
+
+	// a forged ratchet key doesn't advance the ratchet
+	let mut modified = message.clone();
+	modified[0] ^= 1;
+	assert_eq!(bob.decrypt(&modified, b"ad"), Err(Error::DecryptionFailed));
+	assert!(!bob.can_send());
+
+	assert_eq!(bob.decrypt(&message, b"ad").unwrap(), b"hello");
+
+	for _ in 0 .. MAX_SKIP + 1 {
+		alice.encrypt(b"", b"", [0x11; 32]).unwrap();
+	}
+
+	let message = alice.encrypt(b"too far", b"", [0x11; 32]).unwrap();
+	assert_eq!(bob.decrypt(&message, b""), Err(Error::TooManySkippedMessages));
+}
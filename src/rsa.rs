@@ -0,0 +1,540 @@
+//! Verification of RSA signatures, with the PKCS #1 v1.5 and PSS encodings
+//! from [IETF RFC 8017](https://datatracker.ietf.org/doc/html/rfc8017). RSA
+//! is still what most firmware, package and certificate signatures are made
+//! with, so it has to be verified even by systems that sign with Ed25519
+//! themselves. Signing isn't supported.
+//!
+//! Moduli of 2048 to 8192 bits are accepted, with public exponents of up to
+//! 64 bits. PKCS #1 v1.5 signatures are checked by encoding the expected
+//! padding and digest and comparing it as a whole, rather than by parsing
+//! the signature's, which rules out the forgeries that lax parsers allow.
+//!
+//! ```
+//! use libkrypton::rsa::{HashAlgorithm, PublicKey};
+//!
+//! fn check_firmware(vendor_key: &str, image: &[u8], signature: &[u8]) -> bool {
+//!     let key = PublicKey::from_public_key_pem(vendor_key).unwrap();
+//!     key.verify_pkcs1v15(HashAlgorithm::Sha256, image, signature).is_ok()
+//! }
+//! ```
+
+use core::fmt;
+
+use crate::bignum::{Modulus, MAX_LIMBS};
+use crate::der::{self, Reader};
+use crate::sha2::{Sha256, Sha384, Sha512};
+
+/// The smallest modulus accepted, in bits.
+pub const MIN_BITS: usize = 2048;
+
+/// The largest modulus accepted, in bits.
+pub const MAX_BITS: usize = 64 * MAX_LIMBS;
+
+const MAX_LEN: usize = MAX_BITS / 8;
+
+// the OID of rsaEncryption, 1.2.840.113549.1.1.1
+const RSA_ENCRYPTION_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// An error returned when parsing a key or verifying a signature fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The bytes or text passed in are not a valid encoding of a key.
+	InvalidEncoding,
+	/// The key's modulus is too short or too long, or its public exponent is
+	/// even, less than 3, or longer than 64 bits.
+	UnsupportedKey,
+	/// The signature is not valid for the message and key.
+	InvalidSignature,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid rsa key encoding",
+			Error::UnsupportedKey => "unsupported rsa key",
+			Error::InvalidSignature => "invalid rsa signature",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The hash function a message is digested with before it's signed, which
+/// PSS also uses for its mask generation function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+	Sha256,
+	Sha384,
+	Sha512,
+}
+
+impl HashAlgorithm {
+	/// Returns the length of the digest, in bytes.
+	pub fn output_len(self) -> usize {
+		match self {
+			HashAlgorithm::Sha256 => 32,
+			HashAlgorithm::Sha384 => 48,
+			HashAlgorithm::Sha512 => 64,
+		}
+	}
+
+	// the DER encoding of the DigestInfo that the digest is appended to in
+	// PKCS #1 v1.5 signatures, from section 9.2 of RFC 8017
+	fn digest_info_prefix(self) -> [u8; 19] {
+		let (len, oid_last, digest_len) = match self {
+			HashAlgorithm::Sha256 => (0x31, 0x01, 0x20),
+			HashAlgorithm::Sha384 => (0x41, 0x02, 0x30),
+			HashAlgorithm::Sha512 => (0x51, 0x03, 0x40),
+		};
+
+		[
+			0x30, len, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, oid_last,
+			0x05, 0x00, 0x04, digest_len,
+		]
+	}
+
+	// hashes the concatenation of `parts`, returning the digest in the
+	// first output_len() bytes
+	fn hash(self, parts: &[&[u8]]) -> [u8; 64] {
+		let mut out = [0; 64];
+
+		match self {
+			HashAlgorithm::Sha256 => {
+				let mut hasher = Sha256::new();
+				parts.iter().for_each(|part| hasher.add_bytes(part));
+				out[.. 32].copy_from_slice(&hasher.out());
+			},
+			HashAlgorithm::Sha384 => {
+				let mut hasher = Sha384::new();
+				parts.iter().for_each(|part| hasher.add_bytes(part));
+				out[.. 48].copy_from_slice(&hasher.out());
+			},
+			HashAlgorithm::Sha512 => {
+				let mut hasher = Sha512::new();
+				parts.iter().for_each(|part| hasher.add_bytes(part));
+				out = hasher.out();
+			},
+		}
+
+		out
+	}
+
+	// XORs `out` with the output of MGF1 over this hash function
+	fn mgf1_xor(self, seed: &[u8], out: &mut [u8]) {
+		let len = self.output_len();
+
+		for (counter, chunk) in out.chunks_mut(len).enumerate() {
+			let mask = self.hash(&[seed, &(counter as u32).to_be_bytes()]);
+
+			for (byte, mask) in chunk.iter_mut().zip(&mask) {
+				*byte ^= mask;
+			}
+		}
+	}
+}
+
+/// An RSA public key.
+#[derive(Clone)]
+pub struct PublicKey {
+	modulus: Modulus,
+	// big-endian, without leading zeros
+	exponent: [u8; 8],
+	exponent_len: usize,
+}
+
+impl PublicKey {
+	/// Creates a key from its big-endian modulus and public exponent, either
+	/// of which may have leading zeros.
+	pub fn from_components(modulus: &[u8], exponent: &[u8]) -> Result<Self, Error> {
+		let modulus = Modulus::from_be_bytes(modulus).ok_or(Error::UnsupportedKey)?;
+
+		if modulus.bits() < MIN_BITS {
+			return Err(Error::UnsupportedKey);
+		}
+
+		let start = exponent.iter().position(|&byte| byte != 0).unwrap_or(exponent.len());
+		let exponent = &exponent[start ..];
+
+		// an even exponent isn't invertible, and 1 would make signatures
+		// trivial to forge
+		match exponent {
+			[] | [1] => return Err(Error::UnsupportedKey),
+			[.., last] if last & 1 == 0 => return Err(Error::UnsupportedKey),
+			_ if exponent.len() > 8 => return Err(Error::UnsupportedKey),
+			_ => (),
+		}
+
+		let mut bytes = [0; 8];
+		bytes[.. exponent.len()].copy_from_slice(exponent);
+
+		Ok(Self {modulus, exponent: bytes, exponent_len: exponent.len()})
+	}
+
+	/// Parses a DER-encoded PKCS #1 `RSAPublicKey`, as produced by e.g.
+	/// `openssl rsa -pubin -RSAPublicKey_out -outform der`.
+	pub fn from_pkcs1_der(der: &[u8]) -> Result<Self, Error> {
+		let mut outer = Reader::new(der);
+		let mut sequence = outer.read_sequence().map_err(invalid)?;
+		outer.finish().map_err(invalid)?;
+
+		let modulus = sequence.read_integer().map_err(invalid)?;
+		let exponent = sequence.read_integer().map_err(invalid)?;
+		sequence.finish().map_err(invalid)?;
+
+		Self::from_components(modulus, exponent)
+	}
+
+	/// Parses a DER-encoded SubjectPublicKeyInfo public key, as produced by
+	/// e.g. `openssl pkey -pubout -outform der`.
+	pub fn from_public_key_der(der: &[u8]) -> Result<Self, Error> {
+		let mut outer = Reader::new(der);
+		let mut spki = outer.read_sequence().map_err(invalid)?;
+		outer.finish().map_err(invalid)?;
+
+		let mut algorithm = spki.read_sequence().map_err(invalid)?;
+
+		if algorithm.read_oid().map_err(invalid)? != RSA_ENCRYPTION_OID {
+			return Err(Error::InvalidEncoding);
+		}
+
+		algorithm.read_null().map_err(invalid)?;
+		algorithm.finish().map_err(invalid)?;
+
+		let key = spki.read_bit_string().map_err(invalid)?;
+		spki.finish().map_err(invalid)?;
+
+		Self::from_pkcs1_der(key)
+	}
+
+	/// Parses a PEM-encoded SubjectPublicKeyInfo, with the `PUBLIC KEY` label.
+	#[cfg(feature = "std")]
+	pub fn from_public_key_pem(pem: &str) -> Result<Self, Error> {
+		let der = crate::pem::decode_with_label(pem, "PUBLIC KEY").map_err(|_| Error::InvalidEncoding)?;
+		Self::from_public_key_der(&der)
+	}
+
+	/// Returns the length of the modulus in bits.
+	pub fn bits(&self) -> usize {
+		self.modulus.bits()
+	}
+
+	/// Returns the length of the modulus in bytes, which every signature
+	/// must be.
+	pub fn signature_len(&self) -> usize {
+		self.modulus.byte_len()
+	}
+
+	/// Checks a PKCS #1 v1.5 `signature` over `message`, which was hashed
+	/// with `hash` to be signed.
+	pub fn verify_pkcs1v15(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+		let digest = hash.hash(&[message]);
+		self.verify_pkcs1v15_prehashed(hash, &digest[.. hash.output_len()], signature)
+	}
+
+	/// Checks a PKCS #1 v1.5 `signature` over a message whose `digest` was
+	/// computed beforehand with `hash`, such as a large file hashed as it
+	/// was read.
+	pub fn verify_pkcs1v15_prehashed(&self, hash: HashAlgorithm, digest: &[u8], signature: &[u8]) -> Result<(), Error> {
+		if digest.len() != hash.output_len() {
+			return Err(Error::InvalidSignature);
+		}
+
+		let mut buf = [0; MAX_LEN];
+		let encoded = self.open(signature, &mut buf)?;
+
+		// 0x00 0x01, at least eight 0xff bytes, 0x00, the DigestInfo
+		let prefix = hash.digest_info_prefix();
+		let padding_len = encoded.len() - prefix.len() - digest.len() - 3;
+
+		let mut expected = [0; MAX_LEN];
+		let expected = &mut expected[.. encoded.len()];
+		expected[1] = 0x01;
+		expected[2 .. 2 + padding_len].fill(0xff);
+		expected[3 + padding_len .. encoded.len() - digest.len()].copy_from_slice(&prefix);
+		expected[encoded.len() - digest.len() ..].copy_from_slice(digest);
+
+		match encoded == expected {
+			true => Ok(()),
+			false => Err(Error::InvalidSignature),
+		}
+	}
+
+	/// Checks a PSS `signature` over `message`, with `hash` for both the
+	/// message and MGF1, and a salt as long as the digest, which is what
+	/// most signers use.
+	pub fn verify_pss(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+		self.verify_pss_with_salt_len(hash, message, signature, hash.output_len())
+	}
+
+	/// Like [`PublicKey::verify_pss`], but with a salt of `salt_len` bytes.
+	pub fn verify_pss_with_salt_len(
+		&self,
+		hash: HashAlgorithm,
+		message: &[u8],
+		signature: &[u8],
+		salt_len: usize,
+	) -> Result<(), Error> {
+		let mut buf = [0; MAX_LEN];
+		let encoded = self.open(signature, &mut buf)?;
+
+		// the encoded message has one bit less than the modulus, which means
+		// one byte less when the modulus's length is one more than a multiple
+		// of 8
+		let em_bits = self.bits() - 1;
+		let (zero, encoded) = encoded.split_at_mut(encoded.len() - em_bits.div_ceil(8));
+
+		let h_len = hash.output_len();
+
+		// the salt length comes from the caller, so it may be large enough to
+		// overflow
+		let min_len = h_len.checked_add(salt_len).and_then(|len| len.checked_add(2));
+
+		if zero.iter().any(|&byte| byte != 0) || min_len.is_none_or(|min_len| encoded.len() < min_len) {
+			return Err(Error::InvalidSignature);
+		}
+
+		// the bits of the first byte that are above em_bits
+		let top_mask = !(0xff >> (8 * encoded.len() - em_bits));
+
+		let (masked_db, rest) = encoded.split_at_mut(encoded.len() - h_len - 1);
+		let (h, trailer) = rest.split_at(h_len);
+
+		if trailer != [0xbc] || masked_db[0] & top_mask != 0 {
+			return Err(Error::InvalidSignature);
+		}
+
+		hash.mgf1_xor(h, masked_db);
+		masked_db[0] &= !top_mask;
+
+		// the data block is zeros, a one, then the salt
+		let (padding, salt) = masked_db.split_at(masked_db.len() - salt_len);
+
+		match padding.split_last() {
+			Some((0x01, zeros)) if zeros.iter().all(|&byte| byte == 0) => (),
+			_ => return Err(Error::InvalidSignature),
+		}
+
+		let digest = hash.hash(&[message]);
+		let expected = hash.hash(&[&[0; 8], &digest[.. h_len], salt]);
+
+		match &expected[.. h_len] == h {
+			true => Ok(()),
+			false => Err(Error::InvalidSignature),
+		}
+	}
+
+	// returns signature ** e mod n, encoded at the length of the modulus
+	fn open<'a>(&self, signature: &[u8], buf: &'a mut [u8; MAX_LEN]) -> Result<&'a mut [u8], Error> {
+		if signature.len() != self.signature_len() {
+			return Err(Error::InvalidSignature);
+		}
+
+		let signature = self.modulus.parse(signature).ok_or(Error::InvalidSignature)?;
+		let message = self.modulus.pow(&signature, &self.exponent[.. self.exponent_len]);
+
+		let encoded = &mut buf[.. self.signature_len()];
+		self.modulus.encode(&message, encoded);
+		Ok(encoded)
+	}
+}
+
+// any DER error means that the key is malformed
+fn invalid(_: der::Error) -> Error {
+	Error::InvalidEncoding
+}
+
+impl fmt::Debug for PublicKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("PublicKey")
+			.field("bits", &self.bits())
+			.field("exponent", &&self.exponent[.. self.exponent_len])
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// the signatures over "hello" verify with python's cryptography package, with
+// MGF1 over the same hash and a salt as long as the hash for PSS
+#[test]
+fn test_verify_2048() {
+	let key = PublicKey::from_public_key_der(&hex::<294>(
+		"30820122300d06092a864886f70d01010105000382010f003082010a02820101\
+		00eb4dac025a762feafa95d4279e554c3e82cdd1f38d12f4e5deab5a5c1eb665\
+		69a6767703e220c3640c98c2281453321419316515b996dd305c557f3f32335b\
+		111df69bec77b9300cda5921873c198b20eec37f2991eeaf995796738ce85046\
+		321eb236abb224a4f7928e5acec73a4f3d92cdeb5ec4f1c4349ac846ebf4ebba\
+		8fb3e82771527a270fb45039437db6caacbaf2e380c535a17a9c6d600abd30db\
+		6736d83a2eaf4e1ea2c36b9614fe9ba08e1dc9d89724078d3464cd46b26f74ed\
+		5f1347eaff07d6105f54630461e9a6229e0ec3e8ec7dda4d470258d6869b15f6\
+		809625341faf57f5e50ba5cd256145afb692694c0a11e5e4518c15082d338561\
+		c90203010001",
+	)).unwrap();
+	assert_eq!((key.bits(), key.signature_len()), (2048, 256));
+
+	let pkcs1v15_sha256 = hex::<256>(
+		"d1c635d27edee678f5ab586377072984515b0bf6a16a0cfe6b7e9e80228aa0e9\
+		04fabee70649233778acbbf134d8f71c206d23d0711866247badd951be9357bd\
+		a4441df3d91fd6d2edb9d028d496f5588e2f3eee3fd26a70ae777ed6cf98a720\
+		e52994132d5597107b80d0cba28ee7c759302622d7e3d18920b254b72ab5b462\
+		c2015165de8de37e16f53545d2871b404c2e1d2326803cbeed842622f5b707be\
+		5cee2de94ccb62819c364f8ac0d547cd8affeed584d346e92d390ca7b9d9677a\
+		71fd1109fbc98821ccaab8a34c4fbb6b8b811591ced457d4b0c0027ac11bc6c1\
+		a1d25ec08653c88966a8227c8dfdf4eae6cd6ff28e465dde5b546bea169fc5f3",
+	);
+	let pkcs1v15_sha512 = hex::<256>(
+		"60eb568f91dbf8ca836b423090587d2f66b2b1a5db151d5d5bb67a111bfcaa8e\
+		f52e6c8544e12085d80d499e550e2910072bf5c573521323cfcca6579f77788a\
+		92423ea50dcdfa1862fa6ed5e02a13f79172f504023ba9bcb517cd2d00db29c9\
+		3c21f48b349726ef5fab8ed3cd3bafae742d5e7a5713f3d9ee008b1b6897fa5e\
+		64251cf62300a11c2abd2b84dfebe5969a85a850209ebb0c117872d1865b2e2c\
+		01f5f6ac61abba8221dbe5a0159e5759d6997b5c099d1bbd7c7b942adddd76de\
+		fdf695fd047bdd96398818a9e2eb5b00a67bd9168f93083c037b909a3e069e76\
+		f3ec05e39507b9b817522e5a0a97722d0943a828d3ad2655e9ad85eefc0fb0c5",
+	);
+	let pss_sha256 = hex::<256>(
+		"b1e292bbdcdfbd230f1ea0e2ac99ba5c7ff0b64515cc0b3c4419e3409f4835b7\
+		89c7ccc1fb2db4d9a7d7daca0b26c2f5ac08d7b97e885dae002b808cd77dfe1d\
+		0a0b1fc5802b81c8e1a5e19db7529c0e6b063f6f64a3f432924bd6286df8e682\
+		a9eabee946e2dd1037d1925176107b3d8fdf8bf6d163a0208673e0bd4b8b72ed\
+		5358b8eb79da23fd08db9dd4bf7ea03ed3fb2e71338bc8530c1211a4b1e69a09\
+		7280475d4781eb07b358b3160bf8c09590fa51fac46150bb4d626d77cbfc3e86\
+		0751f65b4e6a3598c2f885c7cff79499976cd8cab42fdf3ceb4abd773066472a\
+		6accc0f9eb62587d880388aaf44287b20dc897c377c605ad43f45a78029ac776",
+	);
+	let pss_sha384 = hex::<256>(
+		"4ac295e3a68aed0f11fb10539c981af4db2dbf6af59e2b717762bfb27cafcbe0\
+		7693a9b4e8a7bd64de782630644f6e27d36eb71a139bc3582e374480713b4212\
+		3408466fa8beb8d79fe395bf9c07d9ffc6fb2b520d158336dd62bdf38c568117\
+		89c1036f86fa9b29dd8963d8f07749656cdac805e65926cd4ad457ee46c5a0bd\
+		3d8fa2c0a01ce4ba2af294ce5b1553b1bed13409671c89c80b4d70266bdfcc57\
+		0fea21390bbd56f6d430c99c9073255ca3038fdd2e11a4a9ff1a4f09613c2b1d\
+		f840e8597ce59fcdea1fc39e145f64e8675982d11a3e9d27156611d0780d47d1\
+		3e774d4231aec9b31f84210e24d8e91b5c53bb9bec4f085a6c074b61d794632e",
+	);
+	let pss_sha256_unsalted = hex::<256>(
+		"956ec905f302935efff6a54bdc3ee63af7f433401b7cdafb00cab88fc628549e\
+		182bfdc42ef37264324ad7c1b9ae1a6495ba6fcbaf2c3bbff4006a76c2380b1e\
+		0b4ccd0d9ff477102c1e8df789857514ebaf873e18df3cd45e4c08729c87d8ea\
+		8f335f4263e1808dc25f5217efe527c12b447c1f3f3852450d9348f9c6c41f18\
+		4ddda264c3fbf8e0870f9a31cca9917d3b5ae07b34b0e6b3d0e537e96782d880\
+		9754a580a8444ab1c04d7d13af637ad1f5ac7b72f3c339e6045955068771e788\
+		b1fbea14538aafc694a090aa627a2a34cdd255b6a935715d4876046744a6f242\
+		c79337426d795d8e72ceaf009f16aaad5f0068905d39827e2a1b2b5610b5d048",
+	);
+
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &pkcs1v15_sha256), Ok(()));
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha512, b"hello", &pkcs1v15_sha512), Ok(()));
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha256, b"hello", &pss_sha256), Ok(()));
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha384, b"hello", &pss_sha384), Ok(()));
+	assert_eq!(key.verify_pss_with_salt_len(HashAlgorithm::Sha256, b"hello", &pss_sha256_unsalted, 0), Ok(()));
+
+	let digest = HashAlgorithm::Sha256.hash(&[b"hello"]);
+	assert_eq!(key.verify_pkcs1v15_prehashed(HashAlgorithm::Sha256, &digest[.. 32], &pkcs1v15_sha256), Ok(()));
+
+	// the wrong message, hash, encoding or salt length
+	let invalid = Err(Error::InvalidSignature);
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hellp", &pkcs1v15_sha256), invalid);
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha512, b"hello", &pkcs1v15_sha256), invalid);
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &pss_sha256), invalid);
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha256, b"hellp", &pss_sha256), invalid);
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha384, b"hello", &pss_sha256), invalid);
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha256, b"hello", &pkcs1v15_sha256), invalid);
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha256, b"hello", &pss_sha256_unsalted), invalid);
+	assert_eq!(key.verify_pss_with_salt_len(HashAlgorithm::Sha256, b"hello", &pss_sha256, 0), invalid);
+
+	// a salt too long to fit in the encoded message, by one byte or by so much
+	// that the length overflows
+	assert_eq!(key.verify_pss_with_salt_len(HashAlgorithm::Sha256, b"hello", &pss_sha256, 256 - 32 - 1), invalid);
+	assert_eq!(key.verify_pss_with_salt_len(HashAlgorithm::Sha256, b"hello", &pss_sha256, usize::MAX), invalid);
+
+	// a changed, truncated or unreduced signature
+	let mut tampered = pss_sha256;
+	tampered[100] ^= 1;
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha256, b"hello", &tampered), invalid);
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &pkcs1v15_sha256[1 ..]), invalid);
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &[0xff; 256]), invalid);
+}
+
+// the modulus's length is one more than a multiple of 8, so the encoded
+// message is a byte shorter than the signature
+#[test]
+fn test_verify_2049() {
+	let key = PublicKey::from_pkcs1_der(&hex::<270>(
+		"3082010a02820101017bac908bfebe91d5cbcf1204a9bf3ca3ac3dececad9117\
+		ce3b144bf4780829938760c8f39d3f2f18ae12599c6e44aa9e7c3faf0f5ba815\
+		4f599fba67a7c9ae3c9801ab061f0ae1b8f952e6036ae37b9b2ebc0161922c47\
+		0a548797253f9eb44a732dd863aa3f01fd12444e9e5000a2414c91a39acbd1d5\
+		804a3bbc6bd1d749233860a64d4a2955ba68152a4121c8e6fd0733a3b0cca96a\
+		7128baf67261245f25171ed35cc7d91702938b70fe4e78dc350d973eb88222ac\
+		154f0602d6819ae008dd8922e5fa10faf28d9e0c2e9c608c14e359f0d5cff19c\
+		00440dfa0fe905c967dcd55fc79edcf1339b351ff61cf67a61a5db4663063145\
+		933bada095868de7ad0203010001",
+	)).unwrap();
+	assert_eq!((key.bits(), key.signature_len()), (2049, 257));
+
+	let pkcs1v15_sha256 = hex::<257>(
+		"00352494c3a96d54dfd67bbba19e2ca0aa4ea473e021f84520d18e88c4433526\
+		fcae80d31d3411f56010159989bd49dda36a65675f651c8f86b2d0ac1039ec5f\
+		ead037d11211c0d1f7e21ca830311dcc042c8b0da3b0fbad0c0abc91130a17a5\
+		826793519a038661b077bfe297a34ff3b2e6cfb03b77aff458d4de4d4d4b0b7b\
+		c5a144a9f0d8a6454af0100dd1ad4b84efa36c35c2c4d3cb44c28bc5b070a0e3\
+		560f82da560ce833cc9464642e95b592172af19f2982f4826052a74bb0e5a946\
+		9e2c82d53c4ed25b48c17dfd7b767c919f50fcde98ea8ab3f5eadb1f28e27a5e\
+		524defe4f7274383055e12ca6dcb3308d73d70fe9ebf061dc4d068190defdc84\
+		e8",
+	);
+	let pss_sha512 = hex::<257>(
+		"0043db2c8909b42c9c2f49378dea9bb1119ed68deb7a712c0a0d1301b1c0c302\
+		c4ab316c56b4233e725f0cdb4c3fbf368f9a26ae5563a832053246bf109251d4\
+		269bdebcd477a80cbd63bdda01e4d27b3dc1d3b7005237a8cdae3c2f89291298\
+		15394c221f04d8cf6962e462de4cd61c0524d58d250f07e6f98379a09da79324\
+		1fbe4100ad0131a62560c2812ca4d763708633182005c7d38dc8c61571d09409\
+		07c0c7fdcfdccf700e44f0ac66b8f53f1233d704fd8aec2e15af1543b3655c49\
+		472d5cc5ae0d78955f383968e01e5ab74fd75dd9743dc3bb49af54141597abe1\
+		c26ca412f00de225dbca2fee65ae408b91363e6361eaf05765f356e41c448c12\
+		f7",
+	);
+
+	assert_eq!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &pkcs1v15_sha256), Ok(()));
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha512, b"hello", &pss_sha512), Ok(()));
+	assert_eq!(key.verify_pss(HashAlgorithm::Sha512, b"hellp", &pss_sha512), Err(Error::InvalidSignature));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pem() {
+	let key = PublicKey::from_public_key_pem(
+		"-----BEGIN PUBLIC KEY-----\n\
+		MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA602sAlp2L+r6ldQnnlVM\n\
+		PoLN0fONEvTl3qtaXB62ZWmmdncD4iDDZAyYwigUUzIUGTFlFbmW3TBcVX8/MjNb\n\
+		ER32m+x3uTAM2lkhhzwZiyDuw38pke6vmVeWc4zoUEYyHrI2q7IkpPeSjlrOxzpP\n\
+		PZLN617E8cQ0mshG6/Truo+z6CdxUnonD7RQOUN9tsqsuvLjgMU1oXqcbWAKvTDb\n\
+		ZzbYOi6vTh6iw2uWFP6boI4dydiXJAeNNGTNRrJvdO1fE0fq/wfWEF9UYwRh6aYi\n\
+		ng7D6Ox92k1HAljWhpsV9oCWJTQfr1f15QulzSVhRa+2kmlMChHl5FGMFQgtM4Vh\n\
+		yQIDAQAB\n\
+		-----END PUBLIC KEY-----\n",
+	).unwrap();
+	assert_eq!(key.bits(), 2048);
+
+	assert_eq!(PublicKey::from_public_key_pem("not a key").unwrap_err(), Error::InvalidEncoding);
+}
+
+#[test]
+fn test_unsupported_keys() {
+	let mut modulus = [0xff; 256];
+	assert!(PublicKey::from_components(&modulus, &[0x01, 0x00, 0x01]).is_ok());
+	assert!(PublicKey::from_components(&modulus, &[0x00, 0x03]).is_ok());
+
+	assert_eq!(PublicKey::from_components(&modulus, &[0x01]).unwrap_err(), Error::UnsupportedKey);
+	assert_eq!(PublicKey::from_components(&modulus, &[0x01, 0x00, 0x00]).unwrap_err(), Error::UnsupportedKey);
+	assert_eq!(PublicKey::from_components(&modulus, &[0xff; 9]).unwrap_err(), Error::UnsupportedKey);
+	assert_eq!(PublicKey::from_components(&modulus[1 ..], &[0x03]).unwrap_err(), Error::UnsupportedKey);
+
+	modulus[255] = 0xfe;
+	assert_eq!(PublicKey::from_components(&modulus, &[0x03]).unwrap_err(), Error::UnsupportedKey);
+}
@@ -0,0 +1,179 @@
+//! Short authentication strings, which let two users check out of band, by
+//! reading them aloud or comparing screens, that they ended up with the same
+//! session. Both sides derive the string from a hash of their handshake
+//! transcript, so a man in the middle would have to make both transcripts
+//! collide in the few bits that are shown.
+//!
+//! The string can be shown as three 4-digit numbers, as seven emoji, or as the
+//! names of those emoji. These use the same encodings as Matrix's SAS
+//! verification, from the
+//! [Matrix specification](https://spec.matrix.org/latest/client-server-api/#sas-method-decimal).
+//!
+//! ```
+//! use libkrypton::sas::Sas;
+//!
+//! let transcript_hash = [0x42; 32];
+//! let sas = Sas::new(&transcript_hash, b"example app SAS v1");
+//!
+//! // both sides display this and compare
+//! let [a, b, c] = sas.decimal();
+//! println!("{} {} {}", a, b, c);
+//! ```
+
+use crate::hkdf::hkdf_sha256;
+
+// the 64 emoji and their names, from the Matrix specification
+const EMOJI: [(&str, &str); 64] = [
+	("🐶", "Dog"),
+	("🐱", "Cat"),
+	("🦁", "Lion"),
+	("🐴", "Horse"),
+	("🦄", "Unicorn"),
+	("🐷", "Pig"),
+	("🐘", "Elephant"),
+	("🐰", "Rabbit"),
+	("🐼", "Panda"),
+	("🐓", "Rooster"),
+	("🐧", "Penguin"),
+	("🐢", "Turtle"),
+	("🐟", "Fish"),
+	("🐙", "Octopus"),
+	("🦋", "Butterfly"),
+	("🌷", "Flower"),
+	("🌳", "Tree"),
+	("🌵", "Cactus"),
+	("🍄", "Mushroom"),
+	("🌏", "Globe"),
+	("🌙", "Moon"),
+	("☁\u{fe0f}", "Cloud"),
+	("🔥", "Fire"),
+	("🍌", "Banana"),
+	("🍎", "Apple"),
+	("🍓", "Strawberry"),
+	("🌽", "Corn"),
+	("🍕", "Pizza"),
+	("🎂", "Cake"),
+	("❤\u{fe0f}", "Heart"),
+	("😀", "Smiley"),
+	("🤖", "Robot"),
+	("🎩", "Hat"),
+	("👓", "Glasses"),
+	("🔧", "Spanner"),
+	("🎅", "Santa"),
+	("👍", "Thumbs Up"),
+	("☂\u{fe0f}", "Umbrella"),
+	("⌛", "Hourglass"),
+	("⏰", "Clock"),
+	("🎁", "Gift"),
+	("💡", "Light Bulb"),
+	("📕", "Book"),
+	("✏\u{fe0f}", "Pencil"),
+	("📎", "Paperclip"),
+	("✂\u{fe0f}", "Scissors"),
+	("🔒", "Lock"),
+	("🔑", "Key"),
+	("🔨", "Hammer"),
+	("☎\u{fe0f}", "Telephone"),
+	("🏁", "Flag"),
+	("🚂", "Train"),
+	("🚲", "Bicycle"),
+	("✈\u{fe0f}", "Aeroplane"),
+	("🚀", "Rocket"),
+	("🏆", "Trophy"),
+	("⚽", "Ball"),
+	("🎸", "Guitar"),
+	("🎺", "Trumpet"),
+	("🔔", "Bell"),
+	("⚓", "Anchor"),
+	("🎧", "Headphones"),
+	("📁", "Folder"),
+	("📌", "Pin"),
+];
+
+/// A short authentication string, made of 6 bytes derived from a session.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sas {
+	bytes: [u8; 6],
+}
+
+impl Sas {
+	/// Derives a short authentication string from `transcript_hash`, a hash of
+	/// everything both sides sent during the handshake, with HKDF-SHA256.
+	/// The `label` should be unique to the application and protocol, so that
+	/// strings from different protocols can't be confused.
+	pub fn new(transcript_hash: &[u8], label: &[u8]) -> Self {
+		let mut bytes = [0; 6];
+		hkdf_sha256(&[], transcript_hash, label, &mut bytes);
+		Self {bytes}
+	}
+
+	/// Uses `bytes` directly, for protocols that derive them some other way,
+	/// such as Matrix.
+	pub fn from_bytes(bytes: &[u8; 6]) -> Self {
+		Self {bytes: *bytes}
+	}
+
+	/// Returns the bytes of this string.
+	pub fn as_bytes(&self) -> &[u8; 6] {
+		&self.bytes
+	}
+
+	/// Returns this string as three numbers from 1000 to 9191, each made of
+	/// 13 bits of the first 5 bytes.
+	pub fn decimal(&self) -> [u16; 3] {
+		let mut padded = [0; 8];
+		padded[3 ..].copy_from_slice(&self.bytes[.. 5]);
+		let bits = u64::from_be_bytes(padded);
+
+		[0, 1, 2].map(|i| ((bits >> (27 - 13 * i)) & 0x1fff) as u16 + 1000)
+	}
+
+	/// Returns this string as the indices of seven emoji from the Matrix
+	/// list, each made of 6 bits of the first 42 bits.
+	pub fn emoji_indices(&self) -> [u8; 7] {
+		let mut padded = [0; 8];
+		padded[2 ..].copy_from_slice(&self.bytes);
+		let bits = u64::from_be_bytes(padded);
+
+		[0, 1, 2, 3, 4, 5, 6].map(|i| ((bits >> (42 - 6 * i)) & 0x3f) as u8)
+	}
+
+	/// Returns this string as seven emoji.
+	pub fn emoji(&self) -> [&'static str; 7] {
+		self.emoji_indices().map(|i| EMOJI[i as usize].0)
+	}
+
+	/// Returns this string as the English names of its seven emoji, for
+	/// reading aloud or for displays that can't show emoji.
+	pub fn words(&self) -> [&'static str; 7] {
+		self.emoji_indices().map(|i| EMOJI[i as usize].1)
+	}
+}
+
+#[test]
+fn test_encodings() {
+	let sas = Sas::from_bytes(&[0xff; 6]);
+	assert_eq!(sas.decimal(), [9191; 3]);
+	assert_eq!(sas.emoji_indices(), [63; 7]);
+	assert_eq!(sas.words(), ["Pin"; 7]);
+
+	let sas = Sas::from_bytes(&[0; 6]);
+	assert_eq!(sas.decimal(), [1000; 3]);
+	assert_eq!(sas.emoji(), ["🐶"; 7]);
+
+	// worked out by hand from the bit layouts and emoji table in the Matrix
+	// specification
+	let sas = Sas::from_bytes(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab]);
+	assert_eq!(sas.decimal(), [1036, 4349, 6060]);
+	assert_eq!(sas.emoji_indices(), [0, 18, 13, 5, 25, 56, 38]);
+	assert_eq!(sas.words(), ["Dog", "Mushroom", "Octopus", "Pig", "Strawberry", "Ball", "Hourglass"]);
+}
+
+#[test]
+fn test_derivation() {
+	// checked against the HKDF class of python's cryptography package
+	let sas = Sas::new(&[0x42; 32], b"libkrypton SAS test");
+	assert_eq!(sas.as_bytes(), &[0x54, 0x0c, 0x20, 0xb1, 0x6e, 0x13]);
+
+	assert_ne!(Sas::new(&[0x42; 32], b"another label"), sas);
+}
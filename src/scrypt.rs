@@ -0,0 +1,167 @@
+//! Implemented according to [IETF RFC 7914](https://datatracker.ietf.org/doc/html/rfc7914).
+//! scrypt is a password-based key derivation function that needs a large
+//! amount of memory as well as time, which makes brute force attacks with
+//! custom hardware much more expensive. The memory access pattern depends on
+//! the password, so like bcrypt_pbkdf, this isn't resistant to cache-timing
+//! attacks.
+
+use std::vec;
+use std::vec::Vec;
+
+use crate::hmac::HmacSha256;
+use crate::zeroize::zeroize;
+
+fn salsa20_8(block: &mut [u32; 16]) {
+	let mut x = *block;
+
+	macro_rules! quarter_round {
+		($a:literal, $b:literal, $c:literal, $d:literal) => {
+			x[$b] ^= x[$a].wrapping_add(x[$d]).rotate_left(7);
+			x[$c] ^= x[$b].wrapping_add(x[$a]).rotate_left(9);
+			x[$d] ^= x[$c].wrapping_add(x[$b]).rotate_left(13);
+			x[$a] ^= x[$d].wrapping_add(x[$c]).rotate_left(18);
+		};
+	}
+
+	for _ in 0 .. 4 {
+		// columns
+		quarter_round!(0, 4, 8, 12);
+		quarter_round!(5, 9, 13, 1);
+		quarter_round!(10, 14, 2, 6);
+		quarter_round!(15, 3, 7, 11);
+
+		// rows
+		quarter_round!(0, 1, 2, 3);
+		quarter_round!(5, 6, 7, 4);
+		quarter_round!(10, 11, 8, 9);
+		quarter_round!(15, 12, 13, 14);
+	}
+
+	for i in 0 .. 16 {
+		block[i] = block[i].wrapping_add(x[i]);
+	}
+}
+
+// mixes the 2 * r blocks of `input` into `out`, as scryptBlockMix
+fn block_mix(input: &[u32], out: &mut [u32]) {
+	let num_blocks = input.len() / 16;
+	let mut x: [u32; 16] = input[input.len() - 16 ..].try_into().unwrap();
+
+	for i in 0 .. num_blocks {
+		for j in 0 .. 16 {
+			x[j] ^= input[16 * i + j];
+		}
+
+		salsa20_8(&mut x);
+
+		// the even blocks go in the first half, and the odd ones in the second
+		let dest = (i / 2 + (i % 2) * (num_blocks / 2)) * 16;
+		out[dest .. dest + 16].copy_from_slice(&x);
+	}
+}
+
+// scryptROMix, with `scratch` holding the n blocks of 32 * r words each
+fn ro_mix(block: &mut [u32], scratch: &mut [u32], n: usize) {
+	let len = block.len();
+	let mut x = block.to_vec();
+	let mut y = vec![0; len];
+
+	for i in 0 .. n {
+		scratch[i * len .. (i + 1) * len].copy_from_slice(&x);
+		block_mix(&x, &mut y);
+		core::mem::swap(&mut x, &mut y);
+	}
+
+	for _ in 0 .. n {
+		// n is a power of two, so this is the last block's first word mod n
+		let j = x[len - 16] as usize & (n - 1);
+
+		for k in 0 .. len {
+			x[k] ^= scratch[j * len + k];
+		}
+
+		block_mix(&x, &mut y);
+		core::mem::swap(&mut x, &mut y);
+	}
+
+	block.copy_from_slice(&x);
+
+	zeroize(&mut x);
+	zeroize(&mut y);
+}
+
+// PBKDF2-HMAC-SHA256 with a single iteration, which is all scrypt needs
+fn pbkdf2_sha256_once(password: &[u8], salt: &[u8], out: &mut [u8]) {
+	for (i, chunk) in out.chunks_mut(32).enumerate() {
+		let mut hmac = HmacSha256::new(password);
+		hmac.add_bytes(salt);
+		hmac.add_bytes(&(i as u32 + 1).to_be_bytes());
+
+		let mut block = hmac.out();
+		chunk.copy_from_slice(&block[.. chunk.len()]);
+		zeroize(&mut block);
+	}
+}
+
+/// Derives a key from a `password` and `salt`, filling the `out` buffer. The
+/// cost parameter is `2 ** log_n`, and the memory needed is `128 * r * 2 **
+/// log_n` bytes.
+///
+/// # Panics
+/// * Panics if `log_n` is 0 or at least 32, or if `r` or `p` is 0.
+/// * Panics if the memory needed doesn't fit in a `usize`.
+pub fn scrypt(password: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32, out: &mut [u8]) {
+	assert!(log_n != 0 && log_n < 32);
+	assert!(r != 0 && p != 0);
+
+	let n = 1usize << log_n;
+	let words_per_block = 32 * r as usize;
+	let scratch_len = n.checked_mul(words_per_block).expect("scrypt parameters are too large");
+
+	let mut bytes = vec![0; 4 * words_per_block * p as usize];
+	pbkdf2_sha256_once(password, salt, &mut bytes);
+
+	let mut words: Vec<u32> = bytes
+		.chunks(4)
+		.map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+		.collect();
+
+	let mut scratch = vec![0; scratch_len];
+
+	for block in words.chunks_mut(words_per_block) {
+		ro_mix(block, &mut scratch, n);
+	}
+
+	for (chunk, word) in bytes.chunks_mut(4).zip(&words) {
+		chunk.copy_from_slice(&word.to_le_bytes());
+	}
+
+	pbkdf2_sha256_once(password, &bytes, out);
+
+	zeroize(&mut bytes);
+	zeroize(&mut words);
+	zeroize(&mut scratch);
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 12 of RFC 7914
+#[test]
+fn test_rfc7914_vectors() {
+	let mut out = [0; 64];
+
+	let expected = hex(
+		"77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+		fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906",
+	);
+	scrypt(b"", b"", 4, 1, 1, &mut out);
+	assert_eq!(out, expected);
+
+	let expected = hex(
+		"fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+		2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640",
+	);
+	scrypt(b"password", b"NaCl", 10, 8, 16, &mut out);
+	assert_eq!(out, expected);
+}
@@ -0,0 +1,311 @@
+//! Forward-secure, tamper-evident logs, in the style of the secure audit logs
+//! of Schneier and Kelsey and the forward-secure MACs of Bellare and Yee.
+//!
+//! A [`Logger`] tags each entry with HMAC-SHA256 under a key that's replaced
+//! by a one-way function of itself right after, so an attacker who takes over
+//! the logging machine learns nothing that lets them change, reorder or
+//! remove the entries written before. Each tag also covers the position of
+//! the entry and a running hash of all the tags before it. An [`Auditor`]
+//! holding the initial key replays the keys to check every entry.
+//!
+//! Removing entries from the end can't be detected from the entries alone.
+//! It shows up against a [`Checkpoint`] of the hash chain, which reveals
+//! nothing about the key and can be published elsewhere as the log grows, or
+//! against the [`Seal`] that closes a finished log. An attacker holding the
+//! current key can still truncate the log back to the point of compromise
+//! and seal it there, so only published checkpoints protect the tail.
+//!
+//! ```
+//! use libkrypton::sealed_log::{Auditor, Logger};
+//!
+//! let initial_key = [0x42; 32];
+//! let mut logger = Logger::new(initial_key);
+//!
+//! let tags = [logger.append(b"user alice logged in"), logger.append(b"user alice logged out")];
+//! let seal = logger.seal();
+//!
+//! let mut auditor = Auditor::new(initial_key);
+//! auditor.verify_entry(b"user alice logged in", &tags[0]).unwrap();
+//! auditor.verify_entry(b"user alice logged out", &tags[1]).unwrap();
+//! auditor.finish_sealed(&seal).unwrap();
+//! ```
+
+use core::fmt;
+
+use crate::hmac::HmacSha256;
+use crate::sha2::Sha256;
+use crate::zeroize::zeroize;
+
+const ENTRY_LABEL: u8 = 0;
+const EVOLVE_LABEL: u8 = 1;
+const SEAL_LABEL: u8 = 2;
+
+/// An error returned when a log fails its audit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// An entry's tag is wrong, because the entry was changed, moved,
+	/// inserted, or written with another key.
+	InvalidEntry,
+	/// The log has fewer entries than a checkpoint or seal says it had.
+	Truncated,
+	/// The hash chain doesn't match a checkpoint with the same number of
+	/// entries.
+	ChainMismatch,
+	/// The seal is forged, or entries were added after it.
+	InvalidSeal,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEntry => "invalid log entry",
+			Error::Truncated => "log truncated",
+			Error::ChainMismatch => "log hash chain mismatch",
+			Error::InvalidSeal => "invalid log seal",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The number of entries in a log and the hash chain over their tags, which
+/// can be made public.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+	/// The number of entries.
+	pub count: u64,
+	/// The running hash of the tags of all the entries.
+	pub head: [u8; 32],
+}
+
+/// The closing tag of a finished log, made with the key after its last entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Seal {
+	/// The state of the log when it was sealed.
+	pub checkpoint: Checkpoint,
+	/// The tag over the checkpoint.
+	pub tag: [u8; 32],
+}
+
+// the key, position and chain shared by the logger and the auditor, which
+// both step through the same states
+struct State {
+	key: [u8; 32],
+	checkpoint: Checkpoint,
+}
+
+impl State {
+	fn new(initial_key: [u8; 32]) -> Self {
+		Self {key: initial_key, checkpoint: Checkpoint {count: 0, head: [0; 32]}}
+	}
+
+	fn entry_mac(&self, message: &[u8]) -> HmacSha256 {
+		let mut mac = HmacSha256::new(&self.key);
+		mac.add_bytes(&[ENTRY_LABEL]);
+		mac.add_bytes(&self.checkpoint.count.to_be_bytes());
+		mac.add_bytes(&self.checkpoint.head);
+		mac.add_bytes(message);
+		mac
+	}
+
+	fn seal_mac(&self) -> HmacSha256 {
+		let mut mac = HmacSha256::new(&self.key);
+		mac.add_bytes(&[SEAL_LABEL]);
+		mac.add_bytes(&self.checkpoint.count.to_be_bytes());
+		mac.add_bytes(&self.checkpoint.head);
+		mac
+	}
+
+	// moves past an entry with the given tag, replacing the key so that the
+	// old one can't be recovered
+	fn advance(&mut self, tag: &[u8; 32]) {
+		let mut hasher = Sha256::new();
+		hasher.add_bytes(&self.checkpoint.head);
+		hasher.add_bytes(tag);
+
+		self.checkpoint.head = hasher.out();
+		self.checkpoint.count += 1;
+
+		let mut mac = HmacSha256::new(&self.key);
+		mac.add_bytes(&[EVOLVE_LABEL]);
+		zeroize(&mut self.key);
+		self.key = mac.out();
+	}
+}
+
+impl Drop for State {
+	fn drop(&mut self) {
+		zeroize(&mut self.key);
+	}
+}
+
+/// The writing side of a log, which holds the current key.
+pub struct Logger {
+	state: State,
+}
+
+impl Logger {
+	/// Starts a log with `initial_key`, which must be generated by a secure
+	/// random source and given to the auditor before any entries are written.
+	/// It shouldn't be kept anywhere the logger runs.
+	pub fn new(initial_key: [u8; 32]) -> Self {
+		Self {state: State::new(initial_key)}
+	}
+
+	/// Appends an entry, returning the tag to store alongside it.
+	pub fn append(&mut self, message: &[u8]) -> [u8; 32] {
+		let tag = self.state.entry_mac(message).out();
+		self.state.advance(&tag);
+		tag
+	}
+
+	/// Returns a checkpoint of the log so far, to publish somewhere an
+	/// attacker can't change.
+	pub fn checkpoint(&self) -> Checkpoint {
+		self.state.checkpoint
+	}
+
+	/// Closes the log, returning its seal and destroying the key.
+	pub fn seal(self) -> Seal {
+		Seal {checkpoint: self.state.checkpoint, tag: self.state.seal_mac().out()}
+	}
+}
+
+impl fmt::Debug for Logger {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Logger").field("checkpoint", &self.state.checkpoint).finish_non_exhaustive()
+	}
+}
+
+/// The checking side of a log, which replays the keys from the initial key.
+/// Entries must be given to it in order.
+pub struct Auditor {
+	state: State,
+}
+
+impl Auditor {
+	/// Starts auditing a log written with `initial_key`.
+	pub fn new(initial_key: [u8; 32]) -> Self {
+		Self {state: State::new(initial_key)}
+	}
+
+	/// Checks the next entry against its tag. If it's invalid, the auditor
+	/// stays where it was.
+	pub fn verify_entry(&mut self, message: &[u8], tag: &[u8; 32]) -> Result<(), Error> {
+		if !self.state.entry_mac(message).verify(tag) {
+			return Err(Error::InvalidEntry);
+		}
+
+		self.state.advance(tag);
+		Ok(())
+	}
+
+	/// Returns the checkpoint of the entries checked so far.
+	pub fn checkpoint(&self) -> Checkpoint {
+		self.state.checkpoint
+	}
+
+	/// Finishes an audit of a log that's still open, against the latest
+	/// checkpoint known to be genuine. More entries than the checkpoint are
+	/// accepted, since the log may have grown since it was taken.
+	pub fn finish(self, checkpoint: &Checkpoint) -> Result<(), Error> {
+		let current = self.state.checkpoint;
+
+		if current.count < checkpoint.count {
+			return Err(Error::Truncated);
+		}
+
+		if current.count == checkpoint.count && current.head != checkpoint.head {
+			return Err(Error::ChainMismatch);
+		}
+
+		Ok(())
+	}
+
+	/// Finishes an audit of a sealed log, checking that every entry up to
+	/// the seal was seen and none after it.
+	pub fn finish_sealed(self, seal: &Seal) -> Result<(), Error> {
+		let current = self.state.checkpoint;
+
+		if current.count < seal.checkpoint.count {
+			return Err(Error::Truncated);
+		}
+
+		if current != seal.checkpoint || !self.state.seal_mac().verify(&seal.tag) {
+			return Err(Error::InvalidSeal);
+		}
+
+		Ok(())
+	}
+}
+
+impl fmt::Debug for Auditor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Auditor").field("checkpoint", &self.state.checkpoint).finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[test]
+fn test_known_answer() {
+	// checked against python's hmac and hashlib modules
+	let mut logger = Logger::new([0x42; 32]);
+
+	assert_eq!(logger.append(b"a"), hex("fba102811e5fae068f376cac21f773eeade38cd5fa687fa71d4fe3e574946f09"));
+	assert_eq!(logger.append(b"bc"), hex("19243b8261fc3688d781a74dc9352df3b01db10054223aaf6013c3e9bfb7919c"));
+	assert_eq!(logger.seal().tag, hex("6ea6f71968fd3a84626d1c3e21da60e4eac962f3ba64f249d93860370f79d5f4"));
+}
+
+#[test]
+fn test_audit() {
+	let messages: [&[u8]; 4] = [b"boot", b"login root", b"rm -rf /var/log", b"logout"];
+	let mut logger = Logger::new([7; 32]);
+	let tags = messages.map(|message| logger.append(message));
+	let checkpoint = logger.checkpoint();
+	let seal = logger.seal();
+
+	let audit = |entries: &[(&[u8], [u8; 32])]| {
+		let mut auditor = Auditor::new([7; 32]);
+
+		for (message, tag) in entries {
+			auditor.verify_entry(message, tag)?;
+		}
+
+		Ok(auditor)
+	};
+
+	let entries: [(&[u8], [u8; 32]); 4] = core::array::from_fn(|i| (messages[i], tags[i]));
+	assert_eq!(audit(&entries).and_then(|auditor| auditor.finish_sealed(&seal)), Ok(()));
+	assert_eq!(audit(&entries).and_then(|auditor| auditor.finish(&checkpoint)), Ok(()));
+
+	// changed, reordered and removed entries break the tags
+	let mut changed = entries;
+	changed[2].0 = b"ls /var/log";
+	assert_eq!(audit(&changed).err(), Some(Error::InvalidEntry));
+
+	let mut reordered = entries;
+	reordered.swap(1, 2);
+	assert_eq!(audit(&reordered).err(), Some(Error::InvalidEntry));
+
+	let removed = [entries[0], entries[1], entries[3]];
+	assert_eq!(audit(&removed).err(), Some(Error::InvalidEntry));
+
+	// and removing entries from the end shows up against the seal or a checkpoint
+	let truncated = &entries[.. 3];
+	assert_eq!(audit(truncated).and_then(|auditor| auditor.finish_sealed(&seal)), Err(Error::Truncated));
+	assert_eq!(audit(truncated).and_then(|auditor| auditor.finish(&checkpoint)), Err(Error::Truncated));
+
+	// a seal can't be moved to another point of the log
+	let moved = Seal {checkpoint: audit(truncated).unwrap().checkpoint(), ..seal};
+	assert_eq!(audit(truncated).and_then(|auditor| auditor.finish_sealed(&moved)), Err(Error::InvalidSeal));
+
+	// and the wrong key fails from the first entry
+	let mut auditor = Auditor::new([8; 32]);
+	assert_eq!(auditor.verify_entry(messages[0], &tags[0]), Err(Error::InvalidEntry));
+	assert_eq!(auditor.checkpoint().count, 0);
+}
@@ -0,0 +1,221 @@
+//! Protected memory for secrets that live for the whole life of a process,
+//! such as the long-term keys of a server. A [`SecretBox`] keeps its value on
+//! pages of its own, which are locked into memory with `mlock(2)` or
+//! `VirtualLock` where the platform allows it, so that they're never written
+//! to swap. The value is zeroized when the box is dropped, and can only be
+//! reached from inside a closure, which keeps references to it from
+//! outliving the code that needs them.
+//!
+//! ```
+//! use libkrypton::secret_box::SecretBox;
+//!
+//! let key = SecretBox::new_with(|key: &mut [u8; 32]| {
+//!     key.copy_from_slice(&[0x42; 32]);
+//! });
+//!
+//! let first = key.expose_secret(|key| key[0]);
+//! assert_eq!(first, 0x42);
+//! ```
+//!
+//! Only the memory of the value itself is protected. Anything it owns on the
+//! heap, like the buffer of a `Vec`, is neither locked nor zeroized by the
+//! box.
+
+use core::fmt;
+use core::ptr::NonNull;
+use core::sync::atomic::{compiler_fence, Ordering};
+use std::alloc::{self, Layout};
+
+#[cfg(unix)]
+mod sys {
+	use core::ffi::{c_int, c_void};
+
+	extern "C" {
+		fn getpagesize() -> c_int;
+		fn mlock(addr: *const c_void, len: usize) -> c_int;
+		fn munlock(addr: *const c_void, len: usize) -> c_int;
+	}
+
+	pub fn page_size() -> usize {
+		unsafe {getpagesize() as usize}
+	}
+
+	pub unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+		mlock(ptr as *const c_void, len) == 0
+	}
+
+	pub unsafe fn unlock(ptr: *mut u8, len: usize) {
+		munlock(ptr as *const c_void, len);
+	}
+}
+
+#[cfg(windows)]
+mod sys {
+	use core::ffi::{c_int, c_void};
+
+	#[link(name = "kernel32")]
+	extern "system" {
+		fn VirtualLock(addr: *mut c_void, len: usize) -> c_int;
+		fn VirtualUnlock(addr: *mut c_void, len: usize) -> c_int;
+	}
+
+	// every architecture Windows runs on has 4 KiB pages
+	pub fn page_size() -> usize {
+		4096
+	}
+
+	pub unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+		VirtualLock(ptr as *mut c_void, len) != 0
+	}
+
+	pub unsafe fn unlock(ptr: *mut u8, len: usize) {
+		VirtualUnlock(ptr as *mut c_void, len);
+	}
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+	pub fn page_size() -> usize {
+		4096
+	}
+
+	pub unsafe fn lock(_ptr: *mut u8, _len: usize) -> bool {
+		false
+	}
+
+	pub unsafe fn unlock(_ptr: *mut u8, _len: usize) {}
+}
+
+/// A heap-allocated secret on locked pages of its own, which is zeroized when
+/// dropped and only exposed through closures.
+pub struct SecretBox<T> {
+	ptr: NonNull<T>,
+	layout: Layout,
+	locked: bool,
+}
+
+// the box owns its value, like a Box<T>
+unsafe impl<T: Send> Send for SecretBox<T> {}
+unsafe impl<T: Sync> Sync for SecretBox<T> {}
+
+impl<T> SecretBox<T> {
+	/// Moves `value` into a new box. The value may leave copies of itself on
+	/// the stack on its way in, so [`new_with`](Self::new_with), which never
+	/// has the secret outside the box, should be preferred.
+	///
+	/// # Panics
+	/// * Panics if the memory can't be allocated.
+	pub fn new(value: T) -> Self {
+		let (ptr, layout, locked) = allocate::<T>();
+		unsafe {ptr.as_ptr().write(value)};
+
+		Self {ptr, layout, locked}
+	}
+
+	/// Creates a box holding the default value of `T`, and then has `init`
+	/// fill it in place, so that the secret is only ever written inside the
+	/// box.
+	///
+	/// # Panics
+	/// * Panics if the memory can't be allocated.
+	pub fn new_with<F: FnOnce(&mut T)>(init: F) -> Self where T: Default {
+		let mut secret = Self::new(T::default());
+		init(unsafe {secret.ptr.as_mut()});
+		secret
+	}
+
+	/// Calls `f` with a reference to the secret, returning what it returns.
+	pub fn expose_secret<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+		f(unsafe {self.ptr.as_ref()})
+	}
+
+	/// Calls `f` with a mutable reference to the secret, returning what it
+	/// returns.
+	pub fn expose_secret_mut<R, F: FnOnce(&mut T) -> R>(&mut self, f: F) -> R {
+		f(unsafe {self.ptr.as_mut()})
+	}
+
+	/// Returns whether the pages holding the secret are locked into memory.
+	/// Locking can fail even where it's supported, usually because the
+	/// process has reached its limit of locked memory (`RLIMIT_MEMLOCK` on
+	/// Unix), in which case the secret is still held and zeroized as usual.
+	pub fn is_locked(&self) -> bool {
+		self.locked
+	}
+}
+
+// allocates whole pages, so that unlocking them never unlocks the memory of
+// anything else
+fn allocate<T>() -> (NonNull<T>, Layout, bool) {
+	let page_size = sys::page_size();
+	let size = core::mem::size_of::<T>().max(1).div_ceil(page_size) * page_size;
+	let align = core::mem::align_of::<T>().max(page_size);
+	let layout = Layout::from_size_align(size, align).expect("secret too large");
+
+	let ptr = unsafe {alloc::alloc_zeroed(layout)};
+
+	let Some(ptr) = NonNull::new(ptr) else {
+		alloc::handle_alloc_error(layout);
+	};
+
+	let locked = unsafe {sys::lock(ptr.as_ptr(), size)};
+
+	(ptr.cast(), layout, locked)
+}
+
+impl<T> Drop for SecretBox<T> {
+	fn drop(&mut self) {
+		let bytes = self.ptr.as_ptr() as *mut u8;
+
+		unsafe {
+			core::ptr::drop_in_place(self.ptr.as_ptr());
+
+			for i in 0 .. self.layout.size() {
+				core::ptr::write_volatile(bytes.add(i), 0);
+			}
+
+			compiler_fence(Ordering::SeqCst);
+
+			if self.locked {
+				sys::unlock(bytes, self.layout.size());
+			}
+
+			alloc::dealloc(bytes, self.layout);
+		}
+	}
+}
+
+impl<T> fmt::Debug for SecretBox<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SecretBox").field("locked", &self.locked).finish_non_exhaustive()
+	}
+}
+
+#[test]
+fn test_secret_box() {
+	let mut secret = SecretBox::new_with(|key: &mut [u8; 32]| {
+		key.copy_from_slice(&[7; 32]);
+	});
+
+	assert_eq!(secret.expose_secret(|key| *key), [7; 32]);
+
+	secret.expose_secret_mut(|key| key[0] = 1);
+	assert_eq!(secret.expose_secret(|key| key[0 .. 2].to_vec()), [1, 7]);
+
+	let addr = secret.expose_secret(|key| key.as_ptr() as usize);
+	assert_eq!(addr % sys::page_size(), 0);
+
+	assert!(!format!("{:?}", secret).contains('7'));
+}
+
+#[test]
+fn test_secret_box_drops_value() {
+	use std::rc::Rc;
+
+	let counter = Rc::new(());
+	let secret = SecretBox::new(Rc::clone(&counter));
+	assert_eq!(Rc::strong_count(&counter), 2);
+
+	drop(secret);
+	assert_eq!(Rc::strong_count(&counter), 1);
+}
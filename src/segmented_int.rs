@@ -7,7 +7,12 @@
 use core::ops::{
 	Add,
 	AddAssign,
+	BitAnd,
 	BitAndAssign,
+	BitOr,
+	BitOrAssign,
+	BitXor,
+	BitXorAssign,
 	Mul,
 	MulAssign,
 	Neg,
@@ -17,6 +22,8 @@ use core::ops::{
 	SubAssign,
 };
 
+use crate::choice::{Choice, ConditionallySelectable, ConstantTimeEq};
+
 // TODO: consider whether it's worth it to use a multiplication type as well as a segment type
 // so that, for instance, numbers could be stored as 32-bit integers, but use 64-bit ints to multiply
 pub trait SegmentedIntDescriptor {
@@ -25,8 +32,15 @@ pub trait SegmentedIntDescriptor {
 	type SegmentType:
 		Add<Output = Self::SegmentType> +
 		AddAssign +
+		BitAnd<Output = Self::SegmentType> +
 		BitAndAssign +
+		BitOr<Output = Self::SegmentType> +
+		BitOrAssign +
+		BitXor<Output = Self::SegmentType> +
+		BitXorAssign +
 		Copy +
+		From<u8> +
+		Into<u128> +
 		Mul<Output = Self::SegmentType> +
 		Not<Output = Self::SegmentType> +
 		Shr<u16, Output = Self::SegmentType> +
@@ -40,20 +54,43 @@ pub trait SegmentedIntDescriptor {
 
 	const NUM_ADD_CARRIES: usize = 2;
 	const NUM_MUL_CARRIES: usize = 3;
+
+	// the hooks below let a descriptor swap in its own implementation of an
+	// operation, such as a formally verified one, in place of the generic code
+	// further down; they take and return numbers whose segments all fit in
+	// the segment size, and returning None falls back to the generic code
+
+	fn add_segments<const N: usize>(
+		_num_a: &[Self::SegmentType; N],
+		_num_b: &[Self::SegmentType; N],
+	) -> Option<[Self::SegmentType; N]> {
+		None
+	}
+
+	fn mul_segments<const N: usize>(
+		_num_a: &[Self::SegmentType; N],
+		_num_b: &[Self::SegmentType; N],
+	) -> Option<[Self::SegmentType; N]> {
+		None
+	}
+
+	fn neg_segments<const N: usize>(
+		_num: &[Self::SegmentType; N],
+	) -> Option<[Self::SegmentType; N]> {
+		None
+	}
 }
 
-/// Represents an integer that's been divided into 5 equally sized segments.
-/// When const generics become more of a thing, this can become generic:
-/// instead of always having 5 segments, it could vary.
-pub struct SegmentedInt<T: SegmentedIntDescriptor> {
-	pub segments: [T::SegmentType; 5],
+/// Represents an integer that's been divided into `N` equally sized segments.
+pub struct SegmentedInt<T: SegmentedIntDescriptor, const N: usize> {
+	pub segments: [T::SegmentType; N],
 }
 
-fn carry_propagate<T: SegmentedIntDescriptor>(
-	segments: &mut [T::SegmentType; 5],
+fn carry_propagate<T: SegmentedIntDescriptor, const N: usize>(
+	segments: &mut [T::SegmentType; N],
 	mut carry: T::SegmentType,
 ) -> T::SegmentType {
-	for i in 0 .. 5 {
+	for i in 0 .. N {
 		segments[i] += carry;
 		carry = extract_carry::<T>(&mut segments[i]);
 	}
@@ -61,6 +98,11 @@ fn carry_propagate<T: SegmentedIntDescriptor>(
 	carry
 }
 
+// all ones if `choice` is set, and all zeros otherwise
+fn choice_mask<T: SegmentedIntDescriptor>(choice: Choice) -> T::SegmentType {
+	!T::ZERO * T::SegmentType::from(choice.unwrap_u8())
+}
+
 fn extract_carry<T: SegmentedIntDescriptor>(
 	segment: &mut T::SegmentType,
 ) -> T::SegmentType {
@@ -70,90 +112,118 @@ fn extract_carry<T: SegmentedIntDescriptor>(
 	carry
 }
 
-impl<T: SegmentedIntDescriptor> Copy for SegmentedInt<T> {}
+impl<T: SegmentedIntDescriptor, const N: usize> Copy for SegmentedInt<T, N> {}
 
-impl<T: SegmentedIntDescriptor> Clone for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> Clone for SegmentedInt<T, N> {
 	fn clone(&self) -> Self {
 		*self
 	}
 }
 
-impl<T: SegmentedIntDescriptor> Add for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> Add for SegmentedInt<T, N> {
 	type Output = Self;
 
 	fn add(self, other: Self) -> Self {
-		let mut segments = [T::ZERO; 5];
+		if let Some(segments) = T::add_segments(&self.segments, &other.segments) {
+			return Self {segments};
+		}
 
-		for i in 0 .. 5 {
+		let mut segments = [T::ZERO; N];
+
+		for i in 0 .. N {
 			segments[i] = self.segments[i] + other.segments[i];
 		}
 
-		let mut carry = extract_carry::<T>(&mut segments[4]);
+		let mut carry = extract_carry::<T>(&mut segments[N - 1]);
 
 		for _ in 0 .. T::NUM_ADD_CARRIES {
-			carry = carry_propagate::<T>(&mut segments, carry * T::CARRY_FACTOR);
+			carry = carry_propagate::<T, N>(&mut segments, carry * T::CARRY_FACTOR);
 		}
 
 		Self {segments}
 	}
 }
 
-impl<T: SegmentedIntDescriptor> AddAssign for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> AddAssign for SegmentedInt<T, N> {
 	fn add_assign(&mut self, other: Self) {
-		for i in 0 .. 5 {
+		if let Some(segments) = T::add_segments(&self.segments, &other.segments) {
+			self.segments = segments;
+			return;
+		}
+
+		for i in 0 .. N {
 			self.segments[i] += other.segments[i];
 		}
 
-		let mut carry = extract_carry::<T>(&mut self.segments[4]);
+		let mut carry = extract_carry::<T>(&mut self.segments[N - 1]);
 
 		for _ in 0 .. T::NUM_ADD_CARRIES {
-			carry = carry_propagate::<T>(&mut self.segments, carry * T::CARRY_FACTOR);
+			carry = carry_propagate::<T, N>(&mut self.segments, carry * T::CARRY_FACTOR);
 		}
 	}
 }
 
-impl<T: SegmentedIntDescriptor> Mul for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> Mul for SegmentedInt<T, N> {
 	type Output = Self;
 
 	fn mul(self, other: Self) -> Self {
-		let mut segments = [T::ZERO; 5];
+		if let Some(segments) = T::mul_segments(&self.segments, &other.segments) {
+			return Self {segments};
+		}
 
 		let a = self.segments;
 		let b = other.segments;
 
-		segments[0] = a[0] * b[0] + T::CARRY_FACTOR * (a[1] * b[4] + a[2] * b[3] + a[3] * b[2] + a[4] * b[1]);
-		segments[1] = a[0] * b[1] + a[1] * b[0] + T::CARRY_FACTOR * (a[2] * b[4] + a[3] * b[3] + a[4] * b[2]);
-		segments[2] = a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + T::CARRY_FACTOR * (a[3] * b[4] + a[4] * b[3]);
-		segments[3] = a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + T::CARRY_FACTOR * a[4] * b[4];
-		segments[4] = a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0];
+		let mut segments = [T::ZERO; N];
+
+		// products that land at or above segment N wrap around to the bottom,
+		// multiplied by the carry factor
+		for k in 0 .. N {
+			let mut low = a[0] * b[k];
+			let mut high = T::ZERO;
 
-		let mut carry = extract_carry::<T>(&mut segments[4]);
+			for i in 1 .. k + 1 {
+				low += a[i] * b[k - i];
+			}
+
+			for i in k + 1 .. N {
+				high += a[i] * b[N + k - i];
+			}
+
+			segments[k] = low + T::CARRY_FACTOR * high;
+		}
+
+		let mut carry = extract_carry::<T>(&mut segments[N - 1]);
 
 		for _ in 0 .. T::NUM_MUL_CARRIES {
-			carry = carry_propagate::<T>(&mut segments, carry * T::CARRY_FACTOR);
+			carry = carry_propagate::<T, N>(&mut segments, carry * T::CARRY_FACTOR);
 		}
 
 		Self {segments}
 	}
 }
 
-impl<T: SegmentedIntDescriptor> MulAssign for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> MulAssign for SegmentedInt<T, N> {
 	fn mul_assign(&mut self, other: Self) {
 		*self = *self * other;
 	}
 }
 
-impl<T: SegmentedIntDescriptor> Neg for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> Neg for SegmentedInt<T, N> {
 	type Output = Self;
 
 	fn neg(mut self) -> Self {
+		if let Some(segments) = T::neg_segments(&self.segments) {
+			return Self {segments};
+		}
+
 		let mut carry = T::ONE;
 
 		for _ in 0 .. T::NUM_ADD_CARRIES {
-			carry = carry_propagate::<T>(&mut self.segments, carry * T::CARRY_FACTOR);
+			carry = carry_propagate::<T, N>(&mut self.segments, carry * T::CARRY_FACTOR);
 		}
 
-		for i in 0 .. 5 {
+		for i in 0 .. N {
 			self.segments[i] = !self.segments[i];
 			self.segments[i] &= T::SEGMENT_MASK;
 		}
@@ -161,14 +231,14 @@ impl<T: SegmentedIntDescriptor> Neg for SegmentedInt<T> {
 		let mut carry = T::ONE;
 
 		for _ in 0 .. T::NUM_ADD_CARRIES {
-			carry = carry_propagate::<T>(&mut self.segments, carry) * T::CARRY_FACTOR;
+			carry = carry_propagate::<T, N>(&mut self.segments, carry) * T::CARRY_FACTOR;
 		}
 
 		self
 	}
 }
 
-impl<T: SegmentedIntDescriptor> Sub for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> Sub for SegmentedInt<T, N> {
 	type Output = Self;
 
 	fn sub(self, other: Self) -> Self {
@@ -176,19 +246,68 @@ impl<T: SegmentedIntDescriptor> Sub for SegmentedInt<T> {
 	}
 }
 
-impl<T: SegmentedIntDescriptor> SubAssign for SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> SubAssign for SegmentedInt<T, N> {
 	fn sub_assign(&mut self, other: Self) {
 		*self += -other
 	}
 }
 
-impl<T: SegmentedIntDescriptor> SegmentedInt<T> {
+impl<T: SegmentedIntDescriptor, const N: usize> SegmentedInt<T, N> {
 	/// Reduces the number passed in so that it's guaranteed to be below
 	/// whatever prime modulus we're using.
 	pub fn full_modular_reduction(&mut self) {
 		// TODO: explain what this is doing
 		let mut segments_copy = self.segments;
-		let carry = carry_propagate::<T>(&mut segments_copy, T::CARRY_FACTOR);
-		carry_propagate::<T>(&mut self.segments, carry * T::CARRY_FACTOR);
+		let carry = carry_propagate::<T, N>(&mut segments_copy, T::CARRY_FACTOR);
+		carry_propagate::<T, N>(&mut self.segments, carry * T::CARRY_FACTOR);
+	}
+}
+
+impl<T: SegmentedIntDescriptor, const N: usize> ConstantTimeEq for SegmentedInt<T, N> {
+	/// Returns whether the two numbers are equal modulo the prime, fully
+	/// reducing copies of both first.
+	fn ct_eq(&self, other: &Self) -> Choice {
+		let mut num_a = *self;
+		let mut num_b = *other;
+		num_a.full_modular_reduction();
+		num_b.full_modular_reduction();
+
+		let mut diff = T::ZERO;
+
+		for i in 0 .. N {
+			diff |= num_a.segments[i] ^ num_b.segments[i];
+		}
+
+		// every segment of a reduced number fits in the segment size, so adding
+		// the mask to the difference carries out of it unless it's zero
+		let not_equal: u128 = ((diff + T::SEGMENT_MASK) >> T::SEGMENT_SIZE).into();
+
+		Choice::from(1 - not_equal as u8)
+	}
+}
+
+impl<T: SegmentedIntDescriptor, const N: usize> ConditionallySelectable for SegmentedInt<T, N> {
+	fn conditional_select(num_a: &Self, num_b: &Self, choice: Choice) -> Self {
+		let mut out = *num_a;
+		out.conditional_assign(num_b, choice);
+		out
+	}
+
+	fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+		let mask = choice_mask::<T>(choice);
+
+		for i in 0 .. N {
+			self.segments[i] ^= mask & (self.segments[i] ^ other.segments[i]);
+		}
+	}
+
+	fn conditional_swap(num_a: &mut Self, num_b: &mut Self, choice: Choice) {
+		let mask = choice_mask::<T>(choice);
+
+		for i in 0 .. N {
+			let temp = mask & (num_a.segments[i] ^ num_b.segments[i]);
+			num_a.segments[i] ^= temp;
+			num_b.segments[i] ^= temp;
+		}
 	}
 }
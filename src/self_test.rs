@@ -0,0 +1,342 @@
+//! Power-on self-tests, which check each primitive against a known answer
+//! before it's used, as FIPS 140 style deployments require. A primitive that
+//! gives a wrong answer, whether from a miscompilation, faulty hardware or a
+//! corrupted binary, shows up as a failure in the returned [`Report`] rather
+//! than as a panic, so that the caller decides what to do about it.
+//!
+//! ```
+//! let report = libkrypton::self_test();
+//! assert!(report.passed(), "self-tests failed:\n{}", report);
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "chacha20")]
+use crate::chacha20::ChaCha20;
+#[cfg(feature = "ed25519")]
+use crate::ed25519::{SigningKey, Signature, VerifyingKey};
+#[cfg(feature = "poly1305")]
+use crate::poly1305::poly1305;
+#[cfg(feature = "sha2")]
+use crate::sha2::{sha256, sha512};
+#[cfg(feature = "sha3")]
+use crate::sha3::sha3_256;
+#[cfg(feature = "x25519")]
+use crate::x25519::x25519_derive_secret;
+
+/// A primitive checked by [`self_test`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+	Sha256,
+	Sha512,
+	Sha3_256,
+	ChaCha20,
+	Poly1305,
+	X25519,
+	Ed25519,
+}
+
+impl Algorithm {
+	/// Every algorithm, in the order they're tested.
+	pub const ALL: [Algorithm; 7] = [
+		Algorithm::Sha256,
+		Algorithm::Sha512,
+		Algorithm::Sha3_256,
+		Algorithm::ChaCha20,
+		Algorithm::Poly1305,
+		Algorithm::X25519,
+		Algorithm::Ed25519,
+	];
+
+	/// Returns the usual name of the algorithm.
+	pub fn name(self) -> &'static str {
+		match self {
+			Algorithm::Sha256 => "SHA-256",
+			Algorithm::Sha512 => "SHA-512",
+			Algorithm::Sha3_256 => "SHA3-256",
+			Algorithm::ChaCha20 => "ChaCha20",
+			Algorithm::Poly1305 => "Poly1305",
+			Algorithm::X25519 => "X25519",
+			Algorithm::Ed25519 => "Ed25519",
+		}
+	}
+}
+
+impl fmt::Display for Algorithm {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// The results of [`self_test`], with whether each algorithm that was tested
+/// gave the known answer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+	// indexed by the position of the algorithm in `Algorithm::ALL`, and
+	// `None` for algorithms that weren't tested
+	results: [Option<bool>; Algorithm::ALL.len()],
+}
+
+impl Report {
+	/// Returns whether every tested algorithm passed.
+	pub fn passed(&self) -> bool {
+		self.results.iter().all(|&result| result != Some(false))
+	}
+
+	/// Returns whether `algorithm` passed, or `None` if it wasn't tested.
+	pub fn result(&self, algorithm: Algorithm) -> Option<bool> {
+		self.results[algorithm as usize]
+	}
+
+	/// Returns each tested algorithm along with whether it passed.
+	pub fn results(&self) -> impl Iterator<Item = (Algorithm, bool)> + '_ {
+		Algorithm::ALL.into_iter().filter_map(|algorithm| Some((algorithm, self.result(algorithm)?)))
+	}
+
+	/// Returns the algorithms that failed.
+	pub fn failures(&self) -> impl Iterator<Item = Algorithm> + '_ {
+		self.results().filter(|&(_, passed)| !passed).map(|(algorithm, _)| algorithm)
+	}
+}
+
+/// Lists each tested algorithm with whether it passed, one per line.
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (algorithm, passed) in self.results() {
+			let outcome = match passed {
+				true => "passed",
+				false => "FAILED",
+			};
+
+			writeln!(f, "{}: {}", algorithm, outcome)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Runs a known-answer test for every algorithm in [`Algorithm::ALL`] whose
+/// cargo feature is turned on, and returns which of them passed.
+pub fn self_test() -> Report {
+	let mut results = [None; Algorithm::ALL.len()];
+
+	for algorithm in Algorithm::ALL {
+		results[algorithm as usize] = check(algorithm);
+	}
+
+	Report {results}
+}
+
+// returns `None` for algorithms whose cargo features are turned off
+fn check(algorithm: Algorithm) -> Option<bool> {
+	match algorithm {
+		#[cfg(feature = "sha2")]
+		Algorithm::Sha256 => Some(sha256(b"abc") == SHA256_ABC),
+		#[cfg(feature = "sha2")]
+		Algorithm::Sha512 => Some(sha512(b"abc") == SHA512_ABC),
+		#[cfg(feature = "sha3")]
+		Algorithm::Sha3_256 => Some(sha3_256(b"abc") == SHA3_256_ABC),
+		#[cfg(feature = "chacha20")]
+		Algorithm::ChaCha20 => Some(check_chacha20()),
+		#[cfg(feature = "poly1305")]
+		Algorithm::Poly1305 => Some(check_poly1305()),
+		#[cfg(feature = "x25519")]
+		Algorithm::X25519 => Some(check_x25519()),
+		#[cfg(feature = "ed25519")]
+		Algorithm::Ed25519 => Some(check_ed25519()),
+		#[allow(unreachable_patterns)]
+		_ => None,
+	}
+}
+
+// the digests of "abc", from FIPS 180-4 and FIPS 202
+
+#[cfg(feature = "sha2")]
+const SHA256_ABC: [u8; 32] = [
+	0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea,
+	0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+	0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c,
+	0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+#[cfg(feature = "sha2")]
+const SHA512_ABC: [u8; 64] = [
+	0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba,
+	0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41, 0x31,
+	0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2,
+	0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55, 0xd3, 0x9a,
+	0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8,
+	0x36, 0xba, 0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd,
+	0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e,
+	0x2a, 0x9a, 0xc9, 0x4f, 0xa5, 0x4c, 0xa4, 0x9f,
+];
+
+#[cfg(feature = "sha3")]
+const SHA3_256_ABC: [u8; 32] = [
+	0x3a, 0x98, 0x5d, 0xa7, 0x4f, 0xe2, 0x25, 0xb2,
+	0x04, 0x5c, 0x17, 0x2d, 0x6b, 0xd3, 0x90, 0xbd,
+	0x85, 0x5f, 0x08, 0x6e, 0x3e, 0x9d, 0x52, 0x5b,
+	0x46, 0xbf, 0xe2, 0x45, 0x11, 0x43, 0x15, 0x32,
+];
+
+// from section 2.3.2 of RFC 8439, the second block of the keystream
+#[cfg(feature = "chacha20")]
+fn check_chacha20() -> bool {
+	let key = [
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+		0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+		0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+	];
+	let nonce = [
+		0x00, 0x00, 0x00, 0x09,
+		0x00, 0x00, 0x00, 0x4a,
+		0x00, 0x00, 0x00, 0x00,
+	];
+	let expected = [
+		0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15,
+		0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20, 0x71, 0xc4,
+		0xc7, 0xd1, 0xf4, 0xc7, 0x33, 0xc0, 0x68, 0x03,
+		0x04, 0x22, 0xaa, 0x9a, 0xc3, 0xd4, 0x6c, 0x4e,
+		0xd2, 0x82, 0x64, 0x46, 0x07, 0x9f, 0xaa, 0x09,
+		0x14, 0xc2, 0xd7, 0x05, 0xd9, 0x8b, 0x02, 0xa2,
+		0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16, 0x4e, 0xb9,
+		0xcb, 0xd0, 0x83, 0xe8, 0xa2, 0x50, 0x3c, 0x4e,
+	];
+
+	let mut keystream = [0; 64];
+	let mut cipher = ChaCha20::new(key, nonce);
+	cipher.set_pos(64);
+	cipher.crypt(&mut keystream);
+
+	keystream == expected
+}
+
+// from section 2.5.2 of RFC 8439
+#[cfg(feature = "poly1305")]
+fn check_poly1305() -> bool {
+	let radix = [
+		0x85, 0xd6, 0xbe, 0x78, 0x57, 0x55, 0x6d, 0x33,
+		0x7f, 0x44, 0x52, 0xfe, 0x42, 0xd5, 0x06, 0xa8,
+	];
+	let nonce = [
+		0x01, 0x03, 0x80, 0x8a, 0xfb, 0x0d, 0xb2, 0xfd,
+		0x4a, 0xbf, 0xf6, 0xaf, 0x41, 0x49, 0xf5, 0x1b,
+	];
+	let expected = [
+		0xa8, 0x06, 0x1d, 0xc1, 0x30, 0x51, 0x36, 0xc6,
+		0xc2, 0x2b, 0x8b, 0xaf, 0x0c, 0x01, 0x27, 0xa9,
+	];
+
+	poly1305(b"Cryptographic Forum Research Group", radix, nonce) == expected
+}
+
+// from section 5.2 of RFC 7748
+#[cfg(feature = "x25519")]
+fn check_x25519() -> bool {
+	let scalar = [
+		0xa5, 0x46, 0xe3, 0x6b, 0xf0, 0x52, 0x7c, 0x9d,
+		0x3b, 0x16, 0x15, 0x4b, 0x82, 0x46, 0x5e, 0xdd,
+		0x62, 0x14, 0x4c, 0x0a, 0xc1, 0xfc, 0x5a, 0x18,
+		0x50, 0x6a, 0x22, 0x44, 0xba, 0x44, 0x9a, 0xc4,
+	];
+	let coordinate = [
+		0xe6, 0xdb, 0x68, 0x67, 0x58, 0x30, 0x30, 0xdb,
+		0x35, 0x94, 0xc1, 0xa4, 0x24, 0xb1, 0x5f, 0x7c,
+		0x72, 0x66, 0x24, 0xec, 0x26, 0xb3, 0x35, 0x3b,
+		0x10, 0xa9, 0x03, 0xa6, 0xd0, 0xab, 0x1c, 0x4c,
+	];
+	let expected = [
+		0xc3, 0xda, 0x55, 0x37, 0x9d, 0xe9, 0xc6, 0x90,
+		0x8e, 0x94, 0xea, 0x4d, 0xf2, 0x8d, 0x08, 0x4f,
+		0x32, 0xec, 0xcf, 0x03, 0x49, 0x1c, 0x71, 0xf7,
+		0x54, 0xb4, 0x07, 0x55, 0x77, 0xa2, 0x85, 0x52,
+	];
+
+	x25519_derive_secret(scalar, coordinate) == expected
+}
+
+// test 1 from section 7.1 of RFC 8032, which signs the empty message; both
+// signing and verification are checked, including that verification rejects
+// a corrupted signature
+#[cfg(feature = "ed25519")]
+fn check_ed25519() -> bool {
+	let priv_key = [
+		0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60,
+		0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+		0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+		0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+	];
+	let pub_key = [
+		0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+		0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+		0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+		0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+	];
+	let expected = [
+		0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+		0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+		0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+		0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+		0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+		0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+		0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+		0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b,
+	];
+
+	let priv_key = SigningKey::from_bytes(priv_key);
+	let signature = priv_key.sign(&[]);
+
+	if priv_key.verifying_key().to_bytes() != pub_key || signature.to_bytes() != expected {
+		return false;
+	}
+
+	let mut corrupted = expected;
+	corrupted[0] ^= 1;
+
+	let pub_key = match VerifyingKey::from_bytes(pub_key) {
+		Ok(pub_key) => pub_key,
+		Err(_) => return false,
+	};
+
+	let rejects_corrupted = match Signature::from_bytes(corrupted) {
+		Ok(corrupted) => !pub_key.verify(&[], &corrupted),
+		Err(_) => true,
+	};
+
+	pub_key.verify(&[], &signature) && rejects_corrupted
+}
+
+#[test]
+fn test_self_test() {
+	let report = self_test();
+
+	assert!(report.passed());
+	assert_eq!(report.failures().count(), 0);
+
+	#[cfg(feature = "full")]
+	assert_eq!(report.results().count(), Algorithm::ALL.len());
+}
+
+#[test]
+fn test_report() {
+	let mut results = [Some(true); Algorithm::ALL.len()];
+	results[Algorithm::Poly1305 as usize] = Some(false);
+	results[Algorithm::X25519 as usize] = None;
+	let report = Report {results};
+
+	assert!(!report.passed());
+	assert!(report.failures().eq([Algorithm::Poly1305]));
+	assert_eq!(report.result(Algorithm::X25519), None);
+	assert_eq!(report.results().count(), Algorithm::ALL.len() - 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_report_display() {
+	let mut results = [None; Algorithm::ALL.len()];
+	results[Algorithm::Sha256 as usize] = Some(true);
+	results[Algorithm::Ed25519 as usize] = Some(false);
+
+	assert_eq!(format!("{}", Report {results}), "SHA-256: passed\nEd25519: FAILED\n");
+}
@@ -0,0 +1,188 @@
+//! Serialization of the fixed-size byte strings that keys, signatures and
+//! nonces are encoded as, for the `serde` feature. Human-readable formats,
+//! such as JSON and TOML, get a string: hex for short values, or standard
+//! base64 for long ones such as post-quantum keys. Other formats get a tuple
+//! of bytes, which most of them store without a length prefix.
+
+use core::fmt;
+
+use ::serde::de::{self, Deserializer, SeqAccess, Unexpected, Visitor};
+use ::serde::ser::{SerializeTuple, Serializer};
+
+use crate::text::{self, Encoded, Text};
+use crate::zeroize::zeroize;
+
+pub fn serialize<S: Serializer>(bytes: &[u8], text: Text, serializer: S) -> Result<S::Ok, S::Error> {
+	if serializer.is_human_readable() {
+		return serializer.collect_str(&Encoded(bytes, text));
+	}
+
+	let mut tuple = serializer.serialize_tuple(bytes.len())?;
+
+	for byte in bytes {
+		tuple.serialize_element(byte)?;
+	}
+
+	tuple.end()
+}
+
+struct BytesVisitor<const N: usize>(Text);
+
+impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+	type Value = [u8; N];
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0 {
+			Text::Hex => write!(f, "{} bytes, or a hex string of them", N),
+			Text::Base64 => write!(f, "{} bytes, or a base64 string of them", N),
+		}
+	}
+
+	// the errors never include the text, which may be a secret key
+	fn visit_str<E: de::Error>(self, text: &str) -> Result<[u8; N], E> {
+		text::decode(text, self.0).map_err(|_| E::invalid_value(Unexpected::Other("string"), &self))
+	}
+
+	fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<[u8; N], E> {
+		bytes.try_into().map_err(|_| E::invalid_length(bytes.len(), &self))
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u8; N], A::Error> {
+		let mut out = [0; N];
+
+		for i in 0 .. N {
+			match seq.next_element()? {
+				Some(byte) => out[i] = byte,
+				None => {
+					zeroize(&mut out);
+					return Err(de::Error::invalid_length(i, &self));
+				}
+			}
+		}
+
+		Ok(out)
+	}
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(text: Text, deserializer: D) -> Result<[u8; N], D::Error> {
+	if deserializer.is_human_readable() {
+		deserializer.deserialize_str(BytesVisitor(text))
+	} else {
+		deserializer.deserialize_tuple(N, BytesVisitor(text))
+	}
+}
+
+// implements Serialize and Deserialize for a type encoded as `$len` bytes,
+// given how to convert a value to its bytes and back, which may fail if
+// marked with `try`; the bytes are zeroized afterwards, since they may be
+// secret
+macro_rules! serde_bytes_impl {
+	($type:ty, $len:expr, $text:ident, |$value:ident| $to_bytes:expr, |$bytes:ident| $from_bytes:expr) => {
+		serde_bytes_impl!($type, $len, $text, |$value| $to_bytes, try |$bytes| {
+			Ok::<_, core::convert::Infallible>($from_bytes)
+		});
+	};
+
+	($type:ty, $len:expr, $text:ident, |$value:ident| $to_bytes:expr, try |$bytes:ident| $from_bytes:expr) => {
+		impl ::serde::Serialize for $type {
+			fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				let $value = self;
+				let mut bytes: [u8; $len] = $to_bytes;
+
+				let result = crate::serde_support::serialize(&bytes, crate::text::Text::$text, serializer);
+				crate::zeroize::zeroize(&mut bytes);
+				result
+			}
+		}
+
+		impl<'de> ::serde::Deserialize<'de> for $type {
+			fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				let text = crate::text::Text::$text;
+				let mut $bytes: [u8; $len] = crate::serde_support::deserialize(text, deserializer)?;
+
+				let result = $from_bytes;
+				crate::zeroize::zeroize(&mut $bytes);
+				result.map_err(::serde::de::Error::custom)
+			}
+		}
+	};
+}
+
+#[cfg(all(test, feature = "std"))]
+#[cfg_attr(not(feature = "full"), allow(dead_code))]
+fn tuple_tokens(bytes: &[u8]) -> std::vec::Vec<serde_test::Token> {
+	use serde_test::Token;
+
+	let mut tokens = std::vec![Token::Tuple {len: bytes.len()}];
+	tokens.extend(bytes.iter().map(|&byte| Token::U8(byte)));
+	tokens.push(Token::TupleEnd);
+	tokens
+}
+
+#[cfg(all(feature = "std", feature = "x25519"))]
+#[test]
+fn test_serde_hex() {
+	use serde_test::{assert_de_tokens_error, assert_tokens, Compact, Configure, Readable, Token};
+
+	use crate::x25519::PublicKey;
+
+	let text = "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f";
+	let mut bytes = [0; 32];
+	crate::encoding::decode_hex(text, &mut bytes).unwrap();
+	let key = PublicKey::from_bytes(bytes);
+
+	assert_tokens(&key.readable(), &[Token::Str(text)]);
+	assert_tokens(&key.compact(), &tuple_tokens(&bytes));
+
+	assert_de_tokens_error::<Readable<PublicKey>>(
+		&[Token::Str("de9edb7d")],
+		"invalid value: string, expected 32 bytes, or a hex string of them",
+	);
+	assert_de_tokens_error::<Readable<PublicKey>>(
+		&[Token::Str("secret! not hex")],
+		"invalid value: string, expected 32 bytes, or a hex string of them",
+	);
+	assert_de_tokens_error::<Compact<PublicKey>>(
+		&[Token::Bytes(&[1, 2, 3])],
+		"invalid length 3, expected 32 bytes, or a hex string of them",
+	);
+	assert_de_tokens_error::<Compact<PublicKey>>(
+		&[Token::Tuple {len: 32}, Token::U8(1), Token::TupleEnd],
+		"invalid length 1, expected 32 bytes, or a hex string of them",
+	);
+}
+
+#[cfg(all(feature = "std", feature = "ed25519"))]
+#[test]
+fn test_serde_rejects_invalid_values() {
+	use serde_test::{assert_de_tokens_error, Compact, Readable, Token};
+
+	use crate::ed25519::VerifyingKey;
+	use crate::scalar::Scalar;
+
+	assert_de_tokens_error::<Readable<Scalar>>(
+		&[Token::Str("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff")],
+		"non-canonical scalar",
+	);
+
+	// y = 2 is not the y-coordinate of any point on the curve
+	let mut not_a_point = [0; 32];
+	not_a_point[0] = 2;
+	assert_de_tokens_error::<Compact<VerifyingKey>>(&tuple_tokens(&not_a_point), "invalid ed25519 public key");
+}
+
+#[cfg(all(feature = "std", feature = "ml-kem"))]
+#[test]
+fn test_serde_base64() {
+	use serde_test::{assert_de_tokens_error, assert_tokens, Configure, Readable, Token};
+
+	use crate::kem::ml_kem::{Ciphertext, CIPHERTEXT_LEN};
+
+	let ciphertext = Ciphertext::from_bytes([0x5a; CIPHERTEXT_LEN]);
+	assert_tokens(&ciphertext.compact(), &tuple_tokens(&[0x5a; CIPHERTEXT_LEN]));
+
+	assert_de_tokens_error::<Readable<Ciphertext>>(
+		&[Token::Str("WlpaWg==")],
+		"invalid value: string, expected 1088 bytes, or a base64 string of them",
+	);
+}
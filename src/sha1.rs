@@ -0,0 +1,168 @@
+//! Implemented according to [IETF RFC 3174](https://datatracker.ietf.org/doc/html/rfc3174).
+//! SHA-1 is broken as a collision resistant hash function, so it's only used
+//! inside HMAC, where that doesn't matter, for protocols such as HOTP that
+//! still require it.
+
+use core::convert::TryInto;
+
+use crate::zeroize::zeroize;
+
+const INITIAL_HASH_VALS: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+fn sha1_block(hash_vals: &mut [u32; 5], chunk: &[u8; 64]) {
+	let mut message_schedule = [0; 80];
+
+	for i in 0 .. 16 {
+		message_schedule[i] = u32::from_be_bytes(chunk[4 * i .. 4 * (i + 1)].try_into().unwrap());
+	}
+
+	for i in 16 .. 80 {
+		message_schedule[i] = (message_schedule[i - 3]
+			^ message_schedule[i - 8]
+			^ message_schedule[i - 14]
+			^ message_schedule[i - 16])
+			.rotate_left(1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e] = *hash_vals;
+
+	for i in 0 .. 80 {
+		let (f, k) = match i {
+			0 ..= 19 => ((b & c) | (!b & d), 0x5a827999),
+			20 ..= 39 => (b ^ c ^ d, 0x6ed9eba1),
+			40 ..= 59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+			_ => (b ^ c ^ d, 0xca62c1d6),
+		};
+
+		let temp = a
+			.rotate_left(5)
+			.wrapping_add(f)
+			.wrapping_add(e)
+			.wrapping_add(k)
+			.wrapping_add(message_schedule[i]);
+
+		e = d;
+		d = c;
+		c = b.rotate_left(30);
+		b = a;
+		a = temp;
+	}
+
+	for (hash_val, working_var) in hash_vals.iter_mut().zip([a, b, c, d, e]) {
+		*hash_val = hash_val.wrapping_add(working_var);
+	}
+}
+
+/// Struct used for computing SHA-1 on data not stored contiguously in memory.
+#[derive(Clone)]
+pub struct Sha1 {
+	hash_vals: [u32; 5],
+	block_buffer: [u8; 64],
+	block_pos: usize,
+	num_bytes: u64,
+}
+
+impl Sha1 {
+	/// Creates a new SHA-1 hasher.
+	pub fn new() -> Self {
+		Self {
+			hash_vals: INITIAL_HASH_VALS,
+			block_buffer: [0; 64],
+			block_pos: 0,
+			num_bytes: 0,
+		}
+	}
+
+	/// Adds bytes to the hasher.
+	pub fn add_bytes(&mut self, mut bytes: &[u8]) {
+		let adding_bytes: u64 = bytes.len().try_into().unwrap();
+		self.num_bytes = self.num_bytes.checked_add(adding_bytes).unwrap();
+
+		assert!(self.num_bytes < (1u64 << 61));
+
+		while bytes.len() != 0 {
+			let num_to_copy = (64 - self.block_pos).min(bytes.len());
+			let (copying, remainder) = bytes.split_at(num_to_copy);
+			bytes = remainder;
+			self.block_buffer[self.block_pos .. self.block_pos + num_to_copy].copy_from_slice(copying);
+			self.block_pos += num_to_copy;
+
+			if self.block_pos == 64 {
+				sha1_block(&mut self.hash_vals, &self.block_buffer);
+				self.block_pos = 0;
+			}
+		}
+	}
+
+	/// Consumes the hasher and outputs the SHA-1 digest of the concatenation
+	/// of all bytes added to it.
+	pub fn out(mut self) -> [u8; 20] {
+		let num_bits = 8 * self.num_bytes;
+
+		self.block_buffer[self.block_pos ..].fill(0);
+		self.block_buffer[self.block_pos] = 0x80;
+
+		if self.block_pos + 1 > 64 - 8 {
+			sha1_block(&mut self.hash_vals, &self.block_buffer);
+			self.block_buffer.fill(0);
+		}
+
+		self.block_buffer[64 - 8 ..].copy_from_slice(&num_bits.to_be_bytes());
+		sha1_block(&mut self.hash_vals, &self.block_buffer);
+
+		let mut out = [0; 20];
+
+		for i in 0 .. 5 {
+			out[4 * i .. 4 * (i + 1)].copy_from_slice(&self.hash_vals[i].to_be_bytes());
+		}
+
+		out
+	}
+}
+
+impl Drop for Sha1 {
+	fn drop(&mut self) {
+		zeroize(&mut self.hash_vals);
+		zeroize(&mut self.block_buffer);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sha1 {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.hash_vals);
+		zeroize(&mut self.block_buffer);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sha1 {}
+
+impl Default for Sha1 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from section 7.3 of RFC 3174
+#[test]
+fn test_rfc3174_vectors() {
+	let mut hasher = Sha1::new();
+	hasher.add_bytes(b"abc");
+	assert_eq!(hasher.out(), hex("a9993e364706816aba3e25717850c26c9cd0d89d"));
+
+	let mut hasher = Sha1::new();
+	hasher.add_bytes(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+	assert_eq!(hasher.out(), hex("84983e441c3bd26ebaae4aa1f95129e5e54670f1"));
+
+	let mut hasher = Sha1::new();
+
+	for _ in 0 .. 10000 {
+		hasher.add_bytes(&[b'a'; 100]);
+	}
+
+	assert_eq!(hasher.out(), hex("34aa973cd4c4daa4f61eeb2bdbad27316534016f"));
+}
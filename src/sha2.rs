@@ -2,6 +2,8 @@
 
 //! An implementation of [SHA-2](https://en.wikipedia.org/wiki/SHA-2).
 
+use crate::Error;
+
 const SHA224_INITIAL_HASH_VALS: [u32; 8] = [
 	0xc1059ed8,
 	0x367cd507,
@@ -47,8 +49,17 @@ const SHA512_INITIAL_HASH_VALS: [u64; 8] = [
 ];
 
 /// Returns the SHA-224 digest of the byte slice passed to it.
+///
+/// # Panics
+/// * Panics if the message is 2 ** 61 bytes or longer.
 pub fn sha224(msg: &[u8]) -> [u8; 28] {
-	let final_hash_vals = sha_small::sha_internal(SHA224_INITIAL_HASH_VALS, msg);
+	try_sha224(msg).expect("message too long for sha-224")
+}
+
+/// Like [`sha224`], but returns [`Error::MessageTooLong`] instead of
+/// panicking.
+pub fn try_sha224(msg: &[u8]) -> Result<[u8; 28], Error> {
+	let final_hash_vals = sha_small::sha_internal(SHA224_INITIAL_HASH_VALS, msg)?;
 
 	let mut out = [0; 28];
 
@@ -56,12 +67,21 @@ pub fn sha224(msg: &[u8]) -> [u8; 28] {
 		out[4 * i .. 4 * (i + 1)].copy_from_slice(&final_hash_vals[i].to_be_bytes());
 	}
 
-	out
+	Ok(out)
 }
 
 /// Returns the SHA-256 digest of the byte slice passed to it.
+///
+/// # Panics
+/// * Panics if the message is 2 ** 61 bytes or longer.
 pub fn sha256(msg: &[u8]) -> [u8; 32] {
-	let final_hash_vals = sha_small::sha_internal(SHA256_INITIAL_HASH_VALS, msg);
+	try_sha256(msg).expect("message too long for sha-256")
+}
+
+/// Like [`sha256`], but returns [`Error::MessageTooLong`] instead of
+/// panicking.
+pub fn try_sha256(msg: &[u8]) -> Result<[u8; 32], Error> {
+	let final_hash_vals = sha_small::sha_internal(SHA256_INITIAL_HASH_VALS, msg)?;
 
 	let mut out = [0; 32];
 
@@ -69,12 +89,21 @@ pub fn sha256(msg: &[u8]) -> [u8; 32] {
 		out[4 * i .. 4 * (i + 1)].copy_from_slice(&final_hash_vals[i].to_be_bytes());
 	}
 
-	out
+	Ok(out)
 }
 
 /// Returns the SHA-384 digest of the byte slice passed to it.
+///
+/// # Panics
+/// * Panics if the message is 2 ** 125 bytes or longer.
 pub fn sha384(msg: &[u8]) -> [u8; 48] {
-	let final_hash_vals = sha_big::sha_internal(SHA384_INITIAL_HASH_VALS, msg);
+	try_sha384(msg).expect("message too long for sha-384")
+}
+
+/// Like [`sha384`], but returns [`Error::MessageTooLong`] instead of
+/// panicking.
+pub fn try_sha384(msg: &[u8]) -> Result<[u8; 48], Error> {
+	let final_hash_vals = sha_big::sha_internal(SHA384_INITIAL_HASH_VALS, msg)?;
 
 	let mut out = [0; 48];
 
@@ -82,12 +111,21 @@ pub fn sha384(msg: &[u8]) -> [u8; 48] {
 		out[8 * i .. 8 * (i + 1)].copy_from_slice(&final_hash_vals[i].to_be_bytes());
 	}
 
-	out
+	Ok(out)
 }
 
 /// Returns the SHA-512 digest of the byte slice passed to it.
+///
+/// # Panics
+/// * Panics if the message is 2 ** 125 bytes or longer.
 pub fn sha512(msg: &[u8]) -> [u8; 64] {
-	let final_hash_vals = sha_big::sha_internal(SHA512_INITIAL_HASH_VALS, msg);
+	try_sha512(msg).expect("message too long for sha-512")
+}
+
+/// Like [`sha512`], but returns [`Error::MessageTooLong`] instead of
+/// panicking.
+pub fn try_sha512(msg: &[u8]) -> Result<[u8; 64], Error> {
+	let final_hash_vals = sha_big::sha_internal(SHA512_INITIAL_HASH_VALS, msg)?;
 
 	let mut out = [0; 64];
 
@@ -95,7 +133,7 @@ pub fn sha512(msg: &[u8]) -> [u8; 64] {
 		out[8 * i .. 8 * (i + 1)].copy_from_slice(&final_hash_vals[i].to_be_bytes());
 	}
 
-	out
+	Ok(out)
 }
 
 /// Struct used for computing SHA-224 on data not stored contiguously in memory.
@@ -111,8 +149,17 @@ impl Sha224 {
 	}
 
 	/// Adds bytes to the hasher.
+	///
+	/// # Panics
+	/// * Panics if 2 ** 61 or more bytes have been added in total.
 	pub fn add_bytes(&mut self, bytes: &[u8]) {
-		self.internal.add_bytes(bytes);
+		self.try_add_bytes(bytes).expect("message too long for sha-224");
+	}
+
+	/// Like [`Sha224::add_bytes`], but returns [`Error::MessageTooLong`]
+	/// instead of panicking, in which case the hasher is left unchanged.
+	pub fn try_add_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.internal.try_add_bytes(bytes)
 	}
 
 	/// Consumes the hasher and outputs the SHA-224 digest of the concatenation
@@ -130,6 +177,22 @@ impl Sha224 {
 	}
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sha224 {
+	fn zeroize(&mut self) {
+		self.internal.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sha224 {}
+
+impl Default for Sha224 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 /// Struct used for computing SHA-256 on data not stored contiguously in memory.
 #[derive(Clone)]
 pub struct Sha256 {
@@ -143,8 +206,17 @@ impl Sha256 {
 	}
 
 	/// Adds bytes to the hasher.
+	///
+	/// # Panics
+	/// * Panics if 2 ** 61 or more bytes have been added in total.
 	pub fn add_bytes(&mut self, bytes: &[u8]) {
-		self.internal.add_bytes(bytes);
+		self.try_add_bytes(bytes).expect("message too long for sha-256");
+	}
+
+	/// Like [`Sha256::add_bytes`], but returns [`Error::MessageTooLong`]
+	/// instead of panicking, in which case the hasher is left unchanged.
+	pub fn try_add_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.internal.try_add_bytes(bytes)
 	}
 
 	/// Consumes the hasher and outputs the SHA-256 digest of the concatenation
@@ -162,6 +234,22 @@ impl Sha256 {
 	}
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sha256 {
+	fn zeroize(&mut self) {
+		self.internal.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sha256 {}
+
+impl Default for Sha256 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 /// Struct used for computing SHA-384 on data not stored contiguously in memory.
 #[derive(Clone)]
 pub struct Sha384 {
@@ -175,8 +263,17 @@ impl Sha384 {
 	}
 
 	/// Adds bytes to the hasher.
+	///
+	/// # Panics
+	/// * Panics if 2 ** 125 or more bytes have been added in total.
 	pub fn add_bytes(&mut self, bytes: &[u8]) {
-		self.internal.add_bytes(bytes);
+		self.try_add_bytes(bytes).expect("message too long for sha-384");
+	}
+
+	/// Like [`Sha384::add_bytes`], but returns [`Error::MessageTooLong`]
+	/// instead of panicking, in which case the hasher is left unchanged.
+	pub fn try_add_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.internal.try_add_bytes(bytes)
 	}
 
 	/// Consumes the hasher and outputs the SHA-384 digest of the concatenation
@@ -194,6 +291,22 @@ impl Sha384 {
 	}
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sha384 {
+	fn zeroize(&mut self) {
+		self.internal.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sha384 {}
+
+impl Default for Sha384 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 /// Struct used for computing SHA-512 on data not stored contiguously in memory.
 #[derive(Clone)]
 pub struct Sha512 {
@@ -207,8 +320,17 @@ impl Sha512 {
 	}
 
 	/// Adds bytes to the hasher.
+	///
+	/// # Panics
+	/// * Panics if 2 ** 125 or more bytes have been added in total.
 	pub fn add_bytes(&mut self, bytes: &[u8]) {
-		self.internal.add_bytes(bytes);
+		self.try_add_bytes(bytes).expect("message too long for sha-512");
+	}
+
+	/// Like [`Sha512::add_bytes`], but returns [`Error::MessageTooLong`]
+	/// instead of panicking, in which case the hasher is left unchanged.
+	pub fn try_add_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.internal.try_add_bytes(bytes)
 	}
 
 	/// Consumes the hasher and outputs the SHA-512 digest of the concatenation
@@ -226,9 +348,28 @@ impl Sha512 {
 	}
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sha512 {
+	fn zeroize(&mut self) {
+		self.internal.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sha512 {}
+
+impl Default for Sha512 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 mod sha_small {
 	use core::convert::TryInto;
 
+	use crate::zeroize::zeroize;
+	use crate::Error;
+
 	const ROUND_CONSTANTS: [u32; 64] = [
 		0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
 		0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
@@ -240,11 +381,13 @@ mod sha_small {
 		0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
 	];
 
-	pub fn sha_internal(initial_hash_vals: [u32; 8], msg: &[u8]) -> [u32; 8] {
-		let byte_length: u64 = msg.len().try_into().unwrap();
+	pub fn sha_internal(initial_hash_vals: [u32; 8], msg: &[u8]) -> Result<[u32; 8], Error> {
+		let byte_length: u64 = msg.len().try_into().map_err(|_| Error::MessageTooLong)?;
 
 		// check that the original message has length less than 2 ** 64 bits
-		assert!(byte_length < (1u64 << 61));
+		if byte_length >= (1u64 << 61) {
+			return Err(Error::MessageTooLong);
+		}
 
 		// everything is processed in 512-bit = 64-byte chunks
 
@@ -275,7 +418,7 @@ mod sha_small {
 			sha_block(&mut hash_vals, chunk.try_into().unwrap());
 		}
 
-		hash_vals
+		Ok(hash_vals)
 	}
 
 	#[derive(Clone)]
@@ -296,11 +439,13 @@ mod sha_small {
 			}
 		}
 
-		pub fn add_bytes(&mut self, mut bytes: &[u8]) {
-			let adding_bytes = bytes.len().try_into().unwrap();
-			self.num_bytes = self.num_bytes.checked_add(adding_bytes).unwrap();
+		pub fn try_add_bytes(&mut self, mut bytes: &[u8]) -> Result<(), Error> {
+			let num_bytes = bytes.len().try_into().ok()
+				.and_then(|adding_bytes| self.num_bytes.checked_add(adding_bytes))
+				.filter(|&num_bytes| num_bytes < (1u64 << 61))
+				.ok_or(Error::MessageTooLong)?;
 
-			assert!(self.num_bytes < (1u64 << 61));
+			self.num_bytes = num_bytes;
 
 			while bytes.len() != 0 {
 				let num_to_copy = (64 - self.block_pos).min(bytes.len());
@@ -315,6 +460,8 @@ mod sha_small {
 					self.block_pos = 0;
 				}
 			}
+
+			Ok(())
 		}
 
 		pub fn out(mut self) -> [u32; 8] {
@@ -325,7 +472,7 @@ mod sha_small {
 
 			self.block_pos += 1;
 
-			if self.block_pos < 64 - 8 {
+			if self.block_pos <= 64 - 8 {
 				self.block_buffer[64 - 8 ..].copy_from_slice(&num_bits.to_be_bytes());
 				sha_block(&mut self.hash_vals, &self.block_buffer);
 			} else {
@@ -339,6 +486,21 @@ mod sha_small {
 		}
 	}
 
+	impl Drop for ShaHasher {
+		fn drop(&mut self) {
+			zeroize(&mut self.hash_vals);
+			zeroize(&mut self.block_buffer);
+		}
+	}
+
+	#[cfg(feature = "zeroize")]
+	impl ::zeroize::Zeroize for ShaHasher {
+		fn zeroize(&mut self) {
+			zeroize(&mut self.hash_vals);
+			zeroize(&mut self.block_buffer);
+		}
+	}
+
 	fn mix_shift(val: u32, rotation_a: u32, rotation_b: u32, shift: u32) -> u32 {
 		val.rotate_right(rotation_a) ^ val.rotate_right(rotation_b) ^ (val >> shift)
 	}
@@ -399,6 +561,9 @@ mod sha_small {
 mod sha_big {
 	use core::convert::TryInto;
 
+	use crate::zeroize::zeroize;
+	use crate::Error;
+
 	const ROUND_CONSTANTS: [u64; 80] = [
 		0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc, 0x3956c25bf348b538,
 		0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118, 0xd807aa98a3030242, 0x12835b0145706fbe,
@@ -418,11 +583,13 @@ mod sha_big {
 		0x431d67c49c100d4c, 0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
 	];
 
-	pub fn sha_internal(initial_hash_vals: [u64; 8], msg: &[u8]) -> [u64; 8] {
-		let byte_length: u128 = msg.len().try_into().unwrap();
+	pub fn sha_internal(initial_hash_vals: [u64; 8], msg: &[u8]) -> Result<[u64; 8], Error> {
+		let byte_length: u128 = msg.len().try_into().map_err(|_| Error::MessageTooLong)?;
 
 		// check that the original message has length less than 2 ** 128 bits
-		assert!(byte_length < (1u128 << 125));
+		if byte_length >= (1u128 << 125) {
+			return Err(Error::MessageTooLong);
+		}
 
 		// everything is processed in 1024-bit = 128-byte chunks
 
@@ -453,7 +620,7 @@ mod sha_big {
 			sha_block(&mut hash_vals, chunk.try_into().unwrap());
 		}
 
-		hash_vals
+		Ok(hash_vals)
 	}
 
 	#[derive(Clone)]
@@ -474,11 +641,13 @@ mod sha_big {
 			}
 		}
 
-		pub fn add_bytes(&mut self, mut bytes: &[u8]) {
-			let adding_bytes = bytes.len().try_into().unwrap();
-			self.num_bytes = self.num_bytes.checked_add(adding_bytes).unwrap();
+		pub fn try_add_bytes(&mut self, mut bytes: &[u8]) -> Result<(), Error> {
+			let num_bytes = bytes.len().try_into().ok()
+				.and_then(|adding_bytes| self.num_bytes.checked_add(adding_bytes))
+				.filter(|&num_bytes| num_bytes < (1u128 << 125))
+				.ok_or(Error::MessageTooLong)?;
 
-			assert!(self.num_bytes < (1u128 << 125));
+			self.num_bytes = num_bytes;
 
 			while bytes.len() != 0 {
 				let num_to_copy = (128 - self.block_pos).min(bytes.len());
@@ -493,6 +662,8 @@ mod sha_big {
 					self.block_pos = 0;
 				}
 			}
+
+			Ok(())
 		}
 
 		pub fn out(mut self) -> [u64; 8] {
@@ -503,7 +674,7 @@ mod sha_big {
 
 			self.block_pos += 1;
 
-			if self.block_pos < 128 - 16 {
+			if self.block_pos <= 128 - 16 {
 				self.block_buffer[128 - 16 ..].copy_from_slice(&num_bits.to_be_bytes());
 				sha_block(&mut self.hash_vals, &self.block_buffer);
 			} else {
@@ -517,6 +688,21 @@ mod sha_big {
 		}
 	}
 
+	impl Drop for ShaHasher {
+		fn drop(&mut self) {
+			zeroize(&mut self.hash_vals);
+			zeroize(&mut self.block_buffer);
+		}
+	}
+
+	#[cfg(feature = "zeroize")]
+	impl ::zeroize::Zeroize for ShaHasher {
+		fn zeroize(&mut self) {
+			zeroize(&mut self.hash_vals);
+			zeroize(&mut self.block_buffer);
+		}
+	}
+
 	fn mix_shift(val: u64, rotation_a: u32, rotation_b: u32, shift: u32) -> u64 {
 		val.rotate_right(rotation_a) ^ val.rotate_right(rotation_b) ^ (val >> shift)
 	}
@@ -654,3 +840,34 @@ fn test_empty_inputs_structs() {
 		"cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
 	);
 }
+
+// the length has to fit in the final block after the 0x80 byte, which is the
+// boundary case for 55 and 111 byte messages
+#[test]
+fn test_structs_match_fns() {
+	let message = [0x61; 300];
+
+	for len in 0 .. message.len() {
+		let (first, second) = message[.. len].split_at(len / 3);
+
+		let mut h = Sha224::new();
+		h.add_bytes(first);
+		h.add_bytes(second);
+		assert_eq!(h.out(), sha224(&message[.. len]));
+
+		let mut h = Sha256::new();
+		h.add_bytes(first);
+		h.add_bytes(second);
+		assert_eq!(h.out(), sha256(&message[.. len]));
+
+		let mut h = Sha384::new();
+		h.add_bytes(first);
+		h.add_bytes(second);
+		assert_eq!(h.out(), sha384(&message[.. len]));
+
+		let mut h = Sha512::new();
+		h.add_bytes(first);
+		h.add_bytes(second);
+		assert_eq!(h.out(), sha512(&message[.. len]));
+	}
+}
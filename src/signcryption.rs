@@ -0,0 +1,235 @@
+//! Signcryption: messages that are both signed by an Ed25519 sender and
+//! encrypted to an X25519 recipient, so the recipient knows who wrote the
+//! message and nobody else can read it.
+//!
+//! The message is signed, and the signature is encrypted along with it with
+//! ChaCha20-Poly1305, under a key from [`X25519HkdfSha256`] encapsulation to
+//! the recipient. The signature covers the sender's and recipient's public
+//! keys and the encapsulated key, not just the message, so a recipient can't
+//! forward a message to someone else as if it had been sent to them, and the
+//! encryption key is bound to the sender, so an attacker can't strip the
+//! signature and sign the message as themselves.
+//!
+//! The wire format is the 32-byte encapsulated key, followed by the
+//! encrypted signature and message, followed by the 16-byte tag, for a total
+//! of [`OVERHEAD`] bytes more than the message. The key and nonce are the 44
+//! bytes of HKDF-SHA256 output with the encapsulated secret as the input
+//! keying material, the sender's public key as the salt, and
+//! `libkrypton/signcryption/v1` as the info, and the signature is over that
+//! same string, followed by the sender's public key, the recipient's public
+//! key, the encapsulated key and the message.
+//!
+//! ```
+//! use libkrypton::ed25519::SigningKey;
+//! use libkrypton::signcryption::{open, seal};
+//! use libkrypton::x25519::{PublicKey, StaticSecret};
+//!
+//! // keys and randomness should be generated by a secure random source
+//! let sender = SigningKey::from_bytes([0x42; 32]);
+//! let recipient = StaticSecret::from_bytes([0x43; 32]);
+//!
+//! let sealed = seal(&sender, &PublicKey::from(&recipient), b"attack at dawn", [0x44; 32]).unwrap();
+//! let message = open(&recipient, &sender.verifying_key(), &sealed).unwrap();
+//!
+//! assert_eq!(message, b"attack at dawn");
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::chacha20poly1305::{chacha20poly1305_decrypt, chacha20poly1305_encrypt};
+use crate::ed25519::{Signature, SigningKey, VerifyingKey};
+use crate::hkdf::hkdf_sha256;
+use crate::kem::{Kem, SharedSecret, X25519HkdfSha256};
+use crate::x25519::{PublicKey, StaticSecret};
+use crate::zeroize::zeroize;
+
+/// The number of bytes a sealed message is longer than its plaintext.
+pub const OVERHEAD: usize = 32 + 64 + 16;
+
+const INFO: &[u8] = b"libkrypton/signcryption/v1";
+
+/// An error returned by [`seal`] or [`open`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The recipient's public key is a small-order point.
+	InvalidPublicKey,
+	/// The sealed message is too short, was modified, was sealed to a
+	/// different recipient, or wasn't signed by the expected sender.
+	OpenFailed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPublicKey => "invalid signcryption public key",
+			Error::OpenFailed => "failed to open signcrypted message",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+fn derive_key(shared_secret: &SharedSecret, sender: &VerifyingKey) -> ([u8; 32], [u8; 12]) {
+	let mut okm = [0; 44];
+	hkdf_sha256(sender.as_bytes(), shared_secret.as_bytes(), INFO, &mut okm);
+
+	let key = okm[0 .. 32].try_into().unwrap();
+	let nonce = okm[32 .. 44].try_into().unwrap();
+	zeroize(&mut okm);
+
+	(key, nonce)
+}
+
+fn signed_data(sender: &VerifyingKey, recipient: &PublicKey, encapsulated_key: &PublicKey, message: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(INFO.len() + 96 + message.len());
+	out.extend_from_slice(INFO);
+	out.extend_from_slice(sender.as_bytes());
+	out.extend_from_slice(recipient.as_bytes());
+	out.extend_from_slice(encapsulated_key.as_bytes());
+	out.extend_from_slice(message);
+	out
+}
+
+/// Signs `message` with `sender` and encrypts it so that only the holder of
+/// the private key for `recipient` can read it. `randomness` must be freshly
+/// generated by a secure random source for every message.
+pub fn seal(sender: &SigningKey, recipient: &PublicKey, message: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+	let (shared_secret, encapsulated_key) = X25519HkdfSha256::encapsulate(recipient, randomness)
+		.map_err(|_| Error::InvalidPublicKey)?;
+
+	let sender_pub_key = sender.verifying_key();
+	let signature = sender.sign(&signed_data(&sender_pub_key, recipient, &encapsulated_key, message));
+
+	let (mut key, nonce) = derive_key(&shared_secret, &sender_pub_key);
+
+	let mut out = Vec::with_capacity(message.len() + OVERHEAD);
+	out.extend_from_slice(encapsulated_key.as_bytes());
+	out.extend_from_slice(signature.as_bytes());
+	out.extend_from_slice(message);
+
+	let tag = chacha20poly1305_encrypt(key, nonce, encapsulated_key.as_bytes(), &mut out[32 ..]);
+	out.extend_from_slice(&tag);
+
+	zeroize(&mut key);
+
+	Ok(out)
+}
+
+/// Seals `message` like [`seal`], with randomness generated by `rng`, which
+/// must be a cryptographically secure random number generator.
+#[cfg(feature = "rand_core")]
+pub fn seal_with_rng<R: RngCore + CryptoRng>(
+	sender: &SigningKey,
+	recipient: &PublicKey,
+	message: &[u8],
+	rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+	let mut randomness = [0; 32];
+	rng.fill_bytes(&mut randomness);
+
+	let result = seal(sender, recipient, message, randomness);
+	zeroize(&mut randomness);
+
+	result
+}
+
+/// Decrypts a message that was sealed with [`seal`] for the public key of
+/// `recipient`, and checks that it was signed by `sender`.
+pub fn open(recipient: &StaticSecret, sender: &VerifyingKey, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+	if sealed.len() < OVERHEAD {
+		return Err(Error::OpenFailed);
+	}
+
+	let encapsulated_key = PublicKey::from_bytes(sealed[0 .. 32].try_into().unwrap());
+	let tag = sealed[sealed.len() - 16 ..].try_into().unwrap();
+
+	let shared_secret = X25519HkdfSha256::decapsulate(recipient, &encapsulated_key)
+		.map_err(|_| Error::OpenFailed)?;
+
+	let (mut key, nonce) = derive_key(&shared_secret, sender);
+
+	let mut plaintext = sealed[32 .. sealed.len() - 16].to_vec();
+	let result = chacha20poly1305_decrypt(key, nonce, encapsulated_key.as_bytes(), &mut plaintext, tag);
+
+	zeroize(&mut key);
+	result.map_err(|_| Error::OpenFailed)?;
+
+	let message = plaintext.split_off(64);
+	let signature = Signature::from_bytes(plaintext[..].try_into().unwrap()).map_err(|_| Error::OpenFailed)?;
+	let signed_data = signed_data(sender, &PublicKey::from(recipient), &encapsulated_key, &message);
+
+	if !sender.verify(&signed_data, &signature) {
+		return Err(Error::OpenFailed);
+	}
+
+	Ok(message)
+}
+
+#[test]
+fn test_round_trip() {
+	let sender = SigningKey::from_bytes([0x42; 32]);
+	let recipient = StaticSecret::from_bytes([0x43; 32]);
+	let recipient_pub_key = PublicKey::from(&recipient);
+
+	let sealed = seal(&sender, &recipient_pub_key, b"attack at dawn", [0x11; 32]).unwrap();
+	assert_eq!(sealed.len(), 14 + OVERHEAD);
+	assert_eq!(open(&recipient, &sender.verifying_key(), &sealed).unwrap(), b"attack at dawn");
+
+	let empty = seal(&sender, &recipient_pub_key, b"", [0x12; 32]).unwrap();
+	assert_eq!(open(&recipient, &sender.verifying_key(), &empty).unwrap(), b"");
+
+	let other_sender = SigningKey::from_bytes([0x44; 32]);
+	assert_eq!(open(&recipient, &other_sender.verifying_key(), &sealed), Err(Error::OpenFailed));
+
+	let other_recipient = StaticSecret::from_bytes([0x45; 32]);
+	assert_eq!(open(&other_recipient, &sender.verifying_key(), &sealed), Err(Error::OpenFailed));
+
+	for i in [0, 32, 100, sealed.len() - 1] {
+		let mut modified = sealed.clone();
+		modified[i] ^= 1;
+		assert_eq!(open(&recipient, &sender.verifying_key(), &modified), Err(Error::OpenFailed));
+	}
+
+	assert_eq!(open(&recipient, &sender.verifying_key(), &sealed[.. OVERHEAD - 1]), Err(Error::OpenFailed));
+	assert_eq!(seal(&sender, &PublicKey::from_bytes([0; 32]), b"", [0x11; 32]), Err(Error::InvalidPublicKey));
+}
+
+// a recipient who knows the sender's message and signature can't forward it
+// to someone else, since the signature covers the original recipient
+#[test]
+fn test_no_forwarding() {
+	let sender = SigningKey::from_bytes([0x42; 32]);
+	let recipient = StaticSecret::from_bytes([0x43; 32]);
+	let third_party = StaticSecret::from_bytes([0x45; 32]);
+
+	let sealed = seal(&sender, &PublicKey::from(&recipient), b"message", [0x11; 32]).unwrap();
+
+	// decrypt the envelope by hand to recover the signature
+	let encapsulated_key = PublicKey::from_bytes(sealed[0 .. 32].try_into().unwrap());
+	let shared_secret = X25519HkdfSha256::decapsulate(&recipient, &encapsulated_key).unwrap();
+	let (key, nonce) = derive_key(&shared_secret, &sender.verifying_key());
+
+	let mut plaintext = sealed[32 .. sealed.len() - 16].to_vec();
+	let tag = sealed[sealed.len() - 16 ..].try_into().unwrap();
+	chacha20poly1305_decrypt(key, nonce, encapsulated_key.as_bytes(), &mut plaintext, tag).unwrap();
+
+	// and re-encrypt it to the third party
+	let (shared_secret, encapsulated_key) = X25519HkdfSha256::encapsulate(&PublicKey::from(&third_party), [0x12; 32]).unwrap();
+	let (key, nonce) = derive_key(&shared_secret, &sender.verifying_key());
+
+	let mut forwarded = encapsulated_key.as_bytes().to_vec();
+	forwarded.extend_from_slice(&plaintext);
+
+	let tag = chacha20poly1305_encrypt(key, nonce, encapsulated_key.as_bytes(), &mut forwarded[32 ..]);
+	forwarded.extend_from_slice(&tag);
+
+	assert_eq!(open(&third_party, &sender.verifying_key(), &forwarded), Err(Error::OpenFailed));
+}
@@ -0,0 +1,945 @@
+//! SLH-DSA, the stateless hash-based signature scheme standardized in
+//! [FIPS 205](https://doi.org/10.6028/NIST.FIPS.205), with the SHAKE
+//! parameter sets. Its security rests only on that of SHAKE256, which makes
+//! it the conservative choice among post-quantum signatures, at the cost of
+//! large signatures and slow signing.
+//!
+//! The "s" parameter sets have smaller signatures, and the "f" ones sign
+//! faster. Signing is deterministic by default, and
+//! [`SigningKey::sign_randomized`] mixes in fresh randomness as well, which
+//! FIPS 205 recommends to protect against side-channel attacks. Signatures
+//! may be bound to a context string of up to 255 bytes, like with
+//! [`ml_dsa`](crate::ml_dsa).
+//!
+//! ```
+//! use libkrypton::slh_dsa::{ParameterSet, SigningKey};
+//!
+//! // the seed should be generated by a secure random source
+//! let signing_key = SigningKey::from_seed(ParameterSet::Shake128f, &[0x42; 48]).unwrap();
+//! let signature = signing_key.sign(b"message");
+//!
+//! assert!(signing_key.verifying_key().verify(b"message", &signature));
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha3::Shake256;
+use crate::zeroize::zeroize;
+
+// the largest n of any parameter set
+const MAX_N: usize = 32;
+
+// the largest number of WOTS+ chains, 2 * MAX_N + 3
+const MAX_WOTS_LEN: usize = 67;
+
+// the Winternitz parameter, which is 16 for every parameter set
+const LG_W: usize = 4;
+const W: u32 = 1 << LG_W;
+
+// the address types of section 4.2 of FIPS 205
+const WOTS_HASH: u32 = 0;
+const WOTS_PK: u32 = 1;
+const TREE: u32 = 2;
+const FORS_TREE: u32 = 3;
+const FORS_ROOTS: u32 = 4;
+const WOTS_PRF: u32 = 5;
+const FORS_PRF: u32 = 6;
+
+// a hash output, of which only the first n bytes are used
+type Node = [u8; MAX_N];
+
+/// An error returned when creating keys or signing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// A seed or key has the wrong length for the parameter set.
+	InvalidLength,
+	/// The context string is longer than 255 bytes.
+	ContextTooLong,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidLength => "invalid slh-dsa key length",
+			Error::ContextTooLong => "slh-dsa context string too long",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The SHAKE parameter sets of SLH-DSA.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParameterSet {
+	/// SLH-DSA-SHAKE-128s, with 7856-byte signatures.
+	Shake128s,
+	/// SLH-DSA-SHAKE-128f, with 17088-byte signatures.
+	Shake128f,
+	/// SLH-DSA-SHAKE-192s, with 16224-byte signatures.
+	Shake192s,
+	/// SLH-DSA-SHAKE-192f, with 35664-byte signatures.
+	Shake192f,
+	/// SLH-DSA-SHAKE-256s, with 29792-byte signatures.
+	Shake256s,
+	/// SLH-DSA-SHAKE-256f, with 49856-byte signatures.
+	Shake256f,
+}
+
+// the parameters from table 2 of FIPS 205, with h' being the height of each
+// tree in the hypertree
+#[derive(Copy, Clone)]
+struct Params {
+	n: usize,
+	h: usize,
+	d: usize,
+	h_prime: usize,
+	a: usize,
+	k: usize,
+	m: usize,
+}
+
+impl ParameterSet {
+	fn params(self) -> Params {
+		let (n, h, d, h_prime, a, k, m) = match self {
+			ParameterSet::Shake128s => (16, 63, 7, 9, 12, 14, 30),
+			ParameterSet::Shake128f => (16, 66, 22, 3, 6, 33, 34),
+			ParameterSet::Shake192s => (24, 63, 7, 9, 14, 17, 39),
+			ParameterSet::Shake192f => (24, 66, 22, 3, 8, 33, 42),
+			ParameterSet::Shake256s => (32, 64, 8, 8, 14, 22, 47),
+			ParameterSet::Shake256f => (32, 68, 17, 4, 9, 35, 49),
+		};
+
+		Params {n, h, d, h_prime, a, k, m}
+	}
+
+	/// The length of a seed, which is three times the security parameter n.
+	pub fn seed_len(self) -> usize {
+		3 * self.params().n
+	}
+
+	/// The length of an encoded signing key.
+	pub fn signing_key_len(self) -> usize {
+		4 * self.params().n
+	}
+
+	/// The length of an encoded verifying key.
+	pub fn verifying_key_len(self) -> usize {
+		2 * self.params().n
+	}
+
+	/// The length of a signature.
+	pub fn signature_len(self) -> usize {
+		let p = self.params();
+		p.n * (1 + p.k * (1 + p.a) + p.h + p.d * wots_len(p.n))
+	}
+}
+
+fn wots_len(n: usize) -> usize {
+	// len1 is 2n digits of the message, and len2 is 3 digits of the checksum
+	2 * n + 3
+}
+
+// algorithm 4 of FIPS 205, splitting bytes into big-endian b-bit digits
+fn base_2b(bytes: &[u8], b: usize, out: &mut [u32]) {
+	let mut total = 0u64;
+	let mut bits = 0;
+	let mut pos = 0;
+
+	for x in out.iter_mut() {
+		while bits < b {
+			total = (total << 8) | bytes[pos] as u64;
+			pos += 1;
+			bits += 8;
+		}
+
+		bits -= b;
+		*x = ((total >> bits) & ((1 << b) - 1)) as u32;
+	}
+}
+
+// the 32-byte address of section 4.2 of FIPS 205, which separates the domains
+// of every hash call
+#[derive(Copy, Clone)]
+struct Address([u8; 32]);
+
+impl Address {
+	fn new() -> Self {
+		Self([0; 32])
+	}
+
+	fn set_layer(&mut self, layer: u32) {
+		self.0[.. 4].copy_from_slice(&layer.to_be_bytes());
+	}
+
+	fn set_tree(&mut self, tree: u64) {
+		self.0[4 .. 8].copy_from_slice(&[0; 4]);
+		self.0[8 .. 16].copy_from_slice(&tree.to_be_bytes());
+	}
+
+	fn set_type_and_clear(&mut self, kind: u32) {
+		self.0[16 .. 20].copy_from_slice(&kind.to_be_bytes());
+		self.0[20 ..].copy_from_slice(&[0; 12]);
+	}
+
+	fn set_key_pair(&mut self, key_pair: u32) {
+		self.0[20 .. 24].copy_from_slice(&key_pair.to_be_bytes());
+	}
+
+	fn key_pair(&self) -> u32 {
+		u32::from_be_bytes(self.0[20 .. 24].try_into().unwrap())
+	}
+
+	// the chain address and the tree height share a word
+	fn set_chain(&mut self, chain: u32) {
+		self.0[24 .. 28].copy_from_slice(&chain.to_be_bytes());
+	}
+
+	fn set_tree_height(&mut self, height: u32) {
+		self.set_chain(height);
+	}
+
+	// the hash address and the tree index share a word
+	fn set_hash(&mut self, hash: u32) {
+		self.0[28 ..].copy_from_slice(&hash.to_be_bytes());
+	}
+
+	fn set_tree_index(&mut self, index: u32) {
+		self.set_hash(index);
+	}
+
+	fn tree_index(&self) -> u32 {
+		u32::from_be_bytes(self.0[28 ..].try_into().unwrap())
+	}
+}
+
+// the hash functions of section 11.1 of FIPS 205, and the algorithms built on
+// them, for a given key
+struct Context<'a> {
+	p: Params,
+	pk_seed: &'a [u8],
+	// empty when verifying
+	sk_seed: &'a [u8],
+}
+
+impl Context<'_> {
+	// F, H and T_l, which hash PK.seed || ADRS || M
+	fn hasher(&self, address: &Address) -> Shake256 {
+		let mut hasher = Shake256::new();
+		hasher.add_bytes(self.pk_seed);
+		hasher.add_bytes(&address.0);
+		hasher
+	}
+
+	fn finish(&self, hasher: Shake256) -> Node {
+		let mut out = [0; MAX_N];
+		hasher.out_into(&mut out[.. self.p.n]);
+		out
+	}
+
+	fn hash(&self, address: &Address, parts: &[&[u8]]) -> Node {
+		let mut hasher = self.hasher(address);
+
+		for part in parts {
+			hasher.add_bytes(part);
+		}
+
+		self.finish(hasher)
+	}
+
+	fn prf(&self, address: &Address) -> Node {
+		self.hash(address, &[self.sk_seed])
+	}
+
+	// algorithm 5 of FIPS 205
+	fn chain(&self, x: &Node, start: u32, steps: u32, address: &mut Address) -> Node {
+		let mut out = *x;
+
+		for j in start .. start + steps {
+			address.set_hash(j);
+			out = self.hash(address, &[&out[.. self.p.n]]);
+		}
+
+		out
+	}
+
+	// the base-w digits of a message and its checksum, from algorithms 7 and
+	// 8 of FIPS 205
+	fn wots_digits(&self, message: &Node) -> [u32; MAX_WOTS_LEN] {
+		let len1 = 2 * self.p.n;
+
+		let mut digits = [0; MAX_WOTS_LEN];
+		base_2b(&message[.. self.p.n], LG_W, &mut digits[.. len1]);
+
+		let checksum: u32 = digits[.. len1].iter().map(|x| W - 1 - x).sum();
+
+		// the 12-bit checksum is shifted to fill two bytes
+		base_2b(&((checksum << 4) as u16).to_be_bytes(), LG_W, &mut digits[len1 .. len1 + 3]);
+
+		digits
+	}
+
+	fn wots_secret(&self, address: &Address, chain: u32) -> Node {
+		let mut sk_address = *address;
+		sk_address.set_type_and_clear(WOTS_PRF);
+		sk_address.set_key_pair(address.key_pair());
+		sk_address.set_chain(chain);
+
+		self.prf(&sk_address)
+	}
+
+	// compresses the ends of the chains into a WOTS+ public key
+	fn wots_compress(&self, address: &Address, ends: &[Node]) -> Node {
+		let mut pk_address = *address;
+		pk_address.set_type_and_clear(WOTS_PK);
+		pk_address.set_key_pair(address.key_pair());
+
+		let mut hasher = self.hasher(&pk_address);
+
+		for end in ends {
+			hasher.add_bytes(&end[.. self.p.n]);
+		}
+
+		self.finish(hasher)
+	}
+
+	// algorithm 6 of FIPS 205
+	fn wots_pk_gen(&self, address: &mut Address) -> Node {
+		let len = wots_len(self.p.n);
+		let mut ends = [[0; MAX_N]; MAX_WOTS_LEN];
+
+		for i in 0 .. len {
+			let mut secret = self.wots_secret(address, i as u32);
+			address.set_chain(i as u32);
+			ends[i] = self.chain(&secret, 0, W - 1, address);
+			zeroize(&mut secret);
+		}
+
+		self.wots_compress(address, &ends[.. len])
+	}
+
+	// algorithm 7 of FIPS 205
+	fn wots_sign(&self, message: &Node, address: &mut Address, out: &mut Vec<u8>) {
+		let digits = self.wots_digits(message);
+
+		for i in 0 .. wots_len(self.p.n) {
+			let mut secret = self.wots_secret(address, i as u32);
+			address.set_chain(i as u32);
+			out.extend_from_slice(&self.chain(&secret, 0, digits[i], address)[.. self.p.n]);
+			zeroize(&mut secret);
+		}
+	}
+
+	// algorithm 8 of FIPS 205
+	fn wots_pk_from_sig(&self, signature: &[u8], message: &Node, address: &mut Address) -> Node {
+		let len = wots_len(self.p.n);
+		let digits = self.wots_digits(message);
+		let mut ends = [[0; MAX_N]; MAX_WOTS_LEN];
+
+		for i in 0 .. len {
+			let mut start = [0; MAX_N];
+			start[.. self.p.n].copy_from_slice(&signature[self.p.n * i ..][.. self.p.n]);
+
+			address.set_chain(i as u32);
+			ends[i] = self.chain(&start, digits[i], W - 1 - digits[i], address);
+		}
+
+		self.wots_compress(address, &ends[.. len])
+	}
+
+	// algorithm 9 of FIPS 205, computing the node at index i and height z of
+	// an XMSS tree
+	fn xmss_node(&self, i: u32, z: usize, address: &mut Address) -> Node {
+		if z == 0 {
+			address.set_type_and_clear(WOTS_HASH);
+			address.set_key_pair(i);
+			return self.wots_pk_gen(address);
+		}
+
+		let left = self.xmss_node(2 * i, z - 1, address);
+		let right = self.xmss_node(2 * i + 1, z - 1, address);
+
+		address.set_type_and_clear(TREE);
+		address.set_tree_height(z as u32);
+		address.set_tree_index(i);
+
+		self.hash(address, &[&left[.. self.p.n], &right[.. self.p.n]])
+	}
+
+	// algorithm 10 of FIPS 205
+	fn xmss_sign(&self, message: &Node, index: u32, address: &mut Address, out: &mut Vec<u8>) {
+		let mut auth_path = Vec::with_capacity(self.p.n * self.p.h_prime);
+
+		for j in 0 .. self.p.h_prime {
+			let sibling = (index >> j) ^ 1;
+			auth_path.extend_from_slice(&self.xmss_node(sibling, j, address)[.. self.p.n]);
+		}
+
+		address.set_type_and_clear(WOTS_HASH);
+		address.set_key_pair(index);
+		self.wots_sign(message, address, out);
+
+		out.extend_from_slice(&auth_path);
+	}
+
+	// hashes a leaf up to the root of a tree along an authentication path, as
+	// in algorithms 11 and 17 of FIPS 205, with the address already set to the
+	// leaf's index
+	fn climb(&self, leaf: Node, index: u32, auth_path: &[u8], address: &mut Address) -> Node {
+		let n = self.p.n;
+		let mut node = leaf;
+
+		for (j, sibling) in auth_path.chunks(n).enumerate() {
+			address.set_tree_height(j as u32 + 1);
+			address.set_tree_index(address.tree_index() / 2);
+
+			node = match (index >> j) & 1 {
+				0 => self.hash(address, &[&node[.. n], sibling]),
+				_ => self.hash(address, &[sibling, &node[.. n]]),
+			};
+		}
+
+		node
+	}
+
+	// algorithm 11 of FIPS 205
+	fn xmss_pk_from_sig(&self, index: u32, signature: &[u8], message: &Node, address: &mut Address) -> Node {
+		let wots_bytes = self.p.n * wots_len(self.p.n);
+
+		address.set_type_and_clear(WOTS_HASH);
+		address.set_key_pair(index);
+		let leaf = self.wots_pk_from_sig(&signature[.. wots_bytes], message, address);
+
+		address.set_type_and_clear(TREE);
+		address.set_tree_index(index);
+
+		self.climb(leaf, index, &signature[wots_bytes ..], address)
+	}
+
+	fn xmss_sig_len(&self) -> usize {
+		self.p.n * (wots_len(self.p.n) + self.p.h_prime)
+	}
+
+	// algorithm 12 of FIPS 205
+	fn ht_sign(&self, message: &Node, mut tree: u64, mut leaf: u32, out: &mut Vec<u8>) {
+		let mut address = Address::new();
+		let mut root = *message;
+
+		for j in 0 .. self.p.d {
+			address.set_layer(j as u32);
+			address.set_tree(tree);
+
+			let start = out.len();
+			self.xmss_sign(&root, leaf, &mut address, out);
+
+			if j + 1 < self.p.d {
+				root = self.xmss_pk_from_sig(leaf, &out[start ..], &root, &mut address);
+			}
+
+			leaf = (tree & ((1 << self.p.h_prime) - 1)) as u32;
+			tree >>= self.p.h_prime;
+		}
+	}
+
+	// algorithm 13 of FIPS 205, returning the root of the hypertree
+	fn ht_root_from_sig(&self, message: &Node, signature: &[u8], mut tree: u64, mut leaf: u32) -> Node {
+		let mut address = Address::new();
+		let mut node = *message;
+
+		for (j, xmss_signature) in signature.chunks(self.xmss_sig_len()).enumerate() {
+			address.set_layer(j as u32);
+			address.set_tree(tree);
+			node = self.xmss_pk_from_sig(leaf, xmss_signature, &node, &mut address);
+
+			leaf = (tree & ((1 << self.p.h_prime) - 1)) as u32;
+			tree >>= self.p.h_prime;
+		}
+
+		node
+	}
+
+	// algorithm 14 of FIPS 205
+	fn fors_secret(&self, address: &Address, index: u32) -> Node {
+		let mut sk_address = *address;
+		sk_address.set_type_and_clear(FORS_PRF);
+		sk_address.set_key_pair(address.key_pair());
+		sk_address.set_tree_index(index);
+
+		self.prf(&sk_address)
+	}
+
+	// algorithm 15 of FIPS 205
+	fn fors_node(&self, i: u32, z: usize, address: &mut Address) -> Node {
+		if z == 0 {
+			let mut secret = self.fors_secret(address, i);
+			address.set_tree_height(0);
+			address.set_tree_index(i);
+
+			let node = self.hash(address, &[&secret[.. self.p.n]]);
+			zeroize(&mut secret);
+
+			return node;
+		}
+
+		let left = self.fors_node(2 * i, z - 1, address);
+		let right = self.fors_node(2 * i + 1, z - 1, address);
+
+		address.set_tree_height(z as u32);
+		address.set_tree_index(i);
+
+		self.hash(address, &[&left[.. self.p.n], &right[.. self.p.n]])
+	}
+
+	// algorithm 16 of FIPS 205
+	fn fors_sign(&self, digest: &[u8], address: &mut Address, out: &mut Vec<u8>) {
+		let (a, k) = (self.p.a, self.p.k);
+
+		let mut indices = [0; 35];
+		base_2b(digest, a, &mut indices[.. k]);
+
+		for i in 0 .. k {
+			let offset = (i << a) as u32;
+
+			let mut secret = self.fors_secret(address, offset + indices[i]);
+			out.extend_from_slice(&secret[.. self.p.n]);
+			zeroize(&mut secret);
+
+			for j in 0 .. a {
+				let sibling = (indices[i] >> j) ^ 1;
+				let node = self.fors_node(((i << (a - j)) as u32) + sibling, j, address);
+				out.extend_from_slice(&node[.. self.p.n]);
+			}
+		}
+	}
+
+	// algorithm 17 of FIPS 205
+	fn fors_pk_from_sig(&self, signature: &[u8], digest: &[u8], address: &mut Address) -> Node {
+		let (n, a, k) = (self.p.n, self.p.a, self.p.k);
+
+		let mut indices = [0; 35];
+		base_2b(digest, a, &mut indices[.. k]);
+
+		let mut roots = [[0; MAX_N]; 35];
+
+		for (i, tree) in signature.chunks(n * (1 + a)).enumerate() {
+			let index = ((i << a) as u32) + indices[i];
+
+			address.set_tree_height(0);
+			address.set_tree_index(index);
+			let leaf = self.hash(address, &[&tree[.. n]]);
+
+			roots[i] = self.climb(leaf, indices[i], &tree[n ..], address);
+		}
+
+		let mut roots_address = *address;
+		roots_address.set_type_and_clear(FORS_ROOTS);
+		roots_address.set_key_pair(address.key_pair());
+
+		let mut hasher = self.hasher(&roots_address);
+
+		for root in &roots[.. k] {
+			hasher.add_bytes(&root[.. n]);
+		}
+
+		self.finish(hasher)
+	}
+
+	fn fors_sig_len(&self) -> usize {
+		self.p.n * self.p.k * (1 + self.p.a)
+	}
+
+	// splits H_msg into the FORS message digest and the indices of the tree
+	// and leaf, as in algorithms 19 and 20 of FIPS 205
+	fn digest(&self, randomizer: &[u8], pk_root: &[u8], message: &[&[u8]]) -> ([u8; 49], u64, u32) {
+		let Params {h, d, a, k, m, ..} = self.p;
+
+		let mut hasher = Shake256::new();
+		hasher.add_bytes(randomizer);
+		hasher.add_bytes(self.pk_seed);
+		hasher.add_bytes(pk_root);
+
+		for part in message {
+			hasher.add_bytes(part);
+		}
+
+		let mut digest = [0; 49];
+		hasher.out_into(&mut digest[.. m]);
+
+		let digest_len = (k * a).div_ceil(8);
+		let tree_bits = h - h / d;
+		let leaf_bits = h / d;
+
+		let tree_bytes = &digest[digest_len ..][.. tree_bits.div_ceil(8)];
+		let leaf_bytes = &digest[digest_len + tree_bits.div_ceil(8) ..][.. leaf_bits.div_ceil(8)];
+
+		let tree = tree_bytes.iter().fold(0u64, |acc, &x| (acc << 8) | x as u64);
+		let leaf = leaf_bytes.iter().fold(0u32, |acc, &x| (acc << 8) | x as u32);
+
+		// the tree index can have all 64 bits
+		let tree = tree & u64::MAX >> (64 - tree_bits);
+		let leaf = leaf & ((1 << leaf_bits) - 1);
+
+		let mut fors_digest = [0; 49];
+		fors_digest[.. digest_len].copy_from_slice(&digest[.. digest_len]);
+
+		(fors_digest, tree, leaf)
+	}
+}
+
+// the prefix of M' for pure SLH-DSA, from algorithm 22 of FIPS 205
+fn context_prefix(context: &[u8]) -> [u8; 2] {
+	[0, context.len() as u8]
+}
+
+/// An SLH-DSA signing key. Zeroized when dropped.
+#[derive(Clone)]
+pub struct SigningKey {
+	parameter_set: ParameterSet,
+	// SK.seed || SK.prf || PK.seed || PK.root
+	bytes: [u8; 4 * MAX_N],
+}
+
+impl SigningKey {
+	/// Derives a signing key from a seed of [`ParameterSet::seed_len`] bytes,
+	/// which is the concatenation of SK.seed, SK.prf and PK.seed from FIPS
+	/// 205. A random seed generates a new key.
+	pub fn from_seed(parameter_set: ParameterSet, seed: &[u8]) -> Result<Self, Error> {
+		let p = parameter_set.params();
+
+		if seed.len() != 3 * p.n {
+			return Err(Error::InvalidLength);
+		}
+
+		// algorithm 18 of FIPS 205
+		let context = Context {p, pk_seed: &seed[2 * p.n ..], sk_seed: &seed[.. p.n]};
+
+		let mut address = Address::new();
+		address.set_layer(p.d as u32 - 1);
+		let root = context.xmss_node(0, p.h_prime, &mut address);
+
+		let mut bytes = [0; 4 * MAX_N];
+		bytes[.. 3 * p.n].copy_from_slice(seed);
+		bytes[3 * p.n .. 4 * p.n].copy_from_slice(&root[.. p.n]);
+
+		Ok(Self {parameter_set, bytes})
+	}
+
+	/// Generates a new signing key using a cryptographically secure random
+	/// number generator, such as `rand::rngs::OsRng`.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(parameter_set: ParameterSet, rng: &mut R) -> Self {
+		let mut seed = [0; 3 * MAX_N];
+		let seed = &mut seed[.. parameter_set.seed_len()];
+		rng.fill_bytes(seed);
+
+		let signing_key = Self::from_seed(parameter_set, seed).unwrap();
+		zeroize(seed);
+
+		signing_key
+	}
+
+	/// Parses an encoded signing key of [`ParameterSet::signing_key_len`]
+	/// bytes. The public root inside it isn't checked, since recomputing it
+	/// takes as long as generating a key.
+	pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() != parameter_set.signing_key_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut out = [0; 4 * MAX_N];
+		out[.. bytes.len()].copy_from_slice(bytes);
+
+		Ok(Self {parameter_set, bytes: out})
+	}
+
+	/// Returns the encoding of this signing key. This must be kept secret.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[.. self.parameter_set.signing_key_len()]
+	}
+
+	/// Returns the parameter set of this key.
+	pub fn parameter_set(&self) -> ParameterSet {
+		self.parameter_set
+	}
+
+	/// Returns the public key corresponding to this signing key.
+	pub fn verifying_key(&self) -> VerifyingKey {
+		let n = self.parameter_set.params().n;
+
+		let mut bytes = [0; 2 * MAX_N];
+		bytes[.. 2 * n].copy_from_slice(&self.bytes[2 * n .. 4 * n]);
+
+		VerifyingKey {parameter_set: self.parameter_set, bytes}
+	}
+
+	/// Deterministically signs a `message` with an empty context.
+	pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+		self.sign_with_context(message, b"").unwrap()
+	}
+
+	/// Deterministically signs a `message`, binding the signature to
+	/// `context`, which must be at most 255 bytes long.
+	pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Result<Vec<u8>, Error> {
+		let n = self.parameter_set.params().n;
+		self.sign_internal(&[&context_prefix(context), context, message], context.len(), &self.bytes[2 * n .. 3 * n])
+	}
+
+	/// Signs a `message` bound to `context`, like
+	/// [`SigningKey::sign_with_context`], but mixes the first n bytes of
+	/// `randomness` into the signature. `randomness` should be freshly
+	/// generated by a secure random source for every call.
+	pub fn sign_randomized(&self, message: &[u8], context: &[u8], randomness: [u8; 32]) -> Result<Vec<u8>, Error> {
+		let n = self.parameter_set.params().n;
+		self.sign_internal(&[&context_prefix(context), context, message], context.len(), &randomness[.. n])
+	}
+
+	/// Like [`SigningKey::sign_randomized`], but generates the randomness
+	/// with `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn sign_with_rng<R: RngCore + CryptoRng>(
+		&self,
+		message: &[u8],
+		context: &[u8],
+		rng: &mut R,
+	) -> Result<Vec<u8>, Error> {
+		let mut randomness = [0; 32];
+		rng.fill_bytes(&mut randomness);
+
+		let result = self.sign_randomized(message, context, randomness);
+		zeroize(&mut randomness);
+
+		result
+	}
+
+	// slh_sign_internal, algorithm 19 of FIPS 205
+	fn sign_internal(&self, message: &[&[u8]], context_len: usize, randomness: &[u8]) -> Result<Vec<u8>, Error> {
+		if context_len > 255 {
+			return Err(Error::ContextTooLong);
+		}
+
+		let p = self.parameter_set.params();
+		let n = p.n;
+
+		let sk_prf = &self.bytes[n .. 2 * n];
+		let pk_root = &self.bytes[3 * n .. 4 * n];
+		let context = Context {p, pk_seed: &self.bytes[2 * n .. 3 * n], sk_seed: &self.bytes[.. n]};
+
+		let mut out = Vec::with_capacity(self.parameter_set.signature_len());
+
+		let mut hasher = Shake256::new();
+		hasher.add_bytes(sk_prf);
+		hasher.add_bytes(randomness);
+
+		for part in message {
+			hasher.add_bytes(part);
+		}
+
+		let mut randomizer = [0; MAX_N];
+		hasher.out_into(&mut randomizer[.. n]);
+		out.extend_from_slice(&randomizer[.. n]);
+
+		let (digest, tree, leaf) = context.digest(&randomizer[.. n], pk_root, message);
+
+		let mut address = Address::new();
+		address.set_tree(tree);
+		address.set_type_and_clear(FORS_TREE);
+		address.set_key_pair(leaf);
+
+		let fors_start = out.len();
+		context.fors_sign(&digest, &mut address, &mut out);
+		let fors_pub_key = context.fors_pk_from_sig(&out[fors_start ..], &digest, &mut address);
+
+		context.ht_sign(&fors_pub_key, tree, leaf, &mut out);
+
+		Ok(out)
+	}
+}
+
+impl Drop for SigningKey {
+	fn drop(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SigningKey {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
+// deliberately doesn't print the private key
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SigningKey").field("parameter_set", &self.parameter_set).finish_non_exhaustive()
+	}
+}
+
+/// An SLH-DSA verifying key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+	parameter_set: ParameterSet,
+	// PK.seed || PK.root
+	bytes: [u8; 2 * MAX_N],
+}
+
+impl VerifyingKey {
+	/// Parses an encoded verifying key of
+	/// [`ParameterSet::verifying_key_len`] bytes.
+	pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() != parameter_set.verifying_key_len() {
+			return Err(Error::InvalidLength);
+		}
+
+		let mut out = [0; 2 * MAX_N];
+		out[.. bytes.len()].copy_from_slice(bytes);
+
+		Ok(Self {parameter_set, bytes: out})
+	}
+
+	/// Returns the encoding of this key.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[.. self.parameter_set.verifying_key_len()]
+	}
+
+	/// Returns the parameter set of this key.
+	pub fn parameter_set(&self) -> ParameterSet {
+		self.parameter_set
+	}
+
+	/// Verifies a `signature` over a `message` with an empty context.
+	pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+		self.verify_with_context(message, b"", signature)
+	}
+
+	/// Verifies a `signature` over a `message` that was bound to `context`
+	/// when signing. Returns false if the context is longer than 255 bytes.
+	pub fn verify_with_context(&self, message: &[u8], context: &[u8], signature: &[u8]) -> bool {
+		if context.len() > 255 || signature.len() != self.parameter_set.signature_len() {
+			return false;
+		}
+
+		self.verify_internal(&[&context_prefix(context), context, message], signature)
+	}
+
+	// slh_verify_internal, algorithm 20 of FIPS 205
+	fn verify_internal(&self, message: &[&[u8]], signature: &[u8]) -> bool {
+		let p = self.parameter_set.params();
+		let n = p.n;
+
+		let pk_root = &self.bytes[n .. 2 * n];
+		let context = Context {p, pk_seed: &self.bytes[.. n], sk_seed: &[]};
+
+		let (randomizer, signature) = signature.split_at(n);
+		let (fors_signature, ht_signature) = signature.split_at(context.fors_sig_len());
+
+		let (digest, tree, leaf) = context.digest(randomizer, pk_root, message);
+
+		let mut address = Address::new();
+		address.set_tree(tree);
+		address.set_type_and_clear(FORS_TREE);
+		address.set_key_pair(leaf);
+
+		let fors_pub_key = context.fors_pk_from_sig(fors_signature, &digest, &mut address);
+		let root = context.ht_root_from_sig(&fors_pub_key, ht_signature, tree, leaf);
+
+		root[.. n] == *pk_root
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[cfg(test)]
+fn test_key(parameter_set: ParameterSet) -> SigningKey {
+	let seed: Vec<u8> = (0 .. parameter_set.seed_len() as u8).collect();
+	SigningKey::from_seed(parameter_set, &seed).unwrap()
+}
+
+// checked against OpenSSL 3.5's `openssl genpkey -algorithm SLH-DSA-SHAKE-128f
+// -pkeyopt hexseed:0001..`, and likewise for the other parameter sets
+#[test]
+fn test_key_gen() {
+	let roots = [
+		(ParameterSet::Shake128f, &hex::<16>("a90e4715b9a925c332801767fd786371")[..]),
+		(ParameterSet::Shake192f, &hex::<24>("3f01b06bebed020a459696868d115fe8507ded8dc08e825d")[..]),
+		(ParameterSet::Shake256f, &hex::<32>("818d7e76beef979b5bbf9161fdefa21bd0fe0bfe19157a5711a8de8a8f6878e6")[..]),
+	];
+
+	for (parameter_set, root) in roots {
+		let n = parameter_set.seed_len() / 3;
+		let verifying_key = test_key(parameter_set).verifying_key();
+
+		assert_eq!(verifying_key.as_bytes()[.. n], (2 * n as u8 .. 3 * n as u8).collect::<Vec<_>>());
+		assert_eq!(&verifying_key.as_bytes()[n ..], root);
+	}
+}
+
+// checked against `openssl pkeyutl -sign -rawin` from OpenSSL 3.5, with
+// `-pkeyopt deterministic:1`, then with `-pkeyopt context-string:ctx` and
+// `-pkeyopt hextest-entropy:` set to the first 16 bytes of the randomness
+#[test]
+fn test_sign() {
+	use crate::sha3::sha3_256;
+
+	let signing_key = test_key(ParameterSet::Shake128f);
+	let verifying_key = signing_key.verifying_key();
+
+	let signature = signing_key.sign(b"hello slh-dsa");
+	assert_eq!(signature.len(), 17088);
+	assert_eq!(sha3_256(&signature), hex("25f24f9ebe277eea7b5410bfbff7d1396e6e9299da48954570f2faea447d90c5"));
+	assert!(verifying_key.verify(b"hello slh-dsa", &signature));
+
+	let signature = signing_key.sign_randomized(b"hello slh-dsa", b"ctx", [0x5a; 32]).unwrap();
+	assert_eq!(sha3_256(&signature), hex("952ce485af27af5e015ca6808ce613dc333b876fb6301d9bdd62f459a5bb111e"));
+	assert!(verifying_key.verify_with_context(b"hello slh-dsa", b"ctx", &signature));
+	assert!(!verifying_key.verify(b"hello slh-dsa", &signature));
+	assert!(!verifying_key.verify_with_context(b"hello slh-dsb", b"ctx", &signature));
+
+	// tampering with the randomizer, the FORS signature and the hypertree
+	for i in [0, 100, 17000] {
+		let mut modified = signature.clone();
+		modified[i] ^= 1;
+		assert!(!verifying_key.verify_with_context(b"hello slh-dsa", b"ctx", &modified));
+	}
+
+	assert!(!verifying_key.verify_with_context(b"hello slh-dsa", b"ctx", &signature[1 ..]));
+	assert_eq!(signing_key.sign_with_context(b"", &[0; 256]).unwrap_err(), Error::ContextTooLong);
+}
+
+#[test]
+fn test_encoding() {
+	let signing_key = test_key(ParameterSet::Shake128f);
+	let parsed = SigningKey::from_bytes(ParameterSet::Shake128f, signing_key.as_bytes()).unwrap();
+	assert_eq!(parsed.verifying_key(), signing_key.verifying_key());
+
+	let verifying_key = signing_key.verifying_key();
+	assert_eq!(VerifyingKey::from_bytes(ParameterSet::Shake128f, verifying_key.as_bytes()).unwrap(), verifying_key);
+
+	let lengths = [
+		(ParameterSet::Shake128s, 7856),
+		(ParameterSet::Shake128f, 17088),
+		(ParameterSet::Shake192s, 16224),
+		(ParameterSet::Shake192f, 35664),
+		(ParameterSet::Shake256s, 29792),
+		(ParameterSet::Shake256f, 49856),
+	];
+
+	for (parameter_set, len) in lengths {
+		assert_eq!(parameter_set.signature_len(), len);
+	}
+
+	assert_eq!(SigningKey::from_seed(ParameterSet::Shake128s, &[0; 47]).unwrap_err(), Error::InvalidLength);
+	assert_eq!(VerifyingKey::from_bytes(ParameterSet::Shake256f, &[0; 32]).unwrap_err(), Error::InvalidLength);
+}
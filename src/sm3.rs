@@ -0,0 +1,213 @@
+//! Implemented according to GB/T 32905-2016, the Chinese national standard
+//! hash function, as described in
+//! [draft-sca-cfrg-sm3](https://datatracker.ietf.org/doc/html/draft-sca-cfrg-sm3).
+//! SM3 has a 256-bit output and the same structure as SHA-256, and is
+//! required by regulations in some markets, along with [`sm4`](crate::sm4).
+//! Elsewhere, SHA-256 is the more widely supported choice.
+//!
+//! ```
+//! use libkrypton::sm3::{sm3, Sm3};
+//!
+//! let mut hasher = Sm3::new();
+//! hasher.add_bytes(b"a");
+//! hasher.add_bytes(b"bc");
+//!
+//! assert_eq!(hasher.out(), sm3(b"abc"));
+//! ```
+
+use core::convert::TryInto;
+
+use crate::Error;
+
+const INITIAL_HASH_VALS: [u32; 8] = [
+	0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e,
+];
+
+fn p0(x: u32) -> u32 {
+	x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+fn p1(x: u32) -> u32 {
+	x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+fn sm3_block(hash_vals: &mut [u32; 8], chunk: &[u8; 64]) {
+	let mut w = [0; 68];
+
+	for i in 0 .. 16 {
+		w[i] = u32::from_be_bytes(chunk[4 * i .. 4 * (i + 1)].try_into().unwrap());
+	}
+
+	for i in 16 .. 68 {
+		w[i] = p1(w[i - 16] ^ w[i - 9] ^ w[i - 3].rotate_left(15)) ^ w[i - 13].rotate_left(7) ^ w[i - 6];
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *hash_vals;
+
+	for j in 0 .. 64 {
+		let (t, ff, gg) = match j {
+			0 ..= 15 => (0x79cc4519u32, a ^ b ^ c, e ^ f ^ g),
+			_ => (0x7a879d8a, (a & b) | (a & c) | (b & c), (e & f) | (!e & g)),
+		};
+
+		let ss1 = a
+			.rotate_left(12)
+			.wrapping_add(e)
+			.wrapping_add(t.rotate_left(j as u32 % 32))
+			.rotate_left(7);
+
+		let ss2 = ss1 ^ a.rotate_left(12);
+		let tt1 = ff.wrapping_add(d).wrapping_add(ss2).wrapping_add(w[j] ^ w[j + 4]);
+		let tt2 = gg.wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+
+		d = c;
+		c = b.rotate_left(9);
+		b = a;
+		a = tt1;
+		h = g;
+		g = f.rotate_left(19);
+		f = e;
+		e = p0(tt2);
+	}
+
+	for (hash_val, working_var) in hash_vals.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+		*hash_val ^= working_var;
+	}
+}
+
+/// Computes the SM3 hash of `msg`.
+///
+/// # Panics
+/// * Panics if the message is 2 ** 61 bytes or longer.
+pub fn sm3(msg: &[u8]) -> [u8; 32] {
+	try_sm3(msg).expect("message too long for sm3")
+}
+
+/// Like [`sm3`], but returns [`Error::MessageTooLong`] instead of panicking.
+pub fn try_sm3(msg: &[u8]) -> Result<[u8; 32], Error> {
+	let mut hasher = Sm3::new();
+	hasher.try_add_bytes(msg)?;
+	Ok(hasher.out())
+}
+
+/// Struct used for computing SM3 on data not stored contiguously in memory.
+#[derive(Clone)]
+pub struct Sm3 {
+	hash_vals: [u32; 8],
+	block_buffer: [u8; 64],
+	block_pos: usize,
+	num_bytes: u64,
+}
+
+impl Sm3 {
+	/// Creates a new SM3 instance.
+	pub fn new() -> Self {
+		Self {
+			hash_vals: INITIAL_HASH_VALS,
+			block_buffer: [0; 64],
+			block_pos: 0,
+			num_bytes: 0,
+		}
+	}
+
+	/// Adds bytes of the message.
+	///
+	/// # Panics
+	/// * Panics if 2 ** 61 or more bytes have been added in total.
+	pub fn add_bytes(&mut self, bytes: &[u8]) {
+		self.try_add_bytes(bytes).expect("message too long for sm3");
+	}
+
+	/// Like [`Sm3::add_bytes`], but returns [`Error::MessageTooLong`] instead
+	/// of panicking, in which case the hasher is left unchanged.
+	pub fn try_add_bytes(&mut self, mut bytes: &[u8]) -> Result<(), Error> {
+		let num_bytes = bytes.len().try_into().ok()
+			.and_then(|adding_bytes| self.num_bytes.checked_add(adding_bytes))
+			.filter(|&num_bytes| num_bytes < (1u64 << 61))
+			.ok_or(Error::MessageTooLong)?;
+
+		self.num_bytes = num_bytes;
+
+		while !bytes.is_empty() {
+			let num_to_copy = (64 - self.block_pos).min(bytes.len());
+			let (copying, remainder) = bytes.split_at(num_to_copy);
+			bytes = remainder;
+
+			self.block_buffer[self.block_pos .. self.block_pos + num_to_copy].copy_from_slice(copying);
+			self.block_pos += num_to_copy;
+
+			if self.block_pos == 64 {
+				sm3_block(&mut self.hash_vals, &self.block_buffer);
+				self.block_pos = 0;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Consumes the SM3 instance and outputs the hash of the message.
+	pub fn out(mut self) -> [u8; 32] {
+		let num_bits = 8 * self.num_bytes;
+
+		self.block_buffer[self.block_pos ..].fill(0);
+		self.block_buffer[self.block_pos] = 0x80;
+
+		if self.block_pos + 1 > 64 - 8 {
+			sm3_block(&mut self.hash_vals, &self.block_buffer);
+			self.block_buffer.fill(0);
+		}
+
+		self.block_buffer[64 - 8 ..].copy_from_slice(&num_bits.to_be_bytes());
+		sm3_block(&mut self.hash_vals, &self.block_buffer);
+
+		let mut out = [0; 32];
+
+		for i in 0 .. 8 {
+			out[4 * i .. 4 * (i + 1)].copy_from_slice(&self.hash_vals[i].to_be_bytes());
+		}
+
+		out
+	}
+}
+
+impl Default for Sm3 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// examples 1 and 2 from appendix A of GB/T 32905-2016, a 3 byte message and a
+// 64 byte one that needs a second block for the padding
+#[test]
+fn test_standard_vectors() {
+	assert_eq!(sm3(b"abc"), hex("66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"));
+	assert_eq!(sm3(b"abcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcd"), hex("debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732"));
+}
+
+// runs of "a" up to and past the lengths where the padding needs an extra
+// block, with the digests from OpenSSL's SM3
+#[test]
+fn test_lengths() {
+	let expected = [
+		(0, "1ab21d8355cfa17f8e61194831e81a8f22bec8c728fefb747ed035eb5082aa2b"),
+		(55, "288337eef51eec62e7544d7270424c8dbe656254c99852870a73b2453a6a7fb1"),
+		(56, "ba00ebedaab54065a5fd4f9f56326016203166bcee3eed44ea868d59d67aa3c8"),
+		(1000, "f4bedca973227d45c5b822551d2e762d4cfb0e9af70b241452545727b5fb046f"),
+	];
+
+	for (len, hash) in expected {
+		let message = [0x61; 1000];
+		assert_eq!(sm3(&message[.. len]), hex(hash));
+
+		let mut hasher = Sm3::new();
+
+		for chunk in message[.. len].chunks(7) {
+			hasher.add_bytes(chunk);
+		}
+
+		assert_eq!(hasher.out(), hex(hash));
+	}
+}
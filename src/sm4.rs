@@ -0,0 +1,504 @@
+//! Implemented according to GB/T 32907-2016, the Chinese national standard
+//! block cipher, as described in
+//! [draft-ribose-cfrg-sm4](https://datatracker.ietf.org/doc/html/draft-ribose-cfrg-sm4),
+//! with the GCM mode from [NIST SP 800-38D](https://csrc.nist.gov/pubs/sp/800/38/d/final)
+//! as used by [IETF RFC 8998](https://datatracker.ietf.org/doc/html/rfc8998).
+//! SM4 has 128-bit keys and blocks, and is required by regulations in some
+//! markets, along with [`sm3`](crate::sm3). Elsewhere, ChaCha20-Poly1305 is
+//! the better choice.
+//!
+//! Like the crate's AES, the S-box is computed with arithmetic in GF(2 ** 8)
+//! rather than looked up in a table, so that no memory accesses depend on
+//! secret data, and GHASH multiplies without tables for the same reason.
+//!
+//! GCM is an authenticated encryption scheme, which should be preferred.
+//! CBC and CTR only provide confidentiality, so ciphertexts must be
+//! authenticated separately, for example with HMAC, and checked before
+//! decrypting. Otherwise, an attacker can modify messages, and may learn
+//! plaintexts from whether their padding is valid.
+
+use core::convert::TryInto;
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::choice::ConstantTimeEq;
+use crate::zeroize::zeroize;
+
+// the constants of the key schedule
+const FK: [u32; 4] = [0xa3b1bac6, 0x56aa3350, 0x677d9197, 0xb27022dc];
+
+/// An error returned when decrypting a message fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The tag is not valid for the ciphertext and associated data, meaning
+	/// the message was corrupted, forged, or encrypted with a different key.
+	InvalidTag,
+	/// The ciphertext isn't a whole number of blocks, or its padding is
+	/// invalid after decrypting.
+	InvalidPadding,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidTag => "invalid authentication tag",
+			Error::InvalidPadding => "invalid sm4-cbc padding",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// multiplies two elements of GF(2 ** 8), modulo x^8 + x^7 + x^6 + x^5 + x^4 +
+// x^2 + 1, in constant time
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+	let mut out = 0;
+
+	for _ in 0 .. 8 {
+		out ^= a & 0u8.wrapping_sub(b & 0x01);
+		let high_bit = a >> 7;
+		a = (a << 1) ^ (0xf5 & 0u8.wrapping_sub(high_bit));
+		b >>= 1;
+	}
+
+	out
+}
+
+// the affine map of the S-box, a circulant matrix over GF(2)
+fn affine(x: u8) -> u8 {
+	x ^ x.rotate_left(1) ^ x.rotate_left(3) ^ x.rotate_left(6) ^ x.rotate_left(7) ^ 0xd3
+}
+
+fn sub_byte(x: u8) -> u8 {
+	let x = affine(x);
+
+	// x ** 254 is the multiplicative inverse of x, and maps 0 to 0 as required
+	let x2 = gf_mul(x, x);
+	let x3 = gf_mul(x2, x);
+	let x6 = gf_mul(x3, x3);
+	let x12 = gf_mul(x6, x6);
+	let x15 = gf_mul(x12, x3);
+	let x30 = gf_mul(x15, x15);
+	let x60 = gf_mul(x30, x30);
+	let x120 = gf_mul(x60, x60);
+	let x240 = gf_mul(x120, x120);
+	let x252 = gf_mul(x240, x12);
+	let inv = gf_mul(x252, x2);
+
+	affine(inv)
+}
+
+// the nonlinear transformation tau, applying the S-box to each byte
+fn tau(x: u32) -> u32 {
+	u32::from_be_bytes(x.to_be_bytes().map(sub_byte))
+}
+
+// the round function's transformation T
+fn round_t(x: u32) -> u32 {
+	let b = tau(x);
+	b ^ b.rotate_left(2) ^ b.rotate_left(10) ^ b.rotate_left(18) ^ b.rotate_left(24)
+}
+
+// the key schedule's transformation T'
+fn key_t(x: u32) -> u32 {
+	let b = tau(x);
+	b ^ b.rotate_left(13) ^ b.rotate_left(23)
+}
+
+/// An SM4 key schedule. Zeroized when dropped.
+#[derive(Clone)]
+pub struct Sm4 {
+	round_keys: [u32; 32],
+}
+
+impl Sm4 {
+	/// Creates an SM4 instance from a 16-byte key.
+	pub fn new(key: [u8; 16]) -> Self {
+		let mut k = [0; 36];
+
+		for i in 0 .. 4 {
+			k[i] = u32::from_be_bytes(key[4 * i .. 4 * (i + 1)].try_into().unwrap()) ^ FK[i];
+		}
+
+		let mut round_keys = [0; 32];
+
+		for i in 0 .. 32 {
+			// byte j of CK[i] is 7 * (4i + j) mod 256
+			let ck = [0, 1, 2, 3].map(|j| (7 * (4 * i + j)) as u8);
+			k[i + 4] = k[i] ^ key_t(k[i + 1] ^ k[i + 2] ^ k[i + 3] ^ u32::from_be_bytes(ck));
+			round_keys[i] = k[i + 4];
+		}
+
+		zeroize(&mut k);
+
+		Self {round_keys}
+	}
+
+	fn crypt_block(&self, block: &mut [u8; 16], decrypt: bool) {
+		let mut x = [0; 4];
+
+		for i in 0 .. 4 {
+			x[i] = u32::from_be_bytes(block[4 * i .. 4 * (i + 1)].try_into().unwrap());
+		}
+
+		for i in 0 .. 32 {
+			let round_key = match decrypt {
+				false => self.round_keys[i],
+				true => self.round_keys[31 - i],
+			};
+
+			let next = x[0] ^ round_t(x[1] ^ x[2] ^ x[3] ^ round_key);
+			x = [x[1], x[2], x[3], next];
+		}
+
+		// the output is the final four words in reverse order
+		for i in 0 .. 4 {
+			block[4 * i .. 4 * (i + 1)].copy_from_slice(&x[3 - i].to_be_bytes());
+		}
+	}
+
+	/// Encrypts a single 16-byte block in place.
+	pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+		self.crypt_block(block, false);
+	}
+
+	/// Decrypts a single 16-byte block in place.
+	pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+		self.crypt_block(block, true);
+	}
+
+	// xors the keystream starting from `counter` into `buffer`, incrementing
+	// either the whole counter block, or only its last 32 bits as in GCM
+	fn ctr(&self, mut counter: u128, buffer: &mut [u8], wrap_32: bool) {
+		for chunk in buffer.chunks_mut(16) {
+			let mut keystream = counter.to_be_bytes();
+			self.encrypt_block(&mut keystream);
+
+			for (byte, key) in chunk.iter_mut().zip(keystream) {
+				*byte ^= key;
+			}
+
+			counter = match wrap_32 {
+				false => counter.wrapping_add(1),
+				true => (counter & !0xffffffff) | (counter as u32).wrapping_add(1) as u128,
+			};
+		}
+	}
+}
+
+impl Drop for Sm4 {
+	fn drop(&mut self) {
+		zeroize(&mut self.round_keys);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Sm4 {
+	fn zeroize(&mut self) {
+		zeroize(&mut self.round_keys);
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Sm4 {}
+
+/// Encrypts `buffer` in place with SM4-CBC, without padding. The `iv` must
+/// be unpredictable, so it should be generated by a secure random source for
+/// every message.
+///
+/// # Panics
+/// * Panics if the length of `buffer` isn't a multiple of 16.
+pub fn sm4_cbc_encrypt(key: [u8; 16], iv: [u8; 16], buffer: &mut [u8]) {
+	assert!(buffer.len().is_multiple_of(16), "sm4-cbc needs whole blocks");
+
+	let cipher = Sm4::new(key);
+	let mut previous = iv;
+
+	for chunk in buffer.chunks_exact_mut(16) {
+		let block: &mut [u8; 16] = chunk.try_into().unwrap();
+
+		for i in 0 .. 16 {
+			block[i] ^= previous[i];
+		}
+
+		cipher.encrypt_block(block);
+		previous = *block;
+	}
+}
+
+/// Decrypts `buffer` in place with SM4-CBC, without removing any padding.
+///
+/// # Panics
+/// * Panics if the length of `buffer` isn't a multiple of 16.
+pub fn sm4_cbc_decrypt(key: [u8; 16], iv: [u8; 16], buffer: &mut [u8]) {
+	assert!(buffer.len().is_multiple_of(16), "sm4-cbc needs whole blocks");
+
+	let cipher = Sm4::new(key);
+	let mut previous = iv;
+
+	for chunk in buffer.chunks_exact_mut(16) {
+		let block: &mut [u8; 16] = chunk.try_into().unwrap();
+		let ciphertext = *block;
+
+		cipher.decrypt_block(block);
+
+		for i in 0 .. 16 {
+			block[i] ^= previous[i];
+		}
+
+		previous = ciphertext;
+	}
+}
+
+/// Encrypts `plaintext` with SM4-CBC, after padding it to a whole number of
+/// blocks as in PKCS #7. The `iv` must be unpredictable, so it should be
+/// generated by a secure random source for every message.
+#[cfg(feature = "std")]
+pub fn sm4_cbc_encrypt_padded(key: [u8; 16], iv: [u8; 16], plaintext: &[u8]) -> Vec<u8> {
+	let padding = 16 - plaintext.len() % 16;
+
+	let mut out = Vec::with_capacity(plaintext.len() + padding);
+	out.extend_from_slice(plaintext);
+	out.resize(plaintext.len() + padding, padding as u8);
+
+	sm4_cbc_encrypt(key, iv, &mut out);
+	out
+}
+
+/// Decrypts a message encrypted with [`sm4_cbc_encrypt_padded`], and removes
+/// its padding. The padding is checked in constant time, but whether it's
+/// valid is still revealed by the result, so ciphertexts should be
+/// authenticated before they're decrypted.
+#[cfg(feature = "std")]
+pub fn sm4_cbc_decrypt_padded(key: [u8; 16], iv: [u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+	if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+		return Err(Error::InvalidPadding);
+	}
+
+	let mut out = ciphertext.to_vec();
+	sm4_cbc_decrypt(key, iv, &mut out);
+
+	let last_block = &out[out.len() - 16 ..];
+	let padding = last_block[15];
+
+	// each byte within the padding must equal its length, which is 1 to 16
+	let mut invalid = (padding.wrapping_sub(1) >> 4) as u32;
+
+	for i in 0 .. 16 {
+		let in_padding = 0u8.wrapping_sub(((15 - i as u8).wrapping_sub(padding) >> 7) & 1);
+		invalid |= ((last_block[i] ^ padding) & in_padding) as u32;
+	}
+
+	if invalid != 0 {
+		return Err(Error::InvalidPadding);
+	}
+
+	out.truncate(out.len() - padding as usize);
+	Ok(out)
+}
+
+/// Encrypts or decrypts `buffer` in place with SM4-CTR, treating `iv` as a
+/// 128-bit big-endian counter, as OpenSSL does. The `iv` *must not* be
+/// reused with the same key, including as any of the following counter
+/// values of a previous message.
+pub fn sm4_ctr(key: [u8; 16], iv: [u8; 16], buffer: &mut [u8]) {
+	Sm4::new(key).ctr(u128::from_be_bytes(iv), buffer, false);
+}
+
+// multiplies two elements of GF(2 ** 128) in GCM's bit-reflected
+// representation, in constant time
+fn gf128_mul(x: u128, y: u128) -> u128 {
+	let mut out = 0;
+	let mut v = y;
+
+	for i in 0 .. 128 {
+		out ^= v & 0u128.wrapping_sub((x >> (127 - i)) & 1);
+		v = (v >> 1) ^ (0xe1 << 120 & 0u128.wrapping_sub(v & 1));
+	}
+
+	out
+}
+
+fn ghash(h: u128, associated_data: &[u8], ciphertext: &[u8]) -> u128 {
+	let mut y = 0;
+
+	for data in [associated_data, ciphertext] {
+		for chunk in data.chunks(16) {
+			let mut block = [0; 16];
+			block[.. chunk.len()].copy_from_slice(chunk);
+			y = gf128_mul(y ^ u128::from_be_bytes(block), h);
+		}
+	}
+
+	let lengths = ((associated_data.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+	gf128_mul(y ^ lengths, h)
+}
+
+// returns the GHASH key and the block that masks the tag
+fn gcm_init(cipher: &Sm4, nonce: [u8; 12]) -> (u128, u128) {
+	let mut h = [0; 16];
+	cipher.encrypt_block(&mut h);
+
+	let mut j0 = [0; 16];
+	j0[.. 12].copy_from_slice(&nonce);
+	j0[15] = 1;
+	cipher.encrypt_block(&mut j0);
+
+	(u128::from_be_bytes(h), u128::from_be_bytes(j0))
+}
+
+fn gcm_counter(nonce: [u8; 12]) -> u128 {
+	let mut counter = [0; 16];
+	counter[.. 12].copy_from_slice(&nonce);
+	counter[15] = 2;
+	u128::from_be_bytes(counter)
+}
+
+/// Encrypts `buffer` in place with SM4-GCM, returning the tag that
+/// authenticates it along with `associated_data`. The `nonce` *must not* be
+/// reused to encrypt different messages with the same key.
+///
+/// # Panics
+/// * Panics if `buffer` is longer than the GCM counter allows, about 64 GiB.
+pub fn sm4_gcm_encrypt(key: [u8; 16], nonce: [u8; 12], associated_data: &[u8], buffer: &mut [u8]) -> [u8; 16] {
+	assert!((buffer.len() as u64) <= (1 << 36) - 32, "sm4-gcm message too long");
+
+	let cipher = Sm4::new(key);
+	let (h, mask) = gcm_init(&cipher, nonce);
+
+	cipher.ctr(gcm_counter(nonce), buffer, true);
+
+	(ghash(h, associated_data, buffer) ^ mask).to_be_bytes()
+}
+
+/// Checks the `tag` of a message encrypted with SM4-GCM, then decrypts
+/// `buffer` in place. If the tag is invalid, returns [`Error::InvalidTag`]
+/// and leaves `buffer` unchanged.
+pub fn sm4_gcm_decrypt(
+	key: [u8; 16],
+	nonce: [u8; 12],
+	associated_data: &[u8],
+	buffer: &mut [u8],
+	tag: [u8; 16],
+) -> Result<(), Error> {
+	if (buffer.len() as u64) > (1 << 36) - 32 {
+		return Err(Error::InvalidTag);
+	}
+
+	let cipher = Sm4::new(key);
+	let (h, mask) = gcm_init(&cipher, nonce);
+
+	let correct_tag = (ghash(h, associated_data, buffer) ^ mask).to_be_bytes();
+
+	if correct_tag.ct_ne(&tag).into() {
+		return Err(Error::InvalidTag);
+	}
+
+	cipher.ctr(gcm_counter(nonce), buffer, true);
+
+	Ok(())
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// from appendix A of GB/T 32907-2016
+#[test]
+fn test_standard_vectors() {
+	let key = hex("0123456789abcdeffedcba9876543210");
+	let cipher = Sm4::new(key);
+
+	let mut block = key;
+	cipher.encrypt_block(&mut block);
+	assert_eq!(block, hex("681edf34d206965e86b3e94f536e4246"));
+
+	cipher.decrypt_block(&mut block);
+	assert_eq!(block, key);
+}
+
+// checked against `openssl enc -sm4-cbc` from OpenSSL 3.5
+#[test]
+#[cfg(feature = "std")]
+fn test_cbc() {
+	let key = hex("0123456789abcdeffedcba9876543210");
+	let iv = hex("000102030405060708090a0b0c0d0e0f");
+
+	let ciphertext = sm4_cbc_encrypt_padded(key, iv, b"hello sm4 cbc mode");
+	assert_eq!(ciphertext, hex::<32>("ec4a4632e6f862026a9ecc5ebbbc8912b0fd0541b4f6c695222110b87c92d170"));
+	assert_eq!(sm4_cbc_decrypt_padded(key, iv, &ciphertext).unwrap(), b"hello sm4 cbc mode");
+
+	for len in 0 .. 40 {
+		let plaintext = [0x61; 40];
+		let ciphertext = sm4_cbc_encrypt_padded(key, iv, &plaintext[.. len]);
+		assert_eq!(ciphertext.len(), (len / 16 + 1) * 16);
+		assert_eq!(sm4_cbc_decrypt_padded(key, iv, &ciphertext).unwrap(), &plaintext[.. len]);
+	}
+
+	// the plaintext of the last block is "e" followed by 13 bytes of padding,
+	// so changing the iv changes the padding
+	let mut modified_iv = iv;
+	modified_iv[15] ^= 1;
+	assert_eq!(sm4_cbc_decrypt_padded(key, modified_iv, &ciphertext[16 ..]), Err(Error::InvalidPadding));
+	assert_eq!(sm4_cbc_decrypt_padded(key, iv, &ciphertext[.. 31]), Err(Error::InvalidPadding));
+	assert_eq!(sm4_cbc_decrypt_padded(key, iv, &[]), Err(Error::InvalidPadding));
+}
+
+// checked against `openssl enc -sm4-ctr` from OpenSSL 3.5, with the counter
+// wrapping around from all ones
+#[test]
+fn test_ctr() {
+	let key = hex("0123456789abcdeffedcba9876543210");
+
+	let mut buffer = *b"counter mode wraps aroundcounter mode wraps around";
+	sm4_ctr(key, [0xff; 16], &mut buffer);
+
+	assert_eq!(buffer, hex("0b7eda107d1616c7eb9421ab7ded12915604d40a7bae57a2f3365c6535a0c7586e3434945a03ca6253ebdc76f99af799dd77"));
+
+	sm4_ctr(key, [0xff; 16], &mut buffer);
+	assert_eq!(&buffer, b"counter mode wraps aroundcounter mode wraps around");
+}
+
+// from appendix A.1 of RFC 8998
+#[test]
+fn test_gcm() {
+	let key = hex("0123456789abcdeffedcba9876543210");
+	let nonce = hex("00001234567800000000abcd");
+	let associated_data = hex::<20>("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+
+	let plaintext = hex::<64>(
+		"aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd\
+		eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa",
+	);
+
+	let mut buffer = plaintext;
+	let tag = sm4_gcm_encrypt(key, nonce, &associated_data, &mut buffer);
+
+	assert_eq!(buffer, hex(
+		"17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735\
+		d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d",
+	));
+
+	assert_eq!(tag, hex("83de3541e4c2b58177e065a9bf7b62ec"));
+
+	let mut modified = buffer;
+	modified[0] ^= 1;
+	assert_eq!(sm4_gcm_decrypt(key, nonce, &associated_data, &mut modified, tag), Err(Error::InvalidTag));
+	assert_eq!(modified[0], buffer[0] ^ 1);
+
+	assert_eq!(sm4_gcm_decrypt(key, nonce, b"", &mut buffer, tag), Err(Error::InvalidTag));
+
+	sm4_gcm_decrypt(key, nonce, &associated_data, &mut buffer, tag).unwrap();
+	assert_eq!(buffer, plaintext);
+
+	// a message that isn't a whole number of blocks, checked against SM4 in GCM
+	// mode from python's cryptography package
+	let mut buffer = *b"hello sm4 gcm mode!";
+	let tag = sm4_gcm_encrypt(key, [0; 12], b"aad", &mut buffer);
+	assert_eq!(buffer, hex("db76006821b53b407b45490a22072ca2c54033"));
+	assert_eq!(tag, hex("d51c101140633de64d86e9b9f2f1ffc4"));
+}
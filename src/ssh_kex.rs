@@ -0,0 +1,357 @@
+//! The `curve25519-sha256` key exchange of SSH, from
+//! [RFC 8731](https://datatracker.ietf.org/doc/html/rfc8731), also known as
+//! `curve25519-sha256@libssh.org`, for building SSH clients and servers.
+//!
+//! Each side creates a [`Kex`] with a fresh ephemeral key and sends its
+//! [`public_key`](Kex::public_key) to the other: the client as
+//! `SSH_MSG_KEX_ECDH_INIT`, and the server in `SSH_MSG_KEX_ECDH_REPLY`,
+//! together with its host key and a signature. Once both have the other's
+//! public key and the [`Transcript`] of the connection so far, they finish
+//! the exchange, which gives the shared secret and the exchange hash that the
+//! server signs with its host key. The session keys are then derived with the
+//! key derivation of [RFC 4253, section 7.2](https://datatracker.ietf.org/doc/html/rfc4253#section-7.2).
+//!
+//! Signing and verifying the exchange hash is left to the caller, since it
+//! depends on the type of host key, such as `ssh-ed25519` keys from
+//! [`ed25519`](crate::ed25519).
+
+use core::fmt;
+
+use std::vec::Vec;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sha2::Sha256;
+use crate::x25519::{EphemeralSecret, PublicKey};
+use crate::zeroize::zeroize;
+
+/// The name of the key exchange method in `SSH_MSG_KEXINIT`.
+pub const NAME: &str = "curve25519-sha256";
+
+/// The name of the same key exchange method from before it was standardized,
+/// which older implementations use.
+pub const LIBSSH_NAME: &str = "curve25519-sha256@libssh.org";
+
+/// An error returned when finishing a key exchange.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The other side's public key isn't 32 bytes long, or is a small-order
+	/// point, which would make the shared secret all zeros.
+	InvalidPublicKey,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidPublicKey => "invalid ssh key exchange public key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// What the exchange hash covers from before the key exchange, besides the
+/// two public keys.
+#[derive(Copy, Clone, Debug)]
+pub struct Transcript<'a> {
+	/// The client's identification string, without the trailing CR LF.
+	pub client_version: &'a [u8],
+	/// The server's identification string, without the trailing CR LF.
+	pub server_version: &'a [u8],
+	/// The payload of the client's `SSH_MSG_KEXINIT`, starting with the
+	/// message number.
+	pub client_kexinit: &'a [u8],
+	/// The payload of the server's `SSH_MSG_KEXINIT`, starting with the
+	/// message number.
+	pub server_kexinit: &'a [u8],
+	/// The server's public host key, in the SSH wire format.
+	pub host_key: &'a [u8],
+}
+
+/// One side of a key exchange, holding its ephemeral key until the other
+/// side's public key arrives.
+pub struct Kex {
+	secret: EphemeralSecret,
+	public: PublicKey,
+}
+
+impl Kex {
+	/// Starts a key exchange with `randomness` as the ephemeral private key,
+	/// which must be freshly generated by a secure random source for every
+	/// key exchange.
+	pub fn new(randomness: [u8; 32]) -> Self {
+		let secret = EphemeralSecret::from_bytes(randomness);
+		let public = PublicKey::from(&secret);
+
+		Self {secret, public}
+	}
+
+	/// Starts a key exchange with an ephemeral private key generated by
+	/// `rng`, which must be a cryptographically secure random number
+	/// generator.
+	#[cfg(feature = "rand_core")]
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		let secret = EphemeralSecret::generate(rng);
+		let public = PublicKey::from(&secret);
+
+		Self {secret, public}
+	}
+
+	/// Returns the ephemeral public key to send to the other side, which is
+	/// Q_C for the client and Q_S for the server.
+	pub fn public_key(&self) -> [u8; 32] {
+		self.public.to_bytes()
+	}
+
+	/// Finishes the key exchange as the client, given the public key from the
+	/// server's `SSH_MSG_KEX_ECDH_REPLY`.
+	pub fn client_finish(self, transcript: &Transcript, server_public: &[u8]) -> Result<KexOutput, Error> {
+		let client_public = self.public.to_bytes();
+		self.finish(transcript, &client_public, server_public, server_public)
+	}
+
+	/// Finishes the key exchange as the server, given the public key from the
+	/// client's `SSH_MSG_KEX_ECDH_INIT`.
+	pub fn server_finish(self, transcript: &Transcript, client_public: &[u8]) -> Result<KexOutput, Error> {
+		let server_public = self.public.to_bytes();
+		self.finish(transcript, client_public, &server_public, client_public)
+	}
+
+	fn finish(
+		self,
+		transcript: &Transcript,
+		client_public: &[u8],
+		server_public: &[u8],
+		their_public: &[u8],
+	) -> Result<KexOutput, Error> {
+		let their_public: [u8; 32] = their_public.try_into().map_err(|_| Error::InvalidPublicKey)?;
+
+		let shared_secret = self.secret
+			.diffie_hellman(&PublicKey::from_bytes(their_public))
+			.map_err(|_| Error::InvalidPublicKey)?;
+
+		// the shared secret is used as a big-endian integer, in the mpint
+		// encoding
+		let mut encoded_secret = Vec::with_capacity(4 + 33);
+		put_mpint(&mut encoded_secret, shared_secret.as_bytes());
+
+		let mut hasher = Sha256::new();
+
+		for field in [
+			transcript.client_version,
+			transcript.server_version,
+			transcript.client_kexinit,
+			transcript.server_kexinit,
+			transcript.host_key,
+			client_public,
+			server_public,
+		] {
+			hasher.add_bytes(&(field.len() as u32).to_be_bytes());
+			hasher.add_bytes(field);
+		}
+
+		hasher.add_bytes(&encoded_secret);
+
+		Ok(KexOutput {encoded_secret, exchange_hash: hasher.out()})
+	}
+}
+
+// appends a big-endian unsigned integer in the mpint encoding of RFC 4251,
+// which drops leading zeros and adds one back if the top bit would be set
+fn put_mpint(out: &mut Vec<u8>, value: &[u8]) {
+	let start = value.iter().position(|&byte| byte != 0).unwrap_or(value.len());
+	let value = &value[start ..];
+	let pad = value.first().is_some_and(|&byte| byte & 0x80 != 0);
+
+	out.extend_from_slice(&((value.len() + pad as usize) as u32).to_be_bytes());
+
+	if pad {
+		out.push(0);
+	}
+
+	out.extend_from_slice(value);
+}
+
+/// The keys that [`KexOutput::derive_key`] derives, each named by the letter
+/// that RFC 4253 gives it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+	/// The initial IV from the client to the server, `A`.
+	ClientToServerIv,
+	/// The initial IV from the server to the client, `B`.
+	ServerToClientIv,
+	/// The encryption key from the client to the server, `C`.
+	ClientToServerKey,
+	/// The encryption key from the server to the client, `D`.
+	ServerToClientKey,
+	/// The integrity key from the client to the server, `E`.
+	ClientToServerMac,
+	/// The integrity key from the server to the client, `F`.
+	ServerToClientMac,
+}
+
+impl KeyKind {
+	fn letter(self) -> u8 {
+		match self {
+			KeyKind::ClientToServerIv => b'A',
+			KeyKind::ServerToClientIv => b'B',
+			KeyKind::ClientToServerKey => b'C',
+			KeyKind::ServerToClientKey => b'D',
+			KeyKind::ClientToServerMac => b'E',
+			KeyKind::ServerToClientMac => b'F',
+		}
+	}
+}
+
+/// The result of a key exchange: the shared secret K and the exchange hash H.
+/// The shared secret is zeroized when dropped.
+pub struct KexOutput {
+	encoded_secret: Vec<u8>,
+	exchange_hash: [u8; 32],
+}
+
+impl KexOutput {
+	/// Returns the exchange hash H, which the server signs with its host key
+	/// and the client verifies the signature of. The exchange hash of the
+	/// first key exchange of a connection is also its session identifier.
+	pub fn exchange_hash(&self) -> [u8; 32] {
+		self.exchange_hash
+	}
+
+	/// Returns the shared secret K in the mpint encoding, as it's hashed.
+	/// This must be kept secret.
+	pub fn encoded_secret(&self) -> &[u8] {
+		&self.encoded_secret
+	}
+
+	/// Fills `out` with the key of the given `kind`. The `session_id` is the
+	/// exchange hash of the first key exchange of the connection, which is
+	/// this one's unless this is a re-exchange.
+	pub fn derive_key(&self, kind: KeyKind, session_id: &[u8], out: &mut [u8]) {
+		let mut hasher = Sha256::new();
+		hasher.add_bytes(&self.encoded_secret);
+		hasher.add_bytes(&self.exchange_hash);
+		hasher.add_bytes(&[kind.letter()]);
+		hasher.add_bytes(session_id);
+
+		// each further block hashes K, H and every block before it
+		let mut extension = Sha256::new();
+		extension.add_bytes(&self.encoded_secret);
+		extension.add_bytes(&self.exchange_hash);
+
+		let mut block = hasher.out();
+		let mut filled = 0;
+
+		loop {
+			let len = usize::min(32, out.len() - filled);
+			out[filled .. filled + len].copy_from_slice(&block[.. len]);
+			filled += len;
+
+			if filled == out.len() {
+				break;
+			}
+
+			extension.add_bytes(&block);
+			block = extension.clone().out();
+		}
+
+		zeroize(&mut block);
+	}
+}
+
+impl Drop for KexOutput {
+	fn drop(&mut self) {
+		zeroize(&mut self.encoded_secret);
+	}
+}
+
+impl fmt::Debug for KexOutput {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("KexOutput").field("exchange_hash", &self.exchange_hash).finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+#[test]
+fn test_key_exchange() {
+	// checked against a python transcription of section 3 of RFC 8731 and
+	// sections 7.2 and 8 of RFC 4253, using X25519 from python's cryptography
+	// package
+	let mut host_key = Vec::new();
+	host_key.extend_from_slice(b"\x00\x00\x00\x0bssh-ed25519\x00\x00\x00\x20");
+	host_key.extend_from_slice(&[0xab; 32]);
+
+	let transcript = Transcript {
+		client_version: b"SSH-2.0-client",
+		server_version: b"SSH-2.0-server",
+		client_kexinit: b"\x14client kexinit",
+		server_kexinit: b"\x14server kexinit",
+		host_key: &host_key,
+	};
+
+	let client = Kex::new([0x11; 32]);
+	let server = Kex::new(core::array::from_fn(|i| i as u8));
+
+	let client_public = client.public_key();
+	let server_public = server.public_key();
+	assert_eq!(client_public, hex("7b4e909bbe7ffe44c465a220037d608ee35897d31ef972f07f74892cb0f73f13"));
+	assert_eq!(server_public, hex("8f40c5adb68f25624ae5b214ea767a6ec94d829d3d7b5e1ad1ba6f3e2138285f"));
+
+	let client_output = client.client_finish(&transcript, &server_public).unwrap();
+	let server_output = server.server_finish(&transcript, &client_public).unwrap();
+
+	let exchange_hash = hex("8fecff38623eea140fd69cad5c7284c9041d97861718721b6a0363f235ab71f5");
+	assert_eq!(client_output.exchange_hash(), exchange_hash);
+	assert_eq!(server_output.exchange_hash(), exchange_hash);
+	assert_eq!(client_output.encoded_secret(), server_output.encoded_secret());
+
+	let mut iv = [0; 16];
+	client_output.derive_key(KeyKind::ClientToServerIv, &exchange_hash, &mut iv);
+	assert_eq!(iv, hex("a3cee60c29d0f03930dfd75d08677e49"));
+
+	// longer than one hash, so extended as described in RFC 4253
+	let mut key = [0; 64];
+	server_output.derive_key(KeyKind::ClientToServerKey, &exchange_hash, &mut key);
+	assert_eq!(key, hex(concat!(
+		"63507f75a2ba444ddae198dc02566d28f7d0e9f29713ff4b59b039d8235ae2f2",
+		"7f00ac5d9bd6349c994192b5f5bfcb555116ef2c34045123964fed49a6e33164",
+	)));
+}
+
+#[test]
+fn test_rejects_invalid_public_keys() {
+	let transcript = Transcript {
+		client_version: b"",
+		server_version: b"",
+		client_kexinit: b"",
+		server_kexinit: b"",
+		host_key: b"",
+	};
+
+	let result = Kex::new([1; 32]).client_finish(&transcript, &[9; 31]);
+	assert_eq!(result.unwrap_err(), Error::InvalidPublicKey);
+
+	let result = Kex::new([1; 32]).server_finish(&transcript, &[0; 32]);
+	assert_eq!(result.unwrap_err(), Error::InvalidPublicKey);
+}
+
+#[test]
+fn test_mpint() {
+	let cases: [(&[u8], &[u8]); 4] = [
+		(&[0, 0], &[0, 0, 0, 0]),
+		(&[0, 0x12, 0x34], &[0, 0, 0, 2, 0x12, 0x34]),
+		(&[0x80], &[0, 0, 0, 2, 0, 0x80]),
+		(&[0, 0xff, 0], &[0, 0, 0, 3, 0, 0xff, 0]),
+	];
+
+	for (value, expected) in cases {
+		let mut out = Vec::new();
+		put_mpint(&mut out, value);
+		assert_eq!(out, expected);
+	}
+}
@@ -0,0 +1,97 @@
+//! Helpers shared by the tests: hex decoding for known-answer tests, and
+//! deterministic random number generators, which are of course not actually
+//! cryptographically secure.
+
+#[cfg(feature = "rand_core")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::encoding::decode_hex;
+
+/// Decodes hex into an array, panicking unless it's exactly `N` bytes long.
+pub fn hex<const N: usize>(text: &str) -> [u8; N] {
+	let mut out = [0; N];
+	assert_eq!(decode_hex(text, &mut out), Ok(N));
+	out
+}
+
+/// Decodes hex of any length.
+#[cfg(feature = "std")]
+pub fn hex_vec(text: &str) -> Vec<u8> {
+	let mut out = vec![0; text.len() / 2];
+	assert_eq!(decode_hex(text, &mut out), Ok(out.len()));
+	out
+}
+
+/// Hands out consecutive byte values, starting from the given one and
+/// wrapping around after `0xff`.
+#[cfg(feature = "rand_core")]
+pub struct CountingRng(pub u8);
+
+#[cfg(feature = "rand_core")]
+impl RngCore for CountingRng {
+	fn next_u32(&mut self) -> u32 {
+		rand_core::impls::next_u32_via_fill(self)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		rand_core::impls::next_u64_via_fill(self)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for byte in dest {
+			*byte = self.0;
+			self.0 = self.0.wrapping_add(1);
+		}
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl CryptoRng for CountingRng {}
+
+/// Hands out the given bytes, starting over from the beginning once they
+/// run out.
+#[cfg(feature = "rand_core")]
+pub struct FixedRng<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+#[cfg(feature = "rand_core")]
+impl<'a> FixedRng<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		assert!(!bytes.is_empty());
+
+		Self {bytes, pos: 0}
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl RngCore for FixedRng<'_> {
+	fn next_u32(&mut self) -> u32 {
+		rand_core::impls::next_u32_via_fill(self)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		rand_core::impls::next_u64_via_fill(self)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for byte in dest {
+			*byte = self.bytes[self.pos];
+			self.pos = (self.pos + 1) % self.bytes.len();
+		}
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl CryptoRng for FixedRng<'_> {}
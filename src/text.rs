@@ -0,0 +1,122 @@
+//! The string forms of public keys and signatures, from their `Display` and
+//! `FromStr` implementations: lowercase hex for short values, or standard
+//! base64 for long ones such as post-quantum keys, the same as the strings
+//! they're serialized as with the `serde` feature. Parsing accepts uppercase
+//! hex too, and decodes in constant time.
+
+use core::fmt;
+
+use crate::encoding::{self, Base64Variant};
+use crate::zeroize::zeroize;
+
+/// How a value is written as a string. Some variants go unused when only some
+/// of the primitives are built.
+#[derive(Copy, Clone)]
+#[cfg_attr(not(feature = "full"), allow(dead_code))]
+pub enum Text {
+	Hex,
+	Base64,
+}
+
+pub struct Encoded<'a>(pub &'a [u8], pub Text);
+
+impl fmt::Display for Encoded<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.1 {
+			Text::Hex => encoding::write_hex(self.0, f),
+			Text::Base64 => encoding::write_base64(self.0, Base64Variant::Standard, f),
+		}
+	}
+}
+
+// decodes exactly `N` bytes in constant time, zeroizing them on failure
+pub fn decode<const N: usize>(text: &str, kind: Text) -> Result<[u8; N], encoding::Error> {
+	let mut out = [0; N];
+
+	let len = match kind {
+		Text::Hex => encoding::decode_hex(text, &mut out),
+		Text::Base64 => encoding::decode_base64(text, Base64Variant::Standard, &mut out),
+	};
+
+	match len {
+		Ok(len) if len == N => Ok(out),
+		_ => {
+			zeroize(&mut out);
+			Err(encoding::Error::InvalidEncoding)
+		}
+	}
+}
+
+// implements Display and FromStr for a public type encoded as `$len` bytes,
+// given how to convert a value to its bytes and back, which may fail if
+// marked with `try`; any failure to parse is an `encoding::Error`
+macro_rules! text_impl {
+	($type:ty, $len:expr, $text:ident, |$value:ident| $to_bytes:expr, |$bytes:ident| $from_bytes:expr) => {
+		text_impl!($type, $len, $text, |$value| $to_bytes, try |$bytes| {
+			Ok::<_, core::convert::Infallible>($from_bytes)
+		});
+	};
+
+	($type:ty, $len:expr, $text:ident, |$value:ident| $to_bytes:expr, try |$bytes:ident| $from_bytes:expr) => {
+		impl core::fmt::Display for $type {
+			fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				let $value = self;
+				let bytes: [u8; $len] = $to_bytes;
+
+				core::fmt::Display::fmt(&crate::text::Encoded(&bytes, crate::text::Text::$text), f)
+			}
+		}
+
+		impl core::str::FromStr for $type {
+			type Err = crate::encoding::Error;
+
+			fn from_str(text: &str) -> Result<Self, crate::encoding::Error> {
+				let $bytes: [u8; $len] = crate::text::decode(text, crate::text::Text::$text)?;
+				$from_bytes.map_err(|_| crate::encoding::Error::InvalidEncoding)
+			}
+		}
+	};
+}
+
+#[cfg(all(feature = "std", feature = "ed25519"))]
+#[test]
+fn test_hex() {
+	use std::string::ToString;
+
+	use crate::ed25519::{SigningKey, Signature, VerifyingKey};
+
+	let key = SigningKey::from_bytes([0x42; 32]);
+	let pub_key = key.verifying_key();
+	let signature = key.sign(b"hello");
+
+	let text = pub_key.to_string();
+	assert_eq!(text.len(), 64);
+	assert_eq!(text, text.to_lowercase());
+	assert_eq!(text.parse::<VerifyingKey>(), Ok(pub_key));
+	assert_eq!(text.to_uppercase().parse::<VerifyingKey>(), Ok(pub_key));
+	assert_eq!(signature.to_string().parse::<Signature>(), Ok(signature));
+
+	// wrong lengths, invalid characters and invalid points are all rejected
+	assert_eq!(text[.. 62].parse::<VerifyingKey>(), Err(encoding::Error::InvalidEncoding));
+	assert_eq!(std::format!("{}00", text).parse::<VerifyingKey>(), Err(encoding::Error::InvalidEncoding));
+	assert_eq!(text.replace(&text[.. 1], "g").parse::<VerifyingKey>(), Err(encoding::Error::InvalidEncoding));
+
+	// y = 2 is not the y-coordinate of any point on the curve
+	let not_a_point = "0200000000000000000000000000000000000000000000000000000000000000";
+	assert_eq!(not_a_point.parse::<VerifyingKey>(), Err(encoding::Error::InvalidEncoding));
+}
+
+#[cfg(all(feature = "std", feature = "ml-dsa"))]
+#[test]
+fn test_base64() {
+	use std::string::ToString;
+
+	use crate::ml_dsa::{SigningKey, VerifyingKey, VERIFYING_KEY_LEN};
+
+	let pub_key = SigningKey::from_seed([7; 32]).verifying_key();
+	let text = pub_key.to_string();
+
+	assert_eq!(text.len(), 4 * VERIFYING_KEY_LEN.div_ceil(3));
+	assert_eq!(text.parse::<VerifyingKey>().map(|key| key.to_bytes()), Ok(pub_key.to_bytes()));
+	assert_eq!(text[.. text.len() - 4].parse::<VerifyingKey>().err(), Some(encoding::Error::InvalidEncoding));
+}
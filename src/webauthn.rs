@@ -0,0 +1,470 @@
+//! Verification of WebAuthn assertions, the signatures that passkeys and
+//! security keys make when a user logs in, implemented according to section
+//! 7.2 of the [Web Authentication specification](https://www.w3.org/TR/webauthn-3/#sctn-verifying-assertion).
+//! Registration, and storing each credential's public key and signature
+//! counter, is left to the caller.
+//!
+//! The authenticator signs its authenticator data followed by the SHA-256
+//! hash of the client data JSON, which the browser builds from the challenge
+//! and the origin of the page. [`verify_assertion`] checks both against what
+//! the relying party expects, as well as the signature.
+//!
+//! Only Ed25519 credentials are supported for now. Their public keys can be
+//! read from the COSE key in the attested credential data of a registration
+//! with [`CredentialPublicKey::from_cose_key`].
+
+use core::fmt;
+
+use std::string::String;
+
+use crate::base64;
+use crate::ed25519::{Signature, VerifyingKey};
+use crate::json::JsonParser;
+use crate::sha2::sha256;
+
+const USER_PRESENT: u8 = 1 << 0;
+const USER_VERIFIED: u8 = 1 << 2;
+const BACKUP_ELIGIBLE: u8 = 1 << 3;
+const BACKED_UP: u8 = 1 << 4;
+
+/// An error returned when an assertion can't be verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The client data isn't valid JSON with the required members.
+	InvalidClientData,
+	/// The client data has a type other than `webauthn.get`, so it's from a
+	/// registration or something else.
+	WrongType,
+	/// The challenge in the client data isn't the expected one.
+	ChallengeMismatch,
+	/// The origin in the client data isn't the expected one, or the assertion
+	/// was made in a cross-origin iframe.
+	OriginMismatch,
+	/// The authenticator data is too short.
+	InvalidAuthenticatorData,
+	/// The authenticator data is for a different relying party ID.
+	RpIdMismatch,
+	/// The authenticator didn't check that the user was present.
+	UserNotPresent,
+	/// User verification was required, but the authenticator didn't verify
+	/// the user, such as with a PIN or biometrics.
+	UserNotVerified,
+	/// The signature counter didn't increase, which may mean that the
+	/// credential has been cloned.
+	SignCountNotIncreased,
+	/// The signature is invalid for the credential's public key.
+	InvalidSignature,
+	/// A COSE key isn't a valid Ed25519 public key.
+	InvalidPublicKey,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidClientData => "invalid webauthn client data",
+			Error::WrongType => "wrong webauthn client data type",
+			Error::ChallengeMismatch => "webauthn challenge mismatch",
+			Error::OriginMismatch => "webauthn origin mismatch",
+			Error::InvalidAuthenticatorData => "invalid webauthn authenticator data",
+			Error::RpIdMismatch => "webauthn relying party id mismatch",
+			Error::UserNotPresent => "webauthn user not present",
+			Error::UserNotVerified => "webauthn user not verified",
+			Error::SignCountNotIncreased => "webauthn signature counter didn't increase",
+			Error::InvalidSignature => "invalid webauthn signature",
+			Error::InvalidPublicKey => "invalid webauthn public key",
+		};
+
+		f.write_str(description)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The public key of a credential.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CredentialPublicKey {
+	/// An Ed25519 key, with the COSE algorithm identifier -8.
+	Ed25519(VerifyingKey),
+}
+
+impl CredentialPublicKey {
+	/// Parses a COSE key, as found in the attested credential data of a
+	/// registration. Only the parameters of an Ed25519 key are accepted, in
+	/// any order.
+	pub fn from_cose_key(bytes: &[u8]) -> Result<Self, Error> {
+		let mut reader = CborReader {bytes};
+		let entries = reader.read_head(5).ok_or(Error::InvalidPublicKey)?;
+
+		let mut kty = None;
+		let mut alg = None;
+		let mut crv = None;
+		let mut x = None;
+
+		for _ in 0 .. entries {
+			let key = reader.read_int().ok_or(Error::InvalidPublicKey)?;
+
+			let slot = match key {
+				1 => &mut kty,
+				3 => &mut alg,
+				-1 => &mut crv,
+				-2 => {
+					let len = reader.read_head(2).ok_or(Error::InvalidPublicKey)?;
+
+					if x.is_some() || len != 32 {
+						return Err(Error::InvalidPublicKey);
+					}
+
+					x = Some(reader.read_bytes(32).ok_or(Error::InvalidPublicKey)?);
+					continue;
+				},
+				_ => return Err(Error::InvalidPublicKey),
+			};
+
+			if slot.is_some() {
+				return Err(Error::InvalidPublicKey);
+			}
+
+			*slot = Some(reader.read_int().ok_or(Error::InvalidPublicKey)?);
+		}
+
+		// kty 1 is an octet key pair, alg -8 is EdDSA and crv 6 is Ed25519
+		if reader.bytes.len() != 0 || kty != Some(1) || alg != Some(-8) || crv != Some(6) {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		let x = x.ok_or(Error::InvalidPublicKey)?;
+		let key = VerifyingKey::try_from(x).map_err(|_| Error::InvalidPublicKey)?;
+
+		Ok(CredentialPublicKey::Ed25519(key))
+	}
+
+	fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+		match self {
+			CredentialPublicKey::Ed25519(key) => match Signature::try_from(signature) {
+				Ok(signature) => key.verify(message, &signature),
+				Err(_) => false,
+			},
+		}
+	}
+}
+
+// reads just the CBOR needed for a COSE key: small maps, integers and byte
+// strings, in their shortest encodings
+struct CborReader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+	// reads the head of an item with the given major type, returning its
+	// argument, which must be less than 256
+	fn read_head(&mut self, major_type: u8) -> Option<u8> {
+		let (&initial, rest) = self.bytes.split_first()?;
+		self.bytes = rest;
+
+		if initial >> 5 != major_type {
+			return None;
+		}
+
+		match initial & 0x1f {
+			argument @ 0 ..= 23 => Some(argument),
+			24 => {
+				let (&argument, rest) = self.bytes.split_first()?;
+				self.bytes = rest;
+
+				match argument {
+					24 ..= 255 => Some(argument),
+					_ => None,
+				}
+			},
+			_ => None,
+		}
+	}
+
+	fn read_int(&mut self) -> Option<i16> {
+		let major_type = self.bytes.first()? >> 5;
+		let argument = self.read_head(major_type)? as i16;
+
+		match major_type {
+			0 => Some(argument),
+			1 => Some(-1 - argument),
+			_ => None,
+		}
+	}
+
+	fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+		if self.bytes.len() < len {
+			return None;
+		}
+
+		let (out, rest) = self.bytes.split_at(len);
+		self.bytes = rest;
+
+		Some(out)
+	}
+}
+
+/// What the relying party expects of an assertion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expected<'a> {
+	/// The relying party ID, usually the domain of the site, such as
+	/// `example.com`.
+	pub rp_id: &'a str,
+	/// The origin of the page that requested the assertion, such as
+	/// `https://example.com`.
+	pub origin: &'a str,
+	/// The challenge that was sent for this login, which must be randomly
+	/// generated and only accepted once.
+	pub challenge: &'a [u8],
+	/// Whether the authenticator must have verified the user, rather than
+	/// only checking that they're present.
+	pub require_user_verification: bool,
+	/// The signature counter stored for the credential from its last use.
+	pub stored_sign_count: u32,
+}
+
+/// The information in a verified assertion.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Assertion {
+	/// The new signature counter, which should be stored for the credential.
+	/// Authenticators that don't keep a counter always report 0.
+	pub sign_count: u32,
+	/// Whether the authenticator verified the user.
+	pub user_verified: bool,
+	/// Whether the credential can be backed up, making it a synced passkey.
+	pub backup_eligible: bool,
+	/// Whether the credential is currently backed up.
+	pub backed_up: bool,
+}
+
+fn check_client_data(client_data_json: &[u8], expected: &Expected) -> Result<(), Error> {
+	let mut parser = JsonParser::new(client_data_json, Error::InvalidClientData);
+
+	let mut typ: Option<String> = None;
+	let mut challenge: Option<String> = None;
+	let mut origin: Option<String> = None;
+	let mut cross_origin = None;
+
+	parser.expect(b'{')?;
+
+	if !parser.try_consume(b'}') {
+		loop {
+			let key = parser.parse_string()?;
+			parser.expect(b':')?;
+
+			let slot = match &*key {
+				"type" => Some(&mut typ),
+				"challenge" => Some(&mut challenge),
+				"origin" => Some(&mut origin),
+				"crossOrigin" if cross_origin.is_none() => {
+					cross_origin = Some(parser.parse_bool()?);
+					None
+				},
+				"crossOrigin" => return Err(Error::InvalidClientData),
+				_ => {
+					parser.skip_value(0)?;
+					None
+				},
+			};
+
+			if let Some(slot) = slot {
+				if slot.is_some() {
+					return Err(Error::InvalidClientData);
+				}
+
+				*slot = Some(parser.parse_string()?);
+			}
+
+			if parser.try_consume(b'}') {
+				break;
+			}
+
+			parser.expect(b',')?;
+		}
+	}
+
+	if !parser.is_done() {
+		return Err(Error::InvalidClientData);
+	}
+
+	let (typ, challenge, origin) = match (typ, challenge, origin) {
+		(Some(typ), Some(challenge), Some(origin)) => (typ, challenge, origin),
+		_ => return Err(Error::InvalidClientData),
+	};
+
+	if typ != "webauthn.get" {
+		return Err(Error::WrongType);
+	}
+
+	if base64::decode_url(&challenge).as_deref() != Some(expected.challenge) {
+		return Err(Error::ChallengeMismatch);
+	}
+
+	if origin != expected.origin || cross_origin == Some(true) {
+		return Err(Error::OriginMismatch);
+	}
+
+	Ok(())
+}
+
+/// Verifies an assertion from the `authenticatorData`, `clientDataJSON` and
+/// `signature` of an `AuthenticatorAssertionResponse`, made with the
+/// credential with the public key `public_key`.
+pub fn verify_assertion(
+	public_key: &CredentialPublicKey,
+	authenticator_data: &[u8],
+	client_data_json: &[u8],
+	signature: &[u8],
+	expected: &Expected,
+) -> Result<Assertion, Error> {
+	check_client_data(client_data_json, expected)?;
+
+	// the relying party ID hash, flags and signature counter, which may be
+	// followed by extensions
+	if authenticator_data.len() < 37 {
+		return Err(Error::InvalidAuthenticatorData);
+	}
+
+	if authenticator_data[.. 32] != sha256(expected.rp_id.as_bytes()) {
+		return Err(Error::RpIdMismatch);
+	}
+
+	let flags = authenticator_data[32];
+	let sign_count = u32::from_be_bytes([authenticator_data[33], authenticator_data[34], authenticator_data[35], authenticator_data[36]]);
+
+	if flags & USER_PRESENT == 0 {
+		return Err(Error::UserNotPresent);
+	}
+
+	if expected.require_user_verification && flags & USER_VERIFIED == 0 {
+		return Err(Error::UserNotVerified);
+	}
+
+	let mut signed_data = authenticator_data.to_vec();
+	signed_data.extend_from_slice(&sha256(client_data_json));
+
+	if !public_key.verify(&signed_data, signature) {
+		return Err(Error::InvalidSignature);
+	}
+
+	// the counter is only checked once the signature is known to be valid
+	if (sign_count != 0 || expected.stored_sign_count != 0) && sign_count <= expected.stored_sign_count {
+		return Err(Error::SignCountNotIncreased);
+	}
+
+	Ok(Assertion {
+		sign_count,
+		user_verified: flags & USER_VERIFIED != 0,
+		backup_eligible: flags & BACKUP_ELIGIBLE != 0,
+		backed_up: flags & BACKED_UP != 0,
+	})
+}
+
+#[cfg(test)]
+fn test_assertion(flags: u8, sign_count: u32, client_data_json: &str) -> (std::vec::Vec<u8>, [u8; 64]) {
+	use crate::ed25519::SigningKey;
+
+	let mut authenticator_data = sha256(b"example.com").to_vec();
+	authenticator_data.push(flags);
+	authenticator_data.extend_from_slice(&sign_count.to_be_bytes());
+
+	let mut signed_data = authenticator_data.clone();
+	signed_data.extend_from_slice(&sha256(client_data_json.as_bytes()));
+
+	let signature = SigningKey::from_bytes([0x42; 32]).sign(&signed_data);
+
+	(authenticator_data, signature.to_bytes())
+}
+
+#[cfg(test)]
+const CLIENT_DATA: &str = r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://example.com","crossOrigin":false}"#;
+
+#[cfg(test)]
+const EXPECTED: Expected = Expected {
+	rp_id: "example.com",
+	origin: "https://example.com",
+	challenge: b"challenge",
+	require_user_verification: false,
+	stored_sign_count: 4,
+};
+
+#[test]
+fn test_verify_assertion() {
+	use crate::ed25519::SigningKey;
+
+	let public_key = CredentialPublicKey::Ed25519(SigningKey::from_bytes([0x42; 32]).verifying_key());
+
+	let (authenticator_data, signature) = test_assertion(USER_PRESENT | USER_VERIFIED | BACKUP_ELIGIBLE, 5, CLIENT_DATA);
+	let assertion = verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &EXPECTED).unwrap();
+
+	assert_eq!(assertion, Assertion {sign_count: 5, user_verified: true, backup_eligible: true, backed_up: false});
+
+	let require_uv = Expected {require_user_verification: true, ..EXPECTED};
+	let (authenticator_data, signature) = test_assertion(USER_PRESENT, 5, CLIENT_DATA);
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &require_uv), Err(Error::UserNotVerified));
+	assert!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &EXPECTED).is_ok());
+
+	let (authenticator_data, signature) = test_assertion(0, 5, CLIENT_DATA);
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &EXPECTED), Err(Error::UserNotPresent));
+
+	// a counter that doesn't increase, unless the authenticator has none
+	let (authenticator_data, signature) = test_assertion(USER_PRESENT, 4, CLIENT_DATA);
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &EXPECTED), Err(Error::SignCountNotIncreased));
+
+	let (authenticator_data, signature) = test_assertion(USER_PRESENT, 0, CLIENT_DATA);
+	let no_counter = Expected {stored_sign_count: 0, ..EXPECTED};
+	assert!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &no_counter).is_ok());
+
+	let other_rp = Expected {rp_id: "example.org", ..EXPECTED};
+	let (authenticator_data, signature) = test_assertion(USER_PRESENT, 5, CLIENT_DATA);
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature, &other_rp), Err(Error::RpIdMismatch));
+	assert_eq!(verify_assertion(&public_key, &authenticator_data[.. 36], CLIENT_DATA.as_bytes(), &signature, &EXPECTED), Err(Error::InvalidAuthenticatorData));
+
+	let mut modified = signature;
+	modified[0] ^= 1;
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &modified, &EXPECTED), Err(Error::InvalidSignature));
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, CLIENT_DATA.as_bytes(), &signature[.. 63], &EXPECTED), Err(Error::InvalidSignature));
+
+	// the signature covers the client data
+	let modified = CLIENT_DATA.replace("false", " false");
+	assert_eq!(verify_assertion(&public_key, &authenticator_data, modified.as_bytes(), &signature, &EXPECTED), Err(Error::InvalidSignature));
+}
+
+#[test]
+fn test_client_data() {
+	let check = |json: &str| check_client_data(json.as_bytes(), &EXPECTED);
+
+	assert_eq!(check(CLIENT_DATA), Ok(()));
+	assert_eq!(check(r#"{"origin":"https://example.com","challenge":"Y2hhbGxlbmdl","type":"webauthn.get","other":[1,{"a":null}]}"#), Ok(()));
+
+	assert_eq!(check(r#"{"type":"webauthn.create","challenge":"Y2hhbGxlbmdl","origin":"https://example.com"}"#), Err(Error::WrongType));
+	assert_eq!(check(r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdm","origin":"https://example.com"}"#), Err(Error::ChallengeMismatch));
+	assert_eq!(check(r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://evil.example"}"#), Err(Error::OriginMismatch));
+	assert_eq!(check(r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://example.com","crossOrigin":true}"#), Err(Error::OriginMismatch));
+
+	assert_eq!(check(r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl"}"#), Err(Error::InvalidClientData));
+	assert_eq!(check(r#"{"type":"webauthn.get","type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://example.com"}"#), Err(Error::InvalidClientData));
+	assert_eq!(check(r#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://example.com"} x"#), Err(Error::InvalidClientData));
+}
+
+#[test]
+fn test_cose_key() {
+	use crate::ed25519::SigningKey;
+
+	let verifying_key = SigningKey::from_bytes([0x42; 32]).verifying_key();
+
+	// {1: 1, 3: -8, -1: 6, -2: x}
+	let mut cose_key = std::vec![0xa4, 0x01, 0x01, 0x03, 0x27, 0x20, 0x06, 0x21, 0x58, 0x20];
+	cose_key.extend_from_slice(verifying_key.as_bytes());
+
+	assert_eq!(CredentialPublicKey::from_cose_key(&cose_key), Ok(CredentialPublicKey::Ed25519(verifying_key)));
+
+	// an ES256 algorithm, a missing parameter, and trailing data
+	let mut modified = cose_key.clone();
+	modified[4] = 0x26;
+	assert_eq!(CredentialPublicKey::from_cose_key(&modified), Err(Error::InvalidPublicKey));
+
+	assert_eq!(CredentialPublicKey::from_cose_key(&[&[0xa3][..], &cose_key[3 ..]].concat()), Err(Error::InvalidPublicKey));
+	assert_eq!(CredentialPublicKey::from_cose_key(&[&cose_key[..], &[0]].concat()), Err(Error::InvalidPublicKey));
+	assert_eq!(CredentialPublicKey::from_cose_key(&cose_key[.. 41]), Err(Error::InvalidPublicKey));
+}
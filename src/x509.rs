@@ -0,0 +1,556 @@
+//! Parsing of X.509 certificates, as specified in
+//! [IETF RFC 5280](https://datatracker.ietf.org/doc/html/rfc5280), and
+//! verification of certificates signed with Ed25519, as specified in
+//! [IETF RFC 8410](https://datatracker.ietf.org/doc/html/rfc8410).
+//!
+//! This is enough for certificate pinning and for checking short chains
+//! issued by a private CA, but it's not a full path validation
+//! implementation: names are compared byte for byte, and name constraints,
+//! policies and revocation aren't checked. Certificates with critical
+//! extensions other than basic constraints, key usage and subject alternative
+//! names are rejected, as RFC 5280 requires.
+//!
+//! ```
+//! use libkrypton::x509::Certificate;
+//!
+//! # fn hex(text: &str) -> Vec<u8> {
+//! #     let mut out = vec![0; text.len() / 2];
+//! #     libkrypton::encoding::decode_hex(text, &mut out).unwrap();
+//! #     out
+//! # }
+//! # let root_der = hex(concat!(
+//! #     "3081fb3081aea003020102020101300506032b6570301c311a301806035504030c114b727970746f6e20546573",
+//! #     "7420526f6f743020170d3234303130313030303030305a180f32303531303630313132333030305a301c311a30",
+//! #     "1806035504030c114b727970746f6e205465737420526f6f74302a300506032b6570032100d04ab232742bb4ab",
+//! #     "3a1368bd4615e4e6d0224ab71a016baf8520a332c9778737a3133011300f0603551d130101ff040530030101ff",
+//! #     "300506032b6570034100510711bd7ddde0cbd0907b0046b5cd98a03c1e70f372f4e0495a52d1136ccf394390f4",
+//! #     "e09eb6dda6eb8904ee72bd3b4b9bae1a3a7cc07c016e33654a8f4d5903",
+//! # ));
+//! # let leaf_der = hex(concat!(
+//! #     "3081e6308199a00302010202081234567890abcdef300506032b6570301c311a301806035504030c114b727970",
+//! #     "746f6e205465737420526f6f74301e170d3234303130313030303030305a170d3334303130313030303030305a",
+//! #     "30173115301306035504030c0c6c6561662e6578616d706c65302a300506032b6570032100a09aa5f47a675980",
+//! #     "2ff955f8dc2d2a14a5c99d23be97f864127ff9383455a4f0300506032b65700341001791dba32e9d595588c870",
+//! #     "76c6bcf2772a5cd667208e5af9aad3053fb7c875070b7a70cbefdc935ddd724d6cec222129df0e20d64b30aae1",
+//! #     "0c6142afa330c606",
+//! # ));
+//! # let now = 1_800_000_000;
+//! let root = Certificate::from_der(&root_der).unwrap();
+//! let leaf = Certificate::from_der(&leaf_der).unwrap();
+//!
+//! assert!(libkrypton::x509::verify_chain(&[leaf], &[root], now).is_ok());
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+use crate::der::{self, context_tag, Reader, TAG_GENERALIZED_TIME, TAG_SEQUENCE, TAG_UTC_TIME};
+use crate::ed25519::{Signature, VerifyingKey};
+use crate::sha2::sha256;
+
+// DER encoding of the object identifier 1.3.101.112
+const ED25519_OID: [u8; 3] = [0x2b, 0x65, 0x70];
+
+// the object identifiers of the extensions that are understood
+const BASIC_CONSTRAINTS_OID: [u8; 3] = [0x55, 0x1d, 0x13];
+const KEY_USAGE_OID: [u8; 3] = [0x55, 0x1d, 0x0f];
+const SUBJECT_ALT_NAME_OID: [u8; 3] = [0x55, 0x1d, 0x11];
+
+// the keyCertSign bit of the key usage, counting from the most significant
+// bit of the first byte
+const KEY_CERT_SIGN: u8 = 0x04;
+
+/// The longest chain [`verify_chain`] accepts, not counting the trust anchor.
+pub const MAX_CHAIN_LEN: usize = 8;
+
+/// An error returned when parsing or verifying certificates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// The certificate is not a valid DER-encoded X.509 certificate.
+	InvalidEncoding,
+	/// The certificate is signed with an algorithm other than Ed25519, or has
+	/// a public key of another type.
+	UnsupportedAlgorithm,
+	/// The certificate has a critical extension that isn't understood.
+	UnsupportedExtension,
+	/// The signature on the certificate is invalid.
+	InvalidSignature,
+	/// The issuer of a certificate doesn't match the subject of the next one.
+	NameMismatch,
+	/// A certificate that issued another isn't a CA certificate allowed to
+	/// sign certificates.
+	NotCa,
+	/// A certificate isn't valid at the given time.
+	Expired,
+	/// The chain doesn't end in a trust anchor, or is empty or too long.
+	Untrusted,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Error::InvalidEncoding => "invalid certificate encoding",
+			Error::UnsupportedAlgorithm => "unsupported certificate algorithm",
+			Error::UnsupportedExtension => "unsupported critical certificate extension",
+			Error::InvalidSignature => "invalid certificate signature",
+			Error::NameMismatch => "certificate issuer name mismatch",
+			Error::NotCa => "certificate issuer is not a ca",
+			Error::Expired => "certificate is not valid at the given time",
+			Error::Untrusted => "certificate chain does not end in a trust anchor",
+		};
+
+		f.write_str(description)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// any DER error means that the certificate is malformed
+fn invalid(_: der::Error) -> Error {
+	Error::InvalidEncoding
+}
+
+// reads an AlgorithmIdentifier, returning the oid and the whole encoding
+fn read_algorithm<'a>(reader: &mut Reader<'a>) -> Result<(&'a [u8], &'a [u8]), Error> {
+	let raw = reader.read_raw(TAG_SEQUENCE).map_err(invalid)?;
+	let mut algorithm = Reader::new(raw).read_sequence().map_err(invalid)?;
+	let oid = algorithm.read_oid().map_err(invalid)?;
+
+	Ok((oid, raw))
+}
+
+// returns the number of days between the unix epoch and the given date in
+// the proleptic gregorian calendar
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+	// counts years from march, so that the leap day is at the end of the year
+	let year = if month <= 2 {year - 1} else {year};
+	let era = year / 400;
+	let year_of_era = year % 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = 365 * year_of_era + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	// 719468 is the number of days from 0000-03-01 to 1970-01-01
+	(era * 146097 + day_of_era) - 719468
+}
+
+// parses a UTCTime or GeneralizedTime, which RFC 5280 requires to be in UTC
+// and to include seconds, returning the unix time
+fn read_time(reader: &mut Reader) -> Result<u64, Error> {
+	let (tag, contents) = reader.read_any().map_err(invalid)?;
+
+	let digits = match (tag, contents.split_last()) {
+		(TAG_UTC_TIME, Some((b'Z', digits))) if digits.len() == 12 => digits,
+		(TAG_GENERALIZED_TIME, Some((b'Z', digits))) if digits.len() == 14 => digits,
+		_ => return Err(Error::InvalidEncoding),
+	};
+
+	if !digits.iter().all(|x| x.is_ascii_digit()) {
+		return Err(Error::InvalidEncoding);
+	}
+
+	let number = |range: core::ops::Range<usize>| {
+		digits[range].iter().fold(0, |acc, &x| 10 * acc + (x - b'0') as u64)
+	};
+
+	// two-digit years from 50 to 99 are in the 20th century
+	let (year, rest) = match tag {
+		TAG_UTC_TIME => match number(0 .. 2) {
+			year @ 0 ..= 49 => (2000 + year, 2),
+			year => (1900 + year, 2),
+		},
+		_ => (number(0 .. 4), 4),
+	};
+
+	let month = number(rest .. rest + 2);
+	let day = number(rest + 2 .. rest + 4);
+	let hour = number(rest + 4 .. rest + 6);
+	let minute = number(rest + 6 .. rest + 8);
+	let second = number(rest + 8 .. rest + 10);
+
+	let days_in_month = match month {
+		2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	};
+
+	if year < 1970 || !(1 ..= 12).contains(&month) || !(1 ..= days_in_month).contains(&day) {
+		return Err(Error::InvalidEncoding);
+	}
+
+	if hour > 23 || minute > 59 || second > 59 {
+		return Err(Error::InvalidEncoding);
+	}
+
+	Ok(86400 * days_from_civil(year, month, day) + 3600 * hour + 60 * minute + second)
+}
+
+/// A parsed X.509 certificate, borrowing from its DER encoding.
+#[derive(Copy, Clone, Debug)]
+pub struct Certificate<'a> {
+	der: &'a [u8],
+	tbs: &'a [u8],
+	serial_number: &'a [u8],
+	issuer: &'a [u8],
+	subject: &'a [u8],
+	not_before: u64,
+	not_after: u64,
+	spki: &'a [u8],
+	signature_algorithm: &'a [u8],
+	signature: &'a [u8],
+	is_ca: bool,
+	can_sign_certificates: bool,
+}
+
+impl<'a> Certificate<'a> {
+	/// Parses a DER-encoded certificate. Certificates with any public key and
+	/// signature algorithm can be parsed, but only Ed25519 ones can be
+	/// verified.
+	pub fn from_der(der: &'a [u8]) -> Result<Self, Error> {
+		let mut outer = Reader::new(der);
+		let mut certificate = outer.read_sequence().map_err(invalid)?;
+		outer.finish().map_err(invalid)?;
+
+		let tbs = certificate.read_raw(TAG_SEQUENCE).map_err(invalid)?;
+		let (_, signature_algorithm) = read_algorithm(&mut certificate)?;
+		let signature = certificate.read_bit_string().map_err(invalid)?;
+		certificate.finish().map_err(invalid)?;
+
+		let mut reader = Reader::new(tbs).read_sequence().map_err(invalid)?;
+
+		// the version is v1 if it's absent, and only v3 has extensions
+		let version = match reader.read_optional(context_tag(0, true)).map_err(invalid)? {
+			Some(contents) => Reader::new(contents).read_u64().map_err(invalid)?,
+			None => 0,
+		};
+
+		if version > 2 {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let serial_number = reader.read_integer().map_err(invalid)?;
+
+		// the algorithm is repeated inside the signed part, so it can't be
+		// changed without invalidating the signature
+		if read_algorithm(&mut reader)?.1 != signature_algorithm {
+			return Err(Error::InvalidEncoding);
+		}
+
+		let issuer = reader.read_raw(TAG_SEQUENCE).map_err(invalid)?;
+
+		let mut validity = reader.read_sequence().map_err(invalid)?;
+		let not_before = read_time(&mut validity)?;
+		let not_after = read_time(&mut validity)?;
+		validity.finish().map_err(invalid)?;
+
+		let subject = reader.read_raw(TAG_SEQUENCE).map_err(invalid)?;
+		let spki = reader.read_raw(TAG_SEQUENCE).map_err(invalid)?;
+
+		// the unique identifiers are deprecated, and are skipped
+		reader.read_optional(context_tag(1, false)).map_err(invalid)?;
+		reader.read_optional(context_tag(2, false)).map_err(invalid)?;
+
+		let mut is_ca = false;
+		let mut key_usage = None;
+
+		if let Some(contents) = reader.read_optional(context_tag(3, true)).map_err(invalid)? {
+			if version != 2 {
+				return Err(Error::InvalidEncoding);
+			}
+
+			let mut outer = Reader::new(contents);
+			let mut extensions = outer.read_sequence().map_err(invalid)?;
+			outer.finish().map_err(invalid)?;
+
+			while !extensions.is_empty() {
+				let mut extension = extensions.read_sequence().map_err(invalid)?;
+				let oid = extension.read_oid().map_err(invalid)?;
+
+				// DER forbids encoding the default value of false
+				let critical = match extension.read_optional(der::TAG_BOOLEAN).map_err(invalid)? {
+					Some([0xff]) => true,
+					Some(_) => return Err(Error::InvalidEncoding),
+					None => false,
+				};
+
+				let mut value = Reader::new(extension.read_octet_string().map_err(invalid)?);
+				extension.finish().map_err(invalid)?;
+
+				if oid == BASIC_CONSTRAINTS_OID {
+					let mut constraints = value.read_sequence().map_err(invalid)?;
+					is_ca = constraints.peek_tag() == Some(der::TAG_BOOLEAN) && constraints.read_bool().map_err(invalid)?;
+
+					// the path length constraint isn't checked
+					constraints.read_optional(der::TAG_INTEGER).map_err(invalid)?;
+					constraints.finish().map_err(invalid)?;
+				} else if oid == KEY_USAGE_OID {
+					let bits = value.read(der::TAG_BIT_STRING).map_err(invalid)?;
+					key_usage = Some(bits.get(1).copied().unwrap_or(0));
+				} else if oid == SUBJECT_ALT_NAME_OID || !critical {
+					continue;
+				} else {
+					return Err(Error::UnsupportedExtension);
+				}
+
+				value.finish().map_err(invalid)?;
+			}
+		}
+
+		reader.finish().map_err(invalid)?;
+
+		Ok(Self {
+			der,
+			tbs,
+			serial_number,
+			issuer,
+			subject,
+			not_before,
+			not_after,
+			spki,
+			signature_algorithm,
+			signature,
+			is_ca,
+			can_sign_certificates: is_ca && key_usage.is_none_or(|x| x & KEY_CERT_SIGN != 0),
+		})
+	}
+
+	/// Returns the DER encoding of the whole certificate.
+	pub fn as_der(&self) -> &'a [u8] {
+		self.der
+	}
+
+	/// Returns the DER encoding of the `tbsCertificate`, the part that's
+	/// signed.
+	pub fn tbs_certificate(&self) -> &'a [u8] {
+		self.tbs
+	}
+
+	/// Returns the big-endian serial number.
+	pub fn serial_number(&self) -> &'a [u8] {
+		self.serial_number
+	}
+
+	/// Returns the DER encoding of the issuer's name.
+	pub fn issuer(&self) -> &'a [u8] {
+		self.issuer
+	}
+
+	/// Returns the DER encoding of the subject's name.
+	pub fn subject(&self) -> &'a [u8] {
+		self.subject
+	}
+
+	/// Returns the start of the validity period, as a Unix time in seconds.
+	pub fn not_before(&self) -> u64 {
+		self.not_before
+	}
+
+	/// Returns the end of the validity period, as a Unix time in seconds. The
+	/// certificate is still valid during this second.
+	pub fn not_after(&self) -> u64 {
+		self.not_after
+	}
+
+	/// Returns whether the certificate is valid at `unix_time`.
+	pub fn is_valid_at(&self, unix_time: u64) -> bool {
+		(self.not_before ..= self.not_after).contains(&unix_time)
+	}
+
+	/// Returns whether the basic constraints extension marks this as a CA
+	/// certificate.
+	pub fn is_ca(&self) -> bool {
+		self.is_ca
+	}
+
+	/// Returns the DER encoding of the SubjectPublicKeyInfo.
+	pub fn subject_public_key_info(&self) -> &'a [u8] {
+		self.spki
+	}
+
+	/// Returns the SHA-256 digest of the SubjectPublicKeyInfo, which is the
+	/// value usually pinned, since it stays the same when the certificate is
+	/// renewed with the same key.
+	pub fn spki_sha256(&self) -> [u8; 32] {
+		sha256(self.spki)
+	}
+
+	/// Returns the subject's public key, if it's an Ed25519 key.
+	pub fn ed25519_public_key(&self) -> Result<VerifyingKey, Error> {
+		VerifyingKey::from_public_key_der(self.spki).map_err(|_| Error::UnsupportedAlgorithm)
+	}
+
+	/// Checks that this certificate was signed by `issuer_key` with Ed25519.
+	/// This only checks the signature, not the names or validity period.
+	pub fn verify_signature(&self, issuer_key: &VerifyingKey) -> Result<(), Error> {
+		let mut algorithm = Reader::new(self.signature_algorithm).read_sequence().map_err(invalid)?;
+
+		// RFC 8410 requires the parameters to be absent
+		if algorithm.read_oid().map_err(invalid)? != ED25519_OID || !algorithm.is_empty() {
+			return Err(Error::UnsupportedAlgorithm);
+		}
+
+		let signature: [u8; 64] = self.signature.try_into().map_err(|_| Error::InvalidSignature)?;
+		let signature = Signature::from_bytes(signature).map_err(|_| Error::InvalidSignature)?;
+
+		if !issuer_key.verify(self.tbs, &signature) {
+			return Err(Error::InvalidSignature);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that this certificate was issued by `issuer`: that its issuer
+	/// name matches the subject name of `issuer`, that `issuer` is allowed to
+	/// sign certificates, and that the signature is valid.
+	pub fn verify_issued_by(&self, issuer: &Certificate) -> Result<(), Error> {
+		if self.issuer != issuer.subject {
+			return Err(Error::NameMismatch);
+		}
+
+		if !issuer.can_sign_certificates {
+			return Err(Error::NotCa);
+		}
+
+		self.verify_signature(&issuer.ed25519_public_key()?)
+	}
+}
+
+/// Verifies a chain of certificates at `unix_time`. The chain starts with the
+/// end-entity certificate, followed by the certificate that issued it, and so
+/// on, and the last certificate must be issued by, or be, one of the
+/// `trust_anchors`. Every certificate must be valid at `unix_time`.
+pub fn verify_chain(chain: &[Certificate], trust_anchors: &[Certificate], unix_time: u64) -> Result<(), Error> {
+	if chain.is_empty() || chain.len() > MAX_CHAIN_LEN {
+		return Err(Error::Untrusted);
+	}
+
+	if !chain.iter().all(|x| x.is_valid_at(unix_time)) {
+		return Err(Error::Expired);
+	}
+
+	for i in 1 .. chain.len() {
+		chain[i - 1].verify_issued_by(&chain[i])?;
+	}
+
+	let last = &chain[chain.len() - 1];
+
+	for anchor in trust_anchors {
+		if anchor.der == last.der {
+			return Ok(());
+		}
+
+		if last.issuer == anchor.subject && anchor.is_valid_at(unix_time) {
+			return last.verify_issued_by(anchor);
+		}
+	}
+
+	Err(Error::Untrusted)
+}
+
+#[cfg(test)]
+use crate::test_util::hex;
+
+// generated with pyca/cryptography, with the root key from the seed
+// 0x11 * 32 and the leaf key from the seed 0x22 * 32
+#[cfg(test)]
+const TEST_ROOT: &str = concat!(
+	"3081fb3081aea003020102020101300506032b6570301c311a301806035504030c114b727970746f6e20546573",
+	"7420526f6f743020170d3234303130313030303030305a180f32303531303630313132333030305a301c311a30",
+	"1806035504030c114b727970746f6e205465737420526f6f74302a300506032b6570032100d04ab232742bb4ab",
+	"3a1368bd4615e4e6d0224ab71a016baf8520a332c9778737a3133011300f0603551d130101ff040530030101ff",
+	"300506032b6570034100510711bd7ddde0cbd0907b0046b5cd98a03c1e70f372f4e0495a52d1136ccf394390f4",
+	"e09eb6dda6eb8904ee72bd3b4b9bae1a3a7cc07c016e33654a8f4d5903",
+);
+
+#[cfg(test)]
+const TEST_LEAF: &str = concat!(
+	"3081e6308199a00302010202081234567890abcdef300506032b6570301c311a301806035504030c114b727970",
+	"746f6e205465737420526f6f74301e170d3234303130313030303030305a170d3334303130313030303030305a",
+	"30173115301306035504030c0c6c6561662e6578616d706c65302a300506032b6570032100a09aa5f47a675980",
+	"2ff955f8dc2d2a14a5c99d23be97f864127ff9383455a4f0300506032b65700341001791dba32e9d595588c870",
+	"76c6bcf2772a5cd667208e5af9aad3053fb7c875070b7a70cbefdc935ddd724d6cec222129df0e20d64b30aae1",
+	"0c6142afa330c606",
+);
+
+#[test]
+fn test_parse() {
+	let root_der = hex::<254>(TEST_ROOT);
+	let leaf_der = hex::<233>(TEST_LEAF);
+
+	let root = Certificate::from_der(&root_der).unwrap();
+	let leaf = Certificate::from_der(&leaf_der).unwrap();
+
+	assert!(root.is_ca());
+	assert!(!leaf.is_ca());
+	assert_eq!(root.subject(), root.issuer());
+	assert_eq!(leaf.issuer(), root.subject());
+	assert_eq!(leaf.serial_number(), [0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef]);
+
+	// 2024-01-01, 2034-01-01 and 2051-06-01 12:30, the last in a
+	// GeneralizedTime
+	assert_eq!((leaf.not_before(), leaf.not_after()), (1704067200, 2019686400));
+	assert_eq!(root.not_after(), 2569235400);
+
+	assert_eq!(
+		leaf.spki_sha256(),
+		hex("744f36cca67eb1912cb282e08e86bd2fe0d09004a28cf6e7c6bd56cdd6bf65aa"),
+	);
+
+	let leaf_key = crate::ed25519::SigningKey::from_bytes([0x22; 32]).verifying_key();
+	assert_eq!(leaf.ed25519_public_key(), Ok(leaf_key));
+}
+
+#[test]
+fn test_verify_chain() {
+	let root_der = hex::<254>(TEST_ROOT);
+	let leaf_der = hex::<233>(TEST_LEAF);
+
+	let root = Certificate::from_der(&root_der).unwrap();
+	let leaf = Certificate::from_der(&leaf_der).unwrap();
+	let now = 1800000000;
+
+	let anchors = [root];
+	assert_eq!(verify_chain(&[leaf], &anchors, now), Ok(()));
+	assert_eq!(verify_chain(&[leaf, root], &anchors, now), Ok(()));
+	assert_eq!(verify_chain(&[root], &anchors, now), Ok(()));
+
+	assert_eq!(verify_chain(&[leaf], &anchors, 2019686401), Err(Error::Expired));
+	assert_eq!(verify_chain(&[leaf], &[], now), Err(Error::Untrusted));
+	assert_eq!(verify_chain(&[], &anchors, now), Err(Error::Untrusted));
+
+	// the names have to match, and the leaf isn't a ca
+	assert_eq!(root.verify_issued_by(&leaf), Err(Error::NameMismatch));
+	assert_eq!(leaf.verify_issued_by(&leaf), Err(Error::NameMismatch));
+	assert!(!leaf.can_sign_certificates);
+
+	// a signature that's been tampered with
+	let mut tampered = leaf_der;
+	tampered[232] ^= 1;
+	let tampered = Certificate::from_der(&tampered).unwrap();
+	assert_eq!(verify_chain(&[tampered], &anchors, now), Err(Error::InvalidSignature));
+
+	// the validity period, which is part of the signed data
+	let mut tampered = leaf_der;
+	tampered[63] = b'3';
+	let tampered = Certificate::from_der(&tampered).unwrap();
+	assert_eq!(tampered.not_before(), 1672531200);
+	assert_eq!(tampered.verify_signature(&root.ed25519_public_key().unwrap()), Err(Error::InvalidSignature));
+}
+
+#[test]
+fn test_times() {
+	let times: [(&[u8], Option<u64>); 7] = [
+		(b"\x17\x0d700101000000Z", Some(0)),
+		(b"\x17\x0d491231235959Z", Some(2524607999)),
+		(b"\x17\x0d000229000000Z", Some(951782400)),
+		(b"\x18\x0f21000301000000Z", Some(4107542400)),
+		(b"\x17\x0d010229000000Z", None),
+		(b"\x17\x0d700101000000+", None),
+		(b"\x18\x0d700101000000Z", None),
+	];
+
+	for (encoding, expected) in times {
+		assert_eq!(read_time(&mut Reader::new(encoding)).ok(), expected);
+	}
+}
@@ -0,0 +1,31 @@
+//! Best-effort erasure of secrets from memory once they're no longer needed.
+//!
+//! With the `zeroize` feature, the secret-bearing types of the crate also
+//! implement `Zeroize` from the [zeroize](https://crates.io/crates/zeroize)
+//! crate, and `ZeroizeOnDrop` when they're erased on drop, so they can be
+//! used with code that is generic over those traits.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Overwrites `values` with zeros, or their default values. The writes are
+/// volatile and followed by a compiler fence, so that the compiler neither
+/// removes them as dead stores nor moves them after later code.
+pub fn zeroize<T: Copy + Default>(values: &mut [T]) {
+	for value in values.iter_mut() {
+		// values are Copy, so nothing needs dropping before it's overwritten
+		unsafe {core::ptr::write_volatile(value, T::default())};
+	}
+
+	compiler_fence(Ordering::SeqCst);
+}
+
+#[test]
+fn test_zeroize() {
+	let mut bytes = [0xa5u8; 37];
+	zeroize(&mut bytes);
+	assert_eq!(bytes, [0; 37]);
+
+	let mut words = [u64::MAX; 4];
+	zeroize(&mut words[1 ..]);
+	assert_eq!(words, [u64::MAX, 0, 0, 0]);
+}